@@ -1,13 +1,17 @@
 //! KV Client implementation
 
 use crate::protocol::{
-    decode_value, encode_value, Command, ProtocolError, Status,
+    decode_exec_response, decode_value, encode_collection_payload, encode_exec_payload, encode_value,
+    CollectionCommand, Command, ProtocolError, Status,
 };
-use ouroboros_kv::KvValue;
+use crate::subscription::{parse_count, Subscription};
+use crate::tls::{self, Stream, TlsConfig};
+use ouroboros_kv::{pattern, KvValue, Transaction, TxnResult};
+use std::collections::HashMap;
+use std::ops::Bound;
 use std::time::Duration;
 use thiserror::Error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 
 /// Client error types
 #[derive(Error, Debug)]
@@ -26,11 +30,119 @@ pub enum ClientError {
 
     #[error("Connection pool timeout")]
     Timeout,
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+/// Encode a request frame: cmd(1) + len(4) + payload
+pub(crate) fn encode_request(cmd: Command, payload: &[u8]) -> Vec<u8> {
+    let mut req = Vec::with_capacity(5 + payload.len());
+    req.push(cmd as u8);
+    req.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    req.extend_from_slice(payload);
+    req
+}
+
+/// Read one frame from the server: status(1) + len(4) + payload
+pub(crate) async fn read_frame(stream: &mut (impl AsyncRead + Unpin + ?Sized)) -> Result<(Status, Vec<u8>), ClientError> {
+    let mut header = [0u8; 5];
+    stream.read_exact(&mut header).await?;
+
+    let status = match header[0] {
+        0x00 => Status::Ok,
+        0x01 => Status::Null,
+        0x02 => Status::Error,
+        0x03 => Status::Message,
+        _ => return Err(ClientError::Protocol(ProtocolError::InvalidCommand(header[0]))),
+    };
+
+    let payload_len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+
+    let mut payload = vec![0u8; payload_len];
+    if payload_len > 0 {
+        stream.read_exact(&mut payload).await?;
+    }
+
+    Ok((status, payload))
+}
+
+/// Write a request frame and flush it (TLS streams buffer writes)
+pub(crate) async fn send(stream: &mut (impl Stream + ?Sized), req: &[u8]) -> Result<(), ClientError> {
+    stream.write_all(req).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Encode a list of names: count(2) + [len(2) + name]...
+pub(crate) fn encode_names(names: &[&str]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(names.len() as u16).to_be_bytes());
+    for name in names {
+        payload.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        payload.extend_from_slice(name.as_bytes());
+    }
+    payload
+}
+
+/// Keys examined per SCAN round trip when listing keys
+const KEYS_BATCH_SIZE: usize = 1000;
+
+/// Authentication and TLS settings for a connection
+///
+/// # Example
+///
+/// ```no_run
+/// use ouroboros_kv_client::{ConnectOptions, KvClient, TlsConfig};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let options = ConnectOptions::new()
+///     .auth("worker", "secret")
+///     .tls(TlsConfig::from_ca_file("/etc/kv/ca.pem")?);
+/// let client = KvClient::connect_with("kv.internal:6380/tasks", &options).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// ACL user to authenticate as (the server's default user if None)
+    pub username: Option<String>,
+    /// Password or token; no AUTH is sent if None
+    pub password: Option<String>,
+    /// Encrypt the connection
+    pub tls: Option<TlsConfig>,
+}
+
+impl ConnectOptions {
+    /// Plain TCP, no authentication
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authenticate as `username`
+    pub fn auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Authenticate as the default user (servers started with `--requirepass`)
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.username = None;
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Connect over TLS
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
 }
 
 /// KV Store client
 pub struct KvClient {
-    stream: TcpStream,
+    stream: Box<dyn Stream>,
     namespace: Option<String>,
 }
 
@@ -42,6 +154,14 @@ impl KvClient {
     /// - `127.0.0.1:6380/tasks` → namespace "tasks"
     /// - `127.0.0.1:6380/prod/cache` → namespace "prod/cache"
     pub async fn connect(addr: &str) -> Result<Self, ClientError> {
+        Self::connect_with(addr, &ConnectOptions::default()).await
+    }
+
+    /// Connect to a KV server with authentication and/or TLS
+    ///
+    /// The address accepts a namespace like [`KvClient::connect`]. Fails with
+    /// `ClientError::Server` if the credentials are rejected.
+    pub async fn connect_with(addr: &str, options: &ConnectOptions) -> Result<Self, ClientError> {
         // Parse: "host:port/namespace" or "host:port"
         let (host_port, namespace) = if let Some(idx) = addr.find('/') {
             let (hp, ns) = addr.split_at(idx);
@@ -50,9 +170,12 @@ impl KvClient {
             (addr, None)
         };
 
-        let stream = TcpStream::connect(host_port).await?;
-        stream.set_nodelay(true)?;
-        Ok(Self { stream, namespace })
+        let stream = tls::connect(host_port, options.tls.as_ref()).await?;
+        let mut client = Self { stream, namespace };
+        if let Some(password) = &options.password {
+            client.auth(options.username.as_deref(), password).await?;
+        }
+        Ok(client)
     }
 
     /// Prefix a key with namespace if configured
@@ -63,6 +186,18 @@ impl KvClient {
         }
     }
 
+    /// Remove the namespace prefix from a key returned by the server
+    fn strip_namespace(&self, key: String) -> String {
+        match &self.namespace {
+            Some(ns) => key
+                .strip_prefix(ns.as_str())
+                .and_then(|rest| rest.strip_prefix(':'))
+                .map(str::to_string)
+                .unwrap_or(key),
+            None => key,
+        }
+    }
+
     /// Get the namespace if configured
     pub fn namespace(&self) -> Option<&str> {
         self.namespace.as_deref()
@@ -71,32 +206,12 @@ impl KvClient {
     /// Send a request and read the response
     async fn request(&mut self, cmd: Command, payload: &[u8]) -> Result<(Status, Vec<u8>), ClientError> {
         // Build request
-        let mut req = Vec::with_capacity(5 + payload.len());
-        req.push(cmd as u8);
-        req.extend_from_slice(&(payload.len() as u32).to_be_bytes());
-        req.extend_from_slice(payload);
+        let req = encode_request(cmd, payload);
 
         // Send request
-        self.stream.write_all(&req).await?;
+        send(&mut self.stream, &req).await?;
 
-        // Read response header (5 bytes)
-        let mut header = [0u8; 5];
-        self.stream.read_exact(&mut header).await?;
-
-        let status = match header[0] {
-            0x00 => Status::Ok,
-            0x01 => Status::Null,
-            0x02 => Status::Error,
-            _ => return Err(ClientError::Protocol(ProtocolError::InvalidCommand(header[0]))),
-        };
-
-        let payload_len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
-
-        // Read response payload
-        let mut payload = vec![0u8; payload_len];
-        if payload_len > 0 {
-            self.stream.read_exact(&mut payload).await?;
-        }
+        let (status, payload) = read_frame(&mut self.stream).await?;
 
         // Check for error status
         if status == Status::Error {
@@ -107,6 +222,24 @@ impl KvClient {
         Ok((status, payload))
     }
 
+    /// Split into the raw stream and namespace (used to hand the connection over)
+    pub(crate) fn into_parts(self) -> (Box<dyn Stream>, Option<String>) {
+        (self.stream, self.namespace)
+    }
+
+    /// Authenticate as `username` (the default user if None)
+    ///
+    /// The connection keeps its previous identity if the password is wrong.
+    pub async fn auth(&mut self, username: Option<&str>, password: &str) -> Result<(), ClientError> {
+        let username = username.unwrap_or("");
+        let mut payload = Vec::with_capacity(2 + username.len() + password.len());
+        payload.extend_from_slice(&(username.len() as u16).to_be_bytes());
+        payload.extend_from_slice(username.as_bytes());
+        payload.extend_from_slice(password.as_bytes());
+        self.request(Command::Auth, &payload).await?;
+        Ok(())
+    }
+
     /// Ping the server
     pub async fn ping(&mut self) -> Result<String, ClientError> {
         let (_, payload) = self.request(Command::Ping, &[]).await?;
@@ -433,6 +566,477 @@ impl KvClient {
         let count = u32::from_be_bytes([resp[0], resp[1], resp[2], resp[3]]) as usize;
         Ok(count)
     }
+
+    // ==================== Pub/Sub ====================
+
+    /// Publish a message to a channel
+    ///
+    /// Channels are global and are not prefixed with the client namespace.
+    /// Returns the number of subscriptions the message was delivered to.
+    pub async fn publish(&mut self, channel: &str, message: &[u8]) -> Result<usize, ClientError> {
+        // channel_len(2) + channel + message
+        let mut payload = Vec::with_capacity(2 + channel.len() + message.len());
+        payload.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+        payload.extend_from_slice(channel.as_bytes());
+        payload.extend_from_slice(message);
+
+        let (_, resp) = self.request(Command::Publish, &payload).await?;
+        parse_count(&resp)
+    }
+
+    /// Turn this connection into a subscription on the given channels
+    ///
+    /// A subscribed connection only receives messages; use a separate client
+    /// for regular commands.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use ouroboros_kv_client::KvClient;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = KvClient::connect("127.0.0.1:16380").await?;
+    /// let mut subscription = client.subscribe(&["cache:invalidate"]).await?;
+    ///
+    /// while let Ok(message) = subscription.next_message().await {
+    ///     println!("{}: {:?}", message.channel, message.payload_str());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe(self, channels: &[&str]) -> Result<Subscription, ClientError> {
+        let mut subscription = Subscription::new(self);
+        subscription.subscribe(channels).await?;
+        Ok(subscription)
+    }
+
+    /// Turn this connection into a subscription on the given glob patterns
+    ///
+    /// Keyspace events (when enabled on the server) can be watched with
+    /// patterns such as `__keyspace__:user:*` or `__keyevent__:expired`.
+    pub async fn psubscribe(self, patterns: &[&str]) -> Result<Subscription, ClientError> {
+        let mut subscription = Subscription::new(self);
+        subscription.psubscribe(patterns).await?;
+        Ok(subscription)
+    }
+
+    // ==================== Key Iteration ====================
+
+    /// Incrementally iterate keys (SCAN)
+    ///
+    /// Start with cursor 0 and pass the returned cursor back until it is 0
+    /// again. `count` is a hint for how many keys the server examines per
+    /// call; fewer keys may be returned when `pattern` filters them out.
+    /// With a namespace, only keys in that namespace are visited and they are
+    /// returned without the prefix.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use ouroboros_kv_client::KvClient;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = KvClient::connect("127.0.0.1:16380/tasks").await?;
+    ///
+    /// let mut cursor = 0;
+    /// loop {
+    ///     let (next, keys) = client.scan(cursor, Some("result:*"), 100).await?;
+    ///     client.mdel(&keys.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+    ///     cursor = next;
+    ///     if cursor == 0 {
+    ///         break;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scan(&mut self, cursor: u64, pattern: Option<&str>, count: usize) -> Result<(u64, Vec<String>), ClientError> {
+        let pattern = match (&self.namespace, pattern) {
+            (Some(ns), pattern) => format!("{}:{}", pattern::escape(ns), pattern.unwrap_or("*")),
+            (None, pattern) => pattern.unwrap_or("").to_string(),
+        };
+
+        let pattern_len = u16::try_from(pattern.len()).map_err(|_| {
+            ClientError::InvalidArgument(format!(
+                "SCAN pattern is {} bytes (max {})",
+                pattern.len(),
+                u16::MAX
+            ))
+        })?;
+
+        // cursor(8) + count(4) + pattern_len(2) + pattern
+        let mut payload = Vec::with_capacity(14 + pattern.len());
+        payload.extend_from_slice(&cursor.to_be_bytes());
+        payload.extend_from_slice(&(count.min(u32::MAX as usize) as u32).to_be_bytes());
+        payload.extend_from_slice(&pattern_len.to_be_bytes());
+        payload.extend_from_slice(pattern.as_bytes());
+
+        let (_, resp) = self.request(Command::Scan, &payload).await?;
+
+        // Parse response: cursor(8) + count(4) + [key_len(2) + key]...
+        if resp.len() < 12 {
+            return Err(ClientError::Protocol(ProtocolError::UnexpectedEof));
+        }
+
+        let next = u64::from_be_bytes(resp[0..8].try_into().unwrap());
+        let count = u32::from_be_bytes(resp[8..12].try_into().unwrap()) as usize;
+        let mut keys = Vec::with_capacity(count);
+        let mut pos = 12;
+
+        for _ in 0..count {
+            if resp.len() < pos + 2 {
+                return Err(ClientError::Protocol(ProtocolError::UnexpectedEof));
+            }
+            let key_len = u16::from_be_bytes([resp[pos], resp[pos + 1]]) as usize;
+            pos += 2;
+
+            if resp.len() < pos + key_len {
+                return Err(ClientError::Protocol(ProtocolError::UnexpectedEof));
+            }
+            let key = std::str::from_utf8(&resp[pos..pos + key_len])
+                .map_err(|_| ProtocolError::InvalidUtf8)?
+                .to_string();
+            pos += key_len;
+            keys.push(self.strip_namespace(key));
+        }
+
+        Ok((next, keys))
+    }
+
+    /// All keys matching a glob pattern (KEYS)
+    ///
+    /// Runs a full SCAN, so the server never handles more than one batch at a
+    /// time. Patterns support `*`, `?`, `[abc]`, `[^abc]` and `\` escapes.
+    pub async fn keys(&mut self, pattern: &str) -> Result<Vec<String>, ClientError> {
+        let mut keys = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = self.scan(cursor, Some(pattern), KEYS_BATCH_SIZE).await?;
+            keys.extend(batch);
+            if next == 0 {
+                return Ok(keys);
+            }
+            cursor = next;
+        }
+    }
+
+    // ==================== Collections ====================
+
+    async fn collection(&mut self, cmd: CollectionCommand, key: &str, args: &[KvValue]) -> Result<KvValue, ClientError> {
+        let payload = encode_collection_payload(cmd, &self.prefix_key(key), args);
+        let (_, resp) = self.request(Command::Collection, &payload).await?;
+        let (value, _) = decode_value(&resp)?;
+        Ok(value)
+    }
+
+    /// Set hash fields, returning how many were added (not updated)
+    pub async fn hset(&mut self, key: &str, fields: &[(&str, KvValue)]) -> Result<usize, ClientError> {
+        let args: Vec<KvValue> = fields
+            .iter()
+            .flat_map(|(field, value)| [KvValue::String(field.to_string()), value.clone()])
+            .collect();
+        let reply = self.collection(CollectionCommand::HSet, key, &args).await?;
+        count(reply)
+    }
+
+    /// Set a hash field only if it does not exist
+    pub async fn hsetnx(&mut self, key: &str, field: &str, value: KvValue) -> Result<bool, ClientError> {
+        let reply = self.collection(CollectionCommand::HSetNx, key, &[text(field), value]).await?;
+        Ok(count(reply)? == 1)
+    }
+
+    /// Get a hash field
+    pub async fn hget(&mut self, key: &str, field: &str) -> Result<Option<KvValue>, ClientError> {
+        let reply = self.collection(CollectionCommand::HGet, key, &[text(field)]).await?;
+        Ok(optional(reply))
+    }
+
+    /// Get several hash fields, None for missing ones
+    pub async fn hmget(&mut self, key: &str, fields: &[&str]) -> Result<Vec<Option<KvValue>>, ClientError> {
+        let args: Vec<KvValue> = fields.iter().map(|field| text(field)).collect();
+        let reply = self.collection(CollectionCommand::HMGet, key, &args).await?;
+        Ok(list(reply)?.into_iter().map(optional).collect())
+    }
+
+    /// Get all fields of a hash (empty if the key does not exist)
+    pub async fn hgetall(&mut self, key: &str) -> Result<HashMap<String, KvValue>, ClientError> {
+        match self.collection(CollectionCommand::HGetAll, key, &[]).await? {
+            KvValue::Map(map) => Ok(map),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    /// Delete hash fields, returning how many existed
+    pub async fn hdel(&mut self, key: &str, fields: &[&str]) -> Result<usize, ClientError> {
+        let args: Vec<KvValue> = fields.iter().map(|field| text(field)).collect();
+        let reply = self.collection(CollectionCommand::HDel, key, &args).await?;
+        count(reply)
+    }
+
+    /// Check whether a hash field exists
+    pub async fn hexists(&mut self, key: &str, field: &str) -> Result<bool, ClientError> {
+        let reply = self.collection(CollectionCommand::HExists, key, &[text(field)]).await?;
+        Ok(count(reply)? == 1)
+    }
+
+    /// Number of fields in a hash
+    pub async fn hlen(&mut self, key: &str) -> Result<usize, ClientError> {
+        let reply = self.collection(CollectionCommand::HLen, key, &[]).await?;
+        count(reply)
+    }
+
+    /// Increment an integer hash field, returning its new value
+    pub async fn hincrby(&mut self, key: &str, field: &str, delta: i64) -> Result<i64, ClientError> {
+        let reply = self.collection(CollectionCommand::HIncrBy, key, &[text(field), KvValue::Int(delta)]).await?;
+        int(reply)
+    }
+
+    /// Push values onto the head of a list, returning its new length
+    ///
+    /// Values are pushed one by one, so they end up in reverse order.
+    pub async fn lpush(&mut self, key: &str, values: &[KvValue]) -> Result<usize, ClientError> {
+        let reply = self.collection(CollectionCommand::LPush, key, values).await?;
+        count(reply)
+    }
+
+    /// Append values to a list, returning its new length
+    pub async fn rpush(&mut self, key: &str, values: &[KvValue]) -> Result<usize, ClientError> {
+        let reply = self.collection(CollectionCommand::RPush, key, values).await?;
+        count(reply)
+    }
+
+    /// Remove and return up to `count` values from the head of a list
+    pub async fn lpop(&mut self, key: &str, count: usize) -> Result<Vec<KvValue>, ClientError> {
+        let reply = self.collection(CollectionCommand::LPop, key, &[KvValue::Int(count as i64)]).await?;
+        list(reply)
+    }
+
+    /// Remove and return up to `count` values from the tail of a list
+    pub async fn rpop(&mut self, key: &str, count: usize) -> Result<Vec<KvValue>, ClientError> {
+        let reply = self.collection(CollectionCommand::RPop, key, &[KvValue::Int(count as i64)]).await?;
+        list(reply)
+    }
+
+    /// Keep only the elements from `start` to `stop` (inclusive, negative counts from the end)
+    pub async fn ltrim(&mut self, key: &str, start: i64, stop: i64) -> Result<(), ClientError> {
+        self.collection(CollectionCommand::LTrim, key, &[KvValue::Int(start), KvValue::Int(stop)]).await?;
+        Ok(())
+    }
+
+    /// Elements from `start` to `stop` (inclusive, negative counts from the end)
+    pub async fn lrange(&mut self, key: &str, start: i64, stop: i64) -> Result<Vec<KvValue>, ClientError> {
+        let reply = self.collection(CollectionCommand::LRange, key, &[KvValue::Int(start), KvValue::Int(stop)]).await?;
+        list(reply)
+    }
+
+    /// Element at `index` (negative counts from the end)
+    pub async fn lindex(&mut self, key: &str, index: i64) -> Result<Option<KvValue>, ClientError> {
+        let reply = self.collection(CollectionCommand::LIndex, key, &[KvValue::Int(index)]).await?;
+        Ok(optional(reply))
+    }
+
+    /// Length of a list
+    pub async fn llen(&mut self, key: &str) -> Result<usize, ClientError> {
+        let reply = self.collection(CollectionCommand::LLen, key, &[]).await?;
+        count(reply)
+    }
+
+    /// Add members to a set, returning how many were new
+    pub async fn sadd(&mut self, key: &str, members: &[&str]) -> Result<usize, ClientError> {
+        let args: Vec<KvValue> = members.iter().map(|member| text(member)).collect();
+        let reply = self.collection(CollectionCommand::SAdd, key, &args).await?;
+        count(reply)
+    }
+
+    /// Remove members from a set, returning how many existed
+    pub async fn srem(&mut self, key: &str, members: &[&str]) -> Result<usize, ClientError> {
+        let args: Vec<KvValue> = members.iter().map(|member| text(member)).collect();
+        let reply = self.collection(CollectionCommand::SRem, key, &args).await?;
+        count(reply)
+    }
+
+    /// Check set membership
+    pub async fn sismember(&mut self, key: &str, member: &str) -> Result<bool, ClientError> {
+        let reply = self.collection(CollectionCommand::SIsMember, key, &[text(member)]).await?;
+        Ok(count(reply)? == 1)
+    }
+
+    /// All members of a set, in no particular order
+    pub async fn smembers(&mut self, key: &str) -> Result<Vec<String>, ClientError> {
+        let reply = self.collection(CollectionCommand::SMembers, key, &[]).await?;
+        list(reply)?.into_iter().map(string).collect()
+    }
+
+    /// Number of members in a set
+    pub async fn scard(&mut self, key: &str) -> Result<usize, ClientError> {
+        let reply = self.collection(CollectionCommand::SCard, key, &[]).await?;
+        count(reply)
+    }
+
+    /// Add members to a sorted set or update their scores
+    ///
+    /// Returns how many members were added (not updated).
+    pub async fn zadd(&mut self, key: &str, members: &[(f64, &str)]) -> Result<usize, ClientError> {
+        let args: Vec<KvValue> = members
+            .iter()
+            .flat_map(|(score, member)| [KvValue::Float(*score), text(member)])
+            .collect();
+        let reply = self.collection(CollectionCommand::ZAdd, key, &args).await?;
+        count(reply)
+    }
+
+    /// Increment a member's score, returning the new score
+    pub async fn zincrby(&mut self, key: &str, member: &str, delta: f64) -> Result<f64, ClientError> {
+        let reply = self.collection(CollectionCommand::ZIncrBy, key, &[text(member), KvValue::Float(delta)]).await?;
+        float(reply)
+    }
+
+    /// Remove members from a sorted set, returning how many existed
+    pub async fn zrem(&mut self, key: &str, members: &[&str]) -> Result<usize, ClientError> {
+        let args: Vec<KvValue> = members.iter().map(|member| text(member)).collect();
+        let reply = self.collection(CollectionCommand::ZRem, key, &args).await?;
+        count(reply)
+    }
+
+    /// Score of a member
+    pub async fn zscore(&mut self, key: &str, member: &str) -> Result<Option<f64>, ClientError> {
+        let reply = self.collection(CollectionCommand::ZScore, key, &[text(member)]).await?;
+        optional(reply).map(float).transpose()
+    }
+
+    /// Rank of a member by ascending score, or descending if `rev`
+    pub async fn zrank(&mut self, key: &str, member: &str, rev: bool) -> Result<Option<usize>, ClientError> {
+        let cmd = if rev { CollectionCommand::ZRevRank } else { CollectionCommand::ZRank };
+        let reply = self.collection(cmd, key, &[text(member)]).await?;
+        optional(reply).map(count).transpose()
+    }
+
+    /// Members with scores from rank `start` to `stop` (inclusive, negative counts from the end)
+    ///
+    /// Ranks are by ascending score, or descending if `rev`.
+    pub async fn zrange(&mut self, key: &str, start: i64, stop: i64, rev: bool) -> Result<Vec<(String, f64)>, ClientError> {
+        let cmd = if rev { CollectionCommand::ZRevRange } else { CollectionCommand::ZRange };
+        let reply = self.collection(cmd, key, &[KvValue::Int(start), KvValue::Int(stop)]).await?;
+        scored(reply)
+    }
+
+    /// Members with scores between `min` and `max`, in ascending score order
+    pub async fn zrangebyscore(&mut self, key: &str, min: Bound<f64>, max: Bound<f64>) -> Result<Vec<(String, f64)>, ClientError> {
+        // min, max, flags (bit 0: min exclusive, bit 1: max exclusive)
+        let (min, min_exclusive) = bound(min, f64::NEG_INFINITY);
+        let (max, max_exclusive) = bound(max, f64::INFINITY);
+        let flags = min_exclusive as i64 | (max_exclusive as i64) << 1;
+        let args = [KvValue::Float(min), KvValue::Float(max), KvValue::Int(flags)];
+        let reply = self.collection(CollectionCommand::ZRangeByScore, key, &args).await?;
+        scored(reply)
+    }
+
+    /// Number of members in a sorted set
+    pub async fn zcard(&mut self, key: &str) -> Result<usize, ClientError> {
+        let reply = self.collection(CollectionCommand::ZCard, key, &[]).await?;
+        count(reply)
+    }
+
+    // ==================== Transactions ====================
+
+    /// Current version of a key, for use with [`Transaction::watch`]
+    ///
+    /// Returns 0 if the key does not exist.
+    pub async fn watch(&mut self, key: &str) -> Result<u64, ClientError> {
+        let prefixed_key = self.prefix_key(key);
+        let (_, resp) = self.request(Command::Watch, prefixed_key.as_bytes()).await?;
+        if resp.len() >= 8 {
+            Ok(u64::from_be_bytes(resp[0..8].try_into().unwrap()))
+        } else {
+            Err(ClientError::Protocol(ProtocolError::UnexpectedEof))
+        }
+    }
+
+    /// Execute a transaction atomically on the server
+    ///
+    /// Keys are namespaced like every other command. Returns None if a guard
+    /// failed (nothing was changed), otherwise one result per operation. An
+    /// operation failing on the server (e.g. `incr` on a string) rolls the
+    /// whole transaction back and is returned as [`ClientError::Server`].
+    pub async fn transact(&mut self, txn: &Transaction) -> Result<Option<Vec<TxnResult>>, ClientError> {
+        let payload = encode_exec_payload(txn, |key| self.prefix_key(key));
+        let (status, resp) = self.request(Command::Exec, &payload).await?;
+        if status == Status::Null {
+            return Ok(None);
+        }
+        Ok(Some(decode_exec_response(&resp)?))
+    }
+}
+
+// ==================== Collection replies ====================
+
+fn unexpected(reply: &KvValue) -> ClientError {
+    ClientError::Server(format!("Unexpected {} reply", reply.type_name()))
+}
+
+fn text(s: &str) -> KvValue {
+    KvValue::String(s.to_string())
+}
+
+fn optional(value: KvValue) -> Option<KvValue> {
+    match value {
+        KvValue::Null => None,
+        value => Some(value),
+    }
+}
+
+fn int(value: KvValue) -> Result<i64, ClientError> {
+    match value {
+        KvValue::Int(n) => Ok(n),
+        other => Err(unexpected(&other)),
+    }
+}
+
+fn count(value: KvValue) -> Result<usize, ClientError> {
+    let n = int(value)?;
+    usize::try_from(n).map_err(|_| unexpected(&KvValue::Int(n)))
+}
+
+fn float(value: KvValue) -> Result<f64, ClientError> {
+    match value {
+        KvValue::Float(f) => Ok(f),
+        other => Err(unexpected(&other)),
+    }
+}
+
+fn string(value: KvValue) -> Result<String, ClientError> {
+    match value {
+        KvValue::String(s) => Ok(s),
+        other => Err(unexpected(&other)),
+    }
+}
+
+fn list(value: KvValue) -> Result<Vec<KvValue>, ClientError> {
+    match value {
+        KvValue::List(items) => Ok(items),
+        other => Err(unexpected(&other)),
+    }
+}
+
+/// Sorted set entries sent as `[member, score]` pairs
+fn scored(value: KvValue) -> Result<Vec<(String, f64)>, ClientError> {
+    list(value)?
+        .into_iter()
+        .map(|entry| match entry {
+            KvValue::List(pair) if pair.len() == 2 => {
+                let mut pair = pair.into_iter();
+                Ok((string(pair.next().unwrap())?, float(pair.next().unwrap())?))
+            }
+            other => Err(unexpected(&other)),
+        })
+        .collect()
+}
+
+/// A score bound as (score, exclusive); unbounded becomes infinite
+fn bound(bound: Bound<f64>, unbounded: f64) -> (f64, bool) {
+    match bound {
+        Bound::Included(score) => (score, false),
+        Bound::Excluded(score) => (score, true),
+        Bound::Unbounded => (unbounded, false),
+    }
 }
 
 #[cfg(test)]
@@ -525,6 +1129,20 @@ mod tests {
         assert_eq!(result, Some(KvValue::String("hello".to_string())));
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn test_auth() {
+        // Without an ACL file the default user takes any password
+        let options = ConnectOptions::new().password("anything");
+        let mut client = KvClient::connect_with("127.0.0.1:6380", &options).await.unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+
+        let result = client.auth(Some("nobody"), "secret").await;
+        assert!(matches!(result, Err(ClientError::Server(msg)) if msg.starts_with("WRONGPASS")));
+        // A failed AUTH keeps the connection's identity
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_incr() {
@@ -633,4 +1251,131 @@ mod tests {
         // Clean up
         client.delete("session").await.unwrap();
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_scan_namespace() {
+        let mut client = KvClient::connect("127.0.0.1:6380/scan_test").await.unwrap();
+        let mut other = KvClient::connect("127.0.0.1:6380/scan_other").await.unwrap();
+
+        for i in 0..50 {
+            client.set(&format!("item:{}", i), KvValue::Int(i), None).await.unwrap();
+            other.set(&format!("item:{}", i), KvValue::Int(i), None).await.unwrap();
+        }
+
+        let mut keys = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = client.scan(cursor, Some("item:*"), 10).await.unwrap();
+            keys.extend(batch);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(keys.len(), 50);
+        assert!(keys.iter().all(|k| k.starts_with("item:")));
+
+        assert_eq!(client.keys("item:1*").await.unwrap().len(), 11);
+
+        let refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+        client.mdel(&refs).await.unwrap();
+        other.mdel(&refs).await.unwrap();
+        assert!(client.keys("*").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_scan_rejects_oversized_pattern() {
+        let mut client = KvClient::connect("127.0.0.1:6380").await.unwrap();
+        let pattern = "a".repeat(u16::MAX as usize + 1);
+        let result = client.scan(0, Some(&pattern), 10).await;
+        assert!(matches!(result, Err(ClientError::InvalidArgument(_))));
+
+        // The connection is still usable afterwards
+        client.scan(0, Some("*"), 10).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_publish_subscribe() {
+        let subscriber = KvClient::connect("127.0.0.1:6380").await.unwrap();
+        let mut subscription = subscriber.subscribe(&["test:channel"]).await.unwrap();
+        assert_eq!(subscription.psubscribe(&["test:pattern:*"]).await.unwrap(), 2);
+
+        let mut publisher = KvClient::connect("127.0.0.1:6380").await.unwrap();
+        assert_eq!(publisher.publish("test:channel", b"hello").await.unwrap(), 1);
+        assert_eq!(publisher.publish("test:pattern:1", b"world").await.unwrap(), 1);
+        assert_eq!(publisher.publish("test:other", b"ignored").await.unwrap(), 0);
+
+        let message = subscription.next_message().await.unwrap();
+        assert_eq!(message.channel, "test:channel");
+        assert_eq!(message.pattern, None);
+        assert_eq!(message.payload_str(), Some("hello"));
+
+        let message = subscription.next_message().await.unwrap();
+        assert_eq!(message.channel, "test:pattern:1");
+        assert_eq!(message.pattern.as_deref(), Some("test:pattern:*"));
+
+        assert_eq!(subscription.unsubscribe(&[]).await.unwrap(), 1);
+        assert_eq!(publisher.publish("test:channel", b"gone").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_transact() {
+        use ouroboros_kv::{Guard, KvKey};
+
+        let mut client = KvClient::connect("127.0.0.1:6380/txn").await.unwrap();
+        let stock = KvKey::new("stock").unwrap();
+        let reserved = KvKey::new("reserved").unwrap();
+        client.set("stock", KvValue::Int(5), None).await.unwrap();
+        client.delete("reserved").await.unwrap();
+
+        let reserve = Transaction::new()
+            .guard(&stock, Guard::AtLeast(3))
+            .incr(&stock, -3)
+            .incr(&reserved, 3);
+        let results = client.transact(&reserve).await.unwrap().unwrap();
+        assert_eq!(results, vec![TxnResult::Int(2), TxnResult::Int(3)]);
+
+        // Not enough stock left: nothing changes
+        assert_eq!(client.transact(&reserve).await.unwrap(), None);
+        assert_eq!(client.get("reserved").await.unwrap(), Some(KvValue::Int(3)));
+
+        // A write after WATCH aborts the transaction
+        let version = client.watch("stock").await.unwrap();
+        client.incr("stock", 1).await.unwrap();
+        let txn = Transaction::new().watch(&stock, version).delete(&stock);
+        assert_eq!(client.transact(&txn).await.unwrap(), None);
+
+        client.mdel(&["stock", "reserved"]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_collections() {
+        let mut client = KvClient::connect("127.0.0.1:6380/collections").await.unwrap();
+        client.mdel(&["queue", "board", "user"]).await.unwrap();
+
+        // Job queue
+        assert_eq!(client.rpush("queue", &[KvValue::Int(1), KvValue::Int(2)]).await.unwrap(), 2);
+        assert_eq!(client.lpop("queue", 1).await.unwrap(), vec![KvValue::Int(1)]);
+        assert_eq!(client.llen("queue").await.unwrap(), 1);
+
+        // Leaderboard
+        client.zadd("board", &[(10.0, "alice"), (30.0, "bob")]).await.unwrap();
+        assert_eq!(client.zincrby("board", "alice", 25.0).await.unwrap(), 35.0);
+        assert_eq!(client.zrank("board", "alice", true).await.unwrap(), Some(0));
+        assert_eq!(
+            client.zrangebyscore("board", Bound::Excluded(10.0), Bound::Unbounded).await.unwrap(),
+            vec![("bob".to_string(), 30.0), ("alice".to_string(), 35.0)]
+        );
+
+        assert_eq!(client.hincrby("user", "visits", 2).await.unwrap(), 2);
+        assert_eq!(client.hget("user", "visits").await.unwrap(), Some(KvValue::Int(2)));
+        assert!(client.lpush("user", &[KvValue::Int(1)]).await.is_err());
+
+        client.mdel(&["queue", "board", "user"]).await.unwrap();
+    }
 }
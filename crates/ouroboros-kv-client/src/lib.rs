@@ -5,10 +5,14 @@
 mod protocol;
 mod client;
 mod pool;
+mod subscription;
+mod tls;
 
-pub use client::{ClientError, KvClient};
+pub use client::{ClientError, ConnectOptions, KvClient};
 pub use pool::{KvPool, PoolConfig, PooledClient, PoolStats};
-pub use ouroboros_kv::{KvError, KvValue};
+pub use subscription::{Message, Subscription};
+pub use tls::TlsConfig;
+pub use ouroboros_kv::{Guard, KvError, KvValue, Transaction, TxnOp, TxnResult};
 
 // Re-export protocol types for advanced usage
-pub use protocol::{ProtocolError, Command, Status};
+pub use protocol::{ProtocolError, Command, CollectionCommand, Status};
@@ -5,13 +5,15 @@
 //! - Idle connection timeout
 //! - Automatic connection recycling
 //! - RAII guard for automatic return to pool
+//! - Read routing to replicas
 
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
-use crate::{KvClient, ClientError};
+use crate::{ClientError, ConnectOptions, KvClient, TlsConfig};
 
 /// Pool configuration
 #[derive(Debug, Clone)]
@@ -26,6 +28,10 @@ pub struct PoolConfig {
     pub idle_timeout: Duration,
     /// Timeout for acquiring a connection from pool
     pub acquire_timeout: Duration,
+    /// Read-only replica addresses (host:port), used by `acquire_read`
+    pub replicas: Vec<String>,
+    /// Authentication and TLS, used for the primary and every replica
+    pub options: ConnectOptions,
 }
 
 impl Default for PoolConfig {
@@ -36,6 +42,8 @@ impl Default for PoolConfig {
             max_size: 10,
             idle_timeout: Duration::from_secs(300),  // 5 min
             acquire_timeout: Duration::from_secs(5),
+            replicas: Vec::new(),
+            options: ConnectOptions::default(),
         }
     }
 }
@@ -72,6 +80,43 @@ impl PoolConfig {
         self.acquire_timeout = timeout;
         self
     }
+
+    /// Add a read-only replica (host:port); the primary's namespace is applied to it
+    pub fn replica(mut self, addr: impl Into<String>) -> Self {
+        self.replicas.push(addr.into());
+        self
+    }
+
+    /// Authenticate every connection as `username`
+    pub fn auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.options = self.options.auth(username, password);
+        self
+    }
+
+    /// Authenticate every connection as the default user
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.options = self.options.password(password);
+        self
+    }
+
+    /// Connect over TLS
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.options = self.options.tls(tls);
+        self
+    }
+
+    /// Config for one replica: same limits and namespace, no replicas of its own
+    fn for_replica(&self, addr: &str) -> Self {
+        let addr = match self.addr.find('/') {
+            Some(idx) if !addr.contains('/') => format!("{}{}", addr, &self.addr[idx..]),
+            _ => addr.to_string(),
+        };
+        Self {
+            addr,
+            replicas: Vec::new(),
+            ..self.clone()
+        }
+    }
 }
 
 /// Pooled connection entry with metadata
@@ -113,34 +158,56 @@ pub struct KvPool {
     idle: Mutex<VecDeque<PooledEntry>>,
     /// Count of active (in-use) connections
     active_count: Mutex<usize>,
+    /// One pool per replica, for reads
+    replicas: Vec<Arc<KvPool>>,
+    /// Round-robin position in `replicas`
+    next_replica: AtomicUsize,
 }
 
 impl KvPool {
     /// Create a new pool with the given config (without pre-warming)
     pub fn new(config: PoolConfig) -> Self {
+        let replicas = config
+            .replicas
+            .iter()
+            .map(|addr| Arc::new(Self::new(config.for_replica(addr))))
+            .collect();
         Self {
             config,
             idle: Mutex::new(VecDeque::new()),
             active_count: Mutex::new(0),
+            replicas,
+            next_replica: AtomicUsize::new(0),
         }
     }
 
     /// Create pool and pre-warm with min_size connections
+    ///
+    /// Replicas are pre-warmed too, but an unreachable replica is not an
+    /// error: reads fall back to the primary.
     pub async fn connect(config: PoolConfig) -> Result<Arc<Self>, ClientError> {
         let pool = Arc::new(Self::new(config.clone()));
 
-        // Pre-warm pool with min_size connections
-        for _ in 0..config.min_size {
-            let client = KvClient::connect(&config.addr).await?;
-            let mut idle = pool.idle.lock().await;
+        pool.warm().await?;
+        for replica in &pool.replicas {
+            let _ = replica.warm().await;
+        }
+
+        Ok(pool)
+    }
+
+    /// Pre-warm with min_size connections
+    async fn warm(&self) -> Result<(), ClientError> {
+        for _ in 0..self.config.min_size {
+            let client = KvClient::connect_with(&self.config.addr, &self.config.options).await?;
+            let mut idle = self.idle.lock().await;
             idle.push_back(PooledEntry {
                 client,
                 _created_at: Instant::now(),
                 last_used: Instant::now(),
             });
         }
-
-        Ok(pool)
+        Ok(())
     }
 
     /// Get namespace from config addr
@@ -193,7 +260,7 @@ impl KvPool {
                     drop(active);
 
                     // Create new connection
-                    let client = KvClient::connect(&self.config.addr).await?;
+                    let client = KvClient::connect_with(&self.config.addr, &self.config.options).await?;
                     let mut active = self.active_count.lock().await;
                     *active += 1;
                     return Ok(PooledClient {
@@ -213,6 +280,28 @@ impl KvPool {
         }
     }
 
+    /// Get a connection for reads
+    ///
+    /// Picks replicas round-robin, skipping any that cannot be reached, and
+    /// falls back to the primary when there are none. Replicas apply writes
+    /// asynchronously, so reads may briefly return stale data. Writes sent on
+    /// a replica connection fail with a `READONLY` error.
+    pub async fn acquire_read(self: &Arc<Self>) -> Result<PooledClient, ClientError> {
+        let start = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        for i in 0..self.replicas.len() {
+            let replica = &self.replicas[(start + i) % self.replicas.len()];
+            if let Ok(conn) = replica.acquire().await {
+                return Ok(conn);
+            }
+        }
+        self.acquire().await
+    }
+
+    /// Number of configured replicas
+    pub fn replica_count(&self) -> usize {
+        self.replicas.len()
+    }
+
     /// Return a connection to the pool
     async fn release(&self, client: KvClient) {
         let mut active = self.active_count.lock().await;
@@ -230,6 +319,23 @@ impl KvPool {
         // else: drop the connection (pool is full)
     }
 
+    /// Incrementally iterate keys (SCAN) on a pooled connection
+    ///
+    /// Cursors are valid across connections, so each call may use a different one.
+    /// See [`KvClient::scan`].
+    pub async fn scan(self: &Arc<Self>, cursor: u64, pattern: Option<&str>, count: usize) -> Result<(u64, Vec<String>), ClientError> {
+        let mut conn = self.acquire().await?;
+        conn.client().scan(cursor, pattern, count).await
+    }
+
+    /// All keys matching a glob pattern (KEYS) on a pooled connection
+    ///
+    /// See [`KvClient::keys`].
+    pub async fn keys(self: &Arc<Self>, pattern: &str) -> Result<Vec<String>, ClientError> {
+        let mut conn = self.acquire().await?;
+        conn.client().keys(pattern).await
+    }
+
     /// Get pool statistics
     pub async fn stats(&self) -> PoolStats {
         let idle = self.idle.lock().await;
@@ -305,6 +411,31 @@ mod tests {
         assert_eq!(pool.namespace(), None);
     }
 
+    #[test]
+    fn test_replica_config_inherits_namespace() {
+        let config = PoolConfig::new("10.0.0.1:6380/cache")
+            .max_size(4)
+            .replica("10.0.0.2:6380")
+            .replica("10.0.0.3:6380/other");
+
+        let pool = KvPool::new(config);
+        assert_eq!(pool.replica_count(), 2);
+        assert_eq!(pool.replicas[0].config.addr, "10.0.0.2:6380/cache");
+        assert_eq!(pool.replicas[0].config.max_size, 4);
+        assert!(pool.replicas[0].config.replicas.is_empty());
+        assert_eq!(pool.replicas[1].config.addr, "10.0.0.3:6380/other");
+    }
+
+    #[tokio::test]
+    async fn test_acquire_read_falls_back_to_primary() {
+        // Nothing listens on port 1, so the replica is skipped and the primary is used
+        let config = PoolConfig::new("127.0.0.1:1")
+            .replica("127.0.0.1:1")
+            .acquire_timeout(Duration::from_millis(100));
+        let pool = Arc::new(KvPool::new(config));
+        assert!(matches!(pool.acquire_read().await, Err(ClientError::Connection(_))));
+    }
+
     // Integration tests require a running server
     // Run: cargo run -p ouroboros-kv-server
     // Then: cargo test -p ouroboros-kv-client -- --ignored
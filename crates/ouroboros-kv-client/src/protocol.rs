@@ -2,8 +2,8 @@
 //!
 //! Binary protocol for KV operations.
 
-use ouroboros_kv::KvValue;
-use std::collections::HashMap;
+use ouroboros_kv::{Guard, KvValue, SortedSet, Transaction, TxnOp, TxnResult};
+use std::collections::{HashMap, HashSet};
 use std::io;
 use thiserror::Error;
 
@@ -49,6 +49,20 @@ pub enum Command {
     MGet = 0x0E,
     MSet = 0x0F,
     MDel = 0x10,
+    Scan = 0x11,
+    Publish = 0x12,
+    Subscribe = 0x13,
+    Unsubscribe = 0x14,
+    PSubscribe = 0x15,
+    PUnsubscribe = 0x16,
+    // Replication (sent by replicas to their primary)
+    Sync = 0x17,
+    Watch = 0x18,
+    Exec = 0x19,
+    // Hashes, lists, sets and sorted sets
+    Collection = 0x1A,
+    // Authentication
+    Auth = 0x1B,
 }
 
 impl TryFrom<u8> for Command {
@@ -72,11 +86,64 @@ impl TryFrom<u8> for Command {
             0x0E => Ok(Command::MGet),
             0x0F => Ok(Command::MSet),
             0x10 => Ok(Command::MDel),
+            0x11 => Ok(Command::Scan),
+            0x12 => Ok(Command::Publish),
+            0x13 => Ok(Command::Subscribe),
+            0x14 => Ok(Command::Unsubscribe),
+            0x15 => Ok(Command::PSubscribe),
+            0x16 => Ok(Command::PUnsubscribe),
+            0x17 => Ok(Command::Sync),
+            0x18 => Ok(Command::Watch),
+            0x19 => Ok(Command::Exec),
+            0x1A => Ok(Command::Collection),
+            0x1B => Ok(Command::Auth),
             _ => Err(ProtocolError::InvalidCommand(byte)),
         }
     }
 }
 
+/// Structure commands carried by `Command::Collection`
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollectionCommand {
+    // Hashes
+    HSet = 0x01,
+    HSetNx = 0x02,
+    HDel = 0x03,
+    HIncrBy = 0x04,
+    HGet = 0x05,
+    HMGet = 0x06,
+    HGetAll = 0x07,
+    HExists = 0x08,
+    HLen = 0x09,
+    // Lists
+    LPush = 0x10,
+    RPush = 0x11,
+    LPop = 0x12,
+    RPop = 0x13,
+    LTrim = 0x14,
+    LRange = 0x15,
+    LIndex = 0x16,
+    LLen = 0x17,
+    // Sets
+    SAdd = 0x20,
+    SRem = 0x21,
+    SIsMember = 0x22,
+    SMembers = 0x23,
+    SCard = 0x24,
+    // Sorted sets
+    ZAdd = 0x30,
+    ZIncrBy = 0x31,
+    ZRem = 0x32,
+    ZScore = 0x33,
+    ZRank = 0x34,
+    ZRevRank = 0x35,
+    ZRange = 0x36,
+    ZRevRange = 0x37,
+    ZRangeByScore = 0x38,
+    ZCard = 0x39,
+}
+
 /// Response status codes
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -84,6 +151,8 @@ pub enum Status {
     Ok = 0x00,
     Null = 0x01,
     Error = 0x02,
+    /// Pub/Sub message pushed by the server (not a response)
+    Message = 0x03,
 }
 
 /// Value type codes
@@ -100,6 +169,8 @@ pub enum ValueType {
     Map = 0x07,
     #[allow(dead_code)]
     Bool = 0x08,
+    Set = 0x09,
+    SortedSet = 0x0A,
 }
 
 /// Encode a KvValue to bytes
@@ -154,6 +225,23 @@ fn encode_value_into(buf: &mut Vec<u8>, value: &KvValue) {
                 encode_value_into(buf, v);
             }
         }
+        KvValue::Set(members) => {
+            buf.push(ValueType::Set as u8);
+            buf.extend_from_slice(&(members.len() as u32).to_be_bytes());
+            for member in members {
+                buf.extend_from_slice(&(member.len() as u32).to_be_bytes());
+                buf.extend_from_slice(member.as_bytes());
+            }
+        }
+        KvValue::SortedSet(zset) => {
+            buf.push(ValueType::SortedSet as u8);
+            buf.extend_from_slice(&(zset.len() as u32).to_be_bytes());
+            for (member, score) in zset.iter() {
+                buf.extend_from_slice(&(member.len() as u32).to_be_bytes());
+                buf.extend_from_slice(member.as_bytes());
+                buf.extend_from_slice(&score.to_be_bytes());
+            }
+        }
     }
 }
 
@@ -270,10 +358,166 @@ pub fn decode_value(data: &[u8]) -> Result<(KvValue, usize), ProtocolError> {
             }
             Ok((KvValue::Map(map), pos))
         }
+        0x09 | 0x0A => {
+            // Set / SortedSet: count + [member (+ score)]
+            if data.len() < pos + 4 {
+                return Err(ProtocolError::UnexpectedEof);
+            }
+            let count = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            let mut members = HashSet::new();
+            let mut zset = SortedSet::new();
+            for _ in 0..count {
+                if data.len() < pos + 4 {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
+                let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+                pos += 4;
+                if data.len() < pos + len {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
+                let member = std::str::from_utf8(&data[pos..pos + len])
+                    .map_err(|_| ProtocolError::InvalidUtf8)?
+                    .to_string();
+                pos += len;
+                if type_byte == 0x09 {
+                    members.insert(member);
+                    continue;
+                }
+                if data.len() < pos + 8 {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
+                let score = f64::from_be_bytes(data[pos..pos + 8].try_into().unwrap());
+                pos += 8;
+                if score.is_nan() {
+                    return Err(ProtocolError::InvalidValueType(type_byte));
+                }
+                zset.insert(member, score);
+            }
+            if type_byte == 0x09 {
+                Ok((KvValue::Set(members), pos))
+            } else {
+                Ok((KvValue::SortedSet(zset), pos))
+            }
+        }
         _ => Err(ProtocolError::InvalidValueType(type_byte)),
     }
 }
 
+/// Encode EXEC payload, passing every key through `key`
+///
+/// guard_count(2) + [key_len(2) + key + guard(1) + operand]...
+/// + op_count(2) + [op(1) + key_len(2) + key + operand]...
+pub fn encode_exec_payload(txn: &Transaction, key: impl Fn(&str) -> String) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let push_key = |buf: &mut Vec<u8>, k: &str| {
+        let k = key(k);
+        buf.extend_from_slice(&(k.len() as u16).to_be_bytes());
+        buf.extend_from_slice(k.as_bytes());
+    };
+    let ttl_ms = |ttl: &Option<std::time::Duration>| ttl.map(|d| d.as_millis() as u64).unwrap_or(0);
+
+    buf.extend_from_slice(&(txn.guards.len() as u16).to_be_bytes());
+    for (k, guard) in &txn.guards {
+        push_key(&mut buf, k);
+        match guard {
+            Guard::Exists => buf.push(0),
+            Guard::Missing => buf.push(1),
+            Guard::Equals(value) => {
+                buf.push(2);
+                encode_value_into(&mut buf, value);
+            }
+            Guard::AtLeast(min) => {
+                buf.push(3);
+                buf.extend_from_slice(&min.to_be_bytes());
+            }
+            Guard::Version(version) => {
+                buf.push(4);
+                buf.extend_from_slice(&version.to_be_bytes());
+            }
+        }
+    }
+
+    buf.extend_from_slice(&(txn.ops.len() as u16).to_be_bytes());
+    for op in &txn.ops {
+        let code = match op {
+            TxnOp::Get { .. } => 0,
+            TxnOp::Set { .. } => 1,
+            TxnOp::SetNx { .. } => 2,
+            TxnOp::Delete { .. } => 3,
+            TxnOp::Incr { .. } => 4,
+            TxnOp::Expire { .. } => 5,
+        };
+        buf.push(code);
+        push_key(&mut buf, op.key());
+        match op {
+            TxnOp::Set { value, ttl, .. } | TxnOp::SetNx { value, ttl, .. } => {
+                buf.extend_from_slice(&ttl_ms(ttl).to_be_bytes());
+                encode_value_into(&mut buf, value);
+            }
+            TxnOp::Incr { delta, .. } => buf.extend_from_slice(&delta.to_be_bytes()),
+            TxnOp::Expire { ttl, .. } => buf.extend_from_slice(&ttl_ms(ttl).to_be_bytes()),
+            TxnOp::Get { .. } | TxnOp::Delete { .. } => {}
+        }
+    }
+
+    buf
+}
+
+/// Encode COLLECTION payload: command(1) + key_len(2) + key + arg_count(2) + [value]...
+pub fn encode_collection_payload(cmd: CollectionCommand, key: &str, args: &[KvValue]) -> Vec<u8> {
+    let mut buf = vec![cmd as u8];
+    buf.extend_from_slice(&(key.len() as u16).to_be_bytes());
+    buf.extend_from_slice(key.as_bytes());
+    buf.extend_from_slice(&(args.len() as u16).to_be_bytes());
+    for arg in args {
+        encode_value_into(&mut buf, arg);
+    }
+    buf
+}
+
+/// Decode EXEC response: count(2) + [result]...
+pub fn decode_exec_response(data: &[u8]) -> Result<Vec<TxnResult>, ProtocolError> {
+    if data.len() < 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let count = u16::from_be_bytes([data[0], data[1]]) as usize;
+    let mut pos = 2;
+    let mut results = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let tag = *data.get(pos).ok_or(ProtocolError::UnexpectedEof)?;
+        pos += 1;
+        let result = match tag {
+            0 => {
+                if data.get(pos) == Some(&(ValueType::Null as u8)) {
+                    pos += 1;
+                    TxnResult::Value(None)
+                } else {
+                    let (value, consumed) = decode_value(&data[pos..])?;
+                    pos += consumed;
+                    TxnResult::Value(Some(value))
+                }
+            }
+            1 => {
+                let bytes = data.get(pos..pos + 8).ok_or(ProtocolError::UnexpectedEof)?;
+                pos += 8;
+                TxnResult::Int(i64::from_be_bytes(bytes.try_into().unwrap()))
+            }
+            2 => {
+                let byte = *data.get(pos).ok_or(ProtocolError::UnexpectedEof)?;
+                pos += 1;
+                TxnResult::Bool(byte == 1)
+            }
+            3 => TxnResult::Ok,
+            other => return Err(ProtocolError::InvalidValueType(other)),
+        };
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -305,4 +549,25 @@ mod tests {
         let (decoded, _) = decode_value(&encoded).unwrap();
         assert_eq!(value, decoded);
     }
+
+    #[test]
+    fn test_decode_exec_response() {
+        let mut data = vec![0, 4];
+        data.push(0);
+        data.extend(encode_value(&KvValue::Int(7)));
+        data.extend([0, ValueType::Null as u8]);
+        data.push(1);
+        data.extend(3i64.to_be_bytes());
+        data.extend([2, 1]);
+        assert_eq!(
+            decode_exec_response(&data).unwrap(),
+            vec![
+                TxnResult::Value(Some(KvValue::Int(7))),
+                TxnResult::Value(None),
+                TxnResult::Int(3),
+                TxnResult::Bool(true),
+            ]
+        );
+        assert!(decode_exec_response(&data[..data.len() - 1]).is_err());
+    }
 }
@@ -0,0 +1,201 @@
+//! Pub/Sub subscriptions
+//!
+//! A subscription owns its connection. Messages pushed by the server are
+//! read with [`Subscription::next_message`]; messages that arrive while a
+//! (un)subscribe request is in flight are queued and returned in order.
+
+use crate::client::{encode_names, encode_request, read_frame, send, ClientError, KvClient};
+use crate::protocol::{Command, ProtocolError, Status};
+use crate::tls::Stream;
+use std::collections::VecDeque;
+use tokio::sync::mpsc;
+
+/// A message received on a subscription
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// Channel the message was published to
+    pub channel: String,
+    /// Pattern that matched, for pattern subscriptions
+    pub pattern: Option<String>,
+    /// Message body
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    /// Message body as UTF-8, if valid
+    pub fn payload_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.payload).ok()
+    }
+
+    /// Decode a pushed frame: pattern_len(2) + pattern + channel_len(2) + channel + message
+    fn decode(data: &[u8]) -> Result<Self, ProtocolError> {
+        let (pattern, pos) = read_name(data, 0)?;
+        let (channel, pos) = read_name(data, pos)?;
+        Ok(Self {
+            channel,
+            pattern: if pattern.is_empty() { None } else { Some(pattern) },
+            payload: data[pos..].to_vec(),
+        })
+    }
+}
+
+/// Read a length-prefixed (u16) UTF-8 string starting at `pos`
+fn read_name(data: &[u8], pos: usize) -> Result<(String, usize), ProtocolError> {
+    if data.len() < pos + 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+    let start = pos + 2;
+    if data.len() < start + len {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let name = std::str::from_utf8(&data[start..start + len])
+        .map_err(|_| ProtocolError::InvalidUtf8)?
+        .to_string();
+    Ok((name, start + len))
+}
+
+/// Parse a u32 count response
+pub(crate) fn parse_count(resp: &[u8]) -> Result<usize, ClientError> {
+    if resp.len() < 4 {
+        return Err(ClientError::Protocol(ProtocolError::UnexpectedEof));
+    }
+    Ok(u32::from_be_bytes([resp[0], resp[1], resp[2], resp[3]]) as usize)
+}
+
+/// A connection subscribed to channels and/or patterns
+///
+/// Created with [`KvClient::subscribe`] or [`KvClient::psubscribe`].
+pub struct Subscription {
+    stream: Box<dyn Stream>,
+    /// Messages received while waiting for a command response
+    pending: VecDeque<Message>,
+    /// Channels + patterns currently subscribed
+    count: usize,
+}
+
+impl Subscription {
+    pub(crate) fn new(client: KvClient) -> Self {
+        let (stream, _) = client.into_parts();
+        Self {
+            stream,
+            pending: VecDeque::new(),
+            count: 0,
+        }
+    }
+
+    /// Subscribe to more channels, returns the total subscription count
+    pub async fn subscribe(&mut self, channels: &[&str]) -> Result<usize, ClientError> {
+        self.command(Command::Subscribe, channels).await
+    }
+
+    /// Unsubscribe from channels (all channels if empty), returns the total subscription count
+    pub async fn unsubscribe(&mut self, channels: &[&str]) -> Result<usize, ClientError> {
+        self.command(Command::Unsubscribe, channels).await
+    }
+
+    /// Subscribe to more glob patterns, returns the total subscription count
+    pub async fn psubscribe(&mut self, patterns: &[&str]) -> Result<usize, ClientError> {
+        self.command(Command::PSubscribe, patterns).await
+    }
+
+    /// Unsubscribe from patterns (all patterns if empty), returns the total subscription count
+    pub async fn punsubscribe(&mut self, patterns: &[&str]) -> Result<usize, ClientError> {
+        self.command(Command::PUnsubscribe, patterns).await
+    }
+
+    /// Number of channels and patterns currently subscribed
+    pub fn subscription_count(&self) -> usize {
+        self.count
+    }
+
+    /// Wait for the next message
+    ///
+    /// Returns `ClientError::Server` if the server dropped the subscription
+    /// because it fell too far behind.
+    pub async fn next_message(&mut self) -> Result<Message, ClientError> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(message);
+        }
+
+        loop {
+            let (status, payload) = read_frame(&mut self.stream).await?;
+            match status {
+                Status::Message => return Ok(Message::decode(&payload)?),
+                Status::Error => {
+                    return Err(ClientError::Server(String::from_utf8_lossy(&payload).to_string()))
+                }
+                // Stray response, nothing is waiting for it
+                Status::Ok | Status::Null => continue,
+            }
+        }
+    }
+
+    /// Deliver messages through a channel from a background task
+    ///
+    /// Useful in `tokio::select!` loops. The task ends, after forwarding the
+    /// error, when the connection fails or the receiver is dropped.
+    pub fn into_receiver(mut self, buffer: usize) -> mpsc::Receiver<Result<Message, ClientError>> {
+        let (tx, rx) = mpsc::channel(buffer.max(1));
+        tokio::spawn(async move {
+            loop {
+                let message = self.next_message().await;
+                let failed = message.is_err();
+                if tx.send(message).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Send a (un)subscribe command and wait for its count response
+    async fn command(&mut self, cmd: Command, names: &[&str]) -> Result<usize, ClientError> {
+        let req = encode_request(cmd, &encode_names(names));
+        send(&mut self.stream, &req).await?;
+
+        loop {
+            let (status, payload) = read_frame(&mut self.stream).await?;
+            match status {
+                Status::Message => self.pending.push_back(Message::decode(&payload)?),
+                Status::Error => {
+                    return Err(ClientError::Server(String::from_utf8_lossy(&payload).to_string()))
+                }
+                Status::Ok | Status::Null => {
+                    self.count = parse_count(&payload)?;
+                    return Ok(self.count);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_message() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&7u16.to_be_bytes());
+        data.extend_from_slice(b"cache:*");
+        data.extend_from_slice(&10u16.to_be_bytes());
+        data.extend_from_slice(b"cache:user");
+        data.extend_from_slice(b"invalidate");
+
+        let message = Message::decode(&data).unwrap();
+        assert_eq!(message.pattern.as_deref(), Some("cache:*"));
+        assert_eq!(message.channel, "cache:user");
+        assert_eq!(message.payload_str(), Some("invalidate"));
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(b"c");
+        let message = Message::decode(&data).unwrap();
+        assert_eq!(message.pattern, None);
+        assert!(message.payload.is_empty());
+
+        assert!(Message::decode(&[0, 5, b'a']).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! TLS connections to kv-server
+//!
+//! Enabled per connection through [`ConnectOptions::tls`](crate::ConnectOptions::tls).
+//! The server certificate is checked against either a CA bundle or the
+//! platform's trusted roots.
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use std::fmt;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// A connection that may or may not be encrypted
+pub(crate) trait Stream: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Stream for T {}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// TLS settings for connecting to a kv-server
+#[derive(Clone)]
+pub struct TlsConfig {
+    config: Arc<ClientConfig>,
+    /// Name the certificate must be valid for, instead of the address's host
+    server_name: Option<String>,
+}
+
+impl TlsConfig {
+    /// Trust the certificates in a PEM CA bundle
+    pub fn from_ca_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(std::fs::File::open(path)?);
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut reader) {
+            roots.add(cert?).map_err(|e| invalid(format!("Invalid CA certificate: {}", e)))?;
+        }
+        if roots.is_empty() {
+            return Err(invalid(format!("No certificates found in {}", path.display())));
+        }
+        Self::with_roots(roots)
+    }
+
+    /// Trust the platform's root certificates
+    pub fn native_roots() -> io::Result<Self> {
+        let mut roots = RootCertStore::empty();
+        let found = rustls_native_certs::load_native_certs();
+        roots.add_parsable_certificates(found.certs);
+        if roots.is_empty() {
+            return Err(match found.errors.first() {
+                Some(e) => invalid(format!("No native root certificates found: {}", e)),
+                None => invalid("No native root certificates found".to_string()),
+            });
+        }
+        Self::with_roots(roots)
+    }
+
+    /// Use a prepared rustls configuration (e.g. for client certificates)
+    pub fn from_config(config: Arc<ClientConfig>) -> Self {
+        Self { config, server_name: None }
+    }
+
+    /// Verify the server certificate against this name instead of the host
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    fn with_roots(roots: RootCertStore) -> io::Result<Self> {
+        let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| invalid(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Self::from_config(Arc::new(config)))
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConfig").field("server_name", &self.server_name).finish_non_exhaustive()
+    }
+}
+
+/// Connect to `addr` (host:port), over TLS if configured
+pub(crate) async fn connect(addr: &str, tls: Option<&TlsConfig>) -> io::Result<Box<dyn Stream>> {
+    let stream = TcpStream::connect(addr).await?;
+    // Disable Nagle's algorithm for lower latency
+    stream.set_nodelay(true)?;
+    let Some(tls) = tls else {
+        return Ok(Box::new(stream));
+    };
+
+    let host = match &tls.server_name {
+        Some(name) => name.as_str(),
+        None => {
+            let host = addr.rsplit_once(':').map(|(host, _)| host).unwrap_or(addr);
+            host.trim_start_matches('[').trim_end_matches(']')
+        }
+    };
+    let name = ServerName::try_from(host.to_string()).map_err(|e| invalid(format!("Invalid server name {:?}: {}", host, e)))?;
+    let stream = TlsConnector::from(Arc::clone(&tls.config)).connect(name, stream).await?;
+    Ok(Box::new(stream))
+}
@@ -0,0 +1,534 @@
+//! Authentication and access control
+//!
+//! Users are defined in an ACL file, one Redis-style `user` line each:
+//!
+//! ```text
+//! # Unauthenticated connections get nothing
+//! user default off
+//! user admin on #5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8 allkeys allchannels +@all
+//! user worker on >long-random-token namespace=tasks &tasks:* +@all -@admin
+//! ```
+//!
+//! Rules are applied left to right:
+//!
+//! - `on` / `off` enable or disable the user
+//! - `>password` adds a password, `#<sha256 hex>` adds one by its hash so the
+//!   file holds no secrets; `nopass` accepts any password, `resetpass` forgets
+//!   them all. A token is simply a long random password.
+//! - `~prefix*` allows keys starting with `prefix`, `allkeys` (or `~*`) every
+//!   key, `resetkeys` none. `namespace=ns` allows the keys of `KvClient`
+//!   namespace `ns` (the same as `~ns:*`).
+//! - `&prefix*`, `allchannels` (or `&*`) and `resetchannels` do the same for
+//!   pub/sub channels.
+//! - `+command` / `-command` allow or deny a command, `+@category` /
+//!   `-@category` a whole [`Category`], `allcommands` / `nocommands` every
+//!   command. When rules overlap, the last one wins.
+//!
+//! Commands have their Redis names on both protocols; the native-only ones
+//! are `lock`, `unlock`, `extendlock`, `cas` and `sync`. Connection commands
+//! (AUTH, HELLO, PING, QUIT, ...) need no permission, but every other command
+//! needs an authenticated user.
+//!
+//! Without an ACL file the `default` user is `on nopass allkeys allchannels
+//! allcommands`, or has a single password with `--requirepass`. Connections
+//! start out as `default` when it is enabled and needs no password, and must
+//! AUTH otherwise.
+
+use sha2::{Digest, Sha256};
+use subtle::{Choice, ConstantTimeEq};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Name of the user unauthenticated connections and password-only AUTH use
+pub const DEFAULT_USER: &str = "default";
+
+/// Error loading an ACL file
+#[derive(Error, Debug)]
+pub enum AclError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ACL line {line}: {message}")]
+    Syntax { line: usize, message: String },
+}
+
+/// Why a connection may not do something; the message is sent to the client
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Denied {
+    #[error("NOAUTH Authentication required.")]
+    NoAuth,
+
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+
+    #[error("NOPERM User {user} has no permissions to run the '{command}' command")]
+    Command { user: String, command: String },
+
+    #[error("NOPERM No permissions to access a key")]
+    Key,
+
+    #[error("NOPERM No permissions to access a channel")]
+    Channel,
+}
+
+/// Command categories for `+@category` / `-@category` rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Reads keys
+    Read,
+    /// Modifies keys
+    Write,
+    /// Publishes or subscribes
+    PubSub,
+    /// MULTI/EXEC/WATCH and native transactions
+    Transaction,
+    /// Server-wide commands: INFO, FLUSHDB, KEYS, replication, ...
+    Admin,
+    /// AUTH, HELLO, PING, ...: always allowed
+    Connection,
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" => Ok(Category::Read),
+            "write" => Ok(Category::Write),
+            "pubsub" => Ok(Category::PubSub),
+            "transaction" => Ok(Category::Transaction),
+            "admin" => Ok(Category::Admin),
+            "connection" => Ok(Category::Connection),
+            _ => Err(format!("unknown command category '{}'", s)),
+        }
+    }
+}
+
+/// Command names or categories a `+`/`-` rule applies to
+#[derive(Debug, Clone, PartialEq)]
+enum CommandRule {
+    All,
+    Category(Category),
+    Command(String),
+}
+
+impl CommandRule {
+    fn matches(&self, command: &str, category: Category) -> bool {
+        match self {
+            CommandRule::All => true,
+            CommandRule::Category(c) => *c == category,
+            CommandRule::Command(name) => name == command,
+        }
+    }
+}
+
+/// Keys or channels a user may access
+#[derive(Debug, Clone, PartialEq)]
+enum Prefixes {
+    All,
+    Only(Vec<String>),
+}
+
+impl Prefixes {
+    fn allows(&self, name: &str) -> bool {
+        match self {
+            Prefixes::All => true,
+            Prefixes::Only(prefixes) => prefixes.iter().any(|p| name.starts_with(p.as_str())),
+        }
+    }
+
+    /// Whether every name a glob pattern can match is allowed
+    fn allows_pattern(&self, pattern: &str) -> bool {
+        match self {
+            Prefixes::All => true,
+            Prefixes::Only(_) => self.allows(&literal_prefix(pattern)),
+        }
+    }
+
+    /// Apply a `~pattern` or `&pattern` rule
+    fn add(&mut self, pattern: &str) -> Result<(), String> {
+        if pattern == "*" {
+            *self = Prefixes::All;
+            return Ok(());
+        }
+        let prefix = pattern
+            .strip_suffix('*')
+            .filter(|prefix| !prefix.contains(['*', '?', '[', ']', '\\']))
+            .ok_or_else(|| format!("pattern '{}' must be a literal prefix followed by '*'", pattern))?;
+        if let Prefixes::Only(prefixes) = self {
+            prefixes.push(prefix.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The part of a glob pattern before its first wildcard, unescaped
+fn literal_prefix(pattern: &str) -> String {
+    let mut prefix = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '?' | '[' => break,
+            '\\' => match chars.next() {
+                Some(escaped) => prefix.push(escaped),
+                None => break,
+            },
+            _ => prefix.push(c),
+        }
+    }
+    prefix
+}
+
+/// An ACL user
+#[derive(Debug, Clone)]
+pub struct User {
+    name: String,
+    enabled: bool,
+    nopass: bool,
+    /// SHA-256 of each accepted password
+    passwords: Vec<[u8; 32]>,
+    /// `+`/`-` rules in the order given
+    commands: Vec<(bool, CommandRule)>,
+    keys: Prefixes,
+    channels: Prefixes,
+}
+
+impl User {
+    /// A disabled user with no passwords and no permissions
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            enabled: false,
+            nopass: false,
+            passwords: Vec::new(),
+            commands: Vec::new(),
+            keys: Prefixes::Only(Vec::new()),
+            channels: Prefixes::Only(Vec::new()),
+        }
+    }
+
+    /// Create a user from its rules
+    pub fn with_rules<'a>(name: impl Into<String>, rules: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut user = Self::new(name);
+        for rule in rules {
+            user.apply(rule)?;
+        }
+        Ok(user)
+    }
+
+    /// Apply one rule (see the module docs)
+    pub fn apply(&mut self, rule: &str) -> Result<(), String> {
+        match rule.to_ascii_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.keys = Prefixes::All,
+            "resetkeys" => self.keys = Prefixes::Only(Vec::new()),
+            "allchannels" => self.channels = Prefixes::All,
+            "resetchannels" => self.channels = Prefixes::Only(Vec::new()),
+            "allcommands" => self.commands.push((true, CommandRule::All)),
+            "nocommands" => self.commands.push((false, CommandRule::All)),
+            _ => return self.apply_parameterized(rule),
+        }
+        Ok(())
+    }
+
+    fn apply_parameterized(&mut self, rule: &str) -> Result<(), String> {
+        if let Some(password) = rule.strip_prefix('>') {
+            self.nopass = false;
+            self.passwords.push(Sha256::digest(password.as_bytes()).into());
+        } else if let Some(hash) = rule.strip_prefix('#') {
+            let hash = parse_hash(hash).ok_or_else(|| format!("'{}' is not a SHA-256 hex digest", hash))?;
+            self.nopass = false;
+            self.passwords.push(hash);
+        } else if let Some(pattern) = rule.strip_prefix('~') {
+            self.keys.add(pattern)?;
+        } else if let Some(pattern) = rule.strip_prefix('&') {
+            self.channels.add(pattern)?;
+        } else if let Some(namespace) = rule.strip_prefix("namespace=") {
+            if namespace.is_empty() {
+                return Err("namespace must not be empty".to_string());
+            }
+            if let Prefixes::Only(prefixes) = &mut self.keys {
+                prefixes.push(format!("{}:", namespace));
+            }
+        } else if let Some((allow, target)) = rule
+            .strip_prefix('+')
+            .map(|target| (true, target))
+            .or_else(|| rule.strip_prefix('-').map(|target| (false, target)))
+        {
+            let target = match target.strip_prefix('@') {
+                Some("all") => CommandRule::All,
+                Some(category) => CommandRule::Category(category.parse()?),
+                None if target.is_empty() => return Err(format!("invalid rule '{}'", rule)),
+                None => CommandRule::Command(target.to_ascii_lowercase()),
+            };
+            self.commands.push((allow, target));
+        } else {
+            return Err(format!("unknown rule '{}'", rule));
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the user may log in with this password
+    pub fn check_password(&self, password: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.nopass {
+            return true;
+        }
+        let hash: [u8; 32] = Sha256::digest(password.as_bytes()).into();
+        // Check every stored hash so timing does not reveal which one matched
+        self.passwords
+            .iter()
+            .fold(Choice::from(0), |found, stored| found | stored[..].ct_eq(&hash[..]))
+            .into()
+    }
+
+    /// Whether the user may run a command
+    pub fn check_command(&self, command: &str, category: Category) -> Result<(), Denied> {
+        if category == Category::Connection {
+            return Ok(());
+        }
+        let allowed = self
+            .commands
+            .iter()
+            .rev()
+            .find(|(_, rule)| rule.matches(command, category))
+            .map(|(allow, _)| *allow)
+            .unwrap_or(false);
+        if allowed {
+            Ok(())
+        } else {
+            Err(Denied::Command {
+                user: self.name.clone(),
+                command: command.to_string(),
+            })
+        }
+    }
+
+    /// Whether the user may access every key (so keys need not be checked)
+    pub fn all_keys(&self) -> bool {
+        self.keys == Prefixes::All
+    }
+
+    pub fn check_key(&self, key: &str) -> Result<(), Denied> {
+        self.keys.allows(key).then_some(()).ok_or(Denied::Key)
+    }
+
+    /// Whether every key a glob pattern (SCAN, KEYS) can match is allowed
+    pub fn check_key_pattern(&self, pattern: &str) -> Result<(), Denied> {
+        self.keys.allows_pattern(pattern).then_some(()).ok_or(Denied::Key)
+    }
+
+    pub fn check_channel(&self, channel: &str) -> Result<(), Denied> {
+        self.channels.allows(channel).then_some(()).ok_or(Denied::Channel)
+    }
+
+    /// Whether every channel a PSUBSCRIBE pattern can match is allowed
+    pub fn check_channel_pattern(&self, pattern: &str) -> Result<(), Denied> {
+        self.channels.allows_pattern(pattern).then_some(()).ok_or(Denied::Channel)
+    }
+}
+
+fn parse_hash(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(hash)
+}
+
+/// The users a server accepts
+#[derive(Debug, Clone)]
+pub struct Acl {
+    users: HashMap<String, Arc<User>>,
+}
+
+impl Default for Acl {
+    /// Anyone may do anything, as the `default` user
+    fn default() -> Self {
+        let default = User::with_rules(DEFAULT_USER, ["on", "nopass", "allkeys", "allchannels", "allcommands"])
+            .expect("valid rules");
+        Self::from_users([default])
+    }
+}
+
+impl Acl {
+    fn from_users(users: impl IntoIterator<Item = User>) -> Self {
+        Self {
+            users: users.into_iter().map(|u| (u.name.clone(), Arc::new(u))).collect(),
+        }
+    }
+
+    /// Full access for clients that AUTH with `password` (`requirepass`)
+    pub fn with_password(password: &str) -> Self {
+        let mut default = User::with_rules(DEFAULT_USER, ["on", "allkeys", "allchannels", "allcommands"])
+            .expect("valid rules");
+        default.passwords.push(Sha256::digest(password.as_bytes()).into());
+        Self::from_users([default])
+    }
+
+    /// Parse ACL file contents
+    ///
+    /// Blank lines and lines starting with `#` are ignored. If no `default`
+    /// user is defined, it is disabled.
+    pub fn parse(text: &str) -> Result<Self, AclError> {
+        let mut users: HashMap<String, User> = HashMap::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let syntax = |message: String| AclError::Syntax { line: index + 1, message };
+
+            let mut words = line.split_whitespace();
+            if words.next() != Some("user") {
+                return Err(syntax("expected 'user <name> [rules...]'".to_string()));
+            }
+            let name = words.next().ok_or_else(|| syntax("missing user name".to_string()))?;
+            // Like ACL SETUSER, a repeated user gets more rules
+            let user = users.entry(name.to_string()).or_insert_with(|| User::new(name));
+            for rule in words {
+                user.apply(rule).map_err(syntax)?;
+            }
+        }
+
+        Ok(Self::from_users(users.into_values()))
+    }
+
+    /// Load an ACL file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AclError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// User a new connection starts as: `default`, if it needs no password
+    pub fn default_user(&self) -> Option<Arc<User>> {
+        self.users
+            .get(DEFAULT_USER)
+            .filter(|user| user.enabled && user.nopass)
+            .cloned()
+    }
+
+    /// Check credentials; without a username, `default` is tried
+    pub fn authenticate(&self, username: Option<&str>, password: &str) -> Result<Arc<User>, Denied> {
+        let user = self.users.get(username.unwrap_or(DEFAULT_USER)).ok_or(Denied::WrongPass)?;
+        if user.check_password(password) {
+            Ok(user.clone())
+        } else {
+            Err(Denied::WrongPass)
+        }
+    }
+
+    /// Number of users defined
+    pub fn user_count(&self) -> usize {
+        self.users.len()
+    }
+}
+
+/// Require an authenticated connection
+pub fn require_user(user: &Option<Arc<User>>) -> Result<&User, Denied> {
+    user.as_deref().ok_or(Denied::NoAuth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_acl_allows_everything() {
+        let acl = Acl::default();
+        let user = acl.default_user().unwrap();
+        assert_eq!(user.check_command("flushdb", Category::Admin), Ok(()));
+        assert_eq!(user.check_key("any"), Ok(()));
+        assert!(acl.authenticate(None, "anything").is_ok());
+
+        let acl = Acl::with_password("secret");
+        assert!(acl.default_user().is_none());
+        assert_eq!(acl.authenticate(None, "wrong").unwrap_err(), Denied::WrongPass);
+        assert_eq!(acl.authenticate(Some("default"), "secret").unwrap().name(), "default");
+    }
+
+    #[test]
+    fn test_parse_acl_file() {
+        let acl = Acl::parse(
+            "# comment\n\
+             user admin on #2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b allkeys +@all\n\
+             user worker on >token namespace=tasks &tasks:* +@all -@admin -flushdb\n\
+             user worker +info\n\
+             user retired off >old +@all\n",
+        )
+        .unwrap();
+        assert_eq!(acl.user_count(), 3);
+
+        // No default user: connections must authenticate
+        assert!(acl.default_user().is_none());
+        assert_eq!(acl.authenticate(None, "x").unwrap_err(), Denied::WrongPass);
+
+        // "secret" hashed
+        assert!(acl.authenticate(Some("admin"), "secret").is_ok());
+        assert!(acl.authenticate(Some("retired"), "old").is_err());
+
+        let worker = acl.authenticate(Some("worker"), "token").unwrap();
+        assert!(worker.check_command("set", Category::Write).is_ok());
+        assert!(worker.check_command("flushdb", Category::Admin).is_err());
+        assert!(worker.check_command("info", Category::Admin).is_ok());
+        assert!(worker.check_command("hello", Category::Connection).is_ok());
+        assert!(worker.check_key("tasks:1").is_ok());
+        assert_eq!(worker.check_key("other:1"), Err(Denied::Key));
+        assert!(worker.check_channel("tasks:done").is_ok());
+        assert_eq!(worker.check_channel("__keyspace__:x"), Err(Denied::Channel));
+
+        assert!(matches!(
+            Acl::parse("user a on ~tasks:*:x"),
+            Err(AclError::Syntax { line: 1, .. })
+        ));
+        assert!(Acl::parse("user a +@bogus").is_err());
+        assert!(Acl::parse("group a").is_err());
+    }
+
+    #[test]
+    fn test_key_patterns() {
+        let user = User::with_rules("u", ["on", "nopass", "~tasks:*", "namespace=prod/cache"]).unwrap();
+        assert!(!user.all_keys());
+        assert!(user.check_key_pattern("tasks:*").is_ok());
+        assert!(user.check_key_pattern("tasks:job-?").is_ok());
+        assert!(user.check_key_pattern("prod/cache:*").is_ok());
+        assert!(user.check_key_pattern("*").is_err());
+        assert!(user.check_key_pattern("task*").is_err());
+        // An escaped wildcard is literal
+        assert!(user.check_key_pattern("tasks:\\*-*").is_ok());
+        assert!(user.check_key_pattern("tasks\\*").is_err());
+    }
+
+    #[test]
+    fn test_later_command_rules_win() {
+        let user = User::with_rules("u", ["on", "-@all", "+get", "+@write", "-del"]).unwrap();
+        assert!(user.check_command("get", Category::Read).is_ok());
+        assert!(user.check_command("exists", Category::Read).is_err());
+        assert!(user.check_command("set", Category::Write).is_ok());
+        assert_eq!(
+            user.check_command("del", Category::Write),
+            Err(Denied::Command { user: "u".to_string(), command: "del".to_string() })
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! KV Store persistence inspector
+//!
+//! Offline tool for a kv-server data directory: verifies snapshots and WAL
+//! segments, dumps their contents, and restores the state as of an earlier
+//! time or WAL position into a new data directory.
+
+use clap::{Parser, Subcommand};
+use ouroboros_kv::persistence::inspect::verify_directory;
+use ouroboros_kv::persistence::recovery::RecoveryManager;
+use ouroboros_kv::persistence::snapshot::{find_snapshot_files, SnapshotLoader, SnapshotWriter};
+use ouroboros_kv::persistence::wal::{find_wal_files, WalReader};
+use ouroboros_kv::persistence::{RestoreOptions, SnapshotConfig};
+use ouroboros_kv::KvEngine;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[derive(Parser, Debug)]
+#[command(name = "kv-inspect")]
+#[command(about = "Verify, dump and restore KV store persistence files")]
+struct Args {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Verify every snapshot and WAL segment in a data directory
+    Verify {
+        /// Data directory (or WAL archive directory)
+        dir: PathBuf,
+    },
+
+    /// Print the entries of a WAL segment
+    Wal {
+        /// WAL segment file
+        file: PathBuf,
+
+        /// Skip entries before this WAL position
+        #[arg(long, default_value = "0")]
+        from: u64,
+    },
+
+    /// Print a snapshot's header and, optionally, its keys
+    Snapshot {
+        /// Snapshot file
+        file: PathBuf,
+
+        /// Also list every key with its type, version and expiry
+        #[arg(long, default_value = "false")]
+        keys: bool,
+    },
+
+    /// Restore the state as of a point in time into a new data directory
+    Restore {
+        /// Data directory to restore from (only read)
+        data_dir: PathBuf,
+
+        /// Empty directory to write the restored snapshot to
+        output_dir: PathBuf,
+
+        /// Restore changes logged up to this time (milliseconds since the Unix epoch)
+        #[arg(long, conflicts_with = "until_position")]
+        until_time: Option<u64>,
+
+        /// Restore changes logged before this WAL position
+        #[arg(long)]
+        until_position: Option<u64>,
+
+        /// Also read archived WAL segments from this directory
+        #[arg(long)]
+        archive_dir: Option<PathBuf>,
+
+        /// Number of shards (must match the server's)
+        #[arg(short, long, default_value = "256")]
+        shards: usize,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let result = match args.command {
+        Commands::Verify { dir } => verify(&dir),
+        Commands::Wal { file, from } => dump_wal(&file, from).map(|()| true),
+        Commands::Snapshot { file, keys } => dump_snapshot(&file, keys).map(|()| true),
+        Commands::Restore {
+            data_dir,
+            output_dir,
+            until_time,
+            until_position,
+            archive_dir,
+            shards,
+        } => {
+            let mut options = RestoreOptions::new();
+            if let Some(ms) = until_time {
+                options = options.until_time(UNIX_EPOCH + Duration::from_millis(ms));
+            }
+            if let Some(position) = until_position {
+                options = options.until_position(position);
+            }
+            if let Some(dir) = archive_dir {
+                options = options.with_archive_dir(dir);
+            }
+            restore(&data_dir, &output_dir, &options, shards).map(|()| true)
+        }
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Returns whether everything checked out
+fn verify(dir: &Path) -> Result<bool> {
+    let report = verify_directory(dir)?;
+
+    for snapshot in &report.snapshots {
+        println!(
+            "snapshot {}: {} entries, {} shards, created {}, WAL position {}: {}",
+            snapshot.path.display(),
+            snapshot.total_entries,
+            snapshot.num_shards,
+            format_time(snapshot.created_at),
+            snapshot.wal_position,
+            snapshot.error.as_deref().unwrap_or("ok")
+        );
+    }
+
+    for segment in &report.segments {
+        let span = match (segment.first_timestamp, segment.last_timestamp) {
+            (Some(first), Some(last)) => format!(", {} to {}", format_time(first), format_time(last)),
+            _ => String::new(),
+        };
+        println!(
+            "wal {}: positions {}..{}, {} entries{}: {}",
+            segment.path.display(),
+            segment.base_position,
+            segment.end_position,
+            segment.entries,
+            span,
+            segment.error.as_deref().unwrap_or("ok")
+        );
+    }
+
+    for (path, error) in &report.unreadable {
+        println!("unreadable {}: {}", path.display(), error);
+    }
+
+    for (from, to) in &report.gaps {
+        println!("gap: WAL positions {}..{} are missing", from, to);
+    }
+
+    if report.snapshots.is_empty() && report.segments.is_empty() && report.unreadable.is_empty() {
+        println!("no snapshots or WAL segments found in {}", dir.display());
+    }
+
+    println!("{}", if report.is_ok() { "OK" } else { "FAILED" });
+    Ok(report.is_ok())
+}
+
+fn dump_wal(file: &Path, from: u64) -> Result<()> {
+    let mut reader = WalReader::new(file)?;
+    println!(
+        "# {}: positions {}..{}",
+        file.display(),
+        reader.base_position(),
+        reader.end_position()
+    );
+
+    loop {
+        let position = reader.position();
+        match reader.read_entry()? {
+            Some(entry) if position >= from => {
+                println!(
+                    "{}\t{}\t{:?}\t{}",
+                    position,
+                    format_time(entry.timestamp),
+                    entry.op.op_type(),
+                    entry.op.keys().join(" ")
+                );
+            }
+            Some(_) => {}
+            None => return Ok(()),
+        }
+    }
+}
+
+fn dump_snapshot(file: &Path, keys: bool) -> Result<()> {
+    let header = SnapshotLoader::read_header(file)?;
+    println!("# {}", file.display());
+    println!("created:      {}", format_time(header.created_at));
+    println!("shards:       {}", header.num_shards);
+    println!("entries:      {}", header.total_entries);
+    println!("WAL position: {}", header.wal_position);
+
+    if keys {
+        let (data, _) = SnapshotLoader::load(file)?;
+        let mut entries: Vec<_> = data.shards.into_iter().flat_map(|shard| shard.entries).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, entry) in entries {
+            let expires = entry
+                .expires_at_nanos
+                .map(|nanos| format!("\texpires {}", format_time(nanos)))
+                .unwrap_or_default();
+            println!("{}\t{}\tv{}{}", key, entry.value.type_name(), entry.version, expires);
+        }
+    }
+
+    Ok(())
+}
+
+fn restore(data_dir: &Path, output_dir: &Path, options: &RestoreOptions, shards: usize) -> Result<()> {
+    // Replaying the source's WAL on top of the restored snapshot would undo the restore
+    if !find_snapshot_files(output_dir)?.is_empty() || !find_wal_files(output_dir)?.is_empty() {
+        return Err(format!("{} already holds KV data", output_dir.display()).into());
+    }
+
+    let (engine, stats) = RecoveryManager::restore(KvEngine::with_shards(shards), data_dir, options)?;
+    let path = SnapshotWriter::new(SnapshotConfig::default()).create_snapshot(&engine, output_dir, 0)?;
+
+    println!(
+        "restored {} keys ({} from snapshot, {} WAL entries replayed, {} corrupted) up to WAL position {}",
+        engine.len(),
+        stats.snapshot_entries,
+        stats.wal_entries_replayed,
+        stats.corrupted_entries,
+        stats.wal_position
+    );
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Nanoseconds since the Unix epoch as seconds with millisecond precision
+fn format_time(nanos: i64) -> String {
+    format!("{}.{:03}", nanos / 1_000_000_000, (nanos % 1_000_000_000) / 1_000_000)
+}
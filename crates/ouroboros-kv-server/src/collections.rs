@@ -0,0 +1,200 @@
+//! Hash, list, set and sorted set commands on the native protocol
+//!
+//! A `Command::Collection` request names a [`CollectionCommand`], a key and a
+//! list of argument values, and is answered with a single value: counts and
+//! flags as `Int`, missing entries as `Null`, ranges as `List`, and sorted set
+//! entries as `[member, score]` pairs.
+
+use crate::protocol::CollectionCommand;
+use ouroboros_kv::{KvEngine, KvKey, KvValue};
+use std::ops::Bound;
+
+type Args = std::vec::IntoIter<KvValue>;
+
+/// Run a structure command, returning its reply or an error message
+pub fn execute(engine: &KvEngine, cmd: CollectionCommand, key: &KvKey, args: Vec<KvValue>) -> Result<KvValue, String> {
+    use CollectionCommand::*;
+
+    let count = |n: usize| KvValue::Int(n as i64);
+    let flag = |b: bool| KvValue::Int(b as i64);
+    let optional = |value: Option<KvValue>| value.unwrap_or(KvValue::Null);
+    let mut args = args.into_iter();
+    let next = |args: &mut Args| args.next().ok_or_else(|| format!("Missing argument for {:?}", cmd));
+
+    let reply = match cmd {
+        HSet => {
+            let mut fields = Vec::new();
+            for (field, value) in pairs(&mut args, cmd)? {
+                fields.push((string(field)?, value));
+            }
+            engine.hset(key, fields).map(count)
+        }
+        HSetNx => {
+            let field = string(next(&mut args)?)?;
+            engine.hsetnx(key, &field, next(&mut args)?).map(flag)
+        }
+        HDel => engine.hdel(key, &strings(args)?).map(count),
+        HIncrBy => {
+            let field = string(next(&mut args)?)?;
+            engine.hincrby(key, &field, int(next(&mut args)?)?).map(KvValue::Int)
+        }
+        HGet => engine.hget(key, &string(next(&mut args)?)?).map(optional),
+        HMGet => {
+            let fields = strings(args)?;
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+            engine
+                .hmget(key, &fields)
+                .map(|values| KvValue::List(values.into_iter().map(optional).collect()))
+        }
+        HGetAll => engine.hgetall(key).map(KvValue::Map),
+        HExists => engine.hexists(key, &string(next(&mut args)?)?).map(flag),
+        HLen => engine.hlen(key).map(count),
+
+        LPush => engine.lpush(key, args.collect()).map(count),
+        RPush => engine.rpush(key, args.collect()).map(count),
+        LPop | RPop => {
+            let n = usize::try_from(int(next(&mut args)?)?).map_err(|_| "Count must not be negative".to_string())?;
+            let popped = if cmd == LPop { engine.lpop(key, n) } else { engine.rpop(key, n) };
+            popped.map(KvValue::List)
+        }
+        LTrim => {
+            let start = int(next(&mut args)?)?;
+            engine.ltrim(key, start, int(next(&mut args)?)?).map(|()| KvValue::Null)
+        }
+        LRange => {
+            let start = int(next(&mut args)?)?;
+            engine.lrange(key, start, int(next(&mut args)?)?).map(KvValue::List)
+        }
+        LIndex => engine.lindex(key, int(next(&mut args)?)?).map(optional),
+        LLen => engine.llen(key).map(count),
+
+        SAdd => engine.sadd(key, strings(args)?).map(count),
+        SRem => engine.srem(key, &strings(args)?).map(count),
+        SIsMember => engine.sismember(key, &string(next(&mut args)?)?).map(flag),
+        SMembers => engine
+            .smembers(key)
+            .map(|members| KvValue::List(members.into_iter().map(KvValue::String).collect())),
+        SCard => engine.scard(key).map(count),
+
+        ZAdd => {
+            let mut members = Vec::new();
+            for (score, member) in pairs(&mut args, cmd)? {
+                members.push((float(score)?, string(member)?));
+            }
+            engine.zadd(key, members).map(count)
+        }
+        ZIncrBy => {
+            let member = string(next(&mut args)?)?;
+            engine.zincrby(key, &member, float(next(&mut args)?)?).map(KvValue::Float)
+        }
+        ZRem => engine.zrem(key, &strings(args)?).map(count),
+        ZScore => engine
+            .zscore(key, &string(next(&mut args)?)?)
+            .map(|score| score.map(KvValue::Float).unwrap_or(KvValue::Null)),
+        ZRank | ZRevRank => engine
+            .zrank(key, &string(next(&mut args)?)?, cmd == ZRevRank)
+            .map(|rank| rank.map(|r| KvValue::Int(r as i64)).unwrap_or(KvValue::Null)),
+        ZRange | ZRevRange => {
+            let start = int(next(&mut args)?)?;
+            engine.zrange(key, start, int(next(&mut args)?)?, cmd == ZRevRange).map(scored)
+        }
+        ZRangeByScore => {
+            // min, max, flags (bit 0: min exclusive, bit 1: max exclusive)
+            let (min, max) = (float(next(&mut args)?)?, float(next(&mut args)?)?);
+            let flags = int(next(&mut args)?)?;
+            let bound = |score: f64, exclusive: bool| match (score, exclusive) {
+                (s, _) if s.is_infinite() => Bound::Unbounded,
+                (s, true) => Bound::Excluded(s),
+                (s, false) => Bound::Included(s),
+            };
+            engine
+                .zrangebyscore(key, bound(min, flags & 1 != 0), bound(max, flags & 2 != 0))
+                .map(scored)
+        }
+        ZCard => engine.zcard(key).map(count),
+    };
+
+    reply.map_err(|e| e.to_string())
+}
+
+/// Sorted set entries as `[member, score]` pairs
+fn scored(entries: Vec<(String, f64)>) -> KvValue {
+    KvValue::List(
+        entries
+            .into_iter()
+            .map(|(member, score)| KvValue::List(vec![KvValue::String(member), KvValue::Float(score)]))
+            .collect(),
+    )
+}
+
+/// Consume the remaining arguments as pairs (at least one)
+fn pairs(args: &mut Args, cmd: CollectionCommand) -> Result<Vec<(KvValue, KvValue)>, String> {
+    let mut pairs = Vec::new();
+    while let Some(first) = args.next() {
+        let second = args.next().ok_or_else(|| format!("{:?} expects pairs of arguments", cmd))?;
+        pairs.push((first, second));
+    }
+    if pairs.is_empty() {
+        return Err(format!("Missing argument for {:?}", cmd));
+    }
+    Ok(pairs)
+}
+
+fn string(value: KvValue) -> Result<String, String> {
+    match value {
+        KvValue::String(s) => Ok(s),
+        other => Err(format!("Expected a string argument, got {}", other.type_name())),
+    }
+}
+
+fn strings(values: impl Iterator<Item = KvValue>) -> Result<Vec<String>, String> {
+    values.map(string).collect()
+}
+
+fn int(value: KvValue) -> Result<i64, String> {
+    match value {
+        KvValue::Int(n) => Ok(n),
+        other => Err(format!("Expected an integer argument, got {}", other.type_name())),
+    }
+}
+
+fn float(value: KvValue) -> Result<f64, String> {
+    match value {
+        KvValue::Float(f) if !f.is_nan() => Ok(f),
+        KvValue::Int(n) => Ok(n as f64),
+        other => Err(format!("Expected a numeric argument, got {}", other.type_name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(value: &str) -> KvValue {
+        KvValue::String(value.to_string())
+    }
+
+    #[test]
+    fn test_execute_collection_commands() {
+        let engine = KvEngine::new();
+        let key = KvKey::new("board").unwrap();
+
+        let args = vec![KvValue::Int(10), s("alice"), KvValue::Float(20.5), s("bob")];
+        assert_eq!(execute(&engine, CollectionCommand::ZAdd, &key, args), Ok(KvValue::Int(2)));
+        assert_eq!(
+            execute(&engine, CollectionCommand::ZRevRange, &key, vec![KvValue::Int(0), KvValue::Int(0)]),
+            Ok(KvValue::List(vec![KvValue::List(vec![s("bob"), KvValue::Float(20.5)])]))
+        );
+        let args = vec![KvValue::Float(10.0), KvValue::Float(f64::INFINITY), KvValue::Int(1)];
+        assert_eq!(
+            execute(&engine, CollectionCommand::ZRangeByScore, &key, args),
+            Ok(KvValue::List(vec![KvValue::List(vec![s("bob"), KvValue::Float(20.5)])]))
+        );
+
+        // Bad arguments and wrong types are reported, not panicked on
+        assert!(execute(&engine, CollectionCommand::ZAdd, &key, vec![KvValue::Int(1)]).is_err());
+        assert!(execute(&engine, CollectionCommand::HGet, &key, vec![s("field")]).is_err());
+        assert!(execute(&engine, CollectionCommand::LPop, &key, vec![KvValue::Int(-1)]).is_err());
+        assert_eq!(execute(&engine, CollectionCommand::LLen, &KvKey::new("none").unwrap(), vec![]), Ok(KvValue::Int(0)));
+    }
+}
@@ -3,17 +3,24 @@
 //! High-performance TCP server for the ouroboros KV store.
 
 use clap::Parser;
-use ouroboros_kv::persistence::{PersistenceConfig, PersistenceHandle};
-use ouroboros_kv::KvEngine;
+use ouroboros_kv::persistence::{PersistenceConfig, PersistenceHandle, WalConfig};
+use ouroboros_kv::{EvictionConfig, EvictionPolicy, KvEngine, ReplicationLog, ReplicationPosition, TieredConfig};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
 use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
+mod auth;
+mod collections;
 mod protocol;
+mod pubsub;
+mod replication;
+mod resp;
 mod server;
+mod tls;
 
 #[derive(Parser, Debug)]
 #[command(name = "kv-server")]
@@ -50,6 +57,90 @@ struct Args {
     /// Snapshot creation threshold (operations count)
     #[arg(long, default_value = "100000")]
     snapshot_ops_threshold: usize,
+
+    /// WAL segment size in bytes; segments older than every kept snapshot are removed
+    #[arg(long, default_value_t = WalConfig::default().max_file_size)]
+    wal_segment_size: u64,
+
+    /// Archive removed WAL segments here (for point-in-time restore) instead of deleting them
+    #[arg(long)]
+    wal_archive_dir: Option<PathBuf>,
+
+    /// Memory budget in bytes for resident entries; colder entries spill to disk
+    #[arg(long)]
+    tier_memory_limit: Option<usize>,
+
+    /// Directory for cold-tier segment files (default: <data-dir>/tier)
+    #[arg(long)]
+    tier_dir: Option<PathBuf>,
+
+    /// Cold-tier compaction interval in seconds
+    #[arg(long, default_value = "60")]
+    tier_compaction_interval_secs: u64,
+
+    /// Maximum memory in bytes for resident entries; keys are evicted beyond this
+    #[arg(long)]
+    max_memory: Option<usize>,
+
+    /// Maximum number of keys; keys are evicted beyond this
+    #[arg(long)]
+    max_keys: Option<usize>,
+
+    /// Eviction policy (allkeys-lru, volatile-lru, allkeys-lfu, volatile-lfu, volatile-ttl)
+    #[arg(long, default_value = "allkeys-lru")]
+    eviction_policy: EvictionPolicy,
+
+    /// Publish keyspace events (set, del, expired, evicted) to __keyspace__/__keyevent__ channels
+    #[arg(long, default_value = "false")]
+    keyspace_events: bool,
+
+    /// Interval in milliseconds for removing expired keys (drives `expired` events)
+    #[arg(long, default_value = "1000")]
+    expire_sweep_interval_ms: u64,
+
+    /// Accept replicas, keeping a backlog of recent changes for them to tail
+    #[arg(long, default_value = "false")]
+    enable_replication: bool,
+
+    /// Size in bytes of the replication backlog; replicas further behind need a full sync
+    #[arg(long, default_value_t = ouroboros_kv::replication::DEFAULT_BACKLOG_BYTES)]
+    replication_backlog: usize,
+
+    /// Run as a read-only replica of the primary at this address (host:port)
+    #[arg(long, conflicts_with = "enable_replication")]
+    replica_of: Option<String>,
+
+    /// Also serve the Redis protocol (RESP2/RESP3) on this address
+    #[arg(long)]
+    resp_bind: Option<SocketAddr>,
+
+    /// Require clients to AUTH with this password (as the `default` user)
+    #[arg(long, conflicts_with = "acl_file")]
+    requirepass: Option<String>,
+
+    /// ACL file defining users, passwords and permissions
+    #[arg(long)]
+    acl_file: Option<PathBuf>,
+
+    /// TLS certificate chain (PEM); both listeners then only accept TLS
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// TLS private key (PEM)
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// User to authenticate to the primary as (default: `default`)
+    #[arg(long, requires = "primary_password")]
+    primary_user: Option<String>,
+
+    /// Password to authenticate to the primary with
+    #[arg(long, requires = "replica_of")]
+    primary_password: Option<String>,
+
+    /// CA certificates (PEM) to verify the primary with; connects to it over TLS
+    #[arg(long, requires = "replica_of")]
+    primary_tls_ca: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -65,18 +156,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting KV server on {}", args.bind);
     info!("Shards: {}", args.shards);
 
+    let engine = KvEngine::with_shards(args.shards);
+
+    // Enable tiered storage before loading data so recovery respects the budget
+    if let Some(limit) = args.tier_memory_limit {
+        let tier_dir = args.tier_dir.clone().unwrap_or_else(|| args.data_dir.join("tier"));
+        engine.enable_tiering(TieredConfig::new(&tier_dir, limit))?;
+        info!(
+            "Tiered storage enabled: {} bytes in memory, segments in {}",
+            limit,
+            tier_dir.display()
+        );
+    }
+
+    // Enable eviction before loading data so recovery respects the limits
+    if args.max_memory.is_some() || args.max_keys.is_some() {
+        let mut config = EvictionConfig::new(args.eviction_policy);
+        config.max_memory_bytes = args.max_memory;
+        config.max_keys = args.max_keys;
+        engine.enable_eviction(config)?;
+        info!(
+            "Eviction enabled: policy={}, max_memory={:?}, max_keys={:?}",
+            args.eviction_policy, args.max_memory, args.max_keys
+        );
+    }
+
     // Create or recover engine (returns Arc for sharing with server and persistence)
     let (engine_arc, persistence_handle) = if args.disable_persistence {
         info!("Persistence disabled - running in-memory only");
-        (Arc::new(KvEngine::with_shards(args.shards)), None)
+        (Arc::new(engine), None)
     } else {
         info!("Persistence enabled - data directory: {}", args.data_dir.display());
 
         // Attempt recovery
         let (recovered_engine, stats) =
-            ouroboros_kv::persistence::recovery::RecoveryManager::recover(
+            ouroboros_kv::persistence::recovery::RecoveryManager::recover_into(
+                engine,
                 &args.data_dir,
-                args.shards,
             )?;
 
         if stats.snapshot_loaded {
@@ -107,10 +223,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
 
         // Setup persistence
-        let config = PersistenceConfig::new(&args.data_dir)
+        let mut config = PersistenceConfig::new(&args.data_dir)
             .with_fsync_interval_ms(args.fsync_interval_ms)
             .with_snapshot_interval_secs(args.snapshot_interval_secs)
-            .with_snapshot_ops_threshold(args.snapshot_ops_threshold);
+            .with_snapshot_ops_threshold(args.snapshot_ops_threshold)
+            .with_wal_segment_size(args.wal_segment_size);
+        if let Some(dir) = &args.wal_archive_dir {
+            config = config.with_wal_archive_dir(dir);
+        }
 
         // Wrap recovered engine in Arc for sharing between persistence and server
         let engine_arc = Arc::new(recovered_engine);
@@ -131,8 +251,98 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         (engine_arc, Some(persistence))
     };
 
+    // Periodically reclaim space from sparse cold-tier segments
+    if args.tier_memory_limit.is_some() {
+        let engine = engine_arc.clone();
+        let interval = Duration::from_secs(args.tier_compaction_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let engine = engine.clone();
+                match tokio::task::spawn_blocking(move || engine.compact_cold_storage()).await {
+                    Ok(0) => {}
+                    Ok(moved) => info!("Compacted cold tier: {} entries relocated", moved),
+                    Err(e) => warn!("Cold tier compaction failed: {}", e),
+                }
+            }
+        });
+    }
+
+    // Record changes for replicas before serving any writes
+    if args.enable_replication {
+        engine_arc.enable_replication(Arc::new(ReplicationLog::new(args.replication_backlog)))?;
+        info!("Replication enabled: {} byte backlog", args.replication_backlog);
+    }
+
     // Create server with engine (shares the Arc)
-    let server = server::KvServer::with_engine(engine_arc);
+    let mut server = server::KvServer::with_engine(engine_arc.clone());
+
+    if let Some(path) = &args.acl_file {
+        let acl = auth::Acl::load(path)?;
+        info!("Loaded {} ACL users from {}", acl.user_count(), path.display());
+        server = server.with_acl(acl);
+    } else if let Some(password) = &args.requirepass {
+        server = server.with_acl(auth::Acl::with_password(password));
+        info!("Authentication required");
+    }
+
+    if let (Some(cert), Some(key)) = (&args.tls_cert, &args.tls_key) {
+        server = server.with_tls(tls::acceptor(cert, key)?);
+        info!("TLS enabled with certificate {}", cert.display());
+    }
+
+    if let Some(primary) = &args.replica_of {
+        let mut state = replication::ReplicaState::new(primary.clone());
+        if let Some(password) = &args.primary_password {
+            state = state.with_auth(args.primary_user.clone(), password);
+        }
+        if let Some(ca) = &args.primary_tls_ca {
+            state = state.with_tls(tls::connector(ca)?);
+        }
+        if let Some(persistence) = &persistence_handle {
+            let position = ReplicationPosition::load(&args.data_dir)?;
+            if let Some(position) = &position {
+                info!("Resuming replication at offset {} (replid {})", position.offset, position.replid);
+            }
+            state = state.with_persistence(persistence.clone(), position);
+        }
+        let state = Arc::new(state);
+        server = server.with_replica(state.clone());
+        tokio::spawn(replication::run_replica(engine_arc.clone(), state));
+        info!("Running as read-only replica of {}", primary);
+    }
+
+    if args.keyspace_events {
+        server.enable_keyspace_events()?;
+        info!("Keyspace events enabled");
+
+        // Expired keys are otherwise only noticed when touched, so sweep them
+        let engine = engine_arc.clone();
+        let interval = Duration::from_millis(args.expire_sweep_interval_ms.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let engine = engine.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || engine.cleanup_expired()).await {
+                    warn!("Expiry sweep failed: {}", e);
+                }
+            }
+        });
+    }
+
+    let server = Arc::new(server);
+
+    // Redis-compatible listener for redis-cli and Redis client libraries
+    let resp_task = args.resp_bind.map(|addr| {
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server.run_resp(addr).await {
+                eprintln!("RESP listener error: {}", e);
+            }
+        })
+    });
 
     // Setup graceful shutdown
     let server_task = tokio::spawn(async move {
@@ -171,6 +381,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     server_task.abort();
+    if let Some(task) = resp_task {
+        task.abort();
+    }
     info!("Server shutdown complete");
 
     Ok(())
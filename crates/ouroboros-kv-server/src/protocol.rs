@@ -2,8 +2,9 @@
 //!
 //! Binary protocol for KV operations.
 
-use ouroboros_kv::KvValue;
-use std::collections::HashMap;
+use crate::auth::{Category, Denied};
+use ouroboros_kv::{Guard, KvValue, SortedSet, Transaction, TxnOp, TxnResult};
+use std::collections::{HashMap, HashSet};
 use std::io;
 use thiserror::Error;
 
@@ -27,6 +28,9 @@ pub enum ProtocolError {
 
     #[error("Unexpected end of data")]
     UnexpectedEof,
+
+    #[error("{0}")]
+    Denied(#[from] Denied),
 }
 
 /// Command codes
@@ -51,6 +55,94 @@ pub enum Command {
     MGet = 0x0E,
     MSet = 0x0F,
     MDel = 0x10,
+    // Key iteration
+    Scan = 0x11,
+    // Pub/Sub
+    Publish = 0x12,
+    Subscribe = 0x13,
+    Unsubscribe = 0x14,
+    PSubscribe = 0x15,
+    PUnsubscribe = 0x16,
+    // Replication
+    Sync = 0x17,
+    // Transactions
+    Watch = 0x18,
+    Exec = 0x19,
+    // Hashes, lists, sets and sorted sets
+    Collection = 0x1A,
+    // Authentication
+    Auth = 0x1B,
+}
+
+impl Command {
+    /// Whether the command modifies the keyspace (rejected on read-only replicas)
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Command::Set
+                | Command::Del
+                | Command::Incr
+                | Command::Decr
+                | Command::Cas
+                | Command::Setnx
+                | Command::Lock
+                | Command::Unlock
+                | Command::ExtendLock
+                | Command::MSet
+                | Command::MDel
+                | Command::Exec
+        )
+    }
+
+    /// Name in ACL rules: the Redis name where there is one
+    ///
+    /// `Collection` requests are named by their [`CollectionCommand`].
+    pub fn acl_name(&self) -> &'static str {
+        match self {
+            Command::Get => "get",
+            Command::Set => "set",
+            Command::Del | Command::MDel => "del",
+            Command::Exists => "exists",
+            Command::Incr => "incrby",
+            Command::Decr => "decrby",
+            Command::Cas => "cas",
+            Command::Ping => "ping",
+            Command::Info => "info",
+            Command::Setnx => "setnx",
+            Command::Lock => "lock",
+            Command::Unlock => "unlock",
+            Command::ExtendLock => "extendlock",
+            Command::MGet => "mget",
+            Command::MSet => "mset",
+            Command::Scan => "scan",
+            Command::Publish => "publish",
+            Command::Subscribe => "subscribe",
+            Command::Unsubscribe => "unsubscribe",
+            Command::PSubscribe => "psubscribe",
+            Command::PUnsubscribe => "punsubscribe",
+            Command::Sync => "sync",
+            Command::Watch => "watch",
+            Command::Exec => "exec",
+            Command::Collection => "collection",
+            Command::Auth => "auth",
+        }
+    }
+
+    /// ACL category
+    pub fn category(&self) -> Category {
+        match self {
+            Command::Ping | Command::Auth => Category::Connection,
+            Command::Info | Command::Sync => Category::Admin,
+            Command::Publish
+            | Command::Subscribe
+            | Command::Unsubscribe
+            | Command::PSubscribe
+            | Command::PUnsubscribe => Category::PubSub,
+            Command::Watch | Command::Exec => Category::Transaction,
+            _ if self.is_write() => Category::Write,
+            _ => Category::Read,
+        }
+    }
 }
 
 impl TryFrom<u8> for Command {
@@ -74,11 +166,136 @@ impl TryFrom<u8> for Command {
             0x0E => Ok(Command::MGet),
             0x0F => Ok(Command::MSet),
             0x10 => Ok(Command::MDel),
+            0x11 => Ok(Command::Scan),
+            0x12 => Ok(Command::Publish),
+            0x13 => Ok(Command::Subscribe),
+            0x14 => Ok(Command::Unsubscribe),
+            0x15 => Ok(Command::PSubscribe),
+            0x16 => Ok(Command::PUnsubscribe),
+            0x17 => Ok(Command::Sync),
+            0x18 => Ok(Command::Watch),
+            0x19 => Ok(Command::Exec),
+            0x1A => Ok(Command::Collection),
+            0x1B => Ok(Command::Auth),
             _ => Err(ProtocolError::InvalidCommand(byte)),
         }
     }
 }
 
+/// Structure commands carried by `Command::Collection`
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollectionCommand {
+    // Hashes
+    HSet = 0x01,
+    HSetNx = 0x02,
+    HDel = 0x03,
+    HIncrBy = 0x04,
+    HGet = 0x05,
+    HMGet = 0x06,
+    HGetAll = 0x07,
+    HExists = 0x08,
+    HLen = 0x09,
+    // Lists
+    LPush = 0x10,
+    RPush = 0x11,
+    LPop = 0x12,
+    RPop = 0x13,
+    LTrim = 0x14,
+    LRange = 0x15,
+    LIndex = 0x16,
+    LLen = 0x17,
+    // Sets
+    SAdd = 0x20,
+    SRem = 0x21,
+    SIsMember = 0x22,
+    SMembers = 0x23,
+    SCard = 0x24,
+    // Sorted sets
+    ZAdd = 0x30,
+    ZIncrBy = 0x31,
+    ZRem = 0x32,
+    ZScore = 0x33,
+    ZRank = 0x34,
+    ZRevRank = 0x35,
+    ZRange = 0x36,
+    ZRevRange = 0x37,
+    ZRangeByScore = 0x38,
+    ZCard = 0x39,
+}
+
+impl CollectionCommand {
+    /// Whether the command modifies the keyspace (rejected on read-only replicas)
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            CollectionCommand::HSet
+                | CollectionCommand::HSetNx
+                | CollectionCommand::HDel
+                | CollectionCommand::HIncrBy
+                | CollectionCommand::LPush
+                | CollectionCommand::RPush
+                | CollectionCommand::LPop
+                | CollectionCommand::RPop
+                | CollectionCommand::LTrim
+                | CollectionCommand::SAdd
+                | CollectionCommand::SRem
+                | CollectionCommand::ZAdd
+                | CollectionCommand::ZIncrBy
+                | CollectionCommand::ZRem
+        )
+    }
+
+    /// Name in ACL rules (the variant names are the Redis names)
+    pub fn acl_name(&self) -> String {
+        format!("{:?}", self).to_ascii_lowercase()
+    }
+}
+
+impl TryFrom<u8> for CollectionCommand {
+    type Error = ProtocolError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        use CollectionCommand::*;
+        let cmd = match byte {
+            0x01 => HSet,
+            0x02 => HSetNx,
+            0x03 => HDel,
+            0x04 => HIncrBy,
+            0x05 => HGet,
+            0x06 => HMGet,
+            0x07 => HGetAll,
+            0x08 => HExists,
+            0x09 => HLen,
+            0x10 => LPush,
+            0x11 => RPush,
+            0x12 => LPop,
+            0x13 => RPop,
+            0x14 => LTrim,
+            0x15 => LRange,
+            0x16 => LIndex,
+            0x17 => LLen,
+            0x20 => SAdd,
+            0x21 => SRem,
+            0x22 => SIsMember,
+            0x23 => SMembers,
+            0x24 => SCard,
+            0x30 => ZAdd,
+            0x31 => ZIncrBy,
+            0x32 => ZRem,
+            0x33 => ZScore,
+            0x34 => ZRank,
+            0x35 => ZRevRank,
+            0x36 => ZRange,
+            0x37 => ZRevRange,
+            0x38 => ZRangeByScore,
+            0x39 => ZCard,
+            _ => return Err(ProtocolError::InvalidCommand(byte)),
+        };
+        Ok(cmd)
+    }
+}
+
 /// Response status codes
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -86,6 +303,8 @@ pub enum Status {
     Ok = 0x00,
     Null = 0x01,
     Error = 0x02,
+    /// Pub/Sub message pushed to a subscribed connection (not a response)
+    Message = 0x03,
 }
 
 /// Value type codes
@@ -102,6 +321,8 @@ pub enum ValueType {
     Map = 0x07,
     #[allow(dead_code)]
     Bool = 0x08,
+    Set = 0x09,
+    SortedSet = 0x0A,
 }
 
 const MAX_PAYLOAD_SIZE: u32 = 64 * 1024 * 1024; // 64MB
@@ -128,6 +349,21 @@ pub fn read_request(data: &[u8]) -> Result<(Command, Vec<u8>), ProtocolError> {
     Ok((cmd, payload))
 }
 
+/// Length of the first complete request in `data`, if it has fully arrived
+pub fn request_len(data: &[u8]) -> Result<Option<usize>, ProtocolError> {
+    if data.len() < 5 {
+        return Ok(None);
+    }
+
+    let len = u32::from_be_bytes([data[1], data[2], data[3], data[4]]);
+    if len > MAX_PAYLOAD_SIZE {
+        return Err(ProtocolError::PayloadTooLarge(len));
+    }
+
+    let total = 5 + len as usize;
+    Ok(if data.len() >= total { Some(total) } else { None })
+}
+
 /// Write a response to bytes
 pub fn write_response(status: Status, payload: &[u8]) -> Vec<u8> {
     let mut buf = Vec::with_capacity(5 + payload.len());
@@ -189,6 +425,23 @@ fn encode_value_into(buf: &mut Vec<u8>, value: &KvValue) {
                 encode_value_into(buf, v);
             }
         }
+        KvValue::Set(members) => {
+            buf.push(ValueType::Set as u8);
+            buf.extend_from_slice(&(members.len() as u32).to_be_bytes());
+            for member in members {
+                buf.extend_from_slice(&(member.len() as u32).to_be_bytes());
+                buf.extend_from_slice(member.as_bytes());
+            }
+        }
+        KvValue::SortedSet(zset) => {
+            buf.push(ValueType::SortedSet as u8);
+            buf.extend_from_slice(&(zset.len() as u32).to_be_bytes());
+            for (member, score) in zset.iter() {
+                buf.extend_from_slice(&(member.len() as u32).to_be_bytes());
+                buf.extend_from_slice(member.as_bytes());
+                buf.extend_from_slice(&score.to_be_bytes());
+            }
+        }
     }
 }
 
@@ -305,6 +558,48 @@ pub fn decode_value(data: &[u8]) -> Result<(KvValue, usize), ProtocolError> {
             }
             Ok((KvValue::Map(map), pos))
         }
+        0x09 | 0x0A => {
+            // Set / SortedSet: count + [member (+ score)]
+            if data.len() < pos + 4 {
+                return Err(ProtocolError::UnexpectedEof);
+            }
+            let count = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            let mut members = HashSet::new();
+            let mut zset = SortedSet::new();
+            for _ in 0..count {
+                if data.len() < pos + 4 {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
+                let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+                pos += 4;
+                if data.len() < pos + len {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
+                let member = std::str::from_utf8(&data[pos..pos + len])
+                    .map_err(|_| ProtocolError::InvalidUtf8)?
+                    .to_string();
+                pos += len;
+                if type_byte == 0x09 {
+                    members.insert(member);
+                    continue;
+                }
+                if data.len() < pos + 8 {
+                    return Err(ProtocolError::UnexpectedEof);
+                }
+                let score = f64::from_be_bytes(data[pos..pos + 8].try_into().unwrap());
+                pos += 8;
+                if score.is_nan() {
+                    return Err(ProtocolError::InvalidValueType(type_byte));
+                }
+                zset.insert(member, score);
+            }
+            if type_byte == 0x09 {
+                Ok((KvValue::Set(members), pos))
+            } else {
+                Ok((KvValue::SortedSet(zset), pos))
+            }
+        }
         _ => Err(ProtocolError::InvalidValueType(type_byte)),
     }
 }
@@ -432,8 +727,11 @@ pub fn parse_mget_payload(payload: &[u8]) -> Result<Vec<String>, ProtocolError>
     Ok(keys)
 }
 
+/// Key-value pairs and optional TTL in milliseconds from an MSET payload
+type MsetPayload = (Vec<(String, KvValue)>, Option<u64>);
+
 /// Parse MSET payload: count(2) + ttl(8) + [key_len(2) + key + value]...
-pub fn parse_mset_payload(payload: &[u8]) -> Result<(Vec<(String, KvValue)>, Option<u64>), ProtocolError> {
+pub fn parse_mset_payload(payload: &[u8]) -> Result<MsetPayload, ProtocolError> {
     if payload.len() < 10 { // count(2) + ttl(8)
         return Err(ProtocolError::UnexpectedEof);
     }
@@ -483,6 +781,298 @@ pub fn encode_mget_response(values: &[Option<KvValue>]) -> Vec<u8> {
     buf
 }
 
+/// Parse SCAN payload: cursor(8) + count(4) + pattern_len(2) + pattern
+///
+/// An empty pattern matches every key.
+pub fn parse_scan_payload(payload: &[u8]) -> Result<(u64, usize, Option<String>), ProtocolError> {
+    if payload.len() < 14 { // cursor(8) + count(4) + pattern_len(2)
+        return Err(ProtocolError::UnexpectedEof);
+    }
+
+    let cursor = u64::from_be_bytes(payload[0..8].try_into().unwrap());
+    let count = u32::from_be_bytes(payload[8..12].try_into().unwrap()) as usize;
+    let pattern_len = u16::from_be_bytes(payload[12..14].try_into().unwrap()) as usize;
+    let pos = 14;
+
+    if payload.len() < pos + pattern_len {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let pattern = if pattern_len == 0 {
+        None
+    } else {
+        Some(
+            std::str::from_utf8(&payload[pos..pos + pattern_len])
+                .map_err(|_| ProtocolError::InvalidUtf8)?
+                .to_string(),
+        )
+    };
+
+    Ok((cursor, count, pattern))
+}
+
+/// Encode SCAN response: cursor(8) + count(4) + [key_len(2) + key]...
+pub fn encode_scan_response(cursor: u64, keys: &[String]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(12 + keys.iter().map(|k| 2 + k.len()).sum::<usize>());
+    buf.extend_from_slice(&cursor.to_be_bytes());
+    buf.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+
+    for key in keys {
+        buf.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        buf.extend_from_slice(key.as_bytes());
+    }
+
+    buf
+}
+
+/// Parse PUBLISH payload: channel_len(2) + channel + message
+pub fn parse_publish_payload(payload: &[u8]) -> Result<(String, &[u8]), ProtocolError> {
+    if payload.len() < 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let channel_len = u16::from_be_bytes(payload[0..2].try_into().unwrap()) as usize;
+    let pos = 2;
+
+    if payload.len() < pos + channel_len {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let channel = std::str::from_utf8(&payload[pos..pos + channel_len])
+        .map_err(|_| ProtocolError::InvalidUtf8)?
+        .to_string();
+
+    Ok((channel, &payload[pos + channel_len..]))
+}
+
+/// Encode a pushed message: pattern_len(2) + pattern + channel_len(2) + channel + message
+///
+/// The pattern is empty for direct channel subscriptions.
+pub fn encode_message(pattern: Option<&str>, channel: &str, message: &[u8]) -> Vec<u8> {
+    let pattern = pattern.unwrap_or("");
+    let mut buf = Vec::with_capacity(4 + pattern.len() + channel.len() + message.len());
+    buf.extend_from_slice(&(pattern.len() as u16).to_be_bytes());
+    buf.extend_from_slice(pattern.as_bytes());
+    buf.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+    buf.extend_from_slice(channel.as_bytes());
+    buf.extend_from_slice(message);
+    buf
+}
+
+/// Parse SYNC payload: replid_len(2) + replid + offset(8)
+///
+/// An empty replication id asks for a full sync.
+pub fn parse_sync_payload(payload: &[u8]) -> Result<(String, u64), ProtocolError> {
+    if payload.len() < 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let id_len = u16::from_be_bytes(payload[0..2].try_into().unwrap()) as usize;
+    let pos = 2;
+
+    if payload.len() < pos + id_len + 8 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let replid = std::str::from_utf8(&payload[pos..pos + id_len])
+        .map_err(|_| ProtocolError::InvalidUtf8)?
+        .to_string();
+    let offset = u64::from_be_bytes(payload[pos + id_len..pos + id_len + 8].try_into().unwrap());
+    Ok((replid, offset))
+}
+
+/// Parse AUTH payload: user_len(2) + user + password
+///
+/// An empty user name means the `default` user.
+pub fn parse_auth_payload(payload: &[u8]) -> Result<(Option<String>, String), ProtocolError> {
+    let mut pos = 0;
+    let user = read_key(payload, &mut pos)?;
+    let password = std::str::from_utf8(&payload[pos..])
+        .map_err(|_| ProtocolError::InvalidUtf8)?
+        .to_string();
+    Ok(((!user.is_empty()).then_some(user), password))
+}
+
+/// Encode AUTH payload: user_len(2) + user + password
+pub fn encode_auth_payload(user: Option<&str>, password: &str) -> Vec<u8> {
+    let user = user.unwrap_or("");
+    let mut buf = Vec::with_capacity(2 + user.len() + password.len());
+    buf.extend_from_slice(&(user.len() as u16).to_be_bytes());
+    buf.extend_from_slice(user.as_bytes());
+    buf.extend_from_slice(password.as_bytes());
+    buf
+}
+
+/// Encode SYNC payload: replid_len(2) + replid + offset(8)
+pub fn encode_sync_payload(replid: &str, offset: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(10 + replid.len());
+    buf.extend_from_slice(&(replid.len() as u16).to_be_bytes());
+    buf.extend_from_slice(replid.as_bytes());
+    buf.extend_from_slice(&offset.to_be_bytes());
+    buf
+}
+
+/// Read a length-prefixed key: key_len(2) + key
+fn read_key(payload: &[u8], pos: &mut usize) -> Result<String, ProtocolError> {
+    if payload.len() < *pos + 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let key_len = u16::from_be_bytes(payload[*pos..*pos + 2].try_into().unwrap()) as usize;
+    *pos += 2;
+
+    if payload.len() < *pos + key_len {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let key = std::str::from_utf8(&payload[*pos..*pos + key_len])
+        .map_err(|_| ProtocolError::InvalidUtf8)?
+        .to_string();
+    *pos += key_len;
+    Ok(key)
+}
+
+/// Read a big-endian u64
+fn read_u64(payload: &[u8], pos: &mut usize) -> Result<u64, ProtocolError> {
+    if payload.len() < *pos + 8 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let n = u64::from_be_bytes(payload[*pos..*pos + 8].try_into().unwrap());
+    *pos += 8;
+    Ok(n)
+}
+
+/// Read a byte
+fn read_u8(payload: &[u8], pos: &mut usize) -> Result<u8, ProtocolError> {
+    let byte = *payload.get(*pos).ok_or(ProtocolError::UnexpectedEof)?;
+    *pos += 1;
+    Ok(byte)
+}
+
+/// Parse EXEC payload
+///
+/// guard_count(2) + [key_len(2) + key + guard(1) + operand]...
+/// + op_count(2) + [op(1) + key_len(2) + key + operand]...
+///
+/// Guards: 0 exists, 1 missing, 2 equals + value, 3 at-least + i64(8), 4 version + u64(8).
+/// Ops: 0 get, 1 set + ttl(8) + value, 2 setnx + ttl(8) + value, 3 delete,
+/// 4 incr + delta(8), 5 expire + ttl(8). A ttl of 0 means none.
+pub fn parse_exec_payload(payload: &[u8]) -> Result<Transaction, ProtocolError> {
+    let mut pos = 0;
+    let mut txn = Transaction::new();
+
+    if payload.len() < 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let guard_count = u16::from_be_bytes(payload[0..2].try_into().unwrap());
+    pos += 2;
+    for _ in 0..guard_count {
+        let key = read_key(payload, &mut pos)?;
+        let guard = match read_u8(payload, &mut pos)? {
+            0 => Guard::Exists,
+            1 => Guard::Missing,
+            2 => {
+                let (value, consumed) = decode_value(&payload[pos..])?;
+                pos += consumed;
+                Guard::Equals(value)
+            }
+            3 => Guard::AtLeast(read_u64(payload, &mut pos)? as i64),
+            4 => Guard::Version(read_u64(payload, &mut pos)?),
+            other => return Err(ProtocolError::InvalidCommand(other)),
+        };
+        txn.guards.push((key, guard));
+    }
+
+    if payload.len() < pos + 2 {
+        return Err(ProtocolError::UnexpectedEof);
+    }
+    let op_count = u16::from_be_bytes(payload[pos..pos + 2].try_into().unwrap());
+    pos += 2;
+    for _ in 0..op_count {
+        let op = read_u8(payload, &mut pos)?;
+        let key = read_key(payload, &mut pos)?;
+        let op = match op {
+            0 => TxnOp::Get { key },
+            1 | 2 => {
+                let ttl = read_ttl(payload, &mut pos)?;
+                let (value, consumed) = decode_value(&payload[pos..])?;
+                pos += consumed;
+                if op == 1 {
+                    TxnOp::Set { key, value, ttl }
+                } else {
+                    TxnOp::SetNx { key, value, ttl }
+                }
+            }
+            3 => TxnOp::Delete { key },
+            4 => TxnOp::Incr { key, delta: read_u64(payload, &mut pos)? as i64 },
+            5 => TxnOp::Expire { key, ttl: read_ttl(payload, &mut pos)? },
+            other => return Err(ProtocolError::InvalidCommand(other)),
+        };
+        txn.push(op);
+    }
+
+    Ok(txn)
+}
+
+/// Read a ttl in milliseconds (0 = none)
+fn read_ttl(payload: &[u8], pos: &mut usize) -> Result<Option<std::time::Duration>, ProtocolError> {
+    let ttl_ms = read_u64(payload, pos)?;
+    Ok((ttl_ms != 0).then(|| std::time::Duration::from_millis(ttl_ms)))
+}
+
+/// Encode EXEC response: count(2) + [result]...
+///
+/// Results: 0 value + value (Null type if missing), 1 int + i64(8), 2 bool + u8, 3 ok.
+pub fn encode_exec_response(results: &[TxnResult]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(results.len() as u16).to_be_bytes());
+
+    for result in results {
+        match result {
+            TxnResult::Value(value) => {
+                buf.push(0);
+                match value {
+                    Some(value) => encode_value_into(&mut buf, value),
+                    None => buf.push(ValueType::Null as u8),
+                }
+            }
+            TxnResult::Int(n) => {
+                buf.push(1);
+                buf.extend_from_slice(&n.to_be_bytes());
+            }
+            TxnResult::Bool(b) => {
+                buf.push(2);
+                buf.push(*b as u8);
+            }
+            TxnResult::Ok => buf.push(3),
+        }
+    }
+
+    buf
+}
+
+/// Parse COLLECTION payload: command(1) + key_len(2) + key + arg_count(2) + [value]...
+pub fn parse_collection_payload(payload: &[u8]) -> Result<(CollectionCommand, String, Vec<KvValue>), ProtocolError> {
+    let mut pos = 0;
+    let cmd = CollectionCommand::try_from(read_u8(payload, &mut pos)?)?;
+    let key = read_key(payload, &mut pos)?;
+
+    let count_bytes = payload.get(pos..pos + 2).ok_or(ProtocolError::UnexpectedEof)?;
+    let count = u16::from_be_bytes([count_bytes[0], count_bytes[1]]) as usize;
+    pos += 2;
+
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (value, consumed) = decode_value(&payload[pos..])?;
+        args.push(value);
+        pos += consumed;
+    }
+
+    Ok((cmd, key, args))
+}
+
+/// Encode a request: cmd(1) + len(4) + payload
+pub fn write_request(cmd: Command, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(5 + payload.len());
+    buf.push(cmd as u8);
+    buf.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    buf.extend_from_slice(payload);
+    buf
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -514,4 +1104,124 @@ mod tests {
         let (decoded, _) = decode_value(&encoded).unwrap();
         assert_eq!(value, decoded);
     }
+
+    #[test]
+    fn test_parse_scan_payload() {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&42u64.to_be_bytes());
+        payload.extend_from_slice(&100u32.to_be_bytes());
+        payload.extend_from_slice(&6u16.to_be_bytes());
+        payload.extend_from_slice(b"user:*");
+
+        let (cursor, count, pattern) = parse_scan_payload(&payload).unwrap();
+        assert_eq!(cursor, 42);
+        assert_eq!(count, 100);
+        assert_eq!(pattern.as_deref(), Some("user:*"));
+
+        let mut payload = payload[..12].to_vec();
+        payload.extend_from_slice(&0u16.to_be_bytes());
+        let (_, _, pattern) = parse_scan_payload(&payload).unwrap();
+        assert_eq!(pattern, None);
+    }
+
+    #[test]
+    fn test_request_len() {
+        assert_eq!(request_len(&[0x08, 0, 0]).unwrap(), None);
+        assert_eq!(request_len(&[0x08, 0, 0, 0, 0]).unwrap(), Some(5));
+        assert_eq!(request_len(&[0x01, 0, 0, 0, 3, b'a']).unwrap(), None);
+        assert_eq!(request_len(&[0x01, 0, 0, 0, 1, b'a', 0x08]).unwrap(), Some(6));
+        assert!(request_len(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+    }
+
+    #[test]
+    fn test_parse_publish_payload() {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&5u16.to_be_bytes());
+        payload.extend_from_slice(b"cache");
+        payload.extend_from_slice(b"user:1");
+
+        let (channel, message) = parse_publish_payload(&payload).unwrap();
+        assert_eq!(channel, "cache");
+        assert_eq!(message, b"user:1");
+    }
+
+    #[test]
+    fn test_sync_payload_roundtrip() {
+        let request = write_request(Command::Sync, &encode_sync_payload("abc-123", 42));
+        let (cmd, payload) = read_request(&request).unwrap();
+        assert_eq!(cmd, Command::Sync);
+        assert_eq!(parse_sync_payload(&payload).unwrap(), ("abc-123".to_string(), 42));
+
+        let (replid, offset) = parse_sync_payload(&encode_sync_payload("", 0)).unwrap();
+        assert!(replid.is_empty());
+        assert_eq!(offset, 0);
+
+        assert!(parse_sync_payload(&[0, 3, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_auth_payload_roundtrip() {
+        let request = write_request(Command::Auth, &encode_auth_payload(Some("worker"), "s3cret"));
+        let (cmd, payload) = read_request(&request).unwrap();
+        assert_eq!(cmd, Command::Auth);
+        assert_eq!(
+            parse_auth_payload(&payload).unwrap(),
+            (Some("worker".to_string()), "s3cret".to_string())
+        );
+        assert_eq!(
+            parse_auth_payload(&encode_auth_payload(None, "pw")).unwrap(),
+            (None, "pw".to_string())
+        );
+        assert!(parse_auth_payload(&[0, 6, b'w']).is_err());
+    }
+
+    #[test]
+    fn test_acl_names() {
+        assert_eq!(Command::MDel.acl_name(), "del");
+        assert_eq!(Command::Set.category(), Category::Write);
+        assert_eq!(Command::Scan.category(), Category::Read);
+        assert_eq!(Command::Sync.category(), Category::Admin);
+        assert_eq!(CollectionCommand::ZRangeByScore.acl_name(), "zrangebyscore");
+    }
+
+    #[test]
+    fn test_parse_exec_payload() {
+        let mut payload = Vec::new();
+        // One guard: "stock" at least 3
+        payload.extend_from_slice(&1u16.to_be_bytes());
+        payload.extend_from_slice(&5u16.to_be_bytes());
+        payload.extend_from_slice(b"stock");
+        payload.push(3);
+        payload.extend_from_slice(&3i64.to_be_bytes());
+        // Two ops: incr stock -3, set note "x" with 1s ttl
+        payload.extend_from_slice(&2u16.to_be_bytes());
+        payload.push(4);
+        payload.extend_from_slice(&5u16.to_be_bytes());
+        payload.extend_from_slice(b"stock");
+        payload.extend_from_slice(&(-3i64).to_be_bytes());
+        payload.push(1);
+        payload.extend_from_slice(&4u16.to_be_bytes());
+        payload.extend_from_slice(b"note");
+        payload.extend_from_slice(&1000u64.to_be_bytes());
+        payload.extend_from_slice(&encode_value(&KvValue::String("x".to_string())));
+
+        let txn = parse_exec_payload(&payload).unwrap();
+        assert_eq!(txn.guards, vec![("stock".to_string(), Guard::AtLeast(3))]);
+        assert_eq!(
+            txn.ops,
+            vec![
+                TxnOp::Incr { key: "stock".to_string(), delta: -3 },
+                TxnOp::Set {
+                    key: "note".to_string(),
+                    value: KvValue::String("x".to_string()),
+                    ttl: Some(std::time::Duration::from_secs(1)),
+                },
+            ]
+        );
+
+        assert!(parse_exec_payload(&payload[..payload.len() - 3]).is_err());
+
+        let encoded = encode_exec_response(&[TxnResult::Int(2), TxnResult::Value(None), TxnResult::Ok]);
+        assert_eq!(encoded, [0, 3, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, ValueType::Null as u8, 3]);
+    }
 }
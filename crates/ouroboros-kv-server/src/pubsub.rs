@@ -0,0 +1,377 @@
+//! Pub/Sub channels and keyspace notifications
+//!
+//! Each subscribed connection owns a bounded broadcast queue. Publishing never
+//! blocks: a subscriber that falls more than `SUBSCRIBER_BUFFER` messages
+//! behind is told it lagged and disconnected, so it can resubscribe and
+//! resynchronise instead of silently missing invalidations.
+//!
+//! ## Keyspace events
+//!
+//! When enabled, every change to a key is published twice:
+//!
+//! - `__keyspace__:<key>` with the event name (`set`, `del`, `expired`, `evicted`)
+//! - `__keyevent__:<event>` with the key
+
+use ouroboros_kv::{GlobPattern, KeyspaceEventKind, KeyspaceListener};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// Messages queued per subscriber before it is considered too slow
+pub const SUBSCRIBER_BUFFER: usize = 4096;
+
+/// Channel prefix for per-key notifications
+pub const KEYSPACE_PREFIX: &str = "__keyspace__:";
+
+/// Channel prefix for per-event notifications
+pub const KEYEVENT_PREFIX: &str = "__keyevent__:";
+
+/// A message delivered to a subscriber
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Pattern that matched (None for direct channel subscriptions)
+    pub pattern: Option<Arc<str>>,
+    pub channel: Arc<str>,
+    pub payload: Arc<[u8]>,
+}
+
+type SubscriberId = u64;
+
+struct PatternSubscribers {
+    pattern: GlobPattern,
+    subscribers: HashMap<SubscriberId, broadcast::Sender<Message>>,
+}
+
+/// Channel registry shared by all connections
+#[derive(Default)]
+pub struct PubSub {
+    channels: RwLock<HashMap<String, HashMap<SubscriberId, broadcast::Sender<Message>>>>,
+    patterns: RwLock<HashMap<String, PatternSubscribers>>,
+    /// Total channel + pattern subscriptions, to skip work when nobody listens
+    subscriptions: AtomicUsize,
+    next_id: AtomicU64,
+}
+
+impl PubSub {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create subscription state for a new connection
+    pub fn subscriber(self: &Arc<Self>) -> Subscriber {
+        let (tx, rx) = broadcast::channel(SUBSCRIBER_BUFFER);
+        Subscriber {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            hub: Arc::clone(self),
+            tx,
+            rx,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+        }
+    }
+
+    /// Whether anyone is subscribed to anything
+    #[inline]
+    pub fn has_subscribers(&self) -> bool {
+        self.subscriptions.load(Ordering::Relaxed) > 0
+    }
+
+    /// Publish a message, returns the number of subscriptions it was delivered to
+    pub fn publish(&self, channel: &str, payload: &[u8]) -> usize {
+        if !self.has_subscribers() {
+            return 0;
+        }
+
+        let channel_name: Arc<str> = Arc::from(channel);
+        let payload: Arc<[u8]> = Arc::from(payload);
+        let mut delivered = 0;
+
+        if let Some(subscribers) = self.channels.read().unwrap().get(channel) {
+            let message = Message {
+                pattern: None,
+                channel: channel_name.clone(),
+                payload: payload.clone(),
+            };
+            for tx in subscribers.values() {
+                if tx.send(message.clone()).is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+
+        for (pattern, entry) in self.patterns.read().unwrap().iter() {
+            if entry.subscribers.is_empty() || !entry.pattern.matches(channel) {
+                continue;
+            }
+            let message = Message {
+                pattern: Some(Arc::from(pattern.as_str())),
+                channel: channel_name.clone(),
+                payload: payload.clone(),
+            };
+            for tx in entry.subscribers.values() {
+                if tx.send(message.clone()).is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+
+        delivered
+    }
+
+    fn add_channel(&self, id: SubscriberId, channel: &str, tx: &broadcast::Sender<Message>) {
+        self.channels
+            .write()
+            .unwrap()
+            .entry(channel.to_string())
+            .or_default()
+            .insert(id, tx.clone());
+        self.subscriptions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn remove_channel(&self, id: SubscriberId, channel: &str) {
+        let mut channels = self.channels.write().unwrap();
+        if let Some(subscribers) = channels.get_mut(channel) {
+            if subscribers.remove(&id).is_some() {
+                self.subscriptions.fetch_sub(1, Ordering::Relaxed);
+            }
+            if subscribers.is_empty() {
+                channels.remove(channel);
+            }
+        }
+    }
+
+    fn add_pattern(&self, id: SubscriberId, pattern: &str, tx: &broadcast::Sender<Message>) {
+        self.patterns
+            .write()
+            .unwrap()
+            .entry(pattern.to_string())
+            .or_insert_with(|| PatternSubscribers {
+                pattern: GlobPattern::new(pattern),
+                subscribers: HashMap::new(),
+            })
+            .subscribers
+            .insert(id, tx.clone());
+        self.subscriptions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn remove_pattern(&self, id: SubscriberId, pattern: &str) {
+        let mut patterns = self.patterns.write().unwrap();
+        if let Some(entry) = patterns.get_mut(pattern) {
+            if entry.subscribers.remove(&id).is_some() {
+                self.subscriptions.fetch_sub(1, Ordering::Relaxed);
+            }
+            if entry.subscribers.is_empty() {
+                patterns.remove(pattern);
+            }
+        }
+    }
+}
+
+/// Subscriptions held by one connection
+///
+/// Everything is unsubscribed when this is dropped.
+pub struct Subscriber {
+    id: SubscriberId,
+    hub: Arc<PubSub>,
+    tx: broadcast::Sender<Message>,
+    rx: broadcast::Receiver<Message>,
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+}
+
+impl Subscriber {
+    /// Subscribe to channels, returns the total subscription count
+    pub fn subscribe(&mut self, channels: &[String]) -> usize {
+        for channel in channels {
+            if self.channels.insert(channel.clone()) {
+                self.hub.add_channel(self.id, channel, &self.tx);
+            }
+        }
+        self.count()
+    }
+
+    /// Unsubscribe from channels (all channels if empty), returns the total subscription count
+    pub fn unsubscribe(&mut self, channels: &[String]) -> usize {
+        let removed: Vec<String> = if channels.is_empty() {
+            self.channels.drain().collect()
+        } else {
+            channels.iter().filter(|c| self.channels.remove(*c)).cloned().collect()
+        };
+        for channel in &removed {
+            self.hub.remove_channel(self.id, channel);
+        }
+        self.count()
+    }
+
+    /// Subscribe to glob patterns, returns the total subscription count
+    pub fn psubscribe(&mut self, patterns: &[String]) -> usize {
+        for pattern in patterns {
+            if self.patterns.insert(pattern.clone()) {
+                self.hub.add_pattern(self.id, pattern, &self.tx);
+            }
+        }
+        self.count()
+    }
+
+    /// Unsubscribe from patterns (all patterns if empty), returns the total subscription count
+    pub fn punsubscribe(&mut self, patterns: &[String]) -> usize {
+        let removed: Vec<String> = if patterns.is_empty() {
+            self.patterns.drain().collect()
+        } else {
+            patterns.iter().filter(|p| self.patterns.remove(*p)).cloned().collect()
+        };
+        for pattern in &removed {
+            self.hub.remove_pattern(self.id, pattern);
+        }
+        self.count()
+    }
+
+    /// Channels currently subscribed to
+    pub fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+
+    /// Patterns currently subscribed to
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    /// Number of channels and patterns subscribed to
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// Wait for the next message
+    ///
+    /// Fails with `Lagged` if the subscriber fell too far behind.
+    pub async fn recv(&mut self) -> Result<Message, broadcast::error::RecvError> {
+        self.rx.recv().await
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        for channel in self.channels.drain() {
+            self.hub.remove_channel(self.id, &channel);
+        }
+        for pattern in self.patterns.drain() {
+            self.hub.remove_pattern(self.id, &pattern);
+        }
+    }
+}
+
+/// Publishes engine keyspace events to `__keyspace__:*` and `__keyevent__:*`
+pub struct KeyspaceNotifier {
+    hub: Arc<PubSub>,
+}
+
+impl KeyspaceNotifier {
+    pub fn new(hub: Arc<PubSub>) -> Self {
+        Self { hub }
+    }
+}
+
+impl KeyspaceListener for KeyspaceNotifier {
+    fn on_event(&self, kind: KeyspaceEventKind, key: &str) {
+        if !self.hub.has_subscribers() {
+            return;
+        }
+        self.hub
+            .publish(&format!("{}{}", KEYSPACE_PREFIX, key), kind.as_str().as_bytes());
+        self.hub
+            .publish(&format!("{}{}", KEYEVENT_PREFIX, kind), key.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_channel_and_pattern() {
+        let hub = Arc::new(PubSub::new());
+        let mut direct = hub.subscriber();
+        let mut pattern = hub.subscriber();
+
+        assert_eq!(direct.subscribe(&names(&["cache:users"])), 1);
+        assert_eq!(pattern.psubscribe(&names(&["cache:*"])), 1);
+
+        assert_eq!(hub.publish("cache:users", b"invalidate"), 2);
+        assert_eq!(hub.publish("cache:orders", b"invalidate"), 1);
+        assert_eq!(hub.publish("other", b"ignored"), 0);
+
+        let message = direct.recv().await.unwrap();
+        assert_eq!(&*message.channel, "cache:users");
+        assert!(message.pattern.is_none());
+        assert_eq!(&*message.payload, b"invalidate");
+
+        let first = pattern.recv().await.unwrap();
+        let second = pattern.recv().await.unwrap();
+        assert_eq!(first.pattern.as_deref(), Some("cache:*"));
+        assert_eq!(&*first.channel, "cache:users");
+        assert_eq!(&*second.channel, "cache:orders");
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_and_drop() {
+        let hub = Arc::new(PubSub::new());
+        let mut subscriber = hub.subscriber();
+        subscriber.subscribe(&names(&["a", "b"]));
+        subscriber.psubscribe(&names(&["c*"]));
+        assert_eq!(subscriber.count(), 3);
+
+        assert_eq!(subscriber.unsubscribe(&names(&["a"])), 2);
+        assert_eq!(hub.publish("a", b"x"), 0);
+        assert_eq!(hub.publish("b", b"x"), 1);
+
+        // Empty list removes every channel but keeps patterns
+        assert_eq!(subscriber.unsubscribe(&[]), 1);
+        assert_eq!(hub.publish("cat", b"x"), 1);
+
+        drop(subscriber);
+        assert!(!hub.has_subscribers());
+        assert_eq!(hub.publish("cat", b"x"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_lags() {
+        let hub = Arc::new(PubSub::new());
+        let mut subscriber = hub.subscriber();
+        subscriber.subscribe(&names(&["busy"]));
+
+        for _ in 0..SUBSCRIBER_BUFFER + 10 {
+            hub.publish("busy", b"x");
+        }
+
+        assert!(matches!(
+            subscriber.recv().await,
+            Err(broadcast::error::RecvError::Lagged(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_notifier() {
+        let hub = Arc::new(PubSub::new());
+        let mut subscriber = hub.subscriber();
+        subscriber.psubscribe(&names(&["__keyspace__:user:*"]));
+        subscriber.subscribe(&names(&["__keyevent__:del"]));
+
+        let notifier = KeyspaceNotifier::new(hub.clone());
+        notifier.on_event(KeyspaceEventKind::Set, "user:1");
+        notifier.on_event(KeyspaceEventKind::Delete, "session:1");
+
+        let message = subscriber.recv().await.unwrap();
+        assert_eq!(&*message.channel, "__keyspace__:user:1");
+        assert_eq!(&*message.payload, b"set");
+
+        let message = subscriber.recv().await.unwrap();
+        assert_eq!(&*message.channel, "__keyevent__:del");
+        assert_eq!(&*message.payload, b"session:1");
+    }
+}
@@ -0,0 +1,482 @@
+//! Primary/replica replication
+//!
+//! A replica connects to its primary and sends `SYNC` with the replication id
+//! and offset it last applied (empty on first start). The primary answers with
+//! a stream of `Status::Ok` frames, tagged by their first byte:
+//!
+//! - `FULL_SYNC`: replid_len(2) + replid + offset(8); the replica discards its data
+//! - `SNAPSHOT`: encoded WAL entries recreating part of the keyspace
+//! - `CONTINUE`: replid_len(2) + replid + offset(8); streaming starts after `offset`
+//! - `ENTRIES`: offset(8) of the last entry + encoded WAL entries
+//!
+//! If the replid matches and the offset is still in the primary's backlog, the
+//! primary skips straight to `CONTINUE` (partial resync). Otherwise it sends a
+//! full sync: a snapshot taken after recording the current offset, followed by
+//! every change made since. Entries are post-images, so changes that are in
+//! both the snapshot and the stream are simply applied twice.
+//!
+//! An empty `ENTRIES` frame is a heartbeat. A replica that stops hearing from
+//! its primary reconnects.
+//!
+//! A replica with persistence saves its replid and offset next to its WAL
+//! (after the WAL is flushed) and snapshots itself after each full sync, so it
+//! resumes with a partial resync after a restart.
+//!
+//! A primary that requires authentication is sent `AUTH` before `SYNC`; its
+//! user needs the `sync` permission (`+sync` or `+@admin`). The link can run
+//! over TLS like any client connection.
+
+use crate::protocol::{
+    encode_auth_payload, encode_sync_payload, parse_sync_payload, write_request, write_response,
+    Command, Status,
+};
+use crate::tls::{self, Stream};
+use ouroboros_kv::persistence::format::decode_wal_entry;
+use ouroboros_kv::persistence::recovery::RecoveryManager;
+use ouroboros_kv::persistence::PersistenceHandle;
+use ouroboros_kv::replication::encode_shard;
+use ouroboros_kv::{KvEngine, ReplicationPosition};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio_rustls::TlsConnector;
+use tracing::{info, warn};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const FULL_SYNC: u8 = 0x01;
+const SNAPSHOT: u8 = 0x02;
+const CONTINUE: u8 = 0x03;
+const ENTRIES: u8 = 0x04;
+
+/// Entries sent per frame
+const BATCH_SIZE: usize = 1000;
+
+/// Interval between heartbeats on an idle stream
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Replica gives up on a primary it has not heard from for this long
+const PRIMARY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before a replica reconnects after losing its primary
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Interval between saves of a replica's position while streaming
+const POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+// ==================== Primary ====================
+
+/// Stream changes to a replica that sent `SYNC`
+///
+/// Takes over the connection until the replica disconnects or falls behind
+/// the backlog (it then reconnects and gets a full sync).
+pub async fn serve_replica(
+    socket: &mut impl Stream,
+    engine: &Arc<KvEngine>,
+    payload: &[u8],
+) -> Result<(), Error> {
+    let Some(log) = engine.replication_log().cloned() else {
+        tls::send(socket, &write_response(Status::Error, b"Replication is not enabled on this server")).await?;
+        return Ok(());
+    };
+
+    let (replid, requested) = parse_sync_payload(payload)?;
+    let partial = replid == log.id() && log.entries_after(requested, 0).is_some();
+
+    let mut offset = if partial {
+        info!("Replica resuming from offset {}", requested);
+        requested
+    } else {
+        full_sync(socket, engine, log.id(), log.offset()).await?
+    };
+    tls::send(socket, &position_frame(CONTINUE, log.id(), offset)).await?;
+
+    loop {
+        let Some(batch) = log.entries_after(offset, BATCH_SIZE) else {
+            warn!(
+                "Replica at offset {} fell behind the replication backlog, closing",
+                offset
+            );
+            tls::send(socket, &write_response(Status::Error, b"Replica fell behind the replication backlog")).await?;
+            return Ok(());
+        };
+
+        if batch.is_empty() {
+            tokio::select! {
+                _ = log.wait_beyond(offset) => continue,
+                // Nothing new: fall through and send an empty frame as a heartbeat
+                _ = tokio::time::sleep(HEARTBEAT_INTERVAL) => {}
+            }
+        }
+
+        offset += batch.len() as u64;
+        let mut frame = Vec::with_capacity(9 + batch.iter().map(|e| e.len()).sum::<usize>());
+        frame.push(ENTRIES);
+        frame.extend_from_slice(&offset.to_be_bytes());
+        for entry in &batch {
+            frame.extend_from_slice(entry);
+        }
+        tls::send(socket, &write_response(Status::Ok, &frame)).await?;
+    }
+}
+
+/// Send the whole keyspace, returns the offset streaming resumes from
+async fn full_sync(
+    socket: &mut impl Stream,
+    engine: &Arc<KvEngine>,
+    replid: &str,
+    offset: u64,
+) -> Result<u64, Error> {
+    info!("Starting full sync for replica at offset {}", offset);
+    tls::send(socket, &position_frame(FULL_SYNC, replid, offset)).await?;
+
+    let mut sent = 0;
+    for shard_id in 0..engine.num_shards() {
+        let engine = engine.clone();
+        let entries = tokio::task::spawn_blocking(move || encode_shard(&engine, shard_id)).await?;
+        for chunk in entries.chunks(BATCH_SIZE) {
+            let mut frame = vec![SNAPSHOT];
+            for entry in chunk {
+                frame.extend_from_slice(entry);
+            }
+            tls::send(socket, &write_response(Status::Ok, &frame)).await?;
+        }
+        sent += entries.len();
+    }
+
+    info!("Full sync sent {} keys", sent);
+    Ok(offset)
+}
+
+fn position_frame(kind: u8, replid: &str, offset: u64) -> Vec<u8> {
+    let mut frame = vec![kind];
+    frame.extend_from_slice(&encode_sync_payload(replid, offset));
+    write_response(Status::Ok, &frame)
+}
+
+// ==================== Replica ====================
+
+/// Replication progress of a replica
+pub struct ReplicaState {
+    primary: String,
+    /// User (None for `default`) and password to AUTH with
+    credentials: Option<(Option<String>, String)>,
+    tls: Option<TlsConnector>,
+    /// Where to save the position, if the replica is persistent
+    persistence: Option<Arc<PersistenceHandle>>,
+    replid: Mutex<String>,
+    offset: AtomicU64,
+    link_up: AtomicBool,
+}
+
+impl ReplicaState {
+    /// Track replication from `primary` (host:port)
+    pub fn new(primary: impl Into<String>) -> Self {
+        Self {
+            primary: primary.into(),
+            credentials: None,
+            tls: None,
+            persistence: None,
+            replid: Mutex::new(String::new()),
+            offset: AtomicU64::new(0),
+            link_up: AtomicBool::new(false),
+        }
+    }
+
+    /// Authenticate to the primary; without a user, as `default`
+    pub fn with_auth(mut self, user: Option<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((user, password.into()));
+        self
+    }
+
+    /// Connect to the primary over TLS
+    pub fn with_tls(mut self, connector: TlsConnector) -> Self {
+        self.tls = Some(connector);
+        self
+    }
+
+    /// Save the position through `persistence`, resuming from `position`
+    ///
+    /// `position` is the one saved in the data directory the engine was
+    /// recovered from (see [`ReplicationPosition::load`]).
+    pub fn with_persistence(
+        mut self,
+        persistence: Arc<PersistenceHandle>,
+        position: Option<ReplicationPosition>,
+    ) -> Self {
+        if let Some(position) = position {
+            self.replid = Mutex::new(position.replid);
+            self.offset = AtomicU64::new(position.offset);
+        }
+        self.persistence = Some(persistence);
+        self
+    }
+
+    /// Primary address
+    pub fn primary(&self) -> &str {
+        &self.primary
+    }
+
+    /// Offset of the last change applied from the primary
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::Relaxed)
+    }
+
+    /// Whether the replica is connected and in sync (streaming changes)
+    pub fn link_up(&self) -> bool {
+        self.link_up.load(Ordering::Relaxed)
+    }
+
+    /// Save the current position, if the replica is persistent
+    fn save_position(&self) {
+        if let Some(persistence) = &self.persistence {
+            persistence.save_replication_position(ReplicationPosition {
+                replid: self.replid.lock().unwrap().clone(),
+                offset: self.offset(),
+            });
+        }
+    }
+}
+
+/// Follow the primary forever, reconnecting whenever the link drops
+pub async fn run_replica(engine: Arc<KvEngine>, state: Arc<ReplicaState>) {
+    loop {
+        if let Err(e) = follow(&engine, &state).await {
+            warn!("Replication link to {} lost: {}", state.primary, e);
+        }
+        state.link_up.store(false, Ordering::Relaxed);
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Connect to the primary and apply its stream until the connection fails
+async fn follow(engine: &Arc<KvEngine>, state: &ReplicaState) -> Result<(), Error> {
+    let mut stream = tls::connect(&state.primary, state.tls.as_ref()).await?;
+
+    if let Some((user, password)) = &state.credentials {
+        let request = encode_auth_payload(user.as_deref(), password);
+        tls::send(&mut stream, &write_request(Command::Auth, &request)).await?;
+        let (status, reply) = read_frame(&mut stream).await?;
+        if status != Status::Ok as u8 {
+            return Err(format!("AUTH failed: {}", String::from_utf8_lossy(&reply)).into());
+        }
+    }
+
+    let replid = state.replid.lock().unwrap().clone();
+    let request = encode_sync_payload(&replid, state.offset());
+    tls::send(&mut stream, &write_request(Command::Sync, &request)).await?;
+
+    let mut full_sync = false;
+    let mut last_save = Instant::now();
+    loop {
+        let (status, frame) = tokio::time::timeout(PRIMARY_TIMEOUT, read_frame(&mut stream))
+            .await
+            .map_err(|_| "Timed out waiting for primary")??;
+        if status != Status::Ok as u8 {
+            return Err(String::from_utf8_lossy(&frame).into());
+        }
+        let Some((&kind, body)) = frame.split_first() else {
+            return Err("Empty replication frame".into());
+        };
+
+        match kind {
+            FULL_SYNC => {
+                let (replid, offset) = parse_sync_payload(body)?;
+                info!("Full sync from {} (replid {}, offset {})", state.primary, replid, offset);
+                // Forget the old position until the new snapshot has fully arrived
+                state.replid.lock().unwrap().clear();
+                state.save_position();
+                full_sync = true;
+                let engine = engine.clone();
+                tokio::task::spawn_blocking(move || engine.clear()).await?;
+            }
+            SNAPSHOT => apply_entries(engine, body)?,
+            CONTINUE => {
+                let (replid, offset) = parse_sync_payload(body)?;
+                info!("Replicating from {} at offset {}", state.primary, offset);
+                *state.replid.lock().unwrap() = replid;
+                state.offset.store(offset, Ordering::Relaxed);
+                state.link_up.store(true, Ordering::Relaxed);
+                if full_sync {
+                    // Clearing the keyspace is not logged, so only a snapshot
+                    // keeps pre-sync keys from coming back on restart
+                    if let Some(persistence) = &state.persistence {
+                        persistence.create_snapshot();
+                    }
+                }
+                state.save_position();
+                last_save = Instant::now();
+            }
+            ENTRIES => {
+                if body.len() < 8 {
+                    return Err("Truncated replication frame".into());
+                }
+                let offset = u64::from_be_bytes(body[..8].try_into().unwrap());
+                apply_entries(engine, &body[8..])?;
+                state.offset.store(offset, Ordering::Relaxed);
+                if last_save.elapsed() >= POSITION_SAVE_INTERVAL {
+                    state.save_position();
+                    last_save = Instant::now();
+                }
+            }
+            other => return Err(format!("Unknown replication frame {:#04x}", other).into()),
+        }
+    }
+}
+
+/// Apply concatenated encoded WAL entries
+fn apply_entries(engine: &KvEngine, mut data: &[u8]) -> Result<(), Error> {
+    while !data.is_empty() {
+        if data.len() < 4 {
+            return Err("Truncated replication entry".into());
+        }
+        let len = 4 + u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        if data.len() < len {
+            return Err("Truncated replication entry".into());
+        }
+        let entry = decode_wal_entry(&data[..len], 0)?;
+        RecoveryManager::apply_wal_operation(engine, &entry.op)?;
+        data = &data[len..];
+    }
+    Ok(())
+}
+
+/// Read one response frame: status(1) + len(4) + payload
+async fn read_frame(stream: &mut impl Stream) -> Result<(u8, Vec<u8>), Error> {
+    let mut header = [0u8; 5];
+    stream.read_exact(&mut header).await?;
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok((header[0], payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ouroboros_kv::{KvKey, KvValue, ReplicationLog};
+    use tokio::net::TcpListener;
+
+    async fn wait_for(mut condition: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    /// Serve `SYNC` requests for `primary`, returns the address to connect to
+    async fn serve_primary(primary: Arc<KvEngine>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let engine = primary.clone();
+                tokio::spawn(async move {
+                    let mut header = [0u8; 5];
+                    socket.read_exact(&mut header).await.unwrap();
+                    let mut payload = vec![0u8; u32::from_be_bytes(header[1..].try_into().unwrap()) as usize];
+                    socket.read_exact(&mut payload).await.unwrap();
+                    let _ = serve_replica(&mut socket, &engine, &payload).await;
+                });
+            }
+        });
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_replica_follows_primary() {
+        let primary = Arc::new(KvEngine::with_shards(4));
+        primary
+            .enable_replication(Arc::new(ReplicationLog::new(1024 * 1024)))
+            .unwrap();
+        for i in 0..100 {
+            primary.set(&KvKey::new(format!("key_{}", i)).unwrap(), KvValue::Int(i), None);
+        }
+        let addr = serve_primary(primary.clone()).await;
+
+        let replica = Arc::new(KvEngine::with_shards(8));
+        replica.set(&KvKey::new("stale").unwrap(), KvValue::Int(0), None);
+        let state = Arc::new(ReplicaState::new(addr));
+        let task = tokio::spawn(run_replica(replica.clone(), state.clone()));
+
+        wait_for(|| state.link_up()).await;
+        assert_eq!(replica.len(), 100);
+        assert!(!replica.exists(&KvKey::new("stale").unwrap()));
+
+        let counter = KvKey::new("counter").unwrap();
+        primary.incr(&counter, 5).unwrap();
+        primary.delete(&KvKey::new("key_1").unwrap());
+        let target = primary.replication_log().unwrap().offset();
+        wait_for(|| state.offset() == target).await;
+
+        assert_eq!(replica.get(&counter), Some(KvValue::Int(5)));
+        assert!(!replica.exists(&KvKey::new("key_1").unwrap()));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_persistent_replica_resumes_after_restart() {
+        use ouroboros_kv::persistence::PersistenceConfig;
+
+        let primary = Arc::new(KvEngine::with_shards(4));
+        primary
+            .enable_replication(Arc::new(ReplicationLog::new(1024 * 1024)))
+            .unwrap();
+        for i in 0..50 {
+            primary.set(&KvKey::new(format!("key_{}", i)).unwrap(), KvValue::Int(i), None);
+        }
+        let addr = serve_primary(primary.clone()).await;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = temp_dir.path();
+
+        let persistent_replica = |engine: KvEngine| {
+            let engine = Arc::new(engine);
+            let config = PersistenceConfig::new(data_dir).with_fsync_interval_ms(10);
+            let persistence = Arc::new(PersistenceHandle::new(config, engine.clone()).unwrap());
+            engine.enable_persistence(persistence.clone());
+            let position = ReplicationPosition::load(data_dir).unwrap();
+            let state = Arc::new(ReplicaState::new(addr.clone()).with_persistence(persistence, position));
+            (engine, state)
+        };
+
+        // First run: full sync, then stream a few changes
+        let (replica, state) = persistent_replica(KvEngine::with_shards(4));
+        let task = tokio::spawn(run_replica(replica.clone(), state.clone()));
+        wait_for(|| state.link_up()).await;
+        primary.set(&KvKey::new("after_sync").unwrap(), KvValue::Int(1), None);
+        let target = primary.replication_log().unwrap().offset();
+        wait_for(|| state.offset() == target).await;
+        // Saved on a later frame (here a heartbeat), once the WAL is flushed
+        wait_for(|| {
+            ReplicationPosition::load(data_dir).unwrap().map(|p| p.offset) == Some(target)
+        })
+        .await;
+        task.abort();
+
+        // Restart from disk; a partial resync keeps local-only keys a full sync would drop
+        let (recovered, _) = RecoveryManager::recover(data_dir, 4).unwrap();
+        assert_eq!(recovered.len(), 51);
+        recovered.set(&KvKey::new("local_only").unwrap(), KvValue::Int(1), None);
+        let (replica, state) = persistent_replica(recovered);
+        assert_eq!(state.offset(), target);
+        primary.delete(&KvKey::new("key_0").unwrap());
+
+        let task = tokio::spawn(run_replica(replica.clone(), state.clone()));
+        let target = primary.replication_log().unwrap().offset();
+        wait_for(|| state.link_up() && state.offset() == target).await;
+        assert!(replica.exists(&KvKey::new("local_only").unwrap()));
+        assert!(!replica.exists(&KvKey::new("key_0").unwrap()));
+        task.abort();
+    }
+
+    #[test]
+    fn test_apply_entries_rejects_truncated_data() {
+        let engine = KvEngine::with_shards(1);
+        assert!(apply_entries(&engine, &[0, 0, 0, 9, 1]).is_err());
+        assert!(apply_entries(&engine, &[]).is_ok());
+    }
+}
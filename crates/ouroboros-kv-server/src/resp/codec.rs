@@ -0,0 +1,316 @@
+//! RESP2/RESP3 encoding
+//!
+//! Requests are arrays of bulk strings (`*2\r\n$3\r\nGET\r\n$1\r\nk\r\n`) or
+//! inline commands (`GET k\r\n`), as sent by `redis-cli` and telnet. Replies
+//! are encoded for the protocol version negotiated with `HELLO`; RESP3-only
+//! types fall back to their RESP2 equivalents on RESP2 connections.
+
+use thiserror::Error;
+
+/// Largest bulk string accepted in a request (same as the native protocol)
+const MAX_BULK_LEN: usize = 64 * 1024 * 1024;
+
+/// Most arguments accepted in one request
+const MAX_ARGS: usize = 1024 * 1024;
+
+/// Malformed request; the connection cannot be resynchronised afterwards
+#[derive(Error, Debug, PartialEq)]
+pub enum RespError {
+    #[error("Protocol error: expected '{expected}', got '{got}'")]
+    UnexpectedByte { expected: char, got: char },
+
+    #[error("Protocol error: invalid {0} length")]
+    InvalidLength(&'static str),
+
+    #[error("Protocol error: unbalanced quotes in request")]
+    UnbalancedQuotes,
+}
+
+/// A parsed request (its arguments) and the number of bytes it occupied
+type Parsed = Option<(Vec<Vec<u8>>, usize)>;
+
+/// RESP protocol version of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Resp2,
+    Resp3,
+}
+
+/// A reply to send to the client
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// `+OK`
+    Simple(String),
+    /// `-ERR ...`; the message should start with an error code
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    /// Missing value (`$-1` in RESP2, `_` in RESP3)
+    Nil,
+    Array(Vec<Reply>),
+    /// Key/value pairs (flattened into an array in RESP2)
+    Map(Vec<(Reply, Reply)>),
+    /// Out-of-band message (a plain array in RESP2)
+    Push(Vec<Reply>),
+}
+
+impl Reply {
+    pub fn ok() -> Self {
+        Reply::Simple("OK".to_string())
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Reply::Error(message.into())
+    }
+
+    pub fn bulk(data: impl Into<Vec<u8>>) -> Self {
+        Reply::Bulk(data.into())
+    }
+
+    /// Encode for the given protocol version
+    pub fn encode(&self, protocol: Protocol, buf: &mut Vec<u8>) {
+        match self {
+            Reply::Simple(s) => {
+                buf.push(b'+');
+                buf.extend_from_slice(s.as_bytes());
+                buf.extend_from_slice(b"\r\n");
+            }
+            Reply::Error(e) => {
+                buf.push(b'-');
+                // Line breaks would end the error early
+                buf.extend(e.bytes().map(|b| if b == b'\r' || b == b'\n' { b' ' } else { b }));
+                buf.extend_from_slice(b"\r\n");
+            }
+            Reply::Integer(n) => {
+                buf.extend_from_slice(format!(":{}\r\n", n).as_bytes());
+            }
+            Reply::Bulk(data) => {
+                buf.extend_from_slice(format!("${}\r\n", data.len()).as_bytes());
+                buf.extend_from_slice(data);
+                buf.extend_from_slice(b"\r\n");
+            }
+            Reply::Nil => match protocol {
+                Protocol::Resp2 => buf.extend_from_slice(b"$-1\r\n"),
+                Protocol::Resp3 => buf.extend_from_slice(b"_\r\n"),
+            },
+            Reply::Array(items) => encode_aggregate(b'*', items, protocol, buf),
+            Reply::Push(items) => {
+                let marker = if protocol == Protocol::Resp3 { b'>' } else { b'*' };
+                encode_aggregate(marker, items, protocol, buf);
+            }
+            Reply::Map(pairs) => {
+                match protocol {
+                    Protocol::Resp2 => buf.extend_from_slice(format!("*{}\r\n", pairs.len() * 2).as_bytes()),
+                    Protocol::Resp3 => buf.extend_from_slice(format!("%{}\r\n", pairs.len()).as_bytes()),
+                }
+                for (key, value) in pairs {
+                    key.encode(protocol, buf);
+                    value.encode(protocol, buf);
+                }
+            }
+        }
+    }
+}
+
+fn encode_aggregate(marker: u8, items: &[Reply], protocol: Protocol, buf: &mut Vec<u8>) {
+    buf.push(marker);
+    buf.extend_from_slice(format!("{}\r\n", items.len()).as_bytes());
+    for item in items {
+        item.encode(protocol, buf);
+    }
+}
+
+/// Parse the first complete request in `data`
+///
+/// Returns the arguments and the number of bytes consumed, or None if more
+/// data is needed. Empty inline lines yield an empty argument list.
+pub fn parse_request(data: &[u8]) -> Result<Parsed, RespError> {
+    match data.first() {
+        None => Ok(None),
+        Some(b'*') => parse_multibulk(data),
+        Some(_) => parse_inline(data),
+    }
+}
+
+fn parse_multibulk(data: &[u8]) -> Result<Parsed, RespError> {
+    let Some((count, mut pos)) = read_length(data, 1, "multibulk")? else {
+        return Ok(None);
+    };
+    if count > MAX_ARGS as i64 {
+        return Err(RespError::InvalidLength("multibulk"));
+    }
+
+    let mut args = Vec::with_capacity(count.max(0) as usize);
+    for _ in 0..count.max(0) {
+        match data.get(pos) {
+            None => return Ok(None),
+            Some(b'$') => {}
+            Some(&other) => {
+                return Err(RespError::UnexpectedByte { expected: '$', got: other as char })
+            }
+        }
+        let Some((len, start)) = read_length(data, pos + 1, "bulk")? else {
+            return Ok(None);
+        };
+        if len < 0 || len as usize > MAX_BULK_LEN {
+            return Err(RespError::InvalidLength("bulk"));
+        }
+        let end = start + len as usize;
+        if data.len() < end + 2 {
+            return Ok(None);
+        }
+        if &data[end..end + 2] != b"\r\n" {
+            return Err(RespError::InvalidLength("bulk"));
+        }
+        args.push(data[start..end].to_vec());
+        pos = end + 2;
+    }
+
+    Ok(Some((args, pos)))
+}
+
+/// Read a `<number>\r\n` starting at `pos`, returning the number and the position after it
+fn read_length(data: &[u8], pos: usize, what: &'static str) -> Result<Option<(i64, usize)>, RespError> {
+    let Some(offset) = data[pos.min(data.len())..].windows(2).position(|w| w == b"\r\n") else {
+        // A length line is never this long; don't wait forever for the terminator
+        if data.len() - pos.min(data.len()) > 32 {
+            return Err(RespError::InvalidLength(what));
+        }
+        return Ok(None);
+    };
+    let line = std::str::from_utf8(&data[pos..pos + offset]).map_err(|_| RespError::InvalidLength(what))?;
+    let n = line.parse::<i64>().map_err(|_| RespError::InvalidLength(what))?;
+    Ok(Some((n, pos + offset + 2)))
+}
+
+fn parse_inline(data: &[u8]) -> Result<Parsed, RespError> {
+    let Some(end) = data.iter().position(|&b| b == b'\n') else {
+        if data.len() > MAX_BULK_LEN {
+            return Err(RespError::InvalidLength("inline"));
+        }
+        return Ok(None);
+    };
+    let line = data[..end].strip_suffix(b"\r").unwrap_or(&data[..end]);
+    Ok(Some((split_inline(line)?, end + 1)))
+}
+
+/// Split an inline command on whitespace, honouring double and single quotes
+fn split_inline(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    while i < line.len() {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            break;
+        }
+
+        let mut arg = Vec::new();
+        match line[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                loop {
+                    match line.get(i) {
+                        None => return Err(RespError::UnbalancedQuotes),
+                        Some(&c) if c == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(b'\\') if quote == b'"' && i + 1 < line.len() => {
+                            arg.push(match line[i + 1] {
+                                b'n' => b'\n',
+                                b'r' => b'\r',
+                                b't' => b'\t',
+                                other => other,
+                            });
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            arg.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+            }
+            _ => {
+                while i < line.len() && !line[i].is_ascii_whitespace() {
+                    arg.push(line[i]);
+                    i += 1;
+                }
+            }
+        }
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(items: &[&str]) -> Vec<Vec<u8>> {
+        items.iter().map(|s| s.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_parse_multibulk() {
+        let data = b"*2\r\n$3\r\nGET\r\n$5\r\nmykey\r\n*1\r\n$4\r\nPING\r\n";
+        let (parsed, used) = parse_request(data).unwrap().unwrap();
+        assert_eq!(parsed, args(&["GET", "mykey"]));
+        let (parsed, rest) = parse_request(&data[used..]).unwrap().unwrap();
+        assert_eq!(parsed, args(&["PING"]));
+        assert_eq!(used + rest, data.len());
+
+        // Binary-safe values
+        let (parsed, _) = parse_request(b"*1\r\n$4\r\na\r\nb\r\n").unwrap().unwrap();
+        assert_eq!(parsed, vec![b"a\r\nb".to_vec()]);
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        let data = b"*2\r\n$3\r\nGET\r\n$5\r\nmykey\r\n";
+        for end in 0..data.len() {
+            assert_eq!(parse_request(&data[..end]).unwrap(), None, "prefix {}", end);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_request(b"*1\r\n+PING\r\n").is_err());
+        assert!(parse_request(b"*x\r\n").is_err());
+        assert!(parse_request(b"*1\r\n$-5\r\n").is_err());
+        assert!(parse_request(b"*1\r\n$3\r\nGETXX").is_err());
+    }
+
+    #[test]
+    fn test_parse_inline() {
+        let (parsed, used) = parse_request(b"SET key \"hello world\" 'a b'\r\nPING").unwrap().unwrap();
+        assert_eq!(parsed, args(&["SET", "key", "hello world", "a b"]));
+        assert_eq!(used, 29);
+
+        let (parsed, _) = parse_request(b"\r\n").unwrap().unwrap();
+        assert!(parsed.is_empty());
+        assert!(parse_request(b"GET \"open\n").is_err());
+    }
+
+    #[test]
+    fn test_encode_resp2_and_resp3() {
+        let reply = Reply::Map(vec![(Reply::bulk("proto"), Reply::Integer(3))]);
+        let mut resp2 = Vec::new();
+        reply.encode(Protocol::Resp2, &mut resp2);
+        assert_eq!(resp2, b"*2\r\n$5\r\nproto\r\n:3\r\n");
+        let mut resp3 = Vec::new();
+        reply.encode(Protocol::Resp3, &mut resp3);
+        assert_eq!(resp3, b"%1\r\n$5\r\nproto\r\n:3\r\n");
+
+        let mut buf = Vec::new();
+        Reply::Nil.encode(Protocol::Resp2, &mut buf);
+        Reply::Nil.encode(Protocol::Resp3, &mut buf);
+        Reply::Push(vec![Reply::bulk("message")]).encode(Protocol::Resp3, &mut buf);
+        Reply::error("ERR bad\r\nline").encode(Protocol::Resp2, &mut buf);
+        assert_eq!(buf, b"$-1\r\n_\r\n>1\r\n$7\r\nmessage\r\n-ERR bad  line\r\n");
+    }
+}
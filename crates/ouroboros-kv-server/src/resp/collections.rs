@@ -0,0 +1,334 @@
+//! Hash, list, set and sorted set commands
+//!
+//! Hash fields and list elements are stored like string values (see
+//! [`super::commands`]), so `HINCRBY` works on fields written by `HSET`. Set
+//! and sorted set members must be valid UTF-8. Scores are doubles and are
+//! formatted without a trailing `.0`, with infinities as `inf`/`-inf`.
+//!
+//! `ZRANGE` accepts `BYSCORE`, `REV`, `LIMIT` and `WITHSCORES`; `ZADD` accepts
+//! score/member pairs only (no `NX`/`XX`/`GT`/`LT`/`CH`/`INCR` flags).
+
+use super::codec::Reply;
+use super::commands::{arity, key, parse_int, utf8, value_from_bytes, value_reply, wrong_arity, CommandResult};
+use ouroboros_kv::{KvEngine, KvError};
+use std::ops::Bound;
+
+/// Whether `name` is a structure command handled here
+pub fn is_command(name: &str) -> bool {
+    is_write(name)
+        || matches!(
+            name,
+            "HGET" | "HMGET" | "HGETALL" | "HKEYS" | "HVALS" | "HEXISTS" | "HLEN"
+                | "LRANGE" | "LINDEX" | "LLEN"
+                | "SISMEMBER" | "SMEMBERS" | "SCARD"
+                | "ZSCORE" | "ZRANK" | "ZREVRANK" | "ZCARD"
+                | "ZRANGE" | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE"
+        )
+}
+
+/// Structure commands that modify the keyspace
+pub fn is_write(name: &str) -> bool {
+    matches!(
+        name,
+        "HSET" | "HMSET" | "HSETNX" | "HDEL" | "HINCRBY"
+            | "LPUSH" | "RPUSH" | "LPOP" | "RPOP" | "LTRIM"
+            | "SADD" | "SREM"
+            | "ZADD" | "ZINCRBY" | "ZREM"
+    )
+}
+
+pub fn execute(engine: &KvEngine, name: &str, args: &[Vec<u8>]) -> CommandResult {
+    if args.is_empty() {
+        return Err(wrong_arity(name));
+    }
+    let key = key(&args[0])?;
+    let rest = &args[1..];
+    let count = |n: usize| Reply::Integer(n as i64);
+    let strings = |args: &[Vec<u8>]| -> Result<Vec<String>, Reply> {
+        args.iter().map(|a| utf8(a).map(str::to_string)).collect()
+    };
+
+    match name {
+        // ---------- Hashes ----------
+        "HSET" | "HMSET" => {
+            if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                return Err(wrong_arity(name));
+            }
+            let fields = rest
+                .chunks(2)
+                .map(|pair| Ok((utf8(&pair[0])?.to_string(), value_from_bytes(&pair[1]))))
+                .collect::<Result<Vec<_>, Reply>>()?;
+            let added = engine.hset(&key, fields).map_err(error)?;
+            Ok(if name == "HSET" { count(added) } else { Reply::ok() })
+        }
+        "HSETNX" => {
+            arity(name, args, 3, 3)?;
+            let set = engine.hsetnx(&key, utf8(&rest[0])?, value_from_bytes(&rest[1])).map_err(error)?;
+            Ok(Reply::Integer(set as i64))
+        }
+        "HGET" => {
+            arity(name, args, 2, 2)?;
+            Ok(value_reply(engine.hget(&key, utf8(&rest[0])?).map_err(error)?))
+        }
+        "HMGET" => {
+            arity(name, args, 2, usize::MAX)?;
+            let fields = strings(rest)?;
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+            let values = engine.hmget(&key, &fields).map_err(error)?;
+            Ok(Reply::Array(values.into_iter().map(value_reply).collect()))
+        }
+        "HGETALL" | "HKEYS" | "HVALS" => {
+            arity(name, args, 1, 1)?;
+            let map = engine.hgetall(&key).map_err(error)?;
+            Ok(match name {
+                "HGETALL" => Reply::Map(
+                    map.into_iter()
+                        .map(|(field, value)| (Reply::bulk(field), value_reply(Some(value))))
+                        .collect(),
+                ),
+                "HKEYS" => Reply::Array(map.into_keys().map(Reply::bulk).collect()),
+                _ => Reply::Array(map.into_values().map(|v| value_reply(Some(v))).collect()),
+            })
+        }
+        "HDEL" => {
+            arity(name, args, 2, usize::MAX)?;
+            Ok(count(engine.hdel(&key, &strings(rest)?).map_err(error)?))
+        }
+        "HEXISTS" => {
+            arity(name, args, 2, 2)?;
+            Ok(Reply::Integer(engine.hexists(&key, utf8(&rest[0])?).map_err(error)? as i64))
+        }
+        "HLEN" => {
+            arity(name, args, 1, 1)?;
+            Ok(count(engine.hlen(&key).map_err(error)?))
+        }
+        "HINCRBY" => {
+            arity(name, args, 3, 3)?;
+            let delta = parse_int(&rest[1])?;
+            Ok(Reply::Integer(engine.hincrby(&key, utf8(&rest[0])?, delta).map_err(error)?))
+        }
+
+        // ---------- Lists ----------
+        "LPUSH" | "RPUSH" => {
+            arity(name, args, 2, usize::MAX)?;
+            let values = rest.iter().map(|v| value_from_bytes(v)).collect();
+            let len = if name == "LPUSH" { engine.lpush(&key, values) } else { engine.rpush(&key, values) };
+            Ok(count(len.map_err(error)?))
+        }
+        "LPOP" | "RPOP" => {
+            arity(name, args, 1, 2)?;
+            let n = match rest.first() {
+                Some(arg) => Some(
+                    usize::try_from(parse_int(arg)?)
+                        .map_err(|_| Reply::error("ERR value is out of range, must be positive"))?,
+                ),
+                None => None,
+            };
+            let popped = if name == "LPOP" {
+                engine.lpop(&key, n.unwrap_or(1))
+            } else {
+                engine.rpop(&key, n.unwrap_or(1))
+            }
+            .map_err(error)?;
+            Ok(match n {
+                None => value_reply(popped.into_iter().next()),
+                Some(n) if popped.is_empty() && n > 0 => Reply::Nil,
+                Some(_) => Reply::Array(popped.into_iter().map(|v| value_reply(Some(v))).collect()),
+            })
+        }
+        "LRANGE" => {
+            arity(name, args, 3, 3)?;
+            let items = engine
+                .lrange(&key, parse_int(&rest[0])?, parse_int(&rest[1])?)
+                .map_err(error)?;
+            Ok(Reply::Array(items.into_iter().map(|v| value_reply(Some(v))).collect()))
+        }
+        "LINDEX" => {
+            arity(name, args, 2, 2)?;
+            Ok(value_reply(engine.lindex(&key, parse_int(&rest[0])?).map_err(error)?))
+        }
+        "LLEN" => {
+            arity(name, args, 1, 1)?;
+            Ok(count(engine.llen(&key).map_err(error)?))
+        }
+        "LTRIM" => {
+            arity(name, args, 3, 3)?;
+            engine
+                .ltrim(&key, parse_int(&rest[0])?, parse_int(&rest[1])?)
+                .map_err(error)?;
+            Ok(Reply::ok())
+        }
+
+        // ---------- Sets ----------
+        "SADD" => {
+            arity(name, args, 2, usize::MAX)?;
+            Ok(count(engine.sadd(&key, strings(rest)?).map_err(error)?))
+        }
+        "SREM" => {
+            arity(name, args, 2, usize::MAX)?;
+            Ok(count(engine.srem(&key, &strings(rest)?).map_err(error)?))
+        }
+        "SISMEMBER" => {
+            arity(name, args, 2, 2)?;
+            Ok(Reply::Integer(engine.sismember(&key, utf8(&rest[0])?).map_err(error)? as i64))
+        }
+        "SMEMBERS" => {
+            arity(name, args, 1, 1)?;
+            let members = engine.smembers(&key).map_err(error)?;
+            Ok(Reply::Array(members.into_iter().map(Reply::bulk).collect()))
+        }
+        "SCARD" => {
+            arity(name, args, 1, 1)?;
+            Ok(count(engine.scard(&key).map_err(error)?))
+        }
+
+        // ---------- Sorted sets ----------
+        "ZADD" => {
+            if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                return Err(wrong_arity(name));
+            }
+            let members = rest
+                .chunks(2)
+                .map(|pair| Ok((parse_score(&pair[0])?, utf8(&pair[1])?.to_string())))
+                .collect::<Result<Vec<_>, Reply>>()?;
+            Ok(count(engine.zadd(&key, members).map_err(error)?))
+        }
+        "ZINCRBY" => {
+            arity(name, args, 3, 3)?;
+            let score = engine
+                .zincrby(&key, utf8(&rest[1])?, parse_score(&rest[0])?)
+                .map_err(error)?;
+            Ok(score_reply(score))
+        }
+        "ZREM" => {
+            arity(name, args, 2, usize::MAX)?;
+            Ok(count(engine.zrem(&key, &strings(rest)?).map_err(error)?))
+        }
+        "ZSCORE" => {
+            arity(name, args, 2, 2)?;
+            let score = engine.zscore(&key, utf8(&rest[0])?).map_err(error)?;
+            Ok(score.map(score_reply).unwrap_or(Reply::Nil))
+        }
+        "ZRANK" | "ZREVRANK" => {
+            arity(name, args, 2, 2)?;
+            let rank = engine
+                .zrank(&key, utf8(&rest[0])?, name == "ZREVRANK")
+                .map_err(error)?;
+            Ok(rank.map(|r| Reply::Integer(r as i64)).unwrap_or(Reply::Nil))
+        }
+        "ZCARD" => {
+            arity(name, args, 1, 1)?;
+            Ok(count(engine.zcard(&key).map_err(error)?))
+        }
+        "ZRANGE" | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE" => zrange(engine, name, args),
+
+        _ => Err(Reply::error(format!("ERR unknown command '{}'", name.to_lowercase()))),
+    }
+}
+
+/// ZRANGE key start stop [BYSCORE] [REV] [LIMIT offset count] [WITHSCORES]
+///
+/// Also serves ZREVRANGE, ZRANGEBYSCORE and ZREVRANGEBYSCORE, which imply the
+/// REV and/or BYSCORE options.
+fn zrange(engine: &KvEngine, name: &str, args: &[Vec<u8>]) -> CommandResult {
+    if args.len() < 3 {
+        return Err(wrong_arity(name));
+    }
+    let key = key(&args[0])?;
+    let mut by_score = name.ends_with("BYSCORE");
+    let mut rev = name.starts_with("ZREV");
+    let mut with_scores = false;
+    let mut limit: Option<(usize, Option<usize>)> = None;
+
+    let mut i = 3;
+    while i < args.len() {
+        let option = String::from_utf8_lossy(&args[i]).to_ascii_uppercase();
+        match option.as_str() {
+            "WITHSCORES" => with_scores = true,
+            "BYSCORE" if name == "ZRANGE" => by_score = true,
+            "REV" if name == "ZRANGE" => rev = true,
+            "LIMIT" if i + 2 < args.len() => {
+                let offset = usize::try_from(parse_int(&args[i + 1])?).unwrap_or(usize::MAX);
+                // A negative count means no limit
+                let count = usize::try_from(parse_int(&args[i + 2])?).ok();
+                limit = Some((offset, count));
+                i += 2;
+            }
+            _ => return Err(Reply::error("ERR syntax error")),
+        }
+        i += 1;
+    }
+
+    let entries = if by_score {
+        // Reversed ranges list the upper bound first
+        let (min, max) = if rev { (&args[2], &args[1]) } else { (&args[1], &args[2]) };
+        let mut entries = engine
+            .zrangebyscore(&key, parse_bound(min)?, parse_bound(max)?)
+            .map_err(error)?;
+        if rev {
+            entries.reverse();
+        }
+        entries
+    } else {
+        if limit.is_some() {
+            return Err(Reply::error(
+                "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
+            ));
+        }
+        engine
+            .zrange(&key, parse_int(&args[1])?, parse_int(&args[2])?, rev)
+            .map_err(error)?
+    };
+
+    let (offset, count) = limit.unwrap_or((0, None));
+    let mut items = Vec::new();
+    for (member, score) in entries.into_iter().skip(offset).take(count.unwrap_or(usize::MAX)) {
+        items.push(Reply::bulk(member));
+        if with_scores {
+            items.push(score_reply(score));
+        }
+    }
+    Ok(Reply::Array(items))
+}
+
+/// Parse a score; accepts `inf`, `+inf` and `-inf`
+fn parse_score(arg: &[u8]) -> Result<f64, Reply> {
+    utf8(arg)?
+        .parse::<f64>()
+        .ok()
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| Reply::error("ERR value is not a valid float"))
+}
+
+/// Parse a ZRANGEBYSCORE bound; a leading `(` makes it exclusive
+fn parse_bound(arg: &[u8]) -> Result<Bound<f64>, Reply> {
+    let invalid = || Reply::error("ERR min or max is not a float");
+    let (exclusive, score) = match arg.strip_prefix(b"(") {
+        Some(rest) => (true, rest),
+        None => (false, arg),
+    };
+    let score = parse_score(score).map_err(|_| invalid())?;
+    Ok(match (score, exclusive) {
+        (s, _) if s.is_infinite() => Bound::Unbounded,
+        (s, true) => Bound::Excluded(s),
+        (s, false) => Bound::Included(s),
+    })
+}
+
+fn score_reply(score: f64) -> Reply {
+    Reply::bulk(score.to_string())
+}
+
+/// Map an engine error onto the Redis error for it
+fn error(e: KvError) -> Reply {
+    match e {
+        KvError::TypeMismatch { expected, .. } if expected == "int" => {
+            Reply::error("ERR hash value is not an integer")
+        }
+        KvError::TypeMismatch { .. } => {
+            Reply::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        }
+        KvError::InvalidArgument(msg) => Reply::error(format!("ERR {}", msg)),
+        e => Reply::error(format!("ERR {}", e)),
+    }
+}
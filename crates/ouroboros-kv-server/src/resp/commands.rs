@@ -0,0 +1,1137 @@
+//! RESP command table
+//!
+//! Maps Redis commands onto `KvEngine`. Values written through RESP are
+//! stored as `Int` when they are the canonical form of an integer (so `INCR`
+//! works on them, like Redis' integer encoding), as `String` when they are
+//! valid UTF-8 and as `Bytes` otherwise.
+
+use super::codec::{Protocol, Reply};
+use super::collections;
+use super::multi::{self, MultiState};
+use crate::auth::{require_user, Acl, Category, Denied, User};
+use crate::pubsub::{PubSub, Subscriber};
+use crate::replication::ReplicaState;
+use ouroboros_kv::{KvEngine, KvError, KvKey, KvValue, SetCondition};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Error reply or success reply; lets handlers use `?` on argument parsing
+pub(super) type CommandResult = Result<Reply, Reply>;
+
+/// Keys scanned and deleted per page by FLUSHDB
+const FLUSH_BATCH_SIZE: usize = 1000;
+
+/// Default SCAN count, as in Redis
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// State shared by every connection
+pub struct Context<'a> {
+    pub engine: &'a KvEngine,
+    pub pubsub: &'a Arc<PubSub>,
+    pub replica: Option<&'a ReplicaState>,
+    pub acl: &'a Acl,
+}
+
+/// Per-connection state
+pub struct Session {
+    pub id: u64,
+    pub protocol: Protocol,
+    pub name: Option<String>,
+    /// Authenticated user; None until AUTH if the default user needs a password
+    pub user: Option<Arc<User>>,
+    pub subscriber: Option<Subscriber>,
+    /// MULTI queue and WATCHed keys
+    pub multi: MultiState,
+    /// Set by QUIT: close once the reply is written
+    pub closing: bool,
+}
+
+impl Session {
+    pub fn new(id: u64, user: Option<Arc<User>>) -> Self {
+        Self {
+            id,
+            protocol: Protocol::Resp2,
+            name: None,
+            user,
+            subscriber: None,
+            multi: MultiState::default(),
+            closing: false,
+        }
+    }
+
+    /// RESP2 connections with subscriptions only accept pub/sub commands
+    fn in_subscribe_mode(&self) -> bool {
+        self.protocol == Protocol::Resp2 && self.subscriber.as_ref().map(|s| s.count() > 0).unwrap_or(false)
+    }
+}
+
+/// Execute one request, returning the replies to send (several for multi-channel SUBSCRIBE)
+pub fn execute(ctx: &Context<'_>, session: &mut Session, args: &[Vec<u8>]) -> Vec<Reply> {
+    let Some(name) = args.first() else {
+        return Vec::new();
+    };
+    let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+    let args = &args[1..];
+
+    if let Err(denied) = authorize(session, &name, args) {
+        if session.multi.queued.is_some() {
+            session.multi.failed = true;
+        }
+        return vec![Reply::error(denied.to_string())];
+    }
+
+    if session.in_subscribe_mode()
+        && !matches!(
+            name.as_str(),
+            "SUBSCRIBE" | "UNSUBSCRIBE" | "PSUBSCRIBE" | "PUNSUBSCRIBE" | "PING" | "QUIT" | "RESET"
+        )
+    {
+        return vec![Reply::error(format!(
+            "ERR Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+            name.to_lowercase()
+        ))];
+    }
+
+    if session.multi.queued.is_some()
+        && !matches!(name.as_str(), "EXEC" | "DISCARD" | "MULTI" | "WATCH" | "QUIT" | "RESET")
+    {
+        return vec![queue(ctx, session, &name, args)];
+    }
+
+    if ctx.replica.is_some() && is_write(&name) {
+        return vec![Reply::error("READONLY You can't write against a read only replica.")];
+    }
+
+    match name.as_str() {
+        "SUBSCRIBE" | "UNSUBSCRIBE" | "PSUBSCRIBE" | "PUNSUBSCRIBE" => {
+            match subscription(ctx, session, &name, args) {
+                Ok(replies) => replies,
+                Err(reply) => vec![reply],
+            }
+        }
+        _ => vec![dispatch(ctx, session, &name, args).unwrap_or_else(|e| e)],
+    }
+}
+
+/// Queue a command inside MULTI
+fn queue(ctx: &Context<'_>, session: &mut Session, name: &str, args: &[Vec<u8>]) -> Reply {
+    let queued = if ctx.replica.is_some() && is_write(name) {
+        Err(Reply::error("READONLY You can't write against a read only replica."))
+    } else {
+        multi::queue(name, args)
+    };
+
+    match queued {
+        Ok(command) => {
+            session.multi.queued.get_or_insert_with(Vec::new).push(command);
+            Reply::Simple("QUEUED".to_string())
+        }
+        Err(reply) => {
+            session.multi.failed = true;
+            reply
+        }
+    }
+}
+
+/// ACL category of a command
+fn category(name: &str) -> Category {
+    match name {
+        "PING" | "ECHO" | "HELLO" | "AUTH" | "SELECT" | "QUIT" | "RESET" | "CLIENT" | "COMMAND" | "ACL" => {
+            Category::Connection
+        }
+        "MULTI" | "EXEC" | "DISCARD" | "WATCH" | "UNWATCH" => Category::Transaction,
+        "PUBLISH" | "SUBSCRIBE" | "UNSUBSCRIBE" | "PSUBSCRIBE" | "PUNSUBSCRIBE" => Category::PubSub,
+        "INFO" | "DBSIZE" | "FLUSHDB" | "FLUSHALL" => Category::Admin,
+        _ if is_write(name) => Category::Write,
+        _ => Category::Read,
+    }
+}
+
+/// Check that the session's user may run a command on the keys and channels it names
+///
+/// Queued commands are checked when they are queued, like in Redis.
+fn authorize(session: &Session, name: &str, args: &[Vec<u8>]) -> Result<(), Denied> {
+    let category = category(name);
+    if category == Category::Connection {
+        return Ok(());
+    }
+    let user = require_user(&session.user)?;
+    user.check_command(&name.to_ascii_lowercase(), category)?;
+
+    let text = |arg: &Vec<u8>| String::from_utf8_lossy(arg).into_owned();
+    match name {
+        "PUBLISH" => args.first().map_or(Ok(()), |channel| user.check_channel(&text(channel))),
+        "SUBSCRIBE" => args.iter().try_for_each(|channel| user.check_channel(&text(channel))),
+        "PSUBSCRIBE" => args.iter().try_for_each(|pattern| user.check_channel_pattern(&text(pattern))),
+        _ if user.all_keys() => Ok(()),
+        "FLUSHDB" | "FLUSHALL" => user.check_key_pattern("*"),
+        "KEYS" => args.first().map_or(Ok(()), |pattern| user.check_key_pattern(&text(pattern))),
+        "SCAN" => {
+            let pattern = args
+                .iter()
+                .position(|arg| arg.eq_ignore_ascii_case(b"MATCH"))
+                .and_then(|i| args.get(i + 1))
+                .map(text);
+            user.check_key_pattern(pattern.as_deref().unwrap_or("*"))
+        }
+        "DEL" | "UNLINK" | "EXISTS" | "MGET" | "WATCH" => args.iter().try_for_each(|key| user.check_key(&text(key))),
+        "MSET" => args.iter().step_by(2).try_for_each(|key| user.check_key(&text(key))),
+        "MULTI" | "EXEC" | "DISCARD" | "UNWATCH" | "INFO" | "DBSIZE" | "UNSUBSCRIBE" | "PUNSUBSCRIBE" => Ok(()),
+        // Every other command takes a single key first
+        _ => args.first().map_or(Ok(()), |key| user.check_key(&text(key))),
+    }
+}
+
+/// Commands that modify the keyspace
+fn is_write(name: &str) -> bool {
+    matches!(
+        name,
+        "SET" | "SETNX" | "SETEX" | "PSETEX" | "GETSET" | "GETDEL" | "MSET" | "DEL" | "UNLINK"
+            | "INCR" | "DECR" | "INCRBY" | "DECRBY" | "EXPIRE" | "PEXPIRE" | "PERSIST"
+            | "FLUSHDB" | "FLUSHALL"
+    ) || collections::is_write(name)
+}
+
+fn dispatch(ctx: &Context<'_>, session: &mut Session, name: &str, args: &[Vec<u8>]) -> CommandResult {
+    let engine = ctx.engine;
+
+    match name {
+        // ---------- Connection ----------
+        "PING" => {
+            arity(name, args, 0, 1)?;
+            let message = args.first().cloned();
+            if session.in_subscribe_mode() {
+                return Ok(Reply::Array(vec![Reply::bulk("pong"), Reply::Bulk(message.unwrap_or_default())]));
+            }
+            Ok(match message {
+                Some(message) => Reply::Bulk(message),
+                None => Reply::Simple("PONG".to_string()),
+            })
+        }
+        "ECHO" => {
+            arity(name, args, 1, 1)?;
+            Ok(Reply::Bulk(args[0].clone()))
+        }
+        "HELLO" => hello(ctx, session, args),
+        "AUTH" => {
+            arity(name, args, 1, 2)?;
+            let (user, password) = match args {
+                [password] => (None, utf8(password)?),
+                [user, password] => (Some(utf8(user)?), utf8(password)?),
+                _ => unreachable!(),
+            };
+            session.user = Some(ctx.acl.authenticate(user, password).map_err(|e| Reply::error(e.to_string()))?);
+            Ok(Reply::ok())
+        }
+        "ACL" => match args.first().map(|a| String::from_utf8_lossy(a).to_ascii_uppercase()).as_deref() {
+            Some("WHOAMI") => Ok(session.user.as_ref().map(|u| Reply::bulk(u.name())).unwrap_or(Reply::Nil)),
+            _ => Err(Reply::error("ERR only ACL WHOAMI is supported")),
+        },
+        "SELECT" => {
+            arity(name, args, 1, 1)?;
+            match parse_int(&args[0])? {
+                0 => Ok(Reply::ok()),
+                _ => Err(Reply::error("ERR DB index is out of range")),
+            }
+        }
+        "QUIT" => {
+            session.closing = true;
+            Ok(Reply::ok())
+        }
+        "RESET" => {
+            session.multi.reset();
+            if let Some(mut subscriber) = session.subscriber.take() {
+                subscriber.unsubscribe(&[]);
+                subscriber.punsubscribe(&[]);
+            }
+            session.protocol = Protocol::Resp2;
+            session.name = None;
+            session.user = ctx.acl.default_user();
+            Ok(Reply::Simple("RESET".to_string()))
+        }
+        "CLIENT" => client(session, args),
+        "COMMAND" => {
+            // Clients probe this on connect; command metadata isn't provided
+            match args.first().map(|a| String::from_utf8_lossy(a).to_ascii_uppercase()).as_deref() {
+                Some("COUNT") => Ok(Reply::Integer(0)),
+                Some("DOCS") => Ok(Reply::Map(Vec::new())),
+                _ => Ok(Reply::Array(Vec::new())),
+            }
+        }
+
+        // ---------- Transactions ----------
+        "MULTI" => {
+            arity(name, args, 0, 0)?;
+            if session.multi.queued.is_some() {
+                return Err(Reply::error("ERR MULTI calls can not be nested"));
+            }
+            session.multi.queued = Some(Vec::new());
+            Ok(Reply::ok())
+        }
+        "EXEC" => {
+            arity(name, args, 0, 0)?;
+            let Some(queued) = session.multi.queued.take() else {
+                return Err(Reply::error("ERR EXEC without MULTI"));
+            };
+            let failed = session.multi.failed;
+            let watched = std::mem::take(&mut session.multi.watched);
+            session.multi.reset();
+            if failed {
+                return Err(Reply::error("EXECABORT Transaction discarded because of previous errors."));
+            }
+            Ok(multi::exec(engine, queued, &watched))
+        }
+        "DISCARD" => {
+            arity(name, args, 0, 0)?;
+            if session.multi.queued.is_none() {
+                return Err(Reply::error("ERR DISCARD without MULTI"));
+            }
+            session.multi.reset();
+            Ok(Reply::ok())
+        }
+        "WATCH" => {
+            arity(name, args, 1, usize::MAX)?;
+            if session.multi.queued.is_some() {
+                return Err(Reply::error("ERR WATCH inside MULTI is not allowed"));
+            }
+            for key in keys(args)? {
+                let version = engine.version(&key);
+                session.multi.watched.push((key.as_str().to_string(), version));
+            }
+            Ok(Reply::ok())
+        }
+        "UNWATCH" => {
+            session.multi.watched.clear();
+            Ok(Reply::ok())
+        }
+
+        // ---------- Server ----------
+        "INFO" => Ok(Reply::Bulk(info(ctx).into_bytes())),
+        "DBSIZE" => Ok(Reply::Integer(engine.len() as i64)),
+        "FLUSHDB" | "FLUSHALL" => {
+            arity(name, args, 0, 1)?;
+            // Delete through the engine so the WAL and replicas see it, a page
+            // at a time so the keyspace is never held in memory
+            let mut cursor = 0;
+            loop {
+                let (next, page) = engine.scan(cursor, None, FLUSH_BATCH_SIZE);
+                let page: Vec<KvKey> = page.into_iter().filter_map(|k| KvKey::new(k).ok()).collect();
+                let refs: Vec<&KvKey> = page.iter().collect();
+                engine.mdel(&refs);
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+            Ok(Reply::ok())
+        }
+
+        // ---------- Strings ----------
+        "GET" => {
+            arity(name, args, 1, 1)?;
+            Ok(value_reply(engine.get(&key(&args[0])?)))
+        }
+        "SET" => set(engine, args),
+        "SETNX" => {
+            arity(name, args, 2, 2)?;
+            let stored = engine.setnx(&key(&args[0])?, value_from_bytes(&args[1]), None);
+            Ok(Reply::Integer(stored as i64))
+        }
+        "SETEX" | "PSETEX" => {
+            arity(name, args, 3, 3)?;
+            let amount = parse_int(&args[1])?;
+            let ttl = if name == "SETEX" {
+                Duration::from_secs(amount.max(0) as u64)
+            } else {
+                Duration::from_millis(amount.max(0) as u64)
+            };
+            let ttl = checked_ttl(ttl).ok_or_else(|| invalid_expire_time(name))?;
+            engine.set(&key(&args[0])?, value_from_bytes(&args[2]), Some(ttl));
+            Ok(Reply::ok())
+        }
+        "GETSET" => {
+            arity(name, args, 2, 2)?;
+            Ok(value_reply(engine.get_and_set(&key(&args[0])?, value_from_bytes(&args[1]), None)))
+        }
+        "GETDEL" => {
+            arity(name, args, 1, 1)?;
+            Ok(value_reply(engine.remove_returning(&key(&args[0])?)))
+        }
+        "MGET" => {
+            arity(name, args, 1, usize::MAX)?;
+            let keys = keys(args)?;
+            let refs: Vec<&KvKey> = keys.iter().collect();
+            Ok(Reply::Array(engine.mget(&refs).into_iter().map(value_reply).collect()))
+        }
+        "MSET" => {
+            if args.is_empty() || !args.len().is_multiple_of(2) {
+                return Err(wrong_arity(name));
+            }
+            let pairs = args
+                .chunks(2)
+                .map(|pair| Ok((key(&pair[0])?, value_from_bytes(&pair[1]))))
+                .collect::<Result<Vec<_>, Reply>>()?;
+            let refs: Vec<(&KvKey, KvValue)> = pairs.iter().map(|(k, v)| (k, v.clone())).collect();
+            engine.mset(&refs, None);
+            Ok(Reply::ok())
+        }
+        "INCR" | "DECR" => {
+            arity(name, args, 1, 1)?;
+            let delta = if name == "INCR" { 1 } else { -1 };
+            incr(engine, &args[0], delta)
+        }
+        "INCRBY" | "DECRBY" => {
+            arity(name, args, 2, 2)?;
+            let delta = parse_int(&args[1])?;
+            let delta = if name == "INCRBY" { delta } else { delta.checked_neg().ok_or_else(not_an_integer)? };
+            incr(engine, &args[0], delta)
+        }
+
+        // ---------- Keys ----------
+        "DEL" | "UNLINK" => {
+            arity(name, args, 1, usize::MAX)?;
+            let keys = keys(args)?;
+            let refs: Vec<&KvKey> = keys.iter().collect();
+            Ok(Reply::Integer(engine.mdel(&refs) as i64))
+        }
+        "EXISTS" => {
+            arity(name, args, 1, usize::MAX)?;
+            let keys = keys(args)?;
+            Ok(Reply::Integer(keys.iter().filter(|k| engine.exists(k)).count() as i64))
+        }
+        "EXPIRE" | "PEXPIRE" => {
+            arity(name, args, 2, 2)?;
+            let key = key(&args[0])?;
+            let amount = parse_int(&args[1])?;
+            if amount <= 0 {
+                // A TTL in the past deletes the key
+                return Ok(Reply::Integer(engine.delete(&key) as i64));
+            }
+            let ttl = if name == "EXPIRE" {
+                Duration::from_secs(amount as u64)
+            } else {
+                Duration::from_millis(amount as u64)
+            };
+            let ttl = checked_ttl(ttl).ok_or_else(|| invalid_expire_time(name))?;
+            Ok(Reply::Integer(engine.expire(&key, Some(ttl)) as i64))
+        }
+        "TTL" | "PTTL" => {
+            arity(name, args, 1, 1)?;
+            Ok(Reply::Integer(match engine.ttl(&key(&args[0])?) {
+                None => -2,
+                Some(None) => -1,
+                Some(Some(ttl)) if name == "TTL" => ((ttl.as_millis() + 500) / 1000) as i64,
+                Some(Some(ttl)) => ttl.as_millis() as i64,
+            }))
+        }
+        "PERSIST" => {
+            arity(name, args, 1, 1)?;
+            Ok(Reply::Integer(engine.persist(&key(&args[0])?) as i64))
+        }
+        "TYPE" => {
+            arity(name, args, 1, 1)?;
+            // Peek so that inspecting a cold key does not load it into memory
+            let kind = match engine.peek(&key(&args[0])?) {
+                None => "none",
+                Some(KvValue::List(_)) => "list",
+                Some(KvValue::Map(_)) => "hash",
+                Some(KvValue::Set(_)) => "set",
+                Some(KvValue::SortedSet(_)) => "zset",
+                Some(_) => "string",
+            };
+            Ok(Reply::Simple(kind.to_string()))
+        }
+        "KEYS" => {
+            arity(name, args, 1, 1)?;
+            let pattern = utf8(&args[0])?;
+            Ok(Reply::Array(engine.keys(pattern).into_iter().map(Reply::bulk).collect()))
+        }
+        "SCAN" => scan(engine, args),
+
+        // ---------- Pub/Sub ----------
+        "PUBLISH" => {
+            arity(name, args, 2, 2)?;
+            Ok(Reply::Integer(ctx.pubsub.publish(utf8(&args[0])?, &args[1]) as i64))
+        }
+
+        _ if collections::is_command(name) => collections::execute(engine, name, args),
+
+        _ => Err(Reply::error(format!(
+            "ERR unknown command '{}', with args beginning with: {}",
+            name.to_lowercase(),
+            args.iter()
+                .take(3)
+                .map(|a| format!("'{}'", String::from_utf8_lossy(a)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))),
+    }
+}
+
+// ==================== Command implementations ====================
+
+/// HELLO [protover [AUTH username password] [SETNAME clientname]]
+fn hello(ctx: &Context<'_>, session: &mut Session, args: &[Vec<u8>]) -> CommandResult {
+    let mut protocol = session.protocol;
+    let mut name = None;
+    let mut user = None;
+
+    if let Some(version) = args.first() {
+        protocol = match parse_int(version) {
+            Ok(2) => Protocol::Resp2,
+            Ok(3) => Protocol::Resp3,
+            _ => return Err(Reply::error("NOPROTO unsupported protocol version")),
+        };
+    }
+
+    let mut i = 1;
+    while i < args.len() {
+        match String::from_utf8_lossy(&args[i]).to_ascii_uppercase().as_str() {
+            "AUTH" if i + 2 < args.len() => {
+                let authenticated = ctx.acl.authenticate(Some(utf8(&args[i + 1])?), utf8(&args[i + 2])?);
+                user = Some(authenticated.map_err(|e| Reply::error(e.to_string()))?);
+                i += 3;
+            }
+            "SETNAME" if i + 1 < args.len() => {
+                name = Some(utf8(&args[i + 1])?.to_string());
+                i += 2;
+            }
+            _ => return Err(Reply::error("ERR syntax error")),
+        }
+    }
+
+    if user.is_none() && session.user.is_none() {
+        return Err(Reply::error(
+            "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time",
+        ));
+    }
+
+    session.protocol = protocol;
+    if name.is_some() {
+        session.name = name;
+    }
+    if user.is_some() {
+        session.user = user;
+    }
+
+    let proto = if protocol == Protocol::Resp3 { 3 } else { 2 };
+    Ok(Reply::Map(vec![
+        (Reply::bulk("server"), Reply::bulk("ouroboros-kv")),
+        (Reply::bulk("version"), Reply::bulk(env!("CARGO_PKG_VERSION"))),
+        (Reply::bulk("proto"), Reply::Integer(proto)),
+        (Reply::bulk("id"), Reply::Integer(session.id as i64)),
+        (Reply::bulk("mode"), Reply::bulk("standalone")),
+        (Reply::bulk("role"), Reply::bulk(role(ctx))),
+        (Reply::bulk("modules"), Reply::Array(Vec::new())),
+    ]))
+}
+
+/// CLIENT SETNAME/GETNAME/ID/SETINFO
+fn client(session: &mut Session, args: &[Vec<u8>]) -> CommandResult {
+    let sub = args
+        .first()
+        .map(|a| String::from_utf8_lossy(a).to_ascii_uppercase())
+        .unwrap_or_default();
+
+    match sub.as_str() {
+        "SETNAME" if args.len() == 2 => {
+            let name = utf8(&args[1])?;
+            if name.contains(' ') {
+                return Err(Reply::error("ERR Client names cannot contain spaces, newlines or special characters."));
+            }
+            session.name = if name.is_empty() { None } else { Some(name.to_string()) };
+            Ok(Reply::ok())
+        }
+        "GETNAME" => Ok(session.name.clone().map(Reply::bulk).unwrap_or(Reply::Nil)),
+        "ID" => Ok(Reply::Integer(session.id as i64)),
+        // Library name/version sent by newer clients on connect
+        "SETINFO" => Ok(Reply::ok()),
+        _ => Err(Reply::error(format!(
+            "ERR unknown subcommand '{}'. Try CLIENT HELP.",
+            sub.to_lowercase()
+        ))),
+    }
+}
+
+/// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
+///
+/// The condition check, GET and KEEPTTL happen atomically with the write
+/// (see [`KvEngine::set_if`]).
+fn set(engine: &KvEngine, args: &[Vec<u8>]) -> CommandResult {
+    if args.len() < 2 {
+        return Err(wrong_arity("SET"));
+    }
+    let key = key(&args[0])?;
+    let value = value_from_bytes(&args[1]);
+
+    let (mut nx, mut xx, mut get, mut keep_ttl) = (false, false, false, false);
+    let mut ttl: Option<Duration> = None;
+    let mut i = 2;
+    while i < args.len() {
+        let option = String::from_utf8_lossy(&args[i]).to_ascii_uppercase();
+        match option.as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "GET" => get = true,
+            "KEEPTTL" => keep_ttl = true,
+            "EX" | "PX" | "EXAT" | "PXAT" if i + 1 < args.len() && ttl.is_none() => {
+                let amount = parse_int(&args[i + 1])?;
+                ttl = Some(expire_duration(&option, amount).ok_or_else(|| invalid_expire_time("SET"))?);
+                i += 1;
+            }
+            _ => return Err(Reply::error("ERR syntax error")),
+        }
+        i += 1;
+    }
+    if (nx && xx) || (keep_ttl && ttl.is_some()) {
+        return Err(Reply::error("ERR syntax error"));
+    }
+
+    let condition = if nx {
+        SetCondition::IfAbsent
+    } else if xx {
+        SetCondition::IfPresent
+    } else {
+        SetCondition::Always
+    };
+    let (stored, old) = engine.set_if(&key, value, ttl, condition, keep_ttl);
+
+    Ok(match (get, stored) {
+        (true, _) => value_reply(old),
+        (false, true) => Reply::ok(),
+        (false, false) => Reply::Nil,
+    })
+}
+
+/// Error for an expire time that is not in the future or out of range
+pub(super) fn invalid_expire_time(name: &str) -> Reply {
+    Reply::error(format!("ERR invalid expire time in '{}' command", name.to_lowercase()))
+}
+
+/// Returns `ttl` if it is positive and its expiry can be represented
+///
+/// Checked before anything is written, so the WAL never holds an expiry
+/// that the engine or a snapshot would overflow on.
+pub(super) fn checked_ttl(ttl: Duration) -> Option<Duration> {
+    let expires_at = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.checked_add(ttl)?;
+    let representable = i64::try_from(expires_at.as_nanos()).is_ok() && Instant::now().checked_add(ttl).is_some();
+    (!ttl.is_zero() && representable).then_some(ttl)
+}
+
+/// TTL for an EX/PX/EXAT/PXAT option (None if it is not in the future or out of range)
+pub(super) fn expire_duration(option: &str, amount: i64) -> Option<Duration> {
+    if amount <= 0 {
+        return None;
+    }
+    let amount = amount as u64;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+    let ttl = match option {
+        "EX" => Duration::from_secs(amount),
+        "PX" => Duration::from_millis(amount),
+        "EXAT" => Duration::from_millis(amount.checked_mul(1000)?.checked_sub(now_ms)?),
+        _ => Duration::from_millis(amount.checked_sub(now_ms)?),
+    };
+    checked_ttl(ttl)
+}
+
+fn incr(engine: &KvEngine, key_arg: &[u8], delta: i64) -> CommandResult {
+    match engine.incr(&key(key_arg)?, delta) {
+        Ok(value) => Ok(Reply::Integer(value)),
+        Err(KvError::TypeMismatch { .. }) => Err(not_an_integer()),
+        Err(e) => Err(Reply::error(format!("ERR {}", e))),
+    }
+}
+
+/// SCAN cursor [MATCH pattern] [COUNT count]
+fn scan(engine: &KvEngine, args: &[Vec<u8>]) -> CommandResult {
+    if args.is_empty() {
+        return Err(wrong_arity("SCAN"));
+    }
+    let cursor = utf8(&args[0])?
+        .parse::<u64>()
+        .map_err(|_| Reply::error("ERR invalid cursor"))?;
+
+    let mut pattern = None;
+    let mut count = DEFAULT_SCAN_COUNT;
+    let mut i = 1;
+    while i + 1 < args.len() {
+        match String::from_utf8_lossy(&args[i]).to_ascii_uppercase().as_str() {
+            "MATCH" => pattern = Some(utf8(&args[i + 1])?),
+            "COUNT" => {
+                let n = parse_int(&args[i + 1])?;
+                if n < 1 {
+                    return Err(Reply::error("ERR syntax error"));
+                }
+                count = n as usize;
+            }
+            _ => return Err(Reply::error("ERR syntax error")),
+        }
+        i += 2;
+    }
+    if i != args.len() {
+        return Err(Reply::error("ERR syntax error"));
+    }
+
+    let (next, keys) = engine.scan(cursor, pattern, count);
+    Ok(Reply::Array(vec![
+        Reply::bulk(next.to_string()),
+        Reply::Array(keys.into_iter().map(Reply::bulk).collect()),
+    ]))
+}
+
+/// (P)SUBSCRIBE / (P)UNSUBSCRIBE: one confirmation per channel
+fn subscription(
+    ctx: &Context<'_>,
+    session: &mut Session,
+    name: &str,
+    args: &[Vec<u8>],
+) -> Result<Vec<Reply>, Reply> {
+    if args.is_empty() && matches!(name, "SUBSCRIBE" | "PSUBSCRIBE") {
+        return Err(wrong_arity(name));
+    }
+    let mut names = args
+        .iter()
+        .map(|a| utf8(a).map(str::to_string))
+        .collect::<Result<Vec<_>, Reply>>()?;
+
+    let subscriber = session.subscriber.get_or_insert_with(|| ctx.pubsub.subscriber());
+    if names.is_empty() {
+        names = if name == "UNSUBSCRIBE" { subscriber.channels() } else { subscriber.patterns() };
+    }
+
+    let kind = name.to_lowercase();
+    if names.is_empty() {
+        // Unsubscribing from nothing still gets one confirmation
+        return Ok(vec![Reply::Push(vec![
+            Reply::bulk(kind),
+            Reply::Nil,
+            Reply::Integer(subscriber.count() as i64),
+        ])]);
+    }
+
+    Ok(names
+        .into_iter()
+        .map(|channel| {
+            let one = std::slice::from_ref(&channel);
+            let count = match name {
+                "SUBSCRIBE" => subscriber.subscribe(one),
+                "UNSUBSCRIBE" => subscriber.unsubscribe(one),
+                "PSUBSCRIBE" => subscriber.psubscribe(one),
+                _ => subscriber.punsubscribe(one),
+            };
+            Reply::Push(vec![Reply::bulk(kind.clone()), Reply::bulk(channel), Reply::Integer(count as i64)])
+        })
+        .collect())
+}
+
+fn info(ctx: &Context<'_>) -> String {
+    let engine = ctx.engine;
+    let mut info = format!(
+        "# Server\r\nredis_version:7.0.0\r\nouroboros_kv_version:{}\r\nredis_mode:standalone\r\n\r\n\
+         # Memory\r\nused_memory:{}\r\nevicted_keys:{}\r\n\r\n\
+         # Replication\r\nrole:{}\r\n",
+        env!("CARGO_PKG_VERSION"),
+        engine.memory_usage(),
+        engine.evictions(),
+        role(ctx)
+    );
+    if let Some(replica) = ctx.replica {
+        let (host, port) = replica.primary().rsplit_once(':').unwrap_or((replica.primary(), ""));
+        info.push_str(&format!(
+            "master_host:{}\r\nmaster_port:{}\r\nmaster_link_status:{}\r\nmaster_repl_offset:{}\r\n",
+            host,
+            port,
+            if replica.link_up() { "up" } else { "down" },
+            replica.offset()
+        ));
+    } else if let Some(log) = engine.replication_log() {
+        info.push_str(&format!("master_replid:{}\r\nmaster_repl_offset:{}\r\n", log.id(), log.offset()));
+    }
+    info.push_str(&format!("\r\n# Keyspace\r\ndb0:keys={}\r\n", engine.len()));
+    info
+}
+
+/// Redis name for this server's role
+fn role(ctx: &Context<'_>) -> &'static str {
+    if ctx.replica.is_some() {
+        "slave"
+    } else {
+        "master"
+    }
+}
+
+// ==================== Argument and value helpers ====================
+
+pub(super) fn arity(name: &str, args: &[Vec<u8>], min: usize, max: usize) -> Result<(), Reply> {
+    if args.len() < min || args.len() > max {
+        return Err(wrong_arity(name));
+    }
+    Ok(())
+}
+
+pub(super) fn wrong_arity(name: &str) -> Reply {
+    Reply::error(format!("ERR wrong number of arguments for '{}' command", name.to_lowercase()))
+}
+
+fn not_an_integer() -> Reply {
+    Reply::error("ERR value is not an integer or out of range")
+}
+
+pub(super) fn utf8(arg: &[u8]) -> Result<&str, Reply> {
+    std::str::from_utf8(arg).map_err(|_| Reply::error("ERR invalid UTF-8 argument"))
+}
+
+pub(super) fn parse_int(arg: &[u8]) -> Result<i64, Reply> {
+    utf8(arg)?.parse::<i64>().map_err(|_| not_an_integer())
+}
+
+pub(super) fn key(arg: &[u8]) -> Result<KvKey, Reply> {
+    KvKey::new(utf8(arg)?).map_err(|e| Reply::error(format!("ERR invalid key: {}", e)))
+}
+
+fn keys(args: &[Vec<u8>]) -> Result<Vec<KvKey>, Reply> {
+    args.iter().map(|a| key(a)).collect()
+}
+
+/// Store a RESP string as the most specific value it round-trips through
+pub(super) fn value_from_bytes(data: &[u8]) -> KvValue {
+    match std::str::from_utf8(data) {
+        Ok(s) => match s.parse::<i64>() {
+            Ok(n) if n.to_string() == s => KvValue::Int(n),
+            _ => KvValue::String(s.to_string()),
+        },
+        Err(_) => KvValue::Bytes(data.to_vec()),
+    }
+}
+
+/// Render a stored value as a bulk string
+///
+/// Lists and maps written through the native protocol have no RESP string
+/// form and get a WRONGTYPE error.
+pub(super) fn value_reply(value: Option<KvValue>) -> Reply {
+    match value {
+        None => Reply::Nil,
+        Some(KvValue::Int(n)) => Reply::bulk(n.to_string()),
+        Some(KvValue::Float(f)) => Reply::bulk(f.to_string()),
+        Some(KvValue::Decimal(d)) => Reply::bulk(d.to_string()),
+        Some(KvValue::String(s)) => Reply::bulk(s),
+        Some(KvValue::Bytes(b)) => Reply::Bulk(b),
+        Some(KvValue::Null) => Reply::bulk(""),
+        Some(KvValue::List(_)) | Some(KvValue::Map(_)) | Some(KvValue::Set(_)) | Some(KvValue::SortedSet(_)) => {
+            Reply::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_session(id: u64) -> Session {
+        Session::new(id, Acl::default().default_user())
+    }
+
+    fn run(engine: &KvEngine, session: &mut Session, command: &str) -> Reply {
+        run_as(engine, &Acl::default(), session, command)
+    }
+
+    fn run_as(engine: &KvEngine, acl: &Acl, session: &mut Session, command: &str) -> Reply {
+        let pubsub = Arc::new(PubSub::new());
+        let ctx = Context { engine, pubsub: &pubsub, replica: None, acl };
+        let args: Vec<Vec<u8>> = command.split(' ').map(|s| s.as_bytes().to_vec()).collect();
+        execute(&ctx, session, &args).remove(0)
+    }
+
+    #[test]
+    fn test_value_conversion() {
+        assert_eq!(value_from_bytes(b"42"), KvValue::Int(42));
+        assert_eq!(value_from_bytes(b"-7"), KvValue::Int(-7));
+        // Non-canonical integers keep their exact bytes
+        assert_eq!(value_from_bytes(b"007"), KvValue::String("007".to_string()));
+        assert_eq!(value_from_bytes(b"+1"), KvValue::String("+1".to_string()));
+        assert_eq!(value_from_bytes(&[0xff, 0x00]), KvValue::Bytes(vec![0xff, 0x00]));
+
+        assert_eq!(value_reply(Some(KvValue::Int(42))), Reply::bulk("42"));
+        assert_eq!(value_reply(None), Reply::Nil);
+        assert!(matches!(value_reply(Some(KvValue::List(vec![]))), Reply::Error(_)));
+    }
+
+    #[test]
+    fn test_string_commands() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+
+        assert_eq!(run(&engine, &mut session, "SET counter 10"), Reply::ok());
+        assert_eq!(run(&engine, &mut session, "INCRBY counter 5"), Reply::Integer(15));
+        assert_eq!(run(&engine, &mut session, "GET counter"), Reply::bulk("15"));
+        assert_eq!(run(&engine, &mut session, "SET counter 1 NX"), Reply::Nil);
+        assert_eq!(run(&engine, &mut session, "SET missing 1 XX"), Reply::Nil);
+        assert_eq!(run(&engine, &mut session, "SET counter 2 GET"), Reply::bulk("15"));
+        assert_eq!(run(&engine, &mut session, "SET name alice"), Reply::ok());
+        assert!(matches!(run(&engine, &mut session, "INCR name"), Reply::Error(e) if e.starts_with("ERR value")));
+        assert_eq!(
+            run(&engine, &mut session, "MGET counter name missing"),
+            Reply::Array(vec![Reply::bulk("2"), Reply::bulk("alice"), Reply::Nil])
+        );
+        assert_eq!(run(&engine, &mut session, "DEL counter name missing"), Reply::Integer(2));
+        assert_eq!(run(&engine, &mut session, "EXISTS counter"), Reply::Integer(0));
+        assert!(matches!(run(&engine, &mut session, "SET k v EX 0"), Reply::Error(_)));
+        assert!(matches!(run(&engine, &mut session, "GET"), Reply::Error(e) if e.contains("wrong number")));
+    }
+
+    #[test]
+    fn test_expiry_commands() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+
+        assert_eq!(run(&engine, &mut session, "TTL missing"), Reply::Integer(-2));
+        run(&engine, &mut session, "SET session abc");
+        assert_eq!(run(&engine, &mut session, "TTL session"), Reply::Integer(-1));
+        assert_eq!(run(&engine, &mut session, "EXPIRE session 100"), Reply::Integer(1));
+        assert_eq!(run(&engine, &mut session, "TTL session"), Reply::Integer(100));
+        assert_eq!(run(&engine, &mut session, "PERSIST session"), Reply::Integer(1));
+        assert_eq!(run(&engine, &mut session, "PERSIST session"), Reply::Integer(0));
+        assert_eq!(run(&engine, &mut session, "SETEX temp 50 x"), Reply::ok());
+        assert_eq!(run(&engine, &mut session, "SET temp y KEEPTTL"), Reply::ok());
+        assert_eq!(run(&engine, &mut session, "TTL temp"), Reply::Integer(50));
+        assert_eq!(run(&engine, &mut session, "EXPIRE temp -1"), Reply::Integer(1));
+        assert_eq!(run(&engine, &mut session, "GET temp"), Reply::Nil);
+    }
+
+    #[test]
+    fn test_out_of_range_expire_is_rejected() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+        let max = i64::MAX;
+
+        run(&engine, &mut session, "SET k old");
+        for command in [
+            format!("SETEX k {} v", max),
+            format!("PSETEX k {} v", max),
+            format!("SET k v EX {}", max),
+            format!("SET k v PX {}", max),
+            format!("EXPIRE k {}", max),
+            format!("PEXPIRE k {}", max),
+        ] {
+            let name = command.split(' ').next().unwrap().to_lowercase();
+            assert_eq!(
+                run(&engine, &mut session, &command),
+                Reply::error(format!("ERR invalid expire time in '{}' command", name)),
+                "{}",
+                command
+            );
+        }
+        assert_eq!(run(&engine, &mut session, "GET k"), Reply::bulk("old"));
+        assert_eq!(run(&engine, &mut session, "TTL k"), Reply::Integer(-1));
+
+        run(&engine, &mut session, "MULTI");
+        assert!(matches!(run(&engine, &mut session, &format!("SETEX k {} v", max)), Reply::Error(_)));
+        assert!(matches!(run(&engine, &mut session, "EXEC"), Reply::Error(e) if e.starts_with("EXECABORT")));
+        assert_eq!(run(&engine, &mut session, "GET k"), Reply::bulk("old"));
+    }
+
+    #[test]
+    fn test_flushdb_deletes_every_page() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+
+        for i in 0..FLUSH_BATCH_SIZE * 2 + 10 {
+            run(&engine, &mut session, &format!("SET key{} {}", i, i));
+        }
+        assert_eq!(run(&engine, &mut session, "FLUSHDB"), Reply::ok());
+        assert_eq!(run(&engine, &mut session, "DBSIZE"), Reply::Integer(0));
+    }
+
+    #[test]
+    fn test_collection_commands() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+
+        assert_eq!(run(&engine, &mut session, "HSET user:1 name alice visits 1"), Reply::Integer(2));
+        assert_eq!(run(&engine, &mut session, "HINCRBY user:1 visits 4"), Reply::Integer(5));
+        assert_eq!(run(&engine, &mut session, "HGET user:1 visits"), Reply::bulk("5"));
+        assert!(matches!(run(&engine, &mut session, "HINCRBY user:1 name 1"), Reply::Error(e) if e.contains("not an integer")));
+        assert_eq!(run(&engine, &mut session, "TYPE user:1"), Reply::Simple("hash".to_string()));
+
+        assert_eq!(run(&engine, &mut session, "RPUSH jobs a b c"), Reply::Integer(3));
+        assert_eq!(run(&engine, &mut session, "LPOP jobs"), Reply::bulk("a"));
+        assert_eq!(
+            run(&engine, &mut session, "RPOP jobs 5"),
+            Reply::Array(vec![Reply::bulk("c"), Reply::bulk("b")])
+        );
+        assert_eq!(run(&engine, &mut session, "LPOP jobs"), Reply::Nil);
+        assert_eq!(run(&engine, &mut session, "EXISTS jobs"), Reply::Integer(0));
+
+        assert_eq!(run(&engine, &mut session, "SADD tags x y x"), Reply::Integer(2));
+        assert_eq!(run(&engine, &mut session, "SISMEMBER tags y"), Reply::Integer(1));
+        assert!(matches!(run(&engine, &mut session, "GET tags"), Reply::Error(e) if e.starts_with("WRONGTYPE")));
+        assert!(matches!(run(&engine, &mut session, "LPUSH tags z"), Reply::Error(e) if e.starts_with("WRONGTYPE")));
+
+        assert_eq!(run(&engine, &mut session, "ZADD board 10 alice 30 bob 20 carol"), Reply::Integer(3));
+        assert_eq!(run(&engine, &mut session, "ZINCRBY board 2.5 alice"), Reply::bulk("12.5"));
+        assert_eq!(
+            run(&engine, &mut session, "ZREVRANGE board 0 1 WITHSCORES"),
+            Reply::Array(vec![Reply::bulk("bob"), Reply::bulk("30"), Reply::bulk("carol"), Reply::bulk("20")])
+        );
+        assert_eq!(
+            run(&engine, &mut session, "ZRANGEBYSCORE board (12.5 +inf LIMIT 1 1"),
+            Reply::Array(vec![Reply::bulk("bob")])
+        );
+        assert_eq!(
+            run(&engine, &mut session, "ZRANGE board +inf 20 BYSCORE REV"),
+            Reply::Array(vec![Reply::bulk("bob"), Reply::bulk("carol")])
+        );
+        assert_eq!(run(&engine, &mut session, "ZREVRANK board alice"), Reply::Integer(2));
+        assert!(matches!(run(&engine, &mut session, "ZADD board nan x"), Reply::Error(_)));
+    }
+
+    #[test]
+    fn test_hello_switches_protocol() {
+        let engine = KvEngine::with_shards(1);
+        let mut session = new_session(7);
+
+        match run(&engine, &mut session, "HELLO 3 SETNAME worker") {
+            Reply::Map(pairs) => assert!(pairs.contains(&(Reply::bulk("proto"), Reply::Integer(3)))),
+            other => panic!("Unexpected reply {:?}", other),
+        }
+        assert_eq!(session.protocol, Protocol::Resp3);
+        assert_eq!(session.name.as_deref(), Some("worker"));
+        assert!(matches!(run(&engine, &mut session, "HELLO 4"), Reply::Error(e) if e.starts_with("NOPROTO")));
+    }
+
+    #[test]
+    fn test_subscribe_mode_restricts_commands() {
+        let engine = KvEngine::with_shards(1);
+        let pubsub = Arc::new(PubSub::new());
+        let acl = Acl::default();
+        let ctx = Context { engine: &engine, pubsub: &pubsub, replica: None, acl: &acl };
+        let mut session = new_session(1);
+        let args = |s: &str| s.split(' ').map(|a| a.as_bytes().to_vec()).collect::<Vec<_>>();
+
+        let replies = execute(&ctx, &mut session, &args("SUBSCRIBE a b"));
+        assert_eq!(replies.len(), 2);
+        assert_eq!(
+            replies[1],
+            Reply::Push(vec![Reply::bulk("subscribe"), Reply::bulk("b"), Reply::Integer(2)])
+        );
+        assert!(matches!(&execute(&ctx, &mut session, &args("GET a"))[0], Reply::Error(_)));
+        assert_eq!(
+            execute(&ctx, &mut session, &args("PING"))[0],
+            Reply::Array(vec![Reply::bulk("pong"), Reply::bulk("")])
+        );
+
+        let replies = execute(&ctx, &mut session, &args("UNSUBSCRIBE"));
+        assert_eq!(replies.len(), 2);
+        assert_eq!(execute(&ctx, &mut session, &args("GET a"))[0], Reply::Nil);
+    }
+
+    #[test]
+    fn test_replica_rejects_writes() {
+        let engine = KvEngine::with_shards(1);
+        let pubsub = Arc::new(PubSub::new());
+        let replica = ReplicaState::new("127.0.0.1:6380");
+        let acl = Acl::default();
+        let ctx = Context { engine: &engine, pubsub: &pubsub, replica: Some(&replica), acl: &acl };
+        let mut session = new_session(1);
+
+        let reply = execute(&ctx, &mut session, &[b"SET".to_vec(), b"k".to_vec(), b"v".to_vec()]);
+        assert!(matches!(&reply[0], Reply::Error(e) if e.starts_with("READONLY")));
+        let reply = execute(&ctx, &mut session, &[b"GET".to_vec(), b"k".to_vec()]);
+        assert_eq!(reply[0], Reply::Nil);
+    }
+
+    #[test]
+    fn test_multi_exec() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+
+        run(&engine, &mut session, "SET stock 5");
+        assert_eq!(run(&engine, &mut session, "MULTI"), Reply::ok());
+        assert_eq!(run(&engine, &mut session, "DECRBY stock 3"), Reply::Simple("QUEUED".to_string()));
+        assert_eq!(run(&engine, &mut session, "INCRBY reserved 3"), Reply::Simple("QUEUED".to_string()));
+        assert_eq!(run(&engine, &mut session, "MGET stock reserved"), Reply::Simple("QUEUED".to_string()));
+        assert_eq!(
+            run(&engine, &mut session, "EXEC"),
+            Reply::Array(vec![
+                Reply::Integer(2),
+                Reply::Integer(3),
+                Reply::Array(vec![Reply::bulk("2"), Reply::bulk("3")]),
+            ])
+        );
+
+        // A rejected command discards the whole transaction
+        run(&engine, &mut session, "MULTI");
+        run(&engine, &mut session, "INCR stock");
+        assert!(matches!(run(&engine, &mut session, "KEYS *"), Reply::Error(_)));
+        assert!(matches!(run(&engine, &mut session, "EXEC"), Reply::Error(e) if e.starts_with("EXECABORT")));
+        assert_eq!(run(&engine, &mut session, "GET stock"), Reply::bulk("2"));
+
+        // A failing command rolls back the others
+        run(&engine, &mut session, "SET name widget");
+        run(&engine, &mut session, "MULTI");
+        run(&engine, &mut session, "DECR stock");
+        run(&engine, &mut session, "INCR name");
+        assert!(matches!(run(&engine, &mut session, "EXEC"), Reply::Error(_)));
+        assert_eq!(run(&engine, &mut session, "GET stock"), Reply::bulk("2"));
+
+        assert!(matches!(run(&engine, &mut session, "EXEC"), Reply::Error(e) if e.contains("without MULTI")));
+        run(&engine, &mut session, "MULTI");
+        assert!(matches!(run(&engine, &mut session, "MULTI"), Reply::Error(_)));
+        assert_eq!(run(&engine, &mut session, "DISCARD"), Reply::ok());
+    }
+
+    #[test]
+    fn test_watch_aborts_exec() {
+        let engine = KvEngine::with_shards(4);
+        let mut session = new_session(1);
+        let mut other = new_session(2);
+
+        run(&engine, &mut session, "SET balance 100");
+        assert_eq!(run(&engine, &mut session, "WATCH balance"), Reply::ok());
+        run(&engine, &mut session, "MULTI");
+        run(&engine, &mut session, "DECRBY balance 30");
+
+        // Another client writes the watched key before EXEC
+        run(&engine, &mut other, "SET balance 50");
+        assert_eq!(run(&engine, &mut session, "EXEC"), Reply::Nil);
+        assert_eq!(run(&engine, &mut session, "GET balance"), Reply::bulk("50"));
+
+        // EXEC forgets watched keys
+        run(&engine, &mut session, "MULTI");
+        run(&engine, &mut session, "DECRBY balance 30");
+        assert_eq!(run(&engine, &mut session, "EXEC"), Reply::Array(vec![Reply::Integer(20)]));
+    }
+
+    #[test]
+    fn test_auth_and_acl() {
+        let engine = KvEngine::with_shards(4);
+        let acl = Acl::parse("user default off\nuser worker on >secret namespace=tasks allcommands -@admin").unwrap();
+        let mut session = Session::new(1, acl.default_user());
+        let is_error = |reply: Reply, prefix: &str| matches!(reply, Reply::Error(e) if e.starts_with(prefix));
+
+        assert!(is_error(run_as(&engine, &acl, &mut session, "GET tasks:1"), "NOAUTH"));
+        assert!(is_error(run_as(&engine, &acl, &mut session, "HELLO 3"), "NOAUTH"));
+        assert_eq!(run_as(&engine, &acl, &mut session, "PING"), Reply::Simple("PONG".to_string()));
+        assert!(is_error(run_as(&engine, &acl, &mut session, "AUTH worker wrong"), "WRONGPASS"));
+        assert_eq!(run_as(&engine, &acl, &mut session, "AUTH worker secret"), Reply::ok());
+        assert_eq!(run_as(&engine, &acl, &mut session, "ACL WHOAMI"), Reply::bulk("worker"));
+
+        // Keys and patterns outside the namespace, and admin commands, are refused
+        assert_eq!(run_as(&engine, &acl, &mut session, "SET tasks:1 v"), Reply::ok());
+        assert!(is_error(run_as(&engine, &acl, &mut session, "SET other v"), "NOPERM"));
+        assert!(is_error(run_as(&engine, &acl, &mut session, "MGET tasks:1 other"), "NOPERM"));
+        assert!(is_error(run_as(&engine, &acl, &mut session, "KEYS *"), "NOPERM"));
+        assert!(!is_error(run_as(&engine, &acl, &mut session, "KEYS tasks:*"), "NOPERM"));
+        assert!(is_error(run_as(&engine, &acl, &mut session, "FLUSHDB"), "NOPERM"));
+
+        // A refused command inside MULTI aborts the transaction
+        run_as(&engine, &acl, &mut session, "MULTI");
+        run_as(&engine, &acl, &mut session, "SET other v");
+        assert!(is_error(run_as(&engine, &acl, &mut session, "EXEC"), "EXECABORT"));
+        assert!(engine.get(&KvKey::new("other").unwrap()).is_none());
+
+        // RESET logs out
+        run_as(&engine, &acl, &mut session, "RESET");
+        assert!(is_error(run_as(&engine, &acl, &mut session, "GET tasks:1"), "NOAUTH"));
+    }
+}
@@ -0,0 +1,114 @@
+//! Redis-compatible (RESP2/RESP3) listener
+//!
+//! Serves the engine to `redis-cli` and existing Redis clients alongside the
+//! native protocol. Supports the string, key, expiry and pub/sub commands
+//! listed in [`commands`], hash/list/set/sorted set commands (see
+//! [`collections`]) and MULTI/EXEC/WATCH transactions (see [`multi`]).
+//! Connections start in RESP2 and switch with `HELLO 3`. Only database 0
+//! exists. AUTH and `HELLO ... AUTH` log in as an ACL user (see
+//! [`crate::auth`]).
+
+mod codec;
+mod collections;
+mod commands;
+mod multi;
+
+use crate::auth::Acl;
+use crate::pubsub::PubSub;
+use crate::replication::ReplicaState;
+use crate::server::next_message;
+use crate::tls::{self, Stream};
+use codec::{parse_request, Reply};
+use commands::{Context, Session};
+use ouroboros_kv::KvEngine;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Source of CLIENT ID values
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Serve one RESP connection
+///
+/// Pipelined requests are answered in order. Published messages are sent as
+/// push replies (plain arrays on RESP2 connections) between responses.
+pub async fn handle_connection(
+    mut socket: impl Stream,
+    engine: Arc<KvEngine>,
+    pubsub: Arc<PubSub>,
+    replica: Option<Arc<ReplicaState>>,
+    acl: Arc<Acl>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ctx = Context {
+        engine: &engine,
+        pubsub: &pubsub,
+        replica: replica.as_deref(),
+        acl: &acl,
+    };
+    let mut session = Session::new(NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed), acl.default_user());
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut pending: Vec<u8> = Vec::new();
+    let mut out: Vec<u8> = Vec::new();
+
+    loop {
+        // Answer every request that has fully arrived, in one write
+        let mut consumed = 0;
+        loop {
+            match parse_request(&pending[consumed..]) {
+                Ok(Some((args, len))) => {
+                    consumed += len;
+                    for reply in commands::execute(&ctx, &mut session, &args) {
+                        reply.encode(session.protocol, &mut out);
+                    }
+                    if session.closing {
+                        tls::send(&mut socket, &out).await?;
+                        return Ok(());
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    // Cannot resynchronise after a malformed request
+                    Reply::error(format!("ERR {}", e)).encode(session.protocol, &mut out);
+                    tls::send(&mut socket, &out).await?;
+                    return Ok(());
+                }
+            }
+        }
+        pending.drain(..consumed);
+        if !out.is_empty() {
+            tls::send(&mut socket, &out).await?;
+            out.clear();
+        }
+
+        tokio::select! {
+            read = socket.read(&mut chunk) => {
+                let n = tls::clean_eof(read)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                pending.extend_from_slice(&chunk[..n]);
+            }
+            message = next_message(&mut session.subscriber) => match message {
+                Ok(message) => {
+                    let mut items = vec![Reply::bulk(if message.pattern.is_some() { "pmessage" } else { "message" })];
+                    if let Some(pattern) = &message.pattern {
+                        items.push(Reply::bulk(pattern.as_bytes()));
+                    }
+                    items.push(Reply::bulk(message.channel.as_bytes()));
+                    items.push(Reply::bulk(&message.payload[..]));
+                    Reply::Push(items).encode(session.protocol, &mut out);
+                    tls::send(&mut socket, &out).await?;
+                    out.clear();
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    let msg = format!("ERR subscriber too slow, {} messages dropped", missed);
+                    Reply::error(msg).encode(session.protocol, &mut out);
+                    tls::send(&mut socket, &out).await?;
+                    return Ok(());
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! MULTI/EXEC transactions
+//!
+//! Commands sent after MULTI are translated into [`TxnOp`]s as they arrive and
+//! executed together by `KvEngine::transact` on EXEC, with the keys passed to
+//! WATCH as version guards. Only commands with a transactional equivalent can
+//! be queued; anything else is rejected and makes EXEC fail, like a syntax
+//! error in Redis.
+//!
+//! Unlike Redis, a command failing at EXEC time (e.g. INCR on a string)
+//! rolls back the whole transaction and EXEC returns that error.
+
+use super::codec::Reply;
+use super::commands::{
+    checked_ttl, expire_duration, invalid_expire_time, key, parse_int, value_from_bytes, value_reply, wrong_arity,
+};
+use ouroboros_kv::{Guard, KvEngine, Transaction, TxnOp, TxnResult};
+use std::time::Duration;
+
+/// How a queued command's results become its reply
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReplyKind {
+    /// `+OK`
+    Ok,
+    /// The first result's value
+    Value,
+    /// Each result's value, as an array
+    Values,
+    /// The first result's integer
+    Int,
+    /// Number of operations that changed or found their key
+    Count,
+}
+
+/// A command queued between MULTI and EXEC
+#[derive(Debug)]
+pub struct Queued {
+    ops: Vec<TxnOp>,
+    reply: ReplyKind,
+}
+
+/// Transaction state of a connection
+#[derive(Debug, Default)]
+pub struct MultiState {
+    /// Commands queued since MULTI (None outside a transaction)
+    pub queued: Option<Vec<Queued>>,
+    /// A command was rejected while queueing; EXEC will fail
+    pub failed: bool,
+    /// Keys passed to WATCH and their versions at the time
+    pub watched: Vec<(String, u64)>,
+}
+
+impl MultiState {
+    /// Leave the transaction and forget watched keys
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Translate a command into transaction operations
+pub fn queue(name: &str, args: &[Vec<u8>]) -> Result<Queued, Reply> {
+    let op = |ops: Vec<TxnOp>, reply| Ok(Queued { ops, reply });
+    let key_string = |arg: &[u8]| key(arg).map(|k| k.as_str().to_string());
+    let exact = |n: usize| if args.len() == n { Ok(()) } else { Err(wrong_arity(name)) };
+
+    match name {
+        "GET" => {
+            exact(1)?;
+            op(vec![TxnOp::Get { key: key_string(&args[0])? }], ReplyKind::Value)
+        }
+        "SET" => {
+            // Only the expiry options have a transactional equivalent
+            let ttl = match args.len() {
+                2 => None,
+                4 => {
+                    let option = String::from_utf8_lossy(&args[2]).to_ascii_uppercase();
+                    let amount = parse_int(&args[3])?;
+                    match option.as_str() {
+                        "EX" | "PX" | "EXAT" | "PXAT" => {
+                            Some(expire_duration(&option, amount).ok_or_else(|| invalid_expire_time(name))?)
+                        }
+                        _ => return Err(Reply::error("ERR SET options other than EX/PX/EXAT/PXAT are not supported in MULTI")),
+                    }
+                }
+                n if n > 2 => {
+                    return Err(Reply::error("ERR SET options other than EX/PX/EXAT/PXAT are not supported in MULTI"))
+                }
+                _ => return Err(wrong_arity(name)),
+            };
+            let value = value_from_bytes(&args[1]);
+            op(vec![TxnOp::Set { key: key_string(&args[0])?, value, ttl }], ReplyKind::Ok)
+        }
+        "SETNX" => {
+            exact(2)?;
+            let value = value_from_bytes(&args[1]);
+            op(vec![TxnOp::SetNx { key: key_string(&args[0])?, value, ttl: None }], ReplyKind::Count)
+        }
+        "SETEX" | "PSETEX" => {
+            exact(3)?;
+            let amount = parse_int(&args[1])?;
+            let ttl = if name == "SETEX" {
+                Duration::from_secs(amount.max(0) as u64)
+            } else {
+                Duration::from_millis(amount.max(0) as u64)
+            };
+            let ttl = checked_ttl(ttl).ok_or_else(|| invalid_expire_time(name))?;
+            let value = value_from_bytes(&args[2]);
+            op(vec![TxnOp::Set { key: key_string(&args[0])?, value, ttl: Some(ttl) }], ReplyKind::Ok)
+        }
+        "GETSET" => {
+            exact(2)?;
+            let key = key_string(&args[0])?;
+            let value = value_from_bytes(&args[1]);
+            op(
+                vec![TxnOp::Get { key: key.clone() }, TxnOp::Set { key, value, ttl: None }],
+                ReplyKind::Value,
+            )
+        }
+        "GETDEL" => {
+            exact(1)?;
+            let key = key_string(&args[0])?;
+            op(vec![TxnOp::Get { key: key.clone() }, TxnOp::Delete { key }], ReplyKind::Value)
+        }
+        "MGET" | "EXISTS" => {
+            if args.is_empty() {
+                return Err(wrong_arity(name));
+            }
+            let ops = args
+                .iter()
+                .map(|a| Ok(TxnOp::Get { key: key_string(a)? }))
+                .collect::<Result<_, Reply>>()?;
+            op(ops, if name == "MGET" { ReplyKind::Values } else { ReplyKind::Count })
+        }
+        "MSET" => {
+            if args.is_empty() || !args.len().is_multiple_of(2) {
+                return Err(wrong_arity(name));
+            }
+            let ops = args
+                .chunks(2)
+                .map(|pair| {
+                    Ok(TxnOp::Set {
+                        key: key_string(&pair[0])?,
+                        value: value_from_bytes(&pair[1]),
+                        ttl: None,
+                    })
+                })
+                .collect::<Result<_, Reply>>()?;
+            op(ops, ReplyKind::Ok)
+        }
+        "DEL" | "UNLINK" => {
+            if args.is_empty() {
+                return Err(wrong_arity(name));
+            }
+            let ops = args
+                .iter()
+                .map(|a| Ok(TxnOp::Delete { key: key_string(a)? }))
+                .collect::<Result<_, Reply>>()?;
+            op(ops, ReplyKind::Count)
+        }
+        "INCR" | "DECR" => {
+            exact(1)?;
+            let delta = if name == "INCR" { 1 } else { -1 };
+            op(vec![TxnOp::Incr { key: key_string(&args[0])?, delta }], ReplyKind::Int)
+        }
+        "INCRBY" | "DECRBY" => {
+            exact(2)?;
+            let delta = parse_int(&args[1])?;
+            let delta = if name == "INCRBY" {
+                delta
+            } else {
+                delta
+                    .checked_neg()
+                    .ok_or_else(|| Reply::error("ERR value is not an integer or out of range"))?
+            };
+            op(vec![TxnOp::Incr { key: key_string(&args[0])?, delta }], ReplyKind::Int)
+        }
+        "EXPIRE" | "PEXPIRE" => {
+            exact(2)?;
+            let key = key_string(&args[0])?;
+            let amount = parse_int(&args[1])?;
+            if amount <= 0 {
+                return op(vec![TxnOp::Delete { key }], ReplyKind::Count);
+            }
+            let ttl = if name == "EXPIRE" {
+                Duration::from_secs(amount as u64)
+            } else {
+                Duration::from_millis(amount as u64)
+            };
+            let ttl = checked_ttl(ttl).ok_or_else(|| invalid_expire_time(name))?;
+            op(vec![TxnOp::Expire { key, ttl: Some(ttl) }], ReplyKind::Count)
+        }
+        _ => Err(Reply::error(format!(
+            "ERR Command '{}' is not supported inside MULTI",
+            name.to_lowercase()
+        ))),
+    }
+}
+
+/// Run the queued commands, returning EXEC's reply
+pub fn exec(engine: &KvEngine, queued: Vec<Queued>, watched: &[(String, u64)]) -> Reply {
+    let mut txn = Transaction::new();
+    for (key, version) in watched {
+        txn.guards.push((key.clone(), Guard::Version(*version)));
+    }
+    for command in &queued {
+        for op in &command.ops {
+            txn.push(op.clone());
+        }
+    }
+
+    let results = match engine.transact(&txn) {
+        Ok(Some(results)) => results,
+        // A watched key changed
+        Ok(None) => return Reply::Nil,
+        Err(e) => return Reply::error(format!("EXECABORT Transaction rolled back: {}", e)),
+    };
+
+    let mut results = results.into_iter();
+    let replies = queued
+        .iter()
+        .map(|command| {
+            let results: Vec<TxnResult> = results.by_ref().take(command.ops.len()).collect();
+            command_reply(command.reply, results)
+        })
+        .collect();
+    Reply::Array(replies)
+}
+
+fn command_reply(kind: ReplyKind, results: Vec<TxnResult>) -> Reply {
+    let value = |result: TxnResult| match result {
+        TxnResult::Value(value) => value_reply(value),
+        _ => Reply::Nil,
+    };
+
+    match kind {
+        ReplyKind::Ok => Reply::ok(),
+        ReplyKind::Value => results.into_iter().next().map(value).unwrap_or(Reply::Nil),
+        ReplyKind::Values => Reply::Array(results.into_iter().map(value).collect()),
+        ReplyKind::Int => match results.first() {
+            Some(TxnResult::Int(n)) => Reply::Integer(*n),
+            _ => Reply::Nil,
+        },
+        ReplyKind::Count => Reply::Integer(
+            results
+                .iter()
+                .filter(|r| matches!(r, TxnResult::Bool(true) | TxnResult::Value(Some(_))))
+                .count() as i64,
+        ),
+    }
+}
@@ -1,27 +1,78 @@
 //! TCP server implementation
 
+use crate::auth::{require_user, Acl, Category, User};
+use crate::collections;
 use crate::protocol::{
-    encode_mget_response, encode_value, parse_incr_payload, parse_key, parse_lock_payload,
-    parse_mget_payload, parse_mset_payload, parse_set_payload, read_request, write_response,
-    Command, ProtocolError, Status,
+    encode_exec_response, encode_message, encode_mget_response, encode_scan_response, encode_value,
+    parse_auth_payload, parse_collection_payload, parse_exec_payload, parse_incr_payload, parse_key,
+    parse_lock_payload, parse_mget_payload, parse_mset_payload, parse_publish_payload,
+    parse_scan_payload, parse_set_payload, read_request, request_len, write_response, Command,
+    ProtocolError, Status,
 };
-use ouroboros_kv::{KvEngine, KvKey, KvValue};
+use crate::pubsub::{KeyspaceNotifier, Message, PubSub, Subscriber};
+use crate::replication::{serve_replica, ReplicaState};
+use crate::resp;
+use crate::tls::{self, Stream};
+use ouroboros_kv::{KvEngine, KvKey, KvValue, TxnOp};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_rustls::TlsAcceptor;
 use tracing::{debug, info, warn};
 
 /// KV Server
 pub struct KvServer {
     engine: Arc<KvEngine>,
+    pubsub: Arc<PubSub>,
+    /// Set when this server is a read-only replica
+    replica: Option<Arc<ReplicaState>>,
+    /// Users clients authenticate as
+    acl: Arc<Acl>,
+    /// Set to accept only TLS connections
+    tls: Option<TlsAcceptor>,
 }
 
 impl KvServer {
     /// Create a KV server with an existing engine (for persistence support)
     pub fn with_engine(engine: Arc<KvEngine>) -> Self {
-        Self { engine }
+        Self {
+            engine,
+            pubsub: Arc::new(PubSub::new()),
+            replica: None,
+            acl: Arc::new(Acl::default()),
+            tls: None,
+        }
+    }
+
+    /// Require clients to authenticate as one of these users (see [`crate::auth`])
+    pub fn with_acl(mut self, acl: Acl) -> Self {
+        self.acl = Arc::new(acl);
+        self
+    }
+
+    /// Serve both listeners over TLS
+    pub fn with_tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    /// Serve as a read-only replica; writes are rejected
+    ///
+    /// The engine is kept in sync by [`crate::replication::run_replica`].
+    pub fn with_replica(mut self, state: Arc<ReplicaState>) -> Self {
+        self.replica = Some(state);
+        self
+    }
+
+    /// Publish keyspace events (set, del, expired, evicted) to subscribers
+    ///
+    /// See [`crate::pubsub`] for channel names.
+    pub fn enable_keyspace_events(&self) -> Result<(), ouroboros_kv::KvError> {
+        self.engine
+            .enable_keyspace_events(Arc::new(KeyspaceNotifier::new(self.pubsub.clone())))
     }
 
     /// Run the server
@@ -32,66 +83,236 @@ impl KvServer {
         loop {
             let (socket, peer_addr) = listener.accept().await?;
             let engine = self.engine.clone();
+            let pubsub = self.pubsub.clone();
+            let replica = self.replica.clone();
+            let acl = self.acl.clone();
+            let tls = self.tls.clone();
 
             tokio::spawn(async move {
                 debug!("New connection from {}", peer_addr);
-                if let Err(e) = handle_connection(socket, engine).await {
+                let socket = match tls::accept(socket, tls.as_ref()).await {
+                    Ok(socket) => socket,
+                    Err(e) => return warn!("TLS handshake with {} failed: {}", peer_addr, e),
+                };
+                if let Err(e) = handle_connection(socket, engine, pubsub, replica, acl).await {
                     warn!("Connection error from {}: {}", peer_addr, e);
                 }
                 debug!("Connection closed: {}", peer_addr);
             });
         }
     }
+
+    /// Run a Redis-compatible (RESP) listener sharing this server's engine and channels
+    pub async fn run_resp(&self, addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(addr).await?;
+        info!("RESP listener on {}", addr);
+
+        loop {
+            let (socket, peer_addr) = listener.accept().await?;
+            let engine = self.engine.clone();
+            let pubsub = self.pubsub.clone();
+            let replica = self.replica.clone();
+            let acl = self.acl.clone();
+            let tls = self.tls.clone();
+
+            tokio::spawn(async move {
+                debug!("New RESP connection from {}", peer_addr);
+                let socket = match tls::accept(socket, tls.as_ref()).await {
+                    Ok(socket) => socket,
+                    Err(e) => return warn!("TLS handshake with {} failed: {}", peer_addr, e),
+                };
+                if let Err(e) = resp::handle_connection(socket, engine, pubsub, replica, acl).await {
+                    warn!("RESP connection error from {}: {}", peer_addr, e);
+                }
+                debug!("RESP connection closed: {}", peer_addr);
+            });
+        }
+    }
 }
 
+/// Serve one connection
+///
+/// Requests are answered in order. Once the connection subscribes to a
+/// channel, published messages are interleaved between responses as
+/// `Status::Message` frames. A `SYNC` request turns the connection into a
+/// replication stream.
 async fn handle_connection(
-    mut socket: TcpStream,
+    mut socket: impl Stream,
     engine: Arc<KvEngine>,
+    pubsub: Arc<PubSub>,
+    replica: Option<Arc<ReplicaState>>,
+    acl: Arc<Acl>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Disable Nagle's algorithm for lower latency
-    socket.set_nodelay(true)?;
-
-    let mut buf = vec![0u8; 64 * 1024]; // 64KB buffer
+    let mut user = acl.default_user();
+    let mut chunk = vec![0u8; 64 * 1024]; // 64KB read buffer
+    let mut pending: Vec<u8> = Vec::new();
+    let mut subscriber: Option<Subscriber> = None;
 
     loop {
-        // Read header (5 bytes: 1 cmd + 4 len)
-        let n = socket.read(&mut buf[..5]).await?;
-        if n == 0 {
-            return Ok(()); // Connection closed
+        // Answer every request that has fully arrived
+        let mut consumed = 0;
+        loop {
+            let len = match request_len(&pending[consumed..]) {
+                Ok(Some(len)) => len,
+                Ok(None) => break,
+                Err(e) => {
+                    // Cannot resynchronise after a bad header
+                    tls::send(&mut socket, &write_response(Status::Error, e.to_string().as_bytes())).await?;
+                    return Ok(());
+                }
+            };
+
+            let request = &pending[consumed..consumed + len];
+            if request[0] == Command::Sync as u8 {
+                let allowed = require_user(&user).and_then(|user| user.check_command("sync", Category::Admin));
+                if let Err(denied) = allowed {
+                    tls::send(&mut socket, &write_response(Status::Error, denied.to_string().as_bytes())).await?;
+                    return Ok(());
+                }
+                if replica.is_some() {
+                    tls::send(&mut socket, &write_response(Status::Error, b"Replicas cannot serve SYNC")).await?;
+                    return Ok(());
+                }
+                return serve_replica(&mut socket, &engine, &request[5..]).await;
+            }
+
+            let response = match process_request(request, &engine, &pubsub, &mut subscriber, replica.as_deref(), &acl, &mut user) {
+                Ok(resp) => resp,
+                Err(e) => {
+                    let msg = e.to_string();
+                    write_response(Status::Error, msg.as_bytes())
+                }
+            };
+            tls::send(&mut socket, &response).await?;
+            consumed += len;
         }
-        if n < 5 {
-            // Partial read, try to read more
-            socket.read_exact(&mut buf[n..5]).await?;
+        pending.drain(..consumed);
+
+        tokio::select! {
+            read = socket.read(&mut chunk) => {
+                let n = tls::clean_eof(read)?;
+                if n == 0 {
+                    return Ok(()); // Connection closed
+                }
+                pending.extend_from_slice(&chunk[..n]);
+            }
+            message = next_message(&mut subscriber) => match message {
+                Ok(message) => {
+                    let payload = encode_message(message.pattern.as_deref(), &message.channel, &message.payload);
+                    tls::send(&mut socket, &write_response(Status::Message, &payload)).await?;
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    let msg = format!("Subscriber too slow, {} messages dropped", missed);
+                    tls::send(&mut socket, &write_response(Status::Error, msg.as_bytes())).await?;
+                    return Ok(());
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
         }
+    }
+}
 
-        // Parse payload length
-        let payload_len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+/// Next published message, or never if the connection has not subscribed
+pub(crate) async fn next_message(subscriber: &mut Option<Subscriber>) -> Result<Message, RecvError> {
+    match subscriber {
+        Some(subscriber) => subscriber.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
-        // Read payload
-        if payload_len > 0 {
-            if buf.len() < 5 + payload_len {
-                buf.resize(5 + payload_len, 0);
+/// Check that a user may run a request, with every key and channel it names
+fn authorize(user: &User, cmd: Command, payload: &[u8]) -> Result<(), ProtocolError> {
+    match cmd {
+        Command::Collection => {
+            let (sub, key, _) = parse_collection_payload(payload)?;
+            let category = if sub.is_write() { Category::Write } else { Category::Read };
+            user.check_command(&sub.acl_name(), category)?;
+            return Ok(user.check_key(&key)?);
+        }
+        Command::Exec => {
+            user.check_command(cmd.acl_name(), cmd.category())?;
+            let txn = parse_exec_payload(payload)?;
+            for op in &txn.ops {
+                let (name, category) = match op {
+                    TxnOp::Get { .. } => ("get", Category::Read),
+                    TxnOp::Set { .. } => ("set", Category::Write),
+                    TxnOp::SetNx { .. } => ("setnx", Category::Write),
+                    TxnOp::Delete { .. } => ("del", Category::Write),
+                    TxnOp::Incr { .. } => ("incrby", Category::Write),
+                    TxnOp::Expire { .. } => ("pexpire", Category::Write),
+                };
+                user.check_command(name, category)?;
             }
-            socket.read_exact(&mut buf[5..5 + payload_len]).await?;
+            if !user.all_keys() {
+                for key in txn.keys() {
+                    user.check_key(key)?;
+                }
+            }
+            return Ok(());
         }
+        _ => user.check_command(cmd.acl_name(), cmd.category())?,
+    }
 
-        // Process request
-        let response = match process_request(&buf[..5 + payload_len], &engine) {
-            Ok(resp) => resp,
-            Err(e) => {
-                let msg = e.to_string();
-                write_response(Status::Error, msg.as_bytes())
+    match cmd {
+        Command::Publish => user.check_channel(&parse_publish_payload(payload)?.0)?,
+        Command::Subscribe => {
+            for channel in parse_mget_payload(payload)? {
+                user.check_channel(&channel)?;
             }
-        };
-
-        // Send response
-        socket.write_all(&response).await?;
+        }
+        Command::PSubscribe => {
+            for pattern in parse_mget_payload(payload)? {
+                user.check_channel_pattern(&pattern)?;
+            }
+        }
+        _ if user.all_keys() => {}
+        Command::Get | Command::Del | Command::Exists | Command::Watch => user.check_key(&parse_key(payload)?)?,
+        Command::Set | Command::Setnx => user.check_key(&parse_set_payload(payload)?.0)?,
+        Command::Incr | Command::Decr => user.check_key(&parse_incr_payload(payload)?.0)?,
+        Command::Lock | Command::ExtendLock => user.check_key(&parse_lock_payload(payload, true)?.0)?,
+        Command::Unlock => user.check_key(&parse_lock_payload(payload, false)?.0)?,
+        Command::MGet | Command::MDel => {
+            for key in parse_mget_payload(payload)? {
+                user.check_key(&key)?;
+            }
+        }
+        Command::MSet => {
+            for (key, _) in parse_mset_payload(payload)?.0 {
+                user.check_key(&key)?;
+            }
+        }
+        Command::Scan => user.check_key_pattern(parse_scan_payload(payload)?.2.as_deref().unwrap_or("*"))?,
+        // CAS is not implemented
+        Command::Cas => user.check_key_pattern("*")?,
+        _ => {}
     }
+    Ok(())
 }
 
-fn process_request(data: &[u8], engine: &KvEngine) -> Result<Vec<u8>, ProtocolError> {
+fn process_request(
+    data: &[u8],
+    engine: &KvEngine,
+    pubsub: &Arc<PubSub>,
+    subscriber: &mut Option<Subscriber>,
+    replica: Option<&ReplicaState>,
+    acl: &Acl,
+    user: &mut Option<Arc<User>>,
+) -> Result<Vec<u8>, ProtocolError> {
     let (cmd, payload) = read_request(data)?;
 
+    if cmd == Command::Auth {
+        let (name, password) = parse_auth_payload(&payload)?;
+        *user = Some(acl.authenticate(name.as_deref(), &password)?);
+        return Ok(write_response(Status::Ok, &[]));
+    }
+    if cmd.category() != Category::Connection {
+        authorize(require_user(user)?, cmd, &payload)?;
+    }
+
+    if replica.is_some() && cmd.is_write() {
+        return Ok(write_response(Status::Error, b"READONLY Writes are not allowed on a replica"));
+    }
+
     match cmd {
         Command::Ping => {
             Ok(write_response(Status::Ok, b"PONG"))
@@ -232,7 +453,7 @@ fn process_request(data: &[u8], engine: &KvEngine) -> Result<Vec<u8>, ProtocolEr
         Command::MGet => {
             let keys = parse_mget_payload(&payload)?;
             let kv_keys: Result<Vec<_>, _> = keys.iter()
-                .map(|k| KvKey::new(k))
+                .map(KvKey::new)
                 .collect();
 
             let kv_keys = kv_keys.map_err(|e| ProtocolError::Io(
@@ -267,7 +488,7 @@ fn process_request(data: &[u8], engine: &KvEngine) -> Result<Vec<u8>, ProtocolEr
         Command::MDel => {
             let keys = parse_mget_payload(&payload)?; // Same format as MGET
             let kv_keys: Result<Vec<_>, _> = keys.iter()
-                .map(|k| KvKey::new(k))
+                .map(KvKey::new)
                 .collect();
 
             let kv_keys = kv_keys.map_err(|e| ProtocolError::Io(
@@ -280,13 +501,162 @@ fn process_request(data: &[u8], engine: &KvEngine) -> Result<Vec<u8>, ProtocolEr
             // Return count as u32 big-endian
             Ok(write_response(Status::Ok, &(deleted as u32).to_be_bytes()))
         }
+        Command::Scan => {
+            let (cursor, count, pattern) = parse_scan_payload(&payload)?;
+            let (next, keys) = engine.scan(cursor, pattern.as_deref(), count);
+            Ok(write_response(Status::Ok, &encode_scan_response(next, &keys)))
+        }
+        Command::Publish => {
+            let (channel, message) = parse_publish_payload(&payload)?;
+            let receivers = pubsub.publish(&channel, message);
+            Ok(write_response(Status::Ok, &(receivers as u32).to_be_bytes()))
+        }
+        Command::Watch => {
+            let key_str = parse_key(&payload)?;
+            let key = KvKey::new(&key_str).map_err(|e| ProtocolError::Io(
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+            ))?;
+
+            Ok(write_response(Status::Ok, &engine.version(&key).to_be_bytes()))
+        }
+        Command::Exec => {
+            let txn = parse_exec_payload(&payload)?;
+            if let Some(key) = txn.keys().find(|k| KvKey::new(*k).is_err()) {
+                let msg = format!("Invalid key in transaction: {:?}", key);
+                return Ok(write_response(Status::Error, msg.as_bytes()));
+            }
+
+            match engine.transact(&txn) {
+                Ok(Some(results)) => Ok(write_response(Status::Ok, &encode_exec_response(&results))),
+                // A guard failed; nothing was applied
+                Ok(None) => Ok(write_response(Status::Null, &[])),
+                Err(e) => Ok(write_response(Status::Error, e.to_string().as_bytes())),
+            }
+        }
+        Command::Collection => {
+            let (sub, key_str, args) = parse_collection_payload(&payload)?;
+            if replica.is_some() && sub.is_write() {
+                return Ok(write_response(Status::Error, b"READONLY Writes are not allowed on a replica"));
+            }
+            let key = KvKey::new(&key_str).map_err(|e| ProtocolError::Io(
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+            ))?;
+
+            match collections::execute(engine, sub, &key, args) {
+                Ok(reply) => Ok(write_response(Status::Ok, &encode_value(&reply))),
+                Err(msg) => Ok(write_response(Status::Error, msg.as_bytes())),
+            }
+        }
+        // Handled before reaching here
+        Command::Sync => Ok(write_response(Status::Error, b"Unexpected SYNC")),
+        Command::Auth => Ok(write_response(Status::Error, b"Unexpected AUTH")),
+        Command::Subscribe | Command::Unsubscribe | Command::PSubscribe | Command::PUnsubscribe => {
+            let names = parse_mget_payload(&payload)?; // Same format as MGET
+            let subscriber = subscriber.get_or_insert_with(|| pubsub.subscriber());
+            let count = match cmd {
+                Command::Subscribe => subscriber.subscribe(&names),
+                Command::Unsubscribe => subscriber.unsubscribe(&names),
+                Command::PSubscribe => subscriber.psubscribe(&names),
+                _ => subscriber.punsubscribe(&names),
+            };
+            Ok(write_response(Status::Ok, &(count as u32).to_be_bytes()))
+        }
         Command::Info => {
-            let info = format!(
-                r#"{{"shards":{},"entries":{}}}"#,
+            let mut info = format!(
+                r#"{{"shards":{},"entries":{},"memory_bytes":{},"evictions":{}"#,
                 engine.num_shards(),
-                engine.len()
+                engine.len(),
+                engine.memory_usage(),
+                engine.evictions()
             );
+            if let Some(policy) = engine.eviction_policy() {
+                info.push_str(&format!(r#","eviction_policy":"{}""#, policy));
+            }
+            match (replica, engine.replication_log()) {
+                (Some(replica), _) => info.push_str(&format!(
+                    r#","role":"replica","primary":"{}","link":"{}","replication_offset":{}"#,
+                    replica.primary(),
+                    if replica.link_up() { "up" } else { "down" },
+                    replica.offset()
+                )),
+                (None, Some(log)) => info.push_str(&format!(
+                    r#","role":"primary","replication_offset":{}"#,
+                    log.offset()
+                )),
+                (None, None) => info.push_str(r#","role":"primary""#),
+            }
+            if let Some(tier) = engine.tiered_stats() {
+                info.push_str(&format!(
+                    r#","tier":{{"hot_entries":{},"cold_entries":{},"cold_bytes":{},"segments":{},"demotions":{},"promotions":{}}}"#,
+                    tier.hot_entries,
+                    tier.cold_entries,
+                    tier.cold_bytes,
+                    tier.segments,
+                    tier.demotions,
+                    tier.promotions
+                ));
+            }
+            info.push('}');
             Ok(write_response(Status::Ok, info.as_bytes()))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{encode_auth_payload, encode_sync_payload, write_request};
+    use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+
+    async fn call(client: &mut DuplexStream, cmd: Command, payload: &[u8]) -> (u8, Vec<u8>) {
+        client.write_all(&write_request(cmd, payload)).await.unwrap();
+        let mut header = [0u8; 5];
+        client.read_exact(&mut header).await.unwrap();
+        let mut payload = vec![0u8; u32::from_be_bytes(header[1..5].try_into().unwrap()) as usize];
+        client.read_exact(&mut payload).await.unwrap();
+        (header[0], payload)
+    }
+
+    fn set_payload(key: &str) -> Vec<u8> {
+        let mut payload = (key.len() as u16).to_be_bytes().to_vec();
+        payload.extend_from_slice(key.as_bytes());
+        payload.extend_from_slice(&0u64.to_be_bytes());
+        payload.extend_from_slice(&encode_value(&KvValue::Int(1)));
+        payload
+    }
+
+    #[tokio::test]
+    async fn test_auth_and_acl() {
+        let acl = Acl::parse("user default off\nuser worker on >secret namespace=tasks allcommands -@admin").unwrap();
+        let (mut client, server) = duplex(64 * 1024);
+        tokio::spawn(handle_connection(
+            server,
+            Arc::new(KvEngine::with_shards(4)),
+            Arc::new(PubSub::new()),
+            None,
+            Arc::new(acl),
+        ));
+
+        let (status, message) = call(&mut client, Command::Get, b"tasks:1").await;
+        assert_eq!(status, Status::Error as u8);
+        assert!(message.starts_with(b"NOAUTH"));
+        assert_eq!(call(&mut client, Command::Ping, b"").await.0, Status::Ok as u8);
+
+        let (status, message) = call(&mut client, Command::Auth, &encode_auth_payload(Some("worker"), "wrong")).await;
+        assert_eq!(status, Status::Error as u8);
+        assert!(message.starts_with(b"WRONGPASS"));
+        let (status, _) = call(&mut client, Command::Auth, &encode_auth_payload(Some("worker"), "secret")).await;
+        assert_eq!(status, Status::Ok as u8);
+
+        assert_eq!(call(&mut client, Command::Set, &set_payload("tasks:1")).await.0, Status::Ok as u8);
+        let (status, message) = call(&mut client, Command::Set, &set_payload("other")).await;
+        assert_eq!(status, Status::Error as u8);
+        assert!(message.starts_with(b"NOPERM"));
+
+        // SYNC is an admin command; refusing it ends the connection
+        let (status, message) = call(&mut client, Command::Sync, &encode_sync_payload("?", 0)).await;
+        assert_eq!(status, Status::Error as u8);
+        assert!(message.starts_with(b"NOPERM"));
+        assert_eq!(client.read(&mut [0u8; 1]).await.unwrap(), 0);
+    }
+}
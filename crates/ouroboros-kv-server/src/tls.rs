@@ -0,0 +1,144 @@
+//! TLS for client and replication connections
+//!
+//! Certificates and keys are read from PEM files. Both listeners use the same
+//! certificate; replicas verify their primary against a CA bundle.
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// A connection that may or may not be encrypted
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(invalid(format!("No certificates found in {}", path.display())));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> io::Result<PrivateKeyDer<'static>> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)?
+        .ok_or_else(|| invalid(format!("No private key found in {}", path.display())))
+}
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Acceptor for a certificate chain and its private key
+pub fn acceptor(cert: &Path, key: &Path) -> io::Result<TlsAcceptor> {
+    let config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(e.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(load_certs(cert)?, load_key(key)?)
+        .map_err(|e| invalid(format!("Invalid TLS certificate or key: {}", e)))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Connector trusting the certificates in a CA bundle
+pub fn connector(ca: &Path) -> io::Result<TlsConnector> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca)? {
+        roots.add(cert).map_err(|e| invalid(format!("Invalid CA certificate: {}", e)))?;
+    }
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(e.to_string()))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Write a whole frame and flush it (TLS streams buffer writes)
+pub async fn send(stream: &mut (impl Stream + ?Sized), frame: &[u8]) -> io::Result<()> {
+    stream.write_all(frame).await?;
+    stream.flush().await
+}
+
+/// Treat a peer closing without a TLS `close_notify` as end of stream
+///
+/// Most clients simply drop the connection. Requests are self-delimiting,
+/// so a request cut off this way is never executed.
+pub fn clean_eof(read: io::Result<usize>) -> io::Result<usize> {
+    match read {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+        read => read,
+    }
+}
+
+/// Set up an accepted connection, completing the TLS handshake if enabled
+pub async fn accept(socket: TcpStream, tls: Option<&TlsAcceptor>) -> io::Result<Box<dyn Stream>> {
+    // Disable Nagle's algorithm for lower latency
+    socket.set_nodelay(true)?;
+    match tls {
+        Some(acceptor) => Ok(Box::new(acceptor.accept(socket).await?)),
+        None => Ok(Box::new(socket)),
+    }
+}
+
+/// Connect to `addr` (host:port), over TLS if a connector is given
+///
+/// The host part is the name the server certificate must be valid for.
+pub async fn connect(addr: &str, tls: Option<&TlsConnector>) -> io::Result<Box<dyn Stream>> {
+    let stream = TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+    let Some(connector) = tls else {
+        return Ok(Box::new(stream));
+    };
+
+    let host = addr.rsplit_once(':').map(|(host, _)| host).unwrap_or(addr);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let name = ServerName::try_from(host.to_string()).map_err(|e| invalid(format!("Invalid server name {:?}: {}", host, e)))?;
+    Ok(Box::new(connector.connect(name, stream).await?))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Test CA, and a certificate it signed for localhost and 127.0.0.1
+    pub(crate) fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name)
+    }
+
+    #[tokio::test]
+    async fn test_tls_roundtrip() {
+        let acceptor = acceptor(&fixture("server.pem"), &fixture("server.key")).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(socket).await.unwrap();
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            send(&mut stream, &buf).await.unwrap();
+        });
+
+        let connector = connector(&fixture("ca.pem")).unwrap();
+        let mut stream = connect(&addr.to_string(), Some(&connector)).await.unwrap();
+        send(&mut stream, b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+}
@@ -0,0 +1,308 @@
+//! Hashes, lists, sets and sorted sets
+//!
+//! Collections are ordinary values: hashes are [`KvValue::Map`], lists
+//! [`KvValue::List`], sets [`KvValue::Set`] and sorted sets
+//! [`KvValue::SortedSet`]. The engine's structure commands (`hset`, `lpush`,
+//! `sadd`, `zadd`, ...) change them in place under the shard lock, so each
+//! command is atomic, and log themselves to the WAL as one of the op types
+//! below. Like in Redis, writing to a missing key creates the collection and
+//! a write that leaves it empty removes the key.
+
+use crate::error::KvError;
+use crate::types::{KvValue, SortedSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Write to a hash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HashOp {
+    Set { fields: Vec<(String, KvValue)> },
+    SetNx { field: String, value: KvValue },
+    Delete { fields: Vec<String> },
+    IncrBy { field: String, delta: i64 },
+}
+
+/// Write to a list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ListOp {
+    /// Push values one by one onto the head (`front`) or tail
+    Push { values: Vec<KvValue>, front: bool },
+    /// Remove up to `count` values from the head (`front`) or tail
+    Pop { count: usize, front: bool },
+    /// Keep only the elements from `start` to `stop` (inclusive, negative counts from the end)
+    Trim { start: i64, stop: i64 },
+}
+
+/// Write to a set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SetOp {
+    Add { members: Vec<String> },
+    Remove { members: Vec<String> },
+}
+
+/// Write to a sorted set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ZSetOp {
+    /// Add members or update their scores
+    Add { members: Vec<(f64, String)> },
+    IncrBy { member: String, delta: f64 },
+    Remove { members: Vec<String> },
+}
+
+/// Result of a write and whether it changed the collection
+pub(crate) type Applied<R> = Result<(R, bool), KvError>;
+
+fn wrong_type(expected: &str, actual: &KvValue) -> KvError {
+    KvError::TypeMismatch {
+        expected: expected.to_string(),
+        actual: actual.type_name().to_string(),
+    }
+}
+
+/// Whether a value is a collection with no elements (its key gets removed)
+pub(crate) fn is_empty_collection(value: &KvValue) -> bool {
+    match value {
+        KvValue::Map(map) => map.is_empty(),
+        KvValue::List(items) => items.is_empty(),
+        KvValue::Set(members) => members.is_empty(),
+        KvValue::SortedSet(zset) => zset.is_empty(),
+        _ => false,
+    }
+}
+
+/// Resolve Redis-style inclusive `start..=stop` indexes against a length
+///
+/// Negative indexes count from the end. Returns None if the range is empty.
+pub(crate) fn resolve_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+    (start <= stop && start < len).then(|| (start as usize, stop as usize))
+}
+
+// ==================== Type views ====================
+
+pub(crate) fn as_hash(value: &KvValue) -> Result<&HashMap<String, KvValue>, KvError> {
+    match value {
+        KvValue::Map(map) => Ok(map),
+        other => Err(wrong_type("hash", other)),
+    }
+}
+
+pub(crate) fn as_list(value: &KvValue) -> Result<&Vec<KvValue>, KvError> {
+    match value {
+        KvValue::List(items) => Ok(items),
+        other => Err(wrong_type("list", other)),
+    }
+}
+
+pub(crate) fn as_set(value: &KvValue) -> Result<&HashSet<String>, KvError> {
+    match value {
+        KvValue::Set(members) => Ok(members),
+        other => Err(wrong_type("set", other)),
+    }
+}
+
+pub(crate) fn as_zset(value: &KvValue) -> Result<&SortedSet, KvError> {
+    match value {
+        KvValue::SortedSet(zset) => Ok(zset),
+        other => Err(wrong_type("zset", other)),
+    }
+}
+
+/// Collection stored in `slot`, created empty if the key is missing
+fn hash_mut(slot: &mut Option<KvValue>) -> Result<&mut HashMap<String, KvValue>, KvError> {
+    match slot.get_or_insert_with(|| KvValue::Map(HashMap::new())) {
+        KvValue::Map(map) => Ok(map),
+        other => Err(wrong_type("hash", other)),
+    }
+}
+
+fn list_mut(slot: &mut Option<KvValue>) -> Result<&mut Vec<KvValue>, KvError> {
+    match slot.get_or_insert_with(|| KvValue::List(Vec::new())) {
+        KvValue::List(items) => Ok(items),
+        other => Err(wrong_type("list", other)),
+    }
+}
+
+fn set_mut(slot: &mut Option<KvValue>) -> Result<&mut HashSet<String>, KvError> {
+    match slot.get_or_insert_with(|| KvValue::Set(HashSet::new())) {
+        KvValue::Set(members) => Ok(members),
+        other => Err(wrong_type("set", other)),
+    }
+}
+
+fn zset_mut(slot: &mut Option<KvValue>) -> Result<&mut SortedSet, KvError> {
+    match slot.get_or_insert_with(|| KvValue::SortedSet(SortedSet::new())) {
+        KvValue::SortedSet(zset) => Ok(zset),
+        other => Err(wrong_type("zset", other)),
+    }
+}
+
+// ==================== Writes ====================
+//
+// Each write validates its arguments before touching the collection, so an
+// error leaves the value unchanged.
+
+/// Returns the number of fields that were added (not updated)
+pub(crate) fn hset(slot: &mut Option<KvValue>, fields: Vec<(String, KvValue)>) -> Applied<usize> {
+    let map = hash_mut(slot)?;
+    let changed = !fields.is_empty();
+    let added = fields
+        .into_iter()
+        .filter(|(field, value)| map.insert(field.clone(), value.clone()).is_none())
+        .count();
+    Ok((added, changed))
+}
+
+pub(crate) fn hsetnx(slot: &mut Option<KvValue>, field: String, value: KvValue) -> Applied<bool> {
+    let map = hash_mut(slot)?;
+    if map.contains_key(&field) {
+        return Ok((false, false));
+    }
+    map.insert(field, value);
+    Ok((true, true))
+}
+
+pub(crate) fn hdel(slot: &mut Option<KvValue>, fields: &[String]) -> Applied<usize> {
+    let map = hash_mut(slot)?;
+    let removed = fields.iter().filter(|field| map.remove(*field).is_some()).count();
+    Ok((removed, removed > 0))
+}
+
+/// Returns the field's new value; a missing field counts as 0
+pub(crate) fn hincrby(slot: &mut Option<KvValue>, field: String, delta: i64) -> Applied<i64> {
+    let map = hash_mut(slot)?;
+    let current = match map.get(&field) {
+        None => 0,
+        Some(KvValue::Int(n)) => *n,
+        Some(other) => return Err(wrong_type("int", other)),
+    };
+    let value = current.saturating_add(delta);
+    map.insert(field, KvValue::Int(value));
+    Ok((value, true))
+}
+
+/// Returns the list's new length
+pub(crate) fn push(slot: &mut Option<KvValue>, values: Vec<KvValue>, front: bool) -> Applied<usize> {
+    let items = list_mut(slot)?;
+    let changed = !values.is_empty();
+    if front {
+        items.splice(0..0, values.into_iter().rev());
+    } else {
+        items.extend(values);
+    }
+    Ok((items.len(), changed))
+}
+
+pub(crate) fn pop(slot: &mut Option<KvValue>, count: usize, front: bool) -> Applied<Vec<KvValue>> {
+    let items = list_mut(slot)?;
+    let count = count.min(items.len());
+    let popped: Vec<KvValue> = if front {
+        items.drain(..count).collect()
+    } else {
+        items.drain(items.len() - count..).rev().collect()
+    };
+    let changed = !popped.is_empty();
+    Ok((popped, changed))
+}
+
+pub(crate) fn ltrim(slot: &mut Option<KvValue>, start: i64, stop: i64) -> Applied<()> {
+    let items = list_mut(slot)?;
+    let len = items.len();
+    match resolve_range(start, stop, len) {
+        Some((start, stop)) => {
+            items.truncate(stop + 1);
+            items.drain(..start);
+        }
+        None => items.clear(),
+    }
+    Ok(((), items.len() != len))
+}
+
+/// Returns the number of members that were added
+pub(crate) fn sadd(slot: &mut Option<KvValue>, members: Vec<String>) -> Applied<usize> {
+    let set = set_mut(slot)?;
+    let added = members.into_iter().filter(|member| set.insert(member.clone())).count();
+    Ok((added, added > 0))
+}
+
+pub(crate) fn srem(slot: &mut Option<KvValue>, members: &[String]) -> Applied<usize> {
+    let set = set_mut(slot)?;
+    let removed = members.iter().filter(|member| set.remove(*member)).count();
+    Ok((removed, removed > 0))
+}
+
+/// Returns the number of members that were added (not updated)
+pub(crate) fn zadd(slot: &mut Option<KvValue>, members: Vec<(f64, String)>) -> Applied<usize> {
+    if members.iter().any(|(score, _)| score.is_nan()) {
+        return Err(KvError::InvalidArgument("score is not a number".to_string()));
+    }
+    let zset = zset_mut(slot)?;
+    let changed = members
+        .iter()
+        .any(|(score, member)| zset.score(member) != Some(*score + 0.0));
+    let added = members
+        .into_iter()
+        .filter(|(score, member)| zset.insert(member.clone(), *score))
+        .count();
+    Ok((added, changed))
+}
+
+/// Returns the member's new score; a missing member starts at 0
+pub(crate) fn zincrby(slot: &mut Option<KvValue>, member: String, delta: f64) -> Applied<f64> {
+    let zset = zset_mut(slot)?;
+    let score = zset.score(&member).unwrap_or(0.0) + delta;
+    if score.is_nan() {
+        return Err(KvError::InvalidArgument("resulting score is not a number".to_string()));
+    }
+    zset.insert(member, score);
+    Ok((score, true))
+}
+
+pub(crate) fn zrem(slot: &mut Option<KvValue>, members: &[String]) -> Applied<usize> {
+    let zset = zset_mut(slot)?;
+    let removed = members.iter().filter(|member| zset.remove(member)).count();
+    Ok((removed, removed > 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_range() {
+        assert_eq!(resolve_range(0, -1, 5), Some((0, 4)));
+        assert_eq!(resolve_range(-2, -1, 5), Some((3, 4)));
+        assert_eq!(resolve_range(1, 100, 5), Some((1, 4)));
+        assert_eq!(resolve_range(-100, 1, 5), Some((0, 1)));
+        assert_eq!(resolve_range(3, 1, 5), None);
+        assert_eq!(resolve_range(5, 10, 5), None);
+        assert_eq!(resolve_range(0, -1, 0), None);
+    }
+
+    #[test]
+    fn test_writes_leave_value_unchanged_on_error() {
+        let mut slot = Some(KvValue::Map(HashMap::from([("name".to_string(), KvValue::String("x".into()))])));
+        assert!(hincrby(&mut slot, "name".to_string(), 1).is_err());
+        assert!(push(&mut slot, vec![KvValue::Int(1)], true).is_err());
+        assert_eq!(slot, Some(KvValue::Map(HashMap::from([("name".to_string(), KvValue::String("x".into()))]))));
+
+        let mut slot = None;
+        assert!(zadd(&mut slot, vec![(1.0, "a".to_string()), (f64::NAN, "b".to_string())]).is_err());
+        assert_eq!(slot, None);
+    }
+
+    #[test]
+    fn test_push_pop_order() {
+        let mut slot = None;
+        let values = |items: &[i64]| items.iter().map(|n| KvValue::Int(*n)).collect::<Vec<_>>();
+        assert_eq!(push(&mut slot, values(&[1, 2, 3]), true).unwrap(), (3, true));
+        assert_eq!(push(&mut slot, values(&[4]), false).unwrap(), (4, true));
+        assert_eq!(slot, Some(KvValue::List(values(&[3, 2, 1, 4]))));
+
+        assert_eq!(pop(&mut slot, 2, false).unwrap(), (values(&[4, 1]), true));
+        assert_eq!(pop(&mut slot, 5, true).unwrap(), (values(&[3, 2]), true));
+        assert_eq!(pop(&mut slot, 1, true).unwrap(), (vec![], false));
+    }
+}
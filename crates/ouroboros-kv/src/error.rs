@@ -26,6 +26,9 @@ pub enum KvError {
     #[error("Lock held by different owner")]
     LockOwnerMismatch { expected: String, actual: String },
 
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
     #[error("Storage error: {0}")]
     Storage(String),
 }
@@ -0,0 +1,48 @@
+//! Keyspace notifications
+//!
+//! Lets an embedder observe changes to the keyspace, e.g. to publish cache
+//! invalidation messages. Events are emitted by the shard that owns the key
+//! while its lock is held, so events for a single key arrive in the order the
+//! changes were applied.
+//!
+//! Listeners run on the writer's thread and must not block or call back into
+//! the engine.
+
+use std::fmt;
+
+/// What happened to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyspaceEventKind {
+    /// Key was written (SET, MSET, SETNX, INCR/DECR, CAS, LOCK)
+    Set,
+    /// Key was explicitly removed (DEL, MDEL, UNLOCK)
+    Delete,
+    /// Key was removed because its TTL elapsed
+    Expire,
+    /// Key was removed to satisfy memory or key limits
+    Evict,
+}
+
+impl KeyspaceEventKind {
+    /// Event name used on the wire
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyspaceEventKind::Set => "set",
+            KeyspaceEventKind::Delete => "del",
+            KeyspaceEventKind::Expire => "expired",
+            KeyspaceEventKind::Evict => "evicted",
+        }
+    }
+}
+
+impl fmt::Display for KeyspaceEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Receives keyspace events from an engine
+pub trait KeyspaceListener: Send + Sync {
+    /// Called after `key` changed
+    fn on_event(&self, kind: KeyspaceEventKind, key: &str);
+}
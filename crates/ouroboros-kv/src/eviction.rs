@@ -0,0 +1,240 @@
+//! Memory limits and eviction policies
+//!
+//! Bounds the engine by memory and/or key count. Limits are split evenly
+//! across shards and enforced on every write to the shard that received it,
+//! so no global coordination is needed.
+//!
+//! ## Policies
+//!
+//! - `allkeys-lru`: evict the least recently used key
+//! - `volatile-lru`: evict the least recently used key that has a TTL
+//! - `allkeys-lfu`: evict the least frequently used key
+//! - `volatile-lfu`: evict the least frequently used key that has a TTL
+//! - `volatile-ttl`: evict the key closest to expiring
+//!
+//! Access frequency decays by half for every minute a key goes unused, so
+//! keys that were popular a long time ago eventually become evictable.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+/// Fraction of the limit a shard is trimmed down to once it is exceeded
+///
+/// Evicting slightly more than necessary amortises the victim scan over
+/// several writes instead of paying for it on every insert at the limit.
+const EVICTION_TARGET_RATIO: f64 = 0.95;
+
+/// Microseconds of idleness that halve an entry's access frequency
+const LFU_DECAY_MICROS: u64 = 60 * 1_000_000;
+
+/// Which keys to evict when a shard exceeds its limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Least recently used among all keys
+    #[default]
+    AllKeysLru,
+    /// Least recently used among keys with a TTL
+    VolatileLru,
+    /// Least frequently used among all keys
+    AllKeysLfu,
+    /// Least frequently used among keys with a TTL
+    VolatileLfu,
+    /// Shortest remaining TTL first
+    VolatileTtl,
+}
+
+impl EvictionPolicy {
+    /// Policy name as accepted by [`FromStr`]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+            EvictionPolicy::AllKeysLfu => "allkeys-lfu",
+            EvictionPolicy::VolatileLfu => "volatile-lfu",
+            EvictionPolicy::VolatileTtl => "volatile-ttl",
+        }
+    }
+
+    /// Eviction rank of an entry (lower is evicted first)
+    ///
+    /// Returns None if the policy never evicts this entry.
+    pub(crate) fn rank(
+        &self,
+        last_access: u64,
+        hits: u32,
+        expires_at: Option<Instant>,
+        now_micros: u64,
+    ) -> Option<(u64, u64)> {
+        match self {
+            EvictionPolicy::AllKeysLru => Some((last_access, 0)),
+            EvictionPolicy::VolatileLru => expires_at.map(|_| (last_access, 0)),
+            EvictionPolicy::AllKeysLfu => Some((decayed_hits(hits, last_access, now_micros), last_access)),
+            EvictionPolicy::VolatileLfu => expires_at
+                .map(|_| (decayed_hits(hits, last_access, now_micros), last_access)),
+            EvictionPolicy::VolatileTtl => expires_at.map(|exp| {
+                let remaining = exp.saturating_duration_since(Instant::now());
+                (remaining.as_micros() as u64, last_access)
+            }),
+        }
+    }
+}
+
+/// Usage a shard is trimmed down to once it exceeds `max`
+fn eviction_target(max: usize) -> usize {
+    ((max as f64 * EVICTION_TARGET_RATIO) as usize).max(1)
+}
+
+/// Access count halved for every decay period the entry went unused
+fn decayed_hits(hits: u32, last_access: u64, now_micros: u64) -> u64 {
+    let idle_periods = now_micros.saturating_sub(last_access) / LFU_DECAY_MICROS;
+    (hits as u64) >> idle_periods.min(32)
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "allkeys-lru" => Ok(EvictionPolicy::AllKeysLru),
+            "volatile-lru" => Ok(EvictionPolicy::VolatileLru),
+            "allkeys-lfu" => Ok(EvictionPolicy::AllKeysLfu),
+            "volatile-lfu" => Ok(EvictionPolicy::VolatileLfu),
+            "volatile-ttl" => Ok(EvictionPolicy::VolatileTtl),
+            other => Err(format!(
+                "Unknown eviction policy '{}' (expected allkeys-lru, volatile-lru, allkeys-lfu, volatile-lfu or volatile-ttl)",
+                other
+            )),
+        }
+    }
+}
+
+/// Engine-wide limits
+#[derive(Debug, Clone, Default)]
+pub struct EvictionConfig {
+    /// Maximum estimated memory for resident entries (None = unbounded)
+    pub max_memory_bytes: Option<usize>,
+
+    /// Maximum number of keys (None = unbounded)
+    pub max_keys: Option<usize>,
+
+    /// How victims are chosen
+    pub policy: EvictionPolicy,
+}
+
+impl EvictionConfig {
+    /// Create an unbounded config with the given policy
+    pub fn new(policy: EvictionPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Set maximum memory in bytes
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    /// Set maximum key count
+    pub fn with_max_keys(mut self, keys: usize) -> Self {
+        self.max_keys = Some(keys);
+        self
+    }
+
+    /// Split the limits evenly across shards (rounding up, minimum 1)
+    pub(crate) fn per_shard(&self, num_shards: usize) -> ShardLimits {
+        let split = |total: usize| total.div_ceil(num_shards.max(1)).max(1);
+        ShardLimits {
+            max_bytes: self.max_memory_bytes.map(split),
+            max_keys: self.max_keys.map(split),
+            policy: self.policy,
+        }
+    }
+}
+
+/// Limits applied to a single shard
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ShardLimits {
+    pub max_bytes: Option<usize>,
+    pub max_keys: Option<usize>,
+    pub policy: EvictionPolicy,
+}
+
+impl ShardLimits {
+    /// Whether a shard with this usage must evict
+    pub fn exceeded(&self, bytes: usize, keys: usize) -> bool {
+        self.max_bytes.map(|max| bytes > max).unwrap_or(false)
+            || self.max_keys.map(|max| keys > max).unwrap_or(false)
+    }
+
+    /// Whether a shard with this usage has been trimmed far enough
+    pub fn satisfied(&self, bytes: usize, keys: usize) -> bool {
+        self.max_bytes.map(|max| bytes <= eviction_target(max)).unwrap_or(true)
+            && !self.keys_exceeded(keys)
+    }
+
+    /// Whether the key count is still above its eviction target
+    pub fn keys_exceeded(&self, keys: usize) -> bool {
+        self.max_keys
+            .map(|max| keys > eviction_target(max))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_policy_parsing() {
+        for policy in [
+            EvictionPolicy::AllKeysLru,
+            EvictionPolicy::VolatileLru,
+            EvictionPolicy::AllKeysLfu,
+            EvictionPolicy::VolatileLfu,
+            EvictionPolicy::VolatileTtl,
+        ] {
+            assert_eq!(policy.as_str().parse::<EvictionPolicy>().unwrap(), policy);
+        }
+        assert_eq!("ALLKEYS-LRU".parse::<EvictionPolicy>().unwrap(), EvictionPolicy::AllKeysLru);
+        assert!("random".parse::<EvictionPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_volatile_policies_skip_persistent_keys() {
+        let now = Instant::now();
+        assert!(EvictionPolicy::VolatileLru.rank(0, 1, None, 0).is_none());
+        assert!(EvictionPolicy::VolatileLfu.rank(0, 1, None, 0).is_none());
+        assert!(EvictionPolicy::VolatileTtl.rank(0, 1, None, 0).is_none());
+        assert!(EvictionPolicy::VolatileTtl
+            .rank(0, 1, Some(now + Duration::from_secs(1)), 0)
+            .is_some());
+    }
+
+    #[test]
+    fn test_lfu_decay() {
+        assert_eq!(decayed_hits(8, 0, 0), 8);
+        assert_eq!(decayed_hits(8, 0, LFU_DECAY_MICROS), 4);
+        assert_eq!(decayed_hits(8, 0, 3 * LFU_DECAY_MICROS), 1);
+    }
+
+    #[test]
+    fn test_per_shard_limits() {
+        let limits = EvictionConfig::new(EvictionPolicy::AllKeysLru)
+            .with_max_keys(10)
+            .per_shard(4);
+        assert_eq!(limits.max_keys, Some(3));
+        assert_eq!(limits.max_bytes, None);
+        assert!(limits.exceeded(0, 4));
+        assert!(!limits.exceeded(0, 3));
+    }
+}
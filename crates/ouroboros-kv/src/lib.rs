@@ -4,7 +4,13 @@
 //! - Sharded storage engine for multi-core scalability
 //! - High-precision numeric types (Decimal, f64, i64)
 //! - Hybrid tiered storage (RAM + Disk)
+//! - Memory/key limits with LRU, LFU and TTL eviction
+//! - Cursor-based key scanning with glob patterns
+//! - Keyspace notifications for cache invalidation
+//! - Primary/replica replication over the WAL stream
 //! - Compare-and-swap (CAS) for atomic state transitions
+//! - Atomic multi-key transactions with optimistic WATCH
+//! - Hashes, lists, sets and sorted sets with in-place updates
 //! - Zero-copy serialization
 
 // WIP: Suppress clippy warnings during development
@@ -14,11 +20,25 @@ pub mod engine;
 pub mod types;
 pub mod error;
 pub mod persistence;
+pub mod tiered;
+pub mod eviction;
+pub mod pattern;
+pub mod events;
+pub mod replication;
+pub mod transaction;
+pub mod collections;
 
 pub use ouroboros_common::{DataBridgeError, Result};
-pub use engine::KvEngine;
-pub use types::{KvKey, KvValue};
+pub use engine::{KvEngine, SetCondition};
+pub use types::{KvKey, KvValue, SortedSet};
 pub use error::KvError;
+pub use tiered::{TieredConfig, TieredStats};
+pub use eviction::{EvictionConfig, EvictionPolicy};
+pub use pattern::GlobPattern;
+pub use events::{KeyspaceEventKind, KeyspaceListener};
+pub use replication::{ReplicationLog, ReplicationPosition};
+pub use transaction::{Guard, Transaction, TxnOp, TxnResult};
+pub use collections::{HashOp, ListOp, SetOp, ZSetOp};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,222 @@
+//! Glob-style key patterns for SCAN/KEYS
+//!
+//! Supports the Redis pattern syntax:
+//!
+//! - `*` matches any sequence of characters (including none)
+//! - `?` matches exactly one character
+//! - `[abc]`, `[a-z]` match one character from a set or range
+//! - `[^abc]` / `[!abc]` match one character not in the set
+//! - `\x` matches `x` literally
+//!
+//! An unterminated `[` is treated as a literal character.
+
+/// Special characters that must be escaped to match literally
+const SPECIAL_CHARS: &[char] = &['*', '?', '[', ']', '\\'];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnySequence,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl Token {
+    fn matches_char(&self, c: char) -> bool {
+        match self {
+            Token::Literal(l) => *l == c,
+            Token::AnyChar => true,
+            Token::AnySequence => true,
+            Token::Class { negated, ranges } => {
+                ranges.iter().any(|(lo, hi)| *lo <= c && c <= *hi) != *negated
+            }
+        }
+    }
+}
+
+/// Compiled glob pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobPattern {
+    tokens: Vec<Token>,
+}
+
+impl GlobPattern {
+    /// Compile a pattern
+    pub fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '*' => {
+                    // Consecutive stars are equivalent to one
+                    if tokens.last() != Some(&Token::AnySequence) {
+                        tokens.push(Token::AnySequence);
+                    }
+                    i += 1;
+                }
+                '?' => {
+                    tokens.push(Token::AnyChar);
+                    i += 1;
+                }
+                '\\' if i + 1 < chars.len() => {
+                    tokens.push(Token::Literal(chars[i + 1]));
+                    i += 2;
+                }
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((token, consumed)) => {
+                        tokens.push(token);
+                        i += 1 + consumed;
+                    }
+                    None => {
+                        tokens.push(Token::Literal('['));
+                        i += 1;
+                    }
+                },
+                c => {
+                    tokens.push(Token::Literal(c));
+                    i += 1;
+                }
+            }
+        }
+
+        Self { tokens }
+    }
+
+    /// Whether the pattern matches every key
+    pub fn matches_all(&self) -> bool {
+        self.tokens.iter().all(|t| *t == Token::AnySequence)
+    }
+
+    /// Check whether a key matches the pattern
+    pub fn matches(&self, key: &str) -> bool {
+        let chars: Vec<char> = key.chars().collect();
+        let (mut p, mut k) = (0, 0);
+        // Position of the last `*` and the key index it is currently absorbing up to
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while k < chars.len() {
+            match self.tokens.get(p) {
+                Some(Token::AnySequence) => {
+                    backtrack = Some((p, k));
+                    p += 1;
+                }
+                Some(token) if token.matches_char(chars[k]) => {
+                    p += 1;
+                    k += 1;
+                }
+                _ => match backtrack {
+                    // Let the last star absorb one more character and retry
+                    Some((star, absorbed)) => {
+                        p = star + 1;
+                        k = absorbed + 1;
+                        backtrack = Some((star, absorbed + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        self.tokens[p..].iter().all(|t| *t == Token::AnySequence)
+    }
+}
+
+/// Parse the body of a `[...]` class, returning the token and characters consumed
+/// (including the closing bracket)
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('^') | Some('!'));
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    while i < chars.len() {
+        let c = match chars[i] {
+            ']' => return Some((Token::Class { negated, ranges }, i + 1)),
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                chars[i]
+            }
+            c => c,
+        };
+
+        if i + 2 < chars.len() && chars[i + 1] == '-' && chars[i + 2] != ']' {
+            let end = chars[i + 2];
+            ranges.push((c.min(end), c.max(end)));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Escape a literal string so it can be embedded in a pattern
+pub fn escape(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, key: &str) -> bool {
+        GlobPattern::new(pattern).matches(key)
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "anything"));
+        assert!(matches("user:*", "user:42"));
+        assert!(!matches("user:*", "session:42"));
+        assert!(matches("*:42", "user:42"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxbyy"));
+        assert!(matches("h?llo", "hello"));
+        assert!(!matches("h?llo", "hllo"));
+        assert!(matches("exact", "exact"));
+        assert!(!matches("exact", "exactly"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(matches("h[ae]llo", "hallo"));
+        assert!(!matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(!matches("h[!e]llo", "hello"));
+        assert!(matches("key[0-9]", "key7"));
+        assert!(!matches("key[0-9]", "keyx"));
+        // Unterminated class is literal
+        assert!(matches("key[", "key["));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches("a\\*b", "a*b"));
+        assert!(!matches("a\\*b", "axb"));
+
+        let prefix = escape("ns[1]*");
+        assert_eq!(prefix, "ns\\[1\\]\\*");
+        assert!(matches(&format!("{}:*", prefix), "ns[1]*:key"));
+        assert!(!matches(&format!("{}:*", prefix), "ns1x:key"));
+    }
+
+    #[test]
+    fn test_matches_all() {
+        assert!(GlobPattern::new("*").matches_all());
+        assert!(GlobPattern::new("**").matches_all());
+        assert!(!GlobPattern::new("a*").matches_all());
+    }
+}
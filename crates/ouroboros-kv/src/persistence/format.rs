@@ -3,10 +3,13 @@
 ///! ## WAL File Format
 ///!
 ///! ```text
-///! Header: [Magic:8 | Version:4 | Created:8 | Reserved:12] = 32 bytes
+///! Header: [Magic:8 | Version:4 | Created:8 | BasePosition:8 | Reserved:4] = 32 bytes
 ///! Entry:  [Length:4 | Timestamp:8 | OpType:1 | Payload:N | CRC32:4]
 ///! ```
 ///!
+///! `BasePosition` is the WAL position of the segment's first byte, so positions
+///! keep growing across segments (files written before segmentation have 0).
+///!
 ///! ## Snapshot File Format
 ///!
 ///! ```text
@@ -15,6 +18,7 @@
 ///! Shard:  [ShardID:4 | EntryCount:4 | Entries using bincode]
 ///! ```
 
+use crate::collections::{HashOp, ListOp, SetOp, ZSetOp};
 use crate::types::KvValue;
 use crate::persistence::{PersistenceError, Result};
 use crc32fast::Hasher;
@@ -29,6 +33,9 @@ pub const WAL_MAGIC: &[u8; 8] = b"KVWAL001";
 /// WAL format version
 pub const WAL_VERSION: u32 = 1;
 
+/// Size of the WAL file header in bytes
+pub const WAL_HEADER_SIZE: u64 = 32;
+
 /// Snapshot file magic number: "KVSNAP01"
 pub const SNAPSHOT_MAGIC: &[u8; 8] = b"KVSNAP01";
 
@@ -49,6 +56,13 @@ pub enum WalOpType {
     Lock = 8,
     Unlock = 9,
     ExtendLock = 10,
+    Expire = 11,
+    Transaction = 12,
+    HashWrite = 13,
+    ListWrite = 14,
+    SetWrite = 15,
+    ZSetWrite = 16,
+    SetAt = 17,
 }
 
 impl WalOpType {
@@ -64,6 +78,13 @@ impl WalOpType {
             8 => Some(WalOpType::Lock),
             9 => Some(WalOpType::Unlock),
             10 => Some(WalOpType::ExtendLock),
+            11 => Some(WalOpType::Expire),
+            12 => Some(WalOpType::Transaction),
+            13 => Some(WalOpType::HashWrite),
+            14 => Some(WalOpType::ListWrite),
+            15 => Some(WalOpType::SetWrite),
+            16 => Some(WalOpType::ZSetWrite),
+            17 => Some(WalOpType::SetAt),
             _ => None,
         }
     }
@@ -114,6 +135,38 @@ pub enum WalOp {
         owner: String,
         ttl: Duration,
     },
+    /// Set (or with None, remove) the expiry of an existing key
+    Expire {
+        key: String,
+        ttl: Option<Duration>,
+    },
+    /// Resulting `Set`/`Delete` of every key a transaction changed, applied together
+    Transaction {
+        ops: Vec<WalOp>,
+    },
+    HashWrite {
+        key: String,
+        op: HashOp,
+    },
+    ListWrite {
+        key: String,
+        op: ListOp,
+    },
+    SetWrite {
+        key: String,
+        op: SetOp,
+    },
+    ZSetWrite {
+        key: String,
+        op: ZSetOp,
+    },
+    /// Set with an absolute expiry in Unix milliseconds, so the entry expires
+    /// at the same moment however late it is applied
+    SetAt {
+        key: String,
+        value: KvValue,
+        expires_at_ms: Option<u64>,
+    },
 }
 
 impl WalOp {
@@ -129,10 +182,56 @@ impl WalOp {
             WalOp::Lock { .. } => WalOpType::Lock,
             WalOp::Unlock { .. } => WalOpType::Unlock,
             WalOp::ExtendLock { .. } => WalOpType::ExtendLock,
+            WalOp::Expire { .. } => WalOpType::Expire,
+            WalOp::Transaction { .. } => WalOpType::Transaction,
+            WalOp::HashWrite { .. } => WalOpType::HashWrite,
+            WalOp::ListWrite { .. } => WalOpType::ListWrite,
+            WalOp::SetWrite { .. } => WalOpType::SetWrite,
+            WalOp::ZSetWrite { .. } => WalOpType::ZSetWrite,
+            WalOp::SetAt { .. } => WalOpType::SetAt,
+        }
+    }
+
+    /// Keys the operation writes to
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            WalOp::MSet { pairs, .. } => pairs.iter().map(|(key, _)| key.as_str()).collect(),
+            WalOp::MDel { keys } => keys.iter().map(String::as_str).collect(),
+            WalOp::Transaction { ops } => ops.iter().flat_map(WalOp::keys).collect(),
+            WalOp::Set { key, .. }
+            | WalOp::Delete { key }
+            | WalOp::Incr { key, .. }
+            | WalOp::Decr { key, .. }
+            | WalOp::SetNx { key, .. }
+            | WalOp::Lock { key, .. }
+            | WalOp::Unlock { key, .. }
+            | WalOp::ExtendLock { key, .. }
+            | WalOp::Expire { key, .. }
+            | WalOp::HashWrite { key, .. }
+            | WalOp::ListWrite { key, .. }
+            | WalOp::SetWrite { key, .. }
+            | WalOp::ZSetWrite { key, .. }
+            | WalOp::SetAt { key, .. } => vec![key.as_str()],
         }
     }
 }
 
+/// Current time in Unix milliseconds
+pub fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Time left until `expires_at_ms` (Unix milliseconds), None once it has passed
+pub fn remaining_ttl(expires_at_ms: u64) -> Option<Duration> {
+    expires_at_ms
+        .checked_sub(unix_millis())
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis)
+}
+
 /// WAL entry with metadata
 #[derive(Debug, Clone)]
 pub struct WalEntry {
@@ -149,6 +248,7 @@ pub struct WalHeader {
     pub magic: [u8; 8],
     pub version: u32,
     pub created_at: i64, // Unix timestamp in seconds
+    pub base_position: u64, // WAL position of the segment's first byte
 }
 
 impl WalHeader {
@@ -160,14 +260,22 @@ impl WalHeader {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs() as i64,
+            base_position: 0,
         }
     }
 
+    /// Set the WAL position at which the segment starts
+    pub fn with_base_position(mut self, base_position: u64) -> Self {
+        self.base_position = base_position;
+        self
+    }
+
     pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
         writer.write_all(&self.magic)?;
         writer.write_all(&self.version.to_be_bytes())?;
         writer.write_all(&self.created_at.to_be_bytes())?;
-        writer.write_all(&[0u8; 12])?; // Reserved
+        writer.write_all(&self.base_position.to_be_bytes())?;
+        writer.write_all(&[0u8; 4])?; // Reserved
         Ok(())
     }
 
@@ -194,13 +302,18 @@ impl WalHeader {
         reader.read_exact(&mut created_bytes)?;
         let created_at = i64::from_be_bytes(created_bytes);
 
-        let mut reserved = [0u8; 12];
+        let mut base_bytes = [0u8; 8];
+        reader.read_exact(&mut base_bytes)?;
+        let base_position = u64::from_be_bytes(base_bytes);
+
+        let mut reserved = [0u8; 4];
         reader.read_exact(&mut reserved)?;
 
         Ok(Self {
             magic,
             version,
             created_at,
+            base_position,
         })
     }
 }
@@ -412,6 +525,12 @@ mod tests {
         let decoded = WalHeader::read(&mut buffer.as_slice()).unwrap();
         assert_eq!(header.magic, decoded.magic);
         assert_eq!(header.version, decoded.version);
+        assert_eq!(decoded.base_position, 0);
+
+        let mut buffer = Vec::new();
+        WalHeader::new().with_base_position(4096).write(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), WAL_HEADER_SIZE as usize);
+        assert_eq!(WalHeader::read(&mut buffer.as_slice()).unwrap().base_position, 4096);
     }
 
     #[test]
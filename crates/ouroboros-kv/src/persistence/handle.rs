@@ -4,9 +4,10 @@
 
 use super::{PersistenceConfig, PersistenceError, Result};
 use super::format::WalOp;
-use super::snapshot::SnapshotWriter;
-use super::wal::WalWriter;
+use super::snapshot::{oldest_snapshot_position, SnapshotWriter};
+use super::wal::{remove_segments_before, WalWriter};
 use crate::engine::KvEngine;
+use crate::replication::ReplicationPosition;
 use crossbeam_channel::{bounded, Sender, Receiver};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
@@ -24,6 +25,9 @@ enum PersistenceCommand {
     /// Create snapshot
     CreateSnapshot,
 
+    /// Flush WAL, then record a replica's position next to it
+    SavePosition(ReplicationPosition),
+
     /// Graceful shutdown
     Shutdown,
 }
@@ -48,7 +52,8 @@ impl PersistenceHandle {
     /// - Receives WAL operations through a channel
     /// - Batches writes and flushes every 100ms
     /// - Rotates WAL files at size threshold
-    /// - Creates periodic snapshots
+    /// - Creates periodic snapshots, then deletes or archives WAL segments
+    ///   that no kept snapshot needs
     pub fn new(config: PersistenceConfig, engine: Arc<KvEngine>) -> Result<Self> {
         info!("Starting persistence background thread");
 
@@ -94,6 +99,16 @@ impl PersistenceHandle {
         let _ = self.sender.try_send(PersistenceCommand::CreateSnapshot);
     }
 
+    /// Save a replica's position once everything logged so far is on disk
+    ///
+    /// Queued behind pending WAL operations (and snapshots), so the saved
+    /// position never runs ahead of the data it describes.
+    pub fn save_replication_position(&self, position: ReplicationPosition) {
+        if let Err(e) = self.sender.try_send(PersistenceCommand::SavePosition(position)) {
+            warn!("Failed to queue replication position: {}", e);
+        }
+    }
+
     /// Background persistence thread
     fn persistence_thread(
         config: PersistenceConfig,
@@ -168,6 +183,18 @@ impl PersistenceHandle {
                     last_snapshot = Instant::now();
                 }
 
+                Ok(PersistenceCommand::SavePosition(position)) => {
+                    match wal_writer.flush() {
+                        Ok(()) => {
+                            last_flush = Instant::now();
+                            if let Err(e) = position.save(&config.data_dir) {
+                                error!("Failed to save replication position: {}", e);
+                            }
+                        }
+                        Err(e) => error!("Failed to flush WAL: {}", e),
+                    }
+                }
+
                 Ok(PersistenceCommand::Shutdown) => {
                     info!("Shutdown signal received");
                     break;
@@ -234,6 +261,7 @@ impl PersistenceHandle {
         match snapshot_writer.create_snapshot(engine.as_ref(), &config.data_dir, wal_position) {
             Ok(path) => {
                 info!("Snapshot created: {}", path.display());
+                Self::compact_wal(config);
             }
             Err(e) => {
                 error!("Failed to create snapshot: {}", e);
@@ -241,6 +269,24 @@ impl PersistenceHandle {
         }
     }
 
+    /// Remove WAL segments that every kept snapshot is past
+    fn compact_wal(config: &PersistenceConfig) {
+        let position = match oldest_snapshot_position(&config.data_dir) {
+            Ok(Some(position)) => position,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to read snapshots for WAL compaction: {}", e);
+                return;
+            }
+        };
+
+        match remove_segments_before(&config.data_dir, position, config.wal_config.archive_dir.as_deref()) {
+            Ok(0) => {}
+            Ok(removed) => info!("Compacted WAL: removed {} segment(s) before position {}", removed, position),
+            Err(e) => error!("Failed to compact WAL: {}", e),
+        }
+    }
+
     /// Internal shutdown implementation
     fn shutdown_internal(&mut self) -> Result<()> {
         info!("Shutting down persistence handle");
@@ -295,6 +341,7 @@ mod tests {
             wal_config: WalConfig {
                 flush_interval_ms: 50, // Faster for testing
                 max_file_size: 1024 * 1024,
+                archive_dir: None,
             },
             ..Default::default()
         };
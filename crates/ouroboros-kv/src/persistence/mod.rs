@@ -4,10 +4,12 @@
 ///!
 ///! ## Architecture
 ///!
-///! - **WAL**: Append-only log recording all write operations
+///! - **WAL**: Append-only log recording all write operations, in size-rotated segments
 ///! - **Snapshots**: Periodic full engine state snapshots for faster recovery
+///! - **Compaction**: Segments older than every kept snapshot are deleted or archived
 ///! - **Batched Fsync**: 100ms flush intervals for optimal throughput
-///! - **Recovery**: Load snapshot + replay WAL delta
+///! - **Recovery**: Load snapshot + replay WAL delta, optionally up to a point in time
+///! - **Inspection**: Offline verification and dumps of WAL and snapshot files
 ///!
 ///! ## Durability Guarantee
 ///!
@@ -16,12 +18,14 @@
 
 pub mod format;
 pub mod handle;
+pub mod inspect;
 pub mod recovery;
 pub mod snapshot;
 pub mod wal;
 
 // Re-export key types for convenience
 pub use handle::PersistenceHandle;
+pub use recovery::{RecoveryTarget, RestoreOptions};
 
 use std::path::PathBuf;
 use std::time::Duration;
@@ -77,6 +81,18 @@ impl PersistenceConfig {
         self.snapshot_config.ops_threshold = ops;
         self
     }
+
+    /// Set the WAL segment size in bytes at which a new segment is started
+    pub fn with_wal_segment_size(mut self, bytes: u64) -> Self {
+        self.wal_config.max_file_size = bytes;
+        self
+    }
+
+    /// Move WAL segments no longer needed for recovery here instead of deleting them
+    pub fn with_wal_archive_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.wal_config.archive_dir = Some(dir.into());
+        self
+    }
 }
 
 /// WAL (Write-Ahead Log) configuration
@@ -87,6 +103,10 @@ pub struct WalConfig {
 
     /// Maximum WAL file size before rotation (default: 1GB)
     pub max_file_size: u64,
+
+    /// Directory that segments are moved to once every kept snapshot is past
+    /// them (default: None, they are deleted)
+    pub archive_dir: Option<PathBuf>,
 }
 
 impl Default for WalConfig {
@@ -94,6 +114,7 @@ impl Default for WalConfig {
         Self {
             flush_interval_ms: 100,
             max_file_size: 1024 * 1024 * 1024, // 1GB
+            archive_dir: None,
         }
     }
 }
@@ -136,6 +157,9 @@ pub struct RecoveryStats {
     /// Number of corrupted entries skipped
     pub corrupted_entries: usize,
 
+    /// WAL position recovery stopped at (where the next entry would start)
+    pub wal_position: u64,
+
     /// Total recovery duration
     pub recovery_duration: Duration,
 }
@@ -170,6 +194,9 @@ pub enum PersistenceError {
 
     #[error("Data directory error: {0}")]
     DataDirectory(String),
+
+    #[error("WAL gap: positions {from} to {to} are missing")]
+    WalGap { from: u64, to: u64 },
 }
 
 pub type Result<T> = std::result::Result<T, PersistenceError>;
@@ -1,16 +1,105 @@
 ///! Recovery orchestration
 ///!
-///! Coordinates loading snapshots and replaying WAL on startup.
+///! Coordinates loading snapshots and replaying WAL on startup, and restoring
+///! the state as of an earlier point in time.
 
 use super::{PersistenceError, Result, RecoveryStats};
-use super::format::WalOp;
-use super::snapshot::SnapshotLoader;
-use super::wal::{WalReader, find_wal_files};
+use super::format::{remaining_ttl, WalOp};
+use super::snapshot::{find_snapshot_files, SnapshotData, SnapshotHeader, SnapshotLoader};
+use super::wal::{list_segments, WalReader, WalSegment};
+use crate::collections::{HashOp, ListOp, SetOp, ZSetOp};
 use crate::engine::{Entry, KvEngine};
-use std::path::Path;
-use std::time::Instant;
+use crate::transaction::{Transaction, TxnOp};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, warn};
 
+/// How far recovery replays the WAL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryTarget {
+    /// Everything that was logged
+    #[default]
+    Latest,
+    /// Entries logged at or before this time
+    Time(SystemTime),
+    /// Entries that start before this WAL position
+    Position(u64),
+}
+
+impl RecoveryTarget {
+    /// Whether a snapshot was taken early enough to restore from
+    fn allows_snapshot(&self, header: &SnapshotHeader) -> bool {
+        match *self {
+            RecoveryTarget::Latest => true,
+            RecoveryTarget::Time(time) => header.created_at <= unix_nanos(time),
+            RecoveryTarget::Position(position) => header.wal_position <= position,
+        }
+    }
+
+    /// Whether a WAL entry lies past the target
+    fn excludes(&self, position: u64, timestamp: i64) -> bool {
+        match *self {
+            RecoveryTarget::Latest => false,
+            RecoveryTarget::Time(time) => timestamp > unix_nanos(time),
+            RecoveryTarget::Position(target) => position >= target,
+        }
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+/// Options for [`RecoveryManager::restore`]
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    /// How far to replay the WAL
+    pub target: RecoveryTarget,
+
+    /// Directory of archived WAL segments to read along with the data directory
+    pub archive_dir: Option<PathBuf>,
+}
+
+impl RestoreOptions {
+    /// Restore everything that was logged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restore the state as of `time`
+    pub fn until_time(mut self, time: SystemTime) -> Self {
+        self.target = RecoveryTarget::Time(time);
+        self
+    }
+
+    /// Restore the state as of WAL position `position`
+    pub fn until_position(mut self, position: u64) -> Self {
+        self.target = RecoveryTarget::Position(position);
+        self
+    }
+
+    /// Also read archived WAL segments from `dir`
+    pub fn with_archive_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.archive_dir = Some(dir.into());
+        self
+    }
+}
+
+/// Progress of replaying WAL segments
+#[derive(Default)]
+struct ReplayProgress {
+    replayed: usize,
+    corrupted: usize,
+    /// Position after the last entry read
+    position: u64,
+    /// Whether the recovery target was reached
+    done: bool,
+}
+
 /// Recovery manager for loading persisted state
 pub struct RecoveryManager;
 
@@ -28,22 +117,47 @@ impl RecoveryManager {
     pub fn recover(
         data_dir: impl AsRef<Path>,
         num_shards: usize,
+    ) -> Result<(KvEngine, RecoveryStats)> {
+        Self::recover_into(KvEngine::with_shards(num_shards), data_dir)
+    }
+
+    /// Recover persisted state into an existing, empty engine
+    ///
+    /// Use this when the engine needs configuring before data is loaded, e.g.
+    /// with tiered storage enabled so a dataset larger than RAM can be restored.
+    pub fn recover_into(
+        engine: KvEngine,
+        data_dir: impl AsRef<Path>,
+    ) -> Result<(KvEngine, RecoveryStats)> {
+        Self::restore(engine, data_dir, &RestoreOptions::default())
+    }
+
+    /// Restore persisted state into an empty engine, up to the options' target
+    ///
+    /// Loads the latest snapshot taken before the target, then replays the WAL
+    /// (including archived segments, if given) up to it. Unlike normal
+    /// recovery, a missing stretch of WAL before the target is an error.
+    ///
+    /// The data directory is only read. To carry on from a point-in-time
+    /// restore, snapshot the engine into a new data directory: restarting on
+    /// the original one would replay the later entries again.
+    pub fn restore(
+        engine: KvEngine,
+        data_dir: impl AsRef<Path>,
+        options: &RestoreOptions,
     ) -> Result<(KvEngine, RecoveryStats)> {
         let data_dir = data_dir.as_ref();
         let start = Instant::now();
+        let num_shards = engine.num_shards();
+        let target = options.target;
 
-        info!("Starting recovery from {}", data_dir.display());
-
-        // Create empty engine
-        let engine = KvEngine::with_shards(num_shards);
+        info!("Starting recovery from {} (target: {:?})", data_dir.display(), target);
 
         let mut snapshot_entries = 0;
-        let mut wal_entries_replayed = 0;
-        let mut corrupted_entries = 0;
         let mut wal_position_start = 0u64;
 
-        // Step 1: Load latest snapshot (if exists)
-        if let Some((snapshot_data, wal_position)) = SnapshotLoader::load_latest(data_dir)? {
+        // Step 1: Load the latest snapshot before the target (if exists)
+        if let Some((snapshot_data, wal_position)) = Self::load_snapshot(data_dir, target)? {
             info!(
                 "Found snapshot with {} entries at WAL position {}",
                 snapshot_data.total_entries, wal_position
@@ -85,32 +199,52 @@ impl RecoveryManager {
             info!("No snapshot found, starting from empty state");
         }
 
-        // Step 2: Find and replay WAL files
-        let wal_files = find_wal_files(data_dir)?;
+        // Step 2: Find and replay WAL segments from the snapshot position on
+        let segments = Self::find_segments(data_dir, options.archive_dir.as_deref())?;
+        let mut progress = ReplayProgress {
+            position: wal_position_start,
+            ..Default::default()
+        };
 
-        if wal_files.is_empty() {
+        if segments.is_empty() {
             info!("No WAL files found");
         } else {
-            info!("Found {} WAL file(s) to replay", wal_files.len());
+            info!("Found {} WAL file(s) to replay", segments.len());
 
-            for wal_path in wal_files {
-                debug!("Replaying WAL: {}", wal_path.display());
+            // Everything before this position has been loaded
+            let mut covered = wal_position_start;
 
-                match Self::replay_wal(&engine, &wal_path, wal_position_start) {
-                    Ok((replayed, corrupted)) => {
-                        wal_entries_replayed += replayed;
-                        corrupted_entries += corrupted;
-                    }
-                    Err(e) => {
-                        warn!("Failed to replay WAL {}: {}", wal_path.display(), e);
-                        // Continue with other WAL files
+            for segment in segments {
+                if segment.end_position <= wal_position_start {
+                    continue;
+                }
+
+                if segment.base_position > covered {
+                    let gap = PersistenceError::WalGap {
+                        from: covered,
+                        to: segment.base_position,
+                    };
+                    if target != RecoveryTarget::Latest {
+                        return Err(gap);
                     }
+                    warn!("{}; changes logged there are lost", gap);
+                }
+                covered = covered.max(segment.end_position);
+
+                debug!("Replaying WAL: {}", segment.path.display());
+
+                if let Err(e) = Self::replay_wal(&engine, &segment.path, wal_position_start, target, &mut progress) {
+                    warn!("Failed to replay WAL {}: {}", segment.path.display(), e);
+                    // Continue with other WAL files
+                }
+                if progress.done {
+                    break;
                 }
             }
 
             info!(
                 "Replayed {} WAL entries ({} corrupted/skipped)",
-                wal_entries_replayed, corrupted_entries
+                progress.replayed, progress.corrupted
             );
         }
 
@@ -118,33 +252,77 @@ impl RecoveryManager {
         let stats = RecoveryStats {
             snapshot_loaded: snapshot_entries > 0,
             snapshot_entries,
-            wal_entries_replayed,
-            corrupted_entries,
+            wal_entries_replayed: progress.replayed,
+            corrupted_entries: progress.corrupted,
+            wal_position: progress.position,
             recovery_duration,
         };
 
         info!(
             "Recovery complete in {:?}: {} total entries ({} from snapshot + {} from WAL)",
             recovery_duration,
-            snapshot_entries + wal_entries_replayed,
+            snapshot_entries + stats.wal_entries_replayed,
             snapshot_entries,
-            wal_entries_replayed
+            stats.wal_entries_replayed
         );
 
         Ok((engine, stats))
     }
 
+    /// Load the newest snapshot the target allows
+    fn load_snapshot(data_dir: &Path, target: RecoveryTarget) -> Result<Option<(SnapshotData, u64)>> {
+        if target == RecoveryTarget::Latest {
+            return SnapshotLoader::load_latest(data_dir);
+        }
+
+        for path in find_snapshot_files(data_dir)?.iter().rev() {
+            match SnapshotLoader::read_header(path) {
+                Ok(header) if target.allows_snapshot(&header) => {
+                    return SnapshotLoader::load(path).map(Some);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Skipping unreadable snapshot {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// WAL segments of the data directory and archive, in position order
+    ///
+    /// A segment present in both is read from the data directory.
+    fn find_segments(data_dir: &Path, archive_dir: Option<&Path>) -> Result<Vec<WalSegment>> {
+        let mut segments = list_segments(data_dir)?;
+
+        if let Some(archive_dir) = archive_dir {
+            let names: HashSet<OsString> = segments
+                .iter()
+                .filter_map(|segment| segment.path.file_name().map(OsString::from))
+                .collect();
+            for segment in list_segments(archive_dir)? {
+                let name = segment.path.file_name().map(OsString::from);
+                if !segment.active && !name.is_some_and(|name| names.contains(&name)) {
+                    segments.push(segment);
+                }
+            }
+            segments.sort_by_key(|segment| (segment.base_position, segment.active));
+        }
+
+        Ok(segments)
+    }
+
     /// Replay a single WAL file
     ///
-    /// Returns (entries_replayed, corrupted_entries)
+    /// Skips entries before `skip_before_position` and stops at the first
+    /// entry past the target, marking `progress` done.
     fn replay_wal(
         engine: &KvEngine,
         wal_path: impl AsRef<Path>,
         skip_before_position: u64,
-    ) -> Result<(usize, usize)> {
+        target: RecoveryTarget,
+        progress: &mut ReplayProgress,
+    ) -> Result<()> {
         let mut reader = WalReader::new(wal_path)?;
-        let mut replayed = 0;
-        let mut corrupted = 0;
 
         loop {
             // Skip entries before snapshot position
@@ -153,21 +331,28 @@ impl RecoveryManager {
                     Ok(Some(_)) => continue,
                     Ok(None) => break,
                     Err(_) => {
-                        corrupted += 1;
+                        progress.corrupted += 1;
                         continue;
                     }
                 }
             }
 
             // Read and apply entry
+            let entry_position = reader.position();
             match reader.read_entry() {
                 Ok(Some(entry)) => {
+                    if target.excludes(entry_position, entry.timestamp) {
+                        progress.position = entry_position;
+                        progress.done = true;
+                        return Ok(());
+                    }
+
                     // Apply operation to engine
                     if let Err(e) = Self::apply_wal_operation(engine, &entry.op) {
                         warn!("Failed to apply WAL operation: {}", e);
-                        corrupted += 1;
+                        progress.corrupted += 1;
                     } else {
-                        replayed += 1;
+                        progress.replayed += 1;
                     }
                 }
                 Ok(None) => {
@@ -177,17 +362,20 @@ impl RecoveryManager {
                 Err(e) => {
                     // Corrupted entry - log and skip
                     warn!("Corrupted WAL entry: {}", e);
-                    corrupted += 1;
+                    progress.corrupted += 1;
                     // Try to continue reading
                 }
             }
         }
 
-        Ok((replayed, corrupted))
+        progress.position = progress.position.max(reader.position());
+        Ok(())
     }
 
     /// Apply a single WAL operation to the engine
-    fn apply_wal_operation(engine: &KvEngine, op: &WalOp) -> Result<()> {
+    ///
+    /// Also used by replicas to apply entries streamed from a primary.
+    pub fn apply_wal_operation(engine: &KvEngine, op: &WalOp) -> Result<()> {
         use crate::types::KvKey;
 
         match op {
@@ -200,6 +388,21 @@ impl RecoveryManager {
                 engine.set(&kv_key, value.clone(), *ttl);
             }
 
+            WalOp::SetAt { key, value, expires_at_ms } => {
+                let kv_key = KvKey::new(key)
+                    .map_err(|e| PersistenceError::CorruptedWal {
+                        pos: 0,
+                        reason: format!("Invalid key: {}", e),
+                    })?;
+                match expires_at_ms.map(remaining_ttl) {
+                    // Already expired by the time it was applied
+                    Some(None) => {
+                        engine.delete(&kv_key);
+                    }
+                    ttl => engine.set(&kv_key, value.clone(), ttl.flatten()),
+                }
+            }
+
             WalOp::Delete { key } => {
                 let kv_key = KvKey::new(key)
                     .map_err(|e| PersistenceError::CorruptedWal {
@@ -279,6 +482,103 @@ impl RecoveryManager {
                     })?;
                 let _ = engine.extend_lock(&kv_key, owner, *ttl); // Ignore errors during recovery
             }
+
+            WalOp::Expire { key, ttl } => {
+                let kv_key = KvKey::new(key)
+                    .map_err(|e| PersistenceError::CorruptedWal {
+                        pos: 0,
+                        reason: format!("Invalid key: {}", e),
+                    })?;
+                engine.expire(&kv_key, *ttl);
+            }
+
+            WalOp::Transaction { ops } => {
+                let mut txn = Transaction::new();
+                for op in ops {
+                    txn.push(match op {
+                        WalOp::Set { key, value, ttl } => TxnOp::Set {
+                            key: key.clone(),
+                            value: value.clone(),
+                            ttl: *ttl,
+                        },
+                        WalOp::SetAt { key, value, expires_at_ms } => match expires_at_ms.map(remaining_ttl) {
+                            Some(None) => TxnOp::Delete { key: key.clone() },
+                            ttl => TxnOp::Set {
+                                key: key.clone(),
+                                value: value.clone(),
+                                ttl: ttl.flatten(),
+                            },
+                        },
+                        WalOp::Delete { key } => TxnOp::Delete { key: key.clone() },
+                        other => {
+                            return Err(PersistenceError::CorruptedWal {
+                                pos: 0,
+                                reason: format!("Unexpected {:?} in transaction", other.op_type()),
+                            })
+                        }
+                    });
+                }
+                // Applied in one step so readers of a replica never see part of it
+                engine.transact(&txn).map_err(|e| PersistenceError::CorruptedWal {
+                    pos: 0,
+                    reason: format!("Failed to apply transaction: {}", e),
+                })?;
+            }
+
+            // Structure commands: type errors failed the same way originally
+            WalOp::HashWrite { key, op } => {
+                let kv_key = KvKey::new(key)
+                    .map_err(|e| PersistenceError::CorruptedWal {
+                        pos: 0,
+                        reason: format!("Invalid key: {}", e),
+                    })?;
+                let _ = match op {
+                    HashOp::Set { fields } => engine.hset(&kv_key, fields.clone()).map(drop),
+                    HashOp::SetNx { field, value } => engine.hsetnx(&kv_key, field, value.clone()).map(drop),
+                    HashOp::Delete { fields } => engine.hdel(&kv_key, fields).map(drop),
+                    HashOp::IncrBy { field, delta } => engine.hincrby(&kv_key, field, *delta).map(drop),
+                };
+            }
+
+            WalOp::ListWrite { key, op } => {
+                let kv_key = KvKey::new(key)
+                    .map_err(|e| PersistenceError::CorruptedWal {
+                        pos: 0,
+                        reason: format!("Invalid key: {}", e),
+                    })?;
+                let _ = match op {
+                    ListOp::Push { values, front: true } => engine.lpush(&kv_key, values.clone()).map(drop),
+                    ListOp::Push { values, front: false } => engine.rpush(&kv_key, values.clone()).map(drop),
+                    ListOp::Pop { count, front: true } => engine.lpop(&kv_key, *count).map(drop),
+                    ListOp::Pop { count, front: false } => engine.rpop(&kv_key, *count).map(drop),
+                    ListOp::Trim { start, stop } => engine.ltrim(&kv_key, *start, *stop),
+                };
+            }
+
+            WalOp::SetWrite { key, op } => {
+                let kv_key = KvKey::new(key)
+                    .map_err(|e| PersistenceError::CorruptedWal {
+                        pos: 0,
+                        reason: format!("Invalid key: {}", e),
+                    })?;
+                let _ = match op {
+                    SetOp::Add { members } => engine.sadd(&kv_key, members.clone()),
+                    SetOp::Remove { members } => engine.srem(&kv_key, members),
+                };
+            }
+
+            WalOp::ZSetWrite { key, op } => {
+                let kv_key = KvKey::new(key)
+                    .map_err(|e| PersistenceError::CorruptedWal {
+                        pos: 0,
+                        reason: format!("Invalid key: {}", e),
+                    })?;
+                let _ = match op {
+                    ZSetOp::Add { members } => engine.zadd(&kv_key, members.clone()).map(drop),
+                    ZSetOp::IncrBy { member, delta } => engine.zincrby(&kv_key, member, *delta).map(drop),
+                    ZSetOp::Remove { members } => engine.zrem(&kv_key, members).map(drop),
+                };
+            }
         }
 
         Ok(())
@@ -385,4 +685,205 @@ mod tests {
         assert_eq!(engine.get(&k1), None);
         assert_eq!(engine.get(&k2), Some(KvValue::String("v2".to_string())));
     }
+
+    #[test]
+    fn test_recovery_transaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path();
+
+        let config = WalConfig::default();
+        let mut wal_writer = WalWriter::new(data_dir.to_path_buf(), config).unwrap();
+
+        use super::super::format::WalOp;
+
+        wal_writer.append(WalOp::Set {
+            key: "stock".to_string(),
+            value: KvValue::Int(5),
+            ttl: None,
+        }).unwrap();
+
+        wal_writer.append(WalOp::Transaction {
+            ops: vec![
+                WalOp::Set {
+                    key: "stock".to_string(),
+                    value: KvValue::Int(2),
+                    ttl: None,
+                },
+                WalOp::Set {
+                    key: "reserved".to_string(),
+                    value: KvValue::Int(3),
+                    ttl: None,
+                },
+            ],
+        }).unwrap();
+
+        wal_writer.flush().unwrap();
+        drop(wal_writer);
+
+        let (engine, stats) = RecoveryManager::recover(data_dir, 256).unwrap();
+
+        assert_eq!(stats.wal_entries_replayed, 2);
+        assert_eq!(engine.get(&KvKey::new("stock").unwrap()), Some(KvValue::Int(2)));
+        assert_eq!(engine.get(&KvKey::new("reserved").unwrap()), Some(KvValue::Int(3)));
+    }
+
+    #[test]
+    fn test_restore_until_position_and_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path();
+
+        let mut wal_writer = WalWriter::new(data_dir.to_path_buf(), WalConfig::default()).unwrap();
+        let set = |value| WalOp::Set {
+            key: "counter".to_string(),
+            value: KvValue::Int(value),
+            ttl: None,
+        };
+
+        wal_writer.append(set(1)).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let between = SystemTime::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = wal_writer.append(set(2)).unwrap();
+        wal_writer.flush().unwrap();
+        drop(wal_writer);
+
+        let counter = KvKey::new("counter").unwrap();
+        let restore = |options: RestoreOptions| {
+            RecoveryManager::restore(KvEngine::with_shards(16), data_dir, &options).unwrap()
+        };
+
+        let (engine, stats) = restore(RestoreOptions::new().until_position(second));
+        assert_eq!(engine.get(&counter), Some(KvValue::Int(1)));
+        assert_eq!(stats.wal_entries_replayed, 1);
+        assert_eq!(stats.wal_position, second);
+
+        let (engine, _) = restore(RestoreOptions::new().until_time(between));
+        assert_eq!(engine.get(&counter), Some(KvValue::Int(1)));
+
+        let (engine, stats) = restore(RestoreOptions::new());
+        assert_eq!(engine.get(&counter), Some(KvValue::Int(2)));
+        assert_eq!(stats.wal_entries_replayed, 2);
+    }
+
+    #[test]
+    fn test_recovery_after_wal_compaction() {
+        use crate::persistence::snapshot::{oldest_snapshot_position, SnapshotWriter};
+        use crate::persistence::wal::{list_segments, remove_segments_before};
+        use crate::persistence::SnapshotConfig;
+
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path();
+        let archive_dir = data_dir.join("archive");
+
+        let config = WalConfig {
+            max_file_size: 128,
+            ..Default::default()
+        };
+        let mut wal_writer = WalWriter::new(data_dir.to_path_buf(), config).unwrap();
+        let engine = KvEngine::with_shards(16);
+        let mut write = |engine: &KvEngine, i: i64| {
+            let op = WalOp::Set {
+                key: format!("key{}", i),
+                value: KvValue::Int(i),
+                ttl: None,
+            };
+            let position = wal_writer.append(op.clone()).unwrap();
+            RecoveryManager::apply_wal_operation(engine, &op).unwrap();
+            if wal_writer.should_rotate() {
+                wal_writer.rotate().unwrap();
+            }
+            wal_writer.flush().unwrap();
+            (position, wal_writer.position())
+        };
+
+        for i in 0..6 {
+            write(&engine, i);
+        }
+        let (sixth, snapshot_position) = write(&engine, 6);
+        SnapshotWriter::new(SnapshotConfig::default())
+            .create_snapshot(&engine, data_dir, snapshot_position)
+            .unwrap();
+        let (_, after_seven) = write(&engine, 7);
+        write(&engine, 8);
+
+        // Segments before the snapshot are archived; recovery no longer needs them
+        let position = oldest_snapshot_position(data_dir).unwrap().unwrap();
+        assert!(remove_segments_before(data_dir, position, Some(&archive_dir)).unwrap() > 0);
+        assert!(list_segments(data_dir).unwrap()[0].base_position > 0);
+
+        let (recovered, stats) = RecoveryManager::recover(data_dir, 16).unwrap();
+        assert_eq!(stats.snapshot_entries, 7);
+        assert_eq!(stats.wal_entries_replayed, 2);
+        assert_eq!(recovered.len(), 9);
+
+        let options = RestoreOptions::new().until_position(after_seven);
+        let (restored, stats) = RecoveryManager::restore(KvEngine::with_shards(16), data_dir, &options).unwrap();
+        assert!(stats.snapshot_loaded);
+        assert_eq!(restored.len(), 8);
+        assert_eq!(restored.get(&KvKey::new("key8").unwrap()), None);
+
+        // Restoring to before the snapshot replays the archive into an empty engine
+        let options = RestoreOptions::new().until_position(sixth);
+        let (restored, stats) = RecoveryManager::restore(
+            KvEngine::with_shards(16),
+            data_dir,
+            &options.clone().with_archive_dir(&archive_dir),
+        )
+        .unwrap();
+        assert!(!stats.snapshot_loaded);
+        assert_eq!(restored.len(), 6);
+        assert_eq!(restored.get(&KvKey::new("key6").unwrap()), None);
+
+        assert!(matches!(
+            RecoveryManager::restore(KvEngine::with_shards(16), data_dir, &options),
+            Err(PersistenceError::WalGap { from: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_recovery_collections() {
+        use crate::collections::{ListOp, ZSetOp};
+
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path();
+
+        let config = WalConfig::default();
+        let mut wal_writer = WalWriter::new(data_dir.to_path_buf(), config).unwrap();
+
+        use super::super::format::WalOp;
+
+        wal_writer.append(WalOp::ListWrite {
+            key: "jobs".to_string(),
+            op: ListOp::Push { values: vec![KvValue::Int(1), KvValue::Int(2), KvValue::Int(3)], front: false },
+        }).unwrap();
+
+        wal_writer.append(WalOp::ListWrite {
+            key: "jobs".to_string(),
+            op: ListOp::Pop { count: 1, front: true },
+        }).unwrap();
+
+        wal_writer.append(WalOp::ZSetWrite {
+            key: "board".to_string(),
+            op: ZSetOp::Add { members: vec![(10.0, "alice".to_string()), (20.0, "bob".to_string())] },
+        }).unwrap();
+
+        wal_writer.append(WalOp::ZSetWrite {
+            key: "board".to_string(),
+            op: ZSetOp::IncrBy { member: "alice".to_string(), delta: 15.0 },
+        }).unwrap();
+
+        wal_writer.flush().unwrap();
+        drop(wal_writer);
+
+        let (engine, stats) = RecoveryManager::recover(data_dir, 256).unwrap();
+
+        assert_eq!(stats.wal_entries_replayed, 4);
+        let jobs = KvKey::new("jobs").unwrap();
+        assert_eq!(engine.lrange(&jobs, 0, -1).unwrap(), vec![KvValue::Int(2), KvValue::Int(3)]);
+        let board = KvKey::new("board").unwrap();
+        assert_eq!(
+            engine.zrange(&board, 0, -1, true).unwrap(),
+            vec![("alice".to_string(), 25.0), ("bob".to_string(), 20.0)]
+        );
+    }
 }
@@ -3,7 +3,7 @@
 ///! Provides periodic full engine state snapshots for faster recovery.
 
 use super::{PersistenceError, Result, SnapshotConfig};
-use crate::engine::{Entry, KvEngine};
+use crate::engine::{AccessStats, Entry, KvEngine};
 use crate::types::KvValue;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -129,28 +129,14 @@ pub struct SerializableEntry {
 impl SerializableEntry {
     /// Convert from runtime Entry to serializable format
     pub fn from_entry(entry: &Entry, base_instant: Instant) -> Self {
-        let created_offset = entry.created_at.duration_since(base_instant);
-        let created_at_nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .checked_sub(created_offset)
-            .unwrap_or_else(|| Duration::from_secs(0))
-            .as_nanos() as i64;
-
-        let expires_at_nanos = entry.expires_at.map(|exp| {
-            let expires_offset = exp.duration_since(base_instant);
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .checked_sub(expires_offset)
-                .unwrap_or_else(|| Duration::from_secs(0))
-                .as_nanos() as i64
-        });
+        let base_nanos = unix_nanos_now();
 
         Self {
             value: entry.value.clone(),
-            created_at_nanos,
-            expires_at_nanos,
+            created_at_nanos: instant_to_unix_nanos(entry.created_at, base_instant, base_nanos),
+            expires_at_nanos: entry
+                .expires_at
+                .map(|exp| instant_to_unix_nanos(exp, base_instant, base_nanos)),
             version: entry.version,
         }
     }
@@ -159,28 +145,47 @@ impl SerializableEntry {
     pub fn to_entry(&self) -> Entry {
         // Convert absolute timestamps back to Instant
         let now = Instant::now();
-        let now_absolute = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as i64;
-
-        let created_offset = Duration::from_nanos((now_absolute - self.created_at_nanos).max(0) as u64);
-        let created_at = now.checked_sub(created_offset).unwrap_or(now);
-
-        let expires_at = self.expires_at_nanos.map(|exp_nanos| {
-            let expires_offset = Duration::from_nanos((now_absolute - exp_nanos).max(0) as u64);
-            now.checked_sub(expires_offset).unwrap_or(now)
-        });
+        let now_absolute = unix_nanos_now();
 
         Entry {
             value: self.value.clone(),
-            created_at,
-            expires_at,
+            created_at: unix_nanos_to_instant(self.created_at_nanos, now, now_absolute),
+            expires_at: self
+                .expires_at_nanos
+                .map(|exp_nanos| unix_nanos_to_instant(exp_nanos, now, now_absolute)),
             version: self.version,
+            access: AccessStats::now(),
         }
     }
 }
 
+/// Current wall-clock time in nanoseconds since UNIX_EPOCH
+fn unix_nanos_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64
+}
+
+/// Map an `Instant` onto the wall clock, relative to a known (instant, wall-clock) pair
+fn instant_to_unix_nanos(instant: Instant, base: Instant, base_nanos: i64) -> i64 {
+    if instant >= base {
+        base_nanos + instant.duration_since(base).as_nanos() as i64
+    } else {
+        (base_nanos - base.duration_since(instant).as_nanos() as i64).max(0)
+    }
+}
+
+/// Map a wall-clock timestamp back onto the monotonic clock
+fn unix_nanos_to_instant(nanos: i64, now: Instant, now_nanos: i64) -> Instant {
+    if nanos >= now_nanos {
+        now + Duration::from_nanos((nanos - now_nanos) as u64)
+    } else {
+        let offset = Duration::from_nanos((now_nanos - nanos) as u64);
+        now.checked_sub(offset).unwrap_or(now)
+    }
+}
+
 /// Serializable shard data
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SerializableShard {
@@ -334,9 +339,21 @@ impl SnapshotLoader {
 
         // Latest snapshot is the last one (sorted by timestamp)
         let latest = snapshots.last().unwrap();
-        info!("Loading snapshot: {}", latest.display());
+        Self::load(latest).map(Some)
+    }
+
+    /// Read just the header of a snapshot file
+    pub fn read_header(path: impl AsRef<Path>) -> Result<SnapshotHeader> {
+        let mut reader = BufReader::new(File::open(path)?);
+        SnapshotHeader::read(&mut reader)
+    }
 
-        let file = File::open(latest)?;
+    /// Load and verify a snapshot file, returning its data and WAL position
+    pub fn load(path: impl AsRef<Path>) -> Result<(SnapshotData, u64)> {
+        let path = path.as_ref();
+        info!("Loading snapshot: {}", path.display());
+
+        let file = File::open(path)?;
         let mut reader = BufReader::new(file);
 
         // Read and validate header
@@ -374,13 +391,13 @@ impl SnapshotLoader {
             header.total_entries, header.num_shards
         );
 
-        Ok(Some((
+        Ok((
             SnapshotData {
                 shards,
                 total_entries: header.total_entries,
             },
             header.wal_position,
-        )))
+        ))
     }
 }
 
@@ -391,6 +408,18 @@ pub struct SnapshotData {
     pub total_entries: u64,
 }
 
+/// Lowest WAL position any snapshot in the directory restores from
+///
+/// WAL before this position is not needed to recover from any of them.
+/// Snapshots whose header cannot be read are ignored.
+pub fn oldest_snapshot_position(data_dir: impl AsRef<Path>) -> Result<Option<u64>> {
+    Ok(find_snapshot_files(data_dir)?
+        .iter()
+        .filter_map(|path| SnapshotLoader::read_header(path).ok())
+        .map(|header| header.wal_position)
+        .min())
+}
+
 /// Find all snapshot files in directory, sorted by timestamp (oldest first)
 pub fn find_snapshot_files(data_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
     let data_dir = data_dir.as_ref();
@@ -452,6 +481,7 @@ mod tests {
             created_at: base,
             expires_at: Some(base + Duration::from_secs(60)),
             version: 42,
+            access: AccessStats::now(),
         };
 
         let serializable = SerializableEntry::from_entry(&original, base);
@@ -463,7 +493,9 @@ mod tests {
 
         // Timestamps may have slight differences due to conversion, but should be close
         assert!(restored.created_at <= Instant::now());
-        assert!(restored.expires_at.is_some());
+        let expires_at = restored.expires_at.unwrap();
+        assert!(expires_at > Instant::now() + Duration::from_secs(59));
+        assert!(!restored.is_expired());
     }
 
     #[test]
@@ -4,17 +4,19 @@
 ///!
 ///! ## Architecture
 ///!
-///! - Single WAL file grows until rotation threshold (1GB default)
+///! - The active segment grows until the rotation threshold (1GB default)
 ///! - Background thread flushes every 100ms (batched fsync)
 ///! - CRC32 checksum on every entry for corruption detection
-///! - File rotation creates new file with timestamp suffix
+///! - Positions are global: each segment's header records the position it starts
+///!   at, so a snapshot's `wal_position` identifies one point across all segments
+///! - Segments that every kept snapshot is past are deleted or archived
 ///!
 ///! ## File Naming
 ///!
-///! - Active WAL: `wal-current.log`
-///! - Rotated WAL: `wal-{timestamp}.log`
+///! - Active segment: `wal-current.log`
+///! - Closed segments: `wal-{base_position:020}.log`
 
-use super::format::{encode_wal_entry, decode_wal_entry, WalEntry, WalHeader, WalOp};
+use super::format::{encode_wal_entry, decode_wal_entry, WalEntry, WalHeader, WalOp, WAL_HEADER_SIZE};
 use super::{PersistenceError, Result, WalConfig};
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
@@ -22,10 +24,24 @@ use std::path::{Path, PathBuf};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, warn};
 
+/// Name of the segment being written to
+pub const ACTIVE_SEGMENT: &str = "wal-current.log";
+
+/// Name of the next active segment while a rotation is in progress
+const PENDING_SEGMENT: &str = "wal-current-new.log";
+
+/// File name of a closed segment starting at `base_position`
+pub fn segment_file_name(base_position: u64) -> String {
+    format!("wal-{:020}.log", base_position)
+}
+
 /// WAL writer with batched fsync
 pub struct WalWriter {
     file: BufWriter<File>,
     path: PathBuf,
+    /// Position of the active segment's first byte
+    base_position: u64,
+    /// Offset within the active segment
     position: u64,
     unflushed_bytes: usize,
     last_fsync: Instant,
@@ -41,7 +57,28 @@ impl WalWriter {
             PersistenceError::DataDirectory(format!("Failed to create directory: {}", e))
         })?;
 
-        let wal_path = data_dir.join("wal-current.log");
+        let wal_path = data_dir.join(ACTIVE_SEGMENT);
+
+        // Finish a rotation interrupted between its two renames
+        let pending_path = data_dir.join(PENDING_SEGMENT);
+        if pending_path.exists() {
+            if wal_path.exists() {
+                fs::remove_file(&pending_path)?;
+            } else {
+                fs::rename(&pending_path, &wal_path)?;
+            }
+        }
+
+        // A new active segment continues after the last closed one
+        let next_base = if wal_path.exists() {
+            0
+        } else {
+            list_segments(&data_dir)?
+                .iter()
+                .map(|segment| segment.end_position)
+                .max()
+                .unwrap_or(0)
+        };
 
         // Open or create WAL file
         let file = OpenOptions::new()
@@ -55,18 +92,22 @@ impl WalWriter {
         let mut writer = BufWriter::with_capacity(64 * 1024, file); // 64KB buffer
 
         // Write header if new file
-        if position == 0 {
-            let header = WalHeader::new();
+        let base_position = if position == 0 {
+            let header = WalHeader::new().with_base_position(next_base);
             header.write(&mut writer)?;
             writer.flush()?;
-            debug!("Created new WAL file: {}", wal_path.display());
-        }
+            debug!("Created new WAL file: {} at position {}", wal_path.display(), next_base);
+            next_base
+        } else {
+            WalHeader::read(&mut File::open(&wal_path)?)?.base_position
+        };
 
         let position = writer.seek(SeekFrom::End(0))?;
 
         Ok(Self {
             file: writer,
             path: wal_path,
+            base_position,
             position,
             unflushed_bytes: 0,
             last_fsync: Instant::now(),
@@ -75,7 +116,7 @@ impl WalWriter {
         })
     }
 
-    /// Append an operation to the WAL
+    /// Append an operation to the WAL, returning its position
     pub fn append(&mut self, op: WalOp) -> Result<u64> {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -85,7 +126,7 @@ impl WalWriter {
         let entry = WalEntry { timestamp, op };
         let encoded = encode_wal_entry(&entry)?;
 
-        let position = self.position;
+        let position = self.position();
         self.file.write_all(&encoded)?;
         self.position += encoded.len() as u64;
         self.unflushed_bytes += encoded.len();
@@ -123,19 +164,18 @@ impl WalWriter {
     }
 
     /// Rotate to a new WAL file
+    ///
+    /// The active segment is closed under its base position and the new one
+    /// starts where it ended.
     pub fn rotate(&mut self) -> Result<PathBuf> {
         // Flush and close current file
         self.flush()?;
 
-        // Get timestamp for rotated file
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let rotated_path = self.data_dir.join(format!("wal-{}.log", timestamp));
+        let rotated_path = self.data_dir.join(segment_file_name(self.base_position));
+        let next_base = self.position();
 
         // Create new WAL file first (before renaming old one)
-        let new_path = self.data_dir.join("wal-current-new.log");
+        let new_path = self.data_dir.join(PENDING_SEGMENT);
         let new_file = OpenOptions::new()
             .create(true)
             .write(true)
@@ -145,7 +185,7 @@ impl WalWriter {
         let mut new_writer = BufWriter::with_capacity(64 * 1024, new_file);
 
         // Write header to new file
-        let header = WalHeader::new();
+        let header = WalHeader::new().with_base_position(next_base);
         header.write(&mut new_writer)?;
         new_writer.flush()?;
 
@@ -159,16 +199,17 @@ impl WalWriter {
 
         // Update writer
         self.file = new_writer;
-        self.position = 32; // Header size
+        self.base_position = next_base;
+        self.position = WAL_HEADER_SIZE;
         self.unflushed_bytes = 0;
         self.last_fsync = Instant::now();
 
         Ok(rotated_path)
     }
 
-    /// Get current file position
+    /// Get the current WAL position (where the next entry will start)
     pub fn position(&self) -> u64 {
-        self.position
+        self.base_position + self.position
     }
 
     /// Get the WAL file path
@@ -178,9 +219,12 @@ impl WalWriter {
 }
 
 /// WAL reader for replaying entries
+///
+/// Positions are global WAL positions, like [`WalWriter::position`].
 pub struct WalReader {
     file: File,
     path: PathBuf,
+    base_position: u64,
     position: u64,
     file_size: u64,
 }
@@ -192,9 +236,10 @@ impl WalReader {
         let mut file = File::open(&path)?;
 
         // Read and validate header
-        let _header = WalHeader::read(&mut file)?;
+        let header = WalHeader::read(&mut file)?;
 
-        let position = file.stream_position()?;
+        let base_position = header.base_position;
+        let position = base_position + file.stream_position()?;
         let file_size = file.metadata()?.len();
 
         debug!("Opened WAL for reading: {} ({} bytes)", path.display(), file_size);
@@ -202,6 +247,7 @@ impl WalReader {
         Ok(Self {
             file,
             path,
+            base_position,
             position,
             file_size,
         })
@@ -209,7 +255,7 @@ impl WalReader {
 
     /// Read the next entry from the WAL
     pub fn read_entry(&mut self) -> Result<Option<WalEntry>> {
-        if self.position >= self.file_size {
+        if self.position >= self.end_position() {
             return Ok(None); // EOF
         }
 
@@ -252,6 +298,16 @@ impl WalReader {
         self.position
     }
 
+    /// Position of the segment's first byte
+    pub fn base_position(&self) -> u64 {
+        self.base_position
+    }
+
+    /// Position just past the segment's last byte
+    pub fn end_position(&self) -> u64 {
+        self.base_position + self.file_size
+    }
+
     /// Get the WAL file path
     pub fn path(&self) -> &Path {
         &self.path
@@ -264,6 +320,8 @@ impl WalReader {
 }
 
 /// Find all WAL files in a directory
+///
+/// Sorted by name, which puts the active segment last.
 pub fn find_wal_files(data_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
     let data_dir = data_dir.as_ref();
 
@@ -279,7 +337,7 @@ pub fn find_wal_files(data_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
 
         if let Some(filename) = path.file_name() {
             if let Some(name) = filename.to_str() {
-                if name.starts_with("wal-") && name.ends_with(".log") {
+                if name.starts_with("wal-") && name.ends_with(".log") && name != PENDING_SEGMENT {
                     wal_files.push(path);
                 }
             }
@@ -292,6 +350,101 @@ pub fn find_wal_files(data_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
     Ok(wal_files)
 }
 
+/// A WAL segment file and the positions it covers
+#[derive(Debug, Clone)]
+pub struct WalSegment {
+    pub path: PathBuf,
+    /// Position of the segment's first byte
+    pub base_position: u64,
+    /// Position just past the segment's last byte
+    pub end_position: u64,
+    /// Whether this is the segment being written to
+    pub active: bool,
+}
+
+impl WalSegment {
+    /// Read a segment's header
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let header = WalHeader::read(&mut file)?;
+        let len = file.metadata()?.len();
+        let active = path.file_name().and_then(|n| n.to_str()) == Some(ACTIVE_SEGMENT);
+
+        Ok(Self {
+            path,
+            base_position: header.base_position,
+            end_position: header.base_position + len,
+            active,
+        })
+    }
+}
+
+/// List the WAL segments in a directory in position order
+///
+/// Files whose header cannot be read are skipped with a warning.
+pub fn list_segments(data_dir: impl AsRef<Path>) -> Result<Vec<WalSegment>> {
+    let mut segments = Vec::new();
+
+    for path in find_wal_files(data_dir)? {
+        match WalSegment::open(&path) {
+            Ok(segment) => segments.push(segment),
+            Err(e) => warn!("Skipping unreadable WAL segment {}: {}", path.display(), e),
+        }
+    }
+
+    // Stable: segments written before segmentation all start at 0 and keep name order
+    segments.sort_by_key(|segment| (segment.base_position, segment.active));
+
+    Ok(segments)
+}
+
+/// Remove closed segments that end at or before `position`
+///
+/// With an `archive_dir` the segments are moved there instead of deleted.
+/// Returns the number of segments removed.
+pub fn remove_segments_before(
+    data_dir: impl AsRef<Path>,
+    position: u64,
+    archive_dir: Option<&Path>,
+) -> Result<usize> {
+    let mut removed = 0;
+
+    for segment in list_segments(data_dir)? {
+        if segment.active || segment.end_position > position {
+            continue;
+        }
+
+        match archive_dir {
+            Some(dir) => {
+                archive_segment(&segment.path, dir)?;
+                debug!("Archived WAL segment {} to {}", segment.path.display(), dir.display());
+            }
+            None => {
+                fs::remove_file(&segment.path)?;
+                debug!("Deleted WAL segment {}", segment.path.display());
+            }
+        }
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+fn archive_segment(path: &Path, archive_dir: &Path) -> Result<()> {
+    fs::create_dir_all(archive_dir)?;
+    let target = archive_dir.join(path.file_name().unwrap_or_default());
+
+    // Rename fails across filesystems; fall back to copying
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target)?;
+        File::open(&target)?.sync_all()?;
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
 /// Delete old WAL files (keep only the most recent N)
 pub fn cleanup_old_wal_files(data_dir: impl AsRef<Path>, keep_count: usize) -> Result<usize> {
     let mut wal_files = find_wal_files(data_dir)?;
@@ -338,6 +491,7 @@ mod tests {
         let config = WalConfig {
             flush_interval_ms: 100,
             max_file_size: 1024, // 1KB for testing rotation
+            archive_dir: None,
         };
         (temp_dir, config)
     }
@@ -429,6 +583,85 @@ mod tests {
         assert!(wal_files.len() > 1, "Expected multiple WAL files after rotation");
     }
 
+    #[test]
+    fn test_wal_positions_continue_across_segments() {
+        let (temp_dir, mut config) = create_test_config();
+        config.max_file_size = 256;
+        let data_dir = temp_dir.path().to_path_buf();
+
+        let mut writer = WalWriter::new(data_dir.clone(), config.clone()).unwrap();
+        let mut positions = Vec::new();
+        for i in 0..10 {
+            positions.push(writer.append(WalOp::Delete { key: format!("key{}", i) }).unwrap());
+            if writer.should_rotate() {
+                writer.rotate().unwrap();
+            }
+        }
+        writer.flush().unwrap();
+        let end = writer.position();
+        drop(writer);
+
+        // Segments tile the position space and entries keep their positions
+        let segments = list_segments(&data_dir).unwrap();
+        assert!(segments.len() > 1);
+        assert_eq!(segments[0].base_position, 0);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end_position, pair[1].base_position);
+        }
+        assert!(segments.last().unwrap().active);
+        assert_eq!(segments.last().unwrap().end_position, end);
+
+        let mut read_positions = Vec::new();
+        for segment in &segments {
+            let mut reader = WalReader::new(&segment.path).unwrap();
+            loop {
+                let position = reader.position();
+                match reader.read_entry().unwrap() {
+                    Some(_) => read_positions.push(position),
+                    None => break,
+                }
+            }
+        }
+        assert_eq!(read_positions, positions);
+
+        // Reopening continues where the active segment ends
+        let writer = WalWriter::new(data_dir, config).unwrap();
+        assert_eq!(writer.position(), end);
+    }
+
+    #[test]
+    fn test_remove_segments_before() {
+        let (temp_dir, mut config) = create_test_config();
+        config.max_file_size = 128;
+        let data_dir = temp_dir.path().to_path_buf();
+        let archive_dir = temp_dir.path().join("archive");
+
+        let mut writer = WalWriter::new(data_dir.clone(), config).unwrap();
+        for i in 0..10 {
+            writer.append(WalOp::Delete { key: format!("key{}", i) }).unwrap();
+            if writer.should_rotate() {
+                writer.rotate().unwrap();
+            }
+        }
+        writer.flush().unwrap();
+
+        let segments = list_segments(&data_dir).unwrap();
+        let closed = segments.len() - 1;
+        assert!(closed >= 2);
+
+        // Only segments that end at or before the position go
+        let position = segments[1].end_position;
+        assert_eq!(remove_segments_before(&data_dir, position - 1, Some(&archive_dir)).unwrap(), 1);
+        assert_eq!(remove_segments_before(&data_dir, position, Some(&archive_dir)).unwrap(), 1);
+        assert_eq!(list_segments(&archive_dir).unwrap().len(), 2);
+
+        // The active segment is never removed
+        assert_eq!(remove_segments_before(&data_dir, u64::MAX, None).unwrap(), closed - 2);
+        let remaining = list_segments(&data_dir).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].active);
+    }
+
     #[test]
     fn test_wal_find_files() {
         let (temp_dir, config) = create_test_config();
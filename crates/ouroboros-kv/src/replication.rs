@@ -0,0 +1,333 @@
+//! Replication log for primary/replica setups
+//!
+//! A primary records every change as a WAL entry (see
+//! [`crate::persistence::format`]) in an in-memory backlog that replicas tail.
+//!
+//! ## Post-images
+//!
+//! Entries describe the *resulting* state of a key rather than the command
+//! that produced it: an `INCR` is recorded as a `SetAt` of the new value with
+//! its absolute expiry, an eviction as a `Delete`. Expiries are wall-clock
+//! times, so a replica that applies an entry late (or from a full sync) still
+//! expires the key when the primary does. Entries are appended while the
+//! shard lock is held, so per-key order matches the order changes were applied.
+//! Replaying an entry twice is harmless, which lets a replica load a snapshot
+//! taken while writes continue and then replay the backlog from the offset
+//! recorded *before* the snapshot started.
+//!
+//! ## Offsets
+//!
+//! Every entry gets a sequence number starting at 1. A replica that knows the
+//! log id and the last offset it applied can resume from the backlog as long
+//! as that offset has not been trimmed; otherwise it needs a full sync.
+//!
+//! A replica with persistence keeps its [`ReplicationPosition`] in its data
+//! directory, so it can also resume after a restart.
+
+use crate::engine::{Entry, KvEngine};
+use crate::persistence::format::{encode_wal_entry, unix_millis, WalEntry, WalOp};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use tracing::error;
+
+/// Default backlog size kept for replicas that reconnect (64MB)
+pub const DEFAULT_BACKLOG_BYTES: usize = 64 * 1024 * 1024;
+
+/// File in the data directory holding a replica's position
+const POSITION_FILE: &str = "replication.pos";
+
+/// Log id and last offset a replica has applied
+///
+/// Only saved once the WAL holding every change up to `offset` is on disk
+/// (see [`crate::persistence::PersistenceHandle::save_replication_position`]),
+/// so a restarted replica may replay a few entries but never skips any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicationPosition {
+    pub replid: String,
+    pub offset: u64,
+}
+
+impl ReplicationPosition {
+    /// Position saved in `data_dir`, None if there is none (or a full sync was pending)
+    pub fn load(data_dir: &Path) -> io::Result<Option<Self>> {
+        let contents = match std::fs::read_to_string(data_dir.join(POSITION_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Malformed replication position");
+        let (replid, offset) = contents.trim_end().split_once(' ').ok_or_else(invalid)?;
+        let offset = offset.parse().map_err(|_| invalid())?;
+        Ok((!replid.is_empty()).then(|| Self { replid: replid.to_string(), offset }))
+    }
+
+    /// Atomically replace the position saved in `data_dir`
+    pub(crate) fn save(&self, data_dir: &Path) -> io::Result<()> {
+        let tmp = data_dir.join(format!("{}.tmp", POSITION_FILE));
+        {
+            let mut file = std::fs::File::create(&tmp)?;
+            io::Write::write_all(&mut file, format!("{} {}\n", self.replid, self.offset).as_bytes())?;
+            file.sync_all()?;
+        }
+        std::fs::rename(tmp, data_dir.join(POSITION_FILE))
+    }
+}
+
+struct Backlog {
+    /// Encoded entries, oldest first; the front entry has sequence `first_offset`
+    entries: VecDeque<Arc<[u8]>>,
+    first_offset: u64,
+    bytes: usize,
+}
+
+/// In-memory backlog of recent changes, shared by all shards of a primary
+pub struct ReplicationLog {
+    id: String,
+    backlog: Mutex<Backlog>,
+    /// Sequence number of the last appended entry (0 = none)
+    offset: AtomicU64,
+    max_bytes: usize,
+    appended: Notify,
+}
+
+impl ReplicationLog {
+    /// Create a log keeping up to `max_bytes` of encoded entries
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            id: generate_id(),
+            backlog: Mutex::new(Backlog {
+                entries: VecDeque::new(),
+                first_offset: 1,
+                bytes: 0,
+            }),
+            offset: AtomicU64::new(0),
+            max_bytes: max_bytes.max(1),
+            appended: Notify::new(),
+        }
+    }
+
+    /// Identifies this log; offsets are only meaningful for the same id
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Sequence number of the most recent entry (0 if none yet)
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::Acquire)
+    }
+
+    /// Record the new state of a key (None = deleted)
+    pub(crate) fn record(&self, key: &str, entry: Option<&Entry>) {
+        self.append(post_image(key, entry));
+    }
+
+    /// Append an operation to the backlog
+    pub fn append(&self, op: WalOp) {
+        let Some(encoded) = encode(op) else {
+            return;
+        };
+
+        {
+            let mut backlog = self.backlog.lock();
+            backlog.bytes += encoded.len();
+            backlog.entries.push_back(encoded);
+            while backlog.bytes > self.max_bytes && backlog.entries.len() > 1 {
+                if let Some(old) = backlog.entries.pop_front() {
+                    backlog.bytes -= old.len();
+                    backlog.first_offset += 1;
+                }
+            }
+            // Publish the offset while still ordered with other appends
+            self.offset.fetch_add(1, Ordering::Release);
+        }
+        self.appended.notify_waiters();
+    }
+
+    /// Encoded entries after `offset`, at most `limit` of them
+    ///
+    /// Returns None if entries after `offset` have already been trimmed from
+    /// the backlog (or `offset` is in the future), in which case the replica
+    /// needs a full sync.
+    pub fn entries_after(&self, offset: u64, limit: usize) -> Option<Vec<Arc<[u8]>>> {
+        let backlog = self.backlog.lock();
+        let last = backlog.first_offset + backlog.entries.len() as u64 - 1;
+        if offset + 1 < backlog.first_offset || offset > last {
+            return None;
+        }
+        let skip = (offset + 1 - backlog.first_offset) as usize;
+        Some(backlog.entries.iter().skip(skip).take(limit).cloned().collect())
+    }
+
+    /// Wait until an entry after `offset` has been appended
+    pub async fn wait_beyond(&self, offset: u64) {
+        let appended = self.appended.notified();
+        if self.offset() > offset {
+            return;
+        }
+        appended.await;
+    }
+}
+
+/// Encode a shard's current contents as `Set` entries, for a replica's full sync
+///
+/// Includes cold entries; expired entries are skipped.
+pub fn encode_shard(engine: &KvEngine, shard_id: usize) -> Vec<Arc<[u8]>> {
+    engine
+        .export_shard(shard_id)
+        .unwrap_or_default()
+        .iter()
+        .filter(|(_, entry)| !entry.is_expired())
+        .filter_map(|(key, entry)| encode(post_image(key, Some(entry))))
+        .collect()
+}
+
+/// Operation that recreates a key's state (None = deleted)
+pub(crate) fn post_image(key: &str, entry: Option<&Entry>) -> WalOp {
+    match entry {
+        Some(entry) => WalOp::SetAt {
+            key: key.to_string(),
+            value: entry.value.clone(),
+            expires_at_ms: entry.expires_at.map(|exp| {
+                unix_millis() + exp.saturating_duration_since(Instant::now()).as_millis() as u64
+            }),
+        },
+        None => WalOp::Delete {
+            key: key.to_string(),
+        },
+    }
+}
+
+fn encode(op: WalOp) -> Option<Arc<[u8]>> {
+    let entry = WalEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as i64,
+        op,
+    };
+    match encode_wal_entry(&entry) {
+        Ok(bytes) => Some(bytes.into()),
+        Err(e) => {
+            error!("Failed to encode replication entry: {}", e);
+            None
+        }
+    }
+}
+
+/// Unique-enough id for a log: process id and start time
+fn generate_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("{:x}-{:x}", std::process::id(), nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::format::decode_wal_entry;
+    use crate::types::KvValue;
+    use std::time::Duration;
+
+    fn set_op(key: &str) -> WalOp {
+        WalOp::Set {
+            key: key.to_string(),
+            value: KvValue::Int(1),
+            ttl: None,
+        }
+    }
+
+    #[test]
+    fn test_entries_after() {
+        let log = ReplicationLog::new(DEFAULT_BACKLOG_BYTES);
+        assert_eq!(log.offset(), 0);
+        assert_eq!(log.entries_after(0, 10).unwrap().len(), 0);
+
+        for i in 0..5 {
+            log.append(set_op(&format!("key_{}", i)));
+        }
+        assert_eq!(log.offset(), 5);
+
+        let entries = log.entries_after(2, 10).unwrap();
+        assert_eq!(entries.len(), 3);
+        match decode_wal_entry(&entries[0], 0).unwrap().op {
+            WalOp::Set { key, .. } => assert_eq!(key, "key_2"),
+            other => panic!("Unexpected op {:?}", other),
+        }
+
+        assert_eq!(log.entries_after(0, 2).unwrap().len(), 2);
+        assert_eq!(log.entries_after(5, 10).unwrap().len(), 0);
+        assert!(log.entries_after(6, 10).is_none());
+    }
+
+    #[test]
+    fn test_backlog_is_trimmed() {
+        let log = ReplicationLog::new(200);
+        for i in 0..50 {
+            log.append(set_op(&format!("key_{}", i)));
+        }
+
+        assert_eq!(log.offset(), 50);
+        assert!(log.entries_after(0, 100).is_none(), "Old offsets need a full sync");
+        assert!(!log.entries_after(49, 100).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_post_image() {
+        let log = ReplicationLog::new(DEFAULT_BACKLOG_BYTES);
+        let entry = Entry::new(KvValue::Int(7), Some(Duration::from_secs(60)));
+        log.record("counter", Some(&entry));
+        log.record("gone", None);
+
+        let entries = log.entries_after(0, 10).unwrap();
+        match decode_wal_entry(&entries[0], 0).unwrap().op {
+            WalOp::SetAt { key, value, expires_at_ms } => {
+                assert_eq!(key, "counter");
+                assert_eq!(value, KvValue::Int(7));
+                let ttl = expires_at_ms.unwrap() - unix_millis();
+                assert!(ttl > 59_000 && ttl <= 60_000);
+            }
+            other => panic!("Unexpected op {:?}", other),
+        }
+        assert!(matches!(
+            decode_wal_entry(&entries[1], 0).unwrap().op,
+            WalOp::Delete { .. }
+        ));
+    }
+
+    #[test]
+    fn test_position_round_trip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(ReplicationPosition::load(temp_dir.path()).unwrap(), None);
+
+        let position = ReplicationPosition { replid: "abc-123".to_string(), offset: 42 };
+        position.save(temp_dir.path()).unwrap();
+        assert_eq!(ReplicationPosition::load(temp_dir.path()).unwrap(), Some(position));
+
+        // An empty id marks a full sync in progress
+        ReplicationPosition::default().save(temp_dir.path()).unwrap();
+        assert_eq!(ReplicationPosition::load(temp_dir.path()).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_wait_beyond() {
+        let log = Arc::new(ReplicationLog::new(DEFAULT_BACKLOG_BYTES));
+        let writer = log.clone();
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            writer.append(set_op("key"));
+        });
+
+        tokio::time::timeout(Duration::from_secs(1), log.wait_beyond(0))
+            .await
+            .expect("append should wake waiter");
+        handle.await.unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+//! Hybrid RAM + disk tiered storage
+//!
+//! Keeps the working set in memory and spills cold entries to disk.
+//!
+//! ## Architecture
+//!
+//! - **Hot tier**: the regular in-memory shard maps
+//! - **Cold tier**: append-only segment files, indexed per shard in memory
+//! - **Demotion**: when a shard exceeds its share of the memory budget, its
+//!   least recently accessed entries are written to the cold tier
+//! - **Promotion**: reading or updating a cold entry loads it back into memory
+//! - **Compaction**: sealed segments that are mostly garbage are rewritten
+//!
+//! The cold tier is not a durability mechanism. Segment files are discarded on
+//! restart; use [`crate::persistence`] to survive crashes.
+
+pub mod segment;
+
+pub use segment::{SegmentPointer, SegmentStore};
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Segments with less than this fraction of live bytes are compacted
+const COMPACTION_LIVE_RATIO: f64 = 0.5;
+
+/// Tiered storage configuration
+#[derive(Debug, Clone)]
+pub struct TieredConfig {
+    /// Directory for segment files (default: ./data/tier)
+    pub data_dir: PathBuf,
+
+    /// Memory budget for resident entries across all shards (default: 1GB)
+    pub max_memory_bytes: usize,
+
+    /// Segment size before rotating to a new file (default: 64MB)
+    pub max_segment_size: u64,
+}
+
+impl Default for TieredConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("./data/tier"),
+            max_memory_bytes: 1024 * 1024 * 1024, // 1GB
+            max_segment_size: 64 * 1024 * 1024,   // 64MB
+        }
+    }
+}
+
+impl TieredConfig {
+    /// Create a new tiered config with the given segment directory and memory budget
+    pub fn new(data_dir: impl Into<PathBuf>, max_memory_bytes: usize) -> Self {
+        Self {
+            data_dir: data_dir.into(),
+            max_memory_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Set segment rotation size in bytes
+    pub fn with_max_segment_size(mut self, bytes: u64) -> Self {
+        self.max_segment_size = bytes;
+        self
+    }
+}
+
+/// Tiered storage statistics
+#[derive(Debug, Default, Clone)]
+pub struct TieredStats {
+    /// Entries resident in memory
+    pub hot_entries: usize,
+
+    /// Entries stored on disk
+    pub cold_entries: usize,
+
+    /// Estimated memory used by resident entries
+    pub hot_bytes: usize,
+
+    /// Bytes on disk referenced by cold entries
+    pub cold_bytes: u64,
+
+    /// Number of segment files
+    pub segments: usize,
+
+    /// Entries moved from memory to disk
+    pub demotions: u64,
+
+    /// Entries loaded back from disk into memory
+    pub promotions: u64,
+}
+
+/// Cold tier shared by all shards of an engine
+pub struct TieredStore {
+    segments: SegmentStore,
+    shard_budget: usize,
+    demotions: AtomicU64,
+    promotions: AtomicU64,
+}
+
+impl TieredStore {
+    /// Open the cold tier, splitting the memory budget evenly across shards
+    pub fn open(config: &TieredConfig, num_shards: usize) -> std::io::Result<Self> {
+        Ok(Self {
+            segments: SegmentStore::open(&config.data_dir, config.max_segment_size)?,
+            shard_budget: (config.max_memory_bytes / num_shards.max(1)).max(1),
+            demotions: AtomicU64::new(0),
+            promotions: AtomicU64::new(0),
+        })
+    }
+
+    /// Memory budget for a single shard
+    #[inline]
+    pub fn shard_budget(&self) -> usize {
+        self.shard_budget
+    }
+
+    /// Underlying segment files
+    #[inline]
+    pub fn segments(&self) -> &SegmentStore {
+        &self.segments
+    }
+
+    /// Segments that should be compacted
+    pub fn compaction_candidates(&self) -> std::collections::HashSet<u32> {
+        self.segments.compaction_candidates(COMPACTION_LIVE_RATIO)
+    }
+
+    pub(crate) fn record_demotions(&self, count: u64) {
+        self.demotions.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_promotion(&self) {
+        self.promotions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Entries moved from memory to disk so far
+    pub fn demotions(&self) -> u64 {
+        self.demotions.load(Ordering::Relaxed)
+    }
+
+    /// Entries loaded back from disk so far
+    pub fn promotions(&self) -> u64 {
+        self.promotions.load(Ordering::Relaxed)
+    }
+}
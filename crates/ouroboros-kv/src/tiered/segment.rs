@@ -0,0 +1,377 @@
+//! Append-only segment files backing the cold tier
+//!
+//! ## Record Format
+//!
+//! ```text
+//! Record: [Length:4 | CRC32:4 | Payload:N]
+//! Payload: bincode-encoded (key, SerializableEntry)
+//! ```
+//!
+//! ## File Naming
+//!
+//! Segments are named `segment-{id:08}.seg`. Only the active segment is
+//! appended to; it is sealed once it reaches the configured size. A sealed
+//! segment is deleted as soon as none of its records are referenced anymore.
+//!
+//! Segment files are scratch space: the in-memory index pointing into them is
+//! not persisted, so any files left over from a previous process are removed
+//! on open. Durability is still provided by the WAL and snapshots.
+
+use crate::engine::Entry;
+use crate::persistence::format::calculate_crc32;
+use crate::persistence::snapshot::SerializableEntry;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
+use tracing::{debug, warn};
+
+/// Record header size: length (4) + CRC32 (4)
+const RECORD_HEADER_SIZE: u64 = 8;
+
+/// Location of a single record inside a segment file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentPointer {
+    /// Segment the record lives in
+    pub segment_id: u32,
+    /// Byte offset of the record header
+    pub offset: u64,
+    /// Total record length including header
+    pub len: u32,
+}
+
+/// Byte accounting for one segment
+#[derive(Debug, Default, Clone, Copy)]
+struct SegmentUsage {
+    /// Bytes ever written to the segment
+    total_bytes: u64,
+    /// Bytes still referenced by the cold index
+    live_bytes: u64,
+}
+
+/// Segment currently receiving appends
+struct ActiveSegment {
+    id: u32,
+    writer: BufWriter<File>,
+    position: u64,
+}
+
+/// Append-only store of demoted entries
+pub struct SegmentStore {
+    dir: PathBuf,
+    max_segment_size: u64,
+    active: Mutex<ActiveSegment>,
+    active_id: AtomicU32,
+    usage: Mutex<HashMap<u32, SegmentUsage>>,
+}
+
+impl SegmentStore {
+    /// Open a segment store in `dir`, discarding segments from previous runs
+    pub fn open(dir: impl Into<PathBuf>, max_segment_size: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        for path in find_segment_files(&dir)? {
+            debug!("Removing stale segment: {}", path.display());
+            fs::remove_file(&path)?;
+        }
+
+        let active = ActiveSegment {
+            id: 0,
+            writer: BufWriter::with_capacity(64 * 1024, create_segment(&dir, 0)?),
+            position: 0,
+        };
+
+        let mut usage = HashMap::new();
+        usage.insert(0, SegmentUsage::default());
+
+        Ok(Self {
+            dir,
+            max_segment_size,
+            active: Mutex::new(active),
+            active_id: AtomicU32::new(0),
+            usage: Mutex::new(usage),
+        })
+    }
+
+    /// Append an entry, returning where it was written
+    ///
+    /// The record is buffered; call [`flush`](Self::flush) before reading it back.
+    pub fn append(&self, key: &str, entry: &Entry) -> io::Result<SegmentPointer> {
+        let record = SerializableEntry::from_entry(entry, Instant::now());
+        let payload = bincode::serialize(&(key, &record))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let len = RECORD_HEADER_SIZE + payload.len() as u64;
+
+        let mut active = self.active.lock();
+
+        if active.position > 0 && active.position + len > self.max_segment_size {
+            self.rotate(&mut active)?;
+        }
+
+        active.writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+        active.writer.write_all(&calculate_crc32(&payload).to_be_bytes())?;
+        active.writer.write_all(&payload)?;
+
+        let pointer = SegmentPointer {
+            segment_id: active.id,
+            offset: active.position,
+            len: len as u32,
+        };
+        active.position += len;
+
+        let mut usage = self.usage.lock();
+        let segment = usage.entry(active.id).or_default();
+        segment.total_bytes += len;
+        segment.live_bytes += len;
+
+        Ok(pointer)
+    }
+
+    /// Flush buffered appends so they are visible to readers
+    pub fn flush(&self) -> io::Result<()> {
+        self.active.lock().writer.flush()
+    }
+
+    /// Read an entry back from disk
+    pub fn read(&self, pointer: SegmentPointer) -> io::Result<(String, Entry)> {
+        let mut file = File::open(segment_path(&self.dir, pointer.segment_id))?;
+        file.seek(SeekFrom::Start(pointer.offset))?;
+
+        let mut buf = vec![0u8; pointer.len as usize];
+        file.read_exact(&mut buf)?;
+
+        let payload_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as u64;
+        if payload_len + RECORD_HEADER_SIZE != pointer.len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Segment {} record at {} has length {}, expected {}",
+                    pointer.segment_id,
+                    pointer.offset,
+                    payload_len + RECORD_HEADER_SIZE,
+                    pointer.len
+                ),
+            ));
+        }
+
+        let expected = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        let payload = &buf[RECORD_HEADER_SIZE as usize..];
+        let actual = calculate_crc32(payload);
+        if expected != actual {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Segment {} checksum mismatch at {}: expected {:x}, got {:x}",
+                    pointer.segment_id, pointer.offset, expected, actual
+                ),
+            ));
+        }
+
+        let (key, record): (String, SerializableEntry) = bincode::deserialize(payload)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok((key, record.to_entry()))
+    }
+
+    /// Mark a record as no longer referenced
+    ///
+    /// Sealed segments are deleted once their last live record is released.
+    pub fn release(&self, pointer: SegmentPointer) {
+        let mut usage = self.usage.lock();
+
+        let Some(segment) = usage.get_mut(&pointer.segment_id) else {
+            return;
+        };
+        segment.live_bytes = segment.live_bytes.saturating_sub(pointer.len as u64);
+
+        if segment.live_bytes == 0 && pointer.segment_id != self.active_id.load(Ordering::Acquire) {
+            usage.remove(&pointer.segment_id);
+            drop(usage);
+            self.remove_segment(pointer.segment_id);
+        }
+    }
+
+    /// Sealed segments whose live/total ratio has fallen below `min_live_ratio`
+    pub fn compaction_candidates(&self, min_live_ratio: f64) -> HashSet<u32> {
+        let active_id = self.active_id.load(Ordering::Acquire);
+        self.usage
+            .lock()
+            .iter()
+            .filter(|(id, usage)| {
+                **id != active_id
+                    && usage.total_bytes > 0
+                    && (usage.live_bytes as f64) < usage.total_bytes as f64 * min_live_ratio
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Number of segment files on disk
+    pub fn segment_count(&self) -> usize {
+        self.usage.lock().len()
+    }
+
+    /// Bytes on disk still referenced by the cold index
+    pub fn live_bytes(&self) -> u64 {
+        self.usage.lock().values().map(|u| u.live_bytes).sum()
+    }
+
+    /// Total bytes on disk, including records awaiting compaction
+    pub fn total_bytes(&self) -> u64 {
+        self.usage.lock().values().map(|u| u.total_bytes).sum()
+    }
+
+    /// Seal the active segment and start a new one
+    fn rotate(&self, active: &mut ActiveSegment) -> io::Result<()> {
+        active.writer.flush()?;
+
+        let sealed_id = active.id;
+        let new_id = sealed_id + 1;
+        active.writer = BufWriter::with_capacity(64 * 1024, create_segment(&self.dir, new_id)?);
+        active.id = new_id;
+        active.position = 0;
+        self.active_id.store(new_id, Ordering::Release);
+
+        debug!("Sealed segment {}, now writing segment {}", sealed_id, new_id);
+
+        let mut usage = self.usage.lock();
+        usage.insert(new_id, SegmentUsage::default());
+
+        // Every record of the sealed segment may already have been released
+        if usage.get(&sealed_id).map(|u| u.live_bytes == 0).unwrap_or(false) {
+            usage.remove(&sealed_id);
+            drop(usage);
+            self.remove_segment(sealed_id);
+        }
+
+        Ok(())
+    }
+
+    fn remove_segment(&self, id: u32) {
+        let path = segment_path(&self.dir, id);
+        match fs::remove_file(&path) {
+            Ok(()) => debug!("Deleted empty segment: {}", path.display()),
+            Err(e) => warn!("Failed to delete segment {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn segment_path(dir: &Path, id: u32) -> PathBuf {
+    dir.join(format!("segment-{:08}.seg", id))
+}
+
+fn create_segment(dir: &Path, id: u32) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(segment_path(dir, id))
+}
+
+/// Find all segment files in a directory
+pub fn find_segment_files(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut segments = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            if name.starts_with("segment-") && name.ends_with(".seg") {
+                segments.push(path);
+            }
+        }
+    }
+
+    segments.sort();
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::KvValue;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_append_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SegmentStore::open(temp_dir.path(), 1024 * 1024).unwrap();
+
+        let entry = Entry::new(KvValue::String("cold".to_string()), Some(Duration::from_secs(60)));
+        let pointer = store.append("key1", &entry).unwrap();
+        store.flush().unwrap();
+
+        let (key, restored) = store.read(pointer).unwrap();
+        assert_eq!(key, "key1");
+        assert_eq!(restored.value, KvValue::String("cold".to_string()));
+        assert!(!restored.is_expired());
+        assert_eq!(store.live_bytes(), pointer.len as u64);
+    }
+
+    #[test]
+    fn test_rotation_and_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SegmentStore::open(temp_dir.path(), 256).unwrap();
+
+        let mut pointers = Vec::new();
+        for i in 0..20 {
+            let entry = Entry::new(KvValue::String("x".repeat(50)), None);
+            pointers.push(store.append(&format!("key{}", i), &entry).unwrap());
+        }
+        store.flush().unwrap();
+
+        let segments = find_segment_files(temp_dir.path()).unwrap();
+        assert!(segments.len() > 1, "Expected rotation to create multiple segments");
+
+        // Releasing every record of a sealed segment deletes its file
+        for pointer in pointers.iter().filter(|p| p.segment_id == 0) {
+            store.release(*pointer);
+        }
+        assert!(!segment_path(temp_dir.path(), 0).exists());
+        assert_eq!(store.segment_count(), segments.len() - 1);
+    }
+
+    #[test]
+    fn test_compaction_candidates() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SegmentStore::open(temp_dir.path(), 256).unwrap();
+
+        let mut pointers = Vec::new();
+        for i in 0..20 {
+            let entry = Entry::new(KvValue::Int(i), None);
+            pointers.push(store.append(&format!("key{}", i), &entry).unwrap());
+        }
+
+        assert!(store.compaction_candidates(0.5).is_empty());
+
+        // Release all but one record of segment 0
+        let in_first: Vec<_> = pointers.iter().filter(|p| p.segment_id == 0).collect();
+        for pointer in &in_first[1..] {
+            store.release(**pointer);
+        }
+
+        assert!(store.compaction_candidates(0.5).contains(&0));
+    }
+
+    #[test]
+    fn test_open_discards_stale_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        File::create(temp_dir.path().join("segment-00000007.seg")).unwrap();
+
+        let _store = SegmentStore::open(temp_dir.path(), 1024).unwrap();
+
+        let segments = find_segment_files(temp_dir.path()).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].ends_with("segment-00000000.seg"));
+    }
+}
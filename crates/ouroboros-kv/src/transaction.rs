@@ -0,0 +1,199 @@
+//! Atomic multi-key transactions
+//!
+//! A [`Transaction`] is a list of guards and operations executed by
+//! [`KvEngine::transact`](crate::engine::KvEngine::transact) while holding the
+//! locks of every shard it touches:
+//!
+//! 1. Guards are checked; if any fails the transaction is aborted and nothing
+//!    is changed. [`Transaction::watch`] is the optimistic-locking guard: it
+//!    fails if the key was written after
+//!    [`KvEngine::version`](crate::engine::KvEngine::version) was read.
+//! 2. Operations run in order against a staged copy of the keys, so later
+//!    operations see the effects of earlier ones. If any operation fails (e.g.
+//!    `incr` on a string) the transaction is rolled back.
+//! 3. The resulting state of every modified key is applied at once and logged
+//!    to the WAL as a single record, so recovery and replicas never see half a
+//!    transaction.
+//!
+//! ```
+//! use ouroboros_kv::{Guard, KvEngine, KvKey, KvValue, Transaction};
+//!
+//! let engine = KvEngine::new();
+//! let stock = KvKey::new("stock:widget").unwrap();
+//! let reserved = KvKey::new("reserved:widget").unwrap();
+//! engine.set(&stock, KvValue::Int(10), None);
+//!
+//! // Reserve 3 widgets, but only if at least 3 are in stock
+//! let txn = Transaction::new()
+//!     .guard(&stock, Guard::AtLeast(3))
+//!     .incr(&stock, -3)
+//!     .incr(&reserved, 3);
+//! let results = engine.transact(&txn).unwrap().expect("guard passed");
+//! assert_eq!(results.len(), 2);
+//! assert_eq!(engine.get(&stock), Some(KvValue::Int(7)));
+//! ```
+
+use crate::types::{KvKey, KvValue};
+use std::time::Duration;
+
+/// Condition a key must satisfy for the transaction to run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Guard {
+    /// The key exists
+    Exists,
+    /// The key does not exist
+    Missing,
+    /// The key holds exactly this value
+    Equals(KvValue),
+    /// The key holds an integer of at least this value
+    AtLeast(i64),
+    /// The key has not been written since [`KvEngine::version`](crate::engine::KvEngine::version)
+    /// returned this version (0 = the key did not exist)
+    Version(u64),
+}
+
+impl Guard {
+    /// Check the guard against a key's current value and version
+    pub(crate) fn check(&self, current: Option<(&KvValue, u64)>) -> bool {
+        match (self, current) {
+            (Guard::Exists, current) => current.is_some(),
+            (Guard::Missing, current) => current.is_none(),
+            (Guard::Equals(expected), Some((value, _))) => value == expected,
+            (Guard::AtLeast(min), Some((KvValue::Int(n), _))) => n >= min,
+            (Guard::Version(expected), current) => current.map(|(_, v)| v).unwrap_or(0) == *expected,
+            _ => false,
+        }
+    }
+}
+
+/// Operation executed inside a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxnOp {
+    Get { key: String },
+    Set { key: String, value: KvValue, ttl: Option<Duration> },
+    SetNx { key: String, value: KvValue, ttl: Option<Duration> },
+    Delete { key: String },
+    Incr { key: String, delta: i64 },
+    /// Set or clear the expiry of an existing key
+    Expire { key: String, ttl: Option<Duration> },
+}
+
+impl TxnOp {
+    /// Key the operation applies to
+    pub fn key(&self) -> &str {
+        match self {
+            TxnOp::Get { key }
+            | TxnOp::Set { key, .. }
+            | TxnOp::SetNx { key, .. }
+            | TxnOp::Delete { key }
+            | TxnOp::Incr { key, .. }
+            | TxnOp::Expire { key, .. } => key,
+        }
+    }
+}
+
+/// Result of one operation, in the order the operations were added
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxnResult {
+    /// `get`: the value at that point in the transaction
+    Value(Option<KvValue>),
+    /// `incr`: the new value
+    Int(i64),
+    /// `setnx`, `delete`, `expire`: whether the key was changed
+    Bool(bool),
+    /// `set`
+    Ok,
+}
+
+/// Guards and operations to execute atomically
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transaction {
+    pub guards: Vec<(String, Guard)>,
+    pub ops: Vec<TxnOp>,
+}
+
+impl Transaction {
+    /// Create an empty transaction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort unless `key` satisfies `guard`
+    pub fn guard(mut self, key: &KvKey, guard: Guard) -> Self {
+        self.guards.push((key.as_str().to_string(), guard));
+        self
+    }
+
+    /// Abort if `key` was written after `version` was read (optimistic WATCH)
+    pub fn watch(self, key: &KvKey, version: u64) -> Self {
+        self.guard(key, Guard::Version(version))
+    }
+
+    pub fn get(mut self, key: &KvKey) -> Self {
+        self.ops.push(TxnOp::Get { key: key.as_str().to_string() });
+        self
+    }
+
+    pub fn set(mut self, key: &KvKey, value: KvValue, ttl: Option<Duration>) -> Self {
+        self.ops.push(TxnOp::Set { key: key.as_str().to_string(), value, ttl });
+        self
+    }
+
+    pub fn setnx(mut self, key: &KvKey, value: KvValue, ttl: Option<Duration>) -> Self {
+        self.ops.push(TxnOp::SetNx { key: key.as_str().to_string(), value, ttl });
+        self
+    }
+
+    pub fn delete(mut self, key: &KvKey) -> Self {
+        self.ops.push(TxnOp::Delete { key: key.as_str().to_string() });
+        self
+    }
+
+    pub fn incr(mut self, key: &KvKey, delta: i64) -> Self {
+        self.ops.push(TxnOp::Incr { key: key.as_str().to_string(), delta });
+        self
+    }
+
+    pub fn expire(mut self, key: &KvKey, ttl: Option<Duration>) -> Self {
+        self.ops.push(TxnOp::Expire { key: key.as_str().to_string(), ttl });
+        self
+    }
+
+    /// Append an operation
+    pub fn push(&mut self, op: TxnOp) {
+        self.ops.push(op);
+    }
+
+    /// Every key the transaction reads or writes
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.guards
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .chain(self.ops.iter().map(TxnOp::key))
+    }
+
+    /// Whether the transaction has no operations
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_check() {
+        let value = KvValue::Int(5);
+        assert!(Guard::Exists.check(Some((&value, 3))));
+        assert!(!Guard::Exists.check(None));
+        assert!(Guard::Missing.check(None));
+        assert!(Guard::Equals(KvValue::Int(5)).check(Some((&value, 3))));
+        assert!(Guard::AtLeast(5).check(Some((&value, 3))));
+        assert!(!Guard::AtLeast(6).check(Some((&value, 3))));
+        assert!(!Guard::AtLeast(0).check(None));
+        assert!(Guard::Version(3).check(Some((&value, 3))));
+        assert!(!Guard::Version(2).check(Some((&value, 3))));
+        assert!(Guard::Version(0).check(None));
+    }
+}
@@ -4,6 +4,9 @@
 
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
 
 /// Key type for KV store (max 256 UTF-8 characters)
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -47,4 +50,259 @@ pub enum KvValue {
     Map(std::collections::HashMap<String, KvValue>),
     /// Null/None value
     Null,
+    /// Unordered set of unique members
+    Set(HashSet<String>),
+    /// Members ordered by score
+    SortedSet(SortedSet),
+}
+
+impl KvValue {
+    /// Approximate number of bytes this value occupies in memory
+    ///
+    /// Counts the enum itself plus any heap allocations it owns. Used for
+    /// memory budgeting, so it favours speed over exactness.
+    pub fn estimated_size(&self) -> usize {
+        let heap = match self {
+            KvValue::Int(_) | KvValue::Float(_) | KvValue::Decimal(_) | KvValue::Null => 0,
+            KvValue::String(s) => s.capacity(),
+            KvValue::Bytes(b) => b.capacity(),
+            KvValue::List(items) => items.iter().map(KvValue::estimated_size).sum(),
+            KvValue::Map(map) => map
+                .iter()
+                .map(|(k, v)| std::mem::size_of::<String>() + k.capacity() + v.estimated_size())
+                .sum(),
+            KvValue::Set(members) => members
+                .iter()
+                .map(|m| std::mem::size_of::<String>() + m.capacity())
+                .sum(),
+            KvValue::SortedSet(zset) => zset.estimated_size(),
+        };
+        std::mem::size_of::<KvValue>() + heap
+    }
+
+    /// Name of the value's type, as used in type mismatch errors
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            KvValue::Int(_) => "int",
+            KvValue::Float(_) => "float",
+            KvValue::Decimal(_) => "decimal",
+            KvValue::String(_) => "string",
+            KvValue::Bytes(_) => "bytes",
+            KvValue::List(_) => "list",
+            KvValue::Map(_) => "hash",
+            KvValue::Null => "null",
+            KvValue::Set(_) => "set",
+            KvValue::SortedSet(_) => "zset",
+        }
+    }
+}
+
+/// Score of a sorted set member, totally ordered so it can key a `BTreeSet`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Set of unique members ordered by score, then by member (Redis sorted set)
+///
+/// Score lookups are O(1) and score ranges O(log n + k). Rank-based access
+/// walks the ordered index, so it is O(rank). Scores are never NaN; `-0.0` is
+/// stored as `0.0`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<(String, f64)>", into = "Vec<(String, f64)>")]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    order: BTreeSet<(Score, String)>,
+}
+
+impl SortedSet {
+    /// Create an empty sorted set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Whether the set has no members
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Score of a member
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Add a member or update its score, returns true if it was added
+    ///
+    /// # Panics
+    ///
+    /// If `score` is NaN.
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        assert!(!score.is_nan(), "sorted set score must not be NaN");
+        let score = score + 0.0; // -0.0 => 0.0, so equal scores compare equal
+        let added = match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                self.order.remove(&(Score(old), member.clone()));
+                false
+            }
+            None => true,
+        };
+        self.order.insert((Score(score), member));
+        added
+    }
+
+    /// Remove a member, returns true if it was present
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.order.remove(&(Score(score), member.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Position of a member in ascending (or with `rev`, descending) order
+    pub fn rank(&self, member: &str, rev: bool) -> Option<usize> {
+        let score = self.score(member)?;
+        let below = self.order.range(..(Score(score), member.to_string())).count();
+        Some(if rev { self.len() - 1 - below } else { below })
+    }
+
+    /// Members and scores in ascending order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> + '_ {
+        self.order.iter().map(|(score, member)| (member.as_str(), score.0))
+    }
+
+    /// Members whose score lies within the bounds, in ascending order
+    ///
+    /// Bounds must not be NaN.
+    pub fn range_by_score(&self, min: Bound<f64>, max: Bound<f64>) -> impl DoubleEndedIterator<Item = (&str, f64)> + '_ {
+        // Every member sorts after (score, ""), so score bounds become
+        // inclusive/exclusive bounds on (score, "") entries
+        let entry = |score: f64| (Score(score + 0.0), String::new());
+        let start = match min {
+            Bound::Included(s) => Bound::Included(entry(s)),
+            Bound::Excluded(s) => Bound::Included(entry((s + 0.0).next_up())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match max {
+            Bound::Included(s) if s == f64::INFINITY => Bound::Unbounded,
+            Bound::Included(s) => Bound::Excluded(entry((s + 0.0).next_up())),
+            Bound::Excluded(s) => Bound::Excluded(entry(s)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let empty = match (&start, &end) {
+            (Bound::Included(lo), Bound::Excluded(hi)) => lo >= hi,
+            _ => false,
+        } || matches!(min, Bound::Excluded(s) if s == f64::INFINITY);
+        let range = if empty {
+            // BTreeSet::range panics on inverted bounds
+            (Bound::Included(entry(0.0)), Bound::Excluded(entry(0.0)))
+        } else {
+            (start, end)
+        };
+
+        self.order
+            .range(range)
+            .map(|(score, member)| (member.as_str(), score.0))
+    }
+
+    fn estimated_size(&self) -> usize {
+        // Each member is stored twice: in the score map and in the ordered index
+        self.scores
+            .keys()
+            .map(|m| 2 * (std::mem::size_of::<String>() + m.capacity() + std::mem::size_of::<f64>()))
+            .sum()
+    }
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl From<Vec<(String, f64)>> for SortedSet {
+    fn from(members: Vec<(String, f64)>) -> Self {
+        let mut zset = SortedSet::new();
+        for (member, score) in members {
+            if !score.is_nan() {
+                zset.insert(member, score);
+            }
+        }
+        zset
+    }
+}
+
+impl From<SortedSet> for Vec<(String, f64)> {
+    fn from(zset: SortedSet) -> Self {
+        zset.order.into_iter().map(|(score, member)| (member, score.0)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_set_order_and_rank() {
+        let mut zset = SortedSet::new();
+        assert!(zset.insert("carol".to_string(), 30.0));
+        assert!(zset.insert("alice".to_string(), 10.0));
+        assert!(zset.insert("bob".to_string(), 10.0));
+        assert!(!zset.insert("carol".to_string(), 5.0));
+
+        let order: Vec<&str> = zset.iter().map(|(m, _)| m).collect();
+        assert_eq!(order, vec!["carol", "alice", "bob"]);
+        assert_eq!(zset.rank("bob", false), Some(2));
+        assert_eq!(zset.rank("bob", true), Some(0));
+        assert_eq!(zset.rank("dave", false), None);
+
+        assert!(zset.remove("carol"));
+        assert!(!zset.remove("carol"));
+        assert_eq!(zset.len(), 2);
+    }
+
+    #[test]
+    fn test_sorted_set_range_by_score() {
+        let zset: SortedSet = vec![
+            ("a".to_string(), 1.0),
+            ("b".to_string(), 2.0),
+            ("c".to_string(), 2.0),
+            ("d".to_string(), 3.0),
+        ]
+        .into();
+
+        let members = |min, max| -> Vec<&str> { zset.range_by_score(min, max).map(|(m, _)| m).collect() };
+        assert_eq!(members(Bound::Included(2.0), Bound::Included(3.0)), vec!["b", "c", "d"]);
+        assert_eq!(members(Bound::Excluded(1.0), Bound::Excluded(3.0)), vec!["b", "c"]);
+        assert_eq!(members(Bound::Unbounded, Bound::Excluded(2.0)), vec!["a"]);
+        assert_eq!(members(Bound::Included(3.0), Bound::Included(1.0)), Vec::<&str>::new());
+        assert_eq!(members(Bound::Excluded(2.0), Bound::Excluded(2.0)), Vec::<&str>::new());
+        assert_eq!(members(Bound::Excluded(f64::INFINITY), Bound::Unbounded), Vec::<&str>::new());
+        assert_eq!(zset.range_by_score(Bound::Included(2.0), Bound::Unbounded).rev().next(), Some(("d", 3.0)));
+
+        // Round-trips through serde as (member, score) pairs
+        let bytes = bincode::serialize(&KvValue::SortedSet(zset.clone())).unwrap();
+        let decoded: KvValue = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, KvValue::SortedSet(zset));
+    }
 }
@@ -3,6 +3,7 @@
 use ouroboros_kv::engine::KvEngine;
 use ouroboros_kv::persistence::{PersistenceConfig, PersistenceHandle};
 use ouroboros_kv::types::{KvKey, KvValue};
+use ouroboros_kv::{EvictionConfig, EvictionPolicy, TieredConfig};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -356,3 +357,75 @@ fn test_recovery_performance() {
     assert!(recovery_time < Duration::from_secs(5));
     assert!(stats.snapshot_loaded);
 }
+
+/// Test recovery into an engine with tiered storage enabled
+#[test]
+fn test_recovery_into_tiered_engine() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let tier_dir = temp_dir.path().join("tier");
+
+    {
+        let (engine, persistence) = create_engine_with_persistence(&data_dir);
+
+        for i in 0..500 {
+            let key = KvKey::new(format!("session_{}", i)).unwrap();
+            engine.set(&key, KvValue::String("s".repeat(128)), None);
+        }
+
+        persistence.create_snapshot();
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    let engine = KvEngine::with_shards(256);
+    engine
+        .enable_tiering(TieredConfig::new(&tier_dir, 16 * 1024))
+        .unwrap();
+
+    let (engine, _stats) =
+        ouroboros_kv::persistence::recovery::RecoveryManager::recover_into(engine, &data_dir)
+            .unwrap();
+
+    assert_eq!(engine.len(), 500);
+    assert!(engine.memory_usage() <= 16 * 1024);
+    assert!(engine.tiered_stats().unwrap().cold_entries > 0);
+
+    for i in 0..500 {
+        let key = KvKey::new(format!("session_{}", i)).unwrap();
+        assert_eq!(engine.get(&key), Some(KvValue::String("s".repeat(128))));
+    }
+}
+
+/// Test that keys evicted before a restart stay gone after recovery
+#[test]
+fn test_evictions_survive_recovery() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path();
+
+    let survivors = {
+        let (engine, persistence) = create_engine_with_persistence(data_dir);
+        engine
+            .enable_eviction(EvictionConfig::new(EvictionPolicy::AllKeysLru).with_max_keys(512))
+            .unwrap();
+
+        for i in 0..2000 {
+            let key = KvKey::new(format!("key_{}", i)).unwrap();
+            engine.set(&key, KvValue::Int(i), None);
+        }
+        assert!(engine.evictions() > 0);
+
+        persistence.flush();
+        thread::sleep(Duration::from_millis(200));
+
+        let mut keys = engine.keys("*");
+        keys.sort();
+        keys
+    };
+
+    let (engine, _stats) =
+        ouroboros_kv::persistence::recovery::RecoveryManager::recover(data_dir, 256).unwrap();
+
+    let mut recovered = engine.keys("*");
+    recovered.sort();
+    assert_eq!(recovered, survivors);
+}
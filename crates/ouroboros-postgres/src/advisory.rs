@@ -0,0 +1,188 @@
+//! PostgreSQL advisory locks.
+//!
+//! Advisory locks are application-defined locks on a 64-bit key. They come in
+//! two scopes:
+//!
+//! - **Session-level** ([`AdvisoryLock`]): held by a dedicated pooled
+//!   connection until released, independent of any transaction.
+//! - **Transaction-level** ([`Transaction::advisory_lock`]): released
+//!   automatically when the transaction commits or rolls back.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use ouroboros_postgres::{advisory_key, AdvisoryLock};
+//!
+//! // Only one instance runs the nightly report
+//! if let Some(lock) = AdvisoryLock::try_acquire(&conn, advisory_key("nightly-report")).await? {
+//!     run_report(&conn).await?;
+//!     lock.release().await?;
+//! }
+//! ```
+//!
+//! [`Transaction::advisory_lock`]: crate::Transaction::advisory_lock
+
+use crate::{Connection, DataBridgeError, Result};
+use sqlx::pool::PoolConnection;
+use sqlx::Postgres;
+
+/// Derives a stable advisory lock key from a name.
+///
+/// Uses 64-bit FNV-1a, so the same name maps to the same key across
+/// processes, builds and platforms.
+pub fn advisory_key(name: &str) -> i64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = name.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    hash as i64
+}
+
+/// A session-level advisory lock.
+///
+/// The lock lives on a connection taken from the pool for as long as the
+/// guard exists. Call [`release`](Self::release) to unlock and return the
+/// connection to the pool; dropping the guard instead closes the connection,
+/// which ends the session and so releases the lock.
+pub struct AdvisoryLock {
+    key: i64,
+    conn: Option<PoolConnection<Postgres>>,
+}
+
+impl std::fmt::Debug for AdvisoryLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdvisoryLock").field("key", &self.key).finish_non_exhaustive()
+    }
+}
+
+impl AdvisoryLock {
+    /// Acquires the lock, waiting until it is available.
+    ///
+    /// Cancel-safe: if the future is dropped while waiting, the connection is
+    /// closed rather than returned to the pool, so a lock granted after the
+    /// caller gave up is released with its session.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no connection can be acquired or the lock query fails.
+    pub async fn acquire(conn: &Connection, key: i64) -> Result<Self> {
+        let mut lock = Self::guard(conn, key).await?;
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(key)
+            .execute(lock.connection())
+            .await?;
+
+        tracing::debug!(key, "Acquired advisory lock");
+        Ok(lock)
+    }
+
+    /// Acquires the lock if it is free, or returns `None` at once if another
+    /// session holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no connection can be acquired or the lock query fails.
+    pub async fn try_acquire(conn: &Connection, key: i64) -> Result<Option<Self>> {
+        let mut lock = Self::guard(conn, key).await?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(key)
+            .fetch_one(lock.connection())
+            .await?;
+
+        if !acquired {
+            // Nothing is held, so the connection can go back to the pool
+            drop(lock.conn.take());
+            return Ok(None);
+        }
+        tracing::debug!(key, "Acquired advisory lock");
+        Ok(Some(lock))
+    }
+
+    /// Takes a connection for the lock, owned by the guard from the start so
+    /// that dropping a pending lock query closes it.
+    async fn guard(conn: &Connection, key: i64) -> Result<Self> {
+        let pooled = conn.pool().acquire().await?;
+        Ok(Self { key, conn: Some(pooled) })
+    }
+
+    fn connection(&mut self) -> &mut sqlx::PgConnection {
+        self.conn.as_mut().expect("advisory lock connection is only taken on release")
+    }
+
+    /// Returns the lock key.
+    pub fn key(&self) -> i64 {
+        self.key
+    }
+
+    /// Releases the lock and returns its connection to the pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the unlock query fails; the connection is then closed,
+    /// which releases the lock anyway.
+    pub async fn release(mut self) -> Result<()> {
+        let Some(mut pooled) = self.conn.take() else {
+            return Ok(());
+        };
+
+        let released: bool = match sqlx::query_scalar("SELECT pg_advisory_unlock($1)")
+            .bind(self.key)
+            .fetch_one(&mut *pooled)
+            .await
+        {
+            Ok(released) => released,
+            Err(e) => {
+                close_detached(pooled);
+                return Err(e.into());
+            }
+        };
+
+        if !released {
+            close_detached(pooled);
+            return Err(DataBridgeError::Query(format!(
+                "Advisory lock {} was not held by this session",
+                self.key
+            )));
+        }
+        tracing::debug!(key = self.key, "Released advisory lock");
+        Ok(())
+    }
+}
+
+impl Drop for AdvisoryLock {
+    fn drop(&mut self) {
+        if let Some(pooled) = self.conn.take() {
+            tracing::debug!(key = self.key, "Advisory lock dropped without release, closing its connection");
+            close_detached(pooled);
+        }
+    }
+}
+
+/// Takes a connection out of the pool and closes it, ending its session.
+fn close_detached(pooled: PoolConnection<Postgres>) {
+    use sqlx::Connection as _;
+
+    let conn = pooled.detach();
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn(async move {
+            let _ = conn.close().await;
+        });
+    }
+    // Without a runtime the connection is dropped here, which closes its socket
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advisory_key_is_stable() {
+        assert_eq!(advisory_key("migrations"), advisory_key("migrations"));
+        assert_ne!(advisory_key("migrations"), advisory_key("migration"));
+        // FNV-1a of the empty string is the offset basis
+        assert_eq!(advisory_key(""), 0xcbf2_9ce4_8422_2325_u64 as i64);
+        assert_eq!(advisory_key("a"), 0xaf63_dc4c_8601_ec8c_u64 as i64);
+    }
+}
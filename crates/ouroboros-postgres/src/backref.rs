@@ -186,8 +186,7 @@ impl<'a> BackRefLoader<'a> {
         );
 
         // Add filters
-        let mut param_num = 2;
-        for (col, op, _) in &config.filters {
+        for (param_num, (col, op, _)) in (2..).zip(config.filters.iter()) {
             QueryBuilder::validate_identifier(col)?;
             sql.push_str(&format!(
                 " AND {} {} ${}",
@@ -195,7 +194,6 @@ impl<'a> BackRefLoader<'a> {
                 op,
                 param_num
             ));
-            param_num += 1;
         }
 
         // Add ORDER BY
@@ -2,14 +2,18 @@
 //!
 //! This module provides connection pooling using SQLx's built-in pool manager.
 //! Similar to ouroboros-mongodb's connection management, but optimized for PostgreSQL.
-//! Includes connection resilience with exponential backoff retries.
+//! Includes connection resilience with exponential backoff retries and
+//! optional read-replica routing (see [`crate::replica`]).
 
 use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use std::future::Future;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{info, warn, instrument};
 
-use crate::{DataBridgeError, Result};
+use crate::replica::{self, Replica, ReplicaSet, StickyWindow};
+use crate::{DataBridgeError, Listener, ReplicaConfig, ReplicaStatus, Result};
 
 /// Retry configuration for connection establishment.
 #[derive(Debug, Clone)]
@@ -95,9 +99,19 @@ impl Default for PoolConfig {
 }
 
 /// PostgreSQL connection wrapper with connection pooling.
+///
+/// The pool returned by [`pool`](Self::pool) is the primary. Connections
+/// created with [`new_with_replicas`](Self::new_with_replicas) also route
+/// reads to replicas through [`read_pool`](Self::read_pool) and
+/// [`read`](Self::read); use [`session`](Self::session) for a handle whose
+/// reads follow its own writes.
 #[derive(Clone)]
 pub struct Connection {
     pool: PgPool,
+    retry: RetryConfig,
+    replicas: Option<Arc<ReplicaSet>>,
+    /// Writes through this session, if it is one
+    sticky: Option<Arc<StickyWindow>>,
 }
 
 impl std::fmt::Debug for Connection {
@@ -105,6 +119,8 @@ impl std::fmt::Debug for Connection {
         f.debug_struct("Connection")
             .field("size", &self.pool.size())
             .field("num_idle", &self.pool.num_idle())
+            .field("replicas", &self.replicas.as_ref().map_or(0, |set| set.replicas.len()))
+            .field("session", &self.sticky.is_some())
             .finish()
     }
 }
@@ -135,20 +151,8 @@ impl Connection {
 
         info!("Initializing connection pool");
 
-        // Build pool options with configuration
-        let mut pool_options = PgPoolOptions::new()
-            .min_connections(config.min_connections)
-            .max_connections(config.max_connections)
-            .acquire_timeout(Duration::from_secs(config.connect_timeout));
-
-        // Add optional timeouts
-        if let Some(max_lifetime_secs) = config.max_lifetime {
-            pool_options = pool_options.max_lifetime(Duration::from_secs(max_lifetime_secs));
-        }
-
-        if let Some(idle_timeout_secs) = config.idle_timeout {
-            pool_options = pool_options.idle_timeout(Duration::from_secs(idle_timeout_secs));
-        }
+        let pool_options = Self::pool_options(&config);
+        let retry = config.retry.clone();
 
         // Connect with retry logic and statement caching
         let pool = Self::connect_with_retry(
@@ -165,7 +169,84 @@ impl Connection {
             .map_err(|e| DataBridgeError::Connection(format!("Failed to verify connection: {}", e)))?;
 
         info!("Connection pool initialized successfully");
-        Ok(Self { pool })
+        Ok(Self { pool, retry, replicas: None, sticky: None })
+    }
+
+    /// Creates a primary pool plus one pool per read replica.
+    ///
+    /// The primary is connected as in [`new`](Self::new). Replica pools
+    /// connect lazily, so an unreachable replica does not fail startup; it
+    /// stays out of the read rotation until a health check reaches it.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - Primary connection URI
+    /// * `replica_uris` - Replica connection URIs
+    /// * `config` - Primary pool configuration
+    /// * `replica_config` - Replica pool and routing configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the primary connection fails or a replica URI is invalid.
+    #[instrument(skip(uri, replica_uris, config, replica_config), fields(replicas = replica_uris.len()))]
+    pub async fn new_with_replicas(
+        uri: &str,
+        replica_uris: &[&str],
+        config: PoolConfig,
+        replica_config: ReplicaConfig,
+    ) -> Result<Self> {
+        let mut conn = Self::new(uri, config).await?;
+        if replica_uris.is_empty() {
+            return Ok(conn);
+        }
+
+        let pool_options = Self::pool_options(&replica_config.pool);
+        let mut replicas = Vec::with_capacity(replica_uris.len());
+        for replica_uri in replica_uris {
+            let options = Self::connect_options(replica_uri, replica_config.pool.statement_cache_capacity)?;
+            let name = format!(
+                "{}:{}/{}",
+                options.get_host(),
+                options.get_port(),
+                options.get_database().unwrap_or_default()
+            );
+            replicas.push(Replica::new(name, pool_options.clone().connect_lazy_with(options)));
+        }
+
+        let set = Arc::new(ReplicaSet::new(replicas, replica_config));
+        set.check().await;
+        ReplicaSet::spawn_monitor(&set);
+
+        let in_rotation = set.statuses().iter().filter(|status| status.in_rotation).count();
+        info!(replicas = replica_uris.len(), in_rotation, "Read replicas initialized");
+        conn.replicas = Some(set);
+        Ok(conn)
+    }
+
+    /// Builds pool options from a pool configuration.
+    fn pool_options(config: &PoolConfig) -> PgPoolOptions {
+        let mut pool_options = PgPoolOptions::new()
+            .min_connections(config.min_connections)
+            .max_connections(config.max_connections)
+            .acquire_timeout(Duration::from_secs(config.connect_timeout));
+
+        // Add optional timeouts
+        if let Some(max_lifetime_secs) = config.max_lifetime {
+            pool_options = pool_options.max_lifetime(Duration::from_secs(max_lifetime_secs));
+        }
+
+        if let Some(idle_timeout_secs) = config.idle_timeout {
+            pool_options = pool_options.idle_timeout(Duration::from_secs(idle_timeout_secs));
+        }
+
+        pool_options
+    }
+
+    /// Parses a connection URI and configures the statement cache.
+    fn connect_options(uri: &str, statement_cache_capacity: usize) -> Result<PgConnectOptions> {
+        Ok(PgConnectOptions::from_str(uri)
+            .map_err(|e| DataBridgeError::Connection(format!("Invalid connection URI: {}", e)))?
+            .statement_cache_capacity(statement_cache_capacity))
     }
 
     /// Attempts to connect with exponential backoff retry.
@@ -178,9 +259,7 @@ impl Connection {
         let mut last_error = None;
 
         // Parse connection options and configure statement cache
-        let connect_options = PgConnectOptions::from_str(uri)
-            .map_err(|e| DataBridgeError::Connection(format!("Invalid connection URI: {}", e)))?
-            .statement_cache_capacity(statement_cache_capacity);
+        let connect_options = Self::connect_options(uri, statement_cache_capacity)?;
 
         for attempt in 0..=retry_config.max_retries {
             match pool_options.clone().connect_with(connect_options.clone()).await {
@@ -214,14 +293,145 @@ impl Connection {
             .unwrap_or_else(|| DataBridgeError::Connection("Connection failed".to_string())))
     }
 
-    /// Gets a reference to the connection pool.
+    /// Gets a reference to the primary connection pool.
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
-    /// Closes the connection pool.
+    /// Returns a handle that shares the pools but tracks its own writes.
+    ///
+    /// Reads through the session stay on the primary for
+    /// [`ReplicaConfig::sticky_window_ms`] after a write through the same
+    /// session, so it sees what it wrote. Clones of the session share its
+    /// window; other handles are unaffected. Without replicas or with a zero
+    /// window, this is a plain clone.
+    pub fn session(&self) -> Self {
+        let sticky = self
+            .replicas
+            .as_ref()
+            .filter(|set| set.config.sticky_window_ms > 0)
+            .map(|set| Arc::new(StickyWindow::new(set.config.sticky_window_ms)));
+        Self { sticky, ..self.clone() }
+    }
+
+    /// Picks the replica for a read, or `None` to use the primary.
+    fn select_replica(&self) -> Option<&Replica> {
+        if self.sticky.as_ref().is_some_and(|sticky| sticky.is_active()) {
+            return None;
+        }
+        self.replicas.as_ref().and_then(|set| set.select())
+    }
+
+    /// Gets the pool for a read.
+    ///
+    /// Returns a replica in the read rotation, or the primary if there are
+    /// no replicas, none is available, or this session wrote within the
+    /// sticky window. Prefer [`read`](Self::read), which also fails over.
+    pub fn read_pool(&self) -> &PgPool {
+        self.select_replica().map_or(&self.pool, |replica| &replica.pool)
+    }
+
+    /// Gets the primary pool for a write and starts this session's sticky window.
+    pub fn write_pool(&self) -> &PgPool {
+        self.mark_write();
+        &self.pool
+    }
+
+    /// Starts this session's sticky window, routing its reads to the primary
+    /// for `sticky_window_ms` so they observe a write made outside this API.
+    /// Does nothing on a handle that is not a [`session`](Self::session).
+    pub fn mark_write(&self) {
+        if let Some(sticky) = &self.sticky {
+            sticky.mark();
+        }
+    }
+
+    /// Runs a read on the pool chosen by [`read_pool`](Self::read_pool).
+    ///
+    /// If a replica fails with a connection, timeout or transient error, it
+    /// leaves the read rotation and `f` is retried once on the primary.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `f`.
+    pub async fn read<T, F, Fut>(&self, f: F) -> Result<T>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let Some(replica) = self.select_replica() else {
+            return f(self.pool.clone()).await;
+        };
+
+        match f(replica.pool.clone()).await {
+            Err(e) if replica::report_failure(replica, &e) => {
+                warn!(replica = %replica.status().name, error = %e, "Replica read failed, retrying on primary");
+                f(self.pool.clone()).await
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the last known status of each replica.
+    pub fn replica_status(&self) -> Vec<ReplicaStatus> {
+        self.replicas.as_ref().map(|set| set.statuses()).unwrap_or_default()
+    }
+
+    /// Checks every replica's health and lag now and updates the read rotation.
+    pub async fn check_replicas(&self) -> Vec<ReplicaStatus> {
+        match &self.replicas {
+            Some(set) => {
+                set.check().await;
+                set.statuses()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns each replica's status and pool, with the replica pool configuration.
+    pub(crate) fn replica_pools(&self) -> Option<(Vec<(ReplicaStatus, &PgPool)>, &PoolConfig)> {
+        self.replicas.as_ref().map(|set| {
+            let pools = set.replicas.iter().map(|replica| (replica.status(), &replica.pool)).collect();
+            (pools, &set.config.pool)
+        })
+    }
+
+    /// Returns this session's sticky window, for transactions to mark on commit.
+    pub(crate) fn sticky_window(&self) -> Option<Arc<StickyWindow>> {
+        self.sticky.clone()
+    }
+
+    /// Gets the retry configuration the pool was created with.
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry
+    }
+
+    /// Creates a LISTEN/NOTIFY listener on a dedicated connection.
+    ///
+    /// The listener reconnects with this connection's retry configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no connection can be established.
+    pub async fn listener(&self) -> Result<Listener> {
+        Listener::connect(self).await
+    }
+
+    /// Sends a notification on `channel` with `pg_notify`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the channel name is invalid or the query fails.
+    pub async fn notify(&self, channel: &str, payload: &str) -> Result<()> {
+        crate::listener::notify(&self.pool, channel, payload).await
+    }
+
+    /// Closes the connection pool and any replica pools.
     pub async fn close(&self) -> Result<()> {
         self.pool.close().await;
+        if let Some(set) = &self.replicas {
+            set.close().await;
+        }
         Ok(())
     }
 
@@ -490,4 +700,36 @@ mod tests {
         // Should contain the struct name
         assert!(debug_output.contains("PoolConfig"));
     }
+
+    #[tokio::test]
+    async fn test_sticky_window_is_per_session() {
+        let lazy = |uri: &str| PgPoolOptions::new().connect_lazy(uri).unwrap();
+        let set = ReplicaSet::new(
+            vec![Replica::new("replica:5432/db".to_string(), lazy("postgresql://replica/db"))],
+            ReplicaConfig { sticky_window_ms: 50, ..ReplicaConfig::default() },
+        );
+        set.replicas[0].update(Ok(0), 100);
+        let conn = Connection {
+            pool: lazy("postgresql://primary/db"),
+            retry: RetryConfig::default(),
+            replicas: Some(Arc::new(set)),
+            sticky: None,
+        };
+        let on_primary = |handle: &Connection| std::ptr::eq(handle.read_pool(), handle.pool());
+
+        // Only the session that wrote reads from the primary
+        let session = conn.session();
+        let other = conn.session();
+        let _ = session.write_pool();
+        assert!(on_primary(&session));
+        assert!(on_primary(&session.clone()));
+        assert!(!on_primary(&other));
+        assert!(!on_primary(&conn));
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!on_primary(&session));
+
+        // Plain handles do not track writes
+        conn.mark_write();
+        assert!(!on_primary(&conn));
+    }
 }
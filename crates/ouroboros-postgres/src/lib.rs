@@ -41,6 +41,8 @@
 //!   - ACID-compliant transactions with savepoints
 //!   - Nested transaction support via savepoints
 //!   - Automatic rollback on error
+//!   - Row locking (FOR UPDATE, SKIP LOCKED, NOWAIT) and advisory locks
+//!   - LISTEN/NOTIFY subscriptions with automatic reconnection
 //!   - Connection pooling for optimal performance
 //!
 //! - **Schema Management**:
@@ -51,8 +53,10 @@
 //!
 //! - **Performance Optimizations**:
 //!   - Connection pooling with configurable limits
+//!   - Read-replica routing with lag checks and failover
 //!   - Prepared statement caching
 //!   - Bulk insert/update operations
+//!   - COPY-based bulk import and export
 //!   - Parallel query execution
 //!
 //! # Usage Examples
@@ -179,6 +183,18 @@ pub mod row;
 /// Automatic rollback on error ensures data integrity.
 pub mod transaction;
 
+/// Advisory locks, session-level and transaction-level.
+///
+/// Application-defined locks for coordinating work across processes, such as
+/// making sure only one instance runs migrations.
+pub mod advisory;
+
+/// LISTEN/NOTIFY subscriptions.
+///
+/// Listeners on dedicated connections that reconnect automatically and
+/// yield notifications as an async stream.
+pub mod listener;
+
 /// Type conversion utilities for PostgreSQL types.
 ///
 /// Handles conversion between Rust types and PostgreSQL types, including support
@@ -239,6 +255,18 @@ pub mod history_vis;
 /// leverage Rayon for parallel execution across batches.
 pub mod bulk;
 
+/// Bulk import and export with the COPY protocol.
+///
+/// Binary and CSV COPY IN from an async stream of rows, and COPY OUT to a
+/// writer or as a stream of chunks, with progress reporting.
+pub mod copy;
+
+/// Read-replica routing.
+///
+/// Routes reads to healthy, caught-up replicas and writes to the primary,
+/// with a sticky-primary window after writes.
+pub mod replica;
+
 /// Connection pool metrics and monitoring.
 ///
 /// Provides metrics collection, health checks, and export functionality
@@ -252,12 +280,17 @@ pub mod metrics;
 pub mod backref;
 
 pub use connection::{Connection, PoolConfig, RetryConfig};
+pub use replica::{ReplicaConfig, ReplicaStatus};
 pub use query::{
-    QueryBuilder, Operator, OrderDirection, JoinType, JoinCondition,
-    AggregateFunction, HavingCondition, WindowFunction, WindowSpec, WindowExpression
+    QueryBuilder, Filter, Operator, OrderDirection, JoinType, JoinCondition,
+    AggregateFunction, HavingCondition, WindowFunction, WindowSpec, WindowExpression,
+    RowLock, LockWait,
 };
 pub use row::{Row, RelationConfig};
 pub use transaction::{Transaction, IsolationLevel, AccessMode, TransactionOptions};
+pub use advisory::{AdvisoryLock, advisory_key};
+pub use listener::{Listener, Notification};
+pub use copy::{CopyConfig, CopyExecutor, CopyFormat, CopyOutStream, CopyProgress};
 pub use types::{ExtractedValue, row_to_extracted};
 pub use migration::{Migration, MigrationRunner, MigrationStatus};
 pub use schema::{SchemaInspector, CascadeRule, BackRef, ManyToManyConfig};
@@ -293,7 +326,10 @@ pub use history_vis::{
 pub use bulk::{BulkConfig, BulkResult, BulkExecutor};
 
 // Pool metrics re-exports
-pub use metrics::{PoolMetrics, HealthStatus, HealthCheck, LatencyStats, MetricsCollector};
+pub use metrics::{
+    PoolMetrics, HealthStatus, HealthCheck, LatencyStats, MetricsCollector,
+    ClusterMetrics, ClusterHealth, ReplicaMetrics, ReplicaHealth,
+};
 
 // Back-reference re-exports
 pub use backref::{BackRefConfig, BackRefLoader, EagerLoader, EagerRelation};
@@ -0,0 +1,198 @@
+//! LISTEN/NOTIFY support.
+//!
+//! A [`Listener`] subscribes to channels on a dedicated connection and yields
+//! notifications as they arrive, reconnecting with the pool's [`RetryConfig`]
+//! when the connection drops. Notifications sent while it is disconnected are
+//! lost, as PostgreSQL does not queue them for absent listeners.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use futures::StreamExt;
+//!
+//! let mut listener = conn.listener().await?;
+//! listener.listen("cache_invalidation").await?;
+//!
+//! let mut notifications = listener.into_stream();
+//! while let Some(notification) = notifications.next().await {
+//!     let notification = notification?;
+//!     cache.invalidate(notification.payload());
+//! }
+//!
+//! // Elsewhere, delivered when the transaction commits
+//! tx.notify("cache_invalidation", "users:42").await?;
+//! ```
+
+use futures::Stream;
+use sqlx::postgres::{PgListener, PgNotification};
+use sqlx::Postgres;
+use tracing::{debug, warn};
+
+use crate::{Connection, QueryBuilder, Result, RetryConfig};
+
+/// A notification received on a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    channel: String,
+    payload: String,
+    process_id: u32,
+}
+
+impl Notification {
+    /// Returns the channel the notification was sent on.
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Returns the notification payload (empty if none was given).
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+
+    /// Returns the process ID of the notifying backend.
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+}
+
+impl From<PgNotification> for Notification {
+    fn from(notification: PgNotification) -> Self {
+        Self {
+            channel: notification.channel().to_string(),
+            payload: notification.payload().to_string(),
+            process_id: notification.process_id(),
+        }
+    }
+}
+
+/// Subscribes to notification channels on a dedicated connection.
+pub struct Listener {
+    inner: PgListener,
+    retry: RetryConfig,
+}
+
+impl std::fmt::Debug for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Listener").field("retry", &self.retry).finish_non_exhaustive()
+    }
+}
+
+impl Listener {
+    /// Creates a listener on a connection taken from `conn`'s pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no connection can be established.
+    pub async fn connect(conn: &Connection) -> Result<Self> {
+        let inner = PgListener::connect_with(conn.pool()).await?;
+        Ok(Self {
+            inner,
+            retry: conn.retry_config().clone(),
+        })
+    }
+
+    /// Sets the retry configuration used when reconnecting.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Subscribes to a channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the channel name is invalid or LISTEN fails.
+    pub async fn listen(&mut self, channel: &str) -> Result<()> {
+        QueryBuilder::validate_identifier_part(channel)?;
+        self.inner.listen(channel).await?;
+        debug!(channel, "Listening for notifications");
+        Ok(())
+    }
+
+    /// Unsubscribes from a channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the channel name is invalid or UNLISTEN fails.
+    pub async fn unlisten(&mut self, channel: &str) -> Result<()> {
+        QueryBuilder::validate_identifier_part(channel)?;
+        self.inner.unlisten(channel).await?;
+        debug!(channel, "Stopped listening for notifications");
+        Ok(())
+    }
+
+    /// Unsubscribes from all channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if UNLISTEN fails.
+    pub async fn unlisten_all(&mut self) -> Result<()> {
+        self.inner.unlisten_all().await?;
+        Ok(())
+    }
+
+    /// Waits for the next notification on any subscribed channel.
+    ///
+    /// A lost connection is re-established and every channel subscribed
+    /// again; failed attempts back off per the retry configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns error once reconnecting has failed `max_retries` times in a
+    /// row, or if the pool is closed.
+    pub async fn recv(&mut self) -> Result<Notification> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.try_recv().await {
+                Ok(Some(notification)) => return Ok(notification.into()),
+                Ok(None) => {
+                    warn!("Listener connection lost and re-established, notifications may have been missed");
+                    attempt = 0;
+                }
+                Err(sqlx::Error::PoolClosed) => return Err(sqlx::Error::PoolClosed.into()),
+                Err(e) if attempt < self.retry.max_retries => {
+                    let delay = self.retry.delay_for_attempt(attempt);
+                    warn!(
+                        attempt,
+                        max_retries = self.retry.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %e,
+                        "Listener connection failed, retrying after delay"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Converts the listener into a stream of notifications.
+    ///
+    /// The stream ends after yielding the first error from [`recv`](Self::recv).
+    pub fn into_stream(self) -> impl Stream<Item = Result<Notification>> + Send + Unpin {
+        Box::pin(futures::stream::unfold(Some(self), |listener| async move {
+            let mut listener = listener?;
+            match listener.recv().await {
+                Ok(notification) => Some((Ok(notification), Some(listener))),
+                Err(e) => Some((Err(e), None)),
+            }
+        }))
+    }
+}
+
+/// Sends a notification with `pg_notify` on `executor`.
+///
+/// Inside a transaction the notification is delivered on commit.
+pub(crate) async fn notify<'e, E>(executor: E, channel: &str, payload: &str) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    QueryBuilder::validate_identifier_part(channel)?;
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(channel)
+        .bind(payload)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
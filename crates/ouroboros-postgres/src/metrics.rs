@@ -19,8 +19,9 @@
 //! println!("{}", metrics.to_prometheus("myapp"));
 //! ```
 
-use crate::{Connection, PoolConfig, Result};
+use crate::{Connection, PoolConfig, ReplicaStatus, Result};
 use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
 use std::time::{Duration, Instant};
 use tracing::instrument;
 
@@ -51,7 +52,11 @@ impl PoolMetrics {
 
     /// Collect current metrics from a connection pool with config.
     pub fn from_connection_with_config(conn: &Connection, config: &PoolConfig) -> Self {
-        let pool = conn.pool();
+        Self::from_pool(conn.pool(), config)
+    }
+
+    /// Collect current metrics from a single pool.
+    fn from_pool(pool: &PgPool, config: &PoolConfig) -> Self {
         let pool_size = pool.size();
         let num_idle = pool.num_idle() as u32;
         let num_active = pool_size.saturating_sub(num_idle);
@@ -94,80 +99,25 @@ impl PoolMetrics {
     /// * `prefix` - Metric name prefix (e.g., "myapp" produces "myapp_pool_size")
     pub fn to_prometheus(&self, prefix: &str) -> String {
         let mut output = String::new();
-
-        // Pool size
-        output.push_str(&format!(
-            "# HELP {}_pool_size Current number of connections in the pool\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "# TYPE {}_pool_size gauge\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "{}_pool_size {}\n",
-            prefix, self.pool_size
-        ));
-
-        // Idle connections
-        output.push_str(&format!(
-            "# HELP {}_pool_idle Number of idle connections\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "# TYPE {}_pool_idle gauge\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "{}_pool_idle {}\n",
-            prefix, self.num_idle
-        ));
-
-        // Active connections
-        output.push_str(&format!(
-            "# HELP {}_pool_active Number of active connections\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "# TYPE {}_pool_active gauge\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "{}_pool_active {}\n",
-            prefix, self.num_active
-        ));
-
-        // Max connections
-        output.push_str(&format!(
-            "# HELP {}_pool_max Maximum allowed connections\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "# TYPE {}_pool_max gauge\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "{}_pool_max {}\n",
-            prefix, self.max_connections
-        ));
-
-        // Utilization
-        output.push_str(&format!(
-            "# HELP {}_pool_utilization Pool utilization ratio (0-1)\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "# TYPE {}_pool_utilization gauge\n",
-            prefix
-        ));
-        output.push_str(&format!(
-            "{}_pool_utilization {:.4}\n",
-            prefix, self.utilization
-        ));
-
+        for (name, help, value) in self.gauges() {
+            output.push_str(&format!("# HELP {}_{} {}\n", prefix, name, help));
+            output.push_str(&format!("# TYPE {}_{} gauge\n", prefix, name));
+            output.push_str(&format!("{}_{} {}\n", prefix, name, value));
+        }
         output
     }
 
+    /// Gauge names, help texts and formatted values for Prometheus export.
+    fn gauges(&self) -> [(&'static str, &'static str, String); 5] {
+        [
+            ("pool_size", "Current number of connections in the pool", self.pool_size.to_string()),
+            ("pool_idle", "Number of idle connections", self.num_idle.to_string()),
+            ("pool_active", "Number of active connections", self.num_active.to_string()),
+            ("pool_max", "Maximum allowed connections", self.max_connections.to_string()),
+            ("pool_utilization", "Pool utilization ratio (0-1)", format!("{:.4}", self.utilization)),
+        ]
+    }
+
     /// Export metrics as JSON string.
     pub fn to_json(&self) -> Result<String> {
         serde_json::to_string_pretty(self)
@@ -257,6 +207,158 @@ impl HealthCheck {
     }
 }
 
+/// Pool metrics and routing state of a read replica.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaMetrics {
+    /// Last known replica status, including lag and rotation.
+    pub status: ReplicaStatus,
+    /// Replica pool metrics snapshot.
+    pub metrics: PoolMetrics,
+}
+
+/// Metrics for the primary pool and every replica pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterMetrics {
+    /// Primary pool metrics.
+    pub primary: PoolMetrics,
+    /// Replica pool metrics, empty without replicas.
+    pub replicas: Vec<ReplicaMetrics>,
+}
+
+impl ClusterMetrics {
+    /// Collect current metrics from every pool of a connection.
+    ///
+    /// Replica metrics use the replica pool configuration.
+    pub fn from_connection_with_config(conn: &Connection, config: &PoolConfig) -> Self {
+        let replicas = conn.replica_pools()
+            .map(|(pools, replica_config)| {
+                pools.into_iter()
+                    .map(|(status, pool)| ReplicaMetrics {
+                        status,
+                        metrics: PoolMetrics::from_pool(pool, replica_config),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            primary: PoolMetrics::from_connection_with_config(conn, config),
+            replicas,
+        }
+    }
+
+    /// Export metrics in Prometheus text format, labelled by pool.
+    ///
+    /// Pool gauges carry a `pool` label of `primary` or the replica name;
+    /// replicas also export `replica_lag_ms` and `replica_in_rotation`.
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut pools = vec![("primary".to_string(), self.primary.gauges())];
+        pools.extend(self.replicas.iter().map(|r| (r.status.name.clone(), r.metrics.gauges())));
+
+        let mut output = String::new();
+        for (i, (name, help, _)) in self.primary.gauges().iter().enumerate() {
+            output.push_str(&format!("# HELP {}_{} {}\n", prefix, name, help));
+            output.push_str(&format!("# TYPE {}_{} gauge\n", prefix, name));
+            for (pool, gauges) in &pools {
+                output.push_str(&format!("{}_{}{{pool=\"{}\"}} {}\n", prefix, name, pool, gauges[i].2));
+            }
+        }
+
+        if !self.replicas.is_empty() {
+            output.push_str(&format!("# HELP {}_replica_lag_ms Replica replay lag in milliseconds\n", prefix));
+            output.push_str(&format!("# TYPE {}_replica_lag_ms gauge\n", prefix));
+            for replica in self.replicas.iter().filter(|r| r.status.lag_ms.is_some()) {
+                output.push_str(&format!(
+                    "{}_replica_lag_ms{{pool=\"{}\"}} {}\n",
+                    prefix, replica.status.name, replica.status.lag_ms.unwrap_or_default()
+                ));
+            }
+
+            output.push_str(&format!(
+                "# HELP {}_replica_in_rotation Whether reads are routed to the replica (0 or 1)\n",
+                prefix
+            ));
+            output.push_str(&format!("# TYPE {}_replica_in_rotation gauge\n", prefix));
+            for replica in &self.replicas {
+                output.push_str(&format!(
+                    "{}_replica_in_rotation{{pool=\"{}\"}} {}\n",
+                    prefix, replica.status.name, replica.status.in_rotation as u8
+                ));
+            }
+        }
+
+        output
+    }
+}
+
+/// Health of a read replica.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaHealth {
+    /// Replica status after the check, including lag and rotation.
+    pub status: ReplicaStatus,
+    /// Pool health; `is_connected` reflects the replica check.
+    pub health: HealthStatus,
+}
+
+/// Health of the primary and every replica.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterHealth {
+    /// Primary pool health.
+    pub primary: HealthStatus,
+    /// Replica health, empty without replicas.
+    pub replicas: Vec<ReplicaHealth>,
+}
+
+impl ClusterHealth {
+    /// Check if the primary and every replica are healthy and in rotation.
+    pub fn all_ok(&self) -> bool {
+        self.primary.all_ok()
+            && self.replicas.iter().all(|r| r.health.all_ok() && r.status.in_rotation)
+    }
+
+    /// Number of replicas currently serving reads.
+    pub fn replicas_in_rotation(&self) -> usize {
+        self.replicas.iter().filter(|r| r.status.in_rotation).count()
+    }
+}
+
+impl HealthCheck {
+    /// Check the primary and every replica.
+    ///
+    /// Replica checks also measure lag and update the read rotation.
+    #[instrument(skip(conn, config))]
+    pub async fn check_cluster(conn: &Connection, config: &PoolConfig) -> Result<ClusterHealth> {
+        let primary = Self::check_with_config(conn, config).await?;
+
+        let start = Instant::now();
+        let statuses = conn.check_replicas().await;
+        let check_latency_ms = start.elapsed().as_millis() as u64;
+
+        let replicas = ClusterMetrics::from_connection_with_config(conn, config)
+            .replicas
+            .into_iter()
+            .zip(statuses)
+            .map(|(replica, status)| {
+                let is_saturated = replica.metrics.is_saturated();
+                ReplicaHealth {
+                    health: HealthStatus {
+                        is_healthy: status.is_healthy && !is_saturated,
+                        is_connected: status.is_healthy,
+                        is_near_saturation: replica.metrics.is_near_saturation(),
+                        is_saturated,
+                        check_latency_ms,
+                        error: status.error.clone(),
+                        metrics: replica.metrics,
+                    },
+                    status,
+                }
+            })
+            .collect();
+
+        Ok(ClusterHealth { primary, replicas })
+    }
+}
+
 /// Latency statistics for connection operations.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LatencyStats {
@@ -284,11 +386,7 @@ impl LatencyStats {
 
     /// Calculate average latency.
     pub fn avg_us(&self) -> u64 {
-        if self.count == 0 {
-            0
-        } else {
-            self.sum_us / self.count
-        }
+        self.sum_us.checked_div(self.count).unwrap_or(0)
     }
 
     /// Record a latency sample.
@@ -588,6 +686,40 @@ mod tests {
         assert!(prom.contains("test_pool_utilization 0.2000"));
     }
 
+    #[test]
+    fn test_cluster_metrics_prometheus_format() {
+        let metrics = PoolMetrics {
+            pool_size: 5,
+            num_idle: 3,
+            num_active: 2,
+            max_connections: 10,
+            min_connections: 1,
+            utilization: 0.2,
+            timestamp: 0,
+        };
+        let cluster = ClusterMetrics {
+            primary: metrics.clone(),
+            replicas: vec![ReplicaMetrics {
+                status: ReplicaStatus {
+                    name: "replica1:5432/app".to_string(),
+                    is_healthy: true,
+                    lag_ms: Some(120),
+                    in_rotation: true,
+                    error: None,
+                    last_checked: Some(0),
+                },
+                metrics,
+            }],
+        };
+
+        let prom = cluster.to_prometheus("test");
+        assert_eq!(prom.matches("# TYPE test_pool_size gauge").count(), 1);
+        assert!(prom.contains("test_pool_size{pool=\"primary\"} 5"));
+        assert!(prom.contains("test_pool_size{pool=\"replica1:5432/app\"} 5"));
+        assert!(prom.contains("test_replica_lag_ms{pool=\"replica1:5432/app\"} 120"));
+        assert!(prom.contains("test_replica_in_rotation{pool=\"replica1:5432/app\"} 1"));
+    }
+
     #[test]
     fn test_pool_metrics_json_export() {
         let metrics = PoolMetrics {
@@ -3,7 +3,7 @@
 //! This module provides migration support for schema evolution,
 //! similar to Alembic/SQLAlchemy migrations but in Rust.
 
-use crate::{Connection, Result, DataBridgeError};
+use crate::{AdvisoryLock, Connection, Result, DataBridgeError, advisory_key};
 use chrono::{DateTime, Utc};
 use std::path::Path;
 use std::fs;
@@ -505,8 +505,26 @@ impl MigrationRunner {
         Ok(())
     }
 
+    /// Takes the advisory lock that keeps concurrent runners for the same
+    /// tracking table from migrating or rolling back at the same time.
+    async fn lock(&self) -> Result<AdvisoryLock> {
+        let key = advisory_key(&format!("ouroboros:migrations:{}", self.migrations_table));
+        AdvisoryLock::acquire(&self.conn, key).await
+    }
+
     /// Applies all pending migrations sequentially, returning applied versions.
+    ///
+    /// Holds an advisory lock throughout, so when several instances migrate
+    /// at once, one applies the migrations and the others wait, then find
+    /// nothing pending.
     pub async fn migrate(&self, migrations: &[Migration]) -> Result<Vec<String>> {
+        let lock = self.lock().await?;
+        let result = self.migrate_locked(migrations).await;
+        lock.release().await?;
+        result
+    }
+
+    async fn migrate_locked(&self, migrations: &[Migration]) -> Result<Vec<String>> {
         let pending = self.pending_migrations(migrations).await?;
 
         if pending.is_empty() {
@@ -526,7 +544,16 @@ impl MigrationRunner {
     }
 
     /// Reverts the last N applied migrations in reverse order.
+    ///
+    /// Holds the same advisory lock as [`migrate`](Self::migrate).
     pub async fn rollback(&self, migrations: &[Migration], count: usize) -> Result<Vec<String>> {
+        let lock = self.lock().await?;
+        let result = self.rollback_locked(migrations, count).await;
+        lock.release().await?;
+        result
+    }
+
+    async fn rollback_locked(&self, migrations: &[Migration], count: usize) -> Result<Vec<String>> {
         let applied = self.applied_migrations().await?;
 
         if applied.is_empty() {
@@ -3,10 +3,11 @@
 use crate::{ExtractedValue, Result};
 use super::types::{
     CommonTableExpression, Subquery, Operator, AggregateFunction, HavingCondition,
-    OrderDirection, SetQuery,
+    OrderDirection, SetQuery, RowLock, LockWait,
 };
 use super::join::JoinClause;
 use super::window::WindowExpression;
+use super::filter::Filter;
 use super::helpers::{validate_identifier, validate_identifier_part};
 
 /// Represents a WHERE condition.
@@ -31,6 +32,8 @@ pub struct QueryBuilder {
     pub(crate) joins: Vec<JoinClause>,
     /// WHERE conditions (field, operator, value)
     pub(crate) where_conditions: Vec<WhereCondition>,
+    /// WHERE filter groups, ANDed with the conditions
+    pub(crate) where_filters: Vec<Filter>,
     /// ORDER BY clauses (field, direction)
     pub(crate) order_by_clauses: Vec<(String, OrderDirection)>,
     /// LIMIT clause
@@ -61,6 +64,10 @@ pub struct QueryBuilder {
     pub(crate) only_columns: Option<Vec<String>>,
     /// If true, select_columns are raw SQL expressions (no quoting applied)
     pub(crate) raw_select: bool,
+    /// Row-locking clause (FOR UPDATE, FOR SHARE, ...)
+    pub(crate) row_lock: Option<RowLock>,
+    /// What to do when a row to lock is already locked
+    pub(crate) lock_wait: LockWait,
 }
 
 impl QueryBuilder {
@@ -80,6 +87,7 @@ impl QueryBuilder {
             select_columns: Vec::new(),
             joins: Vec::new(),
             where_conditions: Vec::new(),
+            where_filters: Vec::new(),
             order_by_clauses: Vec::new(),
             limit_value: None,
             offset_value: None,
@@ -95,6 +103,8 @@ impl QueryBuilder {
             deferred_columns: Vec::new(),
             only_columns: None,
             raw_select: false,
+            row_lock: None,
+            lock_wait: LockWait::Wait,
         })
     }
 
@@ -122,6 +132,28 @@ impl QueryBuilder {
         &self.table
     }
 
+    /// Renders the WHERE conditions and filters ANDed together, or `None`
+    /// if there are none.
+    ///
+    /// `condition` renders a single condition for the query being built.
+    pub(crate) fn build_where<F>(&self, params: &mut Vec<ExtractedValue>, condition: F) -> Option<String>
+    where
+        F: Fn(&WhereCondition, &mut Vec<ExtractedValue>) -> String,
+    {
+        if self.where_conditions.is_empty() && self.where_filters.is_empty() {
+            return None;
+        }
+
+        let mut where_parts: Vec<String> = Vec::new();
+        for cond in &self.where_conditions {
+            where_parts.push(condition(cond, params));
+        }
+        for filter in &self.where_filters {
+            where_parts.push(filter.to_sql(params, &condition));
+        }
+        Some(where_parts.join(" AND "))
+    }
+
     /// Builds a query and returns (SQL, parameters) tuple.
     ///
     /// This is a convenience method for SELECT queries.
@@ -0,0 +1,146 @@
+//! Composable boolean filter groups for WHERE clauses.
+
+use crate::{DataBridgeError, ExtractedValue, Result};
+use super::builder::WhereCondition;
+use super::helpers::validate_identifier;
+use super::types::Operator;
+
+/// A WHERE filter: a single condition, or an AND/OR/NOT group of filters.
+///
+/// Groups nest to any depth. Field names are validated when the filter is
+/// built and values are bound as parameters, as with `where_clause`.
+///
+/// # Example
+///
+/// ```ignore
+/// // ("status" = $1 OR "priority" > $2) AND NOT ("archived" = $3)
+/// let filter = Filter::and(vec![
+///     Filter::or(vec![
+///         Filter::condition("status", Operator::Eq, ExtractedValue::String("a".into()))?,
+///         Filter::condition("priority", Operator::Gt, ExtractedValue::Int(3))?,
+///     ]),
+///     !Filter::condition("archived", Operator::Eq, ExtractedValue::Bool(true))?,
+/// ]);
+/// let qb = QueryBuilder::new("tasks")?.where_filter(filter);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Filter(FilterNode);
+
+#[derive(Debug, Clone)]
+enum FilterNode {
+    Condition(WhereCondition),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// A single `field operator value` condition.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the field name is invalid, or for subquery
+    /// operators, which need a subquery rather than a value.
+    pub fn condition(field: &str, operator: Operator, value: ExtractedValue) -> Result<Self> {
+        validate_identifier(field)?;
+
+        let value = match operator {
+            Operator::IsNull | Operator::IsNotNull => None,
+            Operator::InSubquery | Operator::NotInSubquery | Operator::Exists | Operator::NotExists => {
+                return Err(DataBridgeError::Query(format!(
+                    "Operator {:?} needs a subquery and cannot be used in a filter",
+                    operator
+                )));
+            }
+            _ => Some(value),
+        };
+
+        Ok(Self(FilterNode::Condition(WhereCondition {
+            field: field.to_string(),
+            operator,
+            value,
+            subquery: None,
+        })))
+    }
+
+    /// `field IS NULL`
+    pub fn is_null(field: &str) -> Result<Self> {
+        Self::condition(field, Operator::IsNull, ExtractedValue::Null)
+    }
+
+    /// `field IS NOT NULL`
+    pub fn is_not_null(field: &str) -> Result<Self> {
+        Self::condition(field, Operator::IsNotNull, ExtractedValue::Null)
+    }
+
+    /// Matches when every filter matches (always, when empty).
+    pub fn and(filters: Vec<Filter>) -> Self {
+        Self(FilterNode::And(filters))
+    }
+
+    /// Matches when any filter matches (never, when empty).
+    pub fn or(filters: Vec<Filter>) -> Self {
+        Self(FilterNode::Or(filters))
+    }
+
+    /// Matches when `filter` does not.
+    pub fn negate(filter: Filter) -> Self {
+        Self(FilterNode::Not(Box::new(filter)))
+    }
+
+    /// Renders the filter, binding its values to `params`.
+    ///
+    /// `condition` renders a single condition the way the calling query
+    /// does. Groups are parenthesized, so the result can be ANDed with
+    /// other conditions as is.
+    pub(crate) fn to_sql<F>(&self, params: &mut Vec<ExtractedValue>, condition: &F) -> String
+    where
+        F: Fn(&WhereCondition, &mut Vec<ExtractedValue>) -> String,
+    {
+        let sql = self.render(params, condition);
+        if self.is_compound() {
+            format!("({})", sql)
+        } else {
+            sql
+        }
+    }
+
+    /// Whether the filter renders as several terms joined by AND or OR
+    fn is_compound(&self) -> bool {
+        match &self.0 {
+            FilterNode::And(filters) | FilterNode::Or(filters) => match filters.as_slice() {
+                [filter] => filter.is_compound(),
+                filters => filters.len() > 1,
+            },
+            FilterNode::Condition(_) | FilterNode::Not(_) => false,
+        }
+    }
+
+    fn render<F>(&self, params: &mut Vec<ExtractedValue>, condition: &F) -> String
+    where
+        F: Fn(&WhereCondition, &mut Vec<ExtractedValue>) -> String,
+    {
+        let (filters, separator, empty) = match &self.0 {
+            FilterNode::Condition(cond) => return condition(cond, params),
+            FilterNode::Not(filter) => return format!("NOT ({})", filter.render(params, condition)),
+            FilterNode::And(filters) => (filters, " AND ", "TRUE"),
+            FilterNode::Or(filters) => (filters, " OR ", "FALSE"),
+        };
+        match filters.as_slice() {
+            [] => empty.to_string(),
+            [filter] => filter.render(params, condition),
+            _ => {
+                let parts: Vec<String> = filters.iter().map(|f| f.to_sql(params, condition)).collect();
+                parts.join(separator)
+            }
+        }
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::negate(self)
+    }
+}
@@ -22,6 +22,41 @@
 //! // Result: "SELECT id, name FROM users WHERE age >= $1 AND active = $2 ORDER BY name ASC LIMIT $3 OFFSET $4"
 //! ```
 //!
+//! ## Filter Groups
+//!
+//! ```ignore
+//! use ouroboros_postgres::{QueryBuilder, Filter, Operator, ExtractedValue};
+//!
+//! let qb = QueryBuilder::new("tasks")?
+//!     .where_filter(Filter::and(vec![
+//!         Filter::or(vec![
+//!             Filter::condition("status", Operator::Eq, ExtractedValue::String("a".to_string()))?,
+//!             Filter::condition("priority", Operator::Gt, ExtractedValue::Int(3))?,
+//!         ]),
+//!         !Filter::condition("archived", Operator::Eq, ExtractedValue::Bool(true))?,
+//!     ]));
+//!
+//! let (sql, params) = qb.build();
+//! // Result: "SELECT * FROM \"tasks\" WHERE ((\"status\" = $1 OR \"priority\" > $2) AND NOT (\"archived\" = $3))"
+//! ```
+//!
+//! ## Row Locking
+//!
+//! ```ignore
+//! use ouroboros_postgres::{QueryBuilder, Operator, OrderDirection, ExtractedValue};
+//!
+//! // Claim queued jobs no other worker holds; run inside a Transaction
+//! let qb = QueryBuilder::new("jobs")?
+//!     .where_clause("status", Operator::Eq, ExtractedValue::String("queued".to_string()))?
+//!     .order_by("id", OrderDirection::Asc)?
+//!     .limit(10)
+//!     .for_update()
+//!     .skip_locked();
+//!
+//! let (sql, params) = qb.build();
+//! // Result: "SELECT * FROM \"jobs\" WHERE \"status\" = $1 ORDER BY \"id\" ASC LIMIT $2 FOR UPDATE SKIP LOCKED"
+//! ```
+//!
 //! ## INSERT Query
 //!
 //! ```ignore
@@ -63,6 +98,7 @@
 //! ```
 
 mod types;
+mod filter;
 mod join;
 mod window;
 mod helpers;
@@ -76,8 +112,9 @@ mod tests;
 // Re-export all public types
 pub use types::{
     CommonTableExpression, Subquery, Operator, AggregateFunction, HavingCondition,
-    OrderDirection, JoinType, SetOperation, SetQuery,
+    OrderDirection, JoinType, SetOperation, SetQuery, RowLock, LockWait,
 };
+pub use filter::Filter;
 pub use join::{JoinCondition, JoinClause};
 pub use window::{WindowFunction, WindowSpec, WindowExpression};
 pub use builder::QueryBuilder;
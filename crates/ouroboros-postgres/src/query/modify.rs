@@ -80,16 +80,9 @@ impl QueryBuilder {
         sql.push_str(&set_parts.join(", "));
 
         // WHERE clause
-        if !self.where_conditions.is_empty() {
+        if let Some(where_sql) = self.build_where(&mut params, |cond, params| self.build_modify_where_condition(cond, params)) {
             sql.push_str(" WHERE ");
-            let mut where_parts: Vec<String> = Vec::new();
-
-            for cond in &self.where_conditions {
-                let part = self.build_modify_where_condition(cond, &mut params);
-                where_parts.push(part);
-            }
-
-            sql.push_str(&where_parts.join(" AND "));
+            sql.push_str(&where_sql);
         }
 
         // RETURNING clause
@@ -190,16 +183,9 @@ impl QueryBuilder {
         let mut params: Vec<ExtractedValue> = Vec::new();
 
         // WHERE clause
-        if !self.where_conditions.is_empty() {
+        if let Some(where_sql) = self.build_where(&mut params, |cond, params| self.build_modify_where_condition(cond, params)) {
             sql.push_str(" WHERE ");
-            let mut where_parts: Vec<String> = Vec::new();
-
-            for cond in &self.where_conditions {
-                let part = self.build_modify_where_condition(cond, &mut params);
-                where_parts.push(part);
-            }
-
-            sql.push_str(&where_parts.join(" AND "));
+            sql.push_str(&where_sql);
         }
 
         // RETURNING clause
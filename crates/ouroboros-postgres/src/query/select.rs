@@ -4,10 +4,11 @@ use crate::{ExtractedValue, Result};
 use super::builder::{QueryBuilder, WhereCondition};
 use super::types::{
     CommonTableExpression, Subquery, Operator, AggregateFunction, HavingCondition,
-    OrderDirection, JoinType, SetOperation, SetQuery,
+    OrderDirection, JoinType, SetOperation, SetQuery, RowLock, LockWait,
 };
 use super::join::{JoinClause, JoinCondition};
 use super::window::{WindowFunction, WindowSpec, WindowExpression};
+use super::filter::Filter;
 use super::helpers::{quote_identifier, build_aggregate_sql, build_window_sql, adjust_param_indices};
 
 impl QueryBuilder {
@@ -134,6 +135,13 @@ impl QueryBuilder {
         Ok(self)
     }
 
+    /// Adds a WHERE filter group (AND/OR/NOT of conditions), ANDed with the
+    /// other conditions.
+    pub fn where_filter(mut self, filter: Filter) -> Self {
+        self.where_filters.push(filter);
+        self
+    }
+
     /// Adds a WHERE condition for IS NULL.
     pub fn where_null(self, field: &str) -> Result<Self> {
         self.where_clause(field, Operator::IsNull, ExtractedValue::Null)
@@ -433,6 +441,56 @@ impl QueryBuilder {
         self
     }
 
+    /// Locks the selected rows with FOR UPDATE.
+    ///
+    /// Row locks are held until the end of the transaction, so run the query
+    /// inside a `Transaction`; outside one it commits (and unlocks) at once.
+    pub fn for_update(mut self) -> Self {
+        self.row_lock = Some(RowLock::Update);
+        self
+    }
+
+    /// Locks the selected rows with FOR NO KEY UPDATE.
+    pub fn for_no_key_update(mut self) -> Self {
+        self.row_lock = Some(RowLock::NoKeyUpdate);
+        self
+    }
+
+    /// Locks the selected rows with FOR SHARE.
+    pub fn for_share(mut self) -> Self {
+        self.row_lock = Some(RowLock::Share);
+        self
+    }
+
+    /// Locks the selected rows with FOR KEY SHARE.
+    pub fn for_key_share(mut self) -> Self {
+        self.row_lock = Some(RowLock::KeyShare);
+        self
+    }
+
+    /// Skips rows another transaction has locked (SKIP LOCKED).
+    ///
+    /// Implies FOR UPDATE unless another lock strength is set.
+    pub fn skip_locked(mut self) -> Self {
+        self.lock_wait = LockWait::SkipLocked;
+        self
+    }
+
+    /// Fails instead of waiting for rows another transaction has locked (NOWAIT).
+    ///
+    /// Implies FOR UPDATE unless another lock strength is set.
+    pub fn nowait(mut self) -> Self {
+        self.lock_wait = LockWait::NoWait;
+        self
+    }
+
+    /// Clear row-locking settings.
+    pub fn clear_lock(mut self) -> Self {
+        self.row_lock = None;
+        self.lock_wait = LockWait::Wait;
+        self
+    }
+
     /// Add a Common Table Expression (CTE) to the query
     pub fn with_cte(mut self, name: &str, query: QueryBuilder) -> Result<Self> {
         Self::validate_identifier(name)?;
@@ -842,16 +900,9 @@ impl QueryBuilder {
         }
 
         // WHERE clause
-        if !self.where_conditions.is_empty() {
+        if let Some(where_sql) = self.build_where(&mut params, |cond, params| self.build_where_condition(cond, params)) {
             sql.push_str(" WHERE ");
-            let mut where_parts: Vec<String> = Vec::new();
-
-            for cond in &self.where_conditions {
-                let part = self.build_where_condition(cond, &mut params);
-                where_parts.push(part);
-            }
-
-            sql.push_str(&where_parts.join(" AND "));
+            sql.push_str(&where_sql);
         }
 
         // GROUP BY clause
@@ -898,6 +949,18 @@ impl QueryBuilder {
             sql.push_str(&format!(" OFFSET ${}", params.len()));
         }
 
+        // Locking clause
+        let row_lock = match (self.row_lock, self.lock_wait) {
+            (Some(lock), wait) => Some((lock, wait)),
+            (None, LockWait::Wait) => None,
+            (None, wait) => Some((RowLock::Update, wait)),
+        };
+        if let Some((lock, wait)) = row_lock {
+            sql.push(' ');
+            sql.push_str(lock.to_sql());
+            sql.push_str(wait.to_sql());
+        }
+
         // Set operations
         for set_op in &self.set_operations {
             sql.push_str(set_op.operation.to_sql());
@@ -2177,3 +2177,139 @@ fn test_delete_without_returning() {
     let (sql, _) = qb.build_delete();
     assert!(!sql.contains("RETURNING"));
 }
+
+// ========================================
+// Filter group tests
+// ========================================
+
+fn status_or_priority() -> Filter {
+    Filter::or(vec![
+        Filter::condition("status", Operator::Eq, ExtractedValue::String("a".to_string())).unwrap(),
+        Filter::condition("priority", Operator::Gt, ExtractedValue::Int(3)).unwrap(),
+    ])
+}
+
+#[test]
+fn test_where_filter_groups() {
+    let qb = QueryBuilder::new("tasks").unwrap()
+        .where_filter(Filter::and(vec![
+            status_or_priority(),
+            !Filter::condition("archived", Operator::Eq, ExtractedValue::Bool(true)).unwrap(),
+        ]));
+    let (sql, params) = qb.build_select();
+    assert_eq!(
+        sql,
+        "SELECT * FROM \"tasks\" WHERE ((\"status\" = $1 OR \"priority\" > $2) AND NOT (\"archived\" = $3))"
+    );
+    assert_eq!(params.len(), 3);
+}
+
+#[test]
+fn test_where_filter_with_conditions() {
+    let qb = QueryBuilder::new("tasks").unwrap()
+        .where_clause("owner", Operator::Eq, ExtractedValue::Int(7)).unwrap()
+        .where_filter(status_or_priority())
+        .limit(5);
+    let (sql, params) = qb.build_select();
+    assert_eq!(
+        sql,
+        "SELECT * FROM \"tasks\" WHERE \"owner\" = $1 AND (\"status\" = $2 OR \"priority\" > $3) LIMIT $4"
+    );
+    assert_eq!(params[0], ExtractedValue::Int(7));
+    assert_eq!(params[2], ExtractedValue::Int(3));
+}
+
+#[test]
+fn test_where_filter_nesting() {
+    // Single-member and empty groups collapse; negated groups keep their parentheses
+    let filter = Filter::or(vec![
+        Filter::and(vec![Filter::is_null("deleted_at").unwrap()]),
+        Filter::negate(Filter::and(vec![
+            Filter::or(vec![status_or_priority()]),
+            Filter::is_not_null("owner").unwrap(),
+        ])),
+        Filter::or(vec![]),
+    ]);
+    let (sql, params) = QueryBuilder::new("tasks").unwrap().where_filter(filter).build_select();
+    assert_eq!(
+        sql,
+        "SELECT * FROM \"tasks\" WHERE (\"deleted_at\" IS NULL OR NOT ((\"status\" = $1 OR \"priority\" > $2) AND \"owner\" IS NOT NULL) OR FALSE)"
+    );
+    assert_eq!(params.len(), 2);
+}
+
+#[test]
+fn test_where_filter_update_delete() {
+    let qb = QueryBuilder::new("tasks").unwrap()
+        .where_filter(status_or_priority())
+        .returning(&["id"]).unwrap();
+
+    let (sql, params) = qb.build_update(&[("archived".to_string(), ExtractedValue::Bool(true))]).unwrap();
+    assert_eq!(
+        sql,
+        "UPDATE \"tasks\" SET \"archived\" = $1 WHERE (\"status\" = $2 OR \"priority\" > $3) RETURNING \"id\""
+    );
+    assert_eq!(params.len(), 3);
+
+    let (sql, params) = qb.build_delete();
+    assert_eq!(sql, "DELETE FROM \"tasks\" WHERE (\"status\" = $1 OR \"priority\" > $2) RETURNING \"id\"");
+    assert_eq!(params.len(), 2);
+}
+
+#[test]
+fn test_filter_validation() {
+    assert!(Filter::condition("status; DROP TABLE tasks", Operator::Eq, ExtractedValue::Int(1)).is_err());
+    assert!(Filter::is_null("pg_catalog").is_err());
+    assert!(Filter::condition("id", Operator::InSubquery, ExtractedValue::Null).is_err());
+}
+
+// ========================================
+// Row Locking Tests
+// ========================================
+
+#[test]
+fn test_row_locking() {
+    let qb = QueryBuilder::new("jobs").unwrap()
+        .where_clause("status", Operator::Eq, ExtractedValue::String("queued".to_string())).unwrap()
+        .order_by("id", OrderDirection::Asc).unwrap()
+        .limit(10)
+        .for_update()
+        .skip_locked();
+    let (sql, params) = qb.build_select();
+    assert_eq!(
+        sql,
+        "SELECT * FROM \"jobs\" WHERE \"status\" = $1 ORDER BY \"id\" ASC LIMIT $2 FOR UPDATE SKIP LOCKED"
+    );
+    assert_eq!(params.len(), 2);
+
+    let (sql, _) = QueryBuilder::new("jobs").unwrap().for_share().nowait().build_select();
+    assert_eq!(sql, "SELECT * FROM \"jobs\" FOR SHARE NOWAIT");
+
+    let (sql, _) = QueryBuilder::new("jobs").unwrap().for_no_key_update().build_select();
+    assert_eq!(sql, "SELECT * FROM \"jobs\" FOR NO KEY UPDATE");
+
+    // A wait policy alone implies FOR UPDATE
+    let (sql, _) = QueryBuilder::new("jobs").unwrap().skip_locked().build_select();
+    assert_eq!(sql, "SELECT * FROM \"jobs\" FOR UPDATE SKIP LOCKED");
+
+    let (sql, _) = QueryBuilder::new("jobs").unwrap().for_key_share().clear_lock().build_select();
+    assert_eq!(sql, "SELECT * FROM \"jobs\"");
+}
+
+#[test]
+fn test_row_locking_in_subquery() {
+    // Claim a batch: UPDATE ... WHERE id IN (SELECT id ... FOR UPDATE SKIP LOCKED)
+    let claim = QueryBuilder::new("jobs").unwrap()
+        .select(vec!["id".to_string()]).unwrap()
+        .where_clause("status", Operator::Eq, ExtractedValue::String("queued".to_string())).unwrap()
+        .limit(5)
+        .skip_locked();
+    let qb = QueryBuilder::new("jobs").unwrap()
+        .where_in_subquery("id", claim).unwrap();
+    let (sql, params) = qb.build_update(&[("status".to_string(), ExtractedValue::String("running".to_string()))]).unwrap();
+    assert_eq!(
+        sql,
+        "UPDATE \"jobs\" SET \"status\" = $1 WHERE \"id\" IN (SELECT \"id\" FROM \"jobs\" WHERE \"status\" = $2 LIMIT $3 FOR UPDATE SKIP LOCKED)"
+    );
+    assert_eq!(params.len(), 3);
+}
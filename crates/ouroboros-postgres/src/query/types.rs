@@ -247,3 +247,51 @@ pub struct SetQuery {
     /// Parameters for the other query
     pub params: Vec<ExtractedValue>,
 }
+
+/// Row lock strength for `SELECT ... FOR <strength>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowLock {
+    /// FOR UPDATE - exclusive lock, blocks all other locks on the row
+    Update,
+    /// FOR NO KEY UPDATE - like UPDATE, but allows FOR KEY SHARE (e.g. foreign key checks)
+    NoKeyUpdate,
+    /// FOR SHARE - shared lock, blocks updates and deletes
+    Share,
+    /// FOR KEY SHARE - blocks only deletes and key updates
+    KeyShare,
+}
+
+impl RowLock {
+    /// Returns the SQL locking clause.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            RowLock::Update => "FOR UPDATE",
+            RowLock::NoKeyUpdate => "FOR NO KEY UPDATE",
+            RowLock::Share => "FOR SHARE",
+            RowLock::KeyShare => "FOR KEY SHARE",
+        }
+    }
+}
+
+/// What a row-locking SELECT does when a row is already locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockWait {
+    /// Wait for the lock (PostgreSQL default)
+    #[default]
+    Wait,
+    /// NOWAIT - fail immediately
+    NoWait,
+    /// SKIP LOCKED - leave locked rows out of the result
+    SkipLocked,
+}
+
+impl LockWait {
+    /// Returns the SQL wait policy, empty for the default.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            LockWait::Wait => "",
+            LockWait::NoWait => " NOWAIT",
+            LockWait::SkipLocked => " SKIP LOCKED",
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Read-replica routing.
+//!
+//! A [`Connection`](crate::Connection) created with
+//! [`new_with_replicas`](crate::Connection::new_with_replicas) keeps one pool
+//! per streaming replica next to the primary pool:
+//!
+//! - Reads are spread round-robin over replicas that are reachable, in
+//!   recovery, and whose replay lag is within [`ReplicaConfig::max_lag_ms`].
+//! - Writes and transactions always go to the primary.
+//! - A [`session`](crate::Connection::session) handle keeps its own reads on
+//!   the primary for [`ReplicaConfig::sticky_window_ms`] after its own writes,
+//!   so it sees what it wrote. Other handles are not affected, and handles
+//!   that are not sessions do not track writes.
+//! - A replica that fails a health check or a routed read leaves the rotation
+//!   until a later check passes. With no replica available, reads fall back to
+//!   the primary.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ouroboros_postgres::{Connection, PoolConfig, ReplicaConfig};
+//!
+//! let conn = Connection::new_with_replicas(
+//!     "postgresql://primary/app",
+//!     &["postgresql://replica1/app", "postgresql://replica2/app"],
+//!     PoolConfig::default(),
+//!     ReplicaConfig::default(),
+//! ).await?;
+//!
+//! // Retried on the primary if the replica fails
+//! let users = conn.read(|pool| async move {
+//!     Ok(sqlx::query("SELECT * FROM users").fetch_all(&pool).await?)
+//! }).await?;
+//!
+//! // Reads through `session` follow its own writes to the primary
+//! let session = conn.session();
+//! sqlx::query("UPDATE users SET active = true").execute(session.write_pool()).await?;
+//! let users = sqlx::query("SELECT * FROM users").fetch_all(session.read_pool()).await?;
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use tracing::{debug, info, warn};
+
+use crate::{DataBridgeError, PoolConfig};
+
+/// Configuration for read-replica routing.
+#[derive(Debug, Clone)]
+pub struct ReplicaConfig {
+    /// Pool settings used for each replica.
+    pub pool: PoolConfig,
+    /// Maximum replay lag in milliseconds before a replica leaves the rotation.
+    pub max_lag_ms: u64,
+    /// How long a session's reads stay on the primary after its own write,
+    /// in milliseconds (0 = never).
+    pub sticky_window_ms: u64,
+    /// Interval between background health and lag checks in milliseconds (0 = disabled).
+    pub health_check_interval_ms: u64,
+}
+
+impl Default for ReplicaConfig {
+    fn default() -> Self {
+        Self {
+            pool: PoolConfig::default(),
+            max_lag_ms: 5000,
+            sticky_window_ms: 2000,
+            health_check_interval_ms: 5000,
+        }
+    }
+}
+
+/// Last known state of a replica.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicaStatus {
+    /// Replica address (host:port/database).
+    pub name: String,
+    /// Whether the replica answered its last check.
+    pub is_healthy: bool,
+    /// Replay lag in milliseconds, if known.
+    pub lag_ms: Option<u64>,
+    /// Whether reads are currently routed to the replica.
+    pub in_rotation: bool,
+    /// Error from the last failed check or routed read.
+    pub error: Option<String>,
+    /// Time of the last check (Unix timestamp), if any.
+    pub last_checked: Option<u64>,
+}
+
+/// A replica pool and its status.
+pub(crate) struct Replica {
+    pub(crate) pool: PgPool,
+    status: RwLock<ReplicaStatus>,
+}
+
+impl Replica {
+    pub(crate) fn new(name: String, pool: PgPool) -> Self {
+        Self {
+            pool,
+            status: RwLock::new(ReplicaStatus {
+                name,
+                is_healthy: false,
+                lag_ms: None,
+                in_rotation: false,
+                error: None,
+                last_checked: None,
+            }),
+        }
+    }
+
+    pub(crate) fn status(&self) -> ReplicaStatus {
+        self.status.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn in_rotation(&self) -> bool {
+        self.status.read().unwrap_or_else(|e| e.into_inner()).in_rotation
+    }
+
+    pub(crate) fn update(&self, result: std::result::Result<u64, String>, max_lag_ms: u64) {
+        let mut status = self.status.write().unwrap_or_else(|e| e.into_inner());
+        let was_in_rotation = status.in_rotation;
+        match result {
+            Ok(lag_ms) => {
+                status.is_healthy = true;
+                status.lag_ms = Some(lag_ms);
+                status.in_rotation = lag_ms <= max_lag_ms;
+                status.error = None;
+            }
+            Err(error) => {
+                status.is_healthy = false;
+                status.in_rotation = false;
+                status.error = Some(error);
+            }
+        }
+        status.last_checked = Some(unix_now());
+
+        if was_in_rotation && !status.in_rotation {
+            warn!(
+                replica = %status.name,
+                lag_ms = status.lag_ms,
+                error = status.error.as_deref(),
+                "Replica removed from read rotation"
+            );
+        } else if !was_in_rotation && status.in_rotation {
+            info!(replica = %status.name, lag_ms = status.lag_ms, "Replica added to read rotation");
+        }
+    }
+
+    /// Takes the replica out of the rotation after a failed read.
+    fn mark_failed(&self, error: &DataBridgeError) {
+        self.update(Err(error.to_string()), 0);
+    }
+
+    /// Checks reachability and replay lag.
+    async fn check(&self, max_lag_ms: u64) {
+        // Caught-up replicas report no lag even if the primary has been idle
+        let result = sqlx::query_as::<_, (bool, Option<i64>)>(
+            "SELECT pg_is_in_recovery(), \
+                    CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+                         ELSE (EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()) * 1000)::int8 \
+                    END",
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        let result = match result {
+            // A promoted or misconfigured server would serve stale or split reads
+            Ok((false, _)) => Err("Server is not in recovery, so it is not a replica".to_string()),
+            Ok((true, lag_ms)) => lag_ms
+                .map(|lag| lag.max(0) as u64)
+                .ok_or_else(|| "Replica has not replayed any transactions".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        self.update(result, max_lag_ms);
+    }
+}
+
+/// Replica pools with routing state.
+pub(crate) struct ReplicaSet {
+    pub(crate) replicas: Vec<Replica>,
+    pub(crate) config: ReplicaConfig,
+    next: AtomicUsize,
+}
+
+impl ReplicaSet {
+    pub(crate) fn new(replicas: Vec<Replica>, config: ReplicaConfig) -> Self {
+        Self {
+            replicas,
+            config,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Picks the replica for the next read, or `None` to use the primary.
+    pub(crate) fn select(&self) -> Option<&Replica> {
+        let in_rotation: Vec<&Replica> = self.replicas.iter().filter(|replica| replica.in_rotation()).collect();
+        if in_rotation.is_empty() {
+            return None;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        Some(in_rotation[next % in_rotation.len()])
+    }
+
+    /// Checks every replica and updates the rotation.
+    pub(crate) async fn check(&self) {
+        let max_lag_ms = self.config.max_lag_ms;
+        futures::future::join_all(self.replicas.iter().map(|replica| replica.check(max_lag_ms))).await;
+    }
+
+    pub(crate) fn statuses(&self) -> Vec<ReplicaStatus> {
+        self.replicas.iter().map(Replica::status).collect()
+    }
+
+    pub(crate) async fn close(&self) {
+        for replica in &self.replicas {
+            replica.pool.close().await;
+        }
+    }
+
+    /// Runs checks every `health_check_interval_ms` until the set is dropped or closed.
+    pub(crate) fn spawn_monitor(set: &Arc<Self>) {
+        if set.config.health_check_interval_ms == 0 {
+            return;
+        }
+        let interval = Duration::from_millis(set.config.health_check_interval_ms);
+        let weak: Weak<Self> = Arc::downgrade(set);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(set) = weak.upgrade() else { break };
+                if set.replicas.iter().all(|replica| replica.pool.is_closed()) {
+                    break;
+                }
+                set.check().await;
+            }
+            debug!("Replica monitor stopped");
+        });
+    }
+}
+
+/// Time of the last write through one [`session`](crate::Connection::session).
+pub(crate) struct StickyWindow {
+    window: Duration,
+    last_write: Mutex<Option<Instant>>,
+}
+
+impl StickyWindow {
+    pub(crate) fn new(window_ms: u64) -> Self {
+        Self {
+            window: Duration::from_millis(window_ms),
+            last_write: Mutex::new(None),
+        }
+    }
+
+    /// Starts the window.
+    pub(crate) fn mark(&self) {
+        *self.last_write.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+    }
+
+    /// Returns true while reads should stay on the primary.
+    pub(crate) fn is_active(&self) -> bool {
+        self.last_write
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some_and(|at| at.elapsed() < self.window)
+    }
+}
+
+/// Returns true for errors that suggest the server itself is unavailable.
+pub(crate) fn is_failover_error(error: &DataBridgeError) -> bool {
+    matches!(
+        error,
+        DataBridgeError::Connection(_) | DataBridgeError::Timeout(_) | DataBridgeError::Transient(_)
+    )
+}
+
+/// Marks `replica` as failed if `error` calls for a failover.
+pub(crate) fn report_failure(replica: &Replica, error: &DataBridgeError) -> bool {
+    if is_failover_error(error) {
+        replica.mark_failed(error);
+        true
+    } else {
+        false
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    fn replica_set(count: usize) -> ReplicaSet {
+        let replicas = (0..count)
+            .map(|i| {
+                let pool = PgPoolOptions::new()
+                    .connect_lazy(&format!("postgresql://replica{}/db", i))
+                    .unwrap();
+                Replica::new(format!("replica{}:5432/db", i), pool)
+            })
+            .collect();
+        ReplicaSet::new(replicas, ReplicaConfig::default())
+    }
+
+    fn selected(set: &ReplicaSet) -> Option<String> {
+        set.select().map(|replica| replica.status().name)
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_over_healthy_replicas() {
+        let set = replica_set(3);
+        assert_eq!(selected(&set), None);
+
+        set.replicas[0].update(Ok(0), 100);
+        set.replicas[1].update(Ok(500), 100);
+        set.replicas[2].update(Ok(10), 100);
+        assert!(!set.replicas[1].status().in_rotation);
+
+        let picks: Vec<_> = (0..4).map(|_| selected(&set).unwrap()).collect();
+        assert_eq!(picks, ["replica0:5432/db", "replica2:5432/db", "replica0:5432/db", "replica2:5432/db"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_replica_leaves_rotation() {
+        let set = replica_set(2);
+        set.replicas[0].update(Ok(0), 100);
+        set.replicas[1].update(Ok(0), 100);
+
+        let error = DataBridgeError::Connection("connection refused".to_string());
+        assert!(report_failure(&set.replicas[0], &error));
+        assert!(!report_failure(&set.replicas[1], &DataBridgeError::Query("syntax error".to_string())));
+
+        let status = set.replicas[0].status();
+        assert!(!status.is_healthy && !status.in_rotation);
+        assert!(status.error.unwrap().contains("connection refused"));
+        assert_eq!(selected(&set).unwrap(), "replica1:5432/db");
+        assert_eq!(selected(&set).unwrap(), "replica1:5432/db");
+
+        set.replicas[0].update(Ok(0), 100);
+        assert!(set.replicas[0].status().in_rotation);
+    }
+
+    #[tokio::test]
+    async fn test_sticky_window_after_write() {
+        let sticky = StickyWindow::new(50);
+        assert!(!sticky.is_active());
+
+        sticky.mark();
+        assert!(sticky.is_active());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!sticky.is_active());
+    }
+}
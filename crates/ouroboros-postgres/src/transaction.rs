@@ -2,6 +2,9 @@
 //!
 //! This module provides transaction support with ACID guarantees.
 
+use std::sync::Arc;
+
+use crate::replica::StickyWindow;
 use crate::{Connection, Result};
 use sqlx::Postgres;
 
@@ -147,6 +150,8 @@ impl TransactionOptions {
 /// - Durability: Committed changes persist
 pub struct Transaction {
     tx: sqlx::Transaction<'static, Postgres>,
+    /// Sticky window to mark on commit, if the connection is a session
+    sticky: Option<Arc<StickyWindow>>,
 }
 
 impl std::fmt::Debug for Transaction {
@@ -218,7 +223,7 @@ impl Transaction {
             "Started transaction"
         );
 
-        Ok(Self { tx })
+        Ok(Self { tx, sticky: conn.sticky_window() })
     }
 
     /// Commits the transaction.
@@ -228,6 +233,10 @@ impl Transaction {
     /// Returns error if commit fails.
     pub async fn commit(self) -> Result<()> {
         self.tx.commit().await?;
+        // Keep reads on the primary until replicas catch up with the commit
+        if let Some(sticky) = &self.sticky {
+            sticky.mark();
+        }
         Ok(())
     }
 
@@ -309,6 +318,54 @@ impl Transaction {
         tracing::debug!(savepoint = name, "Released savepoint");
         Ok(())
     }
+
+    /// Acquires a transaction-level advisory lock, waiting until it is available.
+    ///
+    /// The lock is released when the transaction commits or rolls back.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the lock query fails.
+    pub async fn advisory_lock(&mut self, key: i64) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(key)
+            .execute(&mut *self.tx)
+            .await?;
+
+        tracing::debug!(key, "Acquired transaction advisory lock");
+        Ok(())
+    }
+
+    /// Acquires a transaction-level advisory lock if it is free.
+    ///
+    /// Returns `false` at once if another session holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the lock query fails.
+    pub async fn try_advisory_lock(&mut self, key: i64) -> Result<bool> {
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock($1)")
+            .bind(key)
+            .fetch_one(&mut *self.tx)
+            .await?;
+
+        if acquired {
+            tracing::debug!(key, "Acquired transaction advisory lock");
+        }
+        Ok(acquired)
+    }
+
+    /// Sends a notification on `channel` with `pg_notify`.
+    ///
+    /// Listeners receive it when the transaction commits; it is discarded on
+    /// rollback.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the channel name is invalid or the query fails.
+    pub async fn notify(&mut self, channel: &str, payload: &str) -> Result<()> {
+        crate::listener::notify(&mut *self.tx, channel, payload).await
+    }
 }
 
 // Auto-rollback on drop if not committed
@@ -0,0 +1,171 @@
+//! Integration tests for COPY import and export.
+//!
+//! These tests require a PostgreSQL database to be running.
+//! Set DATABASE_URL environment variable to customize connection.
+//! Default: postgresql://localhost/test_db
+//!
+//! Run with: cargo test -p ouroboros-postgres --test test_copy
+
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDate;
+use futures::StreamExt;
+use ouroboros_postgres::{
+    Connection, CopyConfig, CopyExecutor, CopyFormat, CopyProgress, DataBridgeError, ExtractedValue, PoolConfig,
+};
+use ouroboros_qc::expect;
+
+/// Helper to get database URL from environment
+fn get_database_url() -> String {
+    std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| "postgresql://localhost/test_db".to_string())
+}
+
+/// Helper to create a fresh table for a test
+async fn setup_table(conn: &Connection, table: &str) -> Result<(), Box<dyn std::error::Error>> {
+    sqlx::query(&format!("DROP TABLE IF EXISTS {}", table))
+        .execute(conn.pool())
+        .await?;
+    sqlx::query(&format!(
+        "CREATE TABLE {} (id BIGINT PRIMARY KEY, name TEXT, score DOUBLE PRECISION, \
+         born DATE, tags INTEGER[], meta JSONB, upper_name TEXT GENERATED ALWAYS AS (upper(name)) STORED)",
+        table
+    ))
+    .execute(conn.pool())
+    .await?;
+    Ok(())
+}
+
+fn rows(count: i64) -> Vec<Vec<ExtractedValue>> {
+    (0..count)
+        .map(|i| vec![
+            ExtractedValue::Int(i as i32),
+            if i % 10 == 0 { ExtractedValue::Null } else { ExtractedValue::String(format!("user \"{}\",\n", i)) },
+            ExtractedValue::Int(i as i32 * 2),
+            ExtractedValue::Date(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+            ExtractedValue::Array(vec![ExtractedValue::Int(1), ExtractedValue::Null]),
+            ExtractedValue::Json(serde_json::json!({"n": i})),
+        ])
+        .collect()
+}
+
+#[tokio::test]
+async fn test_copy_in_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::new(&get_database_url(), PoolConfig::default()).await?;
+
+    for format in [CopyFormat::Binary, CopyFormat::Csv] {
+        let table = format!("test_copy_in_{}", format.to_sql());
+        setup_table(&conn, &table).await?;
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let executor = CopyExecutor::new(&conn, CopyConfig::new().format(format).progress_interval(100).buffer_size(512))
+            .on_progress(move |p| sink.lock().unwrap().push(p));
+
+        let source = futures::stream::iter(rows(250).into_iter().map(Ok));
+        let count = executor.copy_in(&table, &[], source).await?;
+        expect(count).to_equal(&250)?;
+
+        let reports = reports.lock().unwrap().clone();
+        let counts: Vec<u64> = reports.iter().map(|p| p.rows).collect();
+        expect(counts).to_equal(&vec![100, 200, 250])?;
+        expect(reports.last().unwrap().bytes > 0).to_be_true()?;
+
+        let (name, score, tags, meta, upper): (String, f64, Vec<Option<i32>>, serde_json::Value, String) =
+            sqlx::query_as(&format!("SELECT name, score, tags, meta, upper_name FROM {} WHERE id = 7", table))
+                .fetch_one(conn.pool())
+                .await?;
+        expect(name).to_equal(&"user \"7\",\n".to_string())?;
+        expect(score).to_equal(&14.0)?;
+        expect(tags).to_equal(&vec![Some(1), None])?;
+        expect(meta).to_equal(&serde_json::json!({"n": 7}))?;
+        expect(upper).to_equal(&"USER \"7\",\n".to_string())?;
+
+        let nulls: i64 = sqlx::query_scalar(&format!("SELECT count(*) FROM {} WHERE name IS NULL", table))
+            .fetch_one(conn.pool())
+            .await?;
+        expect(nulls).to_equal(&25)?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_copy_in_columns_and_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::new(&get_database_url(), PoolConfig::default()).await?;
+    setup_table(&conn, "test_copy_in_errors").await?;
+    let executor = CopyExecutor::new(&conn, CopyConfig::default());
+
+    // Explicit column subset
+    let source = futures::stream::iter(vec![
+        Ok(vec![ExtractedValue::String("a".into()), ExtractedValue::BigInt(1)]),
+    ]);
+    executor.copy_in("test_copy_in_errors", &["name", "id"], source).await?;
+
+    // A failing source aborts the whole COPY
+    let source = futures::stream::iter(vec![
+        Ok(vec![ExtractedValue::String("b".into()), ExtractedValue::BigInt(2)]),
+        Err(DataBridgeError::Internal("source failed".into())),
+    ]);
+    expect(executor.copy_in("test_copy_in_errors", &["name", "id"], source).await.is_err()).to_be_true()?;
+
+    // Values that do not fit the column type are rejected
+    let source = futures::stream::iter(vec![Ok(vec![ExtractedValue::String("x".into())])]);
+    expect(executor.copy_in("test_copy_in_errors", &["id"], source).await.is_err()).to_be_true()?;
+
+    // Invalid and unknown identifiers are rejected
+    let empty = || futures::stream::iter(Vec::<ouroboros_postgres::Result<Vec<ExtractedValue>>>::new());
+    expect(executor.copy_in("bad; table", &[], empty()).await.is_err()).to_be_true()?;
+    expect(executor.copy_in("test_copy_in_errors", &["bad; col"], empty()).await.is_err()).to_be_true()?;
+    expect(executor.copy_in("test_copy_in_errors", &["missing"], empty()).await.is_err()).to_be_true()?;
+    expect(executor.copy_out_stream("bad; table", &[]).await.is_err()).to_be_true()?;
+
+    let count: i64 = sqlx::query_scalar("SELECT count(*) FROM test_copy_in_errors")
+        .fetch_one(conn.pool())
+        .await?;
+    expect(count).to_equal(&1)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_copy_out() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::new(&get_database_url(), PoolConfig::default()).await?;
+    setup_table(&conn, "test_copy_out").await?;
+    CopyExecutor::new(&conn, CopyConfig::default())
+        .copy_in("test_copy_out", &[], futures::stream::iter(rows(30).into_iter().map(Ok)))
+        .await?;
+
+    // CSV to a writer, with a header and embedded newlines
+    let executor = CopyExecutor::new(&conn, CopyConfig::new().format(CopyFormat::Csv).header(true));
+    let mut out = Vec::new();
+    let progress = executor.copy_out("test_copy_out", &["id", "name"], &mut out).await?;
+    expect(progress.rows).to_equal(&30)?;
+    expect(progress.bytes).to_equal(&(out.len() as u64))?;
+    let text = String::from_utf8(out)?;
+    expect(text.starts_with("id,name\n0,\n1,\"user \"\"1\"\",\n\"\n")).to_be_true()?;
+
+    // Binary as a stream, with progress
+    let last = Arc::new(Mutex::new(CopyProgress::default()));
+    let sink = last.clone();
+    let executor = CopyExecutor::new(&conn, CopyConfig::default())
+        .on_progress(move |p| *sink.lock().unwrap() = p);
+    let mut stream = executor.copy_out_stream("test_copy_out", &[]).await?;
+    let mut data = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        data.extend_from_slice(&chunk?);
+    }
+    expect(data.starts_with(b"PGCOPY\n\xff\r\n\0")).to_be_true()?;
+    let last = *last.lock().unwrap();
+    expect(last.rows).to_equal(&30)?;
+    expect(last.bytes).to_equal(&(data.len() as u64))?;
+
+    // Binary output can be copied back in by another table
+    setup_table(&conn, "test_copy_out_binary").await?;
+    let mut copy = sqlx::postgres::PgPoolCopyExt::copy_in_raw(
+        conn.pool(),
+        "COPY test_copy_out_binary FROM STDIN WITH (FORMAT binary)",
+    )
+    .await?;
+    copy.send(data.as_slice()).await?;
+    expect(copy.finish().await?).to_equal(&30)?;
+    Ok(())
+}
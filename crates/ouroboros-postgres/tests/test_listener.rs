@@ -0,0 +1,117 @@
+//! Integration tests for LISTEN/NOTIFY.
+//!
+//! These tests require a PostgreSQL database to be running.
+//! Set DATABASE_URL environment variable to customize connection.
+//! Default: postgresql://localhost/test_db
+//!
+//! Run with: cargo test -p ouroboros-postgres --test test_listener
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use ouroboros_postgres::{Connection, IsolationLevel, Notification, PoolConfig, RetryConfig, Transaction};
+use ouroboros_qc::expect;
+
+/// Helper to get database URL from environment
+fn get_database_url() -> String {
+    std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| "postgresql://localhost/test_db".to_string())
+}
+
+/// Helper to wait for a notification, failing after a timeout
+async fn next(listener: &mut ouroboros_postgres::Listener) -> Notification {
+    tokio::time::timeout(Duration::from_secs(5), listener.recv())
+        .await
+        .expect("timed out waiting for notification")
+        .expect("listener failed")
+}
+
+#[tokio::test]
+async fn test_listen_notify() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::new(&get_database_url(), PoolConfig::default()).await?;
+    let mut listener = conn.listener().await?;
+    listener.listen("test_listen_a").await?;
+    listener.listen("test_listen_b").await?;
+
+    conn.notify("test_listen_a", "first").await?;
+    conn.notify("test_listen_b", "").await?;
+
+    let notification = next(&mut listener).await;
+    expect(notification.channel()).to_equal(&"test_listen_a")?;
+    expect(notification.payload()).to_equal(&"first")?;
+    expect(notification.process_id() > 0).to_be_true()?;
+
+    let notification = next(&mut listener).await;
+    expect(notification.channel()).to_equal(&"test_listen_b")?;
+    expect(notification.payload()).to_equal(&"")?;
+
+    // Unsubscribed channels are no longer delivered
+    listener.unlisten("test_listen_a").await?;
+    conn.notify("test_listen_a", "ignored").await?;
+    conn.notify("test_listen_b", "second").await?;
+    expect(next(&mut listener).await.payload()).to_equal(&"second")?;
+
+    // Invalid channel names are rejected
+    expect(listener.listen("bad; channel").await.is_err()).to_be_true()?;
+    expect(conn.notify("bad; channel", "x").await.is_err()).to_be_true()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_notify_in_transaction() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::new(&get_database_url(), PoolConfig::default()).await?;
+    let mut listener = conn.listener().await?;
+    listener.listen("test_listen_tx").await?;
+
+    // Discarded on rollback
+    let mut tx = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    tx.notify("test_listen_tx", "rolled back").await?;
+    tx.rollback().await?;
+
+    // Delivered on commit
+    let mut tx = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    tx.notify("test_listen_tx", "committed").await?;
+    tx.commit().await?;
+
+    expect(next(&mut listener).await.payload()).to_equal(&"committed")?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_listener_stream_reconnects() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::new(&get_database_url(), PoolConfig::default()).await?;
+    let mut listener = conn.listener().await?.with_retry(RetryConfig {
+        max_retries: 5,
+        initial_delay_ms: 50,
+        max_delay_ms: 500,
+        backoff_multiplier: 2.0,
+    });
+    listener.listen("test_listen_reconnect").await?;
+
+    // Find the listener's backend, then kill it
+    let pid: i32 = sqlx::query_scalar(
+        "SELECT pid FROM pg_stat_activity WHERE query = 'LISTEN \"test_listen_reconnect\"'",
+    )
+    .fetch_one(conn.pool())
+    .await?;
+    sqlx::query("SELECT pg_terminate_backend($1)")
+        .bind(pid)
+        .execute(conn.pool())
+        .await?;
+
+    let mut stream = listener.into_stream();
+    let sender = conn.clone();
+    tokio::spawn(async move {
+        // Keep notifying until the resubscribed listener picks one up
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let _ = sender.notify("test_listen_reconnect", "after").await;
+        }
+    });
+
+    let notification = tokio::time::timeout(Duration::from_secs(10), stream.next())
+        .await?
+        .expect("stream ended")?;
+    expect(notification.payload()).to_equal(&"after")?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Integration tests for read-replica routing.
+//!
+//! These tests require a PostgreSQL database to be running. The database
+//! stands in for both the primary and a misconfigured replica that is not in
+//! recovery; `application_name` tells the pools apart. Routing over healthy
+//! replicas and the sticky window are covered by the unit tests.
+//! Set DATABASE_URL environment variable to customize connection.
+//! Default: postgresql://localhost/test_db
+//!
+//! Run with: cargo test -p ouroboros-postgres --test test_replica
+
+use ouroboros_postgres::{
+    ClusterMetrics, Connection, DataBridgeError, HealthCheck, IsolationLevel, PoolConfig, ReplicaConfig, Transaction,
+};
+use ouroboros_qc::expect;
+
+/// Helper to get database URL from environment
+fn get_database_url() -> String {
+    std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| "postgresql://localhost/test_db".to_string())
+}
+
+fn with_application_name(url: &str, name: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}application_name={}", url, separator, name)
+}
+
+/// Connects with the test database as primary and as a replica, plus an unreachable replica
+async fn connect() -> Result<Connection, Box<dyn std::error::Error>> {
+    let url = get_database_url();
+    let replica = with_application_name(&url, "ouroboros_replica");
+    let config = ReplicaConfig {
+        pool: PoolConfig { connect_timeout: 1, min_connections: 0, ..PoolConfig::default() },
+        health_check_interval_ms: 0,
+        ..ReplicaConfig::default()
+    };
+    Ok(Connection::new_with_replicas(
+        &with_application_name(&url, "ouroboros_primary"),
+        &[&replica, "postgresql://postgres@127.0.0.1:1/unreachable"],
+        PoolConfig::default(),
+        config,
+    )
+    .await?)
+}
+
+/// Returns the application name of the pool a read is routed to
+async fn routed_to(conn: &Connection) -> Result<String, DataBridgeError> {
+    conn.read(|pool| async move {
+        Ok(sqlx::query_scalar("SELECT current_setting('application_name')")
+            .fetch_one(&pool)
+            .await?)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_server_not_in_recovery_leaves_rotation() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = connect().await?;
+
+    let status = conn.replica_status();
+    expect(status.len()).to_equal(&2)?;
+    expect(status[0].in_rotation).to_be_false()?;
+    expect(status[0].is_healthy).to_be_false()?;
+    expect(status[0].error.clone().unwrap_or_default().contains("not in recovery")).to_be_true()?;
+    expect(status[1].in_rotation).to_be_false()?;
+    expect(status[1].error.is_some()).to_be_true()?;
+
+    // With no replica in rotation, reads and writes use the primary
+    for _ in 0..2 {
+        expect(routed_to(&conn).await?).to_equal(&"ouroboros_primary".to_string())?;
+    }
+    let name: String = sqlx::query_scalar("SELECT current_setting('application_name')")
+        .fetch_one(conn.read_pool())
+        .await?;
+    expect(name).to_equal(&"ouroboros_primary".to_string())?;
+    let name: String = sqlx::query_scalar("SELECT current_setting('application_name')")
+        .fetch_one(conn.write_pool())
+        .await?;
+    expect(name).to_equal(&"ouroboros_primary".to_string())?;
+
+    // Later checks keep it out
+    let status = conn.check_replicas().await;
+    expect(status[0].in_rotation).to_be_false()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_reads_and_transactions() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = connect().await?;
+    let session = conn.session();
+
+    let tx = Transaction::begin(&session, IsolationLevel::ReadCommitted).await?;
+    tx.commit().await?;
+    expect(routed_to(&session).await?).to_equal(&"ouroboros_primary".to_string())?;
+    expect(session.replica_status().len()).to_equal(&2)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cluster_health_and_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = connect().await?;
+    let config = PoolConfig::default();
+
+    let health = HealthCheck::check_cluster(&conn, &config).await?;
+    expect(health.primary.is_connected).to_be_true()?;
+    expect(health.replicas.len()).to_equal(&2)?;
+    expect(health.replicas[0].health.is_healthy).to_be_false()?;
+    expect(health.replicas[1].health.is_connected).to_be_false()?;
+    expect(health.replicas_in_rotation()).to_equal(&0)?;
+    expect(health.all_ok()).to_be_false()?;
+
+    let prom = ClusterMetrics::from_connection_with_config(&conn, &config).to_prometheus("app");
+    expect(prom.contains("app_pool_size{pool=\"primary\"}")).to_be_true()?;
+    expect(prom.contains("app_replica_in_rotation{pool=\"127.0.0.1:1/unreachable\"} 0")).to_be_true()?;
+
+    // Plain connections report no replicas
+    let plain = Connection::new(&get_database_url(), config.clone()).await?;
+    let health = HealthCheck::check_cluster(&plain, &config).await?;
+    expect(health.replicas.is_empty()).to_be_true()?;
+    expect(health.all_ok()).to_be_true()?;
+    Ok(())
+}
@@ -6,7 +6,10 @@
 //!
 //! Run with: cargo test -p ouroboros-postgres --test test_transaction
 
-use ouroboros_postgres::{Connection, IsolationLevel, PoolConfig, Transaction, TransactionOptions};
+use ouroboros_postgres::{
+    advisory_key, AdvisoryLock, Connection, ExtractedValue, IsolationLevel, Operator, OrderDirection,
+    PoolConfig, QueryBuilder, Transaction, TransactionOptions,
+};
 use ouroboros_qc::{expect, AssertionError};
 
 /// Helper to get database URL from environment
@@ -614,3 +617,151 @@ async fn test_nested_savepoint_simulation() -> Result<(), Box<dyn std::error::Er
     cleanup_test_table(pool, table).await?;
     Ok(())
 }
+
+// =============================================================================
+// Locking Tests
+// =============================================================================
+
+/// Claims up to `limit` rows with FOR UPDATE SKIP LOCKED, returning their names
+async fn claim(txn: &mut Transaction, table: &str, limit: i64) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let (sql, params) = QueryBuilder::new(table)?
+        .select(vec!["name".to_string()])?
+        .where_clause("value", Operator::Gte, ExtractedValue::Int(0))?
+        .order_by("id", OrderDirection::Asc)?
+        .limit(limit)
+        .for_update()
+        .skip_locked()
+        .build_select();
+
+    let mut query = sqlx::query_scalar::<_, String>(&sql);
+    for param in params {
+        query = match param {
+            ExtractedValue::Int(v) => query.bind(v),
+            ExtractedValue::BigInt(v) => query.bind(v),
+            other => panic!("unexpected parameter {:?}", other),
+        };
+    }
+    Ok(query.fetch_all(txn.as_mut_transaction().as_mut()).await?)
+}
+
+#[tokio::test]
+async fn test_select_for_update_skip_locked() -> Result<(), Box<dyn std::error::Error>> {
+    let uri = get_database_url();
+    let conn = Connection::new(&uri, PoolConfig::default()).await?;
+    let pool = conn.pool();
+    let table = "test_txn_skip_locked";
+
+    setup_test_table(pool, table).await?;
+    for (name, value) in [("a", 1), ("b", 2), ("c", 3)] {
+        sqlx::query(&format!("INSERT INTO {} (name, value) VALUES ($1, $2)", table))
+            .bind(name)
+            .bind(value)
+            .execute(pool)
+            .await?;
+    }
+
+    // Two concurrent transactions claim disjoint rows
+    let mut first = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    let mut second = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    let first_claim = claim(&mut first, table, 2).await?;
+    let second_claim = claim(&mut second, table, 2).await?;
+    expect(first_claim).to_equal(&vec!["a".to_string(), "b".to_string()])?;
+    expect(second_claim).to_equal(&vec!["c".to_string()])?;
+
+    // NOWAIT fails on a locked row instead of blocking
+    let (sql, _) = QueryBuilder::new(table)?.for_update().nowait().build_select();
+    let result = sqlx::query(&sql).fetch_all(second.as_mut_transaction().as_mut()).await;
+    expect(result.is_err()).to_be_true()?;
+    second.rollback().await?;
+
+    // Locks are released when the transaction ends
+    first.commit().await?;
+    let mut third = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    expect(claim(&mut third, table, 5).await?.len()).to_equal(&3)?;
+    third.rollback().await?;
+
+    cleanup_test_table(pool, table).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_advisory_lock() -> Result<(), Box<dyn std::error::Error>> {
+    let uri = get_database_url();
+    let conn = Connection::new(&uri, PoolConfig::default()).await?;
+    let key = advisory_key("test_session_advisory_lock");
+
+    let lock = AdvisoryLock::acquire(&conn, key).await?;
+    expect(lock.key()).to_equal(&key)?;
+    expect(AdvisoryLock::try_acquire(&conn, key).await?.is_none()).to_be_true()?;
+
+    lock.release().await?;
+    let again = AdvisoryLock::try_acquire(&conn, key).await?;
+    expect(again.is_some()).to_be_true()?;
+
+    // Dropping the guard without releasing ends its session, freeing the lock
+    drop(again);
+    let mut reacquired = None;
+    for _ in 0..50 {
+        reacquired = AdvisoryLock::try_acquire(&conn, key).await?;
+        if reacquired.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    expect(reacquired.is_some()).to_be_true()?;
+    reacquired.unwrap().release().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_advisory_lock_cancelled_while_waiting() -> Result<(), Box<dyn std::error::Error>> {
+    let uri = get_database_url();
+    let conn = Connection::new(&uri, PoolConfig::default()).await?;
+    let key = advisory_key("test_advisory_lock_cancelled_while_waiting");
+
+    let held = AdvisoryLock::acquire(&conn, key).await?;
+    let waiting = tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        AdvisoryLock::acquire(&conn, key),
+    )
+    .await;
+    expect(waiting.is_err()).to_be_true()?;
+
+    // The abandoned wait is granted the lock once it is released, and must
+    // give it up again with its session instead of keeping it on a pooled
+    // connection. Check from another pool: advisory locks are reentrant, so
+    // a session that kept the lock could take it again.
+    held.release().await?;
+    let other = Connection::new(&uri, PoolConfig::default()).await?;
+    let mut reacquired = None;
+    for _ in 0..50 {
+        reacquired = AdvisoryLock::try_acquire(&other, key).await?;
+        if reacquired.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    expect(reacquired.is_some()).to_be_true()?;
+    reacquired.unwrap().release().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_advisory_lock() -> Result<(), Box<dyn std::error::Error>> {
+    let uri = get_database_url();
+    let conn = Connection::new(&uri, PoolConfig::default()).await?;
+    let key = advisory_key("test_transaction_advisory_lock");
+
+    let mut first = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    first.advisory_lock(key).await?;
+
+    let mut second = Transaction::begin(&conn, IsolationLevel::ReadCommitted).await?;
+    expect(second.try_advisory_lock(key).await?).to_be_false()?;
+    expect(AdvisoryLock::try_acquire(&conn, key).await?.is_none()).to_be_true()?;
+
+    // Released at the end of the holding transaction
+    first.commit().await?;
+    expect(second.try_advisory_lock(key).await?).to_be_true()?;
+    second.rollback().await?;
+    Ok(())
+}
@@ -0,0 +1,387 @@
+//! ouroboros-kv-server result backend implementation
+//!
+//! Stores task states and results on a kv-server using the same key layout as
+//! the Redis backend. A result and its state are written with one MSET, so
+//! readers never see a terminal state without its result. Metadata versions
+//! are checked with an optimistic transaction.
+
+use async_trait::async_trait;
+use ouroboros_kv_client::{
+    ClientError, Guard, KvPool, KvValue, PoolConfig, PooledClient, Transaction, TxnOp,
+};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::backend::Metadata;
+use crate::{ResultBackend, TaskError, TaskId, TaskResult, TaskState};
+
+/// ouroboros-kv-server result backend configuration
+#[derive(Debug, Clone)]
+pub struct KvBackendConfig {
+    /// Connection pool settings (address, namespace, authentication, TLS)
+    pub pool: PoolConfig,
+    /// Key prefix for all task data
+    pub key_prefix: String,
+    /// Default result TTL (0 = no expiry)
+    pub default_ttl: Duration,
+}
+
+impl Default for KvBackendConfig {
+    fn default() -> Self {
+        Self {
+            pool: PoolConfig::new("127.0.0.1:6380"),
+            key_prefix: "ouroboros-tasks".to_string(),
+            default_ttl: Duration::from_secs(86400), // 24 hours
+        }
+    }
+}
+
+fn kv_error(context: &str, e: ClientError) -> TaskError {
+    TaskError::Backend(format!("{}: {}", context, e))
+}
+
+fn decode<T: DeserializeOwned>(value: Option<KvValue>, what: &str) -> Result<Option<T>, TaskError> {
+    match value {
+        Some(KvValue::String(v)) => serde_json::from_str(&v)
+            .map(Some)
+            .map_err(|e| TaskError::Deserialization(format!("Failed to deserialize {}: {}", what, e))),
+        Some(other) => Err(TaskError::Deserialization(format!(
+            "Unexpected {} value: {:?}",
+            what, other
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// ouroboros-kv-server result backend implementation
+#[derive(Clone)]
+pub struct KvBackend {
+    config: KvBackendConfig,
+    pool: Arc<KvPool>,
+}
+
+impl KvBackend {
+    /// Create a new kv backend, connecting to the server
+    pub async fn new(config: KvBackendConfig) -> Result<Self, TaskError> {
+        debug!(
+            "Creating KV backend: addr={}, prefix={}",
+            config.pool.addr, config.key_prefix
+        );
+
+        let pool = KvPool::connect(config.pool.clone())
+            .await
+            .map_err(|e| kv_error("Failed to connect to KV server", e))?;
+
+        debug!("KV backend initialized successfully");
+
+        Ok(Self { config, pool })
+    }
+
+    /// Generate state key for a task
+    fn state_key(&self, task_id: &TaskId) -> String {
+        format!("{}:state:{}", self.config.key_prefix, task_id)
+    }
+
+    /// Generate result key for a task
+    fn result_key(&self, task_id: &TaskId) -> String {
+        format!("{}:result:{}", self.config.key_prefix, task_id)
+    }
+
+    /// Generate key for workflow metadata
+    fn metadata_key(&self, key: &str) -> String {
+        format!("{}:meta:{}", self.config.key_prefix, key)
+    }
+
+    /// Get a connection from the pool
+    async fn get_conn(&self) -> Result<PooledClient, TaskError> {
+        self.pool
+            .acquire()
+            .await
+            .map_err(|e| kv_error("Failed to get connection", e))
+    }
+
+    /// Resolve the TTL to apply, `None` meaning no expiry
+    fn ttl(&self, ttl: Option<Duration>) -> Option<Duration> {
+        Some(ttl.unwrap_or(self.config.default_ttl)).filter(|ttl| !ttl.is_zero())
+    }
+}
+
+#[async_trait]
+impl ResultBackend for KvBackend {
+    async fn set_state(&self, task_id: &TaskId, state: TaskState) -> Result<(), TaskError> {
+        let value = serde_json::to_string(&state)
+            .map_err(|e| TaskError::Serialization(format!("Failed to serialize state: {}", e)))?;
+
+        debug!("Setting state for task {}: {:?}", task_id, state);
+
+        let mut conn = self.get_conn().await?;
+        conn.client()
+            .set(&self.state_key(task_id), KvValue::String(value), self.ttl(None))
+            .await
+            .map_err(|e| kv_error("Failed to set state", e))
+    }
+
+    async fn get_state(&self, task_id: &TaskId) -> Result<Option<TaskState>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let value = conn
+            .client()
+            .get(&self.state_key(task_id))
+            .await
+            .map_err(|e| kv_error("Failed to get state", e))?;
+        decode(value, "state")
+    }
+
+    async fn set_result(
+        &self,
+        task_id: &TaskId,
+        result: TaskResult,
+        ttl: Option<Duration>,
+    ) -> Result<(), TaskError> {
+        let state_value = serde_json::to_string(&result.state).map_err(|e| {
+            TaskError::Serialization(format!("Failed to serialize state: {}", e))
+        })?;
+        let result_value = serde_json::to_string(&result).map_err(|e| {
+            TaskError::Serialization(format!("Failed to serialize result: {}", e))
+        })?;
+
+        debug!("Setting result for task {}: {:?}", task_id, result.state);
+
+        let state_key = self.state_key(task_id);
+        let result_key = self.result_key(task_id);
+        let mut conn = self.get_conn().await?;
+        conn.client()
+            .mset(
+                &[
+                    (state_key.as_str(), KvValue::String(state_value)),
+                    (result_key.as_str(), KvValue::String(result_value)),
+                ],
+                self.ttl(ttl),
+            )
+            .await
+            .map_err(|e| kv_error("Failed to set result", e))
+    }
+
+    async fn get_result(&self, task_id: &TaskId) -> Result<Option<TaskResult>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let value = conn
+            .client()
+            .get(&self.result_key(task_id))
+            .await
+            .map_err(|e| kv_error("Failed to get result", e))?;
+        decode(value, "result")
+    }
+
+    async fn wait_for_result(
+        &self,
+        task_id: &TaskId,
+        timeout: Option<Duration>,
+        poll_interval: Duration,
+    ) -> Result<TaskResult, TaskError> {
+        let start = std::time::Instant::now();
+        let timeout_duration = timeout.unwrap_or(Duration::from_secs(3600)); // 1 hour default
+
+        loop {
+            if let Some(result) = self.get_result(task_id).await? {
+                if result.state.is_terminal() {
+                    debug!("Task {} completed with state {:?}", task_id, result.state);
+                    return Ok(result);
+                }
+            }
+
+            if start.elapsed() >= timeout_duration {
+                warn!("Timeout waiting for task {}", task_id);
+                return Err(TaskError::Timeout(format!(
+                    "Task {} did not complete within {:?}",
+                    task_id, timeout_duration
+                )));
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn delete(&self, task_id: &TaskId) -> Result<(), TaskError> {
+        debug!("Deleting task data for {}", task_id);
+
+        let state_key = self.state_key(task_id);
+        let result_key = self.result_key(task_id);
+        let mut conn = self.get_conn().await?;
+        conn.client()
+            .mdel(&[state_key.as_str(), result_key.as_str()])
+            .await
+            .map_err(|e| kv_error("Failed to delete task data", e))?;
+        Ok(())
+    }
+
+    async fn get_many(&self, task_ids: &[TaskId]) -> Result<Vec<Option<TaskResult>>, TaskError> {
+        if task_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys: Vec<String> = task_ids.iter().map(|id| self.result_key(id)).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+
+        let mut conn = self.get_conn().await?;
+        let values = conn
+            .client()
+            .mget(&keys)
+            .await
+            .map_err(|e| kv_error("Failed to get many results", e))?;
+
+        values.into_iter().map(|value| decode(value, "result")).collect()
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        let mut conn = self.get_conn().await?;
+        conn.client()
+            .ping()
+            .await
+            .map_err(|e| kv_error("Health check failed", e))?;
+        Ok(())
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<Metadata>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let value = conn
+            .client()
+            .get(&self.metadata_key(key))
+            .await
+            .map_err(|e| kv_error("Failed to get metadata", e))?;
+        decode(value, "metadata")
+    }
+
+    async fn set_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        expected: Option<u64>,
+        expire: bool,
+    ) -> Result<bool, TaskError> {
+        let key = self.metadata_key(key);
+        let mut conn = self.get_conn().await?;
+        let client = conn.client();
+
+        loop {
+            let watched = client
+                .watch(&key)
+                .await
+                .map_err(|e| kv_error("Failed to watch metadata", e))?;
+            let current: Option<Metadata> = decode(
+                client.get(&key).await.map_err(|e| kv_error("Failed to get metadata", e))?,
+                "metadata",
+            )?;
+            let version = current.map(|meta| meta.version).unwrap_or(0);
+            if version != expected.unwrap_or(0) {
+                return Ok(false);
+            }
+
+            let metadata = Metadata { value: value.clone(), version: version + 1 };
+            let encoded = serde_json::to_string(&metadata).map_err(|e| {
+                TaskError::Serialization(format!("Failed to serialize metadata: {}", e))
+            })?;
+            let mut txn = Transaction::new();
+            txn.guards.push((key.clone(), Guard::Version(watched)));
+            txn.push(TxnOp::Set {
+                key: key.clone(),
+                value: KvValue::String(encoded),
+                ttl: self.ttl(None).filter(|_| expire),
+            });
+
+            // A failed guard means the key changed between the reads; look again
+            if client
+                .transact(&txn)
+                .await
+                .map_err(|e| kv_error("Failed to set metadata", e))?
+                .is_some()
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    async fn delete_metadata(&self, key: &str) -> Result<(), TaskError> {
+        let mut conn = self.get_conn().await?;
+        conn.client()
+            .delete(&self.metadata_key(key))
+            .await
+            .map_err(|e| kv_error("Failed to delete metadata", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults() {
+        let config = KvBackendConfig::default();
+        assert_eq!(config.pool.addr, "127.0.0.1:6380");
+        assert_eq!(config.key_prefix, "ouroboros-tasks");
+        assert_eq!(config.default_ttl, Duration::from_secs(86400));
+    }
+
+    // Integration tests - require a kv-server running
+    #[tokio::test]
+    #[ignore]
+    async fn test_set_get_result() {
+        let backend = KvBackend::new(KvBackendConfig::default()).await.unwrap();
+        let task_id = TaskId::new();
+
+        backend.set_state(&task_id, TaskState::Started).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Started));
+
+        let result = TaskResult::success(task_id.clone(), serde_json::json!({"test": "data"}));
+        backend.set_result(&task_id, result, None).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Success));
+
+        let results = backend.get_many(&[task_id.clone(), TaskId::new()]).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().task_id, task_id);
+        assert!(results[1].is_none());
+
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(1)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(result.state, TaskState::Success);
+
+        backend.delete(&task_id).await.unwrap();
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+        backend.health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_metadata() {
+        let backend = KvBackend::new(KvBackendConfig::default()).await.unwrap();
+        let key = format!("workflow:{}", TaskId::new());
+
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 1}), None, true).await.unwrap());
+        assert!(!backend.set_metadata(&key, serde_json::json!({"step": 9}), None, true).await.unwrap());
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 2}), Some(1), true).await.unwrap());
+        assert!(!backend.set_metadata(&key, serde_json::json!({"step": 3}), Some(1), true).await.unwrap());
+
+        let meta = backend.get_metadata(&key).await.unwrap().unwrap();
+        assert_eq!(meta.value, serde_json::json!({"step": 2}));
+        assert_eq!(meta.version, 2);
+
+        backend.delete_metadata(&key).await.unwrap();
+        assert!(backend.get_metadata(&key).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_wait_for_result_timeout() {
+        let backend = KvBackend::new(KvBackendConfig::default()).await.unwrap();
+        let task_id = TaskId::new();
+        backend.set_state(&task_id, TaskState::Pending).await.unwrap();
+
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_millis(100)), Duration::from_millis(20))
+            .await;
+        assert!(matches!(result, Err(TaskError::Timeout(_))));
+        backend.delete(&task_id).await.unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+//! In-process result backend implementation
+//!
+//! Keeps task states and results in memory, shared by every clone of the
+//! backend. Useful for tests and single-binary deployments.
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::backend::Metadata;
+use crate::{ResultBackend, TaskError, TaskId, TaskResult, TaskState};
+
+/// State and result of one task
+struct Entry {
+    state: Option<TaskState>,
+    result: Option<TaskResult>,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self) -> bool {
+        self.expires_at.map(|at| at <= Instant::now()).unwrap_or(false)
+    }
+}
+
+/// In-memory result backend (thread-safe, non-distributed)
+#[derive(Clone)]
+pub struct InMemoryBackend {
+    entries: Arc<DashMap<TaskId, Entry>>,
+    metadata: Arc<DashMap<String, (Metadata, Option<Instant>)>>,
+    /// Default TTL for states and results
+    default_ttl: Option<Duration>,
+}
+
+impl InMemoryBackend {
+    /// Create a new in-memory backend that keeps results until deleted
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(DashMap::new()),
+            metadata: Arc::new(DashMap::new()),
+            default_ttl: None,
+        }
+    }
+
+    /// Create with a default TTL for all states and results
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(DashMap::new()),
+            metadata: Arc::new(DashMap::new()),
+            default_ttl: Some(ttl),
+        }
+    }
+
+    /// Get the number of tasks stored (including expired)
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if backend is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove expired entries, returns the number removed
+    pub fn cleanup(&self) -> usize {
+        let now = Instant::now();
+        self.metadata
+            .retain(|_, (_, expires_at)| expires_at.map(|at| at > now).unwrap_or(true));
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !entry.is_expired());
+        before - self.entries.len()
+    }
+
+    fn update(&self, task_id: &TaskId, ttl: Option<Duration>, apply: impl FnOnce(&mut Entry)) {
+        let mut entry = self.entries.entry(task_id.clone()).or_insert_with(|| Entry {
+            state: None,
+            result: None,
+            expires_at: None,
+        });
+        if entry.is_expired() {
+            entry.state = None;
+            entry.result = None;
+        }
+        apply(&mut entry);
+        entry.expires_at = ttl.or(self.default_ttl).map(|ttl| Instant::now() + ttl);
+    }
+
+    fn read<T>(&self, task_id: &TaskId, get: impl FnOnce(&Entry) -> Option<T>) -> Option<T> {
+        let entry = self.entries.get(task_id)?;
+        if entry.is_expired() {
+            drop(entry);
+            self.entries.remove_if(task_id, |_, entry| entry.is_expired());
+            return None;
+        }
+        get(&entry)
+    }
+}
+
+impl Default for InMemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ResultBackend for InMemoryBackend {
+    async fn set_state(&self, task_id: &TaskId, state: TaskState) -> Result<(), TaskError> {
+        debug!("Setting state for task {}: {:?}", task_id, state);
+        self.update(task_id, None, |entry| entry.state = Some(state));
+        Ok(())
+    }
+
+    async fn get_state(&self, task_id: &TaskId) -> Result<Option<TaskState>, TaskError> {
+        Ok(self.read(task_id, |entry| entry.state))
+    }
+
+    async fn set_result(
+        &self,
+        task_id: &TaskId,
+        result: TaskResult,
+        ttl: Option<Duration>,
+    ) -> Result<(), TaskError> {
+        debug!("Setting result for task {}: {:?}", task_id, result.state);
+        self.update(task_id, ttl, |entry| {
+            entry.state = Some(result.state);
+            entry.result = Some(result);
+        });
+        Ok(())
+    }
+
+    async fn get_result(&self, task_id: &TaskId) -> Result<Option<TaskResult>, TaskError> {
+        Ok(self.read(task_id, |entry| entry.result.clone()))
+    }
+
+    async fn wait_for_result(
+        &self,
+        task_id: &TaskId,
+        timeout: Option<Duration>,
+        poll_interval: Duration,
+    ) -> Result<TaskResult, TaskError> {
+        let timeout_duration = timeout.unwrap_or(Duration::from_secs(3600)); // 1 hour default
+        let deadline = Instant::now() + timeout_duration;
+
+        loop {
+            if let Some(result) = self.read(task_id, |entry| {
+                entry.result.clone().filter(|result| result.state.is_terminal())
+            }) {
+                return Ok(result);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(TaskError::Timeout(format!(
+                    "Task {} did not complete within {:?}",
+                    task_id, timeout_duration
+                )));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn delete(&self, task_id: &TaskId) -> Result<(), TaskError> {
+        self.entries.remove(task_id);
+        Ok(())
+    }
+
+    async fn get_many(&self, task_ids: &[TaskId]) -> Result<Vec<Option<TaskResult>>, TaskError> {
+        Ok(task_ids
+            .iter()
+            .map(|task_id| self.read(task_id, |entry| entry.result.clone()))
+            .collect())
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<Metadata>, TaskError> {
+        let now = Instant::now();
+        Ok(self
+            .metadata
+            .get(key)
+            .filter(|entry| entry.1.map(|at| at > now).unwrap_or(true))
+            .map(|entry| entry.0.clone()))
+    }
+
+    async fn set_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        expected: Option<u64>,
+        expire: bool,
+    ) -> Result<bool, TaskError> {
+        let now = Instant::now();
+        let live = |expires_at: &Option<Instant>| expires_at.map(|at| at > now).unwrap_or(true);
+
+        let entry = self.metadata.entry(key.to_string());
+        let current = match &entry {
+            dashmap::mapref::entry::Entry::Occupied(e) if live(&e.get().1) => e.get().0.version,
+            _ => 0,
+        };
+        if current != expected.unwrap_or(0) {
+            return Ok(false);
+        }
+
+        let metadata = Metadata { value, version: current + 1 };
+        let expires_at = self.default_ttl.filter(|_| expire).map(|ttl| now + ttl);
+        entry.insert((metadata, expires_at));
+        Ok(true)
+    }
+
+    async fn delete_metadata(&self, key: &str) -> Result<(), TaskError> {
+        self.metadata.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn success(task_id: &TaskId) -> TaskResult {
+        TaskResult {
+            state: TaskState::Success,
+            result: Some(serde_json::json!(42)),
+            ..TaskResult::pending(task_id.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_and_result() {
+        let backend = InMemoryBackend::new();
+        let task_id = TaskId::new();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), None);
+
+        backend.set_state(&task_id, TaskState::Started).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Started));
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+
+        backend.set_result(&task_id, success(&task_id), None).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Success));
+
+        let other = TaskId::new();
+        let results = backend.get_many(&[task_id.clone(), other]).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().result, Some(serde_json::json!(42)));
+        assert!(results[1].is_none());
+
+        backend.delete(&task_id).await.unwrap();
+        assert!(backend.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_result() {
+        let backend = InMemoryBackend::new();
+        let task_id = TaskId::new();
+
+        let writer = backend.clone();
+        let id = task_id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            writer.set_result(&id, success(&id), None).await.unwrap();
+        });
+
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(1)), Duration::from_millis(5))
+            .await
+            .unwrap();
+        assert_eq!(result.state, TaskState::Success);
+
+        let missing = backend
+            .wait_for_result(&TaskId::new(), Some(Duration::from_millis(20)), Duration::from_millis(5))
+            .await;
+        assert!(matches!(missing, Err(TaskError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_metadata() {
+        let backend = InMemoryBackend::new();
+        assert!(backend.get_metadata("workflow:1").await.unwrap().is_none());
+
+        assert!(backend.set_metadata("workflow:1", serde_json::json!({"step": 1}), None, true).await.unwrap());
+        assert!(!backend.set_metadata("workflow:1", serde_json::json!({"step": 9}), None, true).await.unwrap());
+
+        let meta = backend.get_metadata("workflow:1").await.unwrap().unwrap();
+        assert_eq!(meta.version, 1);
+        assert!(backend.set_metadata("workflow:1", serde_json::json!({"step": 2}), Some(1), true).await.unwrap());
+        assert!(!backend.set_metadata("workflow:1", serde_json::json!({"step": 3}), Some(1), true).await.unwrap());
+
+        let meta = backend.get_metadata("workflow:1").await.unwrap().unwrap();
+        assert_eq!(meta.value, serde_json::json!({"step": 2}));
+        assert_eq!(meta.version, 2);
+
+        backend.delete_metadata("workflow:1").await.unwrap();
+        assert!(backend.get_metadata("workflow:1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ttl() {
+        let backend = InMemoryBackend::with_ttl(Duration::from_millis(20));
+        let task_id = TaskId::new();
+        backend.set_result(&task_id, success(&task_id), None).await.unwrap();
+        assert!(backend.get_result(&task_id).await.unwrap().is_some());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+        assert_eq!(backend.cleanup(), 0);
+
+        backend.set_state(&task_id, TaskState::Pending).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(backend.cleanup(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_expiry() {
+        let backend = InMemoryBackend::with_ttl(Duration::from_millis(20));
+        assert!(backend.set_metadata("workflow:1", serde_json::json!({"step": 1}), None, false).await.unwrap());
+
+        // Kept past the TTL until a write asks for expiry
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(backend.get_metadata("workflow:1").await.unwrap().is_some());
+        assert!(backend.set_metadata("workflow:1", serde_json::json!({"done": true}), Some(1), true).await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(backend.get_metadata("workflow:1").await.unwrap().is_none());
+    }
+}
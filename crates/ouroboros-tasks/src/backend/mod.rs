@@ -3,10 +3,20 @@
 //! Provides traits and implementations for storing task results.
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 use crate::{TaskError, TaskId, TaskResult, TaskState};
 
+/// Versioned metadata record (e.g. workflow state)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Stored value
+    pub value: serde_json::Value,
+    /// Incremented on every write, starting at 1
+    pub version: u64,
+}
+
 /// Trait for result backend implementations
 #[async_trait]
 pub trait ResultBackend: Send + Sync + 'static {
@@ -43,11 +53,50 @@ pub trait ResultBackend: Send + Sync + 'static {
 
     /// Health check
     async fn health_check(&self) -> Result<(), TaskError>;
+
+    /// Get metadata stored under `key`
+    async fn get_metadata(&self, key: &str) -> Result<Option<Metadata>, TaskError>;
+
+    /// Store metadata under `key` if its current version is `expected`
+    /// (`None` = the key must not exist)
+    ///
+    /// Returns `false`, changing nothing, if another writer got there first.
+    /// With `expire`, the metadata expires like results, counting from this
+    /// write; without it, it is kept until overwritten or deleted.
+    async fn set_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        expected: Option<u64>,
+        expire: bool,
+    ) -> Result<bool, TaskError>;
+
+    /// Delete metadata
+    async fn delete_metadata(&self, key: &str) -> Result<(), TaskError>;
 }
 
+// In-process backend implementation
+pub mod memory;
+
+pub use memory::InMemoryBackend;
+
 // Redis backend implementation
 #[cfg(feature = "redis")]
 pub mod redis;
 
 #[cfg(feature = "redis")]
 pub use redis::{RedisBackend, RedisBackendConfig};
+
+// ouroboros-kv-server backend
+#[cfg(feature = "kv")]
+pub mod kv;
+
+#[cfg(feature = "kv")]
+pub use kv::{KvBackend, KvBackendConfig};
+
+// PostgreSQL backend implementation
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+#[cfg(feature = "postgres")]
+pub use postgres::{PostgresBackend, PostgresBackendConfig};
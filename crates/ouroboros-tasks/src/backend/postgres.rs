@@ -0,0 +1,479 @@
+//! PostgreSQL result backend implementation
+//!
+//! Stores one row per task holding its state, its result (JSONB) and an
+//! optional expiry. Expired rows are ignored on read and removed by `cleanup`.
+//! Workflow metadata lives in a companion `<table>_metadata` table.
+
+use async_trait::async_trait;
+use ouroboros_postgres::{Connection, QueryBuilder};
+use sqlx::types::Json;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::backend::Metadata;
+use crate::{ResultBackend, TaskError, TaskId, TaskResult, TaskState};
+
+/// PostgreSQL result backend configuration
+#[derive(Debug, Clone)]
+pub struct PostgresBackendConfig {
+    /// Results table (optionally schema-qualified, e.g. "jobs.task_results")
+    pub table: String,
+    /// Default result TTL (0 = no expiry)
+    pub default_ttl: Duration,
+    /// Create the results table on startup
+    pub create_table: bool,
+}
+
+impl Default for PostgresBackendConfig {
+    fn default() -> Self {
+        Self {
+            table: "ouroboros_task_results".to_string(),
+            default_ttl: Duration::from_secs(86400), // 24 hours
+            create_table: true,
+        }
+    }
+}
+
+fn pg_error(context: &str, e: impl std::fmt::Display) -> TaskError {
+    TaskError::Backend(format!("{}: {}", context, e))
+}
+
+fn encode_state(state: TaskState) -> Result<String, TaskError> {
+    match serde_json::to_value(state) {
+        Ok(serde_json::Value::String(s)) => Ok(s),
+        _ => Err(TaskError::Serialization(format!("Failed to serialize state: {:?}", state))),
+    }
+}
+
+fn decode_state(state: String) -> Result<TaskState, TaskError> {
+    serde_json::from_value(serde_json::Value::String(state))
+        .map_err(|e| TaskError::Deserialization(format!("Failed to deserialize state: {}", e)))
+}
+
+/// SQL statements for one results table
+struct Statements {
+    create: String,
+    set_state: String,
+    set_result: String,
+    get_state: String,
+    get_result: String,
+    get_many: String,
+    delete: String,
+    cleanup: String,
+    create_metadata: String,
+    get_metadata: String,
+    insert_metadata: String,
+    update_metadata: String,
+    delete_metadata: String,
+    cleanup_metadata: String,
+}
+
+impl Statements {
+    fn new(table: &str) -> Result<Self, TaskError> {
+        QueryBuilder::validate_identifier(table)
+            .map_err(|e| TaskError::Configuration(format!("Invalid results table: {}", e)))?;
+        let t = QueryBuilder::quote_identifier(table);
+        let m = QueryBuilder::quote_identifier(&format!("{table}_metadata"));
+        let live = "(expires_at IS NULL OR expires_at > now())";
+        // $3 is the TTL in milliseconds, 0 meaning no expiry
+        let expires_at = "CASE WHEN $3 > 0 THEN now() + $3 * interval '1 millisecond' END";
+
+        Ok(Self {
+            create: format!(
+                "CREATE TABLE IF NOT EXISTS {t} (
+                    task_id TEXT PRIMARY KEY,
+                    state TEXT NOT NULL,
+                    result JSONB,
+                    expires_at TIMESTAMPTZ,
+                    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                )"
+            ),
+            // Keeps the stored result unless the row had already expired
+            set_state: format!(
+                "INSERT INTO {t} AS r (task_id, state, expires_at)
+                VALUES ($1, $2, {expires_at})
+                ON CONFLICT (task_id) DO UPDATE SET
+                    state = EXCLUDED.state,
+                    result = CASE WHEN r.expires_at <= now() THEN NULL ELSE r.result END,
+                    expires_at = EXCLUDED.expires_at,
+                    updated_at = now()"
+            ),
+            set_result: format!(
+                "INSERT INTO {t} (task_id, state, result, expires_at)
+                VALUES ($1, $2, $4, {expires_at})
+                ON CONFLICT (task_id) DO UPDATE SET
+                    state = EXCLUDED.state,
+                    result = EXCLUDED.result,
+                    expires_at = EXCLUDED.expires_at,
+                    updated_at = now()"
+            ),
+            get_state: format!("SELECT state FROM {t} WHERE task_id = $1 AND {live}"),
+            get_result: format!("SELECT result FROM {t} WHERE task_id = $1 AND {live}"),
+            get_many: format!(
+                "SELECT task_id, result FROM {t} WHERE task_id = ANY($1) AND {live}"
+            ),
+            delete: format!("DELETE FROM {t} WHERE task_id = $1"),
+            cleanup: format!("DELETE FROM {t} WHERE expires_at <= now()"),
+            create_metadata: format!(
+                "CREATE TABLE IF NOT EXISTS {m} (
+                    key TEXT PRIMARY KEY,
+                    value JSONB NOT NULL,
+                    version BIGINT NOT NULL,
+                    expires_at TIMESTAMPTZ,
+                    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                )"
+            ),
+            get_metadata: format!("SELECT value, version FROM {m} WHERE key = $1 AND {live}"),
+            // An expired row counts as missing
+            insert_metadata: format!(
+                "INSERT INTO {m} AS r (key, value, version, expires_at)
+                VALUES ($1, $2, 1, {expires_at})
+                ON CONFLICT (key) DO UPDATE SET
+                    value = EXCLUDED.value,
+                    version = 1,
+                    expires_at = EXCLUDED.expires_at,
+                    updated_at = now()
+                WHERE r.expires_at <= now()"
+            ),
+            update_metadata: format!(
+                "UPDATE {m} SET
+                    value = $2,
+                    version = version + 1,
+                    expires_at = {expires_at},
+                    updated_at = now()
+                WHERE key = $1 AND version = $4 AND {live}"
+            ),
+            delete_metadata: format!("DELETE FROM {m} WHERE key = $1"),
+            cleanup_metadata: format!("DELETE FROM {m} WHERE expires_at <= now()"),
+        })
+    }
+}
+
+/// PostgreSQL result backend implementation
+#[derive(Clone)]
+pub struct PostgresBackend {
+    conn: Connection,
+    default_ttl: Duration,
+    sql: std::sync::Arc<Statements>,
+}
+
+impl PostgresBackend {
+    /// Create a new Postgres backend on an existing connection pool
+    pub async fn new(conn: Connection, config: PostgresBackendConfig) -> Result<Self, TaskError> {
+        debug!("Creating Postgres backend: table={}", config.table);
+
+        let sql = Statements::new(&config.table)?;
+        if config.create_table {
+            sqlx::query(&sql.create)
+                .execute(conn.pool())
+                .await
+                .map_err(|e| pg_error("Failed to create results table", e))?;
+            sqlx::query(&sql.create_metadata)
+                .execute(conn.pool())
+                .await
+                .map_err(|e| pg_error("Failed to create metadata table", e))?;
+        }
+
+        Ok(Self {
+            conn,
+            default_ttl: config.default_ttl,
+            sql: std::sync::Arc::new(sql),
+        })
+    }
+
+    /// Delete expired rows, returns the number removed
+    pub async fn cleanup(&self) -> Result<u64, TaskError> {
+        let result = sqlx::query(&self.sql.cleanup)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to clean up results", e))?;
+        let metadata = sqlx::query(&self.sql.cleanup_metadata)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to clean up metadata", e))?;
+        Ok(result.rows_affected() + metadata.rows_affected())
+    }
+
+    fn ttl_millis(&self, ttl: Option<Duration>) -> i64 {
+        ttl.unwrap_or(self.default_ttl).as_millis().min(i64::MAX as u128) as i64
+    }
+}
+
+#[async_trait]
+impl ResultBackend for PostgresBackend {
+    async fn set_state(&self, task_id: &TaskId, state: TaskState) -> Result<(), TaskError> {
+        debug!("Setting state for task {}: {:?}", task_id, state);
+
+        sqlx::query(&self.sql.set_state)
+            .bind(task_id.to_string())
+            .bind(encode_state(state)?)
+            .bind(self.ttl_millis(None))
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to set state", e))?;
+        Ok(())
+    }
+
+    async fn get_state(&self, task_id: &TaskId) -> Result<Option<TaskState>, TaskError> {
+        let state: Option<String> = sqlx::query_scalar(&self.sql.get_state)
+            .bind(task_id.to_string())
+            .fetch_optional(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to get state", e))?;
+        state.map(decode_state).transpose()
+    }
+
+    async fn set_result(
+        &self,
+        task_id: &TaskId,
+        result: TaskResult,
+        ttl: Option<Duration>,
+    ) -> Result<(), TaskError> {
+        debug!("Setting result for task {}: {:?}", task_id, result.state);
+
+        sqlx::query(&self.sql.set_result)
+            .bind(task_id.to_string())
+            .bind(encode_state(result.state)?)
+            .bind(self.ttl_millis(ttl))
+            .bind(Json(&result))
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to set result", e))?;
+        Ok(())
+    }
+
+    async fn get_result(&self, task_id: &TaskId) -> Result<Option<TaskResult>, TaskError> {
+        let result: Option<Option<Json<TaskResult>>> = sqlx::query_scalar(&self.sql.get_result)
+            .bind(task_id.to_string())
+            .fetch_optional(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to get result", e))?;
+        Ok(result.flatten().map(|Json(result)| result))
+    }
+
+    async fn wait_for_result(
+        &self,
+        task_id: &TaskId,
+        timeout: Option<Duration>,
+        poll_interval: Duration,
+    ) -> Result<TaskResult, TaskError> {
+        let start = std::time::Instant::now();
+        let timeout_duration = timeout.unwrap_or(Duration::from_secs(3600)); // 1 hour default
+
+        loop {
+            if let Some(result) = self.get_result(task_id).await? {
+                if result.state.is_terminal() {
+                    debug!("Task {} completed with state {:?}", task_id, result.state);
+                    return Ok(result);
+                }
+            }
+
+            if start.elapsed() >= timeout_duration {
+                warn!("Timeout waiting for task {}", task_id);
+                return Err(TaskError::Timeout(format!(
+                    "Task {} did not complete within {:?}",
+                    task_id, timeout_duration
+                )));
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn delete(&self, task_id: &TaskId) -> Result<(), TaskError> {
+        debug!("Deleting task data for {}", task_id);
+
+        sqlx::query(&self.sql.delete)
+            .bind(task_id.to_string())
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to delete task data", e))?;
+        Ok(())
+    }
+
+    async fn get_many(&self, task_ids: &[TaskId]) -> Result<Vec<Option<TaskResult>>, TaskError> {
+        if task_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<String> = task_ids.iter().map(|id| id.to_string()).collect();
+        let rows = sqlx::query(&self.sql.get_many)
+            .bind(&ids)
+            .fetch_all(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to get many results", e))?;
+
+        let mut found = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let task_id: String = row.try_get("task_id").map_err(|e| pg_error("Invalid row", e))?;
+            let result: Option<Json<TaskResult>> = row.try_get("result").map_err(|e| {
+                TaskError::Deserialization(format!("Failed to deserialize result for task {}: {}", task_id, e))
+            })?;
+            if let Some(Json(result)) = result {
+                found.insert(task_id, result);
+            }
+        }
+
+        Ok(ids.iter().map(|id| found.remove(id)).collect())
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        self.conn
+            .ping()
+            .await
+            .map_err(|e| pg_error("Health check failed", e))
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<Metadata>, TaskError> {
+        let row: Option<(Json<serde_json::Value>, i64)> = sqlx::query_as(&self.sql.get_metadata)
+            .bind(key)
+            .fetch_optional(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to get metadata", e))?;
+        Ok(row.map(|(Json(value), version)| Metadata {
+            value,
+            version: version as u64,
+        }))
+    }
+
+    async fn set_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        expected: Option<u64>,
+        expire: bool,
+    ) -> Result<bool, TaskError> {
+        let query = match expected {
+            None => sqlx::query(&self.sql.insert_metadata),
+            Some(_) => sqlx::query(&self.sql.update_metadata),
+        };
+        let result = query
+            .bind(key)
+            .bind(Json(value))
+            .bind(if expire { self.ttl_millis(None) } else { 0 })
+            .bind(expected.unwrap_or(0) as i64)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to set metadata", e))?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_metadata(&self, key: &str) -> Result<(), TaskError> {
+        sqlx::query(&self.sql.delete_metadata)
+            .bind(key)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to delete metadata", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ouroboros_postgres::PoolConfig;
+
+    #[test]
+    fn test_state_encoding() {
+        let encoded = encode_state(TaskState::Success).unwrap();
+        assert_eq!(encoded, "SUCCESS");
+        assert_eq!(decode_state(encoded).unwrap(), TaskState::Success);
+        assert!(Statements::new("results\"; --").is_err());
+    }
+
+    // Integration tests requiring PostgreSQL
+    // Run with DATABASE_URL=postgresql://user@localhost/db
+    async fn backend(config: PostgresBackendConfig) -> PostgresBackend {
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or_else(|_| "postgresql://postgres@localhost/postgres".to_string());
+        let conn = Connection::new(&url, PoolConfig::default()).await.unwrap();
+        PostgresBackend::new(conn, config).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_set_get_result() {
+        let backend = backend(PostgresBackendConfig::default()).await;
+        let task_id = TaskId::new();
+
+        backend.set_state(&task_id, TaskState::Started).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Started));
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+
+        let result = TaskResult::success(task_id.clone(), serde_json::json!({"test": "data"}));
+        backend.set_result(&task_id, result, None).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Success));
+
+        let other = TaskId::new();
+        let results = backend.get_many(&[other, task_id.clone()]).await.unwrap();
+        assert!(results[0].is_none());
+        assert_eq!(results[1].as_ref().unwrap().task_id, task_id);
+
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(1)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(result.result, Some(serde_json::json!({"test": "data"})));
+
+        backend.delete(&task_id).await.unwrap();
+        assert!(backend.get_state(&task_id).await.unwrap().is_none());
+        backend.health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_metadata() {
+        let backend = backend(PostgresBackendConfig::default()).await;
+        let key = format!("workflow:{}", TaskId::new());
+
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 1}), None, true).await.unwrap());
+        assert!(!backend.set_metadata(&key, serde_json::json!({"step": 9}), None, true).await.unwrap());
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 2}), Some(1), true).await.unwrap());
+        assert!(!backend.set_metadata(&key, serde_json::json!({"step": 3}), Some(1), true).await.unwrap());
+
+        let meta = backend.get_metadata(&key).await.unwrap().unwrap();
+        assert_eq!(meta.value, serde_json::json!({"step": 2}));
+        assert_eq!(meta.version, 2);
+
+        backend.delete_metadata(&key).await.unwrap();
+        assert!(backend.get_metadata(&key).await.unwrap().is_none());
+        assert!(!backend.set_metadata(&key, serde_json::json!({}), Some(2), true).await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_ttl_and_cleanup() {
+        let backend = backend(PostgresBackendConfig {
+            default_ttl: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .await;
+        let task_id = TaskId::new();
+
+        let result = TaskResult::success(task_id.clone(), serde_json::json!(1));
+        backend.set_result(&task_id, result, None).await.unwrap();
+        assert!(backend.get_result(&task_id).await.unwrap().is_some());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+
+        // A new state does not bring the expired result back
+        backend.set_state(&task_id, TaskState::Retry).await.unwrap();
+        assert_eq!(backend.get_state(&task_id).await.unwrap(), Some(TaskState::Retry));
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend.cleanup().await.unwrap() >= 1);
+
+        // Metadata only expires once a write asks for it
+        let key = format!("workflow:{}", task_id);
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 1}), None, false).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend.get_metadata(&key).await.unwrap().is_some());
+        assert!(backend.set_metadata(&key, serde_json::json!({"done": true}), Some(1), true).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend.get_metadata(&key).await.unwrap().is_none());
+    }
+}
@@ -10,8 +10,25 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
+use crate::backend::Metadata;
 use crate::{ResultBackend, TaskError, TaskId, TaskResult, TaskState};
 
+/// Replace a metadata hash if its version matches
+/// KEYS[1] = metadata key; ARGV = expected version (0 = missing), value, TTL seconds
+const SET_METADATA_SCRIPT: &str = r#"
+local current = tonumber(redis.call('HGET', KEYS[1], 'version') or '0')
+if current ~= tonumber(ARGV[1]) then
+    return 0
+end
+redis.call('HSET', KEYS[1], 'version', current + 1, 'value', ARGV[2])
+if tonumber(ARGV[3]) > 0 then
+    redis.call('EXPIRE', KEYS[1], ARGV[3])
+else
+    redis.call('PERSIST', KEYS[1])
+end
+return 1
+"#;
+
 /// Redis result backend configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedisBackendConfig {
@@ -76,6 +93,16 @@ impl RedisBackend {
         Ok(Self { config, pool })
     }
 
+    /// Get the connection pool, e.g. to share it with a store
+    pub fn pool(&self) -> &Pool {
+        &self.pool
+    }
+
+    /// Get the key prefix
+    pub fn key_prefix(&self) -> &str {
+        &self.config.key_prefix
+    }
+
     /// Generate state key for a task
     fn state_key(&self, task_id: &TaskId) -> String {
         format!("{}:state:{}", self.config.key_prefix, task_id)
@@ -86,6 +113,11 @@ impl RedisBackend {
         format!("{}:result:{}", self.config.key_prefix, task_id)
     }
 
+    /// Generate key for workflow metadata
+    fn metadata_key(&self, key: &str) -> String {
+        format!("{}:meta:{}", self.config.key_prefix, key)
+    }
+
     /// Get a connection from the pool
     async fn get_conn(&self) -> Result<Connection, TaskError> {
         self.pool
@@ -320,6 +352,55 @@ impl ResultBackend for RedisBackend {
 
         Ok(())
     }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<Metadata>, TaskError> {
+        let mut conn = self.get_conn().await?;
+
+        let (version, value): (Option<u64>, Option<String>) = conn
+            .hget(self.metadata_key(key), &["version", "value"])
+            .await
+            .map_err(|e| TaskError::Backend(format!("Failed to get metadata: {}", e)))?;
+
+        match (version, value) {
+            (Some(version), Some(value)) => {
+                let value = serde_json::from_str(&value).map_err(|e| {
+                    TaskError::Deserialization(format!("Failed to deserialize metadata: {}", e))
+                })?;
+                Ok(Some(Metadata { value, version }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn set_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        expected: Option<u64>,
+        expire: bool,
+    ) -> Result<bool, TaskError> {
+        let value = serde_json::to_string(&value)
+            .map_err(|e| TaskError::Serialization(format!("Failed to serialize metadata: {}", e)))?;
+        let mut conn = self.get_conn().await?;
+
+        let replaced: i64 = redis::Script::new(SET_METADATA_SCRIPT)
+            .key(self.metadata_key(key))
+            .arg(expected.unwrap_or(0))
+            .arg(value)
+            .arg(if expire { self.get_ttl_seconds(None) } else { 0 })
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Failed to set metadata: {}", e)))?;
+
+        Ok(replaced == 1)
+    }
+
+    async fn delete_metadata(&self, key: &str) -> Result<(), TaskError> {
+        let mut conn = self.get_conn().await?;
+        conn.del::<_, ()>(self.metadata_key(key))
+            .await
+            .map_err(|e| TaskError::Backend(format!("Failed to delete metadata: {}", e)))
+    }
 }
 
 // Make backend cloneable by cloning the pool
@@ -570,6 +651,25 @@ mod tests {
         assert!(retrieved.is_none());
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn test_metadata() {
+        let config = RedisBackendConfig::default();
+        let backend = RedisBackend::new(config).await.unwrap();
+        let key = format!("workflow:{}", TaskId::new());
+
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 1}), None, true).await.unwrap());
+        assert!(!backend.set_metadata(&key, serde_json::json!({"step": 9}), None, true).await.unwrap());
+        assert!(backend.set_metadata(&key, serde_json::json!({"step": 2}), Some(1), true).await.unwrap());
+
+        let meta = backend.get_metadata(&key).await.unwrap().unwrap();
+        assert_eq!(meta.value, serde_json::json!({"step": 2}));
+        assert_eq!(meta.version, 2);
+
+        backend.delete_metadata(&key).await.unwrap();
+        assert!(backend.get_metadata(&key).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_health_check() {
@@ -22,6 +22,9 @@ use super::nats::{NatsBroker, NatsBrokerConfig};
 #[cfg(feature = "pubsub")]
 use super::pubsub::{PubSubPullBroker, PubSubPullConfig};
 
+#[cfg(feature = "kv")]
+use super::kv::{KvBroker, KvBrokerConfig};
+
 /// Unified broker configuration enum.
 ///
 /// Supports runtime selection of broker implementation based on
@@ -35,6 +38,10 @@ pub enum BrokerConfig {
     /// Google Cloud Pub/Sub pull broker
     #[cfg(feature = "pubsub")]
     PubSub(PubSubPullConfig),
+
+    /// ouroboros-kv-server broker
+    #[cfg(feature = "kv")]
+    Kv(KvBrokerConfig),
 }
 
 impl BrokerConfig {
@@ -43,6 +50,7 @@ impl BrokerConfig {
     /// Checks `BROKER_TYPE` environment variable:
     /// - `nats` -> Uses NATS_URL, etc.
     /// - `pubsub` -> Uses PUBSUB_PROJECT_ID, PUBSUB_TOPIC, PUBSUB_SUBSCRIPTION
+    /// - `kv` -> Uses KV_ADDR, KV_KEY_PREFIX
     ///
     /// If `BROKER_TYPE` is not set, defaults to NATS if available.
     pub fn from_env() -> Result<Self, TaskError> {
@@ -81,6 +89,20 @@ impl BrokerConfig {
                 }))
             }
 
+            #[cfg(feature = "kv")]
+            "kv" => {
+                let addr = std::env::var("KV_ADDR")
+                    .unwrap_or_else(|_| "127.0.0.1:6380".to_string());
+                let key_prefix = std::env::var("KV_KEY_PREFIX")
+                    .unwrap_or_else(|_| "ouroboros-tasks".to_string());
+
+                Ok(BrokerConfig::Kv(KvBrokerConfig {
+                    pool: ouroboros_kv_client::PoolConfig::new(addr),
+                    key_prefix,
+                    ..Default::default()
+                }))
+            }
+
             other => Err(TaskError::Configuration(format!(
                 "Unknown broker type: '{}'. Available types: {}",
                 other,
@@ -100,6 +122,9 @@ impl BrokerConfig {
         #[cfg(feature = "pubsub")]
         types.push("pubsub");
 
+        #[cfg(feature = "kv")]
+        types.push("kv");
+
         types
     }
 
@@ -111,6 +136,9 @@ impl BrokerConfig {
 
             #[cfg(feature = "pubsub")]
             BrokerConfig::PubSub(_) => "pubsub",
+
+            #[cfg(feature = "kv")]
+            BrokerConfig::Kv(_) => "kv",
         }
     }
 
@@ -119,7 +147,7 @@ impl BrokerConfig {
     /// Returns a `BrokerInstance` enum that wraps the concrete broker type.
     /// Since `PullBroker` is not object-safe (due to generic methods),
     /// we return an enum instead of a trait object.
-    #[cfg(any(feature = "nats", feature = "pubsub"))]
+    #[cfg(any(feature = "nats", feature = "pubsub", feature = "kv"))]
     pub fn into_broker(self) -> BrokerInstance {
         match self {
             #[cfg(feature = "nats")]
@@ -131,6 +159,11 @@ impl BrokerConfig {
             BrokerConfig::PubSub(config) => {
                 BrokerInstance::PubSub(Box::new(PubSubPullBroker::new(config)))
             }
+
+            #[cfg(feature = "kv")]
+            BrokerConfig::Kv(config) => {
+                BrokerInstance::Kv(Box::new(KvBroker::new(config)))
+            }
         }
     }
 }
@@ -141,13 +174,16 @@ impl BrokerConfig {
 /// we use an enum to hold concrete broker types.
 ///
 /// Large variants are boxed to reduce total enum size.
-#[cfg(any(feature = "nats", feature = "pubsub"))]
+#[cfg(any(feature = "nats", feature = "pubsub", feature = "kv"))]
 pub enum BrokerInstance {
     #[cfg(feature = "nats")]
     Nats(Box<NatsBroker>),
 
     #[cfg(feature = "pubsub")]
     PubSub(Box<PubSubPullBroker>),
+
+    #[cfg(feature = "kv")]
+    Kv(Box<KvBroker>),
 }
 
 #[cfg(test)]
@@ -164,6 +200,9 @@ mod tests {
 
         #[cfg(feature = "pubsub")]
         assert!(types.contains(&"pubsub"));
+
+        #[cfg(feature = "kv")]
+        assert!(types.contains(&"kv"));
     }
 
     #[test]
@@ -179,6 +218,12 @@ mod tests {
             let config = BrokerConfig::PubSub(PubSubPullConfig::default());
             assert_eq!(config.broker_type(), "pubsub");
         }
+
+        #[cfg(feature = "kv")]
+        {
+            let config = BrokerConfig::Kv(KvBrokerConfig::default());
+            assert_eq!(config.broker_type(), "kv");
+        }
     }
 
     #[test]
@@ -218,7 +263,7 @@ mod tests {
                     assert_eq!(nats_config.url, "nats://example.com:4222");
                     assert_eq!(nats_config.stream_name, "MY_STREAM");
                 }
-                #[cfg(feature = "pubsub")]
+                #[cfg(any(feature = "pubsub", feature = "kv"))]
                 _ => panic!("Expected NATS config"),
             }
 
@@ -260,7 +305,7 @@ mod tests {
                     assert_eq!(pubsub_config.topic_name, "my-topic");
                     assert_eq!(pubsub_config.subscription_name, "my-sub");
                 }
-                #[cfg(feature = "nats")]
+                #[cfg(any(feature = "nats", feature = "kv"))]
                 _ => panic!("Expected Pub/Sub config"),
             }
 
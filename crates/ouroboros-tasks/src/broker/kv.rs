@@ -0,0 +1,642 @@
+//! ouroboros-kv-server broker implementation
+//!
+//! Each queue is a sorted set of message ids scored by the time (ms since the
+//! Unix epoch) the message may next be delivered, so delayed messages are just
+//! scored in the future. Message bodies live under their own keys.
+//!
+//! A consumer claims a due message by taking its lease (a kv lock that expires
+//! after `ack_wait`) and then moving the message's score past the lease, so
+//! other consumers skip it. Ack releases the lease and removes the message,
+//! and fails if the lease has already run out; a consumer that dies without
+//! acking loses the lease, and the message falls due again.
+//!
+//! Priorities are emulated with sub-queues (see `PriorityBands`): a message
+//! goes to the sub-queue of its priority band, and consumers claim from the
+//! highest band that has a due message.
+//!
+//! Keys, under `key_prefix`:
+//! - `{prefix}:ready:{queue}`: sorted set of message ids (`{queue}.p{band}`
+//!   for priority bands above 0)
+//! - `{prefix}:msg:{id}`: message body and delivery count (JSON)
+//! - `{prefix}:lease:{id}`: lease held while a consumer handles the message
+
+use async_trait::async_trait;
+use chrono::Utc;
+use ouroboros_kv_client::{ClientError, KvPool, KvValue, PoolConfig, PooledClient};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    broker::{BrokerMessage, Delivery, MessageHandler, PoisonMessage, PriorityBands, SubscriptionHandle},
+    Broker, BrokerCapabilities, DelayedBroker, DeliveryModel, PullBroker, QueueInspector, TaskError,
+    TaskMessage,
+};
+
+/// Due messages examined per claim attempt
+const CLAIM_BATCH: i64 = 16;
+
+/// ouroboros-kv-server broker configuration
+#[derive(Debug, Clone)]
+pub struct KvBrokerConfig {
+    /// Connection pool settings (address, namespace, authentication, TLS)
+    pub pool: PoolConfig,
+    /// Key prefix for all broker data
+    pub key_prefix: String,
+    /// Unacknowledged messages are redelivered after this long
+    pub ack_wait: Duration,
+    /// Maximum delivery attempts; the message is then handed to the
+    /// subscriber's `handle_poison` and dropped once that succeeds
+    pub max_deliver: u32,
+    /// Messages handled concurrently by each subscription
+    pub prefetch: usize,
+    /// How often an idle subscription checks for due messages
+    pub poll_interval: Duration,
+    /// Number of priority sub-queues per queue (1 disables priorities)
+    pub priority_levels: u8,
+}
+
+impl Default for KvBrokerConfig {
+    fn default() -> Self {
+        Self {
+            pool: PoolConfig::new("127.0.0.1:6380"),
+            key_prefix: "ouroboros-tasks".to_string(),
+            ack_wait: Duration::from_secs(30),
+            max_deliver: 5,
+            prefetch: 10,
+            poll_interval: Duration::from_millis(100),
+            priority_levels: 3,
+        }
+    }
+}
+
+/// Stored message
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    payload: TaskMessage,
+    headers: HashMap<String, String>,
+    deliveries: u32,
+}
+
+/// Delivery tag: `{id}:{lease owner}:{sub-queue}`
+struct DeliveryTag<'a> {
+    id: &'a str,
+    owner: &'a str,
+    queue: &'a str,
+}
+
+impl<'a> DeliveryTag<'a> {
+    fn parse(tag: &'a str) -> Result<Self, TaskError> {
+        let mut parts = tag.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(id), Some(owner), Some(queue)) => Ok(Self { id, owner, queue }),
+            _ => Err(TaskError::Broker(format!("Invalid delivery tag: {}", tag))),
+        }
+    }
+}
+
+fn kv_error(e: ClientError) -> TaskError {
+    TaskError::Broker(format!("KV error: {}", e))
+}
+
+fn lease_expired(delivery_tag: &str) -> TaskError {
+    TaskError::Broker(format!(
+        "Lease for {} expired, the message was already redelivered",
+        delivery_tag
+    ))
+}
+
+fn now_ms() -> f64 {
+    Utc::now().timestamp_millis() as f64
+}
+
+/// Connection pool and key layout shared with subscription tasks
+struct Queues {
+    pool: Arc<KvPool>,
+    config: KvBrokerConfig,
+}
+
+impl Queues {
+    fn bands(&self) -> PriorityBands {
+        PriorityBands::new(self.config.priority_levels)
+    }
+
+    fn ready_key(&self, queue: &str) -> String {
+        format!("{}:ready:{}", self.config.key_prefix, queue)
+    }
+
+    fn message_key(&self, id: &str) -> String {
+        format!("{}:msg:{}", self.config.key_prefix, id)
+    }
+
+    fn lease_key(&self, id: &str) -> String {
+        format!("{}:lease:{}", self.config.key_prefix, id)
+    }
+
+    async fn conn(&self) -> Result<PooledClient, TaskError> {
+        self.pool.acquire().await.map_err(kv_error)
+    }
+
+    async fn push(&self, queue: &str, message: TaskMessage, delay: Duration) -> Result<(), TaskError> {
+        let mut headers = HashMap::new();
+        headers.insert("task-id".to_string(), message.id.to_string());
+        headers.insert("task-name".to_string(), message.task_name.clone());
+        if let Some(ref correlation_id) = message.correlation_id {
+            headers.insert("correlation-id".to_string(), correlation_id.clone());
+        }
+
+        tracing::debug!(
+            "Publishing message to queue '{}': task_id={}, task_name={}, priority={}, delay={:?}",
+            queue,
+            message.id,
+            message.task_name,
+            message.priority,
+            delay
+        );
+
+        let bands = self.bands();
+        let sub_queue = bands.sub_queue(queue, bands.band(message.priority));
+
+        let envelope = Envelope {
+            payload: message,
+            headers,
+            deliveries: 0,
+        };
+        let body = serde_json::to_string(&envelope)
+            .map_err(|e| TaskError::Serialization(format!("Failed to serialize message: {}", e)))?;
+
+        // Time-ordered ids keep messages due in the same millisecond in publish order
+        let id = uuid::Uuid::now_v7().simple().to_string();
+        let due = now_ms() + delay.as_millis() as f64;
+
+        // Body first: a claimed id must always have one
+        let mut conn = self.conn().await?;
+        let client = conn.client();
+        client
+            .set(&self.message_key(&id), KvValue::String(body), None)
+            .await
+            .map_err(kv_error)?;
+        client
+            .zadd(&self.ready_key(&sub_queue), &[(due, id.as_str())])
+            .await
+            .map_err(kv_error)?;
+        Ok(())
+    }
+
+    /// Claim the next due message of a queue, highest priority band first
+    async fn take(&self, queue: &str) -> Result<Option<Delivery>, TaskError> {
+        for sub_queue in self.bands().sub_queues(queue) {
+            if let Some(delivery) = self.claim(queue, &sub_queue).await? {
+                return Ok(Some(delivery));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Claim the next due message of one priority sub-queue
+    async fn claim(&self, queue: &str, sub_queue: &str) -> Result<Option<Delivery>, TaskError> {
+        let ready_key = self.ready_key(sub_queue);
+        let mut conn = self.conn().await?;
+        let client = conn.client();
+
+        let now = now_ms();
+        let candidates = client.zrange(&ready_key, 0, CLAIM_BATCH - 1, false).await.map_err(kv_error)?;
+        for (id, due) in candidates {
+            if due > now {
+                break;
+            }
+
+            let owner = uuid::Uuid::now_v7().simple().to_string();
+            let lease_key = self.lease_key(&id);
+            if !client.lock(&lease_key, &owner, self.config.ack_wait).await.map_err(kv_error)? {
+                continue; // Another consumer has it
+            }
+
+            let message_key = self.message_key(&id);
+            let body = match client.get(&message_key).await.map_err(kv_error)? {
+                Some(KvValue::String(body)) => body,
+                // Acked by a consumer that crashed before removing it
+                None => {
+                    client.zrem(&ready_key, &[id.as_str()]).await.map_err(kv_error)?;
+                    client.unlock(&lease_key, &owner).await.map_err(kv_error)?;
+                    continue;
+                }
+                Some(other) => format!("{:?}", other),
+            };
+
+            // Falls due again when the lease runs out, unless acked first
+            let redeliver_at = now + self.config.ack_wait.as_millis() as f64;
+            client.zadd(&ready_key, &[(redeliver_at, id.as_str())]).await.map_err(kv_error)?;
+
+            let delivery_tag = format!("{}:{}:{}", id, owner, sub_queue);
+            let mut poison = match serde_json::from_str::<Envelope>(&body) {
+                Ok(envelope) if envelope.deliveries >= self.config.max_deliver => PoisonMessage {
+                    queue: queue.to_string(),
+                    error: format!("Not acknowledged after {} deliveries", envelope.deliveries),
+                    deliveries: envelope.deliveries,
+                    message: Some(envelope.payload),
+                    payload: Vec::new(),
+                },
+                Ok(mut envelope) => {
+                    envelope.deliveries += 1;
+                    let body = serde_json::to_string(&envelope).map_err(|e| {
+                        TaskError::Serialization(format!("Failed to serialize message: {}", e))
+                    })?;
+                    client.set(&message_key, KvValue::String(body), None).await.map_err(kv_error)?;
+
+                    return Ok(Some(Delivery::Message(BrokerMessage {
+                        delivery_tag,
+                        payload: envelope.payload,
+                        headers: envelope.headers,
+                        timestamp: Utc::now(),
+                        redelivered: envelope.deliveries > 1,
+                    })));
+                }
+                Err(e) => PoisonMessage {
+                    queue: queue.to_string(),
+                    error: format!("Failed to parse message {}: {}", id, e),
+                    deliveries: 0,
+                    message: None,
+                    payload: Vec::new(),
+                },
+            };
+
+            // Still leased: only removed once the poison message has been
+            // handled, so a failed dead-letter write sees it again
+            poison.payload = body.into_bytes();
+            return Ok(Some(Delivery::Poison(poison, Some(delivery_tag))));
+        }
+
+        Ok(None)
+    }
+
+    /// Hand a poison message to its handler, then remove it
+    ///
+    /// If the handler fails the message stays and is given to it again once
+    /// the lease runs out.
+    async fn discard<H: MessageHandler + ?Sized>(
+        &self,
+        handler: &H,
+        poison: PoisonMessage,
+        delivery_tag: Option<String>,
+    ) {
+        if let Err(e) = handler.handle_poison(poison).await {
+            tracing::error!("Poison message handler error: {}", e);
+            return;
+        }
+        if let Some(delivery_tag) = delivery_tag {
+            if let Err(e) = self.ack(&delivery_tag).await {
+                tracing::warn!("Failed to remove poison message: {}", e);
+            }
+        }
+    }
+
+    async fn ack(&self, delivery_tag: &str) -> Result<(), TaskError> {
+        let tag = DeliveryTag::parse(delivery_tag)?;
+        let mut conn = self.conn().await?;
+        let client = conn.client();
+        // Once the lease has run out the message belongs to whoever claimed it
+        // next; renewing it checks ownership and keeps it through the removal
+        let lease_key = self.lease_key(tag.id);
+        if !client.extend_lock(&lease_key, tag.owner, self.config.ack_wait).await.map_err(kv_error)? {
+            return Err(lease_expired(delivery_tag));
+        }
+        client.zrem(&self.ready_key(tag.queue), &[tag.id]).await.map_err(kv_error)?;
+        client.delete(&self.message_key(tag.id)).await.map_err(kv_error)?;
+        client.unlock(&lease_key, tag.owner).await.map_err(kv_error)?;
+        Ok(())
+    }
+
+    async fn nack(&self, delivery_tag: &str, requeue: bool) -> Result<(), TaskError> {
+        if !requeue {
+            return self.ack(delivery_tag).await;
+        }
+
+        let tag = DeliveryTag::parse(delivery_tag)?;
+        let mut conn = self.conn().await?;
+        let client = conn.client();
+        let lease_key = self.lease_key(tag.id);
+        if !client.unlock(&lease_key, tag.owner).await.map_err(kv_error)? {
+            return Err(lease_expired(delivery_tag));
+        }
+        client
+            .zadd(&self.ready_key(tag.queue), &[(now_ms(), tag.id)])
+            .await
+            .map_err(kv_error)?;
+        Ok(())
+    }
+}
+
+/// ouroboros-kv-server broker implementation
+pub struct KvBroker {
+    config: KvBrokerConfig,
+    queues: RwLock<Option<Arc<Queues>>>,
+}
+
+impl KvBroker {
+    /// Create a new kv broker with the given configuration
+    pub fn new(config: KvBrokerConfig) -> Self {
+        Self {
+            config,
+            queues: RwLock::new(None),
+        }
+    }
+
+    async fn queues(&self) -> Result<Arc<Queues>, TaskError> {
+        self.queues.read().await.clone().ok_or(TaskError::NotConnected)
+    }
+
+    /// Claim the next due message, to be settled with `ack` or `nack`
+    ///
+    /// Messages that cannot be decoded or are over the delivery limit are
+    /// logged and dropped.
+    pub async fn fetch(&self, queue: &str) -> Result<Option<BrokerMessage>, TaskError> {
+        let queues = self.queues().await?;
+        loop {
+            match queues.take(queue).await? {
+                Some(Delivery::Message(message)) => return Ok(Some(message)),
+                Some(Delivery::Poison(poison, delivery_tag)) => {
+                    poison.log_discarded();
+                    if let Some(delivery_tag) = delivery_tag {
+                        queues.ack(&delivery_tag).await?;
+                    }
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Number of messages in a queue, including delayed and unacknowledged ones
+    pub async fn queue_len(&self, queue: &str) -> Result<usize, TaskError> {
+        let queues = self.queues().await?;
+        let mut conn = queues.conn().await?;
+        let mut len = 0;
+        for sub_queue in queues.bands().sub_queues(queue) {
+            len += conn.client().zcard(&queues.ready_key(&sub_queue)).await.map_err(kv_error)?;
+        }
+        Ok(len)
+    }
+}
+
+#[async_trait]
+impl QueueInspector for KvBroker {
+    async fn queue_depth(&self, queue: &str) -> Result<usize, TaskError> {
+        self.queue_len(queue).await
+    }
+}
+
+#[async_trait]
+impl Broker for KvBroker {
+    async fn connect(&self) -> Result<(), TaskError> {
+        tracing::info!("Connecting to KV server at {}", self.config.pool.addr);
+
+        let pool = KvPool::connect(self.config.pool.clone())
+            .await
+            .map_err(|e| TaskError::Broker(format!("Failed to connect to KV server: {}", e)))?;
+
+        *self.queues.write().await = Some(Arc::new(Queues {
+            pool,
+            config: self.config.clone(),
+        }));
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TaskError> {
+        tracing::info!("Disconnecting from KV server");
+        *self.queues.write().await = None;
+        Ok(())
+    }
+
+    async fn publish(&self, queue: &str, message: TaskMessage) -> Result<(), TaskError> {
+        // Messages with a future ETA wait in the queue until then
+        let delay = message
+            .eta
+            .and_then(|eta| (eta - Utc::now()).to_std().ok())
+            .unwrap_or(Duration::ZERO);
+        self.queues().await?.push(queue, message, delay).await
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        let queues = self.queues().await?;
+        let mut conn = queues.conn().await?;
+        conn.client()
+            .ping()
+            .await
+            .map_err(|e| TaskError::Broker(format!("Health check failed: {}", e)))?;
+        Ok(())
+    }
+
+    fn delivery_model(&self) -> DeliveryModel {
+        DeliveryModel::Pull
+    }
+
+    fn capabilities(&self) -> BrokerCapabilities {
+        BrokerCapabilities {
+            delayed_tasks: true,
+            dead_letter: false,
+            priority: true,
+            batching: false,
+            max_delay: None,
+        }
+    }
+}
+
+#[async_trait]
+impl PullBroker for KvBroker {
+    async fn subscribe<H: MessageHandler + 'static>(
+        &self,
+        queue: &str,
+        handler: Arc<H>,
+    ) -> Result<SubscriptionHandle, TaskError> {
+        let queues = self.queues().await?;
+        let cancel_token = CancellationToken::new();
+        let cancelled = cancel_token.clone();
+        let permits = Arc::new(Semaphore::new(self.config.prefetch.max(1)));
+        let poll_interval = self.config.poll_interval;
+        let queue_owned = queue.to_string();
+
+        tokio::spawn(async move {
+            tracing::info!("Starting message loop for queue: {}", queue_owned);
+
+            loop {
+                let permit = tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    permit = permits.clone().acquire_owned() => permit.expect("semaphore is never closed"),
+                };
+
+                let wait = match queues.take(&queue_owned).await {
+                    Ok(Some(Delivery::Poison(poison, delivery_tag))) => {
+                        drop(permit);
+                        queues.discard(handler.as_ref(), poison, delivery_tag).await;
+                        continue;
+                    }
+                    Ok(Some(Delivery::Message(message))) => {
+                        let queues = queues.clone();
+                        let handler = handler.clone();
+                        tokio::spawn(async move {
+                            let delivery_tag = message.delivery_tag.clone();
+                            let settled = match handler.handle(message).await {
+                                Ok(()) => queues.ack(&delivery_tag).await,
+                                Err(e) => {
+                                    tracing::error!("Handler error: {}", e);
+                                    queues.nack(&delivery_tag, true).await
+                                }
+                            };
+                            if let Err(e) = settled {
+                                tracing::warn!("Failed to settle message: {}", e);
+                            }
+                            drop(permit);
+                        });
+                        continue;
+                    }
+                    Ok(None) => poll_interval,
+                    Err(e) => {
+                        tracing::error!("Error fetching from queue '{}': {}", queue_owned, e);
+                        Duration::from_secs(1)
+                    }
+                };
+
+                drop(permit);
+                tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    _ = tokio::time::sleep(wait) => {}
+                }
+            }
+
+            tracing::info!("Message loop ended for queue: {}", queue_owned);
+        });
+
+        Ok(SubscriptionHandle::new(queue.to_string(), cancel_token))
+    }
+
+    async fn ack(&self, delivery_tag: &str) -> Result<(), TaskError> {
+        self.queues().await?.ack(delivery_tag).await
+    }
+
+    async fn nack(&self, delivery_tag: &str, requeue: bool) -> Result<(), TaskError> {
+        self.queues().await?.nack(delivery_tag, requeue).await
+    }
+}
+
+#[async_trait]
+impl DelayedBroker for KvBroker {
+    async fn publish_delayed(
+        &self,
+        queue: &str,
+        message: TaskMessage,
+        delay: Duration,
+    ) -> Result<(), TaskError> {
+        self.queues().await?.push(queue, message, delay).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivery_tag() {
+        let tag = DeliveryTag::parse("0190a1:0190b2:emails:high").unwrap();
+        assert_eq!(tag.id, "0190a1");
+        assert_eq!(tag.owner, "0190b2");
+        assert_eq!(tag.queue, "emails:high");
+        assert!(DeliveryTag::parse("0190a1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_not_connected() {
+        let broker = KvBroker::new(KvBrokerConfig::default());
+        let message = TaskMessage::new("test_task", serde_json::json!([]));
+        assert!(matches!(broker.publish("test", message).await, Err(TaskError::NotConnected)));
+        assert!(matches!(broker.fetch("test").await, Err(TaskError::NotConnected)));
+    }
+
+    // Integration tests requiring a kv-server
+    // Run: cargo run -p ouroboros-kv-server
+    #[tokio::test]
+    #[ignore]
+    async fn test_ack_nack() {
+        let broker = KvBroker::new(KvBrokerConfig {
+            key_prefix: format!("test-broker-{}", uuid::Uuid::now_v7().simple()),
+            ..Default::default()
+        });
+        broker.connect().await.unwrap();
+
+        let message = TaskMessage::new("test_task", serde_json::json!([1, 2, 3]));
+        broker.publish("test", message.clone()).await.unwrap();
+        broker
+            .publish_delayed("test", TaskMessage::new("later", serde_json::json!([])), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(broker.queue_len("test").await.unwrap(), 2);
+
+        let delivered = broker.fetch("test").await.unwrap().unwrap();
+        assert_eq!(delivered.payload.id, message.id);
+        assert!(!delivered.redelivered);
+        // The other message is delayed, this one is leased
+        assert!(broker.fetch("test").await.unwrap().is_none());
+
+        broker.nack(&delivered.delivery_tag, true).await.unwrap();
+        let redelivered = broker.fetch("test").await.unwrap().unwrap();
+        assert_eq!(redelivered.payload.id, message.id);
+        assert!(redelivered.redelivered);
+
+        broker.ack(&redelivered.delivery_tag).await.unwrap();
+        assert_eq!(broker.queue_len("test").await.unwrap(), 1);
+        broker.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_lease_expiry() {
+        let broker = KvBroker::new(KvBrokerConfig {
+            key_prefix: format!("test-broker-{}", uuid::Uuid::now_v7().simple()),
+            ack_wait: Duration::from_millis(200),
+            ..Default::default()
+        });
+        broker.connect().await.unwrap();
+
+        broker.publish("test", TaskMessage::new("test_task", serde_json::json!([]))).await.unwrap();
+        let first = broker.fetch("test").await.unwrap().unwrap();
+
+        // Never acked: delivered again once the lease runs out
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let second = broker.fetch("test").await.unwrap().unwrap();
+        assert_eq!(second.payload.id, first.payload.id);
+        assert!(broker.nack(&first.delivery_tag, true).await.is_err());
+        // Nor may the first consumer ack the message out from under the second
+        assert!(broker.ack(&first.delivery_tag).await.is_err());
+        assert_eq!(broker.queue_len("test").await.unwrap(), 1);
+        broker.ack(&second.delivery_tag).await.unwrap();
+        assert_eq!(broker.queue_len("test").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_priority() {
+        let broker = KvBroker::new(KvBrokerConfig {
+            key_prefix: format!("test-broker-{}", uuid::Uuid::now_v7().simple()),
+            ..Default::default()
+        });
+        broker.connect().await.unwrap();
+
+        let low = TaskMessage::new("test_task", serde_json::json!([]));
+        let high = TaskMessage::new("test_task", serde_json::json!([])).with_priority(9);
+        let normal = TaskMessage::new("test_task", serde_json::json!([])).with_priority(5);
+        for message in [&low, &high, &normal] {
+            broker.publish("test", message.clone()).await.unwrap();
+        }
+        assert_eq!(broker.queue_len("test").await.unwrap(), 3);
+
+        let mut order = Vec::new();
+        while let Some(delivered) = broker.fetch("test").await.unwrap() {
+            broker.ack(&delivered.delivery_tag).await.unwrap();
+            order.push(delivered.payload.id);
+        }
+        assert_eq!(order, vec![high.id, normal.id, low.id]);
+        broker.disconnect().await.unwrap();
+    }
+}
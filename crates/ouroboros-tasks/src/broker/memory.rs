@@ -0,0 +1,547 @@
+//! In-process broker implementation
+//!
+//! Queues live in memory and are shared by every clone of the broker, so
+//! producers and workers in the same process need no outside infrastructure.
+//! Useful for tests and single-binary deployments; nothing survives a restart.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Notify, Semaphore};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    broker::{BrokerMessage, Delivery, MessageHandler, PoisonMessage, SubscriptionHandle},
+    Broker, BrokerCapabilities, DelayedBroker, DeliveryModel, PullBroker, QueueInspector, TaskError,
+    TaskMessage,
+};
+
+/// How long an idle subscription sleeps when nothing is scheduled
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+
+/// In-memory broker configuration
+#[derive(Debug, Clone)]
+pub struct InMemoryBrokerConfig {
+    /// Unacknowledged messages are redelivered after this long
+    pub ack_wait: Duration,
+    /// Maximum delivery attempts; the message is then handed to the
+    /// subscriber's `handle_poison` and dropped
+    pub max_deliver: u32,
+    /// Messages handled concurrently by each subscription
+    pub prefetch: usize,
+}
+
+impl Default for InMemoryBrokerConfig {
+    fn default() -> Self {
+        Self {
+            ack_wait: Duration::from_secs(30),
+            max_deliver: 5,
+            prefetch: 10,
+        }
+    }
+}
+
+/// A queued message and its delivery count
+struct Envelope {
+    payload: TaskMessage,
+    headers: HashMap<String, String>,
+    deliveries: u32,
+}
+
+/// A delivered message awaiting ack or nack
+struct InFlight {
+    queue: String,
+    envelope: Envelope,
+    deadline: Instant,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Keyed by priority (highest first), then publish order
+    ready: BTreeMap<(Reverse<u8>, u64), Envelope>,
+    /// Keyed by due time, then publish order
+    delayed: BTreeMap<(Instant, u64), Envelope>,
+}
+
+#[derive(Default)]
+struct State {
+    queues: HashMap<String, QueueState>,
+    in_flight: HashMap<String, InFlight>,
+}
+
+struct Inner {
+    config: InMemoryBrokerConfig,
+    state: Mutex<State>,
+    /// Woken whenever a message becomes ready
+    notify: Notify,
+    sequence: AtomicU64,
+}
+
+impl Inner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // A panic while holding the lock cannot leave the queues half-updated
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, queue: &str, envelope: Envelope, due: Option<Instant>) {
+        let mut state = self.lock();
+        let queue_state = state.queues.entry(queue.to_string()).or_default();
+        match due {
+            Some(due) if due > Instant::now() => {
+                let seq = self.sequence.fetch_add(1, Ordering::Relaxed);
+                queue_state.delayed.insert((due, seq), envelope);
+            }
+            _ => {
+                let seq = self.sequence.fetch_add(1, Ordering::Relaxed);
+                queue_state.ready.insert((Reverse(envelope.payload.priority), seq), envelope);
+            }
+        }
+        drop(state);
+        self.notify.notify_waiters();
+    }
+
+    /// Deliver the next ready message of a queue
+    fn take(&self, queue: &str) -> Option<Delivery> {
+        let now = Instant::now();
+        let mut state = self.lock();
+
+        // Redeliver messages whose ack deadline passed
+        let expired: Vec<String> = state
+            .in_flight
+            .iter()
+            .filter(|(_, in_flight)| in_flight.queue == queue && in_flight.deadline <= now)
+            .map(|(tag, _)| tag.clone())
+            .collect();
+        for tag in expired {
+            if let Some(in_flight) = state.in_flight.remove(&tag) {
+                tracing::warn!("Message {} on queue '{}' was not acknowledged in time, redelivering", tag, queue);
+                let seq = self.sequence.fetch_add(1, Ordering::Relaxed);
+                let envelope = in_flight.envelope;
+                state
+                    .queues
+                    .entry(in_flight.queue)
+                    .or_default()
+                    .ready
+                    .insert((Reverse(envelope.payload.priority), seq), envelope);
+            }
+        }
+
+        let queue_state = state.queues.get_mut(queue)?;
+        while let Some(entry) = queue_state.delayed.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let ((_, seq), envelope) = entry.remove_entry();
+            queue_state.ready.insert((Reverse(envelope.payload.priority), seq), envelope);
+        }
+
+        let (_, mut envelope) = queue_state.ready.pop_first()?;
+        if envelope.deliveries >= self.config.max_deliver {
+            return Some(Delivery::Poison(
+                PoisonMessage {
+                    queue: queue.to_string(),
+                    payload: serde_json::to_vec(&envelope.payload).unwrap_or_default(),
+                    message: Some(envelope.payload),
+                    deliveries: envelope.deliveries,
+                    error: format!("Not acknowledged after {} deliveries", envelope.deliveries),
+                },
+                None,
+            ));
+        }
+        envelope.deliveries += 1;
+
+        let delivery_tag = self.sequence.fetch_add(1, Ordering::Relaxed).to_string();
+        let message = BrokerMessage {
+            delivery_tag: delivery_tag.clone(),
+            payload: envelope.payload.clone(),
+            headers: envelope.headers.clone(),
+            timestamp: Utc::now(),
+            redelivered: envelope.deliveries > 1,
+        };
+        state.in_flight.insert(
+            delivery_tag,
+            InFlight {
+                queue: queue.to_string(),
+                envelope,
+                deadline: now + self.config.ack_wait,
+            },
+        );
+        Some(Delivery::Message(message))
+    }
+
+    /// When the next delayed or unacknowledged message of a queue falls due
+    fn next_due(&self, queue: &str) -> Option<Instant> {
+        let state = self.lock();
+        let delayed = state
+            .queues
+            .get(queue)
+            .and_then(|q| q.delayed.keys().next().map(|(due, _)| *due));
+        let in_flight = state
+            .in_flight
+            .values()
+            .filter(|in_flight| in_flight.queue == queue)
+            .map(|in_flight| in_flight.deadline)
+            .min();
+        delayed.into_iter().chain(in_flight).min()
+    }
+
+    fn settle(&self, delivery_tag: &str, requeue: bool) -> Result<(), TaskError> {
+        let in_flight = self
+            .lock()
+            .in_flight
+            .remove(delivery_tag)
+            .ok_or_else(|| TaskError::Broker(format!("Unknown delivery tag: {}", delivery_tag)))?;
+        if requeue {
+            self.push(&in_flight.queue, in_flight.envelope, None);
+        }
+        Ok(())
+    }
+}
+
+/// In-memory broker implementation
+///
+/// Clones share the same queues.
+#[derive(Clone)]
+pub struct InMemoryBroker {
+    inner: Arc<Inner>,
+}
+
+impl InMemoryBroker {
+    /// Create a new in-memory broker with the given configuration
+    pub fn new(config: InMemoryBrokerConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                state: Mutex::new(State::default()),
+                notify: Notify::new(),
+                sequence: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    /// Take the next ready message, to be settled with `ack` or `nack`
+    ///
+    /// Messages over the delivery limit are logged and dropped.
+    pub async fn fetch(&self, queue: &str) -> Result<Option<BrokerMessage>, TaskError> {
+        loop {
+            match self.inner.take(queue) {
+                Some(Delivery::Message(message)) => return Ok(Some(message)),
+                Some(Delivery::Poison(poison, _)) => poison.log_discarded(),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Number of messages waiting in a queue, including delayed ones
+    pub fn queue_len(&self, queue: &str) -> usize {
+        self.inner
+            .lock()
+            .queues
+            .get(queue)
+            .map(|q| q.ready.len() + q.delayed.len())
+            .unwrap_or(0)
+    }
+
+    /// Number of delivered messages awaiting ack or nack
+    pub fn in_flight(&self) -> usize {
+        self.inner.lock().in_flight.len()
+    }
+}
+
+#[async_trait]
+impl QueueInspector for InMemoryBroker {
+    async fn queue_depth(&self, queue: &str) -> Result<usize, TaskError> {
+        Ok(self.queue_len(queue))
+    }
+}
+
+impl Default for InMemoryBroker {
+    fn default() -> Self {
+        Self::new(InMemoryBrokerConfig::default())
+    }
+}
+
+fn envelope(message: TaskMessage) -> Envelope {
+    let mut headers = HashMap::new();
+    headers.insert("task-id".to_string(), message.id.to_string());
+    headers.insert("task-name".to_string(), message.task_name.clone());
+    if let Some(ref correlation_id) = message.correlation_id {
+        headers.insert("correlation-id".to_string(), correlation_id.clone());
+    }
+    Envelope {
+        payload: message,
+        headers,
+        deliveries: 0,
+    }
+}
+
+#[async_trait]
+impl Broker for InMemoryBroker {
+    async fn connect(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    /// Messages with a future `eta` are held back until then
+    async fn publish(&self, queue: &str, message: TaskMessage) -> Result<(), TaskError> {
+        let due = message
+            .eta
+            .and_then(|eta| (eta - Utc::now()).to_std().ok())
+            .map(|delay| Instant::now() + delay);
+        tracing::debug!(
+            "Publishing message to queue '{}': task_id={}, task_name={}",
+            queue,
+            message.id,
+            message.task_name
+        );
+        self.inner.push(queue, envelope(message), due);
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    fn delivery_model(&self) -> DeliveryModel {
+        DeliveryModel::Pull
+    }
+
+    fn capabilities(&self) -> BrokerCapabilities {
+        BrokerCapabilities {
+            delayed_tasks: true,
+            dead_letter: false,
+            priority: true,
+            batching: false,
+            max_delay: None,
+        }
+    }
+}
+
+#[async_trait]
+impl PullBroker for InMemoryBroker {
+    async fn subscribe<H: MessageHandler + 'static>(
+        &self,
+        queue: &str,
+        handler: Arc<H>,
+    ) -> Result<SubscriptionHandle, TaskError> {
+        let cancel_token = CancellationToken::new();
+        let cancelled = cancel_token.clone();
+        let inner = self.inner.clone();
+        let permits = Arc::new(Semaphore::new(inner.config.prefetch.max(1)));
+        let queue_owned = queue.to_string();
+
+        tokio::spawn(async move {
+            tracing::info!("Starting message loop for queue: {}", queue_owned);
+
+            loop {
+                // Register for wakeups before looking, so a publish in between is not missed
+                let notified = inner.notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                let permit = tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    permit = permits.clone().acquire_owned() => permit.expect("semaphore is never closed"),
+                };
+
+                match inner.take(&queue_owned) {
+                    Some(Delivery::Poison(poison, _)) => {
+                        drop(permit);
+                        if let Err(e) = handler.handle_poison(poison).await {
+                            tracing::error!("Poison message handler error: {}", e);
+                        }
+                    }
+                    Some(Delivery::Message(message)) => {
+                        let inner = inner.clone();
+                        let handler = handler.clone();
+                        tokio::spawn(async move {
+                            let delivery_tag = message.delivery_tag.clone();
+                            let requeue = match handler.handle(message).await {
+                                Ok(()) => false,
+                                Err(e) => {
+                                    tracing::error!("Handler error: {}", e);
+                                    true
+                                }
+                            };
+                            // Fails if the ack deadline passed and the message was redelivered
+                            if let Err(e) = inner.settle(&delivery_tag, requeue) {
+                                tracing::warn!("Failed to settle message: {}", e);
+                            }
+                            drop(permit);
+                        });
+                    }
+                    None => {
+                        drop(permit);
+                        let wait = inner
+                            .next_due(&queue_owned)
+                            .map(|due| due.saturating_duration_since(Instant::now()))
+                            .unwrap_or(IDLE_WAIT)
+                            .min(IDLE_WAIT);
+                        tokio::select! {
+                            _ = cancelled.cancelled() => break,
+                            _ = notified => {}
+                            _ = tokio::time::sleep(wait) => {}
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("Message loop ended for queue: {}", queue_owned);
+        });
+
+        Ok(SubscriptionHandle::new(queue.to_string(), cancel_token))
+    }
+
+    async fn ack(&self, delivery_tag: &str) -> Result<(), TaskError> {
+        self.inner.settle(delivery_tag, false)
+    }
+
+    async fn nack(&self, delivery_tag: &str, requeue: bool) -> Result<(), TaskError> {
+        self.inner.settle(delivery_tag, requeue)
+    }
+}
+
+#[async_trait]
+impl DelayedBroker for InMemoryBroker {
+    async fn publish_delayed(
+        &self,
+        queue: &str,
+        message: TaskMessage,
+        delay: Duration,
+    ) -> Result<(), TaskError> {
+        self.inner.push(queue, envelope(message), Some(Instant::now() + delay));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn message() -> TaskMessage {
+        TaskMessage::new("test_task", serde_json::json!([1, 2, 3]))
+    }
+
+    #[tokio::test]
+    async fn test_ack_nack() {
+        let broker = InMemoryBroker::default();
+        let first = message();
+        broker.publish("test", first.clone()).await.unwrap();
+        broker.publish("test", message()).await.unwrap();
+        assert_eq!(broker.queue_len("test"), 2);
+
+        let delivered = broker.fetch("test").await.unwrap().unwrap();
+        assert_eq!(delivered.payload.id, first.id);
+        assert_eq!(delivered.headers["task-name"], "test_task");
+        assert!(!delivered.redelivered);
+        assert_eq!(broker.in_flight(), 1);
+
+        // Requeued messages go to the back of the queue
+        broker.nack(&delivered.delivery_tag, true).await.unwrap();
+        let second = broker.fetch("test").await.unwrap().unwrap();
+        assert_ne!(second.payload.id, first.id);
+        broker.ack(&second.delivery_tag).await.unwrap();
+
+        let redelivered = broker.fetch("test").await.unwrap().unwrap();
+        assert_eq!(redelivered.payload.id, first.id);
+        assert!(redelivered.redelivered);
+
+        // Not requeued: gone
+        broker.nack(&redelivered.delivery_tag, false).await.unwrap();
+        assert!(broker.fetch("test").await.unwrap().is_none());
+        assert_eq!(broker.in_flight(), 0);
+        assert!(broker.ack(&redelivered.delivery_tag).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_priority() {
+        let broker = InMemoryBroker::default();
+        let low = message();
+        let high = message().with_priority(9);
+        let normal = message().with_priority(4);
+        let normal_later = message().with_priority(4);
+        for msg in [&low, &high, &normal, &normal_later] {
+            broker.publish("test", msg.clone()).await.unwrap();
+        }
+
+        let mut order = Vec::new();
+        while let Some(delivered) = broker.fetch("test").await.unwrap() {
+            broker.ack(&delivered.delivery_tag).await.unwrap();
+            order.push(delivered.payload.id);
+        }
+        assert_eq!(order, vec![high.id, normal.id, normal_later.id, low.id]);
+    }
+
+    #[tokio::test]
+    async fn test_delayed_and_max_deliver() {
+        let broker = InMemoryBroker::new(InMemoryBrokerConfig {
+            ack_wait: Duration::from_millis(20),
+            max_deliver: 2,
+            ..Default::default()
+        });
+
+        broker
+            .publish_delayed("test", message(), Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert!(broker.fetch("test").await.unwrap().is_none());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let first = broker.fetch("test").await.unwrap().unwrap();
+
+        // Not acknowledged in time: delivered once more, then dropped
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let second = broker.fetch("test").await.unwrap().unwrap();
+        assert_eq!(second.payload.id, first.payload.id);
+        assert!(second.redelivered);
+        broker.nack(&second.delivery_tag, true).await.unwrap();
+        assert!(broker.fetch("test").await.unwrap().is_none());
+        assert_eq!(broker.queue_len("test"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        struct FlakyHandler {
+            calls: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl MessageHandler for FlakyHandler {
+            async fn handle(&self, _message: BrokerMessage) -> Result<(), TaskError> {
+                // Every other delivery fails and is redelivered
+                if self.calls.fetch_add(1, Ordering::SeqCst).is_multiple_of(2) {
+                    Err(TaskError::Internal("flaky".to_string()))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        let broker = InMemoryBroker::default();
+        let handler = Arc::new(FlakyHandler { calls: AtomicUsize::new(0) });
+        let handle = broker.subscribe("test", handler.clone()).await.unwrap();
+
+        for _ in 0..3 {
+            broker.publish("test", message()).await.unwrap();
+        }
+        for _ in 0..100 {
+            if broker.queue_len("test") == 0 && broker.in_flight() == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(broker.queue_len("test"), 0);
+        assert_eq!(broker.in_flight(), 0);
+        assert!(handler.calls.load(Ordering::SeqCst) >= 4);
+
+        handle.cancel();
+    }
+}
@@ -11,6 +11,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
+use crate::message::MAX_PRIORITY;
 use crate::{TaskError, TaskMessage};
 
 /// Delivery model for broker
@@ -78,6 +79,12 @@ pub trait PullBroker: Broker {
 
 /// Trait for push-based brokers (broker sends HTTP to worker)
 pub trait PushBroker: Broker {
+    /// Check that a request really comes from the broker; called before
+    /// `parse_push_request`. Header names are lowercase.
+    fn verify_push_request(&self, _headers: &HashMap<String, String>, _body: &[u8]) -> Result<(), TaskError> {
+        Ok(())
+    }
+
     /// Parse an incoming HTTP request into a BrokerMessage
     fn parse_push_request(&self, headers: &HashMap<String, String>, body: &[u8])
         -> Result<BrokerMessage, TaskError>;
@@ -121,6 +128,13 @@ pub trait DelayedBroker: Broker {
     }
 }
 
+/// Trait for brokers that can report how many messages a queue holds
+#[async_trait]
+pub trait QueueInspector: Send + Sync {
+    /// Number of messages waiting in a queue, including delayed ones
+    async fn queue_depth(&self, queue: &str) -> Result<usize, TaskError>;
+}
+
 /// Message received from the broker
 #[derive(Debug, Clone)]
 pub struct BrokerMessage {
@@ -136,11 +150,97 @@ pub struct BrokerMessage {
     pub redelivered: bool,
 }
 
+/// Message a broker gave up on before it could be handled
+#[derive(Debug, Clone)]
+pub struct PoisonMessage {
+    /// Queue the message was consumed from
+    pub queue: String,
+    /// Decoded message, if the payload could be decoded
+    pub message: Option<TaskMessage>,
+    /// Raw payload
+    pub payload: Vec<u8>,
+    /// Number of times the message was delivered
+    pub deliveries: u32,
+    /// Why the message was given up on
+    pub error: String,
+}
+
 /// Handler for incoming messages
 #[async_trait]
 pub trait MessageHandler: Send + Sync {
     /// Handle an incoming message
     async fn handle(&self, message: BrokerMessage) -> Result<(), TaskError>;
+
+    /// Handle a message the broker is about to discard, because its payload
+    /// could not be decoded or it reached the broker's delivery limit
+    async fn handle_poison(&self, poison: PoisonMessage) -> Result<(), TaskError> {
+        poison.log_discarded();
+        Ok(())
+    }
+}
+
+impl PoisonMessage {
+    /// Log that the message is being discarded without further handling
+    pub(crate) fn log_discarded(&self) {
+        tracing::error!(
+            "Discarding message on queue '{}' after {} deliveries: {}",
+            self.queue,
+            self.deliveries,
+            self.error
+        );
+    }
+}
+
+/// Priority sub-queues for brokers without native message priorities
+///
+/// Priorities are split into `levels` contiguous bands. Band 0 is the queue
+/// itself, so with a single level nothing changes; higher bands live in
+/// `{queue}.p{band}` and are consumed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityBands {
+    levels: u8,
+}
+
+impl PriorityBands {
+    /// Create with the given number of bands (1 to `MAX_PRIORITY + 1`)
+    pub fn new(levels: u8) -> Self {
+        Self {
+            levels: levels.clamp(1, MAX_PRIORITY + 1),
+        }
+    }
+
+    /// Number of bands
+    pub fn levels(&self) -> u8 {
+        self.levels
+    }
+
+    /// Band a message priority falls into
+    pub fn band(&self, priority: u8) -> u8 {
+        (priority.min(MAX_PRIORITY) as u16 * self.levels as u16 / (MAX_PRIORITY as u16 + 1)) as u8
+    }
+
+    /// Sub-queue holding a band of a queue
+    pub fn sub_queue(&self, queue: &str, band: u8) -> String {
+        if band == 0 {
+            queue.to_string()
+        } else {
+            format!("{}.p{}", queue, band)
+        }
+    }
+
+    /// Sub-queues of a queue, highest band first
+    pub fn sub_queues(&self, queue: &str) -> Vec<String> {
+        (0..self.levels).rev().map(|band| self.sub_queue(queue, band)).collect()
+    }
+}
+
+/// Result of claiming a message from a queue
+pub(crate) enum Delivery {
+    Message(BrokerMessage),
+    /// A poison message, with the delivery tag that discards it for brokers
+    /// that keep it until it has been handled
+    #[cfg_attr(not(any(feature = "kv", feature = "postgres")), allow(dead_code))]
+    Poison(PoisonMessage, Option<String>),
 }
 
 /// Handle for managing subscriptions
@@ -164,6 +264,11 @@ impl SubscriptionHandle {
     }
 }
 
+// In-process broker implementation
+pub mod memory;
+
+pub use memory::{InMemoryBroker, InMemoryBrokerConfig};
+
 // NATS JetStream broker implementation
 #[cfg(feature = "nats")]
 pub mod nats;
@@ -171,6 +276,13 @@ pub mod nats;
 #[cfg(feature = "nats")]
 pub use nats::{NatsBroker, NatsBrokerConfig};
 
+// HTTP push receiver (Pub/Sub push, Cloud Tasks, plain JSON)
+#[cfg(feature = "push")]
+pub mod push;
+
+#[cfg(feature = "push")]
+pub use push::{HttpPushBroker, PushAuth, PushBrokerConfig, PushFormat};
+
 // Google Cloud Pub/Sub broker implementation
 #[cfg(feature = "pubsub")]
 pub mod pubsub;
@@ -178,8 +290,45 @@ pub mod pubsub;
 #[cfg(feature = "pubsub")]
 pub use pubsub::{PubSubPullBroker, PubSubPullConfig};
 
+// ouroboros-kv-server broker
+#[cfg(feature = "kv")]
+pub mod kv;
+
+#[cfg(feature = "kv")]
+pub use kv::{KvBroker, KvBrokerConfig};
+
+// PostgreSQL broker implementation
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+#[cfg(feature = "postgres")]
+pub use postgres::{PostgresBroker, PostgresBrokerConfig};
+
 // Unified broker configuration
 pub use config::BrokerConfig;
 
-#[cfg(any(feature = "nats", feature = "pubsub"))]
+#[cfg(any(feature = "nats", feature = "pubsub", feature = "kv"))]
 pub use config::BrokerInstance;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_bands() {
+        let single = PriorityBands::new(0);
+        assert_eq!(single.levels(), 1);
+        assert_eq!(single.band(MAX_PRIORITY), 0);
+        assert_eq!(single.sub_queues("emails"), vec!["emails"]);
+
+        let bands = PriorityBands::new(3);
+        let assigned: Vec<u8> = (0..=MAX_PRIORITY).map(|p| bands.band(p)).collect();
+        assert_eq!(assigned, vec![0, 0, 0, 0, 1, 1, 1, 2, 2, 2]);
+        assert_eq!(bands.band(200), 2);
+        assert_eq!(bands.sub_queues("emails"), vec!["emails.p2", "emails.p1", "emails"]);
+
+        let full = PriorityBands::new(50);
+        assert_eq!(full.levels(), MAX_PRIORITY + 1);
+        assert_eq!(full.band(7), 7);
+    }
+}
@@ -1,8 +1,18 @@
 //! NATS JetStream broker implementation
+//!
+//! JetStream has no message priorities, so they are emulated with priority
+//! sub-queues (see `PriorityBands`): each band of a queue has its own subject
+//! and consumer, and a subscription drains higher bands before lower ones.
+//!
+//! Messages with an idempotency key carry it as the `Nats-Msg-Id`, so the
+//! stream drops a duplicate published within its duplicate window.
 
 use async_nats::jetstream::{
     self,
-    consumer::{pull::Config as ConsumerConfig, AckPolicy, DeliverPolicy},
+    consumer::{
+        pull::{Batch, Config as ConsumerConfig},
+        AckPolicy, DeliverPolicy, PullConsumer,
+    },
     stream::{Config as StreamConfig, RetentionPolicy},
 };
 use async_trait::async_trait;
@@ -16,8 +26,9 @@ use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    broker::{BrokerMessage, MessageHandler, SubscriptionHandle},
-    Broker, BrokerCapabilities, DelayedBroker, DeliveryModel, PullBroker, TaskError, TaskMessage,
+    broker::{BrokerMessage, MessageHandler, PoisonMessage, PriorityBands, SubscriptionHandle},
+    Broker, BrokerCapabilities, DelayedBroker, DeliveryModel, PullBroker, QueueInspector, TaskError,
+    TaskMessage,
 };
 
 /// NATS JetStream broker configuration
@@ -33,8 +44,13 @@ pub struct NatsBrokerConfig {
     pub max_pending: usize,
     /// Ack wait timeout
     pub ack_wait: Duration,
-    /// Maximum delivery attempts before dead-letter
+    /// Maximum delivery attempts; a message failing its last attempt is
+    /// handed to the subscriber's `handle_poison` and terminated
     pub max_deliver: i64,
+    /// Number of priority sub-queues per queue (1 disables priorities)
+    pub priority_levels: u8,
+    /// Window in which the stream drops messages with a repeated idempotency key
+    pub duplicate_window: Duration,
 }
 
 impl Default for NatsBrokerConfig {
@@ -46,6 +62,8 @@ impl Default for NatsBrokerConfig {
             max_pending: 1000,
             ack_wait: Duration::from_secs(30),
             max_deliver: 5,
+            priority_levels: 3,
+            duplicate_window: Duration::from_secs(120),
         }
     }
 }
@@ -83,35 +101,44 @@ impl NatsBroker {
             .ok_or(TaskError::NotConnected)?
             .clone();
 
-        // Create or get durable pull consumer
-        let consumer_name = format!("{}-{}", self.config.consumer_prefix, queue);
-        let subject_filter = format!("tasks.{}", queue);
-
-        tracing::debug!(
-            "Creating consumer '{}' for subject '{}'",
-            consumer_name,
-            subject_filter
-        );
-
-        let consumer_config = ConsumerConfig {
-            durable_name: Some(consumer_name.clone()),
-            ack_policy: AckPolicy::Explicit,
-            deliver_policy: DeliverPolicy::All,
-            filter_subject: subject_filter.clone(),
-            ack_wait: self.config.ack_wait,
-            max_deliver: self.config.max_deliver,
-            ..Default::default()
-        };
-
         let stream = js
             .get_stream(&self.config.stream_name)
             .await
             .map_err(|e| TaskError::Broker(format!("Failed to get stream: {}", e)))?;
 
-        let consumer = stream
-            .get_or_create_consumer(&consumer_name, consumer_config)
-            .await
-            .map_err(|e| TaskError::Broker(format!("Failed to create consumer: {}", e)))?;
+        // One durable pull consumer per priority band, highest first
+        let bands = PriorityBands::new(self.config.priority_levels);
+        let mut consumers = Vec::new();
+        for band in (0..bands.levels()).rev() {
+            let consumer_name = if band == 0 {
+                format!("{}-{}", self.config.consumer_prefix, queue)
+            } else {
+                format!("{}-{}-p{}", self.config.consumer_prefix, queue, band)
+            };
+            let subject_filter = format!("tasks.{}", bands.sub_queue(queue, band));
+
+            tracing::debug!(
+                "Creating consumer '{}' for subject '{}'",
+                consumer_name,
+                subject_filter
+            );
+
+            let consumer_config = ConsumerConfig {
+                durable_name: Some(consumer_name.clone()),
+                ack_policy: AckPolicy::Explicit,
+                deliver_policy: DeliverPolicy::All,
+                filter_subject: subject_filter,
+                ack_wait: self.config.ack_wait,
+                max_deliver: self.config.max_deliver,
+                ..Default::default()
+            };
+
+            let consumer: PullConsumer = stream
+                .get_or_create_consumer(&consumer_name, consumer_config)
+                .await
+                .map_err(|e| TaskError::Broker(format!("Failed to create consumer: {}", e)))?;
+            consumers.push(consumer);
+        }
 
         // Create cancellation token
         let cancel_token = CancellationToken::new();
@@ -119,45 +146,47 @@ impl NatsBroker {
 
         // Spawn message processing loop
         let queue_owned = queue.to_string();
+        let max_deliver = self.config.max_deliver;
         tokio::spawn(async move {
             tracing::info!("Starting message loop for queue: {}", queue_owned);
 
-            loop {
+            // The lowest band is long-polled; keep the poll short when higher
+            // bands may fill up in the meantime
+            let (lowest, higher) = consumers.split_last().expect("at least one priority band");
+            let idle_wait = if higher.is_empty() {
+                Duration::from_secs(5)
+            } else {
+                Duration::from_secs(1)
+            };
+
+            'outer: loop {
+                if cancel_token_clone.is_cancelled() {
+                    break;
+                }
+
+                // Drain whatever is waiting in the higher bands first
+                for consumer in higher {
+                    match consumer.fetch().max_messages(10).messages().await {
+                        Ok(messages) => {
+                            if Self::handle_batch(messages, &handler, &queue_owned, max_deliver).await > 0 {
+                                continue 'outer;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!("Error fetching batch: {}", e);
+                        }
+                    }
+                }
+
                 tokio::select! {
                     _ = cancel_token_clone.cancelled() => {
                         tracing::info!("Message loop cancelled for queue: {}", queue_owned);
                         break;
                     }
-                    result = consumer.batch().max_messages(10).expires(Duration::from_secs(5)).messages() => {
+                    result = lowest.batch().max_messages(10).expires(idle_wait).messages() => {
                         match result {
-                            Ok(mut messages) => {
-                                while let Some(Ok(nats_msg)) = messages.next().await {
-                                    match Self::nats_to_broker_message(&nats_msg).await {
-                                        Ok(broker_msg) => {
-                                            match handler.handle(broker_msg).await {
-                                                Ok(_) => {
-                                                    if let Err(e) = nats_msg.ack().await {
-                                                        tracing::error!("Failed to ack message: {}", e);
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    tracing::error!("Handler error: {}", e);
-                                                    // Nack with retry
-                                                    if let Err(e) = nats_msg.ack_with(async_nats::jetstream::AckKind::Nak(None)).await {
-                                                        tracing::error!("Failed to nack message: {}", e);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            tracing::error!("Failed to parse message: {}", e);
-                                            // Ack to avoid infinite loop on malformed messages
-                                            if let Err(e) = nats_msg.ack().await {
-                                                tracing::error!("Failed to ack malformed message: {}", e);
-                                            }
-                                        }
-                                    }
-                                }
+                            Ok(messages) => {
+                                Self::handle_batch(messages, &handler, &queue_owned, max_deliver).await;
                             }
                             Err(e) => {
                                 tracing::error!("Error fetching batch: {}", e);
@@ -174,6 +203,65 @@ impl NatsBroker {
         Ok(SubscriptionHandle::new(queue.to_string(), cancel_token))
     }
 
+    /// Handle and settle a batch of messages, returning how many there were
+    async fn handle_batch<H: MessageHandler>(
+        mut messages: Batch,
+        handler: &Arc<H>,
+        queue: &str,
+        max_deliver: i64,
+    ) -> usize {
+        let mut count = 0;
+        while let Some(Ok(nats_msg)) = messages.next().await {
+            count += 1;
+            let deliveries = nats_msg.info().map(|info| info.delivered).unwrap_or(1);
+            let poison = match Self::nats_to_broker_message(&nats_msg).await {
+                Ok(broker_msg) => {
+                    let payload = broker_msg.payload.clone();
+                    match handler.handle(broker_msg).await {
+                        Ok(_) => {
+                            if let Err(e) = nats_msg.ack().await {
+                                tracing::error!("Failed to ack message: {}", e);
+                            }
+                            continue;
+                        }
+                        // Last attempt: JetStream would stop delivering it without removing it
+                        Err(e) if max_deliver > 0 && deliveries >= max_deliver => PoisonMessage {
+                            queue: queue.to_string(),
+                            message: Some(payload),
+                            payload: nats_msg.payload.to_vec(),
+                            deliveries: deliveries as u32,
+                            error: e.to_string(),
+                        },
+                        Err(e) => {
+                            tracing::error!("Handler error: {}", e);
+                            // Nack with retry
+                            if let Err(e) = nats_msg.ack_with(async_nats::jetstream::AckKind::Nak(None)).await {
+                                tracing::error!("Failed to nack message: {}", e);
+                            }
+                            continue;
+                        }
+                    }
+                }
+                Err(e) => PoisonMessage {
+                    queue: queue.to_string(),
+                    message: None,
+                    payload: nats_msg.payload.to_vec(),
+                    deliveries: deliveries as u32,
+                    error: e.to_string(),
+                },
+            };
+
+            if let Err(e) = handler.handle_poison(poison).await {
+                tracing::error!("Poison message handler error: {}", e);
+            }
+            // Terminate so it is never redelivered
+            if let Err(e) = nats_msg.ack_with(async_nats::jetstream::AckKind::Term).await {
+                tracing::error!("Failed to terminate message: {}", e);
+            }
+        }
+        count
+    }
+
     /// Convert NATS message to BrokerMessage
     async fn nats_to_broker_message(
         msg: &async_nats::jetstream::Message,
@@ -215,6 +303,33 @@ impl NatsBroker {
     }
 }
 
+#[async_trait]
+impl QueueInspector for NatsBroker {
+    /// Work-queue retention drops acknowledged messages, so this counts
+    /// waiting and unacknowledged ones
+    async fn queue_depth(&self, queue: &str) -> Result<usize, TaskError> {
+        use futures::TryStreamExt;
+
+        let stream = self.stream.read().await.as_ref().ok_or(TaskError::NotConnected)?.clone();
+        let bands = PriorityBands::new(self.config.priority_levels);
+        let mut depth = 0;
+        for sub_queue in bands.sub_queues(queue) {
+            let mut subjects = stream
+                .info_with_subjects(format!("tasks.{}", sub_queue))
+                .await
+                .map_err(|e| TaskError::Broker(format!("Failed to get stream info: {}", e)))?;
+            while let Some((_, count)) = subjects
+                .try_next()
+                .await
+                .map_err(|e| TaskError::Broker(format!("Failed to get stream info: {}", e)))?
+            {
+                depth += count;
+            }
+        }
+        Ok(depth)
+    }
+}
+
 #[async_trait]
 impl Broker for NatsBroker {
     async fn connect(&self) -> Result<(), TaskError> {
@@ -233,6 +348,7 @@ impl Broker for NatsBroker {
             name: self.config.stream_name.clone(),
             subjects: vec!["tasks.>".to_string()],
             retention: RetentionPolicy::WorkQueue,
+            duplicate_window: self.config.duplicate_window,
             ..Default::default()
         };
 
@@ -278,7 +394,8 @@ impl Broker for NatsBroker {
             .ok_or(TaskError::NotConnected)?
             .clone();
 
-        let subject = format!("tasks.{}", queue);
+        let bands = PriorityBands::new(self.config.priority_levels);
+        let subject = format!("tasks.{}", bands.sub_queue(queue, bands.band(message.priority)));
         let payload = serde_json::to_vec(&message)
             .map_err(|e| TaskError::Serialization(format!("Failed to serialize message: {}", e)))?;
 
@@ -289,6 +406,11 @@ impl Broker for NatsBroker {
         if let Some(ref correlation_id) = message.correlation_id {
             headers.insert("correlation-id", correlation_id.as_str());
         }
+        if let Some(ref key) = message.idempotency_key {
+            // Retries and requeues carry more attempts, so they are not duplicates
+            let msg_id = format!("{}:{}", key, message.attempts.len());
+            headers.insert(async_nats::header::NATS_MESSAGE_ID, msg_id.as_str());
+        }
 
         tracing::debug!(
             "Publishing message to subject '{}': task_id={}, task_name={}",
@@ -329,7 +451,7 @@ impl Broker for NatsBroker {
         BrokerCapabilities {
             delayed_tasks: true,
             dead_letter: true,
-            priority: false,
+            priority: true, // Emulated with priority sub-queues
             batching: true,
             max_delay: None, // NATS doesn't have a hard limit
         }
@@ -446,6 +568,8 @@ mod tests {
         assert_eq!(config.max_pending, 1000);
         assert_eq!(config.ack_wait, Duration::from_secs(30));
         assert_eq!(config.max_deliver, 5);
+        assert_eq!(config.priority_levels, 3);
+        assert_eq!(config.duplicate_window, Duration::from_secs(120));
     }
 
     #[test]
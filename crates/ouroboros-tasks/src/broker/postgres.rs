@@ -0,0 +1,738 @@
+//! PostgreSQL broker implementation
+//!
+//! Messages are rows of a jobs table. Consumers claim the due row with the
+//! highest priority, oldest first, with `FOR UPDATE SKIP LOCKED` and push its
+//! `run_at` past `ack_wait`, so the row
+//! stays invisible while it is handled and falls due again if the consumer
+//! dies without acking. Ack deletes the row.
+//!
+//! Publishing sends a `NOTIFY` on `channel` so idle subscribers wake at once;
+//! they also poll every `poll_interval` to pick up delayed messages. Because
+//! notifications are only delivered on commit, `publish_in` can enqueue a task
+//! in the same transaction as the writes it depends on: the task is handed off
+//! exactly when those writes commit.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ouroboros_postgres::{Connection, QueryBuilder, Transaction};
+use sqlx::postgres::{PgArguments, PgListener};
+use sqlx::query::Query;
+use sqlx::types::Json;
+use sqlx::{Postgres, Row};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    broker::{BrokerMessage, Delivery, MessageHandler, PoisonMessage, SubscriptionHandle},
+    Broker, BrokerCapabilities, DelayedBroker, DeliveryModel, PullBroker, QueueInspector, TaskError,
+    TaskMessage,
+};
+
+/// PostgreSQL broker configuration
+#[derive(Debug, Clone)]
+pub struct PostgresBrokerConfig {
+    /// Jobs table (optionally schema-qualified, e.g. "jobs.task_queue")
+    pub table: String,
+    /// NOTIFY channel used to wake subscribers
+    pub channel: String,
+    /// Unacknowledged messages are redelivered after this long
+    pub ack_wait: Duration,
+    /// Maximum delivery attempts; the message is then handed to the
+    /// subscriber's `handle_poison` and dropped once that succeeds
+    pub max_deliver: u32,
+    /// Messages handled concurrently by each subscription
+    pub prefetch: usize,
+    /// How often an idle subscription checks for due messages
+    pub poll_interval: Duration,
+    /// Create the jobs table and index on connect
+    pub create_table: bool,
+}
+
+impl Default for PostgresBrokerConfig {
+    fn default() -> Self {
+        Self {
+            table: "ouroboros_task_queue".to_string(),
+            channel: "ouroboros_tasks".to_string(),
+            ack_wait: Duration::from_secs(30),
+            max_deliver: 5,
+            prefetch: 10,
+            poll_interval: Duration::from_secs(1),
+            create_table: true,
+        }
+    }
+}
+
+fn pg_error(e: impl std::fmt::Display) -> TaskError {
+    TaskError::Broker(format!("Postgres error: {}", e))
+}
+
+fn millis(duration: Duration) -> i64 {
+    duration.as_millis().min(i64::MAX as u128) as i64
+}
+
+/// SQL statements for one jobs table
+struct Statements {
+    create: Vec<String>,
+    insert: String,
+    claim: String,
+    delete: String,
+    requeue: String,
+    count: String,
+}
+
+impl Statements {
+    fn new(table: &str) -> Result<Self, TaskError> {
+        QueryBuilder::validate_identifier(table)
+            .map_err(|e| TaskError::Configuration(format!("Invalid jobs table: {}", e)))?;
+        let t = QueryBuilder::quote_identifier(table);
+        let index_name = table.rsplit('.').next().unwrap_or(table);
+        let index = QueryBuilder::quote_identifier(&format!("{}_priority_dequeue_idx", index_name));
+
+        Ok(Self {
+            create: vec![
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {t} (
+                        id BIGSERIAL PRIMARY KEY,
+                        queue TEXT NOT NULL,
+                        payload JSONB NOT NULL,
+                        headers JSONB NOT NULL DEFAULT '{{}}',
+                        run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                        deliveries INTEGER NOT NULL DEFAULT 0,
+                        lease TEXT,
+                        created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                        priority SMALLINT NOT NULL DEFAULT 0
+                    )"
+                ),
+                // Tables created before priorities existed
+                format!("ALTER TABLE {t} ADD COLUMN IF NOT EXISTS priority SMALLINT NOT NULL DEFAULT 0"),
+                format!("CREATE INDEX IF NOT EXISTS {index} ON {t} (queue, priority DESC, run_at, id)"),
+            ],
+            // Notifications are sent on commit, so a message published inside a
+            // transaction is never seen before its rows are
+            insert: format!(
+                "WITH job AS (
+                    INSERT INTO {t} (queue, payload, headers, run_at, priority)
+                    VALUES ($1, $2, $3, COALESCE($4, now() + $5 * interval '1 millisecond'), $7)
+                    RETURNING queue
+                )
+                SELECT pg_notify($6, queue) FROM job"
+            ),
+            claim: format!(
+                "UPDATE {t}
+                SET run_at = now() + $2 * interval '1 millisecond',
+                    deliveries = deliveries + 1,
+                    lease = $3
+                WHERE id = (
+                    SELECT id FROM {t}
+                    WHERE queue = $1 AND run_at <= now()
+                    ORDER BY priority DESC, run_at, id
+                    LIMIT 1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, payload, headers, deliveries"
+            ),
+            delete: format!("DELETE FROM {t} WHERE id = $1 AND lease = $2"),
+            requeue: format!(
+                "UPDATE {t} SET run_at = now(), lease = NULL WHERE id = $1 AND lease = $2"
+            ),
+            count: format!("SELECT count(*) FROM {t} WHERE queue = $1"),
+        })
+    }
+}
+
+/// Parse a `{id}:{lease}` delivery tag
+fn parse_tag(tag: &str) -> Result<(i64, &str), TaskError> {
+    tag.split_once(':')
+        .and_then(|(id, lease)| Some((id.parse().ok()?, lease)))
+        .ok_or_else(|| TaskError::Broker(format!("Invalid delivery tag: {}", tag)))
+}
+
+/// Jobs table access shared with subscription tasks
+struct Queues {
+    conn: Connection,
+    config: PostgresBrokerConfig,
+    sql: Statements,
+}
+
+impl Queues {
+    fn insert(
+        &self,
+        queue: &str,
+        message: TaskMessage,
+        run_at: Option<DateTime<Utc>>,
+        delay: Duration,
+    ) -> Query<'_, Postgres, PgArguments> {
+        let mut headers = HashMap::new();
+        headers.insert("task-id".to_string(), message.id.to_string());
+        headers.insert("task-name".to_string(), message.task_name.clone());
+        if let Some(ref correlation_id) = message.correlation_id {
+            headers.insert("correlation-id".to_string(), correlation_id.clone());
+        }
+
+        tracing::debug!(
+            "Publishing message to queue '{}': task_id={}, task_name={}, priority={}",
+            queue,
+            message.id,
+            message.task_name,
+            message.priority
+        );
+
+        let priority = message.priority as i16;
+        sqlx::query(&self.sql.insert)
+            .bind(queue.to_string())
+            .bind(Json(message))
+            .bind(Json(headers))
+            .bind(run_at)
+            .bind(millis(delay))
+            .bind(self.config.channel.clone())
+            .bind(priority)
+    }
+
+    /// Claim the next due message of a queue
+    async fn take(&self, queue: &str) -> Result<Option<Delivery>, TaskError> {
+        let lease = uuid::Uuid::now_v7().simple().to_string();
+        let row = sqlx::query(&self.sql.claim)
+            .bind(queue)
+            .bind(millis(self.config.ack_wait))
+            .bind(&lease)
+            .fetch_optional(self.conn.pool())
+            .await
+            .map_err(pg_error)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let id: i64 = row.try_get("id").map_err(pg_error)?;
+        let deliveries = row.try_get::<i32, _>("deliveries").map_err(pg_error)? as u32;
+        let Json(payload): Json<serde_json::Value> = row.try_get("payload").map_err(pg_error)?;
+        let Json(headers): Json<HashMap<String, String>> =
+            row.try_get("headers").map_err(pg_error)?;
+
+        let (message, error) = match serde_json::from_value::<TaskMessage>(payload.clone()) {
+            Ok(message) if deliveries <= self.config.max_deliver => {
+                return Ok(Some(Delivery::Message(BrokerMessage {
+                    delivery_tag: format!("{}:{}", id, lease),
+                    payload: message,
+                    headers,
+                    timestamp: Utc::now(),
+                    redelivered: deliveries > 1,
+                })));
+            }
+            Ok(message) => (
+                Some(message),
+                format!("Not acknowledged after {} deliveries", deliveries - 1),
+            ),
+            Err(e) => (None, format!("Failed to parse message {}: {}", id, e)),
+        };
+
+        // Still leased: the row is only deleted once the poison message has
+        // been handled, so a failed dead-letter write sees it again
+        Ok(Some(Delivery::Poison(
+            PoisonMessage {
+                queue: queue.to_string(),
+                message,
+                payload: payload.to_string().into_bytes(),
+                deliveries: deliveries - 1,
+                error,
+            },
+            Some(format!("{}:{}", id, lease)),
+        )))
+    }
+
+    /// Hand a poison message to its handler, then delete it
+    ///
+    /// The dead-letter store is the handler's, so the two writes cannot share
+    /// a transaction; if the handler fails the row stays and is given to it
+    /// again once the lease runs out.
+    async fn discard<H: MessageHandler + ?Sized>(
+        &self,
+        handler: &H,
+        poison: PoisonMessage,
+        delivery_tag: Option<String>,
+    ) {
+        if let Err(e) = handler.handle_poison(poison).await {
+            tracing::error!("Poison message handler error: {}", e);
+            return;
+        }
+        if let Some(delivery_tag) = delivery_tag {
+            if let Err(e) = self.settle_tag(&delivery_tag, false).await {
+                tracing::warn!("Failed to delete poison message: {}", e);
+            }
+        }
+    }
+
+    async fn settle_tag(&self, delivery_tag: &str, requeue: bool) -> Result<(), TaskError> {
+        let (id, lease) = parse_tag(delivery_tag)?;
+        self.settle(id, lease, requeue).await
+    }
+
+    /// Delete or requeue a claimed message, failing if its lease has expired
+    async fn settle(&self, id: i64, lease: &str, requeue: bool) -> Result<(), TaskError> {
+        let sql = if requeue { &self.sql.requeue } else { &self.sql.delete };
+        let result = sqlx::query(sql)
+            .bind(id)
+            .bind(lease)
+            .execute(self.conn.pool())
+            .await
+            .map_err(pg_error)?;
+        if result.rows_affected() == 0 {
+            return Err(TaskError::Broker(format!(
+                "Lease for message {} expired, the message was already redelivered",
+                id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// PostgreSQL broker implementation
+pub struct PostgresBroker {
+    queues: Arc<Queues>,
+}
+
+impl PostgresBroker {
+    /// Create a new Postgres broker on an existing connection pool
+    pub fn new(conn: Connection, config: PostgresBrokerConfig) -> Result<Self, TaskError> {
+        let sql = Statements::new(&config.table)?;
+        Ok(Self {
+            queues: Arc::new(Queues { conn, config, sql }),
+        })
+    }
+
+    /// Publish a message as part of a transaction
+    ///
+    /// The message becomes visible to consumers when the transaction commits
+    /// and is discarded if it rolls back.
+    pub async fn publish_in(
+        &self,
+        tx: &mut Transaction,
+        queue: &str,
+        message: TaskMessage,
+    ) -> Result<(), TaskError> {
+        let eta = message.eta;
+        self.queues
+            .insert(queue, message, eta, Duration::ZERO)
+            .execute(&mut **tx.as_mut_transaction())
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+
+    /// Claim the next due message, to be settled with `ack` or `nack`
+    ///
+    /// Messages that cannot be decoded or are over the delivery limit are
+    /// logged and dropped.
+    pub async fn fetch(&self, queue: &str) -> Result<Option<BrokerMessage>, TaskError> {
+        loop {
+            match self.queues.take(queue).await? {
+                Some(Delivery::Message(message)) => return Ok(Some(message)),
+                Some(Delivery::Poison(poison, delivery_tag)) => {
+                    poison.log_discarded();
+                    if let Some(delivery_tag) = delivery_tag {
+                        self.queues.settle_tag(&delivery_tag, false).await?;
+                    }
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Number of messages in a queue, including delayed and unacknowledged ones
+    pub async fn queue_len(&self, queue: &str) -> Result<usize, TaskError> {
+        let count: i64 = sqlx::query_scalar(&self.queues.sql.count)
+            .bind(queue)
+            .fetch_one(self.queues.conn.pool())
+            .await
+            .map_err(pg_error)?;
+        Ok(count as usize)
+    }
+}
+
+#[async_trait]
+impl QueueInspector for PostgresBroker {
+    async fn queue_depth(&self, queue: &str) -> Result<usize, TaskError> {
+        self.queue_len(queue).await
+    }
+}
+
+#[async_trait]
+impl Broker for PostgresBroker {
+    async fn connect(&self) -> Result<(), TaskError> {
+        if self.queues.config.create_table {
+            tracing::info!("Creating jobs table {}", self.queues.config.table);
+            for sql in &self.queues.sql.create {
+                sqlx::query(sql)
+                    .execute(self.queues.conn.pool())
+                    .await
+                    .map_err(pg_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TaskError> {
+        // The connection pool belongs to the caller
+        Ok(())
+    }
+
+    async fn publish(&self, queue: &str, message: TaskMessage) -> Result<(), TaskError> {
+        let eta = message.eta;
+        self.queues
+            .insert(queue, message, eta, Duration::ZERO)
+            .execute(self.queues.conn.pool())
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        self.queues
+            .conn
+            .ping()
+            .await
+            .map_err(|e| TaskError::Broker(format!("Health check failed: {}", e)))
+    }
+
+    fn delivery_model(&self) -> DeliveryModel {
+        DeliveryModel::Pull
+    }
+
+    fn capabilities(&self) -> BrokerCapabilities {
+        BrokerCapabilities {
+            delayed_tasks: true,
+            dead_letter: false,
+            priority: true,
+            batching: false,
+            max_delay: None,
+        }
+    }
+}
+
+#[async_trait]
+impl PullBroker for PostgresBroker {
+    async fn subscribe<H: MessageHandler + 'static>(
+        &self,
+        queue: &str,
+        handler: Arc<H>,
+    ) -> Result<SubscriptionHandle, TaskError> {
+        let queues = self.queues.clone();
+        let mut listener = PgListener::connect_with(queues.conn.pool())
+            .await
+            .map_err(pg_error)?;
+        listener
+            .listen(&queues.config.channel)
+            .await
+            .map_err(pg_error)?;
+
+        let cancel_token = CancellationToken::new();
+        let cancelled = cancel_token.clone();
+        let permits = Arc::new(Semaphore::new(queues.config.prefetch.max(1)));
+        let poll_interval = queues.config.poll_interval;
+        let queue_owned = queue.to_string();
+
+        tokio::spawn(async move {
+            tracing::info!("Starting message loop for queue: {}", queue_owned);
+
+            loop {
+                let permit = tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    permit = permits.clone().acquire_owned() => permit.expect("semaphore is never closed"),
+                };
+
+                let wait = match queues.take(&queue_owned).await {
+                    Ok(Some(Delivery::Poison(poison, delivery_tag))) => {
+                        drop(permit);
+                        queues.discard(handler.as_ref(), poison, delivery_tag).await;
+                        continue;
+                    }
+                    Ok(Some(Delivery::Message(message))) => {
+                        let queues = queues.clone();
+                        let handler = handler.clone();
+                        tokio::spawn(async move {
+                            let delivery_tag = message.delivery_tag.clone();
+                            let settled = match handler.handle(message).await {
+                                Ok(()) => queues.settle_tag(&delivery_tag, false).await,
+                                Err(e) => {
+                                    tracing::error!("Handler error: {}", e);
+                                    queues.settle_tag(&delivery_tag, true).await
+                                }
+                            };
+                            if let Err(e) = settled {
+                                tracing::warn!("Failed to settle message: {}", e);
+                            }
+                            drop(permit);
+                        });
+                        continue;
+                    }
+                    Ok(None) => poll_interval,
+                    Err(e) => {
+                        tracing::error!("Error fetching from queue '{}': {}", queue_owned, e);
+                        Duration::from_secs(1)
+                    }
+                };
+
+                drop(permit);
+                // Any notification may mean new work; a spurious wakeup only costs a query
+                tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    _ = tokio::time::sleep(wait) => {}
+                    notification = listener.recv() => {
+                        if let Err(e) = notification {
+                            tracing::warn!("LISTEN connection error: {}", e);
+                            tokio::time::sleep(wait).await;
+                        }
+                    }
+                }
+            }
+
+            tracing::info!("Message loop ended for queue: {}", queue_owned);
+        });
+
+        Ok(SubscriptionHandle::new(queue.to_string(), cancel_token))
+    }
+
+    async fn ack(&self, delivery_tag: &str) -> Result<(), TaskError> {
+        self.queues.settle_tag(delivery_tag, false).await
+    }
+
+    async fn nack(&self, delivery_tag: &str, requeue: bool) -> Result<(), TaskError> {
+        self.queues.settle_tag(delivery_tag, requeue).await
+    }
+}
+
+#[async_trait]
+impl DelayedBroker for PostgresBroker {
+    async fn publish_delayed(
+        &self,
+        queue: &str,
+        message: TaskMessage,
+        delay: Duration,
+    ) -> Result<(), TaskError> {
+        self.queues
+            .insert(queue, message, None, delay)
+            .execute(self.queues.conn.pool())
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ouroboros_postgres::{IsolationLevel, PoolConfig};
+
+    #[test]
+    fn test_delivery_tag() {
+        assert_eq!(parse_tag("42:0190b2").unwrap(), (42, "0190b2"));
+        assert!(parse_tag("abc:0190b2").is_err());
+        assert!(parse_tag("42").is_err());
+    }
+
+    #[test]
+    fn test_table_validation() {
+        assert!(Statements::new("jobs.task_queue").is_ok());
+        assert!(matches!(
+            Statements::new("jobs; DROP TABLE users"),
+            Err(TaskError::Configuration(_))
+        ));
+    }
+
+    // Integration tests requiring PostgreSQL
+    // Run with DATABASE_URL=postgresql://user@localhost/db
+    async fn broker(config: PostgresBrokerConfig) -> PostgresBroker {
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or_else(|_| "postgresql://postgres@localhost/postgres".to_string());
+        let conn = Connection::new(&url, PoolConfig::default()).await.unwrap();
+        let broker = PostgresBroker::new(conn, config).unwrap();
+        broker.connect().await.unwrap();
+        broker
+    }
+
+    fn test_queue() -> String {
+        format!("test-{}", uuid::Uuid::now_v7().simple())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_ack_nack() {
+        let broker = broker(PostgresBrokerConfig::default()).await;
+        let queue = test_queue();
+
+        let message = TaskMessage::new("test_task", serde_json::json!([1, 2, 3]));
+        broker.publish(&queue, message.clone()).await.unwrap();
+        broker
+            .publish_delayed(&queue, TaskMessage::new("later", serde_json::json!([])), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 2);
+
+        let delivered = broker.fetch(&queue).await.unwrap().unwrap();
+        assert_eq!(delivered.payload.id, message.id);
+        assert!(!delivered.redelivered);
+        // The other message is delayed, this one is leased
+        assert!(broker.fetch(&queue).await.unwrap().is_none());
+
+        broker.nack(&delivered.delivery_tag, true).await.unwrap();
+        let redelivered = broker.fetch(&queue).await.unwrap().unwrap();
+        assert_eq!(redelivered.payload.id, message.id);
+        assert!(redelivered.redelivered);
+
+        // The first lease is gone
+        assert!(broker.ack(&delivered.delivery_tag).await.is_err());
+        broker.ack(&redelivered.delivery_tag).await.unwrap();
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_priority() {
+        let broker = broker(PostgresBrokerConfig::default()).await;
+        let queue = test_queue();
+
+        let low = TaskMessage::new("test_task", serde_json::json!([]));
+        let high = TaskMessage::new("test_task", serde_json::json!([])).with_priority(9);
+        let normal = TaskMessage::new("test_task", serde_json::json!([])).with_priority(5);
+        for message in [&low, &high, &normal] {
+            broker.publish(&queue, message.clone()).await.unwrap();
+        }
+
+        let mut order = Vec::new();
+        while let Some(delivered) = broker.fetch(&queue).await.unwrap() {
+            broker.ack(&delivered.delivery_tag).await.unwrap();
+            order.push(delivered.payload.id);
+        }
+        assert_eq!(order, vec![high.id, normal.id, low.id]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_lease_expiry_and_max_deliver() {
+        let broker = broker(PostgresBrokerConfig {
+            ack_wait: Duration::from_millis(100),
+            max_deliver: 2,
+            ..Default::default()
+        })
+        .await;
+        let queue = test_queue();
+
+        broker.publish(&queue, TaskMessage::new("test_task", serde_json::json!([]))).await.unwrap();
+        let first = broker.fetch(&queue).await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let second = broker.fetch(&queue).await.unwrap().unwrap();
+        assert_eq!(second.payload.id, first.payload.id);
+
+        // Third delivery exceeds max_deliver: dropped
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(broker.fetch(&queue).await.unwrap().is_none());
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_poison_kept_until_handled() {
+        struct DeadLetters {
+            fail: bool,
+        }
+
+        #[async_trait]
+        impl MessageHandler for DeadLetters {
+            async fn handle(&self, _message: BrokerMessage) -> Result<(), TaskError> {
+                Ok(())
+            }
+
+            async fn handle_poison(&self, _poison: PoisonMessage) -> Result<(), TaskError> {
+                if self.fail {
+                    return Err(TaskError::Broker("dead-letter store unavailable".to_string()));
+                }
+                Ok(())
+            }
+        }
+
+        let broker = broker(PostgresBrokerConfig {
+            ack_wait: Duration::from_millis(100),
+            max_deliver: 0,
+            ..Default::default()
+        })
+        .await;
+        let queue = test_queue();
+        broker.publish(&queue, TaskMessage::new("test_task", serde_json::json!([]))).await.unwrap();
+
+        // The dead-letter write fails: the message must not be lost
+        let Some(Delivery::Poison(poison, delivery_tag)) = broker.queues.take(&queue).await.unwrap() else {
+            panic!("expected a poison message");
+        };
+        broker.queues.discard(&DeadLetters { fail: true }, poison, delivery_tag).await;
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let Some(Delivery::Poison(poison, delivery_tag)) = broker.queues.take(&queue).await.unwrap() else {
+            panic!("expected the poison message again");
+        };
+        broker.queues.discard(&DeadLetters { fail: false }, poison, delivery_tag).await;
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_publish_in_transaction() {
+        let broker = broker(PostgresBrokerConfig::default()).await;
+        let queue = test_queue();
+
+        let mut tx = Transaction::begin(&broker.queues.conn, IsolationLevel::ReadCommitted).await.unwrap();
+        broker
+            .publish_in(&mut tx, &queue, TaskMessage::new("discarded", serde_json::json!([])))
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 0);
+
+        let mut tx = Transaction::begin(&broker.queues.conn, IsolationLevel::ReadCommitted).await.unwrap();
+        broker
+            .publish_in(&mut tx, &queue, TaskMessage::new("kept", serde_json::json!([])))
+            .await
+            .unwrap();
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 0);
+        tx.commit().await.unwrap();
+
+        let message = broker.fetch(&queue).await.unwrap().unwrap();
+        assert_eq!(message.payload.task_name, "kept");
+        broker.ack(&message.delivery_tag).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_subscribe_wakes_on_notify() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Counter(AtomicUsize);
+
+        #[async_trait]
+        impl MessageHandler for Counter {
+            async fn handle(&self, _message: BrokerMessage) -> Result<(), TaskError> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        // Polling alone would take a minute
+        let broker = broker(PostgresBrokerConfig {
+            poll_interval: Duration::from_secs(60),
+            ..Default::default()
+        })
+        .await;
+        let queue = test_queue();
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let handle = broker.subscribe(&queue, counter.clone()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        broker.publish(&queue, TaskMessage::new("test_task", serde_json::json!([]))).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(broker.queue_len(&queue).await.unwrap(), 0);
+        handle.cancel();
+    }
+}
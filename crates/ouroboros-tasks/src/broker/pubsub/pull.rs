@@ -1,7 +1,7 @@
 //! Google Cloud Pub/Sub pull-based broker implementation
 
 use crate::{
-    broker::{Broker, BrokerCapabilities, BrokerMessage, DeliveryModel, MessageHandler, PullBroker, SubscriptionHandle},
+    broker::{Broker, BrokerCapabilities, BrokerMessage, DeliveryModel, MessageHandler, PoisonMessage, PullBroker, SubscriptionHandle},
     error::TaskError,
     message::TaskMessage,
 };
@@ -239,7 +239,16 @@ impl PullBroker for PubSubPullBroker {
                                     }
                                 }
                                 Err(e) => {
-                                    tracing::error!("Failed to parse message payload: {}", e);
+                                    let poison = PoisonMessage {
+                                        queue: queue_filter.clone(),
+                                        message: None,
+                                        payload: msg.message.data.clone(),
+                                        deliveries: msg.delivery_attempt().unwrap_or(1) as u32,
+                                        error: format!("Failed to parse message payload: {}", e),
+                                    };
+                                    if let Err(e) = handler.handle_poison(poison).await {
+                                        tracing::error!("Poison message handler error: {}", e);
+                                    }
                                     // Ack malformed messages to avoid infinite loop
                                     if let Err(e) = msg.ack().await {
                                         tracing::error!("Failed to ack malformed message: {}", e);
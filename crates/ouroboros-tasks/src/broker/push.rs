@@ -0,0 +1,385 @@
+//! HTTP push receiver
+//!
+//! Decodes task deliveries pushed over HTTP, for serverless deployments
+//! where nothing can pull. Three formats are understood:
+//!
+//! - `PubSub`: a Google Cloud Pub/Sub push subscription. The message data is
+//!   the JSON `TaskMessage` published by `PubSubPullBroker`; the queue comes
+//!   from the `queue` attribute.
+//! - `CloudTasks`: a Cloud Tasks HTTP target whose body is the JSON
+//!   `TaskMessage`; the queue and retry count come from the
+//!   `X-CloudTasks-*` headers.
+//! - `Json`: the JSON `TaskMessage` as the body, with optional
+//!   `X-Ouroboros-Queue` and `X-Ouroboros-Retry-Count` headers.
+//!
+//! The retry count reported by the pushing service is folded into the
+//! message's `retries`, since redeliveries carry the original message.
+//!
+//! Requests can be authenticated with a bearer token (for Pub/Sub, prefer
+//! letting the platform verify its OIDC tokens) or an HMAC-SHA256 signature
+//! of the body. See `PushWorker` for the endpoint that runs the tasks.
+
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use subtle::ConstantTimeEq;
+
+use super::{Broker, BrokerCapabilities, BrokerMessage, DeliveryModel, PushBroker};
+use crate::{TaskError, TaskMessage};
+
+/// Header carrying the HMAC signature by default
+pub const SIGNATURE_HEADER: &str = "x-ouroboros-signature";
+
+/// Shape of pushed requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PushFormat {
+    /// Google Cloud Pub/Sub push subscription
+    PubSub,
+    /// Google Cloud Tasks HTTP target
+    CloudTasks,
+    /// `TaskMessage` JSON body
+    #[default]
+    Json,
+}
+
+/// How pushed requests are authenticated
+#[derive(Clone, Default)]
+pub enum PushAuth {
+    /// Accept every request (authentication is left to the platform)
+    #[default]
+    None,
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+    /// `sha256=<hex>` HMAC-SHA256 of the body, in `header`
+    HmacSha256 {
+        /// Shared secret
+        secret: Vec<u8>,
+        /// Header name, lowercase
+        header: String,
+    },
+}
+
+impl PushAuth {
+    /// HMAC-SHA256 signatures in the default header
+    pub fn hmac_sha256(secret: impl Into<Vec<u8>>) -> Self {
+        Self::HmacSha256 {
+            secret: secret.into(),
+            header: SIGNATURE_HEADER.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for PushAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print secrets
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Bearer(_) => write!(f, "Bearer(..)"),
+            Self::HmacSha256 { header, .. } => f.debug_struct("HmacSha256").field("header", header).finish(),
+        }
+    }
+}
+
+/// Push receiver configuration
+#[derive(Debug, Clone)]
+pub struct PushBrokerConfig {
+    /// Request format
+    pub format: PushFormat,
+    /// Path the endpoint is served on
+    pub path: String,
+    /// Authentication
+    pub auth: PushAuth,
+    /// Queue for deliveries that do not name one
+    pub default_queue: String,
+}
+
+impl Default for PushBrokerConfig {
+    fn default() -> Self {
+        Self {
+            format: PushFormat::default(),
+            path: "/tasks/push".to_string(),
+            auth: PushAuth::default(),
+            default_queue: "default".to_string(),
+        }
+    }
+}
+
+/// Receives task deliveries pushed over HTTP
+///
+/// Publishing is not supported: tasks are published to the service that
+/// pushes them (a Pub/Sub topic, a Cloud Tasks queue, ...).
+#[derive(Debug, Clone, Default)]
+pub struct HttpPushBroker {
+    config: PushBrokerConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PubSubPush {
+    message: PubSubPushMessage,
+    #[serde(default)]
+    delivery_attempt: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PubSubPushMessage {
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    attributes: HashMap<String, String>,
+    #[serde(default)]
+    message_id: String,
+}
+
+impl HttpPushBroker {
+    /// Create a push receiver
+    pub fn new(config: PushBrokerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configuration
+    pub fn config(&self) -> &PushBrokerConfig {
+        &self.config
+    }
+
+    /// `sha256=<hex>` signature of `body`, for senders and tests
+    pub fn sign(secret: &[u8], body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn decode_message(body: &[u8]) -> Result<TaskMessage, TaskError> {
+        serde_json::from_slice(body)
+            .map_err(|e| TaskError::Deserialization(format!("Invalid task message: {}", e)))
+    }
+
+    /// Decode a Pub/Sub push request into the message, queue, delivery ID
+    /// and number of earlier deliveries
+    fn decode_pubsub(&self, body: &[u8]) -> Result<(TaskMessage, Option<String>, String, u32), TaskError> {
+        let push: PubSubPush = serde_json::from_slice(body)
+            .map_err(|e| TaskError::Deserialization(format!("Invalid Pub/Sub push request: {}", e)))?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(push.message.data.as_bytes())
+            .map_err(|e| TaskError::Deserialization(format!("Invalid Pub/Sub message data: {}", e)))?;
+        let message = Self::decode_message(&data)?;
+        let queue = push.message.attributes.get("queue").cloned();
+        // Only reported when the subscription has a dead-letter policy
+        let earlier = push.delivery_attempt.unwrap_or(1).saturating_sub(1);
+        Ok((message, queue, push.message.message_id, earlier))
+    }
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers.get(name).map(String::as_str)
+}
+
+fn header_count(headers: &HashMap<String, String>, name: &str) -> u32 {
+    header(headers, name).and_then(|v| v.trim().parse().ok()).unwrap_or(0)
+}
+
+#[async_trait]
+impl Broker for HttpPushBroker {
+    async fn connect(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    async fn publish(&self, queue: &str, message: TaskMessage) -> Result<(), TaskError> {
+        Err(TaskError::Broker(format!(
+            "Push receiver cannot publish task {} to queue '{}'; publish to the pushing service instead",
+            message.id, queue
+        )))
+    }
+
+    async fn health_check(&self) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    fn delivery_model(&self) -> DeliveryModel {
+        DeliveryModel::Push
+    }
+
+    fn capabilities(&self) -> BrokerCapabilities {
+        BrokerCapabilities::default()
+    }
+}
+
+impl PushBroker for HttpPushBroker {
+    fn verify_push_request(&self, headers: &HashMap<String, String>, body: &[u8]) -> Result<(), TaskError> {
+        match &self.config.auth {
+            PushAuth::None => Ok(()),
+            PushAuth::Bearer(token) => {
+                let presented = header(headers, "authorization")
+                    .and_then(|v| v.strip_prefix("Bearer "))
+                    .ok_or_else(|| TaskError::Broker("Missing bearer token".to_string()))?;
+                if bool::from(presented.trim().as_bytes().ct_eq(token.as_bytes())) {
+                    Ok(())
+                } else {
+                    Err(TaskError::Broker("Invalid bearer token".to_string()))
+                }
+            }
+            PushAuth::HmacSha256 { secret, header: name } => {
+                let signature = header(headers, name)
+                    .and_then(|v| v.strip_prefix("sha256="))
+                    .and_then(|v| hex::decode(v.trim()).ok())
+                    .ok_or_else(|| TaskError::Broker("Missing or malformed signature".to_string()))?;
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .map_err(|e| TaskError::Configuration(format!("Invalid HMAC secret: {}", e)))?;
+                mac.update(body);
+                mac.verify_slice(&signature)
+                    .map_err(|_| TaskError::Broker("Invalid signature".to_string()))
+            }
+        }
+    }
+
+    fn parse_push_request(&self, headers: &HashMap<String, String>, body: &[u8]) -> Result<BrokerMessage, TaskError> {
+        let (mut payload, queue, delivery_tag, earlier) = match self.config.format {
+            PushFormat::PubSub => self.decode_pubsub(body)?,
+            PushFormat::CloudTasks => {
+                let message = Self::decode_message(body)?;
+                let tag = header(headers, "x-cloudtasks-taskname")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| message.id.to_string());
+                let queue = header(headers, "x-cloudtasks-queuename").map(str::to_string);
+                (message, queue, tag, header_count(headers, "x-cloudtasks-taskretrycount"))
+            }
+            PushFormat::Json => {
+                let message = Self::decode_message(body)?;
+                let tag = message.id.to_string();
+                let queue = header(headers, "x-ouroboros-queue").map(str::to_string);
+                (message, queue, tag, header_count(headers, "x-ouroboros-retry-count"))
+            }
+        };
+        payload.retries = payload.retries.max(earlier);
+
+        let queue = queue.unwrap_or_else(|| self.config.default_queue.clone());
+        let mut message_headers = HashMap::new();
+        message_headers.insert("task-id".to_string(), payload.id.to_string());
+        message_headers.insert("task-name".to_string(), payload.task_name.clone());
+        message_headers.insert("queue".to_string(), queue);
+        if let Some(ref correlation_id) = payload.correlation_id {
+            message_headers.insert("correlation-id".to_string(), correlation_id.clone());
+        }
+
+        Ok(BrokerMessage {
+            delivery_tag,
+            payload,
+            headers: message_headers,
+            timestamp: Utc::now(),
+            redelivered: earlier > 0,
+        })
+    }
+
+    /// Cloud Tasks honours `Retry-After` on 503; any other non-2xx status is
+    /// a plain retry
+    fn nack_status_code(&self) -> u16 {
+        match self.config.format {
+            PushFormat::CloudTasks => 503,
+            PushFormat::PubSub | PushFormat::Json => 500,
+        }
+    }
+
+    fn endpoint_path(&self) -> &str {
+        &self.config.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broker(format: PushFormat, auth: PushAuth) -> HttpPushBroker {
+        HttpPushBroker::new(PushBrokerConfig {
+            format,
+            auth,
+            ..Default::default()
+        })
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let message = TaskMessage::new("add", serde_json::json!([1, 2]));
+        let json = serde_json::to_vec(&message).unwrap();
+
+        // Pub/Sub push
+        let body = serde_json::json!({
+            "message": {
+                "data": base64::engine::general_purpose::STANDARD.encode(&json),
+                "attributes": { "queue": "math" },
+                "messageId": "m-1",
+                "publishTime": "2026-01-01T00:00:00Z",
+            },
+            "subscription": "projects/p/subscriptions/s",
+            "deliveryAttempt": 3,
+        });
+        let pushed = broker(PushFormat::PubSub, PushAuth::None)
+            .parse_push_request(&HashMap::new(), body.to_string().as_bytes())
+            .unwrap();
+        assert_eq!(pushed.payload.id, message.id);
+        assert_eq!(pushed.payload.retries, 2);
+        assert_eq!(pushed.delivery_tag, "m-1");
+        assert_eq!(pushed.headers["queue"], "math");
+        assert!(pushed.redelivered);
+
+        // Cloud Tasks
+        let cloud_headers = headers(&[
+            ("x-cloudtasks-queuename", "math"),
+            ("x-cloudtasks-taskname", "t-1"),
+            ("x-cloudtasks-taskretrycount", "0"),
+        ]);
+        let pushed = broker(PushFormat::CloudTasks, PushAuth::None)
+            .parse_push_request(&cloud_headers, &json)
+            .unwrap();
+        assert_eq!(pushed.delivery_tag, "t-1");
+        assert_eq!(pushed.payload.retries, 0);
+        assert!(!pushed.redelivered);
+
+        // Plain JSON falls back to the default queue
+        let pushed = broker(PushFormat::Json, PushAuth::None)
+            .parse_push_request(&HashMap::new(), &json)
+            .unwrap();
+        assert_eq!(pushed.headers["queue"], "default");
+
+        assert!(matches!(
+            broker(PushFormat::PubSub, PushAuth::None).parse_push_request(&HashMap::new(), b"{}"),
+            Err(TaskError::Deserialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify() {
+        let body = b"{\"task\": 1}";
+
+        let bearer = broker(PushFormat::Json, PushAuth::Bearer("s3cret".to_string()));
+        assert!(bearer
+            .verify_push_request(&headers(&[("authorization", "Bearer s3cret")]), body)
+            .is_ok());
+        assert!(bearer
+            .verify_push_request(&headers(&[("authorization", "Bearer wrong")]), body)
+            .is_err());
+        assert!(bearer.verify_push_request(&HashMap::new(), body).is_err());
+
+        let hmac = broker(PushFormat::Json, PushAuth::hmac_sha256("key"));
+        let signature = HttpPushBroker::sign(b"key", body);
+        assert!(hmac
+            .verify_push_request(&headers(&[(SIGNATURE_HEADER, &signature)]), body)
+            .is_ok());
+        assert!(hmac
+            .verify_push_request(&headers(&[(SIGNATURE_HEADER, &signature)]), b"tampered")
+            .is_err());
+        assert!(format!("{:?}", PushAuth::Bearer("s3cret".to_string())).find("s3cret").is_none());
+    }
+}
@@ -0,0 +1,616 @@
+//! Dead-letter queues
+//!
+//! Messages that ran out of retries, could not be decoded, or hit the
+//! broker's delivery limit are parked per queue together with the error, the
+//! traceback and the attempt history, so they can be inspected, requeued or
+//! purged instead of being lost. Supports in-memory and distributed (Redis)
+//! stores.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::message::TaskAttempt;
+use crate::{Broker, PoisonMessage, TaskError, TaskId, TaskMessage};
+
+/// Why a message was dead-lettered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeadLetterReason {
+    /// The task failed and its retry policy allows no more attempts
+    RetriesExhausted,
+    /// The payload could not be decoded into a task message
+    Undecodable,
+    /// The broker's delivery limit was reached
+    MaxDeliveries,
+}
+
+/// Dead-lettered message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeadLetter {
+    /// Task ID, or a fresh ID if the payload could not be decoded
+    pub id: TaskId,
+    /// Queue the message was consumed from
+    pub queue: String,
+    /// Why the message was dead-lettered
+    pub reason: DeadLetterReason,
+    /// Original task message (with its attempt history)
+    pub message: Option<TaskMessage>,
+    /// Raw payload, kept when the message could not be decoded
+    pub payload: Option<String>,
+    /// Last error
+    pub error: String,
+    /// Last error traceback
+    pub traceback: Option<String>,
+    /// Worker that dead-lettered the message
+    pub worker_id: Option<String>,
+    /// Timestamp when the message was dead-lettered
+    pub dead_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    /// Create a dead letter for a decoded task message
+    pub fn new(
+        queue: impl Into<String>,
+        message: TaskMessage,
+        reason: DeadLetterReason,
+        error: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: message.id.clone(),
+            queue: queue.into(),
+            reason,
+            message: Some(message),
+            payload: None,
+            error: error.into(),
+            traceback: None,
+            worker_id: None,
+            dead_at: Utc::now(),
+        }
+    }
+
+    /// Create a dead letter for a message the broker gave up on
+    pub fn from_poison(poison: PoisonMessage) -> Self {
+        let PoisonMessage { queue, message, payload, error, .. } = poison;
+        match message {
+            Some(message) => Self::new(queue, message, DeadLetterReason::MaxDeliveries, error),
+            None => Self {
+                id: TaskId::new(),
+                queue,
+                reason: DeadLetterReason::Undecodable,
+                message: None,
+                payload: Some(String::from_utf8_lossy(&payload).into_owned()),
+                error,
+                traceback: None,
+                worker_id: None,
+                dead_at: Utc::now(),
+            },
+        }
+    }
+
+    /// Set the error traceback
+    pub fn with_traceback(mut self, traceback: Option<String>) -> Self {
+        self.traceback = traceback;
+        self
+    }
+
+    /// Set the worker ID
+    pub fn with_worker(mut self, worker_id: impl Into<String>) -> Self {
+        self.worker_id = Some(worker_id.into());
+        self
+    }
+
+    /// Failed attempts recorded on the message, oldest first
+    pub fn attempts(&self) -> &[TaskAttempt] {
+        self.message.as_ref().map(|m| m.attempts.as_slice()).unwrap_or(&[])
+    }
+}
+
+/// Trait for dead-letter storage backends
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync + 'static {
+    /// Store a dead letter, replacing any with the same ID on its queue
+    async fn push(&self, letter: DeadLetter) -> Result<(), TaskError>;
+
+    /// List dead letters of a queue, oldest first
+    async fn list(&self, queue: &str, offset: usize, limit: usize) -> Result<Vec<DeadLetter>, TaskError>;
+
+    /// Get a dead letter by ID
+    async fn get(&self, queue: &str, id: &TaskId) -> Result<Option<DeadLetter>, TaskError>;
+
+    /// Remove a dead letter, returning it if it existed
+    async fn remove(&self, queue: &str, id: &TaskId) -> Result<Option<DeadLetter>, TaskError>;
+
+    /// Remove all dead letters of a queue
+    /// Returns the number of dead letters removed
+    async fn purge(&self, queue: &str) -> Result<usize, TaskError>;
+
+    /// Number of dead letters on a queue
+    async fn count(&self, queue: &str) -> Result<usize, TaskError>;
+
+    /// Queues that have dead letters
+    async fn queues(&self) -> Result<Vec<String>, TaskError>;
+}
+
+/// Republish a dead letter to its original queue and remove it from the store
+///
+/// The retry count is reset so the task gets its full retry budget again; the
+/// attempt history is kept. Returns `false` if no such dead letter exists.
+pub async fn requeue<B: Broker + ?Sized>(
+    store: &dyn DeadLetterStore,
+    broker: &B,
+    queue: &str,
+    id: &TaskId,
+) -> Result<bool, TaskError> {
+    let Some(letter) = store.get(queue, id).await? else {
+        return Ok(false);
+    };
+    let Some(mut message) = letter.message else {
+        return Err(TaskError::Deserialization(format!(
+            "Dead letter {} has no decodable message to requeue",
+            id
+        )));
+    };
+
+    message.retries = 0;
+    message.eta = None;
+    broker.publish(queue, message).await?;
+    store.remove(queue, id).await?;
+
+    tracing::info!(
+        task_id = %id,
+        queue = %queue,
+        "Dead letter requeued"
+    );
+
+    Ok(true)
+}
+
+/// Requeue every decodable dead letter of a queue
+/// Returns the number of messages requeued
+pub async fn requeue_all<B: Broker + ?Sized>(
+    store: &dyn DeadLetterStore,
+    broker: &B,
+    queue: &str,
+) -> Result<usize, TaskError> {
+    let mut requeued = 0;
+    let mut skipped = 0;
+
+    loop {
+        let batch = store.list(queue, skipped, 100).await?;
+        if batch.is_empty() {
+            break;
+        }
+        for letter in batch {
+            if letter.message.is_some() {
+                if requeue(store, broker, queue, &letter.id).await? {
+                    requeued += 1;
+                }
+            } else {
+                skipped += 1;
+            }
+        }
+    }
+
+    Ok(requeued)
+}
+
+/// In-memory dead-letter store (thread-safe, non-distributed)
+#[derive(Clone, Default)]
+pub struct InMemoryDeadLetterStore {
+    /// Map of queue -> dead letters, oldest first
+    letters: Arc<DashMap<String, Vec<DeadLetter>>>,
+}
+
+impl InMemoryDeadLetterStore {
+    /// Create a new in-memory dead-letter store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the total number of dead letters
+    pub fn len(&self) -> usize {
+        self.letters.iter().map(|entry| entry.len()).sum()
+    }
+
+    /// Check if store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    async fn push(&self, letter: DeadLetter) -> Result<(), TaskError> {
+        tracing::warn!(
+            task_id = %letter.id,
+            queue = %letter.queue,
+            reason = ?letter.reason,
+            error = %letter.error,
+            "Message dead-lettered"
+        );
+
+        let mut letters = self.letters.entry(letter.queue.clone()).or_default();
+        letters.retain(|l| l.id != letter.id);
+        letters.push(letter);
+        Ok(())
+    }
+
+    async fn list(&self, queue: &str, offset: usize, limit: usize) -> Result<Vec<DeadLetter>, TaskError> {
+        Ok(self
+            .letters
+            .get(queue)
+            .map(|letters| letters.iter().skip(offset).take(limit).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn get(&self, queue: &str, id: &TaskId) -> Result<Option<DeadLetter>, TaskError> {
+        Ok(self
+            .letters
+            .get(queue)
+            .and_then(|letters| letters.iter().find(|l| &l.id == id).cloned()))
+    }
+
+    async fn remove(&self, queue: &str, id: &TaskId) -> Result<Option<DeadLetter>, TaskError> {
+        let Some(mut letters) = self.letters.get_mut(queue) else {
+            return Ok(None);
+        };
+        let removed = letters
+            .iter()
+            .position(|l| &l.id == id)
+            .map(|index| letters.remove(index));
+        let empty = letters.is_empty();
+        drop(letters);
+
+        if empty {
+            self.letters.remove_if(queue, |_, letters| letters.is_empty());
+        }
+        Ok(removed)
+    }
+
+    async fn purge(&self, queue: &str) -> Result<usize, TaskError> {
+        let removed = self.letters.remove(queue).map(|(_, letters)| letters.len()).unwrap_or(0);
+        if removed > 0 {
+            tracing::info!(queue = %queue, removed = removed, "Dead letters purged");
+        }
+        Ok(removed)
+    }
+
+    async fn count(&self, queue: &str) -> Result<usize, TaskError> {
+        Ok(self.letters.get(queue).map(|letters| letters.len()).unwrap_or(0))
+    }
+
+    async fn queues(&self) -> Result<Vec<String>, TaskError> {
+        let mut queues: Vec<String> = self
+            .letters
+            .iter()
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| entry.key().clone())
+            .collect();
+        queues.sort();
+        Ok(queues)
+    }
+}
+
+/// Redis-based dead-letter store (distributed across workers)
+///
+/// Each queue keeps its dead letters in a hash keyed by ID and a sorted set
+/// ordering them by the time they were dead-lettered.
+#[cfg(feature = "redis")]
+pub struct RedisDeadLetterStore {
+    pool: deadpool_redis::Pool,
+    key_prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisDeadLetterStore {
+    /// Create a new Redis dead-letter store
+    pub fn new(pool: deadpool_redis::Pool) -> Self {
+        Self::with_prefix(pool, "dead_letters".to_string())
+    }
+
+    /// Create with a custom key prefix
+    pub fn with_prefix(pool: deadpool_redis::Pool, key_prefix: String) -> Self {
+        Self { pool, key_prefix }
+    }
+
+    /// Get the Redis hash key holding a queue's dead letters
+    fn letters_key(&self, queue: &str) -> String {
+        format!("{}:{}", self.key_prefix, queue)
+    }
+
+    /// Get the Redis sorted set key ordering a queue's dead letters
+    fn index_key(&self, queue: &str) -> String {
+        format!("{}:{}:index", self.key_prefix, queue)
+    }
+
+    /// Get the Redis set key for all queues with dead letters
+    fn queues_key(&self) -> String {
+        format!("{}:queues", self.key_prefix)
+    }
+
+    async fn get_conn(&self) -> Result<deadpool_redis::Connection, TaskError> {
+        self.pool.get().await.map_err(|e| {
+            TaskError::Backend(format!("Failed to get Redis connection: {}", e))
+        })
+    }
+
+    fn decode(value: &str) -> Result<DeadLetter, TaskError> {
+        serde_json::from_str(value).map_err(|e| {
+            TaskError::Deserialization(format!("Failed to deserialize DeadLetter: {}", e))
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl DeadLetterStore for RedisDeadLetterStore {
+    async fn push(&self, letter: DeadLetter) -> Result<(), TaskError> {
+        let serialized = serde_json::to_string(&letter).map_err(|e| {
+            TaskError::Serialization(format!("Failed to serialize DeadLetter: {}", e))
+        })?;
+        let id = letter.id.to_string();
+
+        let mut conn = self.get_conn().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .hset(self.letters_key(&letter.queue), &id, &serialized)
+            .zadd(self.index_key(&letter.queue), &id, letter.dead_at.timestamp_millis())
+            .sadd(self.queues_key(), &letter.queue)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis dead letter push failed: {}", e)))?;
+
+        tracing::warn!(
+            task_id = %letter.id,
+            queue = %letter.queue,
+            reason = ?letter.reason,
+            error = %letter.error,
+            "Message dead-lettered (Redis)"
+        );
+
+        Ok(())
+    }
+
+    async fn list(&self, queue: &str, offset: usize, limit: usize) -> Result<Vec<DeadLetter>, TaskError> {
+        use redis::AsyncCommands;
+
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.get_conn().await?;
+        let ids: Vec<String> = conn
+            .zrange(self.index_key(queue), offset as isize, (offset + limit - 1) as isize)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis ZRANGE failed: {}", e)))?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(self.letters_key(queue))
+            .arg(&ids)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HMGET failed: {}", e)))?;
+
+        values.iter().flatten().map(|value| Self::decode(value)).collect()
+    }
+
+    async fn get(&self, queue: &str, id: &TaskId) -> Result<Option<DeadLetter>, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let value: Option<String> = conn
+            .hget(self.letters_key(queue), id.to_string())
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HGET failed: {}", e)))?;
+
+        value.as_deref().map(Self::decode).transpose()
+    }
+
+    async fn remove(&self, queue: &str, id: &TaskId) -> Result<Option<DeadLetter>, TaskError> {
+        let Some(letter) = self.get(queue, id).await? else {
+            return Ok(None);
+        };
+
+        let mut conn = self.get_conn().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .hdel(self.letters_key(queue), id.to_string())
+            .zrem(self.index_key(queue), id.to_string())
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis dead letter remove failed: {}", e)))?;
+
+        Ok(Some(letter))
+    }
+
+    async fn purge(&self, queue: &str) -> Result<usize, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let (removed, _, _): (usize, (), ()) = redis::pipe()
+            .atomic()
+            .hlen(self.letters_key(queue))
+            .del(&[self.letters_key(queue), self.index_key(queue)])
+            .srem(self.queues_key(), queue)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis dead letter purge failed: {}", e)))?;
+
+        if removed > 0 {
+            tracing::info!(queue = %queue, removed = removed, "Dead letters purged (Redis)");
+        }
+
+        Ok(removed)
+    }
+
+    async fn count(&self, queue: &str) -> Result<usize, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        conn.hlen(self.letters_key(queue))
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HLEN failed: {}", e)))
+    }
+
+    async fn queues(&self) -> Result<Vec<String>, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let candidates: Vec<String> = conn
+            .smembers(self.queues_key())
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis SMEMBERS failed: {}", e)))?;
+
+        // Queues emptied by remove() stay in the set until purged
+        let mut queues = Vec::new();
+        for queue in candidates {
+            let len: usize = conn
+                .hlen(self.letters_key(&queue))
+                .await
+                .map_err(|e| TaskError::Backend(format!("Redis HLEN failed: {}", e)))?;
+            if len > 0 {
+                queues.push(queue);
+            }
+        }
+        queues.sort();
+        Ok(queues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryBroker, InMemoryBrokerConfig};
+
+    fn letter(queue: &str, task: &str) -> DeadLetter {
+        let mut message = TaskMessage::new(task, serde_json::json!([1])).for_retry();
+        let now = Utc::now();
+        message.attempts.push(TaskAttempt {
+            attempt: 0,
+            error: "boom".to_string(),
+            traceback: None,
+            worker_id: None,
+            started_at: now,
+            finished_at: now,
+        });
+        DeadLetter::new(queue, message, DeadLetterReason::RetriesExhausted, "boom")
+            .with_traceback(Some("Traceback".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryDeadLetterStore::new();
+        let first = letter("emails", "send");
+        let second = letter("emails", "send");
+        store.push(first.clone()).await.unwrap();
+        store.push(second.clone()).await.unwrap();
+        store.push(letter("reports", "build")).await.unwrap();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.count("emails").await.unwrap(), 2);
+        assert_eq!(store.queues().await.unwrap(), vec!["emails", "reports"]);
+
+        let listed = store.list("emails", 1, 10).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, second.id);
+
+        let fetched = store.get("emails", &first.id).await.unwrap().unwrap();
+        assert_eq!(fetched.attempts().len(), 1);
+        assert_eq!(fetched.traceback.as_deref(), Some("Traceback"));
+        assert!(store.get("reports", &first.id).await.unwrap().is_none());
+
+        assert!(store.remove("emails", &first.id).await.unwrap().is_some());
+        assert!(store.remove("emails", &first.id).await.unwrap().is_none());
+        assert_eq!(store.purge("emails").await.unwrap(), 1);
+        assert_eq!(store.queues().await.unwrap(), vec!["reports"]);
+    }
+
+    #[test]
+    fn test_from_poison() {
+        let poison = PoisonMessage {
+            queue: "default".to_string(),
+            message: None,
+            payload: b"not json".to_vec(),
+            deliveries: 1,
+            error: "expected value".to_string(),
+        };
+        let letter = DeadLetter::from_poison(poison);
+        assert_eq!(letter.reason, DeadLetterReason::Undecodable);
+        assert_eq!(letter.payload.as_deref(), Some("not json"));
+        assert!(letter.attempts().is_empty());
+
+        let message = TaskMessage::new("task", serde_json::json!([]));
+        let poison = PoisonMessage {
+            queue: "default".to_string(),
+            message: Some(message.clone()),
+            payload: Vec::new(),
+            deliveries: 5,
+            error: "worker crashed".to_string(),
+        };
+        let letter = DeadLetter::from_poison(poison);
+        assert_eq!(letter.reason, DeadLetterReason::MaxDeliveries);
+        assert_eq!(letter.id, message.id);
+    }
+
+    #[tokio::test]
+    async fn test_requeue() {
+        let store = InMemoryDeadLetterStore::new();
+        let broker = InMemoryBroker::new(InMemoryBrokerConfig::default());
+
+        let dead = letter("emails", "send");
+        store.push(dead.clone()).await.unwrap();
+        store
+            .push(DeadLetter::from_poison(PoisonMessage {
+                queue: "emails".to_string(),
+                message: None,
+                payload: b"{".to_vec(),
+                deliveries: 1,
+                error: "EOF".to_string(),
+            }))
+            .await
+            .unwrap();
+
+        assert!(requeue(&store, &broker, "emails", &dead.id).await.unwrap());
+        assert!(!requeue(&store, &broker, "emails", &dead.id).await.unwrap());
+        assert_eq!(broker.queue_len("emails"), 1);
+
+        let requeued = broker.fetch("emails").await.unwrap().unwrap().payload;
+        assert_eq!(requeued.id, dead.id);
+        assert_eq!(requeued.retries, 0);
+        assert_eq!(requeued.attempts.len(), 1);
+
+        // The undecodable one stays put
+        assert_eq!(requeue_all(&store, &broker, "emails").await.unwrap(), 0);
+        assert_eq!(store.count("emails").await.unwrap(), 1);
+    }
+
+    // Integration tests - require Redis running
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_redis_store() {
+        let cfg = deadpool_redis::Config::from_url("redis://127.0.0.1:6379");
+        let pool = cfg.create_pool(Some(deadpool_redis::Runtime::Tokio1)).unwrap();
+        let store = RedisDeadLetterStore::with_prefix(pool, format!("test_dlq_{}", TaskId::new()));
+
+        let dead = letter("emails", "send");
+        store.push(dead.clone()).await.unwrap();
+        assert_eq!(store.count("emails").await.unwrap(), 1);
+        assert_eq!(store.queues().await.unwrap(), vec!["emails"]);
+
+        let listed = store.list("emails", 0, 10).await.unwrap();
+        assert_eq!(listed[0].id, dead.id);
+        assert_eq!(listed[0].attempts().len(), 1);
+
+        assert!(store.remove("emails", &dead.id).await.unwrap().is_some());
+        assert!(store.queues().await.unwrap().is_empty());
+        store.push(dead).await.unwrap();
+        assert_eq!(store.purge("emails").await.unwrap(), 1);
+    }
+}
@@ -49,6 +49,9 @@ pub enum TaskError {
     #[error("Rate limited, retry after {0:?}")]
     RateLimited(Duration),
 
+    #[error("Worker shutting down")]
+    ShuttingDown,
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -0,0 +1,708 @@
+//! Idempotency keys
+//!
+//! A producer that may publish the same logical job more than once tags it
+//! with an idempotency key. `enqueue` reserves the key for a dedupe window and
+//! returns the task ID already holding it instead of publishing a duplicate.
+//! Workers claim the key before executing, so a job that was published or
+//! delivered twice runs its side effects at most once. Supports in-memory and
+//! distributed (Redis) stores.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{Broker, TaskError, TaskId, TaskMessage};
+
+/// Default time a key stays reserved
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default time a worker's claim lasts if the worker goes away mid-task
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(5 * 60);
+
+/// Where the task holding a key is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IdempotencyStatus {
+    /// Published and waiting for a worker
+    Enqueued,
+    /// Claimed by a worker
+    Running,
+    /// Reached a terminal state; it will not run again
+    Completed,
+}
+
+/// Idempotency key record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IdempotencyRecord {
+    /// Idempotency key
+    pub key: String,
+    /// Task holding the key
+    pub task_id: TaskId,
+    /// Where the task is in its life
+    pub status: IdempotencyStatus,
+    /// Worker holding the claim, while running
+    pub worker_id: Option<String>,
+    /// When a running claim lapses
+    pub lease_until: Option<DateTime<Utc>>,
+    /// When the key was reserved
+    pub created_at: DateTime<Utc>,
+    /// When the key is released for reuse
+    pub expires_at: DateTime<Utc>,
+}
+
+impl IdempotencyRecord {
+    fn new(key: &str, task_id: &TaskId, status: IdempotencyStatus, window: Duration) -> Self {
+        let created_at = Utc::now();
+        Self {
+            key: key.to_string(),
+            task_id: task_id.clone(),
+            status,
+            worker_id: None,
+            lease_until: None,
+            created_at,
+            expires_at: after(created_at, window),
+        }
+    }
+
+    /// Check if this record has expired
+    pub fn is_expired(&self) -> bool {
+        Utc::now() > self.expires_at
+    }
+
+    /// Check if a worker is executing the task right now
+    pub fn is_running(&self) -> bool {
+        self.status == IdempotencyStatus::Running
+            && self.lease_until.map(|until| Utc::now() < until).unwrap_or(false)
+    }
+}
+
+/// Result of a worker claiming a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// The worker holds the key and may execute the task
+    Acquired,
+    /// The key belongs to another task; this one is a duplicate
+    Duplicate(TaskId),
+    /// The task already ran
+    Completed,
+    /// Another delivery of the task is executing
+    Running {
+        /// Worker holding the claim
+        worker_id: Option<String>,
+    },
+}
+
+/// Trait for idempotency key storage backends
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// Reserve `key` for `task_id`
+    /// Returns the task already holding the key, or `None` if it was free
+    async fn reserve(&self, key: &str, task_id: &TaskId) -> Result<Option<TaskId>, TaskError>;
+
+    /// Claim `key` for executing `task_id` on `worker_id`
+    ///
+    /// A key nobody reserved is reserved by the claim, so messages published
+    /// without `enqueue` are deduplicated as well.
+    async fn claim(&self, key: &str, task_id: &TaskId, worker_id: &str) -> Result<Claim, TaskError>;
+
+    /// Mark the task as completed; the key stays reserved for another window
+    async fn complete(&self, key: &str, task_id: &TaskId) -> Result<(), TaskError>;
+
+    /// Give up a claim so the task can be claimed again (e.g. for a retry)
+    async fn release(&self, key: &str, task_id: &TaskId) -> Result<(), TaskError>;
+
+    /// Get the record for a key
+    async fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, TaskError>;
+
+    /// Forget a key so it can be reused right away
+    /// Returns `false` if the key was not reserved
+    async fn remove(&self, key: &str) -> Result<bool, TaskError>;
+}
+
+/// Publish a message unless its idempotency key is already reserved
+///
+/// Returns the ID of the task that will run: the message's own ID, or the ID
+/// of the task already holding the key. Messages without a key are always
+/// published.
+pub async fn enqueue<B: Broker + ?Sized>(
+    store: &dyn IdempotencyStore,
+    broker: &B,
+    queue: &str,
+    message: TaskMessage,
+) -> Result<TaskId, TaskError> {
+    let task_id = message.id.clone();
+    let Some(key) = message.idempotency_key.clone() else {
+        broker.publish(queue, message).await?;
+        return Ok(task_id);
+    };
+
+    if let Some(existing) = store.reserve(&key, &task_id).await? {
+        tracing::info!(
+            task_id = %existing,
+            idempotency_key = %key,
+            "Duplicate enqueue, returning existing task"
+        );
+        return Ok(existing);
+    }
+
+    if let Err(e) = broker.publish(queue, message).await {
+        // Let the producer try again with the same key; if that fails too the
+        // reservation simply expires, and the publish error is what matters
+        if let Err(cleanup) = store.remove(&key).await {
+            tracing::warn!(
+                idempotency_key = %key,
+                "Failed to release key after publish error: {}",
+                cleanup
+            );
+        }
+        return Err(e);
+    }
+
+    Ok(task_id)
+}
+
+/// Returns `start + duration`, saturating so `Duration::MAX` means never.
+fn after(start: DateTime<Utc>, duration: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(duration)
+        .ok()
+        .and_then(|duration| start.checked_add_signed(duration))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// In-memory idempotency store (thread-safe, non-distributed)
+#[derive(Clone)]
+pub struct InMemoryIdempotencyStore {
+    /// Map of key -> record
+    records: Arc<DashMap<String, IdempotencyRecord>>,
+    window: Duration,
+    lease: Duration,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Create a new in-memory idempotency store
+    pub fn new() -> Self {
+        Self {
+            records: Arc::new(DashMap::new()),
+            window: DEFAULT_WINDOW,
+            lease: DEFAULT_LEASE,
+        }
+    }
+
+    /// Set how long keys stay reserved
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set how long a claim lasts; keep it above the longest task run time
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Get the number of reserved keys (including expired)
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Check if store is empty
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Clear expired keys (cleanup)
+    /// Returns the number of keys removed
+    pub fn cleanup(&self) -> usize {
+        let mut removed = 0;
+        self.records.retain(|_, record| {
+            let expired = record.is_expired();
+            removed += expired as usize;
+            !expired
+        });
+        removed
+    }
+
+    fn running(&self, mut record: IdempotencyRecord, worker_id: &str) -> IdempotencyRecord {
+        record.status = IdempotencyStatus::Running;
+        record.worker_id = Some(worker_id.to_string());
+        record.lease_until = Some(after(Utc::now(), self.lease));
+        record
+    }
+}
+
+impl Default for InMemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn reserve(&self, key: &str, task_id: &TaskId) -> Result<Option<TaskId>, TaskError> {
+        match self.records.entry(key.to_string()) {
+            Entry::Occupied(entry) if !entry.get().is_expired() => Ok(Some(entry.get().task_id.clone())),
+            entry => {
+                let record = IdempotencyRecord::new(key, task_id, IdempotencyStatus::Enqueued, self.window);
+                entry.insert(record);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn claim(&self, key: &str, task_id: &TaskId, worker_id: &str) -> Result<Claim, TaskError> {
+        match self.records.entry(key.to_string()) {
+            Entry::Occupied(mut entry) if !entry.get().is_expired() => {
+                let record = entry.get();
+                if record.task_id != *task_id {
+                    return Ok(Claim::Duplicate(record.task_id.clone()));
+                }
+                if record.status == IdempotencyStatus::Completed {
+                    return Ok(Claim::Completed);
+                }
+                if record.is_running() {
+                    return Ok(Claim::Running {
+                        worker_id: record.worker_id.clone(),
+                    });
+                }
+                let record = self.running(record.clone(), worker_id);
+                entry.insert(record);
+                Ok(Claim::Acquired)
+            }
+            entry => {
+                let record = IdempotencyRecord::new(key, task_id, IdempotencyStatus::Running, self.window);
+                entry.insert(self.running(record, worker_id));
+                Ok(Claim::Acquired)
+            }
+        }
+    }
+
+    async fn complete(&self, key: &str, task_id: &TaskId) -> Result<(), TaskError> {
+        if let Some(mut record) = self.records.get_mut(key) {
+            if record.task_id == *task_id {
+                record.status = IdempotencyStatus::Completed;
+                record.worker_id = None;
+                record.lease_until = None;
+                record.expires_at = after(Utc::now(), self.window);
+            }
+        }
+        Ok(())
+    }
+
+    async fn release(&self, key: &str, task_id: &TaskId) -> Result<(), TaskError> {
+        if let Some(mut record) = self.records.get_mut(key) {
+            if record.task_id == *task_id && record.status == IdempotencyStatus::Running {
+                record.status = IdempotencyStatus::Enqueued;
+                record.worker_id = None;
+                record.lease_until = None;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, TaskError> {
+        Ok(self
+            .records
+            .get(key)
+            .map(|record| record.clone())
+            .filter(|record| !record.is_expired()))
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, TaskError> {
+        Ok(self.records.remove(key).is_some())
+    }
+}
+
+/// Redis-based idempotency store (distributed across workers)
+///
+/// Each key is a hash expiring with its window; state changes run as Lua
+/// scripts so concurrent producers and workers see a consistent record.
+#[cfg(feature = "redis")]
+pub struct RedisIdempotencyStore {
+    pool: deadpool_redis::Pool,
+    key_prefix: String,
+    window: Duration,
+    lease: Duration,
+}
+
+#[cfg(feature = "redis")]
+const RESERVE_SCRIPT: &str = r#"
+local holder = redis.call('HGET', KEYS[1], 'task_id')
+if holder then
+    return holder
+end
+redis.call('HSET', KEYS[1], 'task_id', ARGV[1], 'status', 'ENQUEUED', 'created_at', ARGV[2])
+redis.call('PEXPIRE', KEYS[1], ARGV[3])
+return false
+"#;
+
+#[cfg(feature = "redis")]
+const CLAIM_SCRIPT: &str = r#"
+local now = tonumber(ARGV[3])
+local lease_until = now + tonumber(ARGV[5])
+local record = redis.call('HMGET', KEYS[1], 'task_id', 'status', 'worker_id', 'lease_until')
+if not record[1] then
+    redis.call('HSET', KEYS[1], 'task_id', ARGV[1], 'status', 'RUNNING', 'worker_id', ARGV[2],
+        'lease_until', lease_until, 'created_at', now)
+    redis.call('PEXPIRE', KEYS[1], ARGV[4])
+    return {'ACQUIRED'}
+end
+if record[1] ~= ARGV[1] then
+    return {'DUPLICATE', record[1]}
+end
+if record[2] == 'COMPLETED' then
+    return {'COMPLETED'}
+end
+if record[2] == 'RUNNING' and tonumber(record[4] or 0) > now then
+    return {'RUNNING', record[3] or ''}
+end
+redis.call('HSET', KEYS[1], 'status', 'RUNNING', 'worker_id', ARGV[2], 'lease_until', lease_until)
+return {'ACQUIRED'}
+"#;
+
+#[cfg(feature = "redis")]
+const COMPLETE_SCRIPT: &str = r#"
+if redis.call('HGET', KEYS[1], 'task_id') == ARGV[1] then
+    redis.call('HSET', KEYS[1], 'status', 'COMPLETED')
+    redis.call('HDEL', KEYS[1], 'worker_id', 'lease_until')
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 1
+"#;
+
+#[cfg(feature = "redis")]
+const RELEASE_SCRIPT: &str = r#"
+local record = redis.call('HMGET', KEYS[1], 'task_id', 'status')
+if record[1] == ARGV[1] and record[2] == 'RUNNING' then
+    redis.call('HSET', KEYS[1], 'status', 'ENQUEUED')
+    redis.call('HDEL', KEYS[1], 'worker_id', 'lease_until')
+end
+return 1
+"#;
+
+#[cfg(feature = "redis")]
+impl RedisIdempotencyStore {
+    /// Create a new Redis idempotency store
+    pub fn new(pool: deadpool_redis::Pool) -> Self {
+        Self::with_prefix(pool, "idempotency".to_string())
+    }
+
+    /// Create with a custom key prefix
+    pub fn with_prefix(pool: deadpool_redis::Pool, key_prefix: String) -> Self {
+        Self {
+            pool,
+            key_prefix,
+            window: DEFAULT_WINDOW,
+            lease: DEFAULT_LEASE,
+        }
+    }
+
+    /// Set how long keys stay reserved
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set how long a claim lasts; keep it above the longest task run time
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Get the Redis key for an idempotency key
+    fn record_key(&self, key: &str) -> String {
+        format!("{}:{}", self.key_prefix, key)
+    }
+
+    async fn get_conn(&self) -> Result<deadpool_redis::Connection, TaskError> {
+        self.pool.get().await.map_err(|e| {
+            TaskError::Backend(format!("Failed to get Redis connection: {}", e))
+        })
+    }
+
+    async fn run_script<T: redis::FromRedisValue>(
+        &self,
+        script: &str,
+        key: &str,
+        args: &[String],
+    ) -> Result<T, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let script = redis::Script::new(script);
+        let mut invocation = script.key(self.record_key(key));
+        for arg in args {
+            invocation.arg(arg);
+        }
+        invocation
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis idempotency script failed: {}", e)))
+    }
+
+    fn parse_task_id(value: &str) -> Result<TaskId, TaskError> {
+        TaskId::from_string(value).map_err(|e| {
+            TaskError::Deserialization(format!("Invalid task ID in idempotency record: {}", e))
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl IdempotencyStore for RedisIdempotencyStore {
+    async fn reserve(&self, key: &str, task_id: &TaskId) -> Result<Option<TaskId>, TaskError> {
+        let args = [
+            task_id.to_string(),
+            Utc::now().timestamp_millis().to_string(),
+            self.window.as_millis().to_string(),
+        ];
+        let holder: Option<String> = self.run_script(RESERVE_SCRIPT, key, &args).await?;
+        holder.map(|id| Self::parse_task_id(&id)).transpose()
+    }
+
+    async fn claim(&self, key: &str, task_id: &TaskId, worker_id: &str) -> Result<Claim, TaskError> {
+        let args = [
+            task_id.to_string(),
+            worker_id.to_string(),
+            Utc::now().timestamp_millis().to_string(),
+            self.window.as_millis().to_string(),
+            self.lease.as_millis().to_string(),
+        ];
+        let reply: Vec<String> = self.run_script(CLAIM_SCRIPT, key, &args).await?;
+        match reply.first().map(String::as_str) {
+            Some("ACQUIRED") => Ok(Claim::Acquired),
+            Some("COMPLETED") => Ok(Claim::Completed),
+            Some("DUPLICATE") => Ok(Claim::Duplicate(Self::parse_task_id(
+                reply.get(1).map(String::as_str).unwrap_or_default(),
+            )?)),
+            Some("RUNNING") => Ok(Claim::Running {
+                worker_id: reply.get(1).filter(|id| !id.is_empty()).cloned(),
+            }),
+            other => Err(TaskError::Backend(format!(
+                "Unexpected idempotency claim reply: {:?}",
+                other
+            ))),
+        }
+    }
+
+    async fn complete(&self, key: &str, task_id: &TaskId) -> Result<(), TaskError> {
+        let args = [task_id.to_string(), self.window.as_millis().to_string()];
+        let _: i64 = self.run_script(COMPLETE_SCRIPT, key, &args).await?;
+        Ok(())
+    }
+
+    async fn release(&self, key: &str, task_id: &TaskId) -> Result<(), TaskError> {
+        let _: i64 = self.run_script(RELEASE_SCRIPT, key, &[task_id.to_string()]).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let record_key = self.record_key(key);
+        let (fields, ttl_ms): (std::collections::HashMap<String, String>, i64) = redis::pipe()
+            .hgetall(&record_key)
+            .pttl(&record_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HGETALL failed: {}", e)))?;
+
+        let Some(task_id) = fields.get("task_id") else {
+            return Ok(None);
+        };
+        let status = match fields.get("status").map(String::as_str) {
+            Some("RUNNING") => IdempotencyStatus::Running,
+            Some("COMPLETED") => IdempotencyStatus::Completed,
+            _ => IdempotencyStatus::Enqueued,
+        };
+        let millis = |field: &str| {
+            fields
+                .get(field)
+                .and_then(|v| v.parse::<i64>().ok())
+                .and_then(DateTime::from_timestamp_millis)
+        };
+
+        Ok(Some(IdempotencyRecord {
+            key: key.to_string(),
+            task_id: Self::parse_task_id(task_id)?,
+            status,
+            worker_id: fields.get("worker_id").cloned(),
+            lease_until: millis("lease_until"),
+            created_at: millis("created_at").unwrap_or_else(Utc::now),
+            expires_at: Utc::now() + chrono::Duration::milliseconds(ttl_ms.max(0)),
+        }))
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let removed: i64 = conn
+            .del(self.record_key(key))
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis DEL failed: {}", e)))?;
+        Ok(removed > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryBroker, InMemoryBrokerConfig};
+
+    #[tokio::test]
+    async fn test_reserve() {
+        let store = InMemoryIdempotencyStore::new();
+        let first = TaskId::new();
+        let second = TaskId::new();
+
+        assert_eq!(store.reserve("order-42", &first).await.unwrap(), None);
+        assert_eq!(store.reserve("order-42", &second).await.unwrap(), Some(first.clone()));
+        assert_eq!(store.reserve("order-43", &second).await.unwrap(), None);
+
+        let record = store.get("order-42").await.unwrap().unwrap();
+        assert_eq!(record.status, IdempotencyStatus::Enqueued);
+        assert_eq!(record.task_id, first);
+
+        assert!(store.remove("order-42").await.unwrap());
+        assert!(!store.remove("order-42").await.unwrap());
+        assert_eq!(store.reserve("order-42", &second).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_window_expiry() {
+        let store = InMemoryIdempotencyStore::new().with_window(Duration::from_millis(20));
+        let first = TaskId::new();
+        assert_eq!(store.reserve("key", &first).await.unwrap(), None);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(store.get("key").await.unwrap().is_none());
+        assert_eq!(store.reserve("key", &TaskId::new()).await.unwrap(), None);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(store.cleanup(), 1);
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unbounded_window() {
+        let store = InMemoryIdempotencyStore::new()
+            .with_window(Duration::MAX)
+            .with_lease(Duration::MAX);
+        let task = TaskId::new();
+        assert_eq!(store.reserve("key", &task).await.unwrap(), None);
+        assert_eq!(store.claim("key", &task, "w1").await.unwrap(), Claim::Acquired);
+        store.complete("key", &task).await.unwrap();
+
+        let record = store.get("key").await.unwrap().unwrap();
+        assert_eq!(record.expires_at, DateTime::<Utc>::MAX_UTC);
+        assert!(!record.is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_claim() {
+        let store = InMemoryIdempotencyStore::new();
+        let task = TaskId::new();
+        store.reserve("key", &task).await.unwrap();
+
+        assert_eq!(store.claim("key", &task, "w1").await.unwrap(), Claim::Acquired);
+        assert_eq!(
+            store.claim("key", &task, "w2").await.unwrap(),
+            Claim::Running {
+                worker_id: Some("w1".to_string())
+            }
+        );
+
+        let other = TaskId::new();
+        assert_eq!(store.claim("key", &other, "w2").await.unwrap(), Claim::Duplicate(task.clone()));
+
+        // A retry can claim again once released
+        store.release("key", &task).await.unwrap();
+        assert_eq!(store.claim("key", &task, "w2").await.unwrap(), Claim::Acquired);
+
+        // Completing with another task's ID changes nothing
+        store.complete("key", &other).await.unwrap();
+        assert_eq!(store.get("key").await.unwrap().unwrap().status, IdempotencyStatus::Running);
+
+        store.complete("key", &task).await.unwrap();
+        assert_eq!(store.claim("key", &task, "w3").await.unwrap(), Claim::Completed);
+        store.release("key", &task).await.unwrap();
+        assert_eq!(store.get("key").await.unwrap().unwrap().status, IdempotencyStatus::Completed);
+
+        // Keys nobody reserved are reserved by the claim
+        let unreserved = TaskId::new();
+        assert_eq!(store.claim("fresh", &unreserved, "w1").await.unwrap(), Claim::Acquired);
+        assert_eq!(store.reserve("fresh", &TaskId::new()).await.unwrap(), Some(unreserved));
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_lease() {
+        let store = InMemoryIdempotencyStore::new().with_lease(Duration::from_millis(20));
+        let task = TaskId::new();
+        assert_eq!(store.claim("key", &task, "w1").await.unwrap(), Claim::Acquired);
+
+        // The first worker went away without completing
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(store.claim("key", &task, "w2").await.unwrap(), Claim::Acquired);
+        assert_eq!(
+            store.get("key").await.unwrap().unwrap().worker_id.as_deref(),
+            Some("w2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enqueue() {
+        let store = InMemoryIdempotencyStore::new();
+        let broker = InMemoryBroker::new(InMemoryBrokerConfig::default());
+
+        let first = TaskMessage::new("charge", serde_json::json!([42])).with_idempotency_key("order-42");
+        let second = TaskMessage::new("charge", serde_json::json!([42])).with_idempotency_key("order-42");
+        let first_id = first.id.clone();
+
+        assert_eq!(enqueue(&store, &broker, "payments", first).await.unwrap(), first_id);
+        assert_eq!(enqueue(&store, &broker, "payments", second).await.unwrap(), first_id);
+        assert_eq!(broker.queue_len("payments"), 1);
+
+        // Messages without a key are never deduplicated
+        let plain = TaskMessage::new("charge", serde_json::json!([42]));
+        enqueue(&store, &broker, "payments", plain.clone()).await.unwrap();
+        enqueue(&store, &broker, "payments", plain).await.unwrap();
+        assert_eq!(broker.queue_len("payments"), 3);
+    }
+
+    // Integration tests - require Redis running
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_redis_store() {
+        let cfg = deadpool_redis::Config::from_url("redis://127.0.0.1:6379");
+        let pool = cfg.create_pool(Some(deadpool_redis::Runtime::Tokio1)).unwrap();
+        let store = RedisIdempotencyStore::with_prefix(pool, format!("test_idem_{}", TaskId::new()));
+
+        let task = TaskId::new();
+        let other = TaskId::new();
+        assert_eq!(store.reserve("key", &task).await.unwrap(), None);
+        assert_eq!(store.reserve("key", &other).await.unwrap(), Some(task.clone()));
+
+        assert_eq!(store.claim("key", &task, "w1").await.unwrap(), Claim::Acquired);
+        assert_eq!(
+            store.claim("key", &task, "w2").await.unwrap(),
+            Claim::Running {
+                worker_id: Some("w1".to_string())
+            }
+        );
+        assert_eq!(store.claim("key", &other, "w2").await.unwrap(), Claim::Duplicate(task.clone()));
+
+        store.release("key", &task).await.unwrap();
+        assert_eq!(store.get("key").await.unwrap().unwrap().status, IdempotencyStatus::Enqueued);
+
+        assert_eq!(store.claim("key", &task, "w2").await.unwrap(), Claim::Acquired);
+        store.complete("key", &task).await.unwrap();
+        assert_eq!(store.claim("key", &task, "w3").await.unwrap(), Claim::Completed);
+
+        assert!(store.remove("key").await.unwrap());
+        assert!(store.get("key").await.unwrap().is_none());
+    }
+}
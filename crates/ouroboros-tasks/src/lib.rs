@@ -11,12 +11,15 @@ pub mod routing;
 pub mod ratelimit;
 pub mod signals;
 pub mod revocation;
+pub mod deadletter;
+pub mod idempotency;
 
 pub mod broker;
 pub mod backend;
 pub mod worker;
 pub mod scheduler;
 pub mod workflow;
+pub mod monitor;
 
 // Optional: Metrics and tracing
 #[cfg(feature = "metrics")]
@@ -33,11 +36,12 @@ pub mod schema;
 pub use error::TaskError;
 pub use state::{TaskState, TaskResult};
 pub use retry::RetryPolicy;
-pub use message::TaskMessage;
+pub use message::{TaskMessage, TaskAttempt, WorkflowStep, MAX_PRIORITY};
 pub use task::{Task, TaskId, TaskContext, TaskOutcome, TaskRegistry};
 pub use routing::{Router, RouterConfig, Route, PatternType, RoutesConfig};
 pub use ratelimit::{
     RateLimiter, RateLimitConfig, RateLimitResult, RateLimitManager,
+    RateLimitScope, RateLimitInfo, RateLimitCommand,
     TokenBucket, SlidingWindow,
 };
 pub use signals::{Signal, SignalHandler, SignalDispatcher, ShutdownReason};
@@ -46,13 +50,30 @@ pub use revocation::{RevocationStore, InMemoryRevocationStore, RevokedTask, Revo
 #[cfg(feature = "redis")]
 pub use revocation::RedisRevocationStore;
 
+pub use deadletter::{
+    DeadLetter, DeadLetterReason, DeadLetterStore, InMemoryDeadLetterStore, requeue, requeue_all,
+};
+
+#[cfg(feature = "redis")]
+pub use deadletter::RedisDeadLetterStore;
+
+pub use idempotency::{
+    Claim, IdempotencyRecord, IdempotencyStatus, IdempotencyStore, InMemoryIdempotencyStore, enqueue,
+};
+
+#[cfg(feature = "redis")]
+pub use idempotency::RedisIdempotencyStore;
+
 // Broker re-exports
 pub use broker::{
     Broker, DeliveryModel, BrokerCapabilities, PullBroker, PushBroker, DelayedBroker,
-    BrokerMessage, MessageHandler, SubscriptionHandle, BrokerConfig,
+    BrokerMessage, MessageHandler, PoisonMessage, PriorityBands, SubscriptionHandle, BrokerConfig,
+    QueueInspector,
 };
 
-#[cfg(any(feature = "nats", feature = "pubsub"))]
+pub use broker::{InMemoryBroker, InMemoryBrokerConfig};
+
+#[cfg(any(feature = "nats", feature = "pubsub", feature = "kv"))]
 pub use broker::BrokerInstance;
 
 #[cfg(feature = "nats")]
@@ -61,21 +82,62 @@ pub use broker::{NatsBroker, NatsBrokerConfig};
 #[cfg(feature = "pubsub")]
 pub use broker::{PubSubPullBroker, PubSubPullConfig};
 
+#[cfg(feature = "kv")]
+pub use broker::{KvBroker, KvBrokerConfig};
+
+#[cfg(feature = "postgres")]
+pub use broker::{PostgresBroker, PostgresBrokerConfig};
+
+#[cfg(feature = "push")]
+pub use broker::{HttpPushBroker, PushAuth, PushBrokerConfig, PushFormat};
+
 // Backend re-exports
-pub use backend::ResultBackend;
+pub use backend::{InMemoryBackend, ResultBackend};
 
 #[cfg(feature = "redis")]
 pub use backend::{RedisBackend, RedisBackendConfig};
 
+#[cfg(feature = "kv")]
+pub use backend::{KvBackend, KvBackendConfig};
+
+#[cfg(feature = "postgres")]
+pub use backend::{PostgresBackend, PostgresBackendConfig};
+
 // Worker re-exports
-pub use worker::{Worker, WorkerConfig};
+pub use worker::{AutoscaleConfig, QueuePolicy, Worker, WorkerConfig};
+
+#[cfg(feature = "push")]
+pub use worker::PushWorker;
 
 // Scheduler re-exports
 #[cfg(all(feature = "scheduler", feature = "nats"))]
 pub use scheduler::{DelayedTaskScheduler, DelayedTaskConfig};
 
 #[cfg(feature = "scheduler")]
-pub use scheduler::{PeriodicScheduler, PeriodicTask, PeriodicSchedule};
+pub use scheduler::{
+    CatchUp, InMemoryScheduleStore, PeriodicScheduler, PeriodicSchedule, PeriodicTask, ScheduleEntry, ScheduleStore,
+};
+
+#[cfg(all(feature = "scheduler", feature = "redis"))]
+pub use scheduler::RedisScheduleStore;
+
+#[cfg(all(feature = "scheduler", feature = "kv"))]
+pub use scheduler::KvScheduleStore;
+
+#[cfg(all(feature = "scheduler", feature = "postgres"))]
+pub use scheduler::PostgresScheduleStore;
+
+// Monitor re-exports
+pub use monitor::{
+    EventCollector, MonitorConfig, MonitorStats, RateLimitControl,
+    TaskCounts, TaskInfo, TaskQuery, WorkerInfo, WorkerStatus,
+};
+
+#[cfg(feature = "monitor")]
+pub use monitor::MonitorApi;
+
+#[cfg(feature = "redis")]
+pub use monitor::RedisEventRelay;
 
 // Workflow re-exports
 pub use workflow::{
@@ -86,6 +148,8 @@ pub use workflow::{
     ChainMeta, ChordMeta,
     Map, Starmap, Chunks,
     xmap, starmap, chunks,
+    Dag, DagNode, DagRun, AsyncDagResult, Condition,
+    NodeRun, NodeState, StepOutcome, WorkflowStatus,
 };
 
 // Metrics re-exports
@@ -5,6 +5,37 @@ use serde::{Deserialize, Serialize};
 
 use crate::TaskId;
 
+/// Highest message priority; priorities range from 0 (default) to this
+pub const MAX_PRIORITY: u8 = 9;
+
+/// One failed execution of a task, kept on the message across retries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TaskAttempt {
+    /// Retry count the attempt ran with (0 = first execution)
+    pub attempt: u32,
+    /// Error message
+    pub error: String,
+    /// Error traceback, if the task reported one
+    pub traceback: Option<String>,
+    /// Worker that ran the attempt
+    pub worker_id: Option<String>,
+    /// When the attempt started
+    pub started_at: DateTime<Utc>,
+    /// When the attempt finished
+    pub finished_at: DateTime<Utc>,
+}
+
+/// The workflow step a task message runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkflowStep {
+    /// Workflow run ID
+    pub workflow_id: TaskId,
+    /// Node name within the workflow
+    pub node: String,
+}
+
 /// Task message sent through the broker
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -19,6 +50,9 @@ pub struct TaskMessage {
     pub kwargs: serde_json::Value,
     /// Number of retry attempts so far
     pub retries: u32,
+    /// Priority from 0 to `MAX_PRIORITY`; higher runs first
+    #[serde(default)]
+    pub priority: u8,
     /// Earliest time to execute (for delayed tasks)
     pub eta: Option<DateTime<Utc>>,
     /// Task expiration time
@@ -29,6 +63,15 @@ pub struct TaskMessage {
     pub parent_id: Option<TaskId>,
     /// Root task ID (for workflows)
     pub root_id: Option<TaskId>,
+    /// Key identifying the logical job, so duplicates can be detected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Workflow step this task runs, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<WorkflowStep>,
+    /// Failed attempts so far, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<TaskAttempt>,
 }
 
 impl TaskMessage {
@@ -40,11 +83,15 @@ impl TaskMessage {
             args,
             kwargs: serde_json::Value::Null,
             retries: 0,
+            priority: 0,
             eta: None,
             expires: None,
             correlation_id: None,
             parent_id: None,
             root_id: None,
+            idempotency_key: None,
+            workflow: None,
+            attempts: Vec::new(),
         }
     }
 
@@ -54,6 +101,12 @@ impl TaskMessage {
         self
     }
 
+    /// Set priority (clamped to `MAX_PRIORITY`)
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority.min(MAX_PRIORITY);
+        self
+    }
+
     /// Set ETA for delayed execution
     pub fn with_eta(mut self, eta: DateTime<Utc>) -> Self {
         self.eta = Some(eta);
@@ -84,6 +137,12 @@ impl TaskMessage {
         self
     }
 
+    /// Set idempotency key
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
     /// Check if the task has expired
     pub fn is_expired(&self) -> bool {
         self.expires.map(|e| e < Utc::now()).unwrap_or(false)
@@ -116,6 +175,32 @@ mod tests {
         assert!(!msg.is_expired());
     }
 
+    #[test]
+    fn test_priority() {
+        let msg = TaskMessage::new("task", serde_json::json!([]));
+        assert_eq!(msg.priority, 0);
+        assert_eq!(msg.clone().with_priority(5).priority, 5);
+        assert_eq!(msg.with_priority(200).priority, MAX_PRIORITY);
+
+        // Messages published before priorities existed decode as 0
+        let mut json = serde_json::to_value(TaskMessage::new("task", serde_json::json!([]))).unwrap();
+        json.as_object_mut().unwrap().remove("priority");
+        let msg: TaskMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(msg.priority, 0);
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let msg = TaskMessage::new("task", serde_json::json!([]));
+        let json = serde_json::to_value(&msg).unwrap();
+        assert!(json.get("idempotency_key").is_none());
+
+        let msg = msg.with_idempotency_key("order-42");
+        let decoded: TaskMessage = serde_json::from_str(&serde_json::to_string(&msg).unwrap()).unwrap();
+        assert_eq!(decoded.idempotency_key.as_deref(), Some("order-42"));
+        assert_eq!(decoded.for_retry().idempotency_key.as_deref(), Some("order-42"));
+    }
+
     #[test]
     fn test_delayed_message() {
         let future = Utc::now() + chrono::Duration::hours(1);
@@ -123,6 +208,28 @@ mod tests {
         assert!(!msg.is_ready());
     }
 
+    #[test]
+    fn test_attempts_roundtrip() {
+        let msg = TaskMessage::new("task", serde_json::json!([]));
+        let json = serde_json::to_value(&msg).unwrap();
+        assert!(json.get("attempts").is_none());
+
+        let mut msg: TaskMessage = serde_json::from_value(json).unwrap();
+        assert!(msg.attempts.is_empty());
+
+        let now = Utc::now();
+        msg.attempts.push(TaskAttempt {
+            attempt: 0,
+            error: "boom".to_string(),
+            traceback: Some("at task()".to_string()),
+            worker_id: Some("worker-1".to_string()),
+            started_at: now,
+            finished_at: now,
+        });
+        let decoded: TaskMessage = serde_json::from_str(&serde_json::to_string(&msg).unwrap()).unwrap();
+        assert_eq!(decoded.attempts, msg.attempts);
+    }
+
     #[test]
     fn test_retry() {
         let msg = TaskMessage::new("retry_task", serde_json::json!([]));
@@ -0,0 +1,428 @@
+//! HTTP API for the monitor
+//!
+//! | Route | |
+//! |---|---|
+//! | `GET /api/workers`, `GET /api/workers/:name` | Workers and their heartbeats |
+//! | `GET /api/queues` | Queues and their depth |
+//! | `GET /api/tasks?state=&task_name=&worker=&limit=`, `GET /api/tasks/:id` | Recent tasks |
+//! | `POST /api/tasks/:id/revoke` | Revoke a task, body `{"terminate": false}` |
+//! | `GET /api/stats` | Worker and task counts |
+//! | `GET /api/events` | Server-sent event stream of signals |
+//! | `GET /api/rate-limits`, `POST /api/rate-limits` | List or change rate limits |
+//!
+//! The API has no authentication of its own: `with_authorization` installs a
+//! check run on every request, and the `POST` routes are refused (403) until
+//! one is installed.
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use super::{EventCollector, RateLimitControl, TaskQuery};
+use crate::broker::QueueInspector;
+use crate::ratelimit::RateLimitCommand;
+use crate::revocation::RevocationStore;
+use crate::{TaskError, TaskId};
+
+/// Decides from a request's headers whether it may use the API
+type Authorizer = Arc<dyn Fn(&HeaderMap) -> bool + Send + Sync>;
+
+/// HTTP API over an `EventCollector`
+#[derive(Clone)]
+pub struct MonitorApi {
+    collector: EventCollector,
+    queues: Option<Arc<dyn QueueInspector>>,
+    revocations: Option<Arc<dyn RevocationStore>>,
+    rate_limits: Option<Arc<dyn RateLimitControl>>,
+    authorizer: Option<Authorizer>,
+}
+
+impl MonitorApi {
+    /// Serve `collector`; revocation and rate-limit routes answer 501 until configured
+    pub fn new(collector: EventCollector) -> Self {
+        Self {
+            collector,
+            queues: None,
+            revocations: None,
+            rate_limits: None,
+            authorizer: None,
+        }
+    }
+
+    /// Report queue depths from a broker
+    pub fn with_queue_inspector(mut self, queues: Arc<dyn QueueInspector>) -> Self {
+        self.queues = Some(queues);
+        self
+    }
+
+    /// Revoke tasks through a revocation store shared with the workers
+    pub fn with_revocation_store(mut self, store: Arc<dyn RevocationStore>) -> Self {
+        self.revocations = Some(store);
+        self
+    }
+
+    /// Change rate limits through `control`
+    pub fn with_rate_limits(mut self, control: Arc<dyn RateLimitControl>) -> Self {
+        self.rate_limits = Some(control);
+        self
+    }
+
+    /// Check every request with `authorize`, answering 401 when it returns
+    /// `false`; revoking tasks and changing rate limits need this
+    pub fn with_authorization<F>(mut self, authorize: F) -> Self
+    where
+        F: Fn(&HeaderMap) -> bool + Send + Sync + 'static,
+    {
+        self.authorizer = Some(Arc::new(authorize));
+        self
+    }
+
+    /// Routes, for mounting in a larger application
+    pub fn router(self) -> Router {
+        let api = Arc::new(self);
+        Router::new()
+            .route("/api/workers", get(list_workers))
+            .route("/api/workers/:name", get(get_worker))
+            .route("/api/queues", get(list_queues))
+            .route("/api/tasks", get(list_tasks))
+            .route("/api/tasks/:id", get(get_task))
+            .route("/api/tasks/:id/revoke", post(revoke_task))
+            .route("/api/stats", get(stats))
+            .route("/api/events", get(events))
+            .route("/api/rate-limits", get(list_rate_limits).post(set_rate_limit))
+            .layer(middleware::from_fn_with_state(api.clone(), authorize))
+            .with_state(api)
+    }
+
+    /// Serve on `addr` until `shutdown` is cancelled
+    pub async fn serve(self, addr: SocketAddr, shutdown: CancellationToken) -> Result<(), TaskError> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| TaskError::Configuration(format!("Failed to bind {}: {}", addr, e)))?;
+        tracing::info!(%addr, "Monitor listening");
+
+        axum::serve(listener, self.router())
+            .with_graceful_shutdown(async move { shutdown.cancelled().await })
+            .await
+            .map_err(|e| TaskError::Internal(format!("Monitor server failed: {}", e)))
+    }
+}
+
+type ApiState = State<Arc<MonitorApi>>;
+
+struct ApiError(StatusCode, String);
+
+impl From<TaskError> for ApiError {
+    fn from(e: TaskError) -> Self {
+        let status = match &e {
+            TaskError::TaskNotFound(_) => StatusCode::NOT_FOUND,
+            TaskError::InvalidTaskId(_) | TaskError::Configuration(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn not_configured(what: &str) -> ApiError {
+    ApiError(StatusCode::NOT_IMPLEMENTED, format!("{} is not configured", what))
+}
+
+async fn authorize(State(api): ApiState, request: Request, next: Next) -> Response {
+    match &api.authorizer {
+        Some(authorizer) if !authorizer(request.headers()) => {
+            ApiError(StatusCode::UNAUTHORIZED, "Unauthorized".to_string()).into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+/// Refuse changes from an API anyone who can reach it may call
+fn require_authorization(api: &MonitorApi, action: &str) -> Result<(), ApiError> {
+    if api.authorizer.is_none() {
+        return Err(ApiError(
+            StatusCode::FORBIDDEN,
+            format!("{} is disabled until the monitor is configured with authorization", action),
+        ));
+    }
+    Ok(())
+}
+
+async fn list_workers(State(api): ApiState) -> impl IntoResponse {
+    Json(api.collector.workers())
+}
+
+async fn get_worker(State(api): ApiState, Path(name): Path<String>) -> Result<impl IntoResponse, ApiError> {
+    api.collector
+        .worker(&name)
+        .map(Json)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Worker not found: {}", name)))
+}
+
+#[derive(Serialize)]
+struct QueueDepth {
+    name: String,
+    /// None without a queue inspector
+    depth: Option<usize>,
+}
+
+async fn list_queues(State(api): ApiState) -> Result<impl IntoResponse, ApiError> {
+    let mut queues = Vec::new();
+    for name in api.collector.queues() {
+        let depth = match &api.queues {
+            Some(inspector) => Some(inspector.queue_depth(&name).await?),
+            None => None,
+        };
+        queues.push(QueueDepth { name, depth });
+    }
+    Ok(Json(queues))
+}
+
+async fn list_tasks(State(api): ApiState, Query(query): Query<TaskQuery>) -> impl IntoResponse {
+    Json(api.collector.tasks(&query))
+}
+
+async fn get_task(State(api): ApiState, Path(id): Path<String>) -> Result<impl IntoResponse, ApiError> {
+    let task_id = TaskId::from_string(&id)?;
+    api.collector
+        .task(&task_id)
+        .map(Json)
+        .ok_or_else(|| TaskError::TaskNotFound(id).into())
+}
+
+#[derive(Default, Deserialize)]
+struct RevokeRequest {
+    #[serde(default)]
+    terminate: bool,
+}
+
+async fn revoke_task(
+    State(api): ApiState,
+    Path(id): Path<String>,
+    body: Option<Json<RevokeRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_authorization(&api, "Revoking tasks")?;
+    let store = api.revocations.as_ref().ok_or_else(|| not_configured("Revocation store"))?;
+    let task_id = TaskId::from_string(&id)?;
+    let terminate = body.map(|Json(b)| b.terminate).unwrap_or_default();
+    store.revoke(&task_id, terminate).await?;
+    Ok(Json(serde_json::json!({ "task_id": task_id, "revoked": true, "terminate": terminate })))
+}
+
+async fn stats(State(api): ApiState) -> impl IntoResponse {
+    Json(api.collector.stats())
+}
+
+async fn events(State(api): ApiState) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures::stream::unfold(api.collector.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(signal) => {
+                    let event = Event::default()
+                        .event(signal.signal_type())
+                        .json_data(&signal)
+                        .unwrap_or_else(|_| Event::default().comment("unserializable signal"));
+                    return Some((Ok(event), rx));
+                }
+                // A slow client misses events rather than holding up the rest
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!(skipped, "Event stream subscriber lagged");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn list_rate_limits(State(api): ApiState) -> Result<impl IntoResponse, ApiError> {
+    let control = api.rate_limits.as_ref().ok_or_else(|| not_configured("Rate limiting"))?;
+    Ok(Json(control.limits().await?))
+}
+
+async fn set_rate_limit(
+    State(api): ApiState,
+    Json(command): Json<RateLimitCommand>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_authorization(&api, "Changing rate limits")?;
+    let control = api.rate_limits.as_ref().ok_or_else(|| not_configured("Rate limiting"))?;
+    control.apply(command).await?;
+    Ok(Json(control.limits().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::{Broker, InMemoryBroker, InMemoryBrokerConfig};
+    use crate::ratelimit::{RateLimitManager, RateLimitScope};
+    use crate::revocation::InMemoryRevocationStore;
+    use crate::signals::Signal;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "Bearer s3cret";
+
+    async fn call(router: &Router, method: &str, uri: &str, body: Option<serde_json::Value>) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .header("authorization", TOKEN);
+        let request = match body {
+            Some(body) => request.body(Body::from(body.to_string())).unwrap(),
+            None => request.body(Body::empty()).unwrap(),
+        };
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_monitor_api() {
+        let collector = EventCollector::default();
+        let broker = Arc::new(InMemoryBroker::new(InMemoryBrokerConfig::default()));
+        let revocations = Arc::new(InMemoryRevocationStore::new());
+        let limits = Arc::new(RateLimitManager::new());
+        let router = MonitorApi::new(collector.clone())
+            .with_queue_inspector(broker.clone())
+            .with_revocation_store(revocations.clone())
+            .with_rate_limits(limits.clone())
+            .with_authorization(|headers| {
+                headers.get("authorization").is_some_and(|value| value == TOKEN)
+            })
+            .router();
+
+        let task_id = TaskId::new();
+        collector.record(Signal::WorkerInit {
+            worker_name: "w1".to_string(),
+            queues: vec!["default".to_string()],
+            concurrency: 2,
+        });
+        collector.record(Signal::TaskReceived {
+            task_id: task_id.clone(),
+            task_name: "add".to_string(),
+            queue: "default".to_string(),
+            worker_name: "w1".to_string(),
+        });
+        broker
+            .publish("default", crate::TaskMessage::new("add", serde_json::json!([1, 2])))
+            .await
+            .unwrap();
+
+        let (status, body) = call(&router, "GET", "/api/workers", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["name"], "w1");
+        assert_eq!(body[0]["status"], "online");
+
+        let (status, _) = call(&router, "GET", "/api/workers/nope", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, body) = call(&router, "GET", "/api/queues", None).await;
+        assert_eq!(body, serde_json::json!([{ "name": "default", "depth": 1 }]));
+
+        let (_, body) = call(&router, "GET", "/api/tasks?state=RECEIVED&worker=w1", None).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        let (_, body) = call(&router, "GET", &format!("/api/tasks/{}", task_id), None).await;
+        assert_eq!(body["task_name"], "add");
+
+        let (status, _) = call(&router, "GET", "/api/tasks/not-a-task-id", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let uri = format!("/api/tasks/{}/revoke", task_id);
+        let (status, _) = call(&router, "POST", &uri, Some(serde_json::json!({ "terminate": true }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(revocations.is_revoked(&task_id).await.unwrap());
+
+        let (_, body) = call(&router, "GET", "/api/stats", None).await;
+        assert_eq!(body["workers_online"], 1);
+        assert_eq!(body["tasks"]["received"], 1);
+
+        let command = serde_json::json!({
+            "action": "set",
+            "scope": "queue",
+            "key": "default",
+            "config": { "rate": 5.0, "capacity": 10 },
+        });
+        let (status, body) = call(&router, "POST", "/api/rate-limits", Some(command)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["key"], "default");
+        let infos = limits.limits();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].scope, RateLimitScope::Queue);
+
+        let command = serde_json::json!({
+            "action": "set",
+            "scope": "task",
+            "key": "add",
+            "config": { "rate": -1.0, "capacity": 10 },
+        });
+        let (status, _) = call(&router, "POST", "/api/rate-limits", Some(command)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_authorization() {
+        let revocations = Arc::new(InMemoryRevocationStore::new());
+        let api = MonitorApi::new(EventCollector::default())
+            .with_revocation_store(revocations.clone())
+            .with_rate_limits(Arc::new(RateLimitManager::new()));
+        let task_id = TaskId::new();
+        let uri = format!("/api/tasks/{}/revoke", task_id);
+        let command = serde_json::json!({
+            "action": "set",
+            "scope": "queue",
+            "key": "default",
+            "config": { "rate": 5.0, "capacity": 10 },
+        });
+
+        // Without an authorizer, reads work and changes are refused
+        let router = api.clone().router();
+        let (status, _) = call(&router, "GET", "/api/stats", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, "POST", &uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(&router, "POST", "/api/rate-limits", Some(command)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(!revocations.is_revoked(&task_id).await.unwrap());
+
+        // A rejecting authorizer guards every route
+        let router = api.with_authorization(|_| false).router();
+        let (status, _) = call(&router, "GET", "/api/stats", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&router, "POST", &uri, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(!revocations.is_revoked(&task_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_not_configured() {
+        let router = MonitorApi::new(EventCollector::default()).with_authorization(|_| true).router();
+
+        let uri = format!("/api/tasks/{}/revoke", TaskId::new());
+        let (status, _) = call(&router, "POST", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+        let (status, _) = call(&router, "GET", "/api/rate-limits", None).await;
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+
+        let (_, body) = call(&router, "GET", "/api/queues", None).await;
+        assert_eq!(body, serde_json::json!([]));
+    }
+}
@@ -0,0 +1,651 @@
+//! Monitoring of workers and tasks
+//!
+//! `EventCollector` is a `SignalHandler` that keeps a live view of workers
+//! (from their heartbeats) and of recent tasks, and re-broadcasts every signal
+//! to subscribers. Register it on a worker's `SignalDispatcher`, or, when
+//! workers run in other processes, feed it from a `RedisEventRelay`.
+//!
+//! With the `monitor` feature, `MonitorApi` serves the collector over HTTP,
+//! with a server-sent event stream and controls for revoking tasks and
+//! changing rate limits.
+//!
+//! # Example
+//! ```rust,ignore
+//! let collector = EventCollector::new(MonitorConfig::default());
+//! let dispatcher = SignalDispatcher::new().on_all(collector.clone());
+//! let worker = Worker::new(config, broker.clone(), backend, registry)
+//!     .with_signal_dispatcher(dispatcher);
+//!
+//! MonitorApi::new(collector)
+//!     .with_queue_inspector(broker)
+//!     .with_authorization(|headers| headers.get("authorization").is_some_and(|v| v == token))
+//!     .serve("0.0.0.0:5555".parse()?, shutdown)
+//!     .await?;
+//! ```
+
+#[cfg(feature = "monitor")]
+mod http;
+#[cfg(feature = "redis")]
+mod relay;
+
+#[cfg(feature = "monitor")]
+pub use http::MonitorApi;
+#[cfg(feature = "redis")]
+pub use relay::RedisEventRelay;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::ratelimit::{RateLimitCommand, RateLimitInfo, RateLimitManager};
+use crate::signals::{Signal, SignalHandler};
+use crate::{TaskError, TaskId, TaskState};
+
+/// Monitoring configuration
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    /// Workers not heard from for this long are reported offline
+    pub worker_timeout: Duration,
+    /// Tasks kept; the oldest are forgotten first
+    pub max_tasks: usize,
+    /// Signals buffered for each live subscriber before it misses some
+    pub event_buffer: usize,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            worker_timeout: Duration::from_secs(60),
+            max_tasks: 10_000,
+            event_buffer: 1024,
+        }
+    }
+}
+
+/// Whether a worker is alive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerStatus {
+    /// Heard from within the worker timeout
+    Online,
+    /// Shut down, or silent for longer than the worker timeout
+    Offline,
+}
+
+/// A worker as last reported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerInfo {
+    /// Worker name
+    pub name: String,
+    /// Liveness when the snapshot was taken
+    pub status: WorkerStatus,
+    /// Queues consumed
+    pub queues: Vec<String>,
+    /// Maximum parallel tasks
+    pub concurrency: Option<usize>,
+    /// Tasks executing at the last heartbeat
+    pub active_tasks: usize,
+    /// Tasks executed at the last heartbeat
+    pub processed: u64,
+    /// When the worker started
+    pub started_at: Option<DateTime<Utc>>,
+    /// Last signal from the worker
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A task as last reported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    /// Task ID
+    pub task_id: TaskId,
+    /// Task name
+    pub task_name: String,
+    /// Latest state
+    pub state: TaskState,
+    /// Queue the task was published to or consumed from
+    pub queue: Option<String>,
+    /// Worker that last handled the task
+    pub worker: Option<String>,
+    /// Positional arguments
+    pub args: Option<serde_json::Value>,
+    /// Keyword arguments
+    pub kwargs: Option<serde_json::Value>,
+    /// Result, once succeeded
+    pub result: Option<serde_json::Value>,
+    /// Error, or the reason for a retry, revocation or rejection
+    pub error: Option<String>,
+    /// Retries so far
+    pub retries: u32,
+    /// When a worker received the task
+    pub received_at: Option<DateTime<Utc>>,
+    /// When execution started
+    pub started_at: Option<DateTime<Utc>>,
+    /// When execution finished
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Runtime of the last execution
+    pub runtime_ms: Option<u64>,
+    /// Last signal for the task
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TaskInfo {
+    fn new(task_id: TaskId, task_name: String, now: DateTime<Utc>) -> Self {
+        Self {
+            task_id,
+            task_name,
+            state: TaskState::Pending,
+            queue: None,
+            worker: None,
+            args: None,
+            kwargs: None,
+            result: None,
+            error: None,
+            retries: 0,
+            received_at: None,
+            started_at: None,
+            finished_at: None,
+            runtime_ms: None,
+            updated_at: now,
+        }
+    }
+
+    /// Signals are dispatched concurrently and may arrive out of order, so a
+    /// finished task is not moved back to an earlier state
+    fn set_state(&mut self, state: TaskState) {
+        if !self.state.is_terminal() || state.is_terminal() {
+            self.state = state;
+        }
+    }
+}
+
+/// Filter for listing tasks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskQuery {
+    /// Only tasks in this state
+    pub state: Option<TaskState>,
+    /// Only tasks with this name
+    pub task_name: Option<String>,
+    /// Only tasks last handled by this worker
+    pub worker: Option<String>,
+    /// Maximum number of tasks (default 100)
+    pub limit: Option<usize>,
+}
+
+/// Number of known tasks in each state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskCounts {
+    pub pending: usize,
+    pub received: usize,
+    pub started: usize,
+    pub success: usize,
+    pub failure: usize,
+    pub retry: usize,
+    pub revoked: usize,
+    pub rejected: usize,
+}
+
+/// Overview of workers and tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorStats {
+    /// Workers online
+    pub workers_online: usize,
+    /// Workers offline
+    pub workers_offline: usize,
+    /// Known tasks by state
+    pub tasks: TaskCounts,
+}
+
+struct WorkerEntry {
+    info: WorkerInfo,
+    shut_down: bool,
+}
+
+#[derive(Default)]
+struct State {
+    workers: HashMap<String, WorkerEntry>,
+    tasks: HashMap<TaskId, TaskInfo>,
+    /// Task IDs, oldest first
+    order: VecDeque<TaskId>,
+    queues: BTreeSet<String>,
+}
+
+impl State {
+    fn worker(&mut self, name: &str, now: DateTime<Utc>) -> &mut WorkerEntry {
+        let entry = self.workers.entry(name.to_string()).or_insert_with(|| WorkerEntry {
+            info: WorkerInfo {
+                name: name.to_string(),
+                status: WorkerStatus::Online,
+                queues: Vec::new(),
+                concurrency: None,
+                active_tasks: 0,
+                processed: 0,
+                started_at: None,
+                last_seen: now,
+            },
+            shut_down: false,
+        });
+        entry.info.last_seen = entry.info.last_seen.max(now);
+        entry
+    }
+
+    fn task(&mut self, task_id: &TaskId, task_name: &str, now: DateTime<Utc>, max_tasks: usize) -> &mut TaskInfo {
+        if !self.tasks.contains_key(task_id) {
+            while self.order.len() >= max_tasks.max(1) {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.tasks.remove(&oldest);
+                }
+            }
+            self.order.push_back(task_id.clone());
+            self.tasks
+                .insert(task_id.clone(), TaskInfo::new(task_id.clone(), task_name.to_string(), now));
+        }
+        let task = self.tasks.get_mut(task_id).expect("task was just inserted");
+        task.updated_at = now;
+        task
+    }
+}
+
+struct Inner {
+    config: MonitorConfig,
+    state: Mutex<State>,
+    events: broadcast::Sender<Signal>,
+}
+
+/// Collects signals into a live view of workers and tasks
+#[derive(Clone)]
+pub struct EventCollector {
+    inner: Arc<Inner>,
+}
+
+impl Default for EventCollector {
+    fn default() -> Self {
+        Self::new(MonitorConfig::default())
+    }
+}
+
+impl EventCollector {
+    /// Create a collector
+    pub fn new(config: MonitorConfig) -> Self {
+        let (events, _) = broadcast::channel(config.event_buffer.max(1));
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                state: Mutex::new(State::default()),
+                events,
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.inner.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Apply a signal to the view and pass it on to subscribers
+    pub fn record(&self, signal: Signal) {
+        let now = Utc::now();
+        let max_tasks = self.inner.config.max_tasks;
+        {
+            let mut state = self.lock();
+            let state = &mut *state;
+            match &signal {
+                Signal::BeforeTaskPublish { task_id, task_name, queue, args, kwargs, .. } => {
+                    state.queues.insert(queue.clone());
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.queue = Some(queue.clone());
+                    task.args = Some(args.clone());
+                    task.kwargs = Some(kwargs.clone());
+                }
+                Signal::AfterTaskPublish { task_id, task_name, queue } => {
+                    state.queues.insert(queue.clone());
+                    state.task(task_id, task_name, now, max_tasks).queue = Some(queue.clone());
+                }
+                Signal::TaskReceived { task_id, task_name, queue, worker_name } => {
+                    state.queues.insert(queue.clone());
+                    state.worker(worker_name, now);
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Received);
+                    task.queue = Some(queue.clone());
+                    task.worker = Some(worker_name.clone());
+                    task.received_at = Some(now);
+                }
+                Signal::TaskPrerun { task_id, task_name, args, kwargs, worker_name } => {
+                    state.worker(worker_name, now);
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Started);
+                    task.args = Some(args.clone());
+                    task.kwargs = Some(kwargs.clone());
+                    task.worker = Some(worker_name.clone());
+                    task.started_at = Some(now);
+                }
+                Signal::TaskPostrun { task_id, task_name, state: task_state, runtime, worker_name } => {
+                    state.worker(worker_name, now);
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(*task_state);
+                    task.runtime_ms = Some(runtime.as_millis() as u64);
+                }
+                Signal::TaskSuccess { task_id, task_name, result, runtime, worker_name } => {
+                    state.worker(worker_name, now);
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Success);
+                    task.result = Some(result.clone());
+                    task.error = None;
+                    task.runtime_ms = Some(runtime.as_millis() as u64);
+                    task.finished_at = Some(now);
+                }
+                Signal::TaskFailure { task_id, task_name, error, runtime, worker_name, .. } => {
+                    state.worker(worker_name, now);
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Failure);
+                    task.error = Some(error.clone());
+                    task.runtime_ms = Some(runtime.as_millis() as u64);
+                    task.finished_at = Some(now);
+                }
+                Signal::TaskRetry { task_id, task_name, reason, retry_count, .. } => {
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Retry);
+                    task.error = Some(reason.clone());
+                    task.retries = *retry_count;
+                }
+                Signal::TaskRevoked { task_id, task_name, reason, .. } => {
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Revoked);
+                    task.error = reason.clone();
+                    task.finished_at = Some(now);
+                }
+                Signal::TaskRejected { task_id, task_name, reason } => {
+                    let task = state.task(task_id, task_name, now, max_tasks);
+                    task.set_state(TaskState::Rejected);
+                    task.error = Some(reason.clone());
+                    task.finished_at = Some(now);
+                }
+                Signal::TaskRateLimited { queue, .. } => {
+                    state.queues.insert(queue.clone());
+                }
+                Signal::WorkerInit { worker_name, queues, concurrency } => {
+                    state.queues.extend(queues.iter().cloned());
+                    let worker = state.worker(worker_name, now);
+                    worker.shut_down = false;
+                    worker.info.queues = queues.clone();
+                    worker.info.concurrency = Some(*concurrency);
+                    worker.info.started_at = Some(now);
+                    worker.info.active_tasks = 0;
+                    worker.info.processed = 0;
+                }
+                Signal::WorkerReady { worker_name } => {
+                    state.worker(worker_name, now).shut_down = false;
+                }
+                Signal::WorkerShutdown { worker_name, .. } => {
+                    state.worker(worker_name, now).shut_down = true;
+                }
+                Signal::WorkerHeartbeat { worker_name, active_tasks, processed, timestamp } => {
+                    let worker = state.worker(worker_name, (*timestamp).min(now));
+                    worker.shut_down = false;
+                    worker.info.active_tasks = *active_tasks;
+                    worker.info.processed = *processed;
+                }
+            }
+        }
+
+        // Nobody listening is fine
+        let _ = self.inner.events.send(signal);
+    }
+
+    /// Receive every signal recorded from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Signal> {
+        self.inner.events.subscribe()
+    }
+
+    fn snapshot(&self, entry: &WorkerEntry, now: DateTime<Utc>) -> WorkerInfo {
+        let silent = (now - entry.info.last_seen).to_std().unwrap_or(Duration::ZERO);
+        let mut info = entry.info.clone();
+        info.status = if entry.shut_down || silent > self.inner.config.worker_timeout {
+            WorkerStatus::Offline
+        } else {
+            WorkerStatus::Online
+        };
+        info
+    }
+
+    /// Known workers, by name
+    pub fn workers(&self) -> Vec<WorkerInfo> {
+        let now = Utc::now();
+        let state = self.lock();
+        let mut workers: Vec<WorkerInfo> = state.workers.values().map(|w| self.snapshot(w, now)).collect();
+        workers.sort_by(|a, b| a.name.cmp(&b.name));
+        workers
+    }
+
+    /// A worker by name
+    pub fn worker(&self, name: &str) -> Option<WorkerInfo> {
+        let state = self.lock();
+        state.workers.get(name).map(|w| self.snapshot(w, Utc::now()))
+    }
+
+    /// Known tasks matching `query`, most recently updated first
+    pub fn tasks(&self, query: &TaskQuery) -> Vec<TaskInfo> {
+        let state = self.lock();
+        let mut tasks: Vec<TaskInfo> = state
+            .tasks
+            .values()
+            .filter(|t| query.state.is_none_or(|s| t.state == s))
+            .filter(|t| query.task_name.as_ref().is_none_or(|n| &t.task_name == n))
+            .filter(|t| query.worker.as_ref().is_none_or(|w| t.worker.as_ref() == Some(w)))
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|t| std::cmp::Reverse(t.updated_at));
+        tasks.truncate(query.limit.unwrap_or(100));
+        tasks
+    }
+
+    /// A task by ID
+    pub fn task(&self, task_id: &TaskId) -> Option<TaskInfo> {
+        self.lock().tasks.get(task_id).cloned()
+    }
+
+    /// Queues seen in signals
+    pub fn queues(&self) -> Vec<String> {
+        self.lock().queues.iter().cloned().collect()
+    }
+
+    /// Worker and task counts
+    pub fn stats(&self) -> MonitorStats {
+        let now = Utc::now();
+        let state = self.lock();
+        let (mut workers_online, mut workers_offline) = (0, 0);
+        for worker in state.workers.values() {
+            match self.snapshot(worker, now).status {
+                WorkerStatus::Online => workers_online += 1,
+                WorkerStatus::Offline => workers_offline += 1,
+            }
+        }
+
+        let mut tasks = TaskCounts::default();
+        for task in state.tasks.values() {
+            let count = match task.state {
+                TaskState::Pending => &mut tasks.pending,
+                TaskState::Received => &mut tasks.received,
+                TaskState::Started => &mut tasks.started,
+                TaskState::Success => &mut tasks.success,
+                TaskState::Failure => &mut tasks.failure,
+                TaskState::Retry => &mut tasks.retry,
+                TaskState::Revoked => &mut tasks.revoked,
+                TaskState::Rejected => &mut tasks.rejected,
+            };
+            *count += 1;
+        }
+
+        MonitorStats {
+            workers_online,
+            workers_offline,
+            tasks,
+        }
+    }
+}
+
+#[async_trait]
+impl SignalHandler for EventCollector {
+    async fn handle(&self, signal: &Signal) {
+        self.record(signal.clone());
+    }
+}
+
+/// Something that can list and change rate limits
+#[async_trait]
+pub trait RateLimitControl: Send + Sync {
+    /// Limits in force
+    async fn limits(&self) -> Result<Vec<RateLimitInfo>, TaskError>;
+
+    /// Change a limit
+    async fn apply(&self, command: RateLimitCommand) -> Result<(), TaskError>;
+}
+
+#[async_trait]
+impl RateLimitControl for RateLimitManager {
+    async fn limits(&self) -> Result<Vec<RateLimitInfo>, TaskError> {
+        Ok(RateLimitManager::limits(self))
+    }
+
+    async fn apply(&self, command: RateLimitCommand) -> Result<(), TaskError> {
+        RateLimitManager::apply(self, command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(task_id: &TaskId, worker: &str) -> Signal {
+        Signal::TaskReceived {
+            task_id: task_id.clone(),
+            task_name: "etl.load".to_string(),
+            queue: "etl".to_string(),
+            worker_name: worker.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_task_lifecycle() {
+        let collector = EventCollector::default();
+        let task_id = TaskId::new();
+
+        collector.record(received(&task_id, "w1"));
+        collector.record(Signal::TaskPrerun {
+            task_id: task_id.clone(),
+            task_name: "etl.load".to_string(),
+            args: serde_json::json!([1]),
+            kwargs: serde_json::json!({"full": true}),
+            worker_name: "w1".to_string(),
+        });
+        assert_eq!(collector.task(&task_id).unwrap().state, TaskState::Started);
+
+        collector.record(Signal::TaskSuccess {
+            task_id: task_id.clone(),
+            task_name: "etl.load".to_string(),
+            result: serde_json::json!("ok"),
+            runtime: Duration::from_millis(250),
+            worker_name: "w1".to_string(),
+        });
+        // A late signal does not undo the outcome
+        collector.record(Signal::TaskPostrun {
+            task_id: task_id.clone(),
+            task_name: "etl.load".to_string(),
+            state: TaskState::Success,
+            runtime: Duration::from_millis(250),
+            worker_name: "w1".to_string(),
+        });
+        collector.record(received(&task_id, "w1"));
+
+        let task = collector.task(&task_id).unwrap();
+        assert_eq!(task.state, TaskState::Success);
+        assert_eq!(task.args, Some(serde_json::json!([1])));
+        assert_eq!(task.result, Some(serde_json::json!("ok")));
+        assert_eq!(task.runtime_ms, Some(250));
+        assert_eq!(task.queue.as_deref(), Some("etl"));
+        assert_eq!(collector.queues(), vec!["etl".to_string()]);
+        assert_eq!(collector.stats().tasks.success, 1);
+    }
+
+    #[test]
+    fn test_task_query_and_eviction() {
+        let collector = EventCollector::new(MonitorConfig {
+            max_tasks: 3,
+            ..Default::default()
+        });
+        let ids: Vec<TaskId> = (0..4).map(|_| TaskId::new()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            collector.record(received(id, if i % 2 == 0 { "w1" } else { "w2" }));
+        }
+
+        // The oldest task was forgotten
+        assert!(collector.task(&ids[0]).is_none());
+        assert_eq!(collector.tasks(&TaskQuery::default()).len(), 3);
+
+        let query = TaskQuery {
+            worker: Some("w2".to_string()),
+            ..Default::default()
+        };
+        let tasks = collector.tasks(&query);
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks.iter().all(|t| t.worker.as_deref() == Some("w2")));
+
+        let query = TaskQuery {
+            state: Some(TaskState::Failure),
+            ..Default::default()
+        };
+        assert!(collector.tasks(&query).is_empty());
+    }
+
+    #[test]
+    fn test_workers() {
+        let collector = EventCollector::new(MonitorConfig {
+            worker_timeout: Duration::from_secs(30),
+            ..Default::default()
+        });
+        collector.record(Signal::WorkerInit {
+            worker_name: "w1".to_string(),
+            queues: vec!["etl".to_string()],
+            concurrency: 4,
+        });
+        collector.record(Signal::WorkerHeartbeat {
+            worker_name: "w1".to_string(),
+            active_tasks: 2,
+            processed: 40,
+            timestamp: Utc::now(),
+        });
+        // Heard from long ago
+        collector.record(Signal::WorkerHeartbeat {
+            worker_name: "w2".to_string(),
+            active_tasks: 0,
+            processed: 1,
+            timestamp: Utc::now() - chrono::Duration::minutes(5),
+        });
+
+        let w1 = collector.worker("w1").unwrap();
+        assert_eq!(w1.status, WorkerStatus::Online);
+        assert_eq!(w1.concurrency, Some(4));
+        assert_eq!((w1.active_tasks, w1.processed), (2, 40));
+        assert_eq!(collector.worker("w2").unwrap().status, WorkerStatus::Offline);
+
+        collector.record(Signal::WorkerShutdown {
+            worker_name: "w1".to_string(),
+            reason: crate::signals::ShutdownReason::Graceful,
+        });
+        let stats = collector.stats();
+        assert_eq!((stats.workers_online, stats.workers_offline), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let collector = EventCollector::default();
+        let mut events = collector.subscribe();
+        let task_id = TaskId::new();
+
+        SignalHandler::handle(&collector, &received(&task_id, "w1")).await;
+        let signal = events.recv().await.unwrap();
+        assert_eq!(signal.task_id(), Some(&task_id));
+    }
+}
@@ -0,0 +1,284 @@
+//! Relay of signals and rate-limit commands between processes over Redis
+//!
+//! Workers register the relay as a signal handler, which publishes every
+//! signal on `{prefix}:events`; the monitor process feeds them into its
+//! `EventCollector` with `forward_to`.
+//!
+//! Rate-limit changes go the other way. The monitor uses the relay as its
+//! `RateLimitControl`: commands are stored in the `{prefix}:rate_limits` hash
+//! and published on `{prefix}:control`. Workers run `listen_for_commands`,
+//! which applies the stored limits first and then every new command.
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use super::{EventCollector, RateLimitControl};
+use crate::ratelimit::{RateLimitCommand, RateLimitInfo, RateLimitManager, RateLimitScope};
+use crate::signals::{Signal, SignalHandler};
+use crate::TaskError;
+
+/// Relays signals and rate-limit commands through Redis pub/sub
+pub struct RedisEventRelay {
+    pool: deadpool_redis::Pool,
+    client: redis::Client,
+    key_prefix: String,
+}
+
+impl RedisEventRelay {
+    /// Create a relay using the default `monitor` prefix
+    pub fn new(url: &str) -> Result<Self, TaskError> {
+        Self::with_prefix(url, "monitor".to_string())
+    }
+
+    /// Create a relay with a custom key prefix
+    pub fn with_prefix(url: &str, key_prefix: String) -> Result<Self, TaskError> {
+        let pool = deadpool_redis::Config::from_url(url)
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .map_err(|e| TaskError::Connection(format!("Failed to create Redis pool: {}", e)))?;
+        let client = redis::Client::open(url)
+            .map_err(|e| TaskError::Connection(format!("Invalid Redis URL: {}", e)))?;
+        Ok(Self {
+            pool,
+            client,
+            key_prefix,
+        })
+    }
+
+    fn events_channel(&self) -> String {
+        format!("{}:events", self.key_prefix)
+    }
+
+    fn control_channel(&self) -> String {
+        format!("{}:control", self.key_prefix)
+    }
+
+    fn limits_key(&self) -> String {
+        format!("{}:rate_limits", self.key_prefix)
+    }
+
+    fn limit_field(scope: RateLimitScope, key: &str) -> String {
+        match scope {
+            RateLimitScope::Task => format!("task:{}", key),
+            RateLimitScope::Queue => format!("queue:{}", key),
+            RateLimitScope::Global => "global".to_string(),
+        }
+    }
+
+    async fn connection(&self) -> Result<deadpool_redis::Connection, TaskError> {
+        self.pool
+            .get()
+            .await
+            .map_err(|e| TaskError::Connection(format!("Failed to get Redis connection: {}", e)))
+    }
+
+    async fn publish(&self, channel: String, payload: String) -> Result<(), TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.connection().await?;
+        let _: () = conn
+            .publish(channel, payload)
+            .await
+            .map_err(|e| TaskError::Broker(format!("Redis PUBLISH failed: {}", e)))?;
+        Ok(())
+    }
+
+    /// Subscribe to `channel` and pass each payload to `on_message` until
+    /// `shutdown` is cancelled
+    async fn listen<F>(&self, channel: String, shutdown: CancellationToken, mut on_message: F) -> Result<(), TaskError>
+    where
+        F: FnMut(&str),
+    {
+        let mut pubsub = self
+            .client
+            .get_async_pubsub()
+            .await
+            .map_err(|e| TaskError::Connection(format!("Failed to open Redis pub/sub: {}", e)))?;
+        pubsub
+            .subscribe(&channel)
+            .await
+            .map_err(|e| TaskError::Broker(format!("Redis SUBSCRIBE failed: {}", e)))?;
+
+        let mut messages = pubsub.on_message();
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                msg = messages.next() => {
+                    let Some(msg) = msg else {
+                        return Err(TaskError::Connection(format!("Redis subscription to {} closed", channel)));
+                    };
+                    match msg.get_payload::<String>() {
+                        Ok(payload) => on_message(&payload),
+                        Err(e) => tracing::warn!(error = %e, channel = %channel, "Unreadable relay message"),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Feed signals published by workers into `collector` until `shutdown` is cancelled
+    pub async fn forward_to(&self, collector: EventCollector, shutdown: CancellationToken) -> Result<(), TaskError> {
+        self.listen(self.events_channel(), shutdown, |payload| {
+            match serde_json::from_str::<Signal>(payload) {
+                Ok(signal) => collector.record(signal),
+                Err(e) => tracing::warn!(error = %e, "Failed to deserialize relayed signal"),
+            }
+        })
+        .await
+    }
+
+    /// Apply stored and newly published rate-limit commands to `limits` until
+    /// `shutdown` is cancelled
+    pub async fn listen_for_commands(
+        &self,
+        limits: Arc<RateLimitManager>,
+        shutdown: CancellationToken,
+    ) -> Result<(), TaskError> {
+        let apply = |payload: &str| {
+            let result = serde_json::from_str::<RateLimitCommand>(payload)
+                .map_err(|e| TaskError::Deserialization(e.to_string()))
+                .and_then(|command| RateLimitManager::apply(&limits, command));
+            if let Err(e) = result {
+                tracing::warn!(error = %e, "Failed to apply rate limit command");
+            }
+        };
+
+        for command in self.stored_commands().await? {
+            apply(&command);
+        }
+        self.listen(self.control_channel(), shutdown, apply).await
+    }
+
+    async fn stored_commands(&self) -> Result<Vec<String>, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.connection().await?;
+        let stored: HashMap<String, String> = conn
+            .hgetall(self.limits_key())
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HGETALL failed: {}", e)))?;
+        Ok(stored.into_values().collect())
+    }
+}
+
+#[async_trait]
+impl SignalHandler for RedisEventRelay {
+    async fn handle(&self, signal: &Signal) {
+        let payload = match serde_json::to_string(signal) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to serialize signal for relay");
+                return;
+            }
+        };
+        if let Err(e) = self.publish(self.events_channel(), payload).await {
+            tracing::warn!(error = %e, signal = signal.signal_type(), "Failed to relay signal");
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimitControl for RedisEventRelay {
+    /// Limits set through the relay; limits configured in worker code are not listed
+    async fn limits(&self) -> Result<Vec<RateLimitInfo>, TaskError> {
+        let mut limits = Vec::new();
+        for payload in self.stored_commands().await? {
+            let command: RateLimitCommand = serde_json::from_str(&payload)
+                .map_err(|e| TaskError::Deserialization(e.to_string()))?;
+            if let RateLimitCommand::Set { scope, key, config } = command {
+                limits.push(RateLimitInfo {
+                    scope,
+                    key,
+                    config: Some(config),
+                });
+            }
+        }
+        limits.sort_by(|a, b| (a.scope as u8, &a.key).cmp(&(b.scope as u8, &b.key)));
+        Ok(limits)
+    }
+
+    async fn apply(&self, command: RateLimitCommand) -> Result<(), TaskError> {
+        use redis::AsyncCommands;
+
+        // Validate here so a bad command is rejected rather than logged by every worker
+        RateLimitManager::new().apply(command.clone())?;
+
+        let payload = serde_json::to_string(&command).map_err(|e| TaskError::Serialization(e.to_string()))?;
+        let mut conn = self.connection().await?;
+        match &command {
+            RateLimitCommand::Set { scope, key, .. } => {
+                let _: () = conn
+                    .hset(self.limits_key(), Self::limit_field(*scope, key), &payload)
+                    .await
+                    .map_err(|e| TaskError::Backend(format!("Redis HSET failed: {}", e)))?;
+            }
+            RateLimitCommand::Remove { scope, key } => {
+                let _: () = conn
+                    .hdel(self.limits_key(), Self::limit_field(*scope, key))
+                    .await
+                    .map_err(|e| TaskError::Backend(format!("Redis HDEL failed: {}", e)))?;
+            }
+        }
+        self.publish(self.control_channel(), payload).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ratelimit::RateLimitConfig;
+    use crate::TaskId;
+    use std::time::Duration;
+
+    #[tokio::test]
+    #[ignore] // Requires Redis
+    async fn test_redis_event_relay() {
+        let prefix = format!("monitor_test:{}", TaskId::new());
+        let relay = Arc::new(RedisEventRelay::with_prefix("redis://127.0.0.1:6379", prefix).unwrap());
+        let shutdown = CancellationToken::new();
+
+        let collector = EventCollector::default();
+        let forward = tokio::spawn({
+            let (relay, collector, shutdown) = (relay.clone(), collector.clone(), shutdown.clone());
+            async move { relay.forward_to(collector, shutdown).await }
+        });
+        let limits = Arc::new(RateLimitManager::new());
+        let listen = tokio::spawn({
+            let (relay, limits, shutdown) = (relay.clone(), limits.clone(), shutdown.clone());
+            async move { relay.listen_for_commands(limits, shutdown).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        relay
+            .handle(&Signal::WorkerReady {
+                worker_name: "w1".to_string(),
+            })
+            .await;
+        let config = RateLimitConfig {
+            rate: 2.0,
+            capacity: 4,
+            key: String::new(),
+        };
+        RateLimitControl::apply(
+            relay.as_ref(),
+            RateLimitCommand::Set {
+                scope: RateLimitScope::Queue,
+                key: "etl".to_string(),
+                config,
+            },
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(collector.worker("w1").is_some());
+        assert_eq!(limits.limits().len(), 1);
+        assert_eq!(RateLimitControl::limits(relay.as_ref()).await.unwrap().len(), 1);
+
+        shutdown.cancel();
+        forward.await.unwrap().unwrap();
+        listen.await.unwrap().unwrap();
+    }
+}
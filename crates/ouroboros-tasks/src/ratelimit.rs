@@ -29,6 +29,8 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+use crate::TaskError;
+
 /// Rate limit configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimitConfig {
@@ -135,6 +137,11 @@ pub trait RateLimiter: Send + Sync {
 
     /// Reset the rate limiter for a key
     async fn reset(&self, key: &str);
+
+    /// Configuration of the limiter, if it has one to report
+    fn config(&self) -> Option<&RateLimitConfig> {
+        None
+    }
 }
 
 /// Token bucket rate limiter (in-memory)
@@ -231,6 +238,10 @@ impl RateLimiter for TokenBucket {
             },
         );
     }
+
+    fn config(&self) -> Option<&RateLimitConfig> {
+        Some(&self.config)
+    }
 }
 
 /// Sliding window rate limiter (in-memory)
@@ -342,16 +353,75 @@ impl RateLimiter for SlidingWindow {
         let mut windows = self.windows.write().await;
         windows.insert(key.to_string(), WindowState { requests: Vec::new() });
     }
+
+    fn config(&self) -> Option<&RateLimitConfig> {
+        Some(&self.config)
+    }
+}
+
+/// What a rate limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitScope {
+    /// One task name
+    Task,
+    /// One queue
+    Queue,
+    /// Every task
+    Global,
+}
+
+/// A rate limit in force
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    /// What the limit applies to
+    pub scope: RateLimitScope,
+    /// Task name or queue ("global" for the global limit)
+    pub key: String,
+    /// Limiter configuration, if the limiter reports one
+    pub config: Option<RateLimitConfig>,
+}
+
+/// A change to the limits of a running `RateLimitManager`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RateLimitCommand {
+    /// Install a token bucket, replacing any limit on the same key
+    Set {
+        scope: RateLimitScope,
+        #[serde(default)]
+        key: String,
+        config: RateLimitConfig,
+    },
+    /// Remove the limit on a key
+    Remove {
+        scope: RateLimitScope,
+        #[serde(default)]
+        key: String,
+    },
+}
+
+type Limits = std::sync::RwLock<HashMap<String, Arc<dyn RateLimiter>>>;
+
+fn read(limits: &Limits) -> std::sync::RwLockReadGuard<'_, HashMap<String, Arc<dyn RateLimiter>>> {
+    limits.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write(limits: &Limits) -> std::sync::RwLockWriteGuard<'_, HashMap<String, Arc<dyn RateLimiter>>> {
+    limits.write().unwrap_or_else(|e| e.into_inner())
 }
 
 /// Composite rate limiter that manages multiple rate limits
+///
+/// Limits can be changed while workers use the manager, e.g. from the
+/// monitoring API.
 pub struct RateLimitManager {
     /// Per-task rate limits
-    task_limits: HashMap<String, Arc<dyn RateLimiter>>,
+    task_limits: Limits,
     /// Per-queue rate limits
-    queue_limits: HashMap<String, Arc<dyn RateLimiter>>,
-    /// Global rate limit
-    global_limit: Option<Arc<dyn RateLimiter>>,
+    queue_limits: Limits,
+    /// Global rate limit, keyed by "global"
+    global_limit: Limits,
 }
 
 impl Default for RateLimitManager {
@@ -364,34 +434,100 @@ impl RateLimitManager {
     /// Create a new rate limit manager
     pub fn new() -> Self {
         Self {
-            task_limits: HashMap::new(),
-            queue_limits: HashMap::new(),
-            global_limit: None,
+            task_limits: Limits::default(),
+            queue_limits: Limits::default(),
+            global_limit: Limits::default(),
         }
     }
 
     /// Add a per-task rate limit
-    pub fn task_limit<R: RateLimiter + 'static>(mut self, task_name: &str, limiter: R) -> Self {
-        self.task_limits.insert(task_name.to_string(), Arc::new(limiter));
+    pub fn task_limit<R: RateLimiter + 'static>(self, task_name: &str, limiter: R) -> Self {
+        self.set_limit(RateLimitScope::Task, task_name, Arc::new(limiter));
         self
     }
 
     /// Add a per-queue rate limit
-    pub fn queue_limit<R: RateLimiter + 'static>(mut self, queue: &str, limiter: R) -> Self {
-        self.queue_limits.insert(queue.to_string(), Arc::new(limiter));
+    pub fn queue_limit<R: RateLimiter + 'static>(self, queue: &str, limiter: R) -> Self {
+        self.set_limit(RateLimitScope::Queue, queue, Arc::new(limiter));
         self
     }
 
     /// Set global rate limit
-    pub fn global_limit<R: RateLimiter + 'static>(mut self, limiter: R) -> Self {
-        self.global_limit = Some(Arc::new(limiter));
+    pub fn global_limit<R: RateLimiter + 'static>(self, limiter: R) -> Self {
+        self.set_limit(RateLimitScope::Global, "global", Arc::new(limiter));
         self
     }
 
+    fn scope(&self, scope: RateLimitScope) -> &Limits {
+        match scope {
+            RateLimitScope::Task => &self.task_limits,
+            RateLimitScope::Queue => &self.queue_limits,
+            RateLimitScope::Global => &self.global_limit,
+        }
+    }
+
+    fn limiter(&self, scope: RateLimitScope, key: &str) -> Option<Arc<dyn RateLimiter>> {
+        read(self.scope(scope)).get(key).cloned()
+    }
+
+    /// Install a limiter, replacing any limit on the same key
+    pub fn set_limit(&self, scope: RateLimitScope, key: &str, limiter: Arc<dyn RateLimiter>) {
+        let key = if scope == RateLimitScope::Global { "global" } else { key };
+        write(self.scope(scope)).insert(key.to_string(), limiter);
+    }
+
+    /// Remove a limit, returning whether there was one
+    pub fn remove_limit(&self, scope: RateLimitScope, key: &str) -> bool {
+        let key = if scope == RateLimitScope::Global { "global" } else { key };
+        write(self.scope(scope)).remove(key).is_some()
+    }
+
+    /// Apply a change to the limits
+    pub fn apply(&self, command: RateLimitCommand) -> Result<(), TaskError> {
+        match command {
+            RateLimitCommand::Set { scope, key, config } => {
+                if !config.rate.is_finite() || config.rate <= 0.0 || config.capacity == 0 {
+                    return Err(TaskError::Configuration(format!(
+                        "Rate limit for '{}' needs a positive rate and capacity",
+                        key
+                    )));
+                }
+                if scope != RateLimitScope::Global && key.is_empty() {
+                    return Err(TaskError::Configuration(
+                        "Task and queue rate limits need a key".to_string(),
+                    ));
+                }
+                self.set_limit(scope, &key, Arc::new(TokenBucket::new(config)));
+            }
+            RateLimitCommand::Remove { scope, key } => {
+                self.remove_limit(scope, &key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Limits in force, global first, then queues and tasks by key
+    pub fn limits(&self) -> Vec<RateLimitInfo> {
+        let mut limits = Vec::new();
+        for scope in [RateLimitScope::Global, RateLimitScope::Queue, RateLimitScope::Task] {
+            let mut scoped: Vec<RateLimitInfo> = read(self.scope(scope))
+                .iter()
+                .map(|(key, limiter)| RateLimitInfo {
+                    scope,
+                    key: key.clone(),
+                    config: limiter.config().cloned(),
+                })
+                .collect();
+            scoped.sort_by(|a, b| a.key.cmp(&b.key));
+            limits.extend(scoped);
+        }
+        limits
+    }
+
     /// Check if a task can be executed
     pub async fn check(&self, task_name: &str, queue: &str) -> RateLimitResult {
         // Check global limit first
-        if let Some(global) = &self.global_limit {
+        if let Some(global) = self.limiter(RateLimitScope::Global, "global") {
             let result = global.acquire("global").await;
             if !result.allowed {
                 return result;
@@ -399,7 +535,7 @@ impl RateLimitManager {
         }
 
         // Check queue limit
-        if let Some(limiter) = self.queue_limits.get(queue) {
+        if let Some(limiter) = self.limiter(RateLimitScope::Queue, queue) {
             let result = limiter.acquire(queue).await;
             if !result.allowed {
                 return result;
@@ -407,7 +543,7 @@ impl RateLimitManager {
         }
 
         // Check task limit
-        if let Some(limiter) = self.task_limits.get(task_name) {
+        if let Some(limiter) = self.limiter(RateLimitScope::Task, task_name) {
             return limiter.acquire(task_name).await;
         }
 
@@ -417,21 +553,21 @@ impl RateLimitManager {
 
     /// Check without consuming (for preview)
     pub async fn peek(&self, task_name: &str, queue: &str) -> RateLimitResult {
-        if let Some(global) = &self.global_limit {
+        if let Some(global) = self.limiter(RateLimitScope::Global, "global") {
             let result = global.peek("global").await;
             if !result.allowed {
                 return result;
             }
         }
 
-        if let Some(limiter) = self.queue_limits.get(queue) {
+        if let Some(limiter) = self.limiter(RateLimitScope::Queue, queue) {
             let result = limiter.peek(queue).await;
             if !result.allowed {
                 return result;
             }
         }
 
-        if let Some(limiter) = self.task_limits.get(task_name) {
+        if let Some(limiter) = self.limiter(RateLimitScope::Task, task_name) {
             return limiter.peek(task_name).await;
         }
 
@@ -514,6 +650,43 @@ mod tests {
         assert!(result.allowed);
     }
 
+    #[tokio::test]
+    async fn test_rate_limit_commands() {
+        let manager = RateLimitManager::new().global_limit(TokenBucket::per_second(100));
+
+        let command: RateLimitCommand = serde_json::from_value(serde_json::json!({
+            "action": "set",
+            "scope": "task",
+            "key": "slow_task",
+            "config": {"rate": 1.0, "capacity": 1}
+        }))
+        .unwrap();
+        manager.apply(command).unwrap();
+        assert!(manager.check("slow_task", "default").await.allowed);
+        assert!(!manager.check("slow_task", "default").await.allowed);
+
+        let limits = manager.limits();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits[0].scope, RateLimitScope::Global);
+        assert_eq!(limits[1].key, "slow_task");
+        assert_eq!(limits[1].config.as_ref().unwrap().capacity, 1);
+
+        manager
+            .apply(RateLimitCommand::Remove {
+                scope: RateLimitScope::Task,
+                key: "slow_task".to_string(),
+            })
+            .unwrap();
+        assert!(manager.check("slow_task", "default").await.allowed);
+
+        let invalid = RateLimitCommand::Set {
+            scope: RateLimitScope::Queue,
+            key: "q".to_string(),
+            config: RateLimitConfig { rate: 0.0, ..Default::default() },
+        };
+        assert!(manager.apply(invalid).is_err());
+    }
+
     #[tokio::test]
     async fn test_per_minute_config() {
         let config = RateLimitConfig::per_minute(60);
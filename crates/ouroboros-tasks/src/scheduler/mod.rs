@@ -1,11 +1,23 @@
 //! Task scheduling
 //!
-//! Delayed and periodic task scheduling.
+//! Delayed and periodic task scheduling, with periodic schedule state kept
+//! in a `ScheduleStore`.
 
 pub mod delay;
 pub mod periodic;
+pub mod store;
 
 #[cfg(feature = "nats")]
 pub use delay::{DelayedTaskScheduler, DelayedTaskConfig};
 
-pub use periodic::{PeriodicScheduler, PeriodicTask, PeriodicSchedule};
+pub use periodic::{CatchUp, PeriodicScheduler, PeriodicTask, PeriodicSchedule};
+pub use store::{InMemoryScheduleStore, ScheduleEntry, ScheduleStore};
+
+#[cfg(feature = "redis")]
+pub use store::RedisScheduleStore;
+
+#[cfg(feature = "kv")]
+pub use store::KvScheduleStore;
+
+#[cfg(feature = "postgres")]
+pub use store::PostgresScheduleStore;
@@ -2,6 +2,7 @@
 //!
 //! Supports both cron expressions and fixed intervals.
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use chrono::{DateTime, Utc};
@@ -12,8 +13,21 @@ use cron::Schedule;
 #[cfg(feature = "scheduler")]
 use std::str::FromStr;
 
+use super::store::{InMemoryScheduleStore, ScheduleEntry, ScheduleStore};
 use crate::{Broker, TaskError, TaskMessage};
 
+/// What to do about runs missed while no scheduler was running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CatchUp {
+    /// Drop missed runs and wait for the next one
+    Skip,
+    /// Fire once for any number of missed runs
+    #[default]
+    Once,
+    /// Fire every missed run, up to this many (0 is the same as `Skip`)
+    All(u32),
+}
+
 /// Periodic task definition
 #[derive(Debug, Clone)]
 pub struct PeriodicTask {
@@ -29,6 +43,72 @@ pub struct PeriodicTask {
     pub queue: String,
     /// Whether task is enabled
     pub enabled: bool,
+    /// Handling of runs missed during downtime
+    pub catch_up: CatchUp,
+}
+
+impl PeriodicTask {
+    /// Run `task_name` on `schedule` without arguments on the default queue
+    pub fn new(name: impl Into<String>, task_name: impl Into<String>, schedule: PeriodicSchedule) -> Self {
+        Self {
+            name: name.into(),
+            task_name: task_name.into(),
+            schedule,
+            args: serde_json::Value::Null,
+            queue: "default".to_string(),
+            enabled: true,
+            catch_up: CatchUp::default(),
+        }
+    }
+
+    /// Set the task arguments
+    pub fn with_args(mut self, args: serde_json::Value) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Set the target queue
+    pub fn with_queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = queue.into();
+        self
+    }
+
+    /// Set the handling of missed runs
+    pub fn with_catch_up(mut self, catch_up: CatchUp) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+
+    /// Scheduled times to fire now, given the stored next run
+    ///
+    /// The first due run counts as on time when it is within `grace` of
+    /// `now`; older ones were missed and follow `catch_up`.
+    ///
+    /// Make `grace` cover a lease handover, or a failover counts as downtime.
+    fn due_runs(&self, next_run: DateTime<Utc>, now: DateTime<Utc>, grace: Duration) -> Vec<DateTime<Utc>> {
+        if next_run > now {
+            return Vec::new();
+        }
+        let grace = chrono::Duration::from_std(grace).unwrap_or(chrono::Duration::zero());
+        if now - next_run <= grace {
+            return vec![next_run];
+        }
+
+        match self.catch_up {
+            CatchUp::Skip | CatchUp::All(0) => Vec::new(),
+            CatchUp::Once => vec![next_run],
+            CatchUp::All(max) => {
+                let mut runs = vec![next_run];
+                while runs.len() < max as usize {
+                    match self.schedule.next_run(*runs.last().unwrap()) {
+                        Some(run) if run <= now => runs.push(run),
+                        _ => break,
+                    }
+                }
+                runs
+            }
+        }
+    }
 }
 
 /// Schedule type for periodic tasks
@@ -55,12 +135,41 @@ impl PeriodicSchedule {
             }
         }
     }
+
+    /// Next run after one that fired at `last`, skipping runs that are
+    /// already more than `grace` late at `now`
+    fn next_run_after(&self, last: DateTime<Utc>, now: DateTime<Utc>, grace: Duration) -> Option<DateTime<Utc>> {
+        let next = self.next_run(last)?;
+        let grace = chrono::Duration::from_std(grace).unwrap_or(chrono::Duration::zero());
+        if now - next <= grace {
+            return Some(next);
+        }
+        match self {
+            #[cfg(feature = "scheduler")]
+            PeriodicSchedule::Cron(_) => self.next_run(now),
+            // Keep the cadence: the first tick after `now`
+            PeriodicSchedule::Interval(seconds) => {
+                let step = (*seconds as i64).max(1);
+                let behind = (now - last).num_seconds();
+                Some(last + chrono::Duration::seconds((behind / step + 1) * step))
+            }
+        }
+    }
 }
 
 /// Scheduler for periodic tasks
+///
+/// Last and next runs are kept in a `ScheduleStore` (in memory unless
+/// `with_store` is used). Any number of schedulers may share a store: only
+/// the one holding its lease fires, and another takes over within the lease
+/// TTL when it stops.
 pub struct PeriodicScheduler<B: Broker> {
     tasks: Vec<PeriodicTask>,
     broker: Arc<B>,
+    store: Arc<dyn ScheduleStore>,
+    id: String,
+    lease_ttl: Duration,
+    leader: Arc<AtomicBool>,
     shutdown: CancellationToken,
 }
 
@@ -70,10 +179,43 @@ impl<B: Broker> PeriodicScheduler<B> {
         Self {
             tasks: Vec::new(),
             broker,
+            store: Arc::new(InMemoryScheduleStore::new()),
+            id: format!("scheduler-{}", uuid::Uuid::now_v7().simple()),
+            lease_ttl: Duration::from_secs(30),
+            leader: Arc::new(AtomicBool::new(false)),
             shutdown: CancellationToken::new(),
         }
     }
 
+    /// Keep schedule state and the leader lease in `store`
+    pub fn with_store<S: ScheduleStore>(mut self, store: S) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Set the name this scheduler holds the lease under
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Set the lease TTL, renewed every third of it; a standby takes over
+    /// at most this long after the leader stops
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.lease_ttl = ttl;
+        self
+    }
+
+    /// Name this scheduler holds the lease under
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether this scheduler currently holds the lease
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
     /// Add a periodic task
     pub fn add_task(&mut self, task: PeriodicTask) {
         tracing::info!("Adding periodic task: {}", task.name);
@@ -92,91 +234,205 @@ impl<B: Broker> PeriodicScheduler<B> {
 
     /// Start the scheduler
     pub async fn start(&self) -> Result<(), TaskError> {
-        if self.tasks.is_empty() {
+        let tasks: Vec<PeriodicTask> = self.tasks.iter().filter(|task| task.enabled).cloned().collect();
+        if tasks.is_empty() {
             tracing::warn!("No periodic tasks to schedule");
             return Ok(());
         }
 
-        let broker = self.broker.clone();
-        let tasks = self.tasks.clone();
-        let shutdown = self.shutdown.clone();
+        let runner = Runner {
+            tasks,
+            entries: Vec::new(),
+            broker: self.broker.clone(),
+            store: self.store.clone(),
+            id: self.id.clone(),
+            lease_ttl: self.lease_ttl,
+            leader: self.leader.clone(),
+        };
+        tokio::spawn(runner.run(self.shutdown.clone()));
+
+        Ok(())
+    }
+
+    /// Shutdown the scheduler
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+}
 
-        tokio::spawn(async move {
-            // Calculate next run times for all tasks
-            let mut next_runs: Vec<(usize, DateTime<Utc>)> = tasks
-                .iter()
-                .enumerate()
-                .filter(|(_, task)| task.enabled)
-                .filter_map(|(idx, task)| {
-                    task.schedule.next_run(Utc::now()).map(|next| (idx, next))
-                })
-                .collect();
+/// Scheduling loop of a started `PeriodicScheduler`
+struct Runner<B: Broker> {
+    tasks: Vec<PeriodicTask>,
+    /// State of each task while leader, loaded from the store on election
+    entries: Vec<ScheduleEntry>,
+    broker: Arc<B>,
+    store: Arc<dyn ScheduleStore>,
+    id: String,
+    lease_ttl: Duration,
+    leader: Arc<AtomicBool>,
+}
 
-            tracing::info!("Periodic scheduler started with {} tasks", next_runs.len());
+impl<B: Broker> Runner<B> {
+    async fn run(mut self, shutdown: CancellationToken) {
+        tracing::info!(scheduler_id = %self.id, "Periodic scheduler started with {} tasks", self.tasks.len());
+        let renew_every = self.lease_ttl / 3;
+
+        loop {
+            let leader = match self.store.acquire_lease(&self.id, self.lease_ttl).await {
+                Ok(leader) => leader,
+                Err(e) => {
+                    tracing::error!(scheduler_id = %self.id, "Failed to take scheduler lease: {}", e);
+                    false
+                }
+            };
+            let was_leader = self.leader.swap(leader, Ordering::Relaxed);
+
+            let mut sleep_duration = renew_every;
+            if leader {
+                if !was_leader {
+                    tracing::info!(scheduler_id = %self.id, "Elected periodic scheduler leader");
+                    self.load().await;
+                }
+                // After a failed publish, wait before retrying the run
+                if self.fire_due().await {
+                    if let Some(next) = self.entries.iter().filter_map(|entry| entry.next_run).min() {
+                        let until_next = (next - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+                        sleep_duration = sleep_duration.min(until_next);
+                    }
+                }
+            } else if was_leader {
+                tracing::warn!(scheduler_id = %self.id, "Lost periodic scheduler lease");
+            }
 
-            loop {
-                if next_runs.is_empty() {
-                    tracing::warn!("No tasks scheduled, stopping");
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    tracing::info!("Periodic scheduler shutting down");
                     break;
                 }
+                _ = tokio::time::sleep(sleep_duration) => {}
+            }
+        }
 
-                // Sort by next run time
-                next_runs.sort_by_key(|(_, next)| *next);
-
-                // Get the soonest task
-                let (task_idx, next_run) = next_runs[0];
-                let task = &tasks[task_idx];
-
-                // Calculate sleep duration
-                let now = Utc::now();
-                let sleep_duration = if next_run > now {
-                    (next_run - now).to_std().unwrap_or(Duration::from_secs(0))
-                } else {
-                    Duration::from_secs(0)
-                };
-
-                // Wait until next run or shutdown
-                tokio::select! {
-                    _ = shutdown.cancelled() => {
-                        tracing::info!("Periodic scheduler shutting down");
-                        break;
+        if self.leader.swap(false, Ordering::Relaxed) {
+            if let Err(e) = self.store.release_lease(&self.id).await {
+                tracing::warn!(scheduler_id = %self.id, "Failed to release scheduler lease: {}", e);
+            }
+        }
+        tracing::info!("Periodic scheduler stopped");
+    }
+
+    /// Pick up each task's state from the store, scheduling new tasks from now
+    async fn load(&mut self) {
+        let now = Utc::now();
+        let mut entries = Vec::with_capacity(self.tasks.len());
+        for task in &self.tasks {
+            let stored = match self.store.get(&task.name).await {
+                Ok(stored) => stored,
+                Err(e) => {
+                    tracing::error!("Failed to load schedule of periodic task {}: {}", task.name, e);
+                    None
+                }
+            };
+            let entry = match stored {
+                Some(entry) if entry.next_run.is_some() => entry,
+                stored => {
+                    let mut entry = stored.unwrap_or_else(|| ScheduleEntry::new(&task.name, None));
+                    entry.next_run = task.schedule.next_run(now);
+                    self.save(&entry).await;
+                    entry
+                }
+            };
+            entries.push(entry);
+        }
+        self.entries = entries;
+    }
+
+    /// Publish every due run and move each task's next run on
+    ///
+    /// A run that fails to publish stays due, so the task is not moved past
+    /// it. Returns `false` if a publish failed or the lease was lost.
+    async fn fire_due(&mut self) -> bool {
+        let now = Utc::now();
+        let grace = self.lease_ttl * 2;
+        let mut published = true;
+        for idx in 0..self.tasks.len() {
+            let Some(next_run) = self.entries[idx].next_run.filter(|next| *next <= now) else {
+                continue;
+            };
+            let task = &self.tasks[idx];
+            let runs = task.due_runs(next_run, now, grace);
+            if runs.is_empty() {
+                tracing::info!("Skipping missed runs of periodic task {}", task.name);
+            } else if !self.still_leader().await {
+                // Another scheduler may have taken over and fired these already
+                return false;
+            }
+
+            let mut fired = 0;
+            for run in &runs {
+                tracing::debug!("Running periodic task: {} (scheduled for {})", task.name, run);
+                let message = TaskMessage::new(&task.task_name, task.args.clone());
+                match self.broker.publish(&task.queue, message).await {
+                    Ok(_) => {
+                        tracing::debug!("Published periodic task: {}", task.name);
+                        fired += 1;
                     }
-                    _ = tokio::time::sleep(sleep_duration) => {
-                        // Time to run the task
-                        tracing::debug!("Running periodic task: {}", task.name);
-
-                        let message = TaskMessage::new(&task.task_name, task.args.clone());
-
-                        match broker.publish(&task.queue, message).await {
-                            Ok(_) => {
-                                tracing::debug!("Published periodic task: {}", task.name);
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to publish periodic task {}: {}", task.name, e);
-                            }
-                        }
-
-                        // Calculate next run time
-                        if let Some(next) = task.schedule.next_run(Utc::now()) {
-                            next_runs[0].1 = next;
-                        } else {
-                            // Remove task if no next run (shouldn't happen for valid schedules)
-                            tracing::warn!("Task {} has no next run time, removing", task.name);
-                            next_runs.remove(0);
-                        }
+                    Err(e) => {
+                        tracing::error!("Failed to publish periodic task {}: {}", task.name, e);
+                        break;
                     }
                 }
             }
+            if fired < runs.len() {
+                published = false;
+                if fired == 0 {
+                    continue;
+                }
+            }
 
-            tracing::info!("Periodic scheduler stopped");
-        });
+            let entry = &mut self.entries[idx];
+            entry.next_run = if fired < runs.len() {
+                Some(runs[fired])
+            } else {
+                // Skipped runs keep the cadence too
+                let last = runs.last().copied().unwrap_or(next_run);
+                task.schedule.next_run_after(last, now, grace)
+            };
+            if fired > 0 {
+                entry.last_run = Some(now);
+                entry.total_runs += fired as u64;
+            }
+            if entry.next_run.is_none() {
+                // Shouldn't happen for valid schedules
+                tracing::warn!("Task {} has no next run time", task.name);
+            }
+            let entry = entry.clone();
+            self.save(&entry).await;
+        }
+        published
+    }
 
-        Ok(())
+    /// Renew the lease before publishing, in case it ran out since the last
+    /// renewal and another scheduler took over
+    async fn still_leader(&self) -> bool {
+        let leader = match self.store.acquire_lease(&self.id, self.lease_ttl).await {
+            Ok(leader) => leader,
+            Err(e) => {
+                tracing::error!(scheduler_id = %self.id, "Failed to renew scheduler lease: {}", e);
+                false
+            }
+        };
+        if !leader {
+            tracing::warn!(scheduler_id = %self.id, "Lost periodic scheduler lease");
+            self.leader.store(false, Ordering::Relaxed);
+        }
+        leader
     }
 
-    /// Shutdown the scheduler
-    pub fn shutdown(&self) {
-        self.shutdown.cancel();
+    async fn save(&self, entry: &ScheduleEntry) {
+        if let Err(e) = self.store.save(entry).await {
+            tracing::error!("Failed to save schedule of periodic task {}: {}", entry.name, e);
+        }
     }
 }
 
@@ -184,6 +440,198 @@ impl<B: Broker> PeriodicScheduler<B> {
 mod tests {
     use super::*;
 
+    use crate::scheduler::store::{InMemoryScheduleStore, ScheduleStore};
+    use crate::{InMemoryBroker, InMemoryBrokerConfig};
+
+    #[test]
+    fn test_due_runs() {
+        let now = Utc::now();
+        let grace = Duration::from_secs(10);
+        let task = PeriodicTask::new("cleanup", "cleanup", PeriodicSchedule::Interval(60));
+        let hour_ago = now - chrono::Duration::hours(1);
+
+        assert!(task.due_runs(now + chrono::Duration::seconds(1), now, grace).is_empty());
+        // Late by less than the grace period is on time, whatever the policy
+        let just_now = now - chrono::Duration::seconds(2);
+        let skip = task.clone().with_catch_up(CatchUp::Skip);
+        assert_eq!(skip.due_runs(just_now, now, grace), [just_now]);
+
+        assert!(skip.due_runs(hour_ago, now, grace).is_empty());
+        assert_eq!(task.due_runs(hour_ago, now, grace), [hour_ago]);
+        let all = task.clone().with_catch_up(CatchUp::All(100));
+        assert_eq!(all.due_runs(hour_ago, now, grace).len(), 61);
+        let capped = task.with_catch_up(CatchUp::All(5));
+        assert_eq!(capped.due_runs(hour_ago, now, grace).len(), 5);
+    }
+
+    /// Scheduler on `store` for a task whose stored next run was an hour ago
+    async fn scheduler(
+        broker: &Arc<InMemoryBroker>,
+        store: &InMemoryScheduleStore,
+        catch_up: CatchUp,
+    ) -> PeriodicScheduler<InMemoryBroker> {
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        store.save(&ScheduleEntry::new("report", Some(hour_ago))).await.unwrap();
+
+        let mut scheduler = PeriodicScheduler::new(broker.clone())
+            .with_store(store.clone())
+            .with_lease_ttl(Duration::from_millis(300));
+        scheduler.add_task(
+            PeriodicTask::new("report", "report", PeriodicSchedule::Interval(60)).with_catch_up(catch_up),
+        );
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_after_restart() {
+        for (catch_up, fired) in [(CatchUp::Skip, 0), (CatchUp::Once, 1), (CatchUp::All(0), 0), (CatchUp::All(3), 3)] {
+            let broker = Arc::new(InMemoryBroker::new(InMemoryBrokerConfig::default()));
+            let store = InMemoryScheduleStore::new();
+            let scheduler = scheduler(&broker, &store, catch_up).await;
+            scheduler.start().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            scheduler.shutdown();
+
+            assert_eq!(broker.queue_len("default"), fired, "{:?}", catch_up);
+            let entry = store.get("report").await.unwrap().unwrap();
+            assert_eq!(entry.total_runs, fired as u64);
+            assert!(entry.next_run.unwrap() > Utc::now());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_leader_election() {
+        let broker = Arc::new(InMemoryBroker::new(InMemoryBrokerConfig::default()));
+        let store = InMemoryScheduleStore::new();
+        let first = scheduler(&broker, &store, CatchUp::Once).await;
+        let second = scheduler(&broker, &store, CatchUp::Once).await;
+
+        first.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        second.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(first.is_leader());
+        assert!(!second.is_leader());
+        assert_eq!(broker.queue_len("default"), 1);
+
+        // The standby takes over once the leader stops
+        first.shutdown();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(second.is_leader());
+        assert_eq!(store.leader().await.unwrap().as_deref(), Some(second.id()));
+        assert_eq!(broker.queue_len("default"), 1);
+        second.shutdown();
+    }
+
+    /// In-memory broker whose publishes fail on demand
+    struct FlakyBroker {
+        inner: InMemoryBroker,
+        fail: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl Broker for FlakyBroker {
+        async fn connect(&self) -> Result<(), TaskError> {
+            self.inner.connect().await
+        }
+
+        async fn disconnect(&self) -> Result<(), TaskError> {
+            self.inner.disconnect().await
+        }
+
+        async fn publish(&self, queue: &str, message: TaskMessage) -> Result<(), TaskError> {
+            if self.fail.load(Ordering::Relaxed) {
+                return Err(TaskError::Broker("unavailable".to_string()));
+            }
+            self.inner.publish(queue, message).await
+        }
+
+        async fn health_check(&self) -> Result<(), TaskError> {
+            self.inner.health_check().await
+        }
+
+        fn delivery_model(&self) -> crate::DeliveryModel {
+            self.inner.delivery_model()
+        }
+
+        fn capabilities(&self) -> crate::BrokerCapabilities {
+            self.inner.capabilities()
+        }
+    }
+
+    /// Leader runner for one interval task due `late` ago
+    async fn runner(store: &InMemoryScheduleStore, late: chrono::Duration) -> (Runner<FlakyBroker>, DateTime<Utc>) {
+        let due = Utc::now() - late;
+        store.save(&ScheduleEntry::new("report", Some(due))).await.unwrap();
+        let broker = Arc::new(FlakyBroker {
+            inner: InMemoryBroker::new(InMemoryBrokerConfig::default()),
+            fail: AtomicBool::new(false),
+        });
+        let mut runner = Runner {
+            tasks: vec![PeriodicTask::new("report", "report", PeriodicSchedule::Interval(60))],
+            entries: Vec::new(),
+            broker,
+            store: Arc::new(store.clone()),
+            id: "scheduler-1".to_string(),
+            lease_ttl: Duration::from_secs(30),
+            leader: Arc::new(AtomicBool::new(true)),
+        };
+        runner.load().await;
+        (runner, due)
+    }
+
+    #[tokio::test]
+    async fn test_failed_publish_keeps_run_due() {
+        let store = InMemoryScheduleStore::new();
+        let (mut runner, due) = runner(&store, chrono::Duration::seconds(5)).await;
+
+        runner.broker.fail.store(true, Ordering::Relaxed);
+        assert!(!runner.fire_due().await);
+        let entry = store.get("report").await.unwrap().unwrap();
+        assert_eq!(entry.next_run, Some(due));
+        assert_eq!(entry.total_runs, 0);
+
+        // The retry fires it, and the next run follows the scheduled time
+        runner.broker.fail.store(false, Ordering::Relaxed);
+        assert!(runner.fire_due().await);
+        assert_eq!(runner.broker.inner.queue_len("default"), 1);
+        let entry = store.get("report").await.unwrap().unwrap();
+        assert_eq!(entry.next_run, Some(due + chrono::Duration::seconds(60)));
+        assert_eq!(entry.total_runs, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lost_lease_stops_publishing() {
+        let store = InMemoryScheduleStore::new();
+        let (mut runner, due) = runner(&store, chrono::Duration::seconds(5)).await;
+
+        // Another scheduler took the lease since the last renewal
+        assert!(store.acquire_lease("scheduler-2", Duration::from_secs(30)).await.unwrap());
+        assert!(!runner.fire_due().await);
+        assert!(!runner.leader.load(Ordering::Relaxed));
+        assert_eq!(runner.broker.inner.queue_len("default"), 0);
+        assert_eq!(store.get("report").await.unwrap().unwrap().next_run, Some(due));
+    }
+
+    #[test]
+    fn test_next_run_after() {
+        let schedule = PeriodicSchedule::Interval(60);
+        let grace = Duration::from_secs(10);
+        let now = Utc::now();
+
+        // On time, or late within the grace period: the next tick
+        let last = now - chrono::Duration::seconds(30);
+        assert_eq!(schedule.next_run_after(last, now, grace), Some(last + chrono::Duration::seconds(60)));
+        let last = now - chrono::Duration::seconds(65);
+        assert_eq!(schedule.next_run_after(last, now, grace), Some(last + chrono::Duration::seconds(60)));
+
+        // Ticks missed since are skipped, keeping the cadence
+        let last = now - chrono::Duration::minutes(90);
+        let next = schedule.next_run_after(last, now, grace).unwrap();
+        assert!(next > now && next <= now + chrono::Duration::seconds(60));
+        assert_eq!((next - last).num_seconds() % 60, 0);
+    }
+
     #[test]
     fn test_interval_schedule() {
         let schedule = PeriodicSchedule::Interval(60);
@@ -0,0 +1,703 @@
+//! Schedule state shared between periodic scheduler instances
+//!
+//! A `ScheduleStore` keeps the last and next run of every periodic task, so
+//! a restarted scheduler picks up where it left off, and a lease naming the
+//! one scheduler allowed to fire. Schedulers renew the lease well within its
+//! TTL; when the leader dies, another instance takes over once it lapses.
+//! Supports in-memory, Redis, ouroboros-kv-server and PostgreSQL stores.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::TaskError;
+
+/// Stored state of one periodic task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    /// Periodic task name
+    pub name: String,
+    /// When it last fired
+    pub last_run: Option<DateTime<Utc>>,
+    /// When it fires next
+    pub next_run: Option<DateTime<Utc>>,
+    /// How many times it has fired
+    pub total_runs: u64,
+}
+
+impl ScheduleEntry {
+    /// Entry for a task that has not fired yet
+    pub fn new(name: impl Into<String>, next_run: Option<DateTime<Utc>>) -> Self {
+        Self {
+            name: name.into(),
+            last_run: None,
+            next_run,
+            total_runs: 0,
+        }
+    }
+}
+
+/// Trait for schedule store implementations
+#[async_trait]
+pub trait ScheduleStore: Send + Sync + 'static {
+    /// Get the entry of a periodic task
+    async fn get(&self, name: &str) -> Result<Option<ScheduleEntry>, TaskError>;
+
+    /// Insert or replace an entry
+    async fn save(&self, entry: &ScheduleEntry) -> Result<(), TaskError>;
+
+    /// Remove an entry, returns whether it existed
+    async fn remove(&self, name: &str) -> Result<bool, TaskError>;
+
+    /// All entries
+    async fn list(&self) -> Result<Vec<ScheduleEntry>, TaskError>;
+
+    /// Take the scheduler lease for `holder`, or renew it if `holder`
+    /// already has it, for `ttl`
+    ///
+    /// Returns `false` while another holder's lease is live.
+    async fn acquire_lease(&self, holder: &str, ttl: Duration) -> Result<bool, TaskError>;
+
+    /// Give up the lease if `holder` has it
+    async fn release_lease(&self, holder: &str) -> Result<(), TaskError>;
+
+    /// Current lease holder
+    async fn leader(&self) -> Result<Option<String>, TaskError>;
+}
+
+/// In-memory schedule store, shared by clones
+///
+/// Only coordinates schedulers within one process; state is lost on restart.
+#[derive(Clone, Default)]
+pub struct InMemoryScheduleStore {
+    entries: Arc<Mutex<HashMap<String, ScheduleEntry>>>,
+    lease: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+impl InMemoryScheduleStore {
+    /// Create a new in-memory schedule store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ScheduleStore for InMemoryScheduleStore {
+    async fn get(&self, name: &str) -> Result<Option<ScheduleEntry>, TaskError> {
+        Ok(self.entries.lock().unwrap().get(name).cloned())
+    }
+
+    async fn save(&self, entry: &ScheduleEntry) -> Result<(), TaskError> {
+        self.entries.lock().unwrap().insert(entry.name.clone(), entry.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str) -> Result<bool, TaskError> {
+        Ok(self.entries.lock().unwrap().remove(name).is_some())
+    }
+
+    async fn list(&self) -> Result<Vec<ScheduleEntry>, TaskError> {
+        let mut entries: Vec<_> = self.entries.lock().unwrap().values().cloned().collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    async fn acquire_lease(&self, holder: &str, ttl: Duration) -> Result<bool, TaskError> {
+        let mut lease = self.lease.lock().unwrap();
+        let now = Instant::now();
+        match lease.as_ref() {
+            Some((current, expires)) if current != holder && *expires > now => Ok(false),
+            _ => {
+                *lease = Some((holder.to_string(), now + ttl));
+                Ok(true)
+            }
+        }
+    }
+
+    async fn release_lease(&self, holder: &str) -> Result<(), TaskError> {
+        let mut lease = self.lease.lock().unwrap();
+        if lease.as_ref().is_some_and(|(current, _)| current == holder) {
+            *lease = None;
+        }
+        Ok(())
+    }
+
+    async fn leader(&self) -> Result<Option<String>, TaskError> {
+        let lease = self.lease.lock().unwrap();
+        Ok(lease
+            .as_ref()
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(holder, _)| holder.clone()))
+    }
+}
+
+fn encode_entry(entry: &ScheduleEntry) -> Result<String, TaskError> {
+    serde_json::to_string(entry)
+        .map_err(|e| TaskError::Serialization(format!("Failed to serialize schedule entry: {}", e)))
+}
+
+#[cfg(any(feature = "redis", feature = "kv"))]
+fn decode_entry(value: &str) -> Result<ScheduleEntry, TaskError> {
+    serde_json::from_str(value)
+        .map_err(|e| TaskError::Deserialization(format!("Failed to deserialize schedule entry: {}", e)))
+}
+
+// ============================================================================
+// Redis
+// ============================================================================
+
+/// Redis schedule store
+///
+/// Entries live in the `{prefix}:entries` hash and the lease in
+/// `{prefix}:leader`, expiring with its TTL.
+#[cfg(feature = "redis")]
+pub struct RedisScheduleStore {
+    pool: deadpool_redis::Pool,
+    key_prefix: String,
+}
+
+#[cfg(feature = "redis")]
+const ACQUIRE_SCRIPT: &str = r#"
+local holder = redis.call('GET', KEYS[1])
+if holder and holder ~= ARGV[1] then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+return 1
+"#;
+
+#[cfg(feature = "redis")]
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('DEL', KEYS[1])
+end
+return 1
+"#;
+
+#[cfg(feature = "redis")]
+impl RedisScheduleStore {
+    /// Create a new Redis schedule store
+    pub fn new(pool: deadpool_redis::Pool) -> Self {
+        Self::with_prefix(pool, "schedule".to_string())
+    }
+
+    /// Create with a custom key prefix
+    pub fn with_prefix(pool: deadpool_redis::Pool, key_prefix: String) -> Self {
+        Self { pool, key_prefix }
+    }
+
+    fn entries_key(&self) -> String {
+        format!("{}:entries", self.key_prefix)
+    }
+
+    fn leader_key(&self) -> String {
+        format!("{}:leader", self.key_prefix)
+    }
+
+    async fn get_conn(&self) -> Result<deadpool_redis::Connection, TaskError> {
+        self.pool.get().await.map_err(|e| {
+            TaskError::Backend(format!("Failed to get Redis connection: {}", e))
+        })
+    }
+
+    async fn run_script(&self, script: &str, args: &[String]) -> Result<i64, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let script = redis::Script::new(script);
+        let mut invocation = script.key(self.leader_key());
+        for arg in args {
+            invocation.arg(arg);
+        }
+        invocation
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis schedule lease script failed: {}", e)))
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl ScheduleStore for RedisScheduleStore {
+    async fn get(&self, name: &str) -> Result<Option<ScheduleEntry>, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let value: Option<String> = conn
+            .hget(self.entries_key(), name)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HGET failed: {}", e)))?;
+        value.as_deref().map(decode_entry).transpose()
+    }
+
+    async fn save(&self, entry: &ScheduleEntry) -> Result<(), TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let _: () = conn
+            .hset(self.entries_key(), &entry.name, encode_entry(entry)?)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HSET failed: {}", e)))?;
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str) -> Result<bool, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let removed: u64 = conn
+            .hdel(self.entries_key(), name)
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HDEL failed: {}", e)))?;
+        Ok(removed > 0)
+    }
+
+    async fn list(&self) -> Result<Vec<ScheduleEntry>, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        let values: HashMap<String, String> = conn
+            .hgetall(self.entries_key())
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis HGETALL failed: {}", e)))?;
+        let mut entries = values.values().map(|v| decode_entry(v)).collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    async fn acquire_lease(&self, holder: &str, ttl: Duration) -> Result<bool, TaskError> {
+        let ttl_ms = ttl.as_millis().max(1).to_string();
+        Ok(self.run_script(ACQUIRE_SCRIPT, &[holder.to_string(), ttl_ms]).await? == 1)
+    }
+
+    async fn release_lease(&self, holder: &str) -> Result<(), TaskError> {
+        self.run_script(RELEASE_SCRIPT, &[holder.to_string()]).await?;
+        Ok(())
+    }
+
+    async fn leader(&self) -> Result<Option<String>, TaskError> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.get_conn().await?;
+        conn.get(self.leader_key())
+            .await
+            .map_err(|e| TaskError::Backend(format!("Redis GET failed: {}", e)))
+    }
+}
+
+// ============================================================================
+// ouroboros-kv-server
+// ============================================================================
+
+/// ouroboros-kv-server schedule store
+///
+/// Uses the same key layout as the Redis store, with the server's own locks
+/// for the lease.
+#[cfg(feature = "kv")]
+pub struct KvScheduleStore {
+    pool: Arc<ouroboros_kv_client::KvPool>,
+    key_prefix: String,
+}
+
+#[cfg(feature = "kv")]
+fn kv_error(context: &str, e: ouroboros_kv_client::ClientError) -> TaskError {
+    TaskError::Backend(format!("{}: {}", context, e))
+}
+
+#[cfg(feature = "kv")]
+impl KvScheduleStore {
+    /// Create a new kv schedule store
+    pub fn new(pool: Arc<ouroboros_kv_client::KvPool>) -> Self {
+        Self::with_prefix(pool, "schedule".to_string())
+    }
+
+    /// Create with a custom key prefix
+    pub fn with_prefix(pool: Arc<ouroboros_kv_client::KvPool>, key_prefix: String) -> Self {
+        Self { pool, key_prefix }
+    }
+
+    fn entries_key(&self) -> String {
+        format!("{}:entries", self.key_prefix)
+    }
+
+    fn leader_key(&self) -> String {
+        format!("{}:leader", self.key_prefix)
+    }
+
+    async fn get_conn(&self) -> Result<ouroboros_kv_client::PooledClient, TaskError> {
+        self.pool
+            .acquire()
+            .await
+            .map_err(|e| kv_error("Failed to get connection", e))
+    }
+}
+
+#[cfg(feature = "kv")]
+fn kv_string(value: ouroboros_kv_client::KvValue) -> Result<String, TaskError> {
+    match value {
+        ouroboros_kv_client::KvValue::String(v) => Ok(v),
+        other => Err(TaskError::Deserialization(format!(
+            "Unexpected schedule entry value: {:?}",
+            other
+        ))),
+    }
+}
+
+#[cfg(feature = "kv")]
+#[async_trait]
+impl ScheduleStore for KvScheduleStore {
+    async fn get(&self, name: &str) -> Result<Option<ScheduleEntry>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let value = conn
+            .client()
+            .hget(&self.entries_key(), name)
+            .await
+            .map_err(|e| kv_error("Failed to get schedule entry", e))?;
+        value.map(|v| decode_entry(&kv_string(v)?)).transpose()
+    }
+
+    async fn save(&self, entry: &ScheduleEntry) -> Result<(), TaskError> {
+        let value = ouroboros_kv_client::KvValue::String(encode_entry(entry)?);
+        let mut conn = self.get_conn().await?;
+        conn.client()
+            .hset(&self.entries_key(), &[(entry.name.as_str(), value)])
+            .await
+            .map_err(|e| kv_error("Failed to save schedule entry", e))?;
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str) -> Result<bool, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let removed = conn
+            .client()
+            .hdel(&self.entries_key(), &[name])
+            .await
+            .map_err(|e| kv_error("Failed to remove schedule entry", e))?;
+        Ok(removed > 0)
+    }
+
+    async fn list(&self) -> Result<Vec<ScheduleEntry>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let values = conn
+            .client()
+            .hgetall(&self.entries_key())
+            .await
+            .map_err(|e| kv_error("Failed to list schedule entries", e))?;
+        let mut entries = values
+            .into_values()
+            .map(|v| decode_entry(&kv_string(v)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    async fn acquire_lease(&self, holder: &str, ttl: Duration) -> Result<bool, TaskError> {
+        let key = self.leader_key();
+        let mut conn = self.get_conn().await?;
+        let client = conn.client();
+        if client
+            .lock(&key, holder, ttl)
+            .await
+            .map_err(|e| kv_error("Failed to take scheduler lease", e))?
+        {
+            return Ok(true);
+        }
+
+        // Held already; renew it if it is ours (the lock may lapse in between)
+        let current = client
+            .get(&key)
+            .await
+            .map_err(|e| kv_error("Failed to get scheduler lease", e))?;
+        if current.map(kv_string).transpose()?.as_deref() != Some(holder) {
+            return Ok(false);
+        }
+        Ok(client.extend_lock(&key, holder, ttl).await.unwrap_or(false))
+    }
+
+    async fn release_lease(&self, holder: &str) -> Result<(), TaskError> {
+        let mut conn = self.get_conn().await?;
+        // Fails when another holder has taken over, leaving their lease alone
+        if let Err(e) = conn.client().unlock(&self.leader_key(), holder).await {
+            tracing::debug!(error = %e, "Scheduler lease not released");
+        }
+        Ok(())
+    }
+
+    async fn leader(&self) -> Result<Option<String>, TaskError> {
+        let mut conn = self.get_conn().await?;
+        let value = conn
+            .client()
+            .get(&self.leader_key())
+            .await
+            .map_err(|e| kv_error("Failed to get scheduler lease", e))?;
+        value.map(kv_string).transpose()
+    }
+}
+
+// ============================================================================
+// PostgreSQL
+// ============================================================================
+
+/// PostgreSQL schedule store
+///
+/// Entries are rows of `table`; the lease is a row of `<table>_lease`.
+#[cfg(feature = "postgres")]
+#[derive(Clone)]
+pub struct PostgresScheduleStore {
+    conn: ouroboros_postgres::Connection,
+    sql: Arc<Statements>,
+}
+
+/// SQL statements for one schedule table
+#[cfg(feature = "postgres")]
+struct Statements {
+    create: String,
+    create_lease: String,
+    get: String,
+    save: String,
+    remove: String,
+    list: String,
+    acquire_lease: String,
+    release_lease: String,
+    leader: String,
+}
+
+#[cfg(feature = "postgres")]
+impl Statements {
+    fn new(table: &str) -> Result<Self, TaskError> {
+        use ouroboros_postgres::QueryBuilder;
+
+        QueryBuilder::validate_identifier(table)
+            .map_err(|e| TaskError::Configuration(format!("Invalid schedule table: {}", e)))?;
+        let t = QueryBuilder::quote_identifier(table);
+        let l = QueryBuilder::quote_identifier(&format!("{table}_lease"));
+        let columns = "name, last_run, next_run, total_runs";
+
+        Ok(Self {
+            create: format!(
+                "CREATE TABLE IF NOT EXISTS {t} (
+                    name TEXT PRIMARY KEY,
+                    last_run TIMESTAMPTZ,
+                    next_run TIMESTAMPTZ,
+                    total_runs BIGINT NOT NULL DEFAULT 0,
+                    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                )"
+            ),
+            create_lease: format!(
+                "CREATE TABLE IF NOT EXISTS {l} (
+                    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+                    holder TEXT NOT NULL,
+                    expires_at TIMESTAMPTZ NOT NULL
+                )"
+            ),
+            get: format!("SELECT {columns} FROM {t} WHERE name = $1"),
+            save: format!(
+                "INSERT INTO {t} ({columns}) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (name) DO UPDATE SET
+                    last_run = EXCLUDED.last_run,
+                    next_run = EXCLUDED.next_run,
+                    total_runs = EXCLUDED.total_runs,
+                    updated_at = now()"
+            ),
+            remove: format!("DELETE FROM {t} WHERE name = $1"),
+            list: format!("SELECT {columns} FROM {t} ORDER BY name"),
+            // Takes a lapsed lease or renews our own; $2 is the TTL in milliseconds
+            acquire_lease: format!(
+                "INSERT INTO {l} AS l (holder, expires_at)
+                VALUES ($1, now() + $2 * interval '1 millisecond')
+                ON CONFLICT (id) DO UPDATE SET
+                    holder = EXCLUDED.holder,
+                    expires_at = EXCLUDED.expires_at
+                WHERE l.holder = EXCLUDED.holder OR l.expires_at <= now()"
+            ),
+            release_lease: format!("DELETE FROM {l} WHERE holder = $1"),
+            leader: format!("SELECT holder FROM {l} WHERE expires_at > now()"),
+        })
+    }
+}
+
+#[cfg(feature = "postgres")]
+type EntryRow = (String, Option<DateTime<Utc>>, Option<DateTime<Utc>>, i64);
+
+#[cfg(feature = "postgres")]
+fn entry_from_row((name, last_run, next_run, total_runs): EntryRow) -> ScheduleEntry {
+    ScheduleEntry {
+        name,
+        last_run,
+        next_run,
+        total_runs: total_runs as u64,
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn pg_error(context: &str, e: impl std::fmt::Display) -> TaskError {
+    TaskError::Backend(format!("{}: {}", context, e))
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresScheduleStore {
+    /// Create a store on `table` (optionally schema-qualified), creating the
+    /// tables if needed
+    pub async fn new(conn: ouroboros_postgres::Connection, table: &str) -> Result<Self, TaskError> {
+        let sql = Statements::new(table)?;
+        for create in [&sql.create, &sql.create_lease] {
+            sqlx::query(create)
+                .execute(conn.pool())
+                .await
+                .map_err(|e| pg_error("Failed to create schedule table", e))?;
+        }
+        Ok(Self {
+            conn,
+            sql: Arc::new(sql),
+        })
+    }
+}
+
+#[cfg(feature = "postgres")]
+#[async_trait]
+impl ScheduleStore for PostgresScheduleStore {
+    async fn get(&self, name: &str) -> Result<Option<ScheduleEntry>, TaskError> {
+        let row: Option<EntryRow> = sqlx::query_as(&self.sql.get)
+            .bind(name)
+            .fetch_optional(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to get schedule entry", e))?;
+        Ok(row.map(entry_from_row))
+    }
+
+    async fn save(&self, entry: &ScheduleEntry) -> Result<(), TaskError> {
+        sqlx::query(&self.sql.save)
+            .bind(&entry.name)
+            .bind(entry.last_run)
+            .bind(entry.next_run)
+            .bind(entry.total_runs.min(i64::MAX as u64) as i64)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to save schedule entry", e))?;
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str) -> Result<bool, TaskError> {
+        let result = sqlx::query(&self.sql.remove)
+            .bind(name)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to remove schedule entry", e))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list(&self) -> Result<Vec<ScheduleEntry>, TaskError> {
+        let rows: Vec<EntryRow> = sqlx::query_as(&self.sql.list)
+            .fetch_all(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to list schedule entries", e))?;
+        Ok(rows.into_iter().map(entry_from_row).collect())
+    }
+
+    async fn acquire_lease(&self, holder: &str, ttl: Duration) -> Result<bool, TaskError> {
+        let result = sqlx::query(&self.sql.acquire_lease)
+            .bind(holder)
+            .bind(ttl.as_millis().clamp(1, i64::MAX as u128) as i64)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to take scheduler lease", e))?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_lease(&self, holder: &str) -> Result<(), TaskError> {
+        sqlx::query(&self.sql.release_lease)
+            .bind(holder)
+            .execute(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to release scheduler lease", e))?;
+        Ok(())
+    }
+
+    async fn leader(&self) -> Result<Option<String>, TaskError> {
+        sqlx::query_scalar(&self.sql.leader)
+            .fetch_optional(self.conn.pool())
+            .await
+            .map_err(|e| pg_error("Failed to get scheduler lease", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TaskId;
+
+    /// Entries and lease semantics every store must share
+    async fn check_store<S: ScheduleStore>(store: &S) {
+        let now = Utc::now();
+        let mut entry = ScheduleEntry::new("cleanup", Some(now));
+        store.save(&entry).await.unwrap();
+        entry.last_run = Some(now);
+        entry.total_runs = 1;
+        store.save(&entry).await.unwrap();
+        store.save(&ScheduleEntry::new("backup", None)).await.unwrap();
+
+        let stored = store.get("cleanup").await.unwrap().unwrap();
+        assert_eq!(stored.total_runs, 1);
+        assert_eq!(stored.last_run.map(|t| t.timestamp_millis()), Some(now.timestamp_millis()));
+        let names: Vec<_> = store.list().await.unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["backup", "cleanup"]);
+        assert!(store.remove("backup").await.unwrap());
+        assert!(!store.remove("backup").await.unwrap());
+
+        let ttl = Duration::from_millis(300);
+        assert!(store.acquire_lease("s1", ttl).await.unwrap());
+        assert!(!store.acquire_lease("s2", ttl).await.unwrap());
+        assert!(store.acquire_lease("s1", ttl).await.unwrap());
+        assert_eq!(store.leader().await.unwrap().as_deref(), Some("s1"));
+
+        // A lapsed lease can be taken over
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(store.acquire_lease("s2", ttl).await.unwrap());
+        store.release_lease("s1").await.unwrap();
+        assert_eq!(store.leader().await.unwrap().as_deref(), Some("s2"));
+        store.release_lease("s2").await.unwrap();
+        assert!(store.leader().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        check_store(&InMemoryScheduleStore::new()).await;
+    }
+
+    // Integration tests - require Redis running
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_redis_store() {
+        let cfg = deadpool_redis::Config::from_url("redis://127.0.0.1:6379");
+        let pool = cfg.create_pool(Some(deadpool_redis::Runtime::Tokio1)).unwrap();
+        check_store(&RedisScheduleStore::with_prefix(pool, format!("test_schedule_{}", TaskId::new()))).await;
+    }
+
+    // Integration tests - require a kv-server running
+    #[cfg(feature = "kv")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_kv_store() {
+        let pool = ouroboros_kv_client::KvPool::connect(ouroboros_kv_client::PoolConfig::new("127.0.0.1:6380"))
+            .await
+            .unwrap();
+        check_store(&KvScheduleStore::with_prefix(pool, format!("test_schedule_{}", TaskId::new()))).await;
+    }
+
+    // Integration tests requiring PostgreSQL
+    // Run with DATABASE_URL=postgresql://user@localhost/db
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_store() {
+        let url = std::env::var("DATABASE_URL")
+            .unwrap_or_else(|_| "postgresql://postgres@localhost/postgres".to_string());
+        let conn = ouroboros_postgres::Connection::new(&url, ouroboros_postgres::PoolConfig::default())
+            .await
+            .unwrap();
+        let table = format!("test_schedule_{}", TaskId::new().to_string().replace('-', ""));
+        check_store(&PostgresScheduleStore::new(conn, &table).await.unwrap()).await;
+    }
+}
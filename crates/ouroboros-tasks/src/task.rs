@@ -5,6 +5,7 @@ use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 use crate::RetryPolicy;
 
@@ -58,6 +59,21 @@ pub struct TaskContext {
     pub parent_id: Option<TaskId>,
     /// Root task ID (for workflows)
     pub root_id: Option<TaskId>,
+    /// Cancelled when the task should stop early: its soft time limit
+    /// passed or its worker is terminating
+    pub cancellation: CancellationToken,
+}
+
+impl TaskContext {
+    /// Whether the task has been asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Wait until the task is asked to stop
+    pub async fn cancelled(&self) {
+        self.cancellation.cancelled().await
+    }
 }
 
 /// Result returned by task execution
@@ -69,6 +85,8 @@ pub enum TaskOutcome {
     Failure {
         /// Error message
         error: String,
+        /// Error traceback, if available
+        traceback: Option<String>,
         /// Whether this error is retryable
         retryable: bool,
     },
@@ -112,12 +130,13 @@ pub trait Task: Send + Sync + 'static {
         RetryPolicy::default()
     }
 
-    /// Soft time limit (task receives warning)
+    /// Soft time limit: the context's cancellation token fires, so the task
+    /// can stop cleanly
     fn soft_time_limit(&self) -> Option<Duration> {
         None
     }
 
-    /// Hard time limit (task is killed)
+    /// Hard time limit: the task's future is dropped and it fails
     fn hard_time_limit(&self) -> Option<Duration> {
         None
     }
@@ -0,0 +1,189 @@
+//! Scaling a worker's concurrency with its load
+//!
+//! Every `interval` the autoscaler compares the slots in use plus the
+//! backlog (tasks waiting for a slot, and messages still in the broker when
+//! the worker has a `QueueInspector`) with the current concurrency. It grows
+//! at once, up to `max`, unless the machine's CPU is already busier than
+//! `max_cpu`; it shrinks, down to `min`, only after demand has stayed below
+//! the concurrency for `scale_down_after`.
+
+use std::time::{Duration, Instant};
+
+/// Autoscaling bounds and pacing
+#[derive(Debug, Clone)]
+pub struct AutoscaleConfig {
+    /// Fewest slots (the worker starts with this many)
+    pub min: usize,
+    /// Most slots
+    pub max: usize,
+    /// How often to re-evaluate
+    pub interval: Duration,
+    /// CPU utilization (0.0 to 1.0) above which concurrency stops growing;
+    /// ignored where utilization cannot be measured
+    pub max_cpu: Option<f64>,
+    /// How long demand must stay below the concurrency before it shrinks
+    pub scale_down_after: Duration,
+}
+
+impl AutoscaleConfig {
+    /// Scale between `min` and `max` slots
+    pub fn new(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for AutoscaleConfig {
+    fn default() -> Self {
+        Self {
+            min: 1,
+            max: num_cpus::get(),
+            interval: Duration::from_secs(5),
+            max_cpu: Some(0.9),
+            scale_down_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Load seen by the autoscaler at one evaluation
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Load {
+    /// Slots in use
+    pub busy: usize,
+    /// Tasks waiting for a slot or in the broker
+    pub backlog: usize,
+    /// CPU utilization since the last evaluation
+    pub cpu: Option<f64>,
+}
+
+pub(crate) struct Autoscaler {
+    config: AutoscaleConfig,
+    /// Last time demand reached the concurrency
+    last_busy: Instant,
+}
+
+impl Autoscaler {
+    pub(crate) fn new(config: AutoscaleConfig) -> Self {
+        Self {
+            config,
+            last_busy: Instant::now(),
+        }
+    }
+
+    fn bounds(&self) -> (usize, usize) {
+        let min = self.config.min.max(1);
+        (min, self.config.max.max(min))
+    }
+
+    /// Concurrency to start with
+    pub(crate) fn initial(&self) -> usize {
+        self.bounds().0
+    }
+
+    /// Concurrency to use given the current concurrency and load
+    pub(crate) fn decide(&mut self, current: usize, load: Load, now: Instant) -> usize {
+        let (min, max) = self.bounds();
+        let demand = load.busy + load.backlog;
+
+        if demand >= current {
+            self.last_busy = now;
+            let cpu_saturated = matches!(
+                (load.cpu, self.config.max_cpu),
+                (Some(cpu), Some(limit)) if cpu > limit
+            );
+            if cpu_saturated {
+                return current.clamp(min, max);
+            }
+            return demand.clamp(min, max);
+        }
+
+        if now.duration_since(self.last_busy) >= self.config.scale_down_after {
+            self.last_busy = now;
+            return demand.clamp(min, max);
+        }
+        current.clamp(min, max)
+    }
+}
+
+/// Machine-wide CPU utilization between successive samples
+#[derive(Default)]
+pub(crate) struct CpuSampler {
+    last: Option<(u64, u64)>,
+}
+
+impl CpuSampler {
+    /// Utilization since the previous call, `None` on the first call or where
+    /// it cannot be measured
+    pub(crate) fn sample(&mut self) -> Option<f64> {
+        let (busy, total) = Self::read()?;
+        let previous = self.last.replace((busy, total));
+        let (last_busy, last_total) = previous?;
+        let elapsed = total.checked_sub(last_total).filter(|&t| t > 0)?;
+        Some(busy.saturating_sub(last_busy) as f64 / elapsed as f64)
+    }
+
+    /// Busy and total jiffies from `/proc/stat`
+    #[cfg(target_os = "linux")]
+    fn read() -> Option<(u64, u64)> {
+        let stat = std::fs::read_to_string("/proc/stat").ok()?;
+        let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+        let times: Vec<u64> = line.split_whitespace().skip(1).filter_map(|t| t.parse().ok()).collect();
+        // user nice system idle iowait ...
+        let idle = times.get(3)? + times.get(4).copied().unwrap_or(0);
+        let total: u64 = times.iter().sum();
+        Some((total - idle, total))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn read() -> Option<(u64, u64)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(busy: usize, backlog: usize, cpu: Option<f64>) -> Load {
+        Load { busy, backlog, cpu }
+    }
+
+    #[test]
+    fn test_autoscaler() {
+        let mut scaler = Autoscaler::new(AutoscaleConfig {
+            min: 2,
+            max: 8,
+            scale_down_after: Duration::from_secs(30),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert_eq!(scaler.initial(), 2);
+
+        // Grows with the backlog, up to max
+        assert_eq!(scaler.decide(2, load(2, 3, Some(0.2)), start), 5);
+        assert_eq!(scaler.decide(5, load(5, 20, None), start), 8);
+
+        // A busy CPU holds it where it is
+        assert_eq!(scaler.decide(5, load(5, 3, Some(0.95)), start), 5);
+
+        // Shrinks only once demand has stayed low for a while
+        let later = start + Duration::from_secs(10);
+        assert_eq!(scaler.decide(8, load(1, 0, None), later), 8);
+        let much_later = start + Duration::from_secs(31);
+        assert_eq!(scaler.decide(8, load(1, 0, None), much_later), 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cpu_sampler() {
+        let mut sampler = CpuSampler::default();
+        assert!(sampler.sample().is_none());
+        std::thread::sleep(Duration::from_millis(50));
+        if let Some(cpu) = sampler.sample() {
+            assert!((0.0..=1.0).contains(&cpu));
+        }
+    }
+}
@@ -1,20 +1,35 @@
 //! Worker runtime for executing tasks
 
+mod autoscale;
+#[cfg(feature = "push")]
+mod push;
+mod scheduling;
+
+pub use autoscale::AutoscaleConfig;
+#[cfg(feature = "push")]
+pub use push::PushWorker;
+pub use scheduling::QueuePolicy;
+
 use async_trait::async_trait;
 use chrono::Utc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Semaphore;
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    BrokerMessage, MessageHandler, PullBroker, ResultBackend, Task, TaskContext,
-    TaskOutcome, TaskRegistry, TaskResult, TaskState, TaskId,
+    Broker, BrokerMessage, MessageHandler, PoisonMessage, PullBroker, QueueInspector, ResultBackend, Task, TaskAttempt,
+    TaskContext, TaskMessage, TaskOutcome, TaskRegistry, TaskResult, TaskState, TaskId,
 };
+use crate::deadletter::{DeadLetter, DeadLetterReason, DeadLetterStore};
+use crate::idempotency::{Claim, IdempotencyStore};
 use crate::ratelimit::RateLimitManager;
 use crate::revocation::RevocationStore;
 use crate::signals::{Signal, SignalDispatcher, ShutdownReason};
+use crate::workflow::dag::{self, StepOutcome};
 use crate::TaskError;
+use autoscale::{Autoscaler, CpuSampler, Load};
+use scheduling::SlotScheduler;
 
 /// Worker configuration
 #[derive(Clone)]
@@ -23,12 +38,18 @@ pub struct WorkerConfig {
     pub name: String,
     /// Queues to consume from
     pub queues: Vec<String>,
-    /// Concurrency (max parallel tasks)
+    /// How concurrency is shared between the queues when they compete
+    pub queue_policy: QueuePolicy,
+    /// Concurrency (max parallel tasks), unless `autoscale` is set
     pub concurrency: usize,
+    /// Scale concurrency with the load instead of keeping it fixed
+    pub autoscale: Option<AutoscaleConfig>,
     /// Prefetch count (messages to buffer)
     pub prefetch: usize,
     /// Heartbeat interval
     pub heartbeat: Duration,
+    /// How long a shutdown waits for running tasks before terminating them
+    pub shutdown_timeout: Duration,
     /// Optional revocation store
     pub revocation_store: Option<Arc<dyn RevocationStore>>,
 }
@@ -38,9 +59,12 @@ impl std::fmt::Debug for WorkerConfig {
         f.debug_struct("WorkerConfig")
             .field("name", &self.name)
             .field("queues", &self.queues)
+            .field("queue_policy", &self.queue_policy)
             .field("concurrency", &self.concurrency)
+            .field("autoscale", &self.autoscale)
             .field("prefetch", &self.prefetch)
             .field("heartbeat", &self.heartbeat)
+            .field("shutdown_timeout", &self.shutdown_timeout)
             .field("revocation_store", &self.revocation_store.as_ref().map(|_| "Some(RevocationStore)"))
             .finish()
     }
@@ -51,46 +75,103 @@ impl Default for WorkerConfig {
         Self {
             name: format!("worker-{}", uuid::Uuid::now_v7().simple()),
             queues: vec!["default".to_string()],
+            queue_policy: QueuePolicy::default(),
             concurrency: num_cpus::get(),
+            autoscale: None,
             prefetch: 4,
             heartbeat: Duration::from_secs(10),
+            shutdown_timeout: Duration::from_secs(30),
             revocation_store: None,
         }
     }
 }
 
-/// Task executor that handles incoming messages
-struct TaskExecutor<R: ResultBackend> {
+/// Counters reported in heartbeats and waited on at shutdown
+#[derive(Default)]
+struct WorkerStats {
+    active: AtomicUsize,
+    processed: AtomicU64,
+    /// Messages being handled, including those waiting for a slot
+    in_flight: AtomicUsize,
+    drained: tokio::sync::Notify,
+}
+
+impl WorkerStats {
+    fn start(&self) -> ActiveTask<'_> {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        ActiveTask(self)
+    }
+
+    fn receive(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(self)
+    }
+
+    /// Wait until no message is being handled
+    async fn drained(&self) {
+        loop {
+            let notified = self.drained.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A task counted as executing until dropped
+struct ActiveTask<'a>(&'a WorkerStats);
+
+impl Drop for ActiveTask<'_> {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+        self.0.processed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A message counted as being handled until dropped
+struct InFlight<'a>(&'a WorkerStats);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.drained.notify_waiters();
+        }
+    }
+}
+
+/// How a handled message is to be settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Settlement {
+    /// Done with, successfully or not
+    Ack,
+    /// Deliver again after the delay, to retry the task
+    Redeliver(Duration),
+}
+
+/// Task executor that handles incoming messages from one queue
+struct TaskExecutor<B: Broker + ?Sized, R: ResultBackend> {
+    broker: Arc<B>,
+    queue: String,
     registry: Arc<TaskRegistry>,
     backend: Arc<R>,
-    semaphore: Arc<Semaphore>,
+    slots: Arc<SlotScheduler>,
+    stats: Arc<WorkerStats>,
+    terminate: CancellationToken,
     worker_id: String,
     rate_limiter: Option<Arc<RateLimitManager>>,
     signal_dispatcher: Option<Arc<SignalDispatcher>>,
     revocation_store: Option<Arc<dyn RevocationStore>>,
+    dead_letters: Option<Arc<dyn DeadLetterStore>>,
+    idempotency: Option<Arc<dyn IdempotencyStore>>,
+    /// Retry by having the broker redeliver the message instead of
+    /// publishing a new one, for brokers that cannot be published to
+    redeliver: bool,
 }
 
-impl<R: ResultBackend> TaskExecutor<R> {
-    fn new(
-        registry: Arc<TaskRegistry>,
-        backend: Arc<R>,
-        semaphore: Arc<Semaphore>,
-        worker_id: String,
-        rate_limiter: Option<Arc<RateLimitManager>>,
-        signal_dispatcher: Option<Arc<SignalDispatcher>>,
-        revocation_store: Option<Arc<dyn RevocationStore>>,
-    ) -> Self {
-        Self {
-            registry,
-            backend,
-            semaphore,
-            worker_id,
-            rate_limiter,
-            signal_dispatcher,
-            revocation_store,
-        }
-    }
-
+impl<B: Broker + ?Sized, R: ResultBackend> TaskExecutor<B, R> {
     /// Check if a task has been revoked
     async fn check_revocation(&self, task_id: &TaskId) -> Result<bool, TaskError> {
         if let Some(store) = &self.revocation_store {
@@ -100,42 +181,223 @@ impl<R: ResultBackend> TaskExecutor<R> {
         }
     }
 
-    /// Execute a task with timeout handling
+    /// Execute a task within its time limits
+    ///
+    /// At the soft limit the context's cancellation token fires and the task
+    /// may still finish; at the hard limit it is dropped and fails. If the
+    /// worker terminates first, the task is dropped and `ShuttingDown`
+    /// returned, so the message goes back to the queue.
     async fn execute_with_timeout(
         &self,
         task: Arc<dyn Task>,
         ctx: TaskContext,
         args: serde_json::Value,
     ) -> Result<TaskOutcome, TaskError> {
-        // Check for hard time limit
-        if let Some(timeout) = task.hard_time_limit() {
+        let start = tokio::time::Instant::now();
+        let soft = task.soft_time_limit();
+        let hard = task.hard_time_limit();
+        let deadline = |limit: Option<Duration>| async move {
+            match limit {
+                Some(limit) => tokio::time::sleep_until(start + limit).await,
+                None => std::future::pending().await,
+            }
+        };
+        let soft_deadline = deadline(soft);
+        let hard_deadline = deadline(hard);
+        tokio::pin!(soft_deadline, hard_deadline);
+
+        let execution = task.execute(ctx.clone(), args);
+        tokio::pin!(execution);
+        let mut soft_expired = soft.is_none();
+
+        loop {
             tokio::select! {
-                result = task.execute(ctx.clone(), args) => {
-                    Ok(result)
+                biased;
+                _ = self.terminate.cancelled() => {
+                    ctx.cancellation.cancel();
+                    tracing::warn!(
+                        task_id = %ctx.task_id,
+                        task_name = %ctx.task_name,
+                        "Task interrupted by worker shutdown"
+                    );
+                    return Err(TaskError::ShuttingDown);
                 }
-                _ = tokio::time::sleep(timeout) => {
+                outcome = &mut execution => return Ok(outcome),
+                _ = &mut hard_deadline => {
+                    let timeout = hard.unwrap_or_default();
+                    ctx.cancellation.cancel();
                     tracing::warn!(
                         task_id = %ctx.task_id,
                         task_name = %ctx.task_name,
                         timeout_secs = timeout.as_secs(),
                         "Task exceeded hard time limit"
                     );
-                    Ok(TaskOutcome::Failure {
+                    return Ok(TaskOutcome::Failure {
                         error: format!("Task exceeded hard time limit of {}s", timeout.as_secs()),
+                        traceback: None,
                         retryable: false,
-                    })
+                    });
                 }
+                _ = &mut soft_deadline, if !soft_expired => {
+                    soft_expired = true;
+                    ctx.cancellation.cancel();
+                    tracing::warn!(
+                        task_id = %ctx.task_id,
+                        task_name = %ctx.task_name,
+                        timeout_secs = soft.unwrap_or_default().as_secs(),
+                        "Task exceeded soft time limit, cancelling"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Claim the message's idempotency key before executing
+    /// Returns `false` if the task must not run
+    async fn claim_key(&self, msg: &TaskMessage) -> Result<bool, TaskError> {
+        let (Some(store), Some(key)) = (&self.idempotency, &msg.idempotency_key) else {
+            return Ok(true);
+        };
+
+        match store.claim(key, &msg.id, &self.worker_id).await? {
+            Claim::Acquired => Ok(true),
+            Claim::Completed => {
+                tracing::info!(
+                    task_id = %msg.id,
+                    task_name = %msg.task_name,
+                    idempotency_key = %key,
+                    "Task already ran, skipping redelivery"
+                );
+                Ok(false)
+            }
+            Claim::Running { worker_id } => {
+                tracing::info!(
+                    task_id = %msg.id,
+                    task_name = %msg.task_name,
+                    idempotency_key = %key,
+                    running_on = ?worker_id,
+                    "Task is already running, skipping redelivery"
+                );
+                Ok(false)
             }
+            Claim::Duplicate(original) => {
+                tracing::warn!(
+                    task_id = %msg.id,
+                    task_name = %msg.task_name,
+                    idempotency_key = %key,
+                    original_task_id = %original,
+                    "Duplicate task, skipping execution"
+                );
+                let result = TaskResult {
+                    state: TaskState::Rejected,
+                    worker_id: Some(self.worker_id.clone()),
+                    ..TaskResult::failure(msg.id.clone(), format!("Duplicate of task {}", original))
+                };
+                self.backend.set_result(&msg.id, result, None).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Settle the claim on the message's idempotency key once an attempt is
+    /// over: keep it if the task is finished, release it if it will retry
+    async fn settle_key(&self, msg: &TaskMessage, finished: bool) -> Result<(), TaskError> {
+        let (Some(store), Some(key)) = (&self.idempotency, &msg.idempotency_key) else {
+            return Ok(());
+        };
+        if finished {
+            store.complete(key, &msg.id).await
         } else {
-            // No timeout, execute normally
-            Ok(task.execute(ctx, args).await)
+            store.release(key, &msg.id).await
         }
     }
-}
 
-#[async_trait]
-impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
-    async fn handle(&self, message: BrokerMessage) -> Result<(), TaskError> {
+    /// Republish a failed task to its queue with the attempt recorded, or
+    /// have it redelivered
+    async fn retry(
+        &self,
+        msg: &TaskMessage,
+        attempt: TaskAttempt,
+        reason: String,
+        max_retries: u32,
+        delay: Duration,
+    ) -> Result<Settlement, TaskError> {
+        let mut retry = msg.clone().for_retry();
+        retry.attempts.push(attempt);
+        if !delay.is_zero() {
+            retry.eta = chrono::Duration::from_std(delay).ok().map(|delay| Utc::now() + delay);
+        }
+        let eta = retry.eta;
+        let retry_count = retry.retries;
+
+        self.settle_key(msg, false).await?;
+        let settlement = if self.redeliver {
+            Settlement::Redeliver(delay)
+        } else {
+            self.broker.publish(&self.queue, retry).await?;
+            Settlement::Ack
+        };
+        self.backend.set_state(&msg.id, TaskState::Retry).await?;
+
+        tracing::info!(
+            task_id = %msg.id,
+            task_name = %msg.task_name,
+            retry_count = retry_count,
+            max_retries = max_retries,
+            delay_ms = delay.as_millis() as u64,
+            "Task scheduled for retry"
+        );
+
+        if let Some(dispatcher) = &self.signal_dispatcher {
+            dispatcher.dispatch_background(Signal::TaskRetry {
+                task_id: msg.id.clone(),
+                task_name: msg.task_name.clone(),
+                reason,
+                retry_count,
+                max_retries,
+                eta,
+            });
+        }
+
+        Ok(settlement)
+    }
+
+    /// Move the task's workflow on, if it runs a workflow step
+    async fn advance_workflow(&self, msg: &TaskMessage, outcome: StepOutcome) -> Result<(), TaskError> {
+        match &msg.workflow {
+            Some(step) => {
+                dag::advance(self.broker.as_ref(), self.backend.as_ref(), step, &msg.id, outcome).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Move a task that ran out of retries to the dead-letter store
+    async fn dead_letter(&self, msg: &TaskMessage, attempt: TaskAttempt) -> Result<(), TaskError> {
+        let Some(store) = &self.dead_letters else {
+            tracing::warn!(
+                task_id = %msg.id,
+                task_name = %msg.task_name,
+                retries = msg.retries,
+                "Task exhausted its retries and no dead-letter store is configured"
+            );
+            return Ok(());
+        };
+
+        let error = attempt.error.clone();
+        let traceback = attempt.traceback.clone();
+        let mut message = msg.clone();
+        message.attempts.push(attempt);
+
+        let letter = DeadLetter::new(self.queue.clone(), message, DeadLetterReason::RetriesExhausted, error)
+            .with_traceback(traceback)
+            .with_worker(self.worker_id.clone());
+        store.push(letter).await
+    }
+
+    /// Handle a message, returning how the broker should settle it
+    pub(crate) async fn process(&self, message: BrokerMessage) -> Result<Settlement, TaskError> {
+        let _in_flight = self.stats.receive();
         let msg = message.payload;
         let task_id = msg.id.clone();
         let task_name = msg.task_name.clone();
@@ -157,7 +419,9 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
             self.backend
                 .set_state(&task_id, TaskState::Revoked)
                 .await?;
-            return Ok(());
+            self.advance_workflow(&msg, StepOutcome::Failed("Task expired".to_string()))
+                .await?;
+            return Ok(Settlement::Ack);
         }
 
         // Check if task has been revoked
@@ -182,7 +446,9 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
             self.backend
                 .set_state(&task_id, TaskState::Revoked)
                 .await?;
-            return Ok(());
+            self.advance_workflow(&msg, StepOutcome::Failed("Task was revoked".to_string()))
+                .await?;
+            return Ok(Settlement::Ack);
         }
 
         // Look up task in registry
@@ -231,10 +497,16 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
             }
         }
 
-        // Acquire semaphore permit for concurrency control
-        let _permit = self.semaphore.acquire().await.map_err(|e| {
-            TaskError::Internal(format!("Failed to acquire semaphore permit: {}", e))
-        })?;
+        // Wait for an execution slot, shared with the worker's other queues;
+        // once the worker is shutting down the message goes back to the queue
+        let Some(_permit) = self.slots.acquire(&self.queue, msg.priority).await else {
+            return Err(TaskError::ShuttingDown);
+        };
+
+        if !self.claim_key(&msg).await? {
+            return Ok(Settlement::Ack);
+        }
+        let _active = self.stats.start();
 
         // Update state to RECEIVED
         self.backend
@@ -254,6 +526,7 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
             correlation_id: msg.correlation_id.clone(),
             parent_id: msg.parent_id.clone(),
             root_id: msg.root_id.clone(),
+            cancellation: CancellationToken::new(),
         };
 
         // Update state to STARTED
@@ -275,16 +548,24 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
                 task_id: task_id.clone(),
                 task_name: task_name.clone(),
                 args: msg.args.clone(),
-                kwargs: serde_json::json!({}),
+                kwargs: msg.kwargs.clone(),
                 worker_name: self.worker_id.clone(),
             });
         }
 
         // Execute task with timeout handling
         let start_instant = std::time::Instant::now();
-        let outcome = self
+        let outcome = match self
             .execute_with_timeout(task.clone(), ctx.clone(), msg.args.clone())
-            .await?;
+            .await
+        {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                // Let the redelivered message claim the key again
+                self.settle_key(&msg, false).await?;
+                return Err(e);
+            }
+        };
 
         let end_time = Utc::now();
         let runtime_ms = (end_time - start_time).num_milliseconds() as u64;
@@ -322,6 +603,8 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
                     });
                 }
 
+                self.settle_key(&msg, true).await?;
+
                 // Update state to SUCCESS
                 self.backend
                     .set_state(&task_id, TaskState::Success)
@@ -331,7 +614,7 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
                 let result = TaskResult {
                     task_id: task_id.clone(),
                     state: TaskState::Success,
-                    result: Some(value),
+                    result: Some(value.clone()),
                     error: None,
                     traceback: None,
                     started_at: Some(start_time),
@@ -341,8 +624,44 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
                     worker_id: Some(self.worker_id.clone()),
                 };
                 self.backend.set_result(&task_id, result, None).await?;
+                self.advance_workflow(&msg, StepOutcome::Succeeded(value)).await?;
             }
-            TaskOutcome::Failure { error, retryable } => {
+            TaskOutcome::Failure { error, traceback, retryable } => {
+                let attempt = TaskAttempt {
+                    attempt: msg.retries,
+                    error: error.clone(),
+                    traceback: traceback.clone(),
+                    worker_id: Some(self.worker_id.clone()),
+                    started_at: start_time,
+                    finished_at: end_time,
+                };
+
+                if retryable && retry_policy.should_retry(&error, msg.retries) {
+                    tracing::warn!(
+                        task_id = %task_id,
+                        task_name = %task_name,
+                        runtime_ms = runtime_ms,
+                        error = %error,
+                        "Task failed, retrying"
+                    );
+
+                    // Emit TaskPostrun signal
+                    if let Some(dispatcher) = &self.signal_dispatcher {
+                        dispatcher.dispatch_background(Signal::TaskPostrun {
+                            task_id: task_id.clone(),
+                            task_name: task_name.clone(),
+                            state: TaskState::Retry,
+                            runtime,
+                            worker_name: self.worker_id.clone(),
+                        });
+                    }
+
+                    let delay = retry_policy.delay_for_attempt(msg.retries + 1);
+                    return self
+                        .retry(&msg, attempt, error, retry_policy.max_retries, delay)
+                        .await;
+                }
+
                 tracing::warn!(
                     task_id = %task_id,
                     task_name = %task_name,
@@ -369,33 +688,27 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
                         task_id: task_id.clone(),
                         task_name: task_name.clone(),
                         error: error.clone(),
-                        traceback: None,
+                        traceback: traceback.clone(),
                         runtime,
                         worker_name: self.worker_id.clone(),
                     });
                 }
 
-                // IMPORTANT: We need a broker instance to republish for retry.
-                // Since MessageHandler doesn't have access to broker, we can't retry here.
-                // The retry logic needs to be handled differently - either:
-                // 1. Pass broker to TaskExecutor (requires refactoring MessageHandler trait)
-                // 2. Store retry state and let a separate component handle republishing
-                // 3. Return error to let caller (NatsBroker) handle retry via nack
-                //
-                // For now, we'll mark as FAILURE and log. The nack in NatsBroker will
-                // trigger redelivery, which provides basic retry functionality.
-                // TODO: Implement proper retry logic with delay calculation
+                self.settle_key(&msg, true).await?;
 
-                self.backend
-                    .set_state(&task_id, TaskState::Failure)
-                    .await?;
+                // Retryable errors only stop here once the retry budget is spent.
+                // Dead-letter before storing the result, so a terminal result
+                // implies the dead letter exists.
+                if retryable && msg.retries >= retry_policy.max_retries {
+                    self.dead_letter(&msg, attempt).await?;
+                }
 
                 let result = TaskResult {
                     task_id: task_id.clone(),
                     state: TaskState::Failure,
                     result: None,
                     error: Some(error.clone()),
-                    traceback: None,
+                    traceback,
                     started_at: Some(start_time),
                     completed_at: Some(end_time),
                     runtime_ms: Some(runtime_ms),
@@ -403,34 +716,101 @@ impl<R: ResultBackend> MessageHandler for TaskExecutor<R> {
                     worker_id: Some(self.worker_id.clone()),
                 };
                 self.backend.set_result(&task_id, result, None).await?;
+                self.advance_workflow(&msg, StepOutcome::Failed(error)).await?;
+            }
+            TaskOutcome::Retry { reason, countdown } => {
+                let attempt = TaskAttempt {
+                    attempt: msg.retries,
+                    error: reason.clone(),
+                    traceback: None,
+                    worker_id: Some(self.worker_id.clone()),
+                    started_at: start_time,
+                    finished_at: end_time,
+                };
 
-                // Return error to trigger nack if retryable
-                if retryable {
-                    return Err(TaskError::Internal(error));
+                if msg.retries < retry_policy.max_retries {
+                    tracing::info!(
+                        task_id = %task_id,
+                        task_name = %task_name,
+                        runtime_ms = runtime_ms,
+                        reason = %reason,
+                        "Task requested retry"
+                    );
+
+                    let delay = countdown
+                        .unwrap_or_else(|| retry_policy.delay_for_attempt(msg.retries + 1));
+                    return self
+                        .retry(&msg, attempt, reason, retry_policy.max_retries, delay)
+                        .await;
                 }
-            }
-            TaskOutcome::Retry { reason, countdown: _ } => {
-                tracing::info!(
+
+                let error = format!("Max retries ({}) exceeded: {}", retry_policy.max_retries, reason);
+                tracing::warn!(
                     task_id = %task_id,
                     task_name = %task_name,
                     runtime_ms = runtime_ms,
-                    reason = %reason,
-                    "Task requested retry"
+                    error = %error,
+                    "Task requested retry with no retries left"
                 );
 
-                // Same issue as above - need broker for retry
-                // For now, return error to trigger nack
-                self.backend
-                    .set_state(&task_id, TaskState::Retry)
-                    .await?;
+                self.settle_key(&msg, true).await?;
+                self.dead_letter(&msg, attempt).await?;
+
+                let result = TaskResult {
+                    task_id: task_id.clone(),
+                    state: TaskState::Failure,
+                    result: None,
+                    error: Some(error.clone()),
+                    traceback: None,
+                    started_at: Some(start_time),
+                    completed_at: Some(end_time),
+                    runtime_ms: Some(runtime_ms),
+                    retries: msg.retries,
+                    worker_id: Some(self.worker_id.clone()),
+                };
+                self.backend.set_result(&task_id, result, None).await?;
+                self.advance_workflow(&msg, StepOutcome::Failed(error)).await?;
+            }
+        }
+
+        Ok(Settlement::Ack)
+    }
+}
+
+#[async_trait]
+impl<B: Broker + ?Sized, R: ResultBackend> MessageHandler for TaskExecutor<B, R> {
+    async fn handle(&self, message: BrokerMessage) -> Result<(), TaskError> {
+        match self.process(message).await? {
+            Settlement::Ack => Ok(()),
+            Settlement::Redeliver(_) => Err(TaskError::Broker("Task asked for redelivery".to_string())),
+        }
+    }
 
-                return Err(TaskError::Internal(format!(
-                    "Retry requested: {}",
-                    reason
-                )));
+    async fn handle_poison(&self, poison: PoisonMessage) -> Result<(), TaskError> {
+        let message = poison.message.clone();
+        let error = poison.error.clone();
+
+        // The task will never run again, so its result must not stay pending
+        let result = poison.message.as_ref().map(|msg| TaskResult {
+            retries: msg.retries,
+            worker_id: Some(self.worker_id.clone()),
+            ..TaskResult::failure(msg.id.clone(), poison.error.clone())
+        });
+
+        match &self.dead_letters {
+            Some(store) => {
+                let letter = DeadLetter::from_poison(poison).with_worker(self.worker_id.clone());
+                store.push(letter).await?;
             }
+            None => poison.log_discarded(),
         }
 
+        if let Some(result) = result {
+            self.backend.set_result(&result.task_id.clone(), result, None).await?;
+        }
+        if let Some(msg) = message {
+            self.advance_workflow(&msg, StepOutcome::Failed(error)).await?;
+        }
         Ok(())
     }
 }
@@ -441,11 +821,16 @@ pub struct Worker<B: PullBroker, R: ResultBackend> {
     pub(crate) broker: Arc<B>,
     pub(crate) backend: Arc<R>,
     registry: Arc<TaskRegistry>,
-    semaphore: Arc<Semaphore>,
+    slots: Arc<SlotScheduler>,
+    stats: Arc<WorkerStats>,
     shutdown: CancellationToken,
+    terminate: CancellationToken,
     rate_limiter: Option<Arc<RateLimitManager>>,
     signal_dispatcher: Option<Arc<SignalDispatcher>>,
     revocation_store: Option<Arc<dyn RevocationStore>>,
+    dead_letters: Option<Arc<dyn DeadLetterStore>>,
+    idempotency: Option<Arc<dyn IdempotencyStore>>,
+    queue_inspector: Option<Arc<dyn QueueInspector>>,
 }
 
 impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
@@ -456,7 +841,11 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
         backend: R,
         registry: Arc<TaskRegistry>,
     ) -> Self {
-        let semaphore = Arc::new(Semaphore::new(config.concurrency));
+        let concurrency = match &config.autoscale {
+            Some(autoscale) => Autoscaler::new(autoscale.clone()).initial(),
+            None => config.concurrency,
+        };
+        let slots = SlotScheduler::new(concurrency, &config.queues, config.queue_policy.clone());
         let shutdown = CancellationToken::new();
         let revocation_store = config.revocation_store.clone();
 
@@ -465,11 +854,16 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
             broker: Arc::new(broker),
             backend: Arc::new(backend),
             registry,
-            semaphore,
+            slots,
+            stats: Arc::default(),
             shutdown,
+            terminate: CancellationToken::new(),
             rate_limiter: None,
             signal_dispatcher: None,
             revocation_store,
+            dead_letters: None,
+            idempotency: None,
+            queue_inspector: None,
         }
     }
 
@@ -479,6 +873,12 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
         self
     }
 
+    /// Rate limiter in use, shared so its limits can be changed while the
+    /// worker runs
+    pub fn rate_limiter(&self) -> Option<Arc<RateLimitManager>> {
+        self.rate_limiter.clone()
+    }
+
     /// Set the signal dispatcher for this worker
     pub fn with_signal_dispatcher(mut self, dispatcher: SignalDispatcher) -> Self {
         self.signal_dispatcher = Some(Arc::new(dispatcher));
@@ -491,15 +891,35 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
         self
     }
 
+    /// Set the dead-letter store for tasks that exhaust their retries
+    /// and messages the broker gives up on
+    pub fn with_dead_letter_store<S: DeadLetterStore>(mut self, store: S) -> Self {
+        self.dead_letters = Some(Arc::new(store));
+        self
+    }
+
+    /// Set the idempotency store; tasks with an idempotency key claim it
+    /// before executing, so duplicates and redeliveries of a task that is
+    /// running or already ran are skipped
+    pub fn with_idempotency_store<S: IdempotencyStore>(mut self, store: S) -> Self {
+        self.idempotency = Some(Arc::new(store));
+        self
+    }
+
+    /// Count messages still in the broker as backlog when autoscaling
+    pub fn with_queue_inspector(mut self, inspector: Arc<dyn QueueInspector>) -> Self {
+        self.queue_inspector = Some(inspector);
+        self
+    }
+
     /// Start the worker
-    #[cfg(feature = "nats")]
     pub async fn start(&self) -> Result<(), TaskError> {
         use crate::SubscriptionHandle;
 
         tracing::info!(
             worker_id = %self.config.name,
             queues = ?self.config.queues,
-            concurrency = self.config.concurrency,
+            concurrency = self.concurrency(),
             "Starting worker"
         );
 
@@ -508,7 +928,7 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
             dispatcher.dispatch_background(Signal::WorkerInit {
                 worker_name: self.config.name.clone(),
                 queues: self.config.queues.clone(),
-                concurrency: self.config.concurrency,
+                concurrency: self.concurrency(),
             });
         }
 
@@ -518,17 +938,6 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
         // Connect to backend (perform health check)
         self.backend.health_check().await?;
 
-        // Create task executor
-        let executor = Arc::new(TaskExecutor::new(
-            self.registry.clone(),
-            self.backend.clone(),
-            self.semaphore.clone(),
-            self.config.name.clone(),
-            self.rate_limiter.clone(),
-            self.signal_dispatcher.clone(),
-            self.revocation_store.clone(),
-        ));
-
         // Subscribe to all queues
         let mut subscription_handles: Vec<SubscriptionHandle> = Vec::new();
         for queue in &self.config.queues {
@@ -538,8 +947,27 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
                 "Subscribing to queue"
             );
 
+            // Each queue gets its own executor so retries and dead letters
+            // go back to the queue the message came from
+            let executor = Arc::new(TaskExecutor {
+                broker: self.broker.clone(),
+                queue: queue.clone(),
+                registry: self.registry.clone(),
+                backend: self.backend.clone(),
+                slots: self.slots.clone(),
+                stats: self.stats.clone(),
+                terminate: self.terminate.clone(),
+                worker_id: self.config.name.clone(),
+                rate_limiter: self.rate_limiter.clone(),
+                signal_dispatcher: self.signal_dispatcher.clone(),
+                revocation_store: self.revocation_store.clone(),
+                dead_letters: self.dead_letters.clone(),
+                idempotency: self.idempotency.clone(),
+                redeliver: false,
+            });
+
             // Call subscribe directly on the PullBroker trait
-            let handle = self.broker.subscribe(queue, executor.clone()).await?;
+            let handle = self.broker.subscribe(queue, executor).await?;
             subscription_handles.push(handle);
         }
 
@@ -556,6 +984,8 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
         let broker = self.broker.clone();
         let shutdown = self.shutdown.clone();
         let worker_id = self.config.name.clone();
+        let dispatcher = self.signal_dispatcher.clone();
+        let stats = self.stats.clone();
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(heartbeat_interval);
@@ -574,11 +1004,23 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
                         if let Err(e) = broker.health_check().await {
                             tracing::error!(worker_id = %worker_id, error = %e, "Broker health check failed");
                         }
+                        if let Some(dispatcher) = &dispatcher {
+                            dispatcher.dispatch_background(Signal::WorkerHeartbeat {
+                                worker_name: worker_id.clone(),
+                                active_tasks: stats.active.load(Ordering::Relaxed),
+                                processed: stats.processed.load(Ordering::Relaxed),
+                                timestamp: Utc::now(),
+                            });
+                        }
                     }
                 }
             }
         });
 
+        if let Some(autoscale) = &self.config.autoscale {
+            self.spawn_autoscaler(autoscale.clone());
+        }
+
         // Wait for shutdown signal
         tracing::info!(worker_id = %self.config.name, "Worker started, waiting for shutdown signal");
         self.shutdown.cancelled().await;
@@ -594,13 +1036,32 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
             });
         }
 
-        // Cancel all subscriptions
+        // Stop consuming, and send prefetched messages still waiting for a
+        // slot back to the queue
         for handle in subscription_handles {
             handle.cancel();
         }
+        self.slots.close();
+
+        // Let running tasks finish, then terminate the rest so their messages
+        // are redelivered
+        let drain = tokio::time::timeout(self.config.shutdown_timeout, self.stats.drained());
+        let drained = tokio::select! {
+            drained = drain => drained.is_ok(),
+            _ = self.terminate.cancelled() => false,
+        };
+        if !drained {
+            tracing::warn!(
+                worker_id = %self.config.name,
+                active_tasks = self.stats.active.load(Ordering::Relaxed),
+                "Terminating tasks still running at shutdown"
+            );
+            self.terminate.cancel();
+            self.stats.drained().await;
+        }
 
-        // Wait a bit for in-flight tasks to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        // Give the broker a moment to settle the last messages
+        tokio::time::sleep(Duration::from_millis(100)).await;
 
         // Disconnect from broker
         self.broker.disconnect().await?;
@@ -609,12 +1070,80 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
         Ok(())
     }
 
-    /// Shutdown the worker gracefully
+    /// Adjust the slots to the load every `autoscale.interval` until shutdown
+    fn spawn_autoscaler(&self, autoscale: AutoscaleConfig) {
+        let interval = autoscale.interval;
+        let mut scaler = Autoscaler::new(autoscale);
+        let mut cpu = CpuSampler::default();
+        let slots = self.slots.clone();
+        let stats = self.stats.clone();
+        let inspector = self.queue_inspector.clone();
+        let queues = self.config.queues.clone();
+        let shutdown = self.shutdown.clone();
+        let worker_id = self.config.name.clone();
+
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticks.tick() => {}
+                }
+
+                let mut backlog = slots.waiting();
+                if let Some(inspector) = &inspector {
+                    for queue in &queues {
+                        match inspector.queue_depth(queue).await {
+                            Ok(depth) => backlog += depth,
+                            Err(e) => tracing::warn!(worker_id = %worker_id, queue = %queue, error = %e, "Failed to read queue depth"),
+                        }
+                    }
+                }
+                let load = Load {
+                    busy: stats.active.load(Ordering::Relaxed),
+                    backlog,
+                    cpu: cpu.sample(),
+                };
+
+                let current = slots.capacity();
+                let target = scaler.decide(current, load, std::time::Instant::now());
+                if target != current {
+                    tracing::info!(
+                        worker_id = %worker_id,
+                        from = current,
+                        to = target,
+                        busy = load.busy,
+                        backlog = load.backlog,
+                        cpu = ?load.cpu,
+                        "Scaling worker concurrency"
+                    );
+                    slots.resize(target);
+                }
+            }
+        });
+    }
+
+    /// Shutdown the worker gracefully: stop consuming, return prefetched
+    /// messages to the queue and give running tasks `shutdown_timeout` to
+    /// finish before terminating them
     pub fn shutdown(&self) {
         tracing::info!(worker_id = %self.config.name, "Shutdown requested");
         self.shutdown.cancel();
     }
 
+    /// Shutdown the worker without waiting: running tasks are terminated
+    /// and their messages redelivered
+    pub fn terminate(&self) {
+        tracing::info!(worker_id = %self.config.name, "Termination requested");
+        self.terminate.cancel();
+        self.shutdown.cancel();
+    }
+
+    /// Current concurrency, which changes over time when autoscaling
+    pub fn concurrency(&self) -> usize {
+        self.slots.capacity()
+    }
+
     /// Get worker configuration
     pub fn config(&self) -> &WorkerConfig {
         &self.config
@@ -629,11 +1158,13 @@ impl<B: PullBroker, R: ResultBackend> Worker<B, R> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::sync::Semaphore;
 
     #[test]
     fn test_worker_config_defaults() {
         let config = WorkerConfig::default();
         assert_eq!(config.queues, vec!["default".to_string()]);
+        assert_eq!(config.queue_policy, QueuePolicy::RoundRobin);
         assert_eq!(config.concurrency, num_cpus::get());
         assert_eq!(config.prefetch, 4);
         assert_eq!(config.heartbeat, Duration::from_secs(10));
@@ -646,10 +1177,13 @@ mod tests {
         let config = WorkerConfig {
             name: "custom-worker".to_string(),
             queues: vec!["queue1".to_string(), "queue2".to_string()],
+            queue_policy: QueuePolicy::Strict,
             concurrency: 16,
             prefetch: 10,
             heartbeat: Duration::from_secs(30),
             revocation_store: None,
+            autoscale: None,
+            shutdown_timeout: Duration::from_secs(5),
         };
         assert_eq!(config.name, "custom-worker");
         assert_eq!(config.queues.len(), 2);
@@ -733,6 +1267,518 @@ mod tests {
         assert_eq!(count.load(Ordering::SeqCst), 0);
     }
 
+    #[tokio::test]
+    async fn test_worker_in_memory() {
+        use crate::{Broker, InMemoryBackend, InMemoryBroker, TaskMessage};
+
+        struct EchoTask;
+
+        #[async_trait]
+        impl Task for EchoTask {
+            fn name(&self) -> &'static str {
+                "echo"
+            }
+
+            async fn execute(&self, _ctx: TaskContext, args: serde_json::Value) -> TaskOutcome {
+                TaskOutcome::Success(args)
+            }
+        }
+
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(EchoTask);
+
+        let worker_config = WorkerConfig {
+            queues: vec!["test".to_string()],
+            concurrency: 2,
+            ..Default::default()
+        };
+        let worker = Arc::new(Worker::new(worker_config, broker.clone(), backend.clone(), registry));
+
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        // Clones share the same queues and results
+        let msg = TaskMessage::new("echo", serde_json::json!([1, 2, 3]));
+        let task_id = msg.id.clone();
+        broker.publish("test", msg).await.unwrap();
+
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(5)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(result.state, TaskState::Success);
+        assert_eq!(result.result, Some(serde_json::json!([1, 2, 3])));
+
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+        assert_eq!(broker.queue_len("test"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_worker_retries_then_dead_letters() {
+        use crate::deadletter::{requeue, InMemoryDeadLetterStore};
+        use crate::{Broker, InMemoryBackend, InMemoryBroker, InMemoryBrokerConfig, RetryPolicy};
+
+        struct FlakyTask;
+
+        #[async_trait]
+        impl Task for FlakyTask {
+            fn name(&self) -> &'static str {
+                "flaky"
+            }
+
+            fn retry_policy(&self) -> RetryPolicy {
+                RetryPolicy::fixed(2, Duration::from_millis(10))
+            }
+
+            async fn execute(&self, ctx: TaskContext, _args: serde_json::Value) -> TaskOutcome {
+                TaskOutcome::Failure {
+                    error: format!("attempt {} failed", ctx.retry_count),
+                    traceback: Some("Traceback (most recent call last)".to_string()),
+                    retryable: true,
+                }
+            }
+        }
+
+        let broker = InMemoryBroker::new(InMemoryBrokerConfig {
+            max_deliver: 2,
+            ..Default::default()
+        });
+        let backend = InMemoryBackend::new();
+        let dead_letters = InMemoryDeadLetterStore::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(FlakyTask);
+
+        let worker_config = WorkerConfig {
+            queues: vec!["test".to_string()],
+            concurrency: 2,
+            ..Default::default()
+        };
+        let worker = Arc::new(
+            Worker::new(worker_config, broker.clone(), backend.clone(), registry)
+                .with_dead_letter_store(dead_letters.clone()),
+        );
+
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        let msg = TaskMessage::new("flaky", serde_json::json!([]));
+        let task_id = msg.id.clone();
+        broker.publish("test", msg).await.unwrap();
+
+        // Unknown tasks are nacked until the broker gives up on them
+        let unknown = TaskMessage::new("missing", serde_json::json!([]));
+        let unknown_id = unknown.id.clone();
+        broker.publish("test", unknown).await.unwrap();
+
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(5)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(result.state, TaskState::Failure);
+        assert_eq!(result.retries, 2);
+        assert_eq!(result.error.as_deref(), Some("attempt 2 failed"));
+
+        let letter = dead_letters.get("test", &task_id).await.unwrap().unwrap();
+        assert_eq!(letter.reason, DeadLetterReason::RetriesExhausted);
+        assert_eq!(letter.traceback.as_deref(), Some("Traceback (most recent call last)"));
+        let attempts: Vec<u32> = letter.attempts().iter().map(|a| a.attempt).collect();
+        assert_eq!(attempts, vec![0, 1, 2]);
+
+        let poisoned = backend
+            .wait_for_result(&unknown_id, Some(Duration::from_secs(5)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(poisoned.state, TaskState::Failure);
+        let letter = dead_letters.get("test", &unknown_id).await.unwrap().unwrap();
+        assert_eq!(letter.reason, DeadLetterReason::MaxDeliveries);
+
+        // A requeued task gets a fresh retry budget and keeps its history
+        backend.delete(&task_id).await.unwrap();
+        assert!(requeue(&dead_letters, &broker, "test", &task_id).await.unwrap());
+        backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(5)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        let letter = dead_letters.get("test", &task_id).await.unwrap().unwrap();
+        assert_eq!(letter.attempts().len(), 6);
+
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_worker_idempotency() {
+        use crate::idempotency::{IdempotencyStatus, InMemoryIdempotencyStore};
+        use crate::{Broker, InMemoryBackend, InMemoryBroker, RetryPolicy};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Fails its first attempt, counting every execution
+        struct ChargeTask {
+            executions: Arc<AtomicUsize>,
+        }
+
+        #[async_trait]
+        impl Task for ChargeTask {
+            fn name(&self) -> &'static str {
+                "charge"
+            }
+
+            fn retry_policy(&self) -> RetryPolicy {
+                RetryPolicy::fixed(1, Duration::from_millis(10))
+            }
+
+            async fn execute(&self, ctx: TaskContext, args: serde_json::Value) -> TaskOutcome {
+                self.executions.fetch_add(1, Ordering::SeqCst);
+                if ctx.retry_count == 0 {
+                    TaskOutcome::Failure {
+                        error: "gateway timeout".to_string(),
+                        traceback: None,
+                        retryable: true,
+                    }
+                } else {
+                    TaskOutcome::Success(args)
+                }
+            }
+        }
+
+        let executions = Arc::new(AtomicUsize::new(0));
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let store = InMemoryIdempotencyStore::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(ChargeTask {
+            executions: executions.clone(),
+        });
+
+        let worker_config = WorkerConfig {
+            queues: vec!["test".to_string()],
+            concurrency: 1,
+            ..Default::default()
+        };
+        let worker = Arc::new(
+            Worker::new(worker_config, broker.clone(), backend.clone(), registry)
+                .with_idempotency_store(store.clone()),
+        );
+
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        // The retry claims the key again after the failed attempt released it
+        let msg = TaskMessage::new("charge", serde_json::json!([42])).with_idempotency_key("order-42");
+        let task_id = msg.id.clone();
+        broker.publish("test", msg.clone()).await.unwrap();
+        let result = backend
+            .wait_for_result(&task_id, Some(Duration::from_secs(5)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(result.state, TaskState::Success);
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+        assert_eq!(store.get("order-42").await.unwrap().unwrap().status, IdempotencyStatus::Completed);
+
+        // A duplicate published without going through `enqueue` is rejected
+        let duplicate = TaskMessage::new("charge", serde_json::json!([42])).with_idempotency_key("order-42");
+        let duplicate_id = duplicate.id.clone();
+        broker.publish("test", duplicate).await.unwrap();
+        let rejected = backend
+            .wait_for_result(&duplicate_id, Some(Duration::from_secs(5)), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(rejected.state, TaskState::Rejected);
+        assert_eq!(rejected.error, Some(format!("Duplicate of task {}", task_id)));
+
+        // A redelivery of the completed task is acked without running
+        broker.publish("test", msg.for_retry()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(broker.queue_len("test"), 0);
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_worker_runs_dag() {
+        use crate::workflow::{Condition, Dag, DagNode, NodeState, TaskSignature, WorkflowStatus};
+        use crate::{InMemoryBackend, InMemoryBroker};
+
+        /// Sums its numeric arguments
+        struct SumTask;
+
+        #[async_trait]
+        impl Task for SumTask {
+            fn name(&self) -> &'static str {
+                "sum"
+            }
+
+            async fn execute(&self, _ctx: TaskContext, args: serde_json::Value) -> TaskOutcome {
+                let total: i64 = args
+                    .as_array()
+                    .map(|args| args.iter().filter_map(|v| v.as_i64()).sum())
+                    .unwrap_or(0);
+                TaskOutcome::Success(serde_json::json!(total))
+            }
+        }
+
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(SumTask);
+
+        let worker_config = WorkerConfig {
+            queues: vec!["default".to_string()],
+            concurrency: 2,
+            ..Default::default()
+        };
+        let worker = Arc::new(Worker::new(worker_config, broker.clone(), backend.clone(), registry));
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        let sum = |args: serde_json::Value| TaskSignature::new("sum", args);
+        let dag = Dag::new()
+            .with_node(DagNode::new("a", sum(serde_json::json!([1, 2]))))
+            .with_node(DagNode::new("b", sum(serde_json::json!([10]))).after(["a"]))
+            .with_node(DagNode::new("c", sum(serde_json::json!([100]))).after(["a"]))
+            .with_node(
+                DagNode::new("big", sum(serde_json::json!([])))
+                    .after(["b"])
+                    .when(Condition::equals("b", "", serde_json::json!(1000))),
+            )
+            .with_node(DagNode::new("total", sum(serde_json::json!([]))).after(["b", "c", "big"]));
+        let dag_result = dag.apply_async(&broker, &backend).await.unwrap();
+
+        let results = dag_result.get(&backend, Some(Duration::from_secs(5))).await.unwrap();
+        assert_eq!(results["b"], serde_json::json!(13));
+        assert_eq!(results["c"], serde_json::json!(103));
+        assert_eq!(results["total"], serde_json::json!(116));
+
+        let run = dag_result.state(&backend).await.unwrap().unwrap();
+        assert_eq!(run.status, WorkflowStatus::Succeeded);
+        assert_eq!(run.node("big").unwrap().state, NodeState::Skipped);
+
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+    }
+
+    /// Sleeps for `args[0]` milliseconds, stopping early if cancelled
+    struct SleepTask {
+        soft_limit: Option<Duration>,
+        hard_limit: Option<Duration>,
+    }
+
+    #[async_trait]
+    impl Task for SleepTask {
+        fn name(&self) -> &'static str {
+            "sleep"
+        }
+
+        fn soft_time_limit(&self) -> Option<Duration> {
+            self.soft_limit
+        }
+
+        fn hard_time_limit(&self) -> Option<Duration> {
+            self.hard_limit
+        }
+
+        async fn execute(&self, ctx: TaskContext, args: serde_json::Value) -> TaskOutcome {
+            let millis = args[0].as_u64().unwrap_or(0);
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(millis)) => {
+                    TaskOutcome::Success(serde_json::json!("slept"))
+                }
+                _ = ctx.cancelled() => TaskOutcome::Success(serde_json::json!("cancelled")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_time_limits() {
+        use crate::{Broker, InMemoryBackend, InMemoryBroker};
+
+        /// Ignores cancellation
+        struct StubbornTask;
+
+        #[async_trait]
+        impl Task for StubbornTask {
+            fn name(&self) -> &'static str {
+                "stubborn"
+            }
+
+            fn soft_time_limit(&self) -> Option<Duration> {
+                Some(Duration::from_millis(20))
+            }
+
+            fn hard_time_limit(&self) -> Option<Duration> {
+                Some(Duration::from_millis(60))
+            }
+
+            async fn execute(&self, _ctx: TaskContext, _args: serde_json::Value) -> TaskOutcome {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                TaskOutcome::Success(serde_json::Value::Null)
+            }
+        }
+
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(SleepTask {
+            soft_limit: Some(Duration::from_millis(20)),
+            hard_limit: None,
+        });
+        registry.register(StubbornTask);
+
+        let worker_config = WorkerConfig {
+            concurrency: 2,
+            ..Default::default()
+        };
+        let worker = Arc::new(Worker::new(worker_config, broker.clone(), backend.clone(), registry));
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        let cooperative = TaskMessage::new("sleep", serde_json::json!([60_000]));
+        let stubborn = TaskMessage::new("stubborn", serde_json::json!(null));
+        let (cooperative_id, stubborn_id) = (cooperative.id.clone(), stubborn.id.clone());
+        broker.publish("default", cooperative).await.unwrap();
+        broker.publish("default", stubborn).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // The soft limit lets the task stop on its own terms
+        let result = backend.get_result(&cooperative_id).await.unwrap().unwrap();
+        assert_eq!(result.state, TaskState::Success);
+        assert_eq!(result.result, Some(serde_json::json!("cancelled")));
+
+        // The hard limit stops it regardless
+        let result = backend.get_result(&stubborn_id).await.unwrap().unwrap();
+        assert_eq!(result.state, TaskState::Failure);
+        assert!(result.error.unwrap().contains("hard time limit"));
+
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_worker_warm_shutdown() {
+        use crate::{Broker, InMemoryBackend, InMemoryBroker};
+
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(SleepTask {
+            soft_limit: None,
+            hard_limit: None,
+        });
+
+        let worker_config = WorkerConfig {
+            concurrency: 1,
+            prefetch: 4,
+            shutdown_timeout: Duration::from_secs(5),
+            ..Default::default()
+        };
+        let worker = Arc::new(Worker::new(worker_config, broker.clone(), backend.clone(), registry));
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        let messages: Vec<TaskMessage> = (0..3)
+            .map(|_| TaskMessage::new("sleep", serde_json::json!([200])))
+            .collect();
+        let first = messages[0].id.clone();
+        for msg in messages {
+            broker.publish("default", msg).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The running task finishes; the two waiting for a slot go back
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+        let result = backend.get_result(&first).await.unwrap().unwrap();
+        assert_eq!(result.result, Some(serde_json::json!("slept")));
+        assert_eq!(broker.queue_len("default"), 2);
+        assert_eq!(broker.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_worker_terminates_after_shutdown_timeout() {
+        use crate::{Broker, InMemoryBackend, InMemoryBroker};
+
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(SleepTask {
+            soft_limit: None,
+            hard_limit: None,
+        });
+
+        let worker_config = WorkerConfig {
+            concurrency: 1,
+            shutdown_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let worker = Arc::new(Worker::new(worker_config, broker.clone(), backend.clone(), registry));
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        let msg = TaskMessage::new("sleep", serde_json::json!([60_000]));
+        let task_id = msg.id.clone();
+        broker.publish("default", msg).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The task outlives the deadline and is redelivered later
+        worker.shutdown();
+        tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .expect("shutdown should not wait for the task")
+            .unwrap()
+            .unwrap();
+        assert!(backend.get_result(&task_id).await.unwrap().is_none());
+        assert_eq!(broker.queue_len("default"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_worker_autoscales() {
+        use crate::{Broker, InMemoryBackend, InMemoryBroker};
+
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(SleepTask {
+            soft_limit: None,
+            hard_limit: None,
+        });
+
+        let worker_config = WorkerConfig {
+            autoscale: Some(AutoscaleConfig {
+                min: 1,
+                max: 4,
+                interval: Duration::from_millis(20),
+                max_cpu: None,
+                scale_down_after: Duration::from_millis(100),
+            }),
+            ..Default::default()
+        };
+        let worker = Worker::new(worker_config, broker.clone(), backend.clone(), registry)
+            .with_queue_inspector(Arc::new(broker.clone()));
+        let worker = Arc::new(worker);
+        assert_eq!(worker.concurrency(), 1);
+        let worker_clone = worker.clone();
+        let handle = tokio::spawn(async move { worker_clone.start().await });
+
+        for _ in 0..8 {
+            let msg = TaskMessage::new("sleep", serde_json::json!([300]));
+            broker.publish("default", msg).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(worker.concurrency(), 4);
+
+        // Back down once the backlog is gone
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert_eq!(worker.concurrency(), 1);
+
+        worker.shutdown();
+        handle.await.unwrap().unwrap();
+    }
+
     #[tokio::test]
     async fn test_worker_without_signal_dispatcher() {
         // Verify that Worker works without signal dispatcher (backward compatibility)
@@ -781,6 +1827,7 @@ mod tests {
             ) -> TaskOutcome {
                 TaskOutcome::Failure {
                     error: "Task failed".to_string(),
+                    traceback: None,
                     retryable: false,
                 }
             }
@@ -826,10 +1873,12 @@ mod tests {
             let worker_config = WorkerConfig {
                 name: "test-worker".to_string(),
                 queues: vec!["test".to_string()],
+                queue_policy: QueuePolicy::default(),
                 concurrency: 2,
                 prefetch: 4,
                 heartbeat: Duration::from_secs(10),
                 revocation_store: None,
+                ..Default::default()
             };
 
             let worker = Worker::new(worker_config, broker, backend, registry);
@@ -873,10 +1922,12 @@ mod tests {
             let worker_config = WorkerConfig {
                 name: "test-worker".to_string(),
                 queues: vec!["test".to_string()],
+                queue_policy: QueuePolicy::default(),
                 concurrency: 2,
                 prefetch: 4,
                 heartbeat: Duration::from_secs(10),
                 revocation_store: None,
+                ..Default::default()
             };
 
             let worker = Arc::new(Worker::new(
@@ -934,10 +1985,12 @@ mod tests {
             let worker_config = WorkerConfig {
                 name: "test-worker".to_string(),
                 queues: vec!["test".to_string()],
+                queue_policy: QueuePolicy::default(),
                 concurrency: 1,
                 prefetch: 1,
                 heartbeat: Duration::from_secs(10),
                 revocation_store: None,
+                ..Default::default()
             };
 
             let worker = Arc::new(Worker::new(
@@ -0,0 +1,384 @@
+//! HTTP endpoint running pushed tasks
+//!
+//! `PushWorker` serves a push broker's endpoint and runs each delivery
+//! through the `TaskRegistry`, like a pulling `Worker` does. The response
+//! tells the pushing service what to do with the delivery:
+//!
+//! | Outcome | Status |
+//! |---|---|
+//! | Success, final failure, revoked, expired, undecodable | ack (2xx) |
+//! | Retry (failure within the retry budget, or `TaskOutcome::Retry`) | nack, with `Retry-After` |
+//! | Worker shutting down | 503, with `Retry-After` |
+//! | Authentication failed | 401 |
+//!
+//! Retries are left to the pushing service's redelivery, so its retry and
+//! backoff settings apply. Follow-up messages, such as the next steps of a
+//! DAG workflow, need a broker to publish to: see `with_publisher`.
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use super::scheduling::SlotScheduler;
+use super::{Settlement, TaskExecutor, WorkerConfig, WorkerStats};
+use crate::deadletter::DeadLetterStore;
+use crate::idempotency::IdempotencyStore;
+use crate::ratelimit::RateLimitManager;
+use crate::revocation::RevocationStore;
+use crate::signals::{ShutdownReason, Signal, SignalDispatcher};
+use crate::{Broker, MessageHandler, PoisonMessage, PushBroker, ResultBackend, TaskError, TaskRegistry};
+
+/// Runs tasks pushed over HTTP
+///
+/// Of the `WorkerConfig`, the name, queues (the first is where undecodable
+/// deliveries are recorded), queue policy, concurrency, shutdown timeout
+/// and revocation store apply.
+pub struct PushWorker<P: PushBroker, R: ResultBackend> {
+    config: WorkerConfig,
+    push: Arc<P>,
+    publisher: Arc<dyn Broker>,
+    backend: Arc<R>,
+    registry: Arc<TaskRegistry>,
+    slots: Arc<SlotScheduler>,
+    stats: Arc<WorkerStats>,
+    terminate: CancellationToken,
+    rate_limiter: Option<Arc<RateLimitManager>>,
+    signal_dispatcher: Option<Arc<SignalDispatcher>>,
+    revocation_store: Option<Arc<dyn RevocationStore>>,
+    dead_letters: Option<Arc<dyn DeadLetterStore>>,
+    idempotency: Option<Arc<dyn IdempotencyStore>>,
+    executors: DashMap<String, Arc<TaskExecutor<dyn Broker, R>>>,
+}
+
+impl<P: PushBroker, R: ResultBackend> PushWorker<P, R> {
+    /// Create a push worker
+    pub fn new(config: WorkerConfig, push: P, backend: R, registry: Arc<TaskRegistry>) -> Self {
+        let slots = SlotScheduler::new(config.concurrency, &config.queues, config.queue_policy.clone());
+        let push = Arc::new(push);
+        let revocation_store = config.revocation_store.clone();
+
+        Self {
+            config,
+            publisher: push.clone(),
+            push,
+            backend: Arc::new(backend),
+            registry,
+            slots,
+            stats: Arc::default(),
+            terminate: CancellationToken::new(),
+            rate_limiter: None,
+            signal_dispatcher: None,
+            revocation_store,
+            dead_letters: None,
+            idempotency: None,
+            executors: DashMap::new(),
+        }
+    }
+
+    /// Publish follow-up messages, such as workflow steps, with `broker`
+    /// (by default the push broker, which cannot publish)
+    pub fn with_publisher<B: Broker>(mut self, broker: B) -> Self {
+        self.publisher = Arc::new(broker);
+        self
+    }
+
+    /// Set the rate limiter
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimitManager) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+        self
+    }
+
+    /// Set the signal dispatcher
+    pub fn with_signal_dispatcher(mut self, dispatcher: SignalDispatcher) -> Self {
+        self.signal_dispatcher = Some(Arc::new(dispatcher));
+        self
+    }
+
+    /// Set the revocation store
+    pub fn with_revocation_store<S: RevocationStore>(mut self, store: S) -> Self {
+        self.revocation_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Set the dead-letter store for tasks that exhaust their retries and
+    /// deliveries that cannot be decoded
+    pub fn with_dead_letter_store<S: DeadLetterStore>(mut self, store: S) -> Self {
+        self.dead_letters = Some(Arc::new(store));
+        self
+    }
+
+    /// Set the idempotency store
+    pub fn with_idempotency_store<S: IdempotencyStore>(mut self, store: S) -> Self {
+        self.idempotency = Some(Arc::new(store));
+        self
+    }
+
+    /// Executor for the deliveries of one queue
+    fn executor(&self, queue: &str) -> Arc<TaskExecutor<dyn Broker, R>> {
+        self.executors
+            .entry(queue.to_string())
+            .or_insert_with(|| {
+                Arc::new(TaskExecutor {
+                    broker: self.publisher.clone(),
+                    queue: queue.to_string(),
+                    registry: self.registry.clone(),
+                    backend: self.backend.clone(),
+                    slots: self.slots.clone(),
+                    stats: self.stats.clone(),
+                    terminate: self.terminate.clone(),
+                    worker_id: self.config.name.clone(),
+                    rate_limiter: self.rate_limiter.clone(),
+                    signal_dispatcher: self.signal_dispatcher.clone(),
+                    revocation_store: self.revocation_store.clone(),
+                    dead_letters: self.dead_letters.clone(),
+                    idempotency: self.idempotency.clone(),
+                    redeliver: true,
+                })
+            })
+            .clone()
+    }
+
+    /// Route serving the push broker's endpoint, for mounting in a larger
+    /// application
+    pub fn router(self) -> Router {
+        let path = self.push.endpoint_path().to_string();
+        Router::new()
+            .route(&path, post(receive::<P, R>))
+            .with_state(Arc::new(self))
+    }
+
+    /// Serve on `addr` until `shutdown` is cancelled, then give running
+    /// tasks `shutdown_timeout` to finish
+    pub async fn serve(self, addr: SocketAddr, shutdown: CancellationToken) -> Result<(), TaskError> {
+        let name = self.config.name.clone();
+        let dispatcher = self.signal_dispatcher.clone();
+        let (slots, stats, terminate) = (self.slots.clone(), self.stats.clone(), self.terminate.clone());
+        let shutdown_timeout = self.config.shutdown_timeout;
+
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| TaskError::Configuration(format!("Failed to bind {}: {}", addr, e)))?;
+        if let Some(dispatcher) = &dispatcher {
+            dispatcher.dispatch_background(Signal::WorkerInit {
+                worker_name: name.clone(),
+                queues: self.config.queues.clone(),
+                concurrency: self.config.concurrency,
+            });
+            dispatcher.dispatch_background(Signal::WorkerReady {
+                worker_name: name.clone(),
+            });
+        }
+        tracing::info!(worker_id = %name, %addr, "Push worker listening");
+
+        // Once shutdown starts, turn away new deliveries and terminate the
+        // tasks still running at the deadline, so they are redelivered
+        let drain = {
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown.cancelled().await;
+                slots.close();
+                if tokio::time::timeout(shutdown_timeout, stats.drained()).await.is_err() {
+                    terminate.cancel();
+                }
+            }
+        };
+        tokio::spawn(drain);
+
+        let result = axum::serve(listener, self.router())
+            .with_graceful_shutdown(async move { shutdown.cancelled().await })
+            .await
+            .map_err(|e| TaskError::Internal(format!("Push worker server failed: {}", e)));
+
+        if let Some(dispatcher) = &dispatcher {
+            dispatcher.dispatch_background(Signal::WorkerShutdown {
+                worker_name: name.clone(),
+                reason: ShutdownReason::Graceful,
+            });
+        }
+        tracing::info!(worker_id = %name, "Push worker stopped");
+        result
+    }
+}
+
+fn status(code: u16) -> StatusCode {
+    StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Response asking for redelivery after `delay`
+fn redeliver(code: StatusCode, delay: Duration) -> Response {
+    let mut response = code.into_response();
+    let seconds = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+    response.headers_mut().insert("retry-after", HeaderValue::from(seconds));
+    response
+}
+
+async fn receive<P: PushBroker, R: ResultBackend>(
+    State(worker): State<Arc<PushWorker<P, R>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let headers: HashMap<String, String> = headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    if let Err(e) = worker.push.verify_push_request(&headers, &body) {
+        tracing::warn!(worker_id = %worker.config.name, error = %e, "Rejected push request");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let message = match worker.push.parse_push_request(&headers, &body) {
+        Ok(message) => message,
+        // Redelivering would not help, so record it and acknowledge
+        Err(e) => {
+            let queue = worker.config.queues.first().cloned().unwrap_or_else(|| "default".to_string());
+            let poison = PoisonMessage {
+                queue: queue.clone(),
+                message: None,
+                payload: body.to_vec(),
+                deliveries: 1,
+                error: e.to_string(),
+            };
+            return match worker.executor(&queue).handle_poison(poison).await {
+                Ok(()) => status(worker.push.ack_status_code()).into_response(),
+                Err(e) => {
+                    tracing::error!(worker_id = %worker.config.name, error = %e, "Failed to record undecodable delivery");
+                    status(worker.push.nack_status_code()).into_response()
+                }
+            };
+        }
+    };
+
+    let queue = message.headers.get("queue").cloned().unwrap_or_else(|| "default".to_string());
+    match worker.executor(&queue).process(message).await {
+        Ok(Settlement::Ack) => status(worker.push.ack_status_code()).into_response(),
+        Ok(Settlement::Redeliver(delay)) => redeliver(status(worker.push.nack_status_code()), delay),
+        Err(TaskError::ShuttingDown) => redeliver(StatusCode::SERVICE_UNAVAILABLE, Duration::from_secs(1)),
+        Err(e) => {
+            tracing::error!(worker_id = %worker.config.name, error = %e, "Push delivery failed");
+            status(worker.push.nack_status_code()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::{HttpPushBroker, PushAuth, PushBrokerConfig, PushFormat};
+    use crate::deadletter::{DeadLetterStore, InMemoryDeadLetterStore};
+    use crate::{InMemoryBackend, RetryPolicy, Task, TaskContext, TaskMessage, TaskOutcome, TaskState};
+    use async_trait::async_trait;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    /// Succeeds with its argument, fails retryably on "flaky" and for good on "broken"
+    struct EchoTask;
+
+    #[async_trait]
+    impl Task for EchoTask {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        fn retry_policy(&self) -> RetryPolicy {
+            RetryPolicy::fixed(2, Duration::ZERO)
+        }
+
+        async fn execute(&self, _ctx: TaskContext, args: serde_json::Value) -> TaskOutcome {
+            match args.as_str() {
+                Some("flaky") | Some("broken") => TaskOutcome::Failure {
+                    error: "it broke".to_string(),
+                    traceback: None,
+                    retryable: args == "flaky",
+                },
+                _ => TaskOutcome::Success(args),
+            }
+        }
+    }
+
+    fn push_worker(backend: &InMemoryBackend, dead_letters: &InMemoryDeadLetterStore) -> Router {
+        let registry = Arc::new(TaskRegistry::new());
+        registry.register(EchoTask);
+        let push = HttpPushBroker::new(PushBrokerConfig {
+            format: PushFormat::Json,
+            auth: PushAuth::hmac_sha256("key"),
+            ..Default::default()
+        });
+        PushWorker::new(WorkerConfig::default(), push, backend.clone(), registry)
+            .with_dead_letter_store(dead_letters.clone())
+            .router()
+    }
+
+    async fn deliver(router: &Router, body: Vec<u8>, retry_count: u32, sign: bool) -> Response {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/tasks/push")
+            .header("x-ouroboros-retry-count", retry_count.to_string());
+        if sign {
+            request = request.header("x-ouroboros-signature", HttpPushBroker::sign(b"key", &body));
+        }
+        router.clone().oneshot(request.body(Body::from(body)).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_push_worker() {
+        let backend = InMemoryBackend::new();
+        let dead_letters = InMemoryDeadLetterStore::new();
+        let router = push_worker(&backend, &dead_letters);
+
+        // Success is acknowledged and stored
+        let msg = TaskMessage::new("echo", serde_json::json!("hi"));
+        let response = deliver(&router, serde_json::to_vec(&msg).unwrap(), 0, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let result = backend.get_result(&msg.id).await.unwrap().unwrap();
+        assert_eq!(result.result, Some(serde_json::json!("hi")));
+
+        // A retryable failure asks for redelivery until the budget is spent
+        let msg = TaskMessage::new("echo", serde_json::json!("flaky"));
+        let body = serde_json::to_vec(&msg).unwrap();
+        let response = deliver(&router, body.clone(), 0, true).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().contains_key("retry-after"));
+        assert_eq!(backend.get_state(&msg.id).await.unwrap(), Some(TaskState::Retry));
+
+        let response = deliver(&router, body, 2, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let result = backend.get_result(&msg.id).await.unwrap().unwrap();
+        assert_eq!(result.state, TaskState::Failure);
+        assert_eq!(result.retries, 2);
+        assert_eq!(dead_letters.count("default").await.unwrap(), 1);
+
+        // A final failure is acknowledged
+        let msg = TaskMessage::new("echo", serde_json::json!("broken"));
+        let response = deliver(&router, serde_json::to_vec(&msg).unwrap(), 0, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(backend.get_state(&msg.id).await.unwrap(), Some(TaskState::Failure));
+    }
+
+    #[tokio::test]
+    async fn test_push_worker_rejects_and_records() {
+        let backend = InMemoryBackend::new();
+        let dead_letters = InMemoryDeadLetterStore::new();
+        let router = push_worker(&backend, &dead_letters);
+
+        let msg = TaskMessage::new("echo", serde_json::json!("hi"));
+        let response = deliver(&router, serde_json::to_vec(&msg).unwrap(), 0, false).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(backend.get_result(&msg.id).await.unwrap().is_none());
+
+        // Undecodable deliveries are dead-lettered, not redelivered forever
+        let response = deliver(&router, b"not json".to_vec(), 0, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(dead_letters.count("default").await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,405 @@
+//! Sharing a worker's concurrency between its queues
+//!
+//! Every task needs an execution slot before it runs. When slots are free a
+//! task takes one at once; when they are not, it waits, and each released
+//! slot goes to a waiting task chosen by the worker's `QueuePolicy`. Within a
+//! queue, waiting tasks are served by message priority, then arrival.
+//!
+//! The number of slots can change while the worker runs. When it shrinks,
+//! tasks already running keep their slots and fewer are handed out as they
+//! finish. Closing the scheduler turns away waiting and new tasks.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// How a worker shares its slots between queues that compete for them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Waiting queues take turns
+    #[default]
+    RoundRobin,
+    /// Waiting queues get slots in proportion to their weight (queues not
+    /// listed weigh 1, a weight of 0 only gets slots nobody else wants)
+    Weighted(HashMap<String, u32>),
+    /// Queues listed earlier in `WorkerConfig::queues` always go first
+    Strict,
+}
+
+impl QueuePolicy {
+    /// Weighted policy from `(queue, weight)` pairs
+    pub fn weighted<I, S>(weights: I) -> Self
+    where
+        I: IntoIterator<Item = (S, u32)>,
+        S: Into<String>,
+    {
+        Self::Weighted(weights.into_iter().map(|(queue, weight)| (queue.into(), weight)).collect())
+    }
+
+    fn weight(&self, queue: &str) -> i64 {
+        match self {
+            Self::Weighted(weights) => weights.get(queue).copied().unwrap_or(1) as i64,
+            Self::RoundRobin | Self::Strict => 1,
+        }
+    }
+}
+
+/// A task waiting for a slot
+struct Waiter {
+    priority: u8,
+    seq: u64,
+    tx: oneshot::Sender<SlotPermit>,
+}
+
+impl Waiter {
+    fn key(&self) -> (u8, Reverse<u64>) {
+        (self.priority, Reverse(self.seq))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+struct QueueSlots {
+    name: String,
+    weight: i64,
+    /// Smooth weighted round-robin credit
+    credit: i64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+struct State {
+    /// Slots the worker may use
+    capacity: usize,
+    /// Slots held by running tasks
+    held: usize,
+    closed: bool,
+    /// In `WorkerConfig::queues` order
+    queues: Vec<QueueSlots>,
+    seq: u64,
+}
+
+impl State {
+    fn queue_index(&mut self, queue: &str, policy: &QueuePolicy) -> usize {
+        if let Some(index) = self.queues.iter().position(|q| q.name == queue) {
+            return index;
+        }
+        self.queues.push(QueueSlots {
+            name: queue.to_string(),
+            weight: policy.weight(queue),
+            credit: 0,
+            waiters: BinaryHeap::new(),
+        });
+        self.queues.len() - 1
+    }
+
+    /// Choose the queue whose waiter gets the next slot
+    fn next_queue(&mut self, policy: &QueuePolicy) -> Option<usize> {
+        if let QueuePolicy::Strict = policy {
+            return self.queues.iter().position(|q| !q.waiters.is_empty());
+        }
+
+        let waiting = || self.queues.iter().enumerate().filter(|(_, q)| !q.waiters.is_empty());
+        let total: i64 = waiting().map(|(_, q)| q.weight).sum();
+        if total == 0 {
+            // Only zero-weight queues are waiting
+            return waiting().map(|(index, _)| index).next();
+        }
+
+        let waiting: Vec<usize> = waiting().map(|(index, _)| index).collect();
+        for &index in &waiting {
+            let queue = &mut self.queues[index];
+            queue.credit += queue.weight;
+        }
+        // Highest credit wins, earlier queues break ties
+        let chosen = waiting
+            .into_iter()
+            .rev()
+            .max_by_key(|&index| self.queues[index].credit)?;
+        self.queues[chosen].credit -= total;
+        Some(chosen)
+    }
+}
+
+/// Execution slots of a worker
+pub(crate) struct SlotScheduler {
+    policy: QueuePolicy,
+    state: Mutex<State>,
+}
+
+impl SlotScheduler {
+    pub(crate) fn new(slots: usize, queues: &[String], policy: QueuePolicy) -> Arc<Self> {
+        let mut state = State {
+            capacity: slots,
+            held: 0,
+            closed: false,
+            queues: Vec::new(),
+            seq: 0,
+        };
+        for queue in queues {
+            state.queue_index(queue, &policy);
+        }
+        Arc::new(Self {
+            policy,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait for a slot for a task from `queue`
+    /// Returns `None` once the scheduler is closed
+    pub(crate) async fn acquire(self: &Arc<Self>, queue: &str, priority: u8) -> Option<SlotPermit> {
+        let rx = {
+            let mut state = self.lock();
+            if state.closed {
+                return None;
+            }
+            let nobody_waiting = state.queues.iter().all(|q| q.waiters.is_empty());
+            if state.held < state.capacity && nobody_waiting {
+                state.held += 1;
+                return Some(SlotPermit {
+                    scheduler: Some(self.clone()),
+                });
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let index = state.queue_index(queue, &self.policy);
+            state.seq += 1;
+            let seq = state.seq;
+            state.queues[index].waiters.push(Waiter { priority, seq, tx });
+            rx
+        };
+
+        // The sender is only dropped without a permit when closing
+        rx.await.ok()
+    }
+
+    /// Hand free slots to waiters
+    fn hand_out(self: &Arc<Self>, state: &mut State) {
+        while state.held < state.capacity {
+            let Some(index) = state.next_queue(&self.policy) else {
+                return;
+            };
+            let Some(waiter) = state.queues[index].waiters.pop() else {
+                continue;
+            };
+            state.held += 1;
+            let permit = SlotPermit {
+                scheduler: Some(self.clone()),
+            };
+            // The waiter gave up; the slot is still ours to hand out
+            if let Err(mut permit) = waiter.tx.send(permit) {
+                permit.scheduler = None;
+                state.held -= 1;
+            }
+        }
+    }
+
+    /// Hand a released slot to the next waiter, or put it back
+    fn release(self: &Arc<Self>) {
+        let mut state = self.lock();
+        state.held -= 1;
+        self.hand_out(&mut state);
+    }
+
+    /// Change the number of slots
+    pub(crate) fn resize(self: &Arc<Self>, slots: usize) {
+        let mut state = self.lock();
+        state.capacity = slots;
+        self.hand_out(&mut state);
+    }
+
+    /// Number of slots
+    pub(crate) fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    /// Tasks waiting for a slot
+    pub(crate) fn waiting(&self) -> usize {
+        self.lock().queues.iter().map(|q| q.waiters.len()).sum()
+    }
+
+    /// Turn away waiting tasks and every later `acquire`
+    pub(crate) fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        for queue in &mut state.queues {
+            queue.waiters.clear();
+        }
+    }
+
+    #[cfg(test)]
+    fn available(&self) -> usize {
+        let state = self.lock();
+        state.capacity.saturating_sub(state.held)
+    }
+}
+
+/// An execution slot, released on drop
+pub(crate) struct SlotPermit {
+    scheduler: Option<Arc<SlotScheduler>>,
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Queue `tasks` behind one held slot and record the order they run in
+    async fn run_order(policy: QueuePolicy, queues: &[&str], tasks: &[(&str, u8)]) -> Vec<String> {
+        let queue_names: Vec<String> = queues.iter().map(|q| q.to_string()).collect();
+        let scheduler = SlotScheduler::new(1, &queue_names, policy);
+        let held = scheduler.acquire("hold", 0).await.unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for (i, (queue, priority)) in tasks.iter().enumerate() {
+            let scheduler = scheduler.clone();
+            let order = order.clone();
+            let label = format!("{}{}", queue, i);
+            let queue = queue.to_string();
+            let priority = *priority;
+            handles.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(&queue, priority).await.unwrap();
+                order.lock().unwrap().push(label);
+            }));
+            // Register waiters in a fixed order
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        drop(held);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(scheduler.available(), 1);
+        let order = order.lock().unwrap().clone();
+        order
+    }
+
+    #[tokio::test]
+    async fn test_free_slots_are_taken_at_once() {
+        let scheduler = SlotScheduler::new(2, &["a".to_string()], QueuePolicy::default());
+        let first = scheduler.acquire("a", 0).await.unwrap();
+        let _second = scheduler.acquire("b", 0).await.unwrap();
+        assert_eq!(scheduler.available(), 0);
+        drop(first);
+        assert_eq!(scheduler.available(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let tasks = [("bulk", 0), ("bulk", 0), ("bulk", 0), ("user", 0), ("user", 0)];
+        let order = run_order(QueuePolicy::RoundRobin, &["bulk", "user"], &tasks).await;
+        assert_eq!(order, vec!["bulk0", "user3", "bulk1", "user4", "bulk2"]);
+    }
+
+    #[tokio::test]
+    async fn test_weighted() {
+        let tasks = [("bulk", 0), ("bulk", 0), ("user", 0), ("user", 0), ("user", 0), ("user", 0)];
+        let policy = QueuePolicy::weighted([("user", 3)]);
+        let order = run_order(policy, &["bulk", "user"], &tasks).await;
+        // Three user slots for every bulk one, interleaved
+        assert_eq!(order, vec!["user2", "bulk0", "user3", "user4", "user5", "bulk1"]);
+    }
+
+    #[tokio::test]
+    async fn test_strict() {
+        let tasks = [("bulk", 0), ("bulk", 0), ("user", 0), ("user", 0)];
+        let order = run_order(QueuePolicy::Strict, &["user", "bulk"], &tasks).await;
+        assert_eq!(order, vec!["user2", "user3", "bulk0", "bulk1"]);
+    }
+
+    #[tokio::test]
+    async fn test_message_priority_within_queue() {
+        let tasks = [("user", 0), ("user", 9), ("user", 5), ("user", 9)];
+        let order = run_order(QueuePolicy::RoundRobin, &["user"], &tasks).await;
+        assert_eq!(order, vec!["user1", "user3", "user2", "user0"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter() {
+        let scheduler = SlotScheduler::new(1, &["a".to_string()], QueuePolicy::default());
+        let held = scheduler.acquire("a", 0).await.unwrap();
+
+        let waiting = scheduler.clone();
+        let abandoned = tokio::spawn(async move {
+            let _permit = waiting.acquire("a", 0).await;
+        });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        abandoned.abort();
+        let _ = abandoned.await;
+
+        // The slot is not lost to the waiter that went away
+        drop(held);
+        assert_eq!(scheduler.available(), 1);
+        let _permit = scheduler.acquire("a", 0).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resize() {
+        let scheduler = SlotScheduler::new(1, &["a".to_string()], QueuePolicy::default());
+        let first = scheduler.acquire("a", 0).await.unwrap();
+
+        let waiting = scheduler.clone();
+        let second = tokio::spawn(async move { waiting.acquire("a", 0).await.unwrap() });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(scheduler.waiting(), 1);
+
+        // Growing hands the new slot to the waiter
+        scheduler.resize(2);
+        let second = second.await.unwrap();
+        assert_eq!(scheduler.available(), 0);
+
+        // Shrinking takes slots back only as they are released
+        scheduler.resize(1);
+        drop(first);
+        assert_eq!(scheduler.available(), 0);
+        drop(second);
+        assert_eq!(scheduler.available(), 1);
+        assert_eq!(scheduler.capacity(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let scheduler = SlotScheduler::new(1, &["a".to_string()], QueuePolicy::default());
+        let held = scheduler.acquire("a", 0).await.unwrap();
+
+        let waiting = scheduler.clone();
+        let turned_away = tokio::spawn(async move { waiting.acquire("a", 0).await.is_none() });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        scheduler.close();
+        assert!(turned_away.await.unwrap());
+        drop(held);
+        assert!(scheduler.acquire("a", 0).await.is_none());
+    }
+}
@@ -69,6 +69,11 @@ impl Chain {
         if let Some(expires) = first_task.options.expires.or(self.options.expires) {
             message.expires = Some(expires);
         }
+        if let Some(priority) = first_task.options.priority.or(self.options.priority) {
+            message = message.with_priority(priority);
+        }
+        // Keys name a single job, so they are never inherited from the workflow
+        message.idempotency_key = first_task.options.idempotency_key.clone();
 
         // Determine target queue
         let queue = first_task
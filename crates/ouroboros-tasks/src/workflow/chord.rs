@@ -65,14 +65,11 @@ impl Chord {
         );
 
         let meta_key = format!("chord:{}", self.id);
-        let meta_json = serde_json::to_vec(&chord_meta)
+        let meta_json = serde_json::to_value(&chord_meta)
             .map_err(|e| TaskError::Serialization(e.to_string()))?;
 
-        // Store metadata in backend
-        // TODO: Add set_metadata to ResultBackend trait
-        // For now, we'll store it as a special result
-        let meta_task_id = TaskId::new();
-        let _ = (backend, meta_key, meta_json, meta_task_id);
+        // Store metadata in backend; a chord applied twice keeps its first
+        backend.set_metadata(&meta_key, meta_json, None, true).await?;
 
         Ok(AsyncChordResult {
             chord_id: self.id.clone(),
@@ -102,6 +99,11 @@ impl Chord {
         message.parent_id = Some(self.id.clone());
 
         // Apply options
+        if let Some(priority) = self.callback.options.priority.or(self.options.priority) {
+            message = message.with_priority(priority);
+        }
+        // Keys name a single job, so they are never inherited from the workflow
+        message.idempotency_key = self.callback.options.idempotency_key.clone();
         let queue = self
             .callback
             .options
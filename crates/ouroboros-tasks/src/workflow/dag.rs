@@ -0,0 +1,1079 @@
+//! Dag - durable workflows over an arbitrary dependency graph
+//!
+//! A node runs once all of its dependencies have finished, receiving their
+//! results ahead of its own arguments. Nodes may carry a `Condition` on those
+//! results and are skipped when it does not hold. A failed node fails
+//! everything downstream of it while independent branches carry on.
+//!
+//! The state of a run lives in the result backend as metadata under
+//! `workflow:<id>`. Workers advance it as steps finish, every update being a
+//! compare-and-set, so no single process coordinates the run and any client
+//! can inspect, cancel, retry or resume it with an `AsyncDagResult`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use crate::message::WorkflowStep;
+use crate::{Broker, ResultBackend, TaskError, TaskId, TaskMessage, TaskState};
+use super::{TaskOptions, TaskSignature};
+
+/// Backend metadata key of a workflow run
+fn metadata_key(dag_id: &TaskId) -> String {
+    format!("workflow:{}", dag_id)
+}
+
+/// A predicate on the results of a node's dependencies
+///
+/// Pointers are JSON pointers into a dependency's result ("" for the whole
+/// result); skipped dependencies have a null result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Condition {
+    /// The value at `pointer` equals `value`
+    Equals {
+        node: String,
+        pointer: String,
+        value: serde_json::Value,
+    },
+    /// The value at `pointer` is present and not null, false, zero or empty
+    Truthy { node: String, pointer: String },
+    /// The inner condition does not hold
+    Not { condition: Box<Condition> },
+    /// Every inner condition holds
+    All { conditions: Vec<Condition> },
+    /// At least one inner condition holds
+    Any { conditions: Vec<Condition> },
+}
+
+impl Condition {
+    /// The value at `pointer` in `node`'s result equals `value`
+    pub fn equals(node: impl Into<String>, pointer: impl Into<String>, value: serde_json::Value) -> Self {
+        Self::Equals {
+            node: node.into(),
+            pointer: pointer.into(),
+            value,
+        }
+    }
+
+    /// The value at `pointer` in `node`'s result is truthy
+    pub fn truthy(node: impl Into<String>, pointer: impl Into<String>) -> Self {
+        Self::Truthy {
+            node: node.into(),
+            pointer: pointer.into(),
+        }
+    }
+
+    /// Negate a condition
+    pub fn negate(condition: Condition) -> Self {
+        Self::Not {
+            condition: Box::new(condition),
+        }
+    }
+
+    /// Nodes whose results the condition reads
+    fn nodes(&self) -> Vec<&str> {
+        match self {
+            Self::Equals { node, .. } | Self::Truthy { node, .. } => vec![node.as_str()],
+            Self::Not { condition } => condition.nodes(),
+            Self::All { conditions } | Self::Any { conditions } => {
+                conditions.iter().flat_map(|c| c.nodes()).collect()
+            }
+        }
+    }
+
+    fn evaluate(&self, nodes: &BTreeMap<String, NodeRun>) -> bool {
+        let lookup = |node: &str, pointer: &str| {
+            nodes
+                .get(node)
+                .and_then(|run| run.result.as_ref())
+                .and_then(|result| result.pointer(pointer))
+                .cloned()
+        };
+        match self {
+            Self::Equals { node, pointer, value } => {
+                lookup(node, pointer).unwrap_or(serde_json::Value::Null) == *value
+            }
+            Self::Truthy { node, pointer } => lookup(node, pointer).map(|v| is_truthy(&v)).unwrap_or(false),
+            Self::Not { condition } => !condition.evaluate(nodes),
+            Self::All { conditions } => conditions.iter().all(|c| c.evaluate(nodes)),
+            Self::Any { conditions } => conditions.iter().any(|c| c.evaluate(nodes)),
+        }
+    }
+}
+
+fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().map(|n| n != 0.0).unwrap_or(true),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a) => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+    }
+}
+
+/// A named step of a DAG
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DagNode {
+    /// Name, unique within the DAG
+    pub name: String,
+    /// Task to run
+    pub signature: TaskSignature,
+    /// Nodes that must finish first; their results are prepended to the
+    /// task's arguments in this order unless the signature is immutable
+    pub depends_on: Vec<String>,
+    /// Run only if this holds, otherwise skip the node
+    pub condition: Option<Condition>,
+}
+
+impl DagNode {
+    /// Create a node without dependencies
+    pub fn new(name: impl Into<String>, signature: TaskSignature) -> Self {
+        Self {
+            name: name.into(),
+            signature,
+            depends_on: Vec::new(),
+            condition: None,
+        }
+    }
+
+    /// Run after the given nodes
+    pub fn after<I, S>(mut self, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.depends_on.extend(nodes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Run only if `condition` holds
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+}
+
+/// A workflow of tasks with dependencies between them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dag {
+    /// Unique workflow ID
+    pub id: TaskId,
+    /// Nodes in definition order
+    pub nodes: Vec<DagNode>,
+    /// Workflow-level options
+    pub options: TaskOptions,
+}
+
+impl Default for Dag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dag {
+    /// Create an empty DAG
+    pub fn new() -> Self {
+        Self {
+            id: TaskId::new(),
+            nodes: Vec::new(),
+            options: TaskOptions::default(),
+        }
+    }
+
+    /// Add a node
+    pub fn with_node(mut self, node: DagNode) -> Self {
+        self.nodes.push(node);
+        self
+    }
+
+    /// Create a DAG with options
+    pub fn with_options(mut self, options: TaskOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn node(&self, name: &str) -> Option<&DagNode> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// Check names are unique, dependencies exist, conditions only read
+    /// dependencies and there are no cycles
+    pub fn validate(&self) -> Result<(), TaskError> {
+        let invalid = |msg: String| Err(TaskError::InvalidWorkflow(msg));
+        if self.nodes.is_empty() {
+            return invalid("DAG must have at least one node".to_string());
+        }
+
+        let mut names = HashSet::new();
+        for node in &self.nodes {
+            if !names.insert(node.name.as_str()) {
+                return invalid(format!("Duplicate DAG node '{}'", node.name));
+            }
+        }
+        for node in &self.nodes {
+            for dep in &node.depends_on {
+                if !names.contains(dep.as_str()) {
+                    return invalid(format!("Node '{}' depends on unknown node '{}'", node.name, dep));
+                }
+            }
+            if let Some(condition) = &node.condition {
+                for read in condition.nodes() {
+                    if !node.depends_on.iter().any(|dep| dep == read) {
+                        return invalid(format!(
+                            "Condition of node '{}' reads '{}', which is not a dependency",
+                            node.name, read
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Kahn's algorithm: whatever cannot be ordered is on a cycle
+        let mut remaining: HashMap<&str, usize> = self
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.depends_on.len()))
+            .collect();
+        let mut ready: Vec<&str> = remaining.iter().filter(|(_, n)| **n == 0).map(|(name, _)| *name).collect();
+        let mut ordered = 0;
+        while let Some(name) = ready.pop() {
+            ordered += 1;
+            for node in self.nodes.iter().filter(|n| n.depends_on.iter().any(|d| d == name)) {
+                let count = remaining.get_mut(node.name.as_str()).expect("node is known");
+                *count -= node.depends_on.iter().filter(|d| *d == name).count();
+                if *count == 0 {
+                    ready.push(node.name.as_str());
+                }
+            }
+        }
+        if ordered < self.nodes.len() {
+            return invalid("DAG contains a cycle".to_string());
+        }
+        Ok(())
+    }
+
+    /// Store the run and publish the nodes without dependencies
+    pub async fn apply_async<B: Broker, R: ResultBackend>(
+        &self,
+        broker: &B,
+        backend: &R,
+    ) -> Result<AsyncDagResult, TaskError> {
+        self.validate()?;
+
+        let mut run = DagRun::new(self.clone());
+        let dispatch = run.plan();
+        let created = backend
+            .set_metadata(&metadata_key(&self.id), encode(&run)?, None, run.status.is_terminal())
+            .await?;
+        if !created {
+            return Err(TaskError::InvalidWorkflow(format!("Workflow {} was already started", self.id)));
+        }
+
+        let result = AsyncDagResult { dag_id: self.id.clone() };
+        result.publish(broker, backend, dispatch).await?;
+        Ok(result)
+    }
+}
+
+/// Status of a whole workflow run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WorkflowStatus {
+    /// Nodes are still pending or running
+    Running,
+    /// Every node succeeded or was skipped
+    Succeeded,
+    /// Every node finished and at least one failed
+    Failed,
+    /// Cancelled before finishing
+    Cancelled,
+}
+
+impl WorkflowStatus {
+    /// Check if the run has stopped
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Running)
+    }
+}
+
+/// State of a node within a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NodeState {
+    /// Waiting for dependencies
+    Pending,
+    /// Task published
+    Running,
+    /// Task succeeded
+    Succeeded,
+    /// Task failed
+    Failed,
+    /// Condition did not hold, or every dependency was skipped
+    Skipped,
+    /// A dependency failed
+    UpstreamFailed,
+    /// The run was cancelled first
+    Cancelled,
+}
+
+impl NodeState {
+    /// Check if the node has finished
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Pending | Self::Running)
+    }
+
+    fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed | Self::UpstreamFailed | Self::Cancelled)
+    }
+}
+
+/// Progress of one node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeRun {
+    /// Current state
+    pub state: NodeState,
+    /// Task of the current attempt
+    pub task_id: Option<TaskId>,
+    /// Whether that task has reached the broker
+    #[serde(default)]
+    pub published: bool,
+    /// Result, once succeeded
+    pub result: Option<serde_json::Value>,
+    /// Error, once failed
+    pub error: Option<String>,
+}
+
+impl NodeRun {
+    fn pending() -> Self {
+        Self {
+            state: NodeState::Pending,
+            task_id: None,
+            published: false,
+            result: None,
+            error: None,
+        }
+    }
+}
+
+/// A node's task, ready to publish
+#[derive(Debug)]
+struct Dispatch {
+    node: String,
+    queue: String,
+    message: TaskMessage,
+}
+
+/// How a workflow step ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    /// The task succeeded with this result
+    Succeeded(serde_json::Value),
+    /// The task failed for good
+    Failed(String),
+}
+
+/// Persisted state of a workflow run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DagRun {
+    /// The workflow definition
+    pub dag: Dag,
+    /// Overall status
+    pub status: WorkflowStatus,
+    /// Progress by node name
+    pub nodes: BTreeMap<String, NodeRun>,
+}
+
+impl DagRun {
+    fn new(dag: Dag) -> Self {
+        let nodes = dag.nodes.iter().map(|node| (node.name.clone(), NodeRun::pending())).collect();
+        Self {
+            dag,
+            status: WorkflowStatus::Running,
+            nodes,
+        }
+    }
+
+    /// Progress of a node
+    pub fn node(&self, name: &str) -> Option<&NodeRun> {
+        self.nodes.get(name)
+    }
+
+    /// Results of the succeeded nodes
+    pub fn results(&self) -> HashMap<String, serde_json::Value> {
+        self.nodes
+            .iter()
+            .filter_map(|(name, run)| Some((name.clone(), run.result.clone()?)))
+            .collect()
+    }
+
+    /// Resolve every pending node whose dependencies have finished, and the
+    /// run's status; returns the tasks of the nodes that now run
+    fn plan(&mut self) -> Vec<Dispatch> {
+        let mut dispatch = Vec::new();
+        if self.status != WorkflowStatus::Running {
+            return dispatch;
+        }
+
+        // Skips and failures cascade, so repeat until nothing changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in &self.dag.nodes {
+                if self.nodes[&node.name].state != NodeState::Pending {
+                    continue;
+                }
+                let deps: Vec<NodeState> = node.depends_on.iter().map(|dep| self.nodes[dep].state).collect();
+                if !deps.iter().all(NodeState::is_terminal) {
+                    continue;
+                }
+
+                let run = if deps.iter().any(NodeState::is_failed) {
+                    NodeRun {
+                        state: NodeState::UpstreamFailed,
+                        ..NodeRun::pending()
+                    }
+                } else if (!deps.is_empty() && deps.iter().all(|s| *s == NodeState::Skipped))
+                    || node.condition.as_ref().is_some_and(|c| !c.evaluate(&self.nodes))
+                {
+                    NodeRun {
+                        state: NodeState::Skipped,
+                        ..NodeRun::pending()
+                    }
+                } else {
+                    let task_id = TaskId::new();
+                    dispatch.push(self.dispatch(node, task_id.clone()));
+                    NodeRun {
+                        state: NodeState::Running,
+                        task_id: Some(task_id),
+                        ..NodeRun::pending()
+                    }
+                };
+                self.nodes.insert(node.name.clone(), run);
+                changed = true;
+            }
+        }
+
+        if self.nodes.values().all(|run| run.state.is_terminal()) {
+            self.status = if self.nodes.values().any(|run| run.state.is_failed()) {
+                WorkflowStatus::Failed
+            } else {
+                WorkflowStatus::Succeeded
+            };
+        }
+        dispatch
+    }
+
+    /// Build the task running `node` as `task_id`
+    fn dispatch(&self, node: &DagNode, task_id: TaskId) -> Dispatch {
+        let sig = &node.signature;
+        let mut args = sig.args.clone();
+        if !sig.immutable && !node.depends_on.is_empty() {
+            let mut inputs: Vec<serde_json::Value> = node
+                .depends_on
+                .iter()
+                .map(|dep| self.nodes[dep].result.clone().unwrap_or(serde_json::Value::Null))
+                .collect();
+            match args {
+                serde_json::Value::Array(rest) => inputs.extend(rest),
+                serde_json::Value::Null => {}
+                other => inputs.push(other),
+            }
+            args = serde_json::Value::Array(inputs);
+        }
+
+        let mut message = TaskMessage::new(sig.task_name.clone(), args).with_kwargs(sig.kwargs.clone());
+        message.id = task_id;
+        message.root_id = Some(self.dag.id.clone());
+        message.parent_id = Some(self.dag.id.clone());
+        message.workflow = Some(WorkflowStep {
+            workflow_id: self.dag.id.clone(),
+            node: node.name.clone(),
+        });
+
+        // Apply options
+        if let Some(eta) = sig.options.eta.or(self.dag.options.eta) {
+            message.eta = Some(eta);
+        }
+        if let Some(expires) = sig.options.expires.or(self.dag.options.expires) {
+            message.expires = Some(expires);
+        }
+        if let Some(priority) = sig.options.priority.or(self.dag.options.priority) {
+            message = message.with_priority(priority);
+        }
+        // Keys name a single job, so they are never inherited from the workflow
+        message.idempotency_key = sig.options.idempotency_key.clone();
+
+        let queue = sig
+            .options
+            .queue
+            .as_ref()
+            .or(self.dag.options.queue.as_ref())
+            .map(|s| s.as_str())
+            .unwrap_or("default");
+        Dispatch {
+            node: node.name.clone(),
+            queue: queue.to_string(),
+            message,
+        }
+    }
+
+    /// Record how a node's task ended; false if the task is no longer the
+    /// node's current attempt
+    fn finish(&mut self, node: &str, task_id: &TaskId, outcome: StepOutcome) -> bool {
+        let Some(run) = self.nodes.get_mut(node) else {
+            return false;
+        };
+        if run.state != NodeState::Running || run.task_id.as_ref() != Some(task_id) {
+            return false;
+        }
+        match outcome {
+            StepOutcome::Succeeded(value) => {
+                run.state = NodeState::Succeeded;
+                run.result = Some(value);
+            }
+            StepOutcome::Failed(error) => {
+                run.state = NodeState::Failed;
+                run.error = Some(error);
+            }
+        }
+        true
+    }
+}
+
+fn encode(run: &DagRun) -> Result<serde_json::Value, TaskError> {
+    serde_json::to_value(run).map_err(|e| TaskError::Serialization(format!("Failed to serialize workflow: {}", e)))
+}
+
+/// Load a run, apply `f` and store the result if `f` returns `Some`,
+/// starting over when another writer got there first
+async fn update<R, T, F>(backend: &R, dag_id: &TaskId, mut f: F) -> Result<Option<T>, TaskError>
+where
+    R: ResultBackend + ?Sized,
+    F: FnMut(&mut DagRun) -> Result<Option<T>, TaskError>,
+{
+    let key = metadata_key(dag_id);
+    loop {
+        let meta = backend
+            .get_metadata(&key)
+            .await?
+            .ok_or_else(|| TaskError::InvalidWorkflow(format!("Workflow {} not found", dag_id)))?;
+        let mut run: DagRun = serde_json::from_value(meta.value)
+            .map_err(|e| TaskError::Deserialization(format!("Failed to deserialize workflow: {}", e)))?;
+
+        let Some(output) = f(&mut run)? else {
+            return Ok(None);
+        };
+        // A run that is still going must not expire under its workers
+        let expire = run.status.is_terminal();
+        if backend.set_metadata(&key, encode(&run)?, Some(meta.version), expire).await? {
+            return Ok(Some(output));
+        }
+    }
+}
+
+/// Record the outcome of a workflow step and publish the nodes it unblocks
+///
+/// Called by workers once a step's task has finished for good. Outcomes for a
+/// task that is no longer the node's current attempt are ignored.
+pub async fn advance<B, R>(
+    broker: &B,
+    backend: &R,
+    step: &WorkflowStep,
+    task_id: &TaskId,
+    outcome: StepOutcome,
+) -> Result<(), TaskError>
+where
+    B: Broker + ?Sized,
+    R: ResultBackend + ?Sized,
+{
+    let dispatch = update(backend, &step.workflow_id, |run| {
+        if !run.finish(&step.node, task_id, outcome.clone()) {
+            return Ok(None);
+        }
+        Ok(Some(run.plan()))
+    })
+    .await?;
+
+    match dispatch {
+        Some(dispatch) => {
+            let handle = AsyncDagResult {
+                dag_id: step.workflow_id.clone(),
+            };
+            handle.publish(broker, backend, dispatch).await
+        }
+        None => {
+            tracing::debug!(
+                workflow_id = %step.workflow_id,
+                node = %step.node,
+                task_id = %task_id,
+                "Ignoring outcome of a stale workflow step"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Handle to track and control a workflow run
+#[derive(Debug, Clone)]
+pub struct AsyncDagResult {
+    /// Workflow ID
+    pub dag_id: TaskId,
+}
+
+impl AsyncDagResult {
+    /// Publish planned nodes, then mark them published
+    async fn publish<B, R>(
+        &self,
+        broker: &B,
+        backend: &R,
+        dispatch: Vec<Dispatch>,
+    ) -> Result<(), TaskError>
+    where
+        B: Broker + ?Sized,
+        R: ResultBackend + ?Sized,
+    {
+        if dispatch.is_empty() {
+            return Ok(());
+        }
+
+        let mut published = Vec::with_capacity(dispatch.len());
+        for Dispatch { node, queue, message } in dispatch {
+            let task_id = message.id.clone();
+            broker.publish(&queue, message).await?;
+            published.push((node, task_id));
+        }
+
+        update(backend, &self.dag_id, |run| {
+            let mut changed = false;
+            for (node, task_id) in &published {
+                if let Some(node) = run.nodes.get_mut(node) {
+                    if node.task_id.as_ref() == Some(task_id) && !node.published {
+                        node.published = true;
+                        changed = true;
+                    }
+                }
+            }
+            Ok(changed.then_some(()))
+        })
+        .await?;
+        Ok(())
+    }
+
+    fn not_found(&self) -> TaskError {
+        TaskError::InvalidWorkflow(format!("Workflow {} not found", self.dag_id))
+    }
+
+    /// Get the persisted state of the run
+    pub async fn state<R: ResultBackend + ?Sized>(&self, backend: &R) -> Result<Option<DagRun>, TaskError> {
+        let Some(meta) = backend.get_metadata(&metadata_key(&self.dag_id)).await? else {
+            return Ok(None);
+        };
+        serde_json::from_value(meta.value)
+            .map(Some)
+            .map_err(|e| TaskError::Deserialization(format!("Failed to deserialize workflow: {}", e)))
+    }
+
+    /// Get the status of the run
+    pub async fn status<R: ResultBackend + ?Sized>(&self, backend: &R) -> Result<Option<WorkflowStatus>, TaskError> {
+        Ok(self.state(backend).await?.map(|run| run.status))
+    }
+
+    /// Wait for the run to finish and return the results by node
+    pub async fn get<R: ResultBackend + ?Sized>(
+        &self,
+        backend: &R,
+        timeout: Option<Duration>,
+    ) -> Result<HashMap<String, serde_json::Value>, TaskError> {
+        let poll_interval = Duration::from_millis(100);
+        let start = std::time::Instant::now();
+        let timeout_duration = timeout.unwrap_or(Duration::from_secs(3600)); // 1 hour default
+
+        loop {
+            let run = self.state(backend).await?.ok_or_else(|| self.not_found())?;
+            match run.status {
+                WorkflowStatus::Succeeded => return Ok(run.results()),
+                WorkflowStatus::Failed => {
+                    let (node, error) = run
+                        .nodes
+                        .iter()
+                        .find(|(_, node)| node.state == NodeState::Failed)
+                        .map(|(name, node)| (name.as_str(), node.error.as_deref().unwrap_or("Task failed")))
+                        .unwrap_or(("?", "Task failed"));
+                    return Err(TaskError::Internal(format!(
+                        "Workflow {} failed at node '{}': {}",
+                        self.dag_id, node, error
+                    )));
+                }
+                WorkflowStatus::Cancelled => {
+                    return Err(TaskError::Revoked(format!("Workflow {} was cancelled", self.dag_id)));
+                }
+                WorkflowStatus::Running => {}
+            }
+
+            if start.elapsed() >= timeout_duration {
+                return Err(TaskError::Timeout(format!(
+                    "Workflow {} did not complete within {:?}",
+                    self.dag_id, timeout_duration
+                )));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Cancel the run, returning the tasks still running
+    ///
+    /// Running tasks are left alone; revoke them to stop them. Their outcomes
+    /// are ignored either way.
+    pub async fn cancel<R: ResultBackend + ?Sized>(&self, backend: &R) -> Result<Vec<TaskId>, TaskError> {
+        let running = update(backend, &self.dag_id, |run| {
+            if run.status.is_terminal() {
+                return Ok(None);
+            }
+            run.status = WorkflowStatus::Cancelled;
+            let mut running = Vec::new();
+            for node in run.nodes.values_mut() {
+                if node.state == NodeState::Running {
+                    running.extend(node.task_id.clone());
+                }
+                if !node.state.is_terminal() {
+                    node.state = NodeState::Cancelled;
+                }
+            }
+            Ok(Some(running))
+        })
+        .await?;
+        Ok(running.unwrap_or_default())
+    }
+
+    /// Run a failed or cancelled workflow again from the nodes that did not
+    /// finish, keeping the results of those that did
+    pub async fn retry<B, R>(&self, broker: &B, backend: &R) -> Result<(), TaskError>
+    where
+        B: Broker + ?Sized,
+        R: ResultBackend + ?Sized,
+    {
+        let dispatch = update(backend, &self.dag_id, |run| {
+            if !matches!(run.status, WorkflowStatus::Failed | WorkflowStatus::Cancelled) {
+                return Err(TaskError::InvalidWorkflow(format!(
+                    "Workflow {} is {:?}, only failed or cancelled workflows can be retried",
+                    self.dag_id, run.status
+                )));
+            }
+            for node in run.nodes.values_mut() {
+                if node.state.is_failed() {
+                    *node = NodeRun::pending();
+                }
+            }
+            run.status = WorkflowStatus::Running;
+            Ok(Some(run.plan()))
+        })
+        .await?;
+        self.publish(broker, backend, dispatch.unwrap_or_default()).await
+    }
+
+    /// Pick the run up after the process driving it died
+    ///
+    /// Publishes nodes that were planned but never reached the broker, and
+    /// applies outcomes that were stored without advancing the run. Tasks
+    /// still queued or running are left to their workers.
+    pub async fn resume<B, R>(&self, broker: &B, backend: &R) -> Result<(), TaskError>
+    where
+        B: Broker + ?Sized,
+        R: ResultBackend + ?Sized,
+    {
+        let run = self.state(backend).await?.ok_or_else(|| self.not_found())?;
+        if run.status.is_terminal() {
+            return Ok(());
+        }
+
+        let mut unpublished = Vec::new();
+        for (name, node) in &run.nodes {
+            let (NodeState::Running, Some(task_id)) = (node.state, &node.task_id) else {
+                continue;
+            };
+            if !node.published {
+                let definition = run.dag.node(name).expect("node is known");
+                unpublished.push(run.dispatch(definition, task_id.clone()));
+                continue;
+            }
+
+            let Some(result) = backend.get_result(task_id).await? else {
+                continue;
+            };
+            let outcome = match result.state {
+                TaskState::Success => StepOutcome::Succeeded(result.result.unwrap_or(serde_json::Value::Null)),
+                state if state.is_terminal() => StepOutcome::Failed(
+                    result.error.unwrap_or_else(|| format!("Task ended in state {:?}", state)),
+                ),
+                _ => continue,
+            };
+            let step = WorkflowStep {
+                workflow_id: self.dag_id.clone(),
+                node: name.clone(),
+            };
+            advance(broker, backend, &step, task_id, outcome).await?;
+        }
+
+        tracing::info!(
+            workflow_id = %self.dag_id,
+            unpublished = unpublished.len(),
+            "Resuming workflow"
+        );
+        self.publish(broker, backend, unpublished).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryBackend, InMemoryBroker, PullBroker, TaskResult};
+
+    fn sig(name: &str) -> TaskSignature {
+        TaskSignature::new(name, serde_json::json!([]))
+    }
+
+    /// extract -> (transform_a, transform_b) -> load
+    fn diamond() -> Dag {
+        Dag::new()
+            .with_node(DagNode::new("extract", sig("extract")))
+            .with_node(DagNode::new("transform_a", sig("transform")).after(["extract"]))
+            .with_node(DagNode::new("transform_b", sig("transform")).after(["extract"]))
+            .with_node(DagNode::new("load", sig("load")).after(["transform_a", "transform_b"]))
+    }
+
+    /// Take every queued message
+    async fn drain(broker: &InMemoryBroker) -> Vec<TaskMessage> {
+        let mut messages = Vec::new();
+        while let Some(message) = broker.fetch("default").await.unwrap() {
+            broker.ack(&message.delivery_tag).await.unwrap();
+            messages.push(message.payload);
+        }
+        messages
+    }
+
+    async fn finish(broker: &InMemoryBroker, backend: &InMemoryBackend, msg: &TaskMessage, outcome: StepOutcome) {
+        advance(broker, backend, msg.workflow.as_ref().unwrap(), &msg.id, outcome)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(diamond().validate().is_ok());
+        assert!(Dag::new().validate().is_err());
+
+        let duplicate = Dag::new()
+            .with_node(DagNode::new("a", sig("a")))
+            .with_node(DagNode::new("a", sig("a")));
+        assert!(duplicate.validate().is_err());
+
+        let unknown = Dag::new().with_node(DagNode::new("a", sig("a")).after(["b"]));
+        assert!(unknown.validate().is_err());
+
+        let cycle = Dag::new()
+            .with_node(DagNode::new("root", sig("root")))
+            .with_node(DagNode::new("a", sig("a")).after(["root", "b"]))
+            .with_node(DagNode::new("b", sig("b")).after(["a"]));
+        assert!(matches!(cycle.validate(), Err(TaskError::InvalidWorkflow(_))));
+
+        let condition = Dag::new()
+            .with_node(DagNode::new("a", sig("a")))
+            .with_node(DagNode::new("b", sig("b")))
+            .with_node(DagNode::new("c", sig("c")).after(["a"]).when(Condition::truthy("b", "")));
+        assert!(condition.validate().is_err());
+    }
+
+    #[test]
+    fn test_condition() {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(
+            "check".to_string(),
+            NodeRun {
+                state: NodeState::Succeeded,
+                result: Some(serde_json::json!({"rows": 0, "format": "csv"})),
+                ..NodeRun::pending()
+            },
+        );
+
+        assert!(Condition::equals("check", "/format", serde_json::json!("csv")).evaluate(&nodes));
+        assert!(!Condition::truthy("check", "/rows").evaluate(&nodes));
+        assert!(!Condition::truthy("check", "/missing").evaluate(&nodes));
+        assert!(Condition::negate(Condition::truthy("check", "/rows")).evaluate(&nodes));
+        assert!(Condition::Any {
+            conditions: vec![Condition::truthy("check", "/rows"), Condition::truthy("check", "")],
+        }
+        .evaluate(&nodes));
+
+        let json = serde_json::to_value(Condition::truthy("check", "/rows")).unwrap();
+        assert_eq!(json["op"], "truthy");
+    }
+
+    #[tokio::test]
+    async fn test_diamond() {
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let handle = diamond().apply_async(&broker, &backend).await.unwrap();
+
+        let extract = drain(&broker).await;
+        assert_eq!(extract.len(), 1);
+        assert_eq!(extract[0].task_name, "extract");
+        assert_eq!(extract[0].root_id, Some(handle.dag_id.clone()));
+        finish(&broker, &backend, &extract[0], StepOutcome::Succeeded(serde_json::json!(10))).await;
+
+        // Fan-out: both transforms get the extract result
+        let transforms = drain(&broker).await;
+        assert_eq!(transforms.len(), 2);
+        assert!(transforms.iter().all(|m| m.args == serde_json::json!([10])));
+        finish(&broker, &backend, &transforms[0], StepOutcome::Succeeded(serde_json::json!("a"))).await;
+        assert!(drain(&broker).await.is_empty());
+
+        // A repeated outcome changes nothing
+        finish(&broker, &backend, &transforms[0], StepOutcome::Failed("late".into())).await;
+        finish(&broker, &backend, &transforms[1], StepOutcome::Succeeded(serde_json::json!("b"))).await;
+
+        // Fan-in: results arrive in dependency order
+        let load = drain(&broker).await;
+        assert_eq!(load.len(), 1);
+        assert_eq!(load[0].args, serde_json::json!(["a", "b"]));
+        let run = handle.state(&backend).await.unwrap().unwrap();
+        assert!(run.nodes.values().filter(|n| n.state == NodeState::Running).all(|n| n.published));
+
+        finish(&broker, &backend, &load[0], StepOutcome::Succeeded(serde_json::json!("done"))).await;
+        assert_eq!(handle.status(&backend).await.unwrap(), Some(WorkflowStatus::Succeeded));
+        let results = handle.get(&backend, Some(Duration::from_secs(1))).await.unwrap();
+        assert_eq!(results["load"], serde_json::json!("done"));
+        assert_eq!(results.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_branches() {
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let large = Condition::truthy("inspect", "/large");
+        let dag = Dag::new()
+            .with_node(DagNode::new("inspect", sig("inspect")))
+            .with_node(DagNode::new("bulk", sig("bulk")).after(["inspect"]).when(large.clone()))
+            .with_node(DagNode::new("bulk_report", sig("report")).after(["bulk"]))
+            .with_node(DagNode::new("simple", sig("simple")).after(["inspect"]).when(Condition::negate(large)))
+            .with_node(DagNode::new("publish", sig("publish")).after(["bulk", "simple"]));
+        let handle = dag.apply_async(&broker, &backend).await.unwrap();
+
+        let inspect = drain(&broker).await;
+        finish(&broker, &backend, &inspect[0], StepOutcome::Succeeded(serde_json::json!({"large": false}))).await;
+
+        let simple = drain(&broker).await;
+        assert_eq!(simple.len(), 1);
+        assert_eq!(simple[0].task_name, "simple");
+        let run = handle.state(&backend).await.unwrap().unwrap();
+        assert_eq!(run.node("bulk").unwrap().state, NodeState::Skipped);
+        // Everything below a skipped node alone is skipped too
+        assert_eq!(run.node("bulk_report").unwrap().state, NodeState::Skipped);
+
+        finish(&broker, &backend, &simple[0], StepOutcome::Succeeded(serde_json::json!(1))).await;
+        let publish = drain(&broker).await;
+        assert_eq!(publish[0].args, serde_json::json!([null, 1]));
+        finish(&broker, &backend, &publish[0], StepOutcome::Succeeded(serde_json::json!(2))).await;
+        assert_eq!(handle.status(&backend).await.unwrap(), Some(WorkflowStatus::Succeeded));
+    }
+
+    #[tokio::test]
+    async fn test_failure_and_retry() {
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let handle = diamond().apply_async(&broker, &backend).await.unwrap();
+
+        let extract = drain(&broker).await;
+        finish(&broker, &backend, &extract[0], StepOutcome::Succeeded(serde_json::json!(1))).await;
+        let transforms = drain(&broker).await;
+        finish(&broker, &backend, &transforms[0], StepOutcome::Failed("bad row".into())).await;
+        assert_eq!(handle.status(&backend).await.unwrap(), Some(WorkflowStatus::Running));
+        finish(&broker, &backend, &transforms[1], StepOutcome::Succeeded(serde_json::json!("b"))).await;
+
+        let run = handle.state(&backend).await.unwrap().unwrap();
+        assert_eq!(run.status, WorkflowStatus::Failed);
+        assert_eq!(run.node("load").unwrap().state, NodeState::UpstreamFailed);
+        let err = handle.get(&backend, Some(Duration::from_secs(1))).await.unwrap_err();
+        assert!(err.to_string().contains("bad row"));
+
+        // Only the failed node runs again
+        handle.retry(&broker, &backend).await.unwrap();
+        let retried = drain(&broker).await;
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].workflow, transforms[0].workflow);
+        assert_ne!(retried[0].id, transforms[0].id);
+        assert!(handle.retry(&broker, &backend).await.is_err());
+
+        finish(&broker, &backend, &retried[0], StepOutcome::Succeeded(serde_json::json!("a"))).await;
+        let load = drain(&broker).await;
+        finish(&broker, &backend, &load[0], StepOutcome::Succeeded(serde_json::json!(3))).await;
+        assert_eq!(handle.status(&backend).await.unwrap(), Some(WorkflowStatus::Succeeded));
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let handle = diamond().apply_async(&broker, &backend).await.unwrap();
+
+        let extract = drain(&broker).await;
+        assert_eq!(handle.cancel(&backend).await.unwrap(), vec![extract[0].id.clone()]);
+        assert!(handle.cancel(&backend).await.unwrap().is_empty());
+
+        // The outcome of the cancelled task is ignored
+        finish(&broker, &backend, &extract[0], StepOutcome::Succeeded(serde_json::json!(1))).await;
+        assert!(drain(&broker).await.is_empty());
+        let run = handle.state(&backend).await.unwrap().unwrap();
+        assert_eq!(run.status, WorkflowStatus::Cancelled);
+        assert!(run.nodes.values().all(|n| n.state == NodeState::Cancelled));
+        assert!(matches!(
+            handle.get(&backend, Some(Duration::from_secs(1))).await,
+            Err(TaskError::Revoked(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resume() {
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let handle = diamond().apply_async(&broker, &backend).await.unwrap();
+
+        // The extract result was stored, but nothing advanced the run
+        let extract = drain(&broker).await;
+        let result = TaskResult::success(extract[0].id.clone(), serde_json::json!(5));
+        backend.set_result(&extract[0].id, result, None).await.unwrap();
+        handle.resume(&broker, &backend).await.unwrap();
+        let transforms = drain(&broker).await;
+        assert_eq!(transforms.len(), 2);
+
+        // A planned node never reached the broker
+        update(&backend, &handle.dag_id, |run| {
+            run.nodes.get_mut("transform_a").unwrap().published = false;
+            Ok(Some(()))
+        })
+        .await
+        .unwrap();
+        handle.resume(&broker, &backend).await.unwrap();
+        let republished = drain(&broker).await;
+        assert_eq!(republished.len(), 1);
+        assert_eq!(republished[0].args, serde_json::json!([5]));
+        assert!(transforms.iter().any(|m| m.id == republished[0].id));
+
+        // Queued tasks are left alone
+        handle.resume(&broker, &backend).await.unwrap();
+        assert!(drain(&broker).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_apply_twice() {
+        let broker = InMemoryBroker::default();
+        let backend = InMemoryBackend::new();
+        let dag = diamond();
+        dag.apply_async(&broker, &backend).await.unwrap();
+        assert!(matches!(
+            dag.apply_async(&broker, &backend).await,
+            Err(TaskError::InvalidWorkflow(_))
+        ));
+    }
+}
@@ -63,6 +63,11 @@ impl Group {
             if let Some(expires) = task_sig.options.expires.or(self.options.expires) {
                 message.expires = Some(expires);
             }
+            if let Some(priority) = task_sig.options.priority.or(self.options.priority) {
+                message = message.with_priority(priority);
+            }
+            // Keys name a single job, so they are never inherited from the workflow
+            message.idempotency_key = task_sig.options.idempotency_key.clone();
 
             // Determine target queue
             let queue = task_sig
@@ -1,6 +1,7 @@
 //! Workflow primitives
 //!
-//! Chain, Group, and Chord implementations for composing task workflows.
+//! Chain, Group, and Chord implementations for composing task workflows,
+//! and durable DAG workflows for anything beyond them.
 
 pub mod signature;
 pub mod chain;
@@ -9,6 +10,7 @@ pub mod chord;
 pub mod map;
 pub mod starmap;
 pub mod chunks;
+pub mod dag;
 
 pub use signature::{TaskSignature, TaskOptions};
 pub use chain::{Chain, AsyncChainResult};
@@ -17,6 +19,10 @@ pub use chord::{Chord, AsyncChordResult};
 pub use map::{Map, xmap};
 pub use starmap::{Starmap, starmap};
 pub use chunks::{Chunks, chunks};
+pub use dag::{
+    AsyncDagResult, Condition, Dag, DagNode, DagRun, NodeRun, NodeState, StepOutcome,
+    WorkflowStatus,
+};
 
 use serde::{Deserialize, Serialize};
 use crate::TaskId;
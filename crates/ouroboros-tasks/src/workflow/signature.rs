@@ -18,6 +18,12 @@ pub struct TaskOptions {
     pub expires: Option<DateTime<Utc>>,
     /// Custom retry policy
     pub retry_policy: Option<RetryPolicy>,
+    /// Message priority (0 to `MAX_PRIORITY`, higher runs first)
+    #[serde(default)]
+    pub priority: Option<u8>,
+    /// Key identifying the logical job, so duplicates can be detected
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 impl TaskOptions {
@@ -55,6 +61,18 @@ impl TaskOptions {
         self.retry_policy = Some(policy);
         self
     }
+
+    /// Set message priority
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Set idempotency key
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
 }
 
 /// A task signature - represents a task call
@@ -5,7 +5,7 @@
 use ouroboros_kv_client::{ClientError, KvClient, KvPool, KvValue, PoolConfig};
 use pyo3::exceptions::{PyConnectionError, PyKeyError, PyRuntimeError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyBytes, PyDict, PyFloat, PyInt, PyList, PyString};
+use pyo3::types::{PyBytes, PyDict, PyFloat, PyInt, PyList, PySet, PyString};
 use pyo3_async_runtimes::tokio::future_into_py;
 use rust_decimal::Decimal;
 use std::collections::HashMap;
@@ -102,6 +102,15 @@ fn kv_value_to_py(py: Python<'_>, value: KvValue) -> PyResult<PyObject> {
             }
             Ok(py_dict.into_any().unbind())
         }
+        KvValue::Set(members) => Ok(PySet::new(py, &members)?.into_any().unbind()),
+        KvValue::SortedSet(zset) => {
+            // (member, score) pairs in score order
+            let py_list = PyList::empty(py);
+            for (member, score) in zset.iter() {
+                py_list.append((member, score))?;
+            }
+            Ok(py_list.into_any().unbind())
+        }
     }
 }
 
@@ -113,6 +122,7 @@ fn client_error_to_py(e: ClientError) -> PyErr {
         ClientError::Server(msg) => PyRuntimeError::new_err(format!("Server error: {}", msg)),
         ClientError::KeyNotFound => PyKeyError::new_err("Key not found"),
         ClientError::Timeout => PyRuntimeError::new_err("Connection pool timeout"),
+        ClientError::InvalidArgument(msg) => PyValueError::new_err(msg),
     }
 }
 
@@ -479,13 +489,11 @@ impl PyPoolConfig {
             return Err(PyValueError::new_err("acquire_timeout cannot be negative"));
         }
         Ok(Self {
-            inner: PoolConfig {
-                addr,
-                min_size,
-                max_size,
-                idle_timeout: Duration::from_secs_f64(idle_timeout),
-                acquire_timeout: Duration::from_secs_f64(acquire_timeout),
-            },
+            inner: PoolConfig::new(addr)
+                .min_size(min_size)
+                .max_size(max_size)
+                .idle_timeout(Duration::from_secs_f64(idle_timeout))
+                .acquire_timeout(Duration::from_secs_f64(acquire_timeout)),
         })
     }
 }
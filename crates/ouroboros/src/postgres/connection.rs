@@ -2,9 +2,10 @@
 
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
 use pyo3_async_runtimes::tokio::future_into_py;
 use std::sync::Arc;
-use ouroboros_postgres::{Connection, PoolConfig, RetryConfig};
+use ouroboros_postgres::{Connection, PoolConfig, ReplicaConfig, RetryConfig};
 
 use super::PG_POOL;
 
@@ -21,6 +22,12 @@ use super::PG_POOL;
 ///     initial_retry_delay_ms: Initial delay between retries in milliseconds (default: 100)
 ///     max_retry_delay_ms: Maximum delay between retries in milliseconds (default: 5000)
 ///     statement_cache_capacity: Number of prepared statements to cache per connection (default: 100, 0 to disable)
+///     read_replicas: Replica connection URIs; ORM reads go to replicas, writes and transactions to the primary
+///     replica_max_lag_ms: Maximum replica replay lag before it stops serving reads (default: 5000)
+///     sticky_window_ms: How long reads stay on the primary after a write (default: 0, disabled).
+///         The window is shared by the whole process, so one caller's write moves every read
+///         to the primary; leave it off unless reads must always follow writes
+///     replica_check_interval_ms: Interval between replica health and lag checks (default: 5000, 0 to disable)
 ///
 /// Returns:
 ///     Awaitable that resolves when connection is established
@@ -46,6 +53,12 @@ use super::PG_POOL;
 ///         max_retries=3,
 ///         statement_cache_capacity=200,  # More caching for high-query workloads
 ///     )
+///
+///     # With read replicas
+///     await init(
+///         "postgresql://primary/mydb",
+///         read_replicas=["postgresql://replica1/mydb", "postgresql://replica2/mydb"],
+///     )
 #[pyfunction]
 #[pyo3(signature = (
     connection_string,
@@ -57,7 +70,11 @@ use super::PG_POOL;
     max_retries=3,
     initial_retry_delay_ms=100,
     max_retry_delay_ms=5000,
-    statement_cache_capacity=100
+    statement_cache_capacity=100,
+    read_replicas=None,
+    replica_max_lag_ms=5000,
+    sticky_window_ms=0,
+    replica_check_interval_ms=5000
 ))]
 #[allow(clippy::too_many_arguments)]
 pub(super) fn init<'py>(
@@ -72,6 +89,10 @@ pub(super) fn init<'py>(
     initial_retry_delay_ms: u64,
     max_retry_delay_ms: u64,
     statement_cache_capacity: usize,
+    read_replicas: Option<Vec<String>>,
+    replica_max_lag_ms: u64,
+    sticky_window_ms: u64,
+    replica_check_interval_ms: u64,
 ) -> PyResult<Bound<'py, PyAny>> {
     future_into_py(py, async move {
         let retry_config = RetryConfig {
@@ -91,7 +112,16 @@ pub(super) fn init<'py>(
             statement_cache_capacity,
         };
 
-        let connection = Connection::new(&connection_string, config)
+        // Replica pools share the primary's pool settings
+        let replica_config = ReplicaConfig {
+            pool: config.clone(),
+            max_lag_ms: replica_max_lag_ms,
+            sticky_window_ms,
+            health_check_interval_ms: replica_check_interval_ms,
+        };
+        let replica_uris: Vec<&str> = read_replicas.iter().flatten().map(String::as_str).collect();
+
+        let connection = Connection::new_with_replicas(&connection_string, &replica_uris, config, replica_config)
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Failed to initialize PostgreSQL: {}", e)))?;
 
@@ -99,6 +129,8 @@ pub(super) fn init<'py>(
             .write()
             .map_err(|e| PyRuntimeError::new_err(format!("Failed to acquire pool lock: {}", e)))?;
 
+        // A single process-wide session when reads should follow writes
+        let connection = if sticky_window_ms > 0 { connection.session() } else { connection };
         *pool = Some(Arc::new(connection));
 
         Ok(())
@@ -146,3 +178,29 @@ pub(super) fn is_connected(_py: Python<'_>) -> PyResult<bool> {
 
     Ok(pool.is_some())
 }
+
+/// Get the status of each read replica
+///
+/// Returns:
+///     List of dicts with `name`, `is_healthy`, `lag_ms`, `in_rotation`,
+///     `error` and `last_checked` keys (empty without replicas)
+///
+/// Example:
+///     for replica in replica_status():
+///         print(replica["name"], replica["in_rotation"], replica["lag_ms"])
+#[pyfunction]
+pub(super) fn replica_status(py: Python<'_>) -> PyResult<PyObject> {
+    let conn = super::conversion::get_connection()?;
+    let list = PyList::empty(py);
+    for status in conn.replica_status() {
+        let dict = PyDict::new(py);
+        dict.set_item("name", status.name)?;
+        dict.set_item("is_healthy", status.is_healthy)?;
+        dict.set_item("lag_ms", status.lag_ms)?;
+        dict.set_item("in_rotation", status.in_rotation)?;
+        dict.set_item("error", status.error)?;
+        dict.set_item("last_checked", status.last_checked)?;
+        list.append(dict)?;
+    }
+    Ok(list.into_any().unbind())
+}
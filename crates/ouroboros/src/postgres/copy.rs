@@ -0,0 +1,233 @@
+//! COPY import and export for PostgreSQL.
+
+use futures::StreamExt;
+use pyo3::exceptions::{PyRuntimeError, PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use pyo3_async_runtimes::tokio::future_into_py;
+use std::sync::Arc;
+
+use ouroboros_postgres::{CopyConfig, CopyExecutor, CopyFormat, CopyOutStream, CopyProgress, DataBridgeError, ExtractedValue};
+
+use super::conversion::{get_connection, py_value_to_extracted};
+
+/// Rows pulled from the Python iterable per GIL acquisition
+const ROW_CHUNK_SIZE: usize = 1000;
+
+fn parse_format(format: &str) -> PyResult<CopyFormat> {
+    match format.to_lowercase().as_str() {
+        "binary" => Ok(CopyFormat::Binary),
+        "csv" => Ok(CopyFormat::Csv),
+        other => Err(PyValueError::new_err(format!(
+            "Unsupported COPY format '{}', expected 'binary' or 'csv'",
+            other
+        ))),
+    }
+}
+
+/// Wraps a Python callable as a progress callback taking `(rows, bytes)`
+fn progress_callback(callback: PyObject) -> impl Fn(CopyProgress) + Send + Sync + 'static {
+    move |progress| {
+        Python::with_gil(|py| {
+            if let Err(e) = callback.call1(py, (progress.rows, progress.bytes)) {
+                e.print(py);
+            }
+        })
+    }
+}
+
+/// Converts one Python row (sequence, or dict keyed by column) to values
+fn py_row_to_values(py: Python<'_>, row: &Bound<'_, PyAny>, columns: &[String]) -> PyResult<Vec<ExtractedValue>> {
+    if let Ok(dict) = row.downcast::<PyDict>() {
+        if columns.is_empty() {
+            return Err(PyValueError::new_err("Dict rows require an explicit column list"));
+        }
+        return columns.iter()
+            .map(|column| match dict.get_item(column)? {
+                Some(value) => py_value_to_extracted(py, &value),
+                None => Ok(ExtractedValue::Null),
+            })
+            .collect();
+    }
+
+    row.try_iter()?
+        .map(|value| py_value_to_extracted(py, &value?))
+        .collect()
+}
+
+/// Pulls the next chunk of rows from a Python iterator
+fn next_rows(iterator: &PyObject, columns: &[String]) -> Option<Vec<ouroboros_postgres::Result<Vec<ExtractedValue>>>> {
+    Python::with_gil(|py| {
+        let iterator = iterator.bind(py);
+        let mut rows = Vec::with_capacity(ROW_CHUNK_SIZE);
+        while rows.len() < ROW_CHUNK_SIZE {
+            let row = match iterator.call_method0("__next__") {
+                Ok(row) => py_row_to_values(py, &row, columns),
+                Err(e) if e.is_instance_of::<pyo3::exceptions::PyStopIteration>(py) => break,
+                Err(e) => Err(e),
+            };
+            let failed = row.is_err();
+            rows.push(row.map_err(|e| DataBridgeError::Validation(e.to_string())));
+            if failed {
+                break;
+            }
+        }
+        (!rows.is_empty()).then_some(rows)
+    })
+}
+
+/// Bulk import rows with COPY FROM STDIN
+///
+/// Rows are read lazily from any iterable, so generators can stream data
+/// that does not fit in memory. If a row fails to convert, nothing is
+/// imported.
+///
+/// Args:
+///     table: Table name
+///     rows: Iterable of rows; each a sequence of values in column order,
+///         or a dict keyed by column name when `columns` is given
+///     columns: Columns to fill (default: all non-generated columns)
+///     format: "binary" (default) or "csv"
+///     on_progress: Optional callable receiving `(rows, bytes)` as the copy proceeds
+///     progress_interval: Rows between progress calls (default: 10000)
+///
+/// Returns:
+///     Number of rows copied
+///
+/// Example:
+///     count = await copy_in("users", [("Alice", 30), ("Bob", 25)], columns=["name", "age"])
+#[pyfunction]
+#[pyo3(signature = (table, rows, columns=None, format="binary", on_progress=None, progress_interval=10_000))]
+#[allow(clippy::too_many_arguments)]
+pub(super) fn copy_in<'py>(
+    py: Python<'py>,
+    table: String,
+    rows: &Bound<'py, PyAny>,
+    columns: Option<Vec<String>>,
+    format: &str,
+    on_progress: Option<PyObject>,
+    progress_interval: u64,
+) -> PyResult<Bound<'py, PyAny>> {
+    let conn = get_connection()?;
+    let config = CopyConfig::new()
+        .format(parse_format(format)?)
+        .progress_interval(progress_interval);
+    let iterator: PyObject = rows.try_iter()?.into_any().unbind();
+    let columns = columns.unwrap_or_default();
+
+    future_into_py(py, async move {
+        let mut executor = CopyExecutor::new(&conn, config);
+        if let Some(callback) = on_progress {
+            executor = executor.on_progress(progress_callback(callback));
+        }
+
+        let column_names = columns.clone();
+        let source = futures::stream::unfold(iterator, move |iterator| {
+            let chunk = next_rows(&iterator, &column_names);
+            async move { chunk.map(|rows| (futures::stream::iter(rows), iterator)) }
+        })
+        .flatten();
+
+        let column_refs: Vec<&str> = columns.iter().map(String::as_str).collect();
+        let count = executor.copy_in(&table, &column_refs, source).await
+            .map_err(|e| PyRuntimeError::new_err(format!("COPY failed: {}", e)))?;
+        Ok(count)
+    })
+}
+
+/// Async iterator over COPY OUT data chunks
+#[pyclass]
+#[derive(Clone)]
+pub(super) struct PyCopyOut {
+    stream: Arc<tokio::sync::Mutex<Option<CopyOutStream>>>,
+}
+
+#[pymethods]
+impl PyCopyOut {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let stream_mutex = self.stream.clone();
+
+        future_into_py(py, async move {
+            let mut stream_lock = stream_mutex.lock().await;
+            let stream = stream_lock.as_mut()
+                .ok_or_else(|| PyStopAsyncIteration::new_err(()))?;
+
+            match stream.next().await {
+                Some(Ok(chunk)) => Python::with_gil(|py| Ok(PyBytes::new(py, &chunk).into_any().unbind())),
+                Some(Err(e)) => {
+                    stream_lock.take();
+                    Err(PyRuntimeError::new_err(format!("COPY failed: {}", e)))
+                }
+                None => {
+                    stream_lock.take();
+                    Err(PyStopAsyncIteration::new_err(()))
+                }
+            }
+        })
+    }
+
+    /// Stop the export and release its connection
+    fn close<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let stream_mutex = self.stream.clone();
+
+        future_into_py(py, async move {
+            stream_mutex.lock().await.take();
+            Python::with_gil(|py| Ok(py.None()))
+        })
+    }
+}
+
+/// Bulk export a table with COPY TO STDOUT
+///
+/// Args:
+///     table: Table name
+///     columns: Columns to export (default: all columns)
+///     format: "csv" (default) or "binary"
+///     header: Whether CSV output starts with a header line (default: False)
+///     on_progress: Optional callable receiving `(rows, bytes)` as the copy proceeds
+///     progress_interval: Rows between progress calls (default: 10000)
+///
+/// Returns:
+///     Async iterator yielding `bytes` chunks
+///
+/// Example:
+///     export = await copy_out("users", format="csv", header=True)
+///     async for chunk in export:
+///         file.write(chunk)
+#[pyfunction]
+#[pyo3(signature = (table, columns=None, format="csv", header=false, on_progress=None, progress_interval=10_000))]
+pub(super) fn copy_out<'py>(
+    py: Python<'py>,
+    table: String,
+    columns: Option<Vec<String>>,
+    format: &str,
+    header: bool,
+    on_progress: Option<PyObject>,
+    progress_interval: u64,
+) -> PyResult<Bound<'py, PyAny>> {
+    let conn = get_connection()?;
+    let config = CopyConfig::new()
+        .format(parse_format(format)?)
+        .header(header)
+        .progress_interval(progress_interval);
+    let columns = columns.unwrap_or_default();
+
+    future_into_py(py, async move {
+        let mut executor = CopyExecutor::new(&conn, config);
+        if let Some(callback) = on_progress {
+            executor = executor.on_progress(progress_callback(callback));
+        }
+
+        let column_refs: Vec<&str> = columns.iter().map(String::as_str).collect();
+        let stream = executor.copy_out_stream(&table, &column_refs).await
+            .map_err(|e| PyRuntimeError::new_err(format!("COPY failed: {}", e)))?;
+
+        Python::with_gil(|py| {
+            Ok(PyCopyOut { stream: Arc::new(tokio::sync::Mutex::new(Some(stream))) }.into_py(py))
+        })
+    })
+}
@@ -40,7 +40,7 @@ pub(super) fn insert_one<'py>(
 
     // Phase 2: Execute SQL (GIL released via future_into_py)
     future_into_py(py, async move {
-        let row = Row::insert(conn.pool(), &table, &values)
+        let row = Row::insert(conn.write_pool(), &table, &values)
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Insert failed: {}", e)))?;
 
@@ -85,7 +85,7 @@ pub(super) fn insert_many<'py>(
     // Phase 2: Execute batch INSERT (GIL released via future_into_py)
     future_into_py(py, async move {
         // Use Row::insert_many() batch method for better performance
-        let batch_results = Row::insert_many(conn.pool(), &table, &extracted_rows)
+        let batch_results = Row::insert_many(conn.write_pool(), &table, &extracted_rows)
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Batch insert failed: {}", e)))?;
 
@@ -132,7 +132,7 @@ pub(super) fn upsert_one<'py>(
     // Phase 2: Execute SQL (GIL released via future_into_py)
     future_into_py(py, async move {
         let row = Row::upsert(
-            conn.pool(),
+            conn.write_pool(),
             &table,
             &values,
             &conflict_target,
@@ -187,7 +187,7 @@ pub(super) fn upsert_many<'py>(
     future_into_py(py, async move {
         // Use Row::upsert_many() batch method for better performance
         let batch_results = Row::upsert_many(
-            conn.pool(),
+            conn.write_pool(),
             &table,
             &extracted_rows,
             &conflict_target,
@@ -253,8 +253,10 @@ pub(super) fn fetch_one<'py>(
         }
 
         // Execute query
-        let result = sqlx::query_with(&sql, args)
-            .fetch_optional(conn.pool())
+        let result = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_optional(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Query failed: {}", e)))?;
 
@@ -358,8 +360,10 @@ pub(super) fn fetch_all<'py>(
         }
 
         // Execute query
-        let pg_rows = sqlx::query_with(&sql, args)
-            .fetch_all(conn.pool())
+        let pg_rows = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_all(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Query failed: {}", e)))?;
 
@@ -458,12 +462,10 @@ pub(super) fn fetch_one_with_relations<'py>(
     }
 
     future_into_py(py, async move {
-        let result = Row::find_with_relations(
-            conn.pool(),
-            &table,
-            id,
-            &relation_configs,
-        )
+        let result = conn.read(|pool| {
+            let (table, relation_configs) = (&table, &relation_configs);
+            async move { Row::find_with_relations(&pool, table, id, relation_configs).await }
+        })
         .await
         .map_err(|e| PyRuntimeError::new_err(format!("Fetch with relations failed: {}", e)))?;
 
@@ -510,12 +512,10 @@ pub(super) fn fetch_one_eager<'py>(
             .map(|(name, fk, ref_table)| (name.as_str(), fk.as_str(), ref_table.as_str()))
             .collect();
 
-        let result = Row::find_one_eager(
-            conn.pool(),
-            &table,
-            id,
-            &join_refs,
-        )
+        let result = conn.read(|pool| {
+            let (table, join_refs) = (&table, &join_refs);
+            async move { Row::find_one_eager(&pool, table, id, join_refs).await }
+        })
         .await
         .map_err(|e| PyRuntimeError::new_err(format!("Eager fetch failed: {}", e)))?;
 
@@ -632,15 +632,14 @@ pub(super) fn fetch_many_with_relations<'py>(
     });
 
     future_into_py(py, async move {
-        let results = Row::find_many_with_relations(
-            conn.pool(),
-            &table,
-            &relation_configs,
-            where_clause.as_ref().map(|(c, o, v)| (c.as_str(), o.clone(), v.clone())),
-            order.as_ref().map(|(c, d)| (c.as_str(), d.clone())),
-            limit,
-            offset,
-        )
+        let results = conn.read(|pool| {
+            let (table, relation_configs) = (&table, &relation_configs);
+            let where_clause = where_clause.as_ref().map(|(c, o, v)| (c.as_str(), o.clone(), v.clone()));
+            let order = order.as_ref().map(|(c, d)| (c.as_str(), d.clone()));
+            async move {
+                Row::find_many_with_relations(&pool, table, relation_configs, where_clause, order, limit, offset).await
+            }
+        })
         .await
         .map_err(|e| PyRuntimeError::new_err(format!("Fetch many with relations failed: {}", e)))?;
 
@@ -703,7 +702,7 @@ pub(super) fn update_one<'py>(
 
         // Execute query
         let result = sqlx::query_with(&sql, args)
-            .execute(conn.pool())
+            .execute(conn.write_pool())
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Update failed: {}", e)))?;
 
@@ -802,7 +801,7 @@ pub(super) fn update_many<'py>(
         if returning.is_some() {
             // With RETURNING clause, fetch rows
             let rows = sqlx::query_with(&sql, args)
-                .fetch_all(conn.pool())
+                .fetch_all(conn.write_pool())
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("Update failed: {}", e)))?;
 
@@ -829,7 +828,7 @@ pub(super) fn update_many<'py>(
         } else {
             // Without RETURNING, return row count
             let result = sqlx::query_with(&sql, args)
-                .execute(conn.pool())
+                .execute(conn.write_pool())
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("Update failed: {}", e)))?;
 
@@ -886,7 +885,7 @@ pub(super) fn delete_one<'py>(
 
         // Execute query
         let result = sqlx::query_with(&sql, args)
-            .execute(conn.pool())
+            .execute(conn.write_pool())
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Delete failed: {}", e)))?;
 
@@ -963,7 +962,7 @@ pub(super) fn delete_many<'py>(
         if returning.is_some() {
             // With RETURNING clause, fetch rows
             let rows = sqlx::query_with(&sql, args)
-                .fetch_all(conn.pool())
+                .fetch_all(conn.write_pool())
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("Delete failed: {}", e)))?;
 
@@ -990,7 +989,7 @@ pub(super) fn delete_many<'py>(
         } else {
             // Without RETURNING, return row count
             let result = sqlx::query_with(&sql, args)
-                .execute(conn.pool())
+                .execute(conn.write_pool())
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("Delete failed: {}", e)))?;
 
@@ -1032,7 +1031,7 @@ pub(super) fn delete_with_cascade<'py>(
     let id_col = id_column.unwrap_or_else(|| "id".to_string());
 
     future_into_py(py, async move {
-        let deleted = Row::delete_with_cascade(conn.pool(), &table, id, &id_col)
+        let deleted = Row::delete_with_cascade(conn.write_pool(), &table, id, &id_col)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
         Ok(deleted)
@@ -1066,7 +1065,7 @@ pub(super) fn delete_checked<'py>(
     let id_col = id_column.unwrap_or_else(|| "id".to_string());
 
     future_into_py(py, async move {
-        let deleted = Row::delete_checked(conn.pool(), &table, id, &id_col)
+        let deleted = Row::delete_checked(conn.write_pool(), &table, id, &id_col)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
         Ok(deleted)
@@ -1175,8 +1174,10 @@ pub(super) fn count<'py>(
         }
 
         // Execute query
-        let row = sqlx::query_with(&sql, args)
-            .fetch_one(conn.pool())
+        let row = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_one(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Count query failed: {}", e)))?;
 
@@ -0,0 +1,159 @@
+//! LISTEN/NOTIFY support for PostgreSQL.
+
+use pyo3::exceptions::{PyRuntimeError, PyStopAsyncIteration};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3_async_runtimes::tokio::future_into_py;
+use std::sync::Arc;
+
+use ouroboros_postgres::{Listener, Notification};
+
+use super::conversion::get_connection;
+
+/// Python wrapper for a PostgreSQL notification listener
+///
+/// Iterate with `async for` to receive notifications as dicts with
+/// `channel`, `payload` and `process_id` keys.
+#[pyclass]
+#[derive(Clone)]
+pub(super) struct PyListener {
+    listener: Arc<tokio::sync::Mutex<Option<Listener>>>,
+}
+
+fn notification_to_py(py: Python<'_>, notification: &Notification) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("channel", notification.channel())?;
+    dict.set_item("payload", notification.payload())?;
+    dict.set_item("process_id", notification.process_id())?;
+    Ok(dict.into_any().unbind())
+}
+
+#[pymethods]
+impl PyListener {
+    /// Subscribe to a channel
+    fn listen<'py>(&self, py: Python<'py>, channel: String) -> PyResult<Bound<'py, PyAny>> {
+        let listener_mutex = self.listener.clone();
+
+        future_into_py(py, async move {
+            let mut listener_lock = listener_mutex.lock().await;
+            let listener = listener_lock.as_mut()
+                .ok_or_else(|| PyRuntimeError::new_err("Listener is closed"))?;
+
+            listener.listen(&channel).await
+                .map_err(|e| PyRuntimeError::new_err(format!("Listen failed: {}", e)))?;
+            Python::with_gil(|py| Ok(py.None()))
+        })
+    }
+
+    /// Unsubscribe from a channel
+    fn unlisten<'py>(&self, py: Python<'py>, channel: String) -> PyResult<Bound<'py, PyAny>> {
+        let listener_mutex = self.listener.clone();
+
+        future_into_py(py, async move {
+            let mut listener_lock = listener_mutex.lock().await;
+            let listener = listener_lock.as_mut()
+                .ok_or_else(|| PyRuntimeError::new_err("Listener is closed"))?;
+
+            listener.unlisten(&channel).await
+                .map_err(|e| PyRuntimeError::new_err(format!("Unlisten failed: {}", e)))?;
+            Python::with_gil(|py| Ok(py.None()))
+        })
+    }
+
+    /// Wait for the next notification
+    fn recv<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let listener_mutex = self.listener.clone();
+
+        future_into_py(py, async move {
+            let mut listener_lock = listener_mutex.lock().await;
+            let listener = listener_lock.as_mut()
+                .ok_or_else(|| PyRuntimeError::new_err("Listener is closed"))?;
+
+            let notification = listener.recv().await
+                .map_err(|e| PyRuntimeError::new_err(format!("Listener failed: {}", e)))?;
+            Python::with_gil(|py| notification_to_py(py, &notification))
+        })
+    }
+
+    /// Close the listener and release its connection
+    fn close<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let listener_mutex = self.listener.clone();
+
+        future_into_py(py, async move {
+            listener_mutex.lock().await.take();
+            Python::with_gil(|py| Ok(py.None()))
+        })
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let listener_mutex = self.listener.clone();
+
+        future_into_py(py, async move {
+            let mut listener_lock = listener_mutex.lock().await;
+            let listener = listener_lock.as_mut()
+                .ok_or_else(|| PyStopAsyncIteration::new_err(()))?;
+
+            let notification = listener.recv().await
+                .map_err(|e| PyRuntimeError::new_err(format!("Listener failed: {}", e)))?;
+            Python::with_gil(|py| notification_to_py(py, &notification))
+        })
+    }
+}
+
+/// Listen for notifications on one or more channels
+///
+/// The listener holds a dedicated connection and reconnects automatically
+/// using the pool's retry settings. Notifications sent while it is
+/// reconnecting are lost.
+///
+/// Args:
+///     channels: Channel names to subscribe to
+///
+/// Returns:
+///     Listener handle, iterable with `async for`
+///
+/// Example:
+///     listener = await listen(["cache_invalidation"])
+///     async for notification in listener:
+///         print(notification["channel"], notification["payload"])
+#[pyfunction]
+pub(super) fn listen<'py>(py: Python<'py>, channels: Vec<String>) -> PyResult<Bound<'py, PyAny>> {
+    let conn = get_connection()?;
+
+    future_into_py(py, async move {
+        let mut listener = conn.listener().await
+            .map_err(|e| PyRuntimeError::new_err(format!("Failed to create listener: {}", e)))?;
+        for channel in &channels {
+            listener.listen(channel).await
+                .map_err(|e| PyRuntimeError::new_err(format!("Listen failed: {}", e)))?;
+        }
+
+        Python::with_gil(|py| {
+            Ok(PyListener { listener: Arc::new(tokio::sync::Mutex::new(Some(listener))) }.into_py(py))
+        })
+    })
+}
+
+/// Send a notification on a channel
+///
+/// Args:
+///     channel: Channel name
+///     payload: Notification payload (default: empty)
+///
+/// Example:
+///     await notify("cache_invalidation", "users:42")
+#[pyfunction]
+#[pyo3(signature = (channel, payload=String::new()))]
+pub(super) fn notify<'py>(py: Python<'py>, channel: String, payload: String) -> PyResult<Bound<'py, PyAny>> {
+    let conn = get_connection()?;
+
+    future_into_py(py, async move {
+        conn.notify(&channel, &payload).await
+            .map_err(|e| PyRuntimeError::new_err(format!("Notify failed: {}", e)))?;
+        Python::with_gil(|py| Ok(py.None()))
+    })
+}
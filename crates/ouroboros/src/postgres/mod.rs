@@ -20,6 +20,8 @@ mod connection;
 mod crud;
 mod relations;
 mod transaction;
+mod listener;
+mod copy;
 mod schema;
 mod migration;
 mod query_functions;
@@ -34,11 +36,14 @@ pub(crate) use wrappers::{RowWrapper, OptionalRowWrapper, RowsWrapper};
 pub fn register_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Register classes
     m.add_class::<transaction::PyTransaction>()?;
+    m.add_class::<listener::PyListener>()?;
+    m.add_class::<copy::PyCopyOut>()?;
 
     // Connection functions
     m.add_function(wrap_pyfunction!(connection::init, m)?)?;
     m.add_function(wrap_pyfunction!(connection::close, m)?)?;
     m.add_function(wrap_pyfunction!(connection::is_connected, m)?)?;
+    m.add_function(wrap_pyfunction!(connection::replica_status, m)?)?;
 
     // CRUD functions
     m.add_function(wrap_pyfunction!(crud::insert_one, m)?)?;
@@ -73,6 +78,14 @@ pub fn register_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Transaction functions
     m.add_function(wrap_pyfunction!(transaction::begin_transaction, m)?)?;
 
+    // LISTEN/NOTIFY functions
+    m.add_function(wrap_pyfunction!(listener::listen, m)?)?;
+    m.add_function(wrap_pyfunction!(listener::notify, m)?)?;
+
+    // COPY functions
+    m.add_function(wrap_pyfunction!(copy::copy_in, m)?)?;
+    m.add_function(wrap_pyfunction!(copy::copy_out, m)?)?;
+
     // Query functions
     m.add_function(wrap_pyfunction!(query_functions::find_by_foreign_key, m)?)?;
     m.add_function(wrap_pyfunction!(query_functions::find_many, m)?)?;
@@ -4,7 +4,7 @@ use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3_async_runtimes::tokio::future_into_py;
 
-use ouroboros_postgres::{QueryBuilder, Operator, OrderDirection, Row};
+use ouroboros_postgres::{QueryBuilder, Filter, Operator, OrderDirection, Row};
 use ouroboros_postgres::query::{AggregateFunction, WindowFunction, WindowSpec};
 
 use super::conversion::{get_connection, py_value_to_extracted};
@@ -38,8 +38,10 @@ pub(super) fn find_by_foreign_key<'py>(
                 .map_err(|e| PyRuntimeError::new_err(format!("Failed to bind parameter: {}", e)))?;
         }
 
-        let result = sqlx::query_with(&sql, args)
-            .fetch_optional(conn.pool())
+        let result = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_optional(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Query failed: {}", e)))?;
 
@@ -122,8 +124,10 @@ pub(super) fn find_many<'py>(
                 .map_err(|e| PyRuntimeError::new_err(format!("Failed to bind parameter: {}", e)))?;
         }
 
-        let pg_rows = sqlx::query_with(&sql, args)
-            .fetch_all(conn.pool())
+        let pg_rows = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_all(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Query failed: {}", e)))?;
 
@@ -161,6 +165,31 @@ pub(super) fn parse_operator(op_str: &str) -> PyResult<Operator> {
     }
 }
 
+/// Helper function to parse a WHERE filter from Python
+///
+/// Accepts a `(field, operator, value)` condition or an
+/// `("and" | "or" | "not", [filters])` group, nested to any depth.
+pub(super) fn py_to_filter(py: Python<'_>, item: &Bound<'_, PyAny>) -> PyResult<Filter> {
+    if let Ok((field, op_str, value)) = item.extract::<(String, String, Bound<'_, PyAny>)>() {
+        let operator = parse_operator(&op_str)?;
+        let value = py_value_to_extracted(py, &value)?;
+        return Filter::condition(&field, operator, value)
+            .map_err(|e| PyValueError::new_err(format!("Invalid filter: {}", e)));
+    }
+
+    let (kind, members) = item.extract::<(String, Vec<Bound<'_, PyAny>>)>().map_err(|_| {
+        PyValueError::new_err("Filter must be a (field, operator, value) condition or an (and|or|not, [filters]) group")
+    })?;
+    let mut filters = members.iter().map(|member| py_to_filter(py, member)).collect::<PyResult<Vec<_>>>()?;
+    match kind.to_lowercase().as_str() {
+        "and" => Ok(Filter::and(filters)),
+        "or" => Ok(Filter::or(filters)),
+        "not" if filters.len() == 1 => Ok(Filter::negate(filters.remove(0))),
+        "not" => Err(PyValueError::new_err("NOT filter requires exactly one member")),
+        _ => Err(PyValueError::new_err(format!("Unknown filter group: {}", kind))),
+    }
+}
+
 /// Execute an aggregate query with GROUP BY support
 #[pyfunction]
 #[pyo3(signature = (table, aggregates, group_by=None, having=None, where_conditions=None, order_by=None, limit=None, distinct=None, distinct_on=None, ctes=None, subqueries=None, windows=None, set_operations=None))]
@@ -170,7 +199,7 @@ pub(super) fn query_aggregate<'py>(
     aggregates: Vec<(String, Option<String>, Option<String>)>,
     group_by: Option<Vec<String>>,
     having: Option<Vec<(String, Option<String>, String, Bound<'py, PyAny>)>>,
-    where_conditions: Option<Vec<(String, String, Bound<'py, PyAny>)>>,
+    where_conditions: Option<Vec<Bound<'py, PyAny>>>,
     order_by: Option<Vec<(String, String)>>,
     limit: Option<i64>,
     distinct: Option<bool>,
@@ -198,13 +227,9 @@ pub(super) fn query_aggregate<'py>(
         agg_funcs.push((agg_func, alias));
     }
 
-    // Extract WHERE conditions
-    let where_params: Vec<(String, Operator, ouroboros_postgres::ExtractedValue)> = if let Some(conditions) = where_conditions {
-        conditions.into_iter().map(|(field, op_str, value)| {
-            let operator = parse_operator(&op_str)?;
-            let extracted_value = py_value_to_extracted(py, &value)?;
-            Ok((field, operator, extracted_value))
-        }).collect::<PyResult<Vec<_>>>()?
+    // Extract WHERE conditions and filter groups
+    let where_filters: Vec<Filter> = if let Some(conditions) = where_conditions {
+        conditions.iter().map(|item| py_to_filter(py, item)).collect::<PyResult<Vec<_>>>()?
     } else {
         Vec::new()
     };
@@ -340,9 +365,8 @@ pub(super) fn query_aggregate<'py>(
         }
 
         // Add WHERE conditions
-        for (field, operator, value) in where_params {
-            query = query.where_clause(&field, operator, value)
-                .map_err(|e| PyRuntimeError::new_err(format!("Invalid filter: {}", e)))?;
+        for filter in where_filters {
+            query = query.where_filter(filter);
         }
 
         // Add subquery conditions
@@ -419,8 +443,10 @@ pub(super) fn query_aggregate<'py>(
                 .map_err(|e| PyRuntimeError::new_err(format!("Failed to bind parameter: {}", e)))?;
         }
 
-        let pg_rows = sqlx::query_with(&sql, args)
-            .fetch_all(conn.pool())
+        let pg_rows = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_all(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("Aggregate query failed: {}", e)))?;
 
@@ -443,7 +469,7 @@ pub(super) fn query_with_cte<'py>(
     main_table: String,
     ctes: Vec<(String, String, Vec<Bound<'py, PyAny>>)>,
     select_columns: Option<Vec<String>>,
-    where_conditions: Option<Vec<(String, String, Bound<'py, PyAny>)>>,
+    where_conditions: Option<Vec<Bound<'py, PyAny>>>,
     order_by: Option<Vec<(String, String)>>,
     limit: Option<i64>,
     subqueries: Option<Vec<(String, Option<String>, String, Vec<Bound<'py, PyAny>>)>>,
@@ -459,13 +485,9 @@ pub(super) fn query_with_cte<'py>(
         })
         .collect::<PyResult<Vec<_>>>()?;
 
-    // Extract WHERE conditions
-    let where_params: Vec<(String, Operator, ouroboros_postgres::ExtractedValue)> = if let Some(conditions) = where_conditions {
-        conditions.into_iter().map(|(field, op_str, value)| {
-            let operator = parse_operator(&op_str)?;
-            let extracted_value = py_value_to_extracted(py, &value)?;
-            Ok((field, operator, extracted_value))
-        }).collect::<PyResult<Vec<_>>>()?
+    // Extract WHERE conditions and filter groups
+    let where_filters: Vec<Filter> = if let Some(conditions) = where_conditions {
+        conditions.iter().map(|item| py_to_filter(py, item)).collect::<PyResult<Vec<_>>>()?
     } else {
         Vec::new()
     };
@@ -497,9 +519,8 @@ pub(super) fn query_with_cte<'py>(
         }
 
         // Add WHERE conditions
-        for (field, operator, value) in where_params {
-            query = query.where_clause(&field, operator, value)
-                .map_err(|e| PyRuntimeError::new_err(format!("Invalid filter: {}", e)))?;
+        for filter in where_filters {
+            query = query.where_filter(filter);
         }
 
         // Add subquery conditions
@@ -549,8 +570,10 @@ pub(super) fn query_with_cte<'py>(
                 .map_err(|e| PyRuntimeError::new_err(format!("Failed to bind parameter: {}", e)))?;
         }
 
-        let pg_rows = sqlx::query_with(&sql, args)
-            .fetch_all(conn.pool())
+        let pg_rows = conn.read(|pool| {
+                let (sql, args) = (&sql, args.clone());
+                async move { Ok(sqlx::query_with(sql, args).fetch_all(&pool).await?) }
+            })
             .await
             .map_err(|e| PyRuntimeError::new_err(format!("CTE query failed: {}", e)))?;
 
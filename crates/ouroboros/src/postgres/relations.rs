@@ -45,7 +45,7 @@ pub(super) fn m2m_create_join_table<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        ouroboros_postgres::row::Row::create_join_table(conn.pool(), &config, &source_table)
+        ouroboros_postgres::row::Row::create_join_table(conn.write_pool(), &config, &source_table)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -91,7 +91,7 @@ pub(super) fn m2m_add_relation<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        ouroboros_postgres::row::Row::add_m2m_relation(conn.pool(), &config, source_id, target_id)
+        ouroboros_postgres::row::Row::add_m2m_relation(conn.write_pool(), &config, source_id, target_id)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -140,7 +140,7 @@ pub(super) fn m2m_remove_relation<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        let affected = ouroboros_postgres::row::Row::remove_m2m_relation(conn.pool(), &config, source_id, target_id)
+        let affected = ouroboros_postgres::row::Row::remove_m2m_relation(conn.write_pool(), &config, source_id, target_id)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -187,7 +187,7 @@ pub(super) fn m2m_clear_relations<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        let affected = ouroboros_postgres::row::Row::clear_m2m_relations(conn.pool(), &config, source_id)
+        let affected = ouroboros_postgres::row::Row::clear_m2m_relations(conn.write_pool(), &config, source_id)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -251,14 +251,12 @@ pub(super) fn m2m_fetch_related<'py>(
             v.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect()
         });
 
-        let results = ouroboros_postgres::row::Row::fetch_m2m_related(
-            conn.pool(),
-            &config,
-            source_id,
-            cols_refs.as_deref(),
-            order_refs.as_deref(),
-            limit,
-        )
+        let results = conn.read(|pool| {
+            let (config, cols_refs, order_refs) = (&config, cols_refs.as_deref(), order_refs.as_deref());
+            async move {
+                ouroboros_postgres::row::Row::fetch_m2m_related(&pool, config, source_id, cols_refs, order_refs, limit).await
+            }
+        })
         .await
         .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -319,7 +317,10 @@ pub(super) fn m2m_count_related<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        let count = ouroboros_postgres::row::Row::count_m2m_related(conn.pool(), &config, source_id)
+        let count = conn.read(|pool| {
+            let config = &config;
+            async move { ouroboros_postgres::row::Row::count_m2m_related(&pool, config, source_id).await }
+        })
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -368,7 +369,10 @@ pub(super) fn m2m_has_relation<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        let exists = ouroboros_postgres::row::Row::has_m2m_relation(conn.pool(), &config, source_id, target_id)
+        let exists = conn.read(|pool| {
+            let config = &config;
+            async move { ouroboros_postgres::row::Row::has_m2m_relation(&pool, config, source_id, target_id).await }
+        })
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -414,7 +418,7 @@ pub(super) fn m2m_set_relations<'py>(
         .with_source_reference(source_ref)
         .with_target_reference(target_ref);
 
-        ouroboros_postgres::row::Row::set_m2m_relations(conn.pool(), &config, source_id, &target_ids)
+        ouroboros_postgres::row::Row::set_m2m_relations(conn.write_pool(), &config, source_id, &target_ids)
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
@@ -464,8 +468,6 @@ pub(super) fn execute<'py>(
     future_into_py(py, async move {
         use sqlx::postgres::PgArguments;
 
-        let pool = conn.pool();
-
         // Determine query type by examining the SQL (case-insensitive)
         let sql_upper = sql.trim().to_uppercase();
         let has_returning = sql_upper.contains("RETURNING");
@@ -474,6 +476,13 @@ pub(super) fn execute<'py>(
             || sql_upper.starts_with("UPDATE")
             || sql_upper.starts_with("DELETE")) && !has_returning;
 
+        // Raw SQL always runs on the primary; anything but a plain SELECT counts as a write
+        let pool = if sql_upper.starts_with("SELECT") && !has_returning {
+            conn.pool()
+        } else {
+            conn.write_pool()
+        };
+
         // Bind parameters to query
         let mut args = PgArguments::default();
         for param in &extracted_params {
@@ -230,6 +230,22 @@ impl PyTransaction {
         })
     }
 
+    /// Send a notification, delivered when this transaction commits
+    #[pyo3(signature = (channel, payload=String::new()))]
+    fn notify<'py>(&mut self, py: Python<'py>, channel: String, payload: String) -> PyResult<Bound<'py, PyAny>> {
+        let tx_mutex = self.tx.clone();
+
+        future_into_py(py, async move {
+            let mut tx_lock = tx_mutex.lock().await;
+            let tx = tx_lock.as_mut()
+                .ok_or_else(|| PyRuntimeError::new_err("Transaction already completed"))?;
+
+            tx.notify(&channel, &payload).await
+                .map_err(|e| PyRuntimeError::new_err(format!("Notify failed: {}", e)))?;
+            Python::with_gil(|py| Ok(py.None()))
+        })
+    }
+
     /// Execute raw SQL within this transaction
     fn execute<'py>(&mut self, py: Python<'py>, sql: String, params: Option<Vec<Bound<'py, PyAny>>>) -> PyResult<Bound<'py, PyAny>> {
         let extracted_params: Vec<ouroboros_postgres::ExtractedValue> = if let Some(param_list) = params {
@@ -38,7 +38,8 @@ use tokio::sync::RwLock;
 
 // Import from ouroboros-tasks
 use ouroboros_tasks::{
-    Broker, DelayedBroker, RedisBackend, RedisBackendConfig, ResultBackend,
+    Broker, DelayedBroker, IdempotencyStore, RedisBackend, RedisBackendConfig,
+    RedisIdempotencyStore, ResultBackend,
     TaskError, TaskId, TaskMessage, TaskOptions, TaskSignature, TaskState,
     Chain, Group, Chord, GroupResult,
 };
@@ -258,7 +259,7 @@ impl PyTask {
         args: &Bound<'_, PyTuple>,
         kwargs: Option<&Bound<'_, PyDict>>,
     ) -> PyResult<Bound<'py, PyAny>> {
-        self.apply_async_impl(py, args, kwargs, None, None)
+        self.apply_async_impl(py, args, kwargs, None, None, None, None)
     }
 
     /// Send task with options
@@ -268,6 +269,9 @@ impl PyTask {
     /// * `args` - Positional arguments
     /// * `countdown` - Delay in seconds before execution
     /// * `eta` - ISO 8601 timestamp for scheduled execution
+    /// * `priority` - Message priority from 0 to 9 (higher runs first)
+    /// * `idempotency_key` - Key of the logical job; while it is reserved, a
+    ///   second call returns the existing task instead of publishing again
     /// * `kwargs` - Keyword arguments
     ///
     /// # Example
@@ -278,17 +282,25 @@ impl PyTask {
     ///
     /// # Schedule for specific time
     /// result = await add.apply_async(1, 2, eta="2026-01-05T10:00:00Z")
+    ///
+    /// # Run ahead of queued lower-priority tasks
+    /// result = await add.apply_async(1, 2, priority=9)
+    ///
+    /// # Publish at most once per order
+    /// result = await charge.apply_async(order_id, idempotency_key=f"charge-{order_id}")
     /// ```
-    #[pyo3(signature = (*args, countdown = None, eta = None, **kwargs))]
+    #[pyo3(signature = (*args, countdown = None, eta = None, priority = None, idempotency_key = None, **kwargs))]
     fn apply_async<'py>(
         &self,
         py: Python<'py>,
         args: &Bound<'_, PyTuple>,
         countdown: Option<f64>,
         eta: Option<String>,
+        priority: Option<u8>,
+        idempotency_key: Option<String>,
         kwargs: Option<&Bound<'_, PyDict>>,
     ) -> PyResult<Bound<'py, PyAny>> {
-        self.apply_async_impl(py, args, kwargs, countdown, eta)
+        self.apply_async_impl(py, args, kwargs, countdown, eta, priority, idempotency_key)
     }
 
     /// Create a signature for this task (for workflows)
@@ -347,6 +359,8 @@ impl PyTask {
         kwargs: Option<&Bound<'_, PyDict>>,
         countdown: Option<f64>,
         eta: Option<String>,
+        priority: Option<u8>,
+        idempotency_key: Option<String>,
     ) -> PyResult<Bound<'py, PyAny>> {
         // Convert args and kwargs to JSON
         let args_json = python_to_json(args)?;
@@ -373,6 +387,9 @@ impl PyTask {
             if kwargs_json != serde_json::Value::Null {
                 message = message.with_kwargs(kwargs_json);
             }
+            if let Some(priority) = priority {
+                message = message.with_priority(priority);
+            }
 
             // Set ETA for delayed or scheduled tasks
             if let Some(delay) = countdown {
@@ -384,50 +401,81 @@ impl PyTask {
                 }
             }
 
+            // A reserved key means the job was already published
+            let idempotency = match idempotency_key {
+                Some(key) => {
+                    let store = RedisIdempotencyStore::with_prefix(
+                        backend.pool().clone(),
+                        format!("{}:idempotency", backend.key_prefix()),
+                    );
+                    if let Some(existing) = store
+                        .reserve(&key, &task_id)
+                        .await
+                        .map_err(task_error_to_pyerr)?
+                    {
+                        return Python::with_gil(|py| Ok(PyAsyncResult { task_id: existing }.into_py(py)));
+                    }
+                    message = message.with_idempotency_key(key.clone());
+                    Some((store, key))
+                }
+                None => None,
+            };
+
             // Set initial state
             backend
                 .set_state(&task_id, TaskState::Pending)
                 .await
                 .map_err(task_error_to_pyerr)?;
 
-            // Send to broker based on type
-            match broker.as_ref() {
-                #[cfg(feature = "tasks-nats")]
-                BrokerInstance::Nats(b) => {
-                    if message.eta.is_some() {
-                        let delay = Duration::from_millis(
-                            (message.eta.unwrap().timestamp_millis() - chrono::Utc::now().timestamp_millis()) as u64
-                        );
-                        b.publish_delayed(&queue, message, delay)
-                            .await
-                            .map_err(task_error_to_pyerr)?;
-                    } else {
-                        b.publish(&queue, message)
-                            .await
-                            .map_err(task_error_to_pyerr)?;
-                    }
-                }
-                #[cfg(feature = "tasks-pubsub")]
-                BrokerInstance::PubSub(b) => {
-                    // Pub/Sub doesn't support native delayed publishing
-                    // The worker will check the ETA field and delay execution
-                    b.publish(&queue, message)
-                        .await
-                        .map_err(task_error_to_pyerr)?;
-                }
-                #[allow(unreachable_patterns)]
-                _ => {
-                    return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                        "Broker type not supported in this build"
-                    ));
-                }
+            let published = publish_message(&broker, &queue, message).await;
+            if let (Err(_), Some((store, key))) = (&published, &idempotency) {
+                // Let the caller try again with the same key
+                store.remove(key).await.map_err(task_error_to_pyerr)?;
             }
+            published?;
 
             Python::with_gil(|py| Ok(PyAsyncResult { task_id }.into_py(py)))
         })
     }
 }
 
+/// Send a task message to the broker based on its type
+async fn publish_message(broker: &BrokerInstance, queue: &str, message: TaskMessage) -> PyResult<()> {
+    match broker {
+        #[cfg(feature = "tasks-nats")]
+        BrokerInstance::Nats(b) => {
+            if message.eta.is_some() {
+                let delay = Duration::from_millis(
+                    (message.eta.unwrap().timestamp_millis() - chrono::Utc::now().timestamp_millis()) as u64
+                );
+                b.publish_delayed(queue, message, delay)
+                    .await
+                    .map_err(task_error_to_pyerr)?;
+            } else {
+                b.publish(queue, message)
+                    .await
+                    .map_err(task_error_to_pyerr)?;
+            }
+        }
+        #[cfg(feature = "tasks-pubsub")]
+        BrokerInstance::PubSub(b) => {
+            // Pub/Sub doesn't support native delayed publishing
+            // The worker will check the ETA field and delay execution
+            b.publish(queue, message)
+                .await
+                .map_err(task_error_to_pyerr)?;
+        }
+        #[allow(unreachable_patterns)]
+        _ => {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "Broker type not supported in this build"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 /// Handle to track async task execution
 ///
 /// Provides methods to check status and retrieve results.
@@ -14,7 +14,9 @@ from .query_ext import (
     active_filter, date_range_filter, in_list_filter, null_check_filter
 )
 from .connection import (
-    init, close, is_connected, execute, query_aggregate, query_with_cte,
+    init, close, is_connected, replica_status, execute, query_aggregate, query_with_cte,
+    listen, notify,
+    copy_in, copy_out,
     insert_one, insert_many,
     upsert_one, upsert_many,
     list_tables, table_exists, get_columns, get_indexes, get_foreign_keys, inspect_table,
@@ -128,9 +130,16 @@ __all__ = [
     "init",
     "close",
     "is_connected",
+    "replica_status",
     "execute",
     "query_aggregate",
     "query_with_cte",
+    # LISTEN/NOTIFY
+    "listen",
+    "notify",
+    # Bulk COPY
+    "copy_in",
+    "copy_out",
     # CRUD Operations
     "insert_one",
     "insert_many",
@@ -1,6 +1,6 @@
 """PostgreSQL connection management."""
 
-from typing import Optional, Literal, List, Dict, Any, Union
+from typing import Optional, Literal, List, Dict, Any, Union, Iterable, Sequence, Callable, AsyncIterator
 from contextlib import asynccontextmanager
 
 # Import from Rust engine when available
@@ -23,6 +23,10 @@ async def init(
     password: Optional[str] = None,
     min_connections: int = 1,
     max_connections: int = 10,
+    read_replicas: Optional[List[str]] = None,
+    replica_max_lag_ms: int = 5000,
+    sticky_window_ms: int = 0,
+    replica_check_interval_ms: int = 5000,
 ) -> None:
     """
     Initialize PostgreSQL connection pool.
@@ -46,6 +50,15 @@ async def init(
         password: Database password
         min_connections: Minimum number of connections in pool (default: 1)
         max_connections: Maximum number of connections in pool (default: 10)
+        read_replicas: Replica connection strings. ORM reads are spread over
+            healthy replicas; writes, transactions and raw ``execute`` calls use
+            the primary. A replica that fails or lags behind is skipped until
+            it recovers, and reads fall back to the primary if none is available.
+        replica_max_lag_ms: Maximum replay lag before a replica stops serving reads (default: 5000)
+        sticky_window_ms: How long reads stay on the primary after a write, so
+            they see it (default: 0, disabled). The window is shared by the
+            whole process, so any write moves every read to the primary.
+        replica_check_interval_ms: Interval between replica health and lag checks (default: 5000)
 
     Example:
         >>> # RECOMMENDED: Using connection string from environment variable
@@ -69,6 +82,12 @@ async def init(
         ...     username="testuser",
         ...     password="<your-password-here>"  # Use environment variable instead
         ... )
+        >>>
+        >>> # Primary with read replicas
+        >>> await init(
+        ...     os.environ["DATABASE_URL"],
+        ...     read_replicas=os.environ["DATABASE_REPLICA_URLS"].split(","),
+        ... )
 
     Raises:
         RuntimeError: If connection fails or Rust engine is not available
@@ -90,7 +109,15 @@ async def init(
         del auth
 
     # Connection string is passed to Rust engine where it's handled securely
-    await _engine.init(connection_string, min_connections, max_connections)
+    await _engine.init(
+        connection_string,
+        min_connections,
+        max_connections,
+        read_replicas=read_replicas,
+        replica_max_lag_ms=replica_max_lag_ms,
+        sticky_window_ms=sticky_window_ms,
+        replica_check_interval_ms=replica_check_interval_ms,
+    )
 
 
 async def close() -> None:
@@ -133,6 +160,26 @@ def is_connected() -> bool:
     return _engine.is_connected()
 
 
+def replica_status() -> List[Dict[str, Any]]:
+    """
+    Get the status of each read replica.
+
+    Returns:
+        List of dicts with ``name``, ``is_healthy``, ``lag_ms``, ``in_rotation``,
+        ``error`` and ``last_checked`` keys; empty without replicas
+
+    Example:
+        >>> for replica in replica_status():
+        ...     print(replica["name"], replica["in_rotation"], replica["lag_ms"])
+
+    Raises:
+        RuntimeError: If PostgreSQL engine not available or not connected
+    """
+    if _engine is None:
+        raise RuntimeError("PostgreSQL engine not available.")
+    return _engine.replica_status()
+
+
 async def execute(
     sql: str,
     params: Optional[list] = None
@@ -190,7 +237,8 @@ async def query_aggregate(
                    alias: Optional alias for the aggregate result
         group_by: List of column names to group by
         having: List of (func_type, column, operator, value) tuples for HAVING clause
-        where_conditions: List of (field, operator, value) tuples for WHERE clause
+        where_conditions: List of (field, operator, value) tuples for WHERE clause,
+            or ("and" | "or" | "not", [conditions]) groups, nested to any depth
         order_by: List of (column, direction) tuples - direction: "asc" or "desc"
         limit: Optional row limit
         distinct: Optional boolean to select distinct rows
@@ -258,7 +306,8 @@ async def query_with_cte(
         main_table: The table or CTE name to query from in the main SELECT
         ctes: List of (name, sql, params) tuples defining CTEs
         select_columns: Optional list of columns to select (defaults to *)
-        where_conditions: List of (field, operator, value) tuples for WHERE clause
+        where_conditions: List of (field, operator, value) tuples for WHERE clause,
+            or ("and" | "or" | "not", [conditions]) groups, nested to any depth
         order_by: List of (column, direction) tuples - direction: "asc" or "desc"
         limit: Optional row limit
         subqueries: Optional list of (type, field, sql, params) tuples for subquery conditions
@@ -502,6 +551,9 @@ class TransactionWrapper:
     async def execute(self, sql: str, params: Optional[list] = None):
         return await self._tx.execute(sql, params)
 
+    async def notify(self, channel: str, payload: str = "") -> None:
+        await self._tx.notify(channel, payload)
+
 
 @asynccontextmanager
 async def begin_transaction(isolation_level: Optional[IsolationLevel] = None):
@@ -567,6 +619,150 @@ async def insert_one(
     return await _engine.insert_one(table, document)
 
 
+@asynccontextmanager
+async def listen(*channels: str):
+    """
+    Listen for notifications on one or more channels.
+
+    The listener holds a dedicated connection for the duration of the block
+    and reconnects automatically if it drops. Notifications sent while it is
+    reconnecting are lost, so treat a long gap as a reason to resync.
+
+    Args:
+        *channels: Channel names to subscribe to
+
+    Yields:
+        Listener to iterate with ``async for``; each notification is a dict
+        with ``channel``, ``payload`` and ``process_id`` keys. The listener
+        also has ``listen(channel)``, ``unlisten(channel)`` and ``recv()``.
+
+    Example:
+        >>> async with listen("cache_invalidation") as listener:
+        ...     async for notification in listener:
+        ...         cache.pop(notification["payload"], None)
+    """
+    if _engine is None:
+        raise RuntimeError("PostgreSQL engine not available.")
+
+    listener = await _engine.listen(list(channels))
+    try:
+        yield listener
+    finally:
+        await listener.close()
+
+
+async def notify(channel: str, payload: str = "") -> None:
+    """
+    Send a notification on a channel. Uses active transaction if available.
+
+    Inside a transaction the notification is delivered on commit and
+    discarded on rollback.
+
+    Args:
+        channel: Channel name
+        payload: Notification payload (default: empty)
+
+    Example:
+        >>> await notify("cache_invalidation", "users:42")
+    """
+    if _engine is None:
+        raise RuntimeError("PostgreSQL engine not available.")
+
+    tx = _active_transaction.get()
+    if tx:
+        return await tx.notify(channel, payload)
+
+    await _engine.notify(channel, payload)
+
+
+# ============================================================================
+# Bulk COPY
+# ============================================================================
+
+
+async def copy_in(
+    table: str,
+    rows: Iterable[Union[Sequence[Any], Dict[str, Any]]],
+    columns: Optional[List[str]] = None,
+    format: Literal["binary", "csv"] = "binary",
+    on_progress: Optional[Callable[[int, int], None]] = None,
+    progress_interval: int = 10_000,
+) -> int:
+    """
+    Bulk import rows with COPY FROM STDIN.
+
+    Much faster than ``insert_many`` for large loads. Rows are read lazily,
+    so a generator can stream data that does not fit in memory. If any row
+    fails, nothing is imported.
+
+    Args:
+        table: Table name
+        rows: Iterable of rows; each a sequence of values in column order,
+            or a dict keyed by column name when ``columns`` is given
+        columns: Columns to fill (default: all non-generated columns)
+        format: "binary" (default) or "csv"
+        on_progress: Optional callable receiving ``(rows, bytes)`` as the copy proceeds
+        progress_interval: Rows between progress calls (default: 10000)
+
+    Returns:
+        Number of rows copied
+
+    Example:
+        >>> count = await copy_in(
+        ...     "users",
+        ...     ((name, age) for name, age in read_csv("users.csv")),
+        ...     columns=["name", "age"],
+        ... )
+
+    Raises:
+        RuntimeError: If PostgreSQL engine not available or the copy fails
+    """
+    if _engine is None:
+        raise RuntimeError("PostgreSQL engine not available.")
+    return await _engine.copy_in(table, rows, columns, format, on_progress, progress_interval)
+
+
+async def copy_out(
+    table: str,
+    columns: Optional[List[str]] = None,
+    format: Literal["csv", "binary"] = "csv",
+    header: bool = False,
+    on_progress: Optional[Callable[[int, int], None]] = None,
+    progress_interval: int = 10_000,
+) -> AsyncIterator[bytes]:
+    """
+    Bulk export a table with COPY TO STDOUT.
+
+    Args:
+        table: Table name
+        columns: Columns to export (default: all columns)
+        format: "csv" (default) or "binary"
+        header: Whether CSV output starts with a header line (default: False)
+        on_progress: Optional callable receiving ``(rows, bytes)`` as the copy proceeds
+        progress_interval: Rows between progress calls (default: 10000)
+
+    Yields:
+        Chunks of exported data as ``bytes``
+
+    Example:
+        >>> with open("users.csv", "wb") as f:
+        ...     async for chunk in copy_out("users", header=True):
+        ...         f.write(chunk)
+
+    Raises:
+        RuntimeError: If PostgreSQL engine not available or the copy fails
+    """
+    if _engine is None:
+        raise RuntimeError("PostgreSQL engine not available.")
+
+    export = await _engine.copy_out(table, columns, format, header, on_progress, progress_interval)
+    try:
+        async for chunk in export:
+            yield chunk
+    finally:
+        await export.close()
+
+
 # ============================================================================
 # Schema Introspection
 # ============================================================================
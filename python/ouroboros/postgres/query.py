@@ -1549,28 +1549,8 @@ class QueryBuilder(Generic[T]):
 
         # Convert where clause to conditions format expected by query_aggregate
         where_conditions = []
-        if self._filters:
-            for filter_item in self._filters:
-                if isinstance(filter_item, SqlExpr):
-                    # Map SQL operators to Rust engine format
-                    op_map = {
-                        "=": "eq",
-                        "!=": "ne",
-                        ">": "gt",
-                        ">=": "gte",
-                        "<": "lt",
-                        "<=": "lte",
-                        "LIKE": "like",
-                        "ILIKE": "ilike",
-                        "IN": "in",
-                        "IS NULL": "is_null",
-                        "IS NOT NULL": "is_not_null",
-                    }
-                    operator = op_map.get(filter_item.op, filter_item.op.lower())
-                    where_conditions.append((filter_item.column, operator, filter_item.value))
-                elif isinstance(filter_item, dict):
-                    for key, value in filter_item.items():
-                        where_conditions.append((key, "eq", value))
+        for filter_item in self._filters or []:
+            where_conditions.extend(self._filter_to_conditions(filter_item))
 
         # Add JSON conditions to where_conditions
         for op_type, column, value in self._json_conditions:
@@ -1849,6 +1829,45 @@ class QueryBuilder(Generic[T]):
                 add_exception(span, e)
                 raise
 
+    def _filter_to_conditions(self, filter_item: Any) -> list[Any]:
+        """
+        Convert a filter to the engine's WHERE condition format.
+
+        Expressions become (column, operator, value) tuples and boolean
+        clauses become ("and" | "or" | "not", [conditions]) groups, nested
+        the same way as the clause.
+
+        Returns:
+            List of conditions (a dict filter yields one per key)
+        """
+        from .query_ext import BooleanClause
+
+        if isinstance(filter_item, SqlExpr):
+            # Map SQL operators to Rust engine format
+            op_map = {
+                "=": "eq",
+                "!=": "ne",
+                ">": "gt",
+                ">=": "gte",
+                "<": "lt",
+                "<=": "lte",
+                "LIKE": "like",
+                "ILIKE": "ilike",
+                "IN": "in",
+                "IS NULL": "is_null",
+                "IS NOT NULL": "is_not_null",
+            }
+            operator = op_map.get(filter_item.op, filter_item.op.lower())
+            return [(filter_item.column, operator, filter_item.value)]
+        if isinstance(filter_item, BooleanClause):
+            members = []
+            for condition in filter_item.conditions:
+                members.extend(self._filter_to_conditions(condition))
+            return [(filter_item.operator.lower(), members)]
+        if isinstance(filter_item, dict):
+            return [(key, "eq", value) for key, value in filter_item.items()]
+        raise TypeError(f"Invalid filter type: {type(filter_item)}")
+
     def _build_where_clause(self) -> tuple[str, list[Any]]:
         """
         Build WHERE clause from filters and JSON conditions.
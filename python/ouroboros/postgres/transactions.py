@@ -88,6 +88,27 @@ class Transaction:
         await self._tx.rollback()
         self._rolled_back = True
 
+    async def notify(self, channel: str, payload: str = "") -> None:
+        """
+        Send a notification, delivered when this transaction commits.
+
+        Args:
+            channel: Channel name
+            payload: Notification payload (default: empty)
+
+        Example:
+            >>> async with pg_transaction() as tx:
+            ...     await user.save()
+            ...     await tx.notify("cache_invalidation", f"users:{user.id}")
+
+        Raises:
+            RuntimeError: If transaction is not active
+        """
+        if not self.is_active:
+            raise RuntimeError("Transaction is not active")
+
+        await self._tx.notify(channel, payload)
+
     async def savepoint(self, name: str) -> "Savepoint":
         """
         Create a savepoint within this transaction.
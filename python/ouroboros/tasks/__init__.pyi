@@ -108,6 +108,8 @@ class Task:
         *args: Any,
         countdown: Optional[float] = None,
         eta: Optional[str] = None,
+        priority: Optional[int] = None,
+        idempotency_key: Optional[str] = None,
         **kwargs: Any,
     ) -> Awaitable[AsyncResult]:
         """Send task with options."""
@@ -0,0 +1,63 @@
+"""Integration tests for PostgreSQL COPY import and export."""
+from ouroboros.postgres import copy_in, copy_out, execute
+from ouroboros.qc import expect, test
+from tests.postgres.base import PostgresSuite
+
+
+class TestCopy(PostgresSuite):
+
+    async def _create_table(self):
+        await execute('CREATE TABLE test_copy_users (id BIGINT PRIMARY KEY, name TEXT, score DOUBLE PRECISION)')
+
+    @test
+    async def test_copy_in_binary(self):
+        """Test rows from a generator are imported in binary format."""
+        await self._create_table()
+        progress = []
+        rows = ((i, f'user {i}', i * 1.5) for i in range(2500))
+        count = await copy_in(
+            'test_copy_users', rows,
+            on_progress=lambda rows, _bytes: progress.append(rows),
+            progress_interval=1000,
+        )
+        expect(count).to_equal(2500)
+        expect(progress).to_equal([1000, 2000, 2500])
+        result = await execute('SELECT name, score FROM test_copy_users WHERE id = 10')
+        expect(result[0]['name']).to_equal('user 10')
+        expect(result[0]['score']).to_equal(15.0)
+
+    @test
+    async def test_copy_in_csv_dict_rows(self):
+        """Test dict rows are imported in CSV format by column name."""
+        await self._create_table()
+        rows = [{'id': 1, 'name': 'say "hi",\nbye'}, {'id': 2}]
+        count = await copy_in('test_copy_users', rows, columns=['id', 'name'], format='csv')
+        expect(count).to_equal(2)
+        result = await execute('SELECT name FROM test_copy_users ORDER BY id')
+        expect(result[0]['name']).to_equal('say "hi",\nbye')
+        expect(result[1]['name']).to_be_none()
+
+    @test
+    async def test_copy_in_rejects_bad_rows(self):
+        """Test a bad row or identifier aborts the whole import."""
+        await self._create_table()
+        for table, rows in [
+            ('test_copy_users', [(1, 'a', 1.0), ('x', 'b', 2.0)]),
+            ('test_copy_users; DROP TABLE x', []),
+        ]:
+            raised = False
+            try:
+                await copy_in(table, rows)
+            except RuntimeError:
+                raised = True
+            expect(raised).to_be_true()
+        result = await execute('SELECT count(*) AS n FROM test_copy_users')
+        expect(result[0]['n']).to_equal(0)
+
+    @test
+    async def test_copy_out_csv(self):
+        """Test a table is exported as CSV chunks."""
+        await self._create_table()
+        await copy_in('test_copy_users', [(1, 'Alice', 1.0), (2, 'Bob', 2.0)])
+        data = b''.join([chunk async for chunk in copy_out('test_copy_users', columns=['id', 'name'], header=True)])
+        expect(data.decode()).to_equal('id,name\n1,Alice\n2,Bob\n')
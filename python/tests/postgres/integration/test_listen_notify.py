@@ -0,0 +1,46 @@
+"""Integration tests for PostgreSQL LISTEN/NOTIFY."""
+import asyncio
+
+from ouroboros.postgres import connection
+from ouroboros.qc import expect, test
+from tests.postgres.base import PostgresSuite
+
+
+async def _next(listener):
+    return await asyncio.wait_for(listener.__anext__(), timeout=5)
+
+
+class TestListenNotify(PostgresSuite):
+
+    @test
+    async def test_listen_notify(self):
+        """Test notifications are delivered to a listener."""
+        async with connection.listen('test_listen_a', 'test_listen_b') as listener:
+            await connection.notify('test_listen_a', 'first')
+            await connection.notify('test_listen_b')
+            notification = await _next(listener)
+            expect(notification['channel']).to_equal('test_listen_a')
+            expect(notification['payload']).to_equal('first')
+            notification = await _next(listener)
+            expect(notification['channel']).to_equal('test_listen_b')
+            expect(notification['payload']).to_equal('')
+
+    @test
+    async def test_notify_in_transaction(self):
+        """Test notifications in a transaction are delivered only on commit."""
+        async with connection.listen('test_listen_tx') as listener:
+            async with connection.begin_transaction() as tx:
+                await connection.notify('test_listen_tx', 'rolled back')
+                await tx.rollback()
+            async with connection.begin_transaction():
+                await connection.notify('test_listen_tx', 'committed')
+            notification = await _next(listener)
+            expect(notification['payload']).to_equal('committed')
+
+    @test
+    async def test_listener_closed(self):
+        """Test iteration stops once the listener is closed."""
+        async with connection.listen('test_listen_closed') as listener:
+            pass
+        messages = [notification async for notification in listener]
+        expect(messages).to_equal([])